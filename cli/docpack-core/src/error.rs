@@ -0,0 +1,59 @@
+use std::fmt;
+use std::io;
+
+/// Why a document could not be loaded or saved, with the file and, when
+/// the JSON is readable, the field it is about.
+#[derive(Debug)]
+pub struct Error {
+    /// The file as given to `load` or `from_slice`
+    pub file: String,
+    /// Dotted path of the field, e.g. `environment.constraints.memory_limit_mb`
+    /// or `tasks[1].output.path`
+    pub field: Option<String>,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Read(io::Error),
+    Write(io::Error),
+    /// Not JSON at all
+    Syntax(serde_json::Error),
+    /// A required field is absent
+    Missing,
+    /// A field has the wrong type or shape
+    Invalid(String),
+    /// A field the types don't name, with [`crate::Strictness::Strict`]
+    Unknown,
+}
+
+impl Error {
+    pub(crate) fn new(file: &str, field: Option<String>, kind: ErrorKind) -> Error {
+        Error { file: file.to_string(), field, kind }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = self.field.as_deref().unwrap_or_default();
+        match &self.kind {
+            ErrorKind::Read(e) => write!(f, "Cannot read {}: {}", self.file, e),
+            ErrorKind::Write(e) => write!(f, "Cannot write {}: {}", self.file, e),
+            ErrorKind::Syntax(e) => write!(f, "Invalid JSON in {}: {}", self.file, e),
+            ErrorKind::Missing => write!(f, "{}: missing required field '{}'", self.file, field),
+            ErrorKind::Invalid(message) if self.field.is_some() => write!(f, "{}: '{}': {}", self.file, field, message),
+            ErrorKind::Invalid(message) => write!(f, "{}: {}", self.file, message),
+            ErrorKind::Unknown => write!(f, "{}: unknown field '{}'", self.file, field),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Read(e) | ErrorKind::Write(e) => Some(e),
+            ErrorKind::Syntax(e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Typed `docpack.json` and `tasks.json`, shared by the `localdoc` CLI and
+//! the website's wasm parser so both read a docpack the same way.
+//!
+//! Fields the types don't name are kept in each struct's `extra` map and
+//! written back where they were, so loading and saving a document loses
+//! nothing. [`Strictness::Strict`] rejects them instead.
+
+use std::fs;
+use std::path::Path;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+mod error;
+mod manifest;
+mod tasks;
+
+pub use error::{Error, ErrorKind};
+pub use manifest::{Constraints, Environment, Manifest, MANIFEST_FILE, MANIFEST_VERSION};
+pub use tasks::{OutputSpec, Task, TasksFile, TASKS_FILE};
+
+/// What loading does with fields the types don't name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Keep them in `extra`
+    #[default]
+    Lenient,
+    /// Fail on the first one, as `#[serde(deny_unknown_fields)]` would
+    Strict,
+}
+
+/// A typed document, with the checks that find the field it failed on.
+trait Typed: Serialize + DeserializeOwned {
+    /// Each typed field with a check of its value on its own
+    const FIELDS: &'static [(&'static str, Check)];
+
+    /// Dotted paths of the fields kept in `extra`, nested ones included.
+    fn unknown_fields(&self) -> Vec<String>;
+}
+
+/// Why a field's value doesn't load, or `None` when it does.
+type Check = fn(&Value) -> Option<Miss>;
+
+/// Where a document failed to load.
+struct Miss {
+    /// Dotted path below the checked value, empty for the value itself
+    path: String,
+    /// Set when the field at `path` is absent rather than wrong
+    missing: bool,
+    message: String,
+}
+
+impl Miss {
+    /// The same miss, seen from the object holding it under `name`.
+    fn under(mut self, name: &str, present: bool) -> Miss {
+        self.missing |= self.path.is_empty() && !present;
+        self.path = join(name, &self.path);
+        self
+    }
+}
+
+fn join(parent: &str, child: &str) -> String {
+    match child {
+        "" => parent.to_string(),
+        _ if parent.is_empty() => child.to_string(),
+        _ if child.starts_with('[') => format!("{}{}", parent, child),
+        _ => format!("{}.{}", parent, child),
+    }
+}
+
+/// A field of a plain type: fine if it deserializes.
+fn leaf<T: DeserializeOwned>(value: &Value) -> Option<Miss> {
+    let error = T::deserialize(value).err()?;
+    Some(Miss { path: String::new(), missing: false, message: error.to_string() })
+}
+
+/// A field holding a typed object, narrowed to the field inside it that fails.
+fn nested<T: Typed>(value: &Value) -> Option<Miss> {
+    let error = T::deserialize(value).err()?;
+    Some(locate::<T>(value).unwrap_or(Miss { path: String::new(), missing: false, message: error.to_string() }))
+}
+
+fn optional<T: Typed>(value: &Value) -> Option<Miss> {
+    if value.is_null() {
+        return None;
+    }
+    nested::<T>(value)
+}
+
+/// An optional list of typed objects, narrowed to the item that fails.
+fn list<T: Typed>(value: &Value) -> Option<Miss> {
+    match value {
+        Value::Null => None,
+        Value::Array(items) => items.iter().enumerate().find_map(|(i, item)| {
+            nested::<T>(item).map(|miss| Miss { path: join(&format!("[{}]", i), &miss.path), ..miss })
+        }),
+        _ => leaf::<Vec<T>>(value),
+    }
+}
+
+/// The first typed field of `value` that fails to load on its own, or
+/// `None` when `value` isn't an object or its fields all load.
+fn locate<T: Typed>(value: &Value) -> Option<Miss> {
+    let object = value.as_object()?;
+    T::FIELDS.iter().find_map(|(name, check)| {
+        let field = object.get(*name).unwrap_or(&Value::Null);
+        check(field).map(|miss| miss.under(name, object.contains_key(*name)))
+    })
+}
+
+fn from_value<T: Typed>(value: &Value, file: &str, strictness: Strictness) -> Result<T, Error> {
+    let document = T::deserialize(value).map_err(|e| match locate::<T>(value) {
+        Some(miss) if miss.missing => Error::new(file, Some(miss.path), ErrorKind::Missing),
+        Some(miss) => Error::new(file, Some(miss.path), ErrorKind::Invalid(miss.message)),
+        None => Error::new(file, None, ErrorKind::Invalid(e.to_string())),
+    })?;
+    if strictness == Strictness::Strict {
+        if let Some(field) = document.unknown_fields().into_iter().next() {
+            return Err(Error::new(file, Some(field), ErrorKind::Unknown));
+        }
+    }
+    Ok(document)
+}
+
+fn from_slice<T: Typed>(bytes: &[u8], file: &str, strictness: Strictness) -> Result<T, Error> {
+    let value: Value = serde_json::from_slice(bytes).map_err(|e| Error::new(file, None, ErrorKind::Syntax(e)))?;
+    from_value(&value, file, strictness)
+}
+
+fn load<T: Typed>(path: &Path, strictness: Strictness) -> Result<T, Error> {
+    let file = path.display().to_string();
+    let bytes = fs::read(path).map_err(|e| Error::new(&file, None, ErrorKind::Read(e)))?;
+    from_slice(&bytes, &file, strictness)
+}
+
+fn save<T: Typed>(document: &T, path: &Path) -> Result<(), Error> {
+    fs::write(path, to_json(document)).map_err(|e| Error::new(&path.display().to_string(), None, ErrorKind::Write(e)))
+}
+
+fn to_json<T: Typed>(document: &T) -> String {
+    serde_json::to_string_pretty(document).expect("documents serialize")
+}
+
+fn extra_fields(extra: &serde_json::Map<String, Value>) -> Vec<String> {
+    extra.keys().cloned().collect()
+}
+
+/// Loading and saving for a [`Typed`] document. `file` names the document
+/// in errors, as a path or just `docpack.json` for one read from an archive.
+macro_rules! document {
+    ($type:ty) => {
+        impl $type {
+            /// Read and parse the document at `path`.
+            pub fn load(path: &std::path::Path, strictness: $crate::Strictness) -> Result<Self, $crate::Error> {
+                $crate::load(path, strictness)
+            }
+
+            pub fn from_slice(bytes: &[u8], file: &str, strictness: $crate::Strictness) -> Result<Self, $crate::Error> {
+                $crate::from_slice(bytes, file, strictness)
+            }
+
+            pub fn from_value(value: &serde_json::Value, file: &str, strictness: $crate::Strictness) -> Result<Self, $crate::Error> {
+                $crate::from_value(value, file, strictness)
+            }
+
+            /// Write the document to `path` as pretty-printed JSON.
+            pub fn save(&self, path: &std::path::Path) -> Result<(), $crate::Error> {
+                $crate::save(self, path)
+            }
+
+            /// The document as pretty-printed JSON, as [`Self::save`] writes it.
+            pub fn to_json(&self) -> String {
+                $crate::to_json(self)
+            }
+
+            /// The document as a JSON value, for code that works on the raw tree.
+            pub fn to_value(&self) -> serde_json::Value {
+                serde_json::to_value(self).expect("documents serialize")
+            }
+        }
+    };
+}
+use document;
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{document, extra_fields, join, leaf, nested, optional, Check, Typed};
+
+/// Name of the manifest inside a docpack.
+pub const MANIFEST_FILE: &str = "docpack.json";
+
+/// `version` written into new manifests.
+pub const MANIFEST_VERSION: &str = "1.0";
+
+/// `docpack.json`: how to build the sandbox and what tools the agent gets.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub environment: Environment,
+    /// Provenance and bookkeeping; open-ended, so kept as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Map<String, Value>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `environment`: the sandbox the agent runs in.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    pub tools: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constraints: Option<Constraints>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `environment.constraints`: the run limits.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Constraints {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_reads: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_execution_time_seconds: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_limit_mb: Option<u64>,
+    /// Largest total size of `output/` after a run, in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_bytes: Option<u64>,
+    /// Largest size of one output file, in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_file_bytes: Option<u64>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Manifest {
+            version: MANIFEST_VERSION.to_string(),
+            name: None,
+            description: None,
+            environment: Environment::default(),
+            metadata: None,
+            extra: Map::new(),
+        }
+    }
+}
+
+/// No tools: which ones a docpack gets is up to whoever builds it.
+impl Default for Environment {
+    fn default() -> Self {
+        Environment {
+            tools: Vec::new(),
+            interpreter: Some("python3.12".to_string()),
+            constraints: Some(Constraints::default()),
+            extra: Map::new(),
+        }
+    }
+}
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Constraints {
+            max_file_reads: Some(1000),
+            max_execution_time_seconds: Some(300),
+            memory_limit_mb: Some(2048),
+            max_output_bytes: None,
+            max_output_file_bytes: None,
+            extra: Map::new(),
+        }
+    }
+}
+
+impl Manifest {
+    /// `metadata.<key>` as a string, if it is one.
+    pub fn metadata_str(&self, key: &str) -> Option<&str> {
+        self.metadata.as_ref()?.get(key)?.as_str()
+    }
+
+    /// `metadata`, created empty if the manifest has none.
+    pub fn metadata_mut(&mut self) -> &mut Map<String, Value> {
+        self.metadata.get_or_insert_with(Map::new)
+    }
+}
+
+document!(Manifest);
+
+impl Typed for Manifest {
+    const FIELDS: &'static [(&'static str, Check)] = &[
+        ("version", leaf::<String>),
+        ("name", leaf::<Option<String>>),
+        ("description", leaf::<Option<String>>),
+        ("environment", nested::<Environment>),
+        ("metadata", leaf::<Option<Map<String, Value>>>),
+    ];
+
+    fn unknown_fields(&self) -> Vec<String> {
+        let mut fields = extra_fields(&self.extra);
+        fields.extend(self.environment.unknown_fields().iter().map(|field| join("environment", field)));
+        fields
+    }
+}
+
+impl Typed for Environment {
+    const FIELDS: &'static [(&'static str, Check)] = &[
+        ("tools", leaf::<Vec<String>>),
+        ("interpreter", leaf::<Option<String>>),
+        ("constraints", optional::<Constraints>),
+    ];
+
+    fn unknown_fields(&self) -> Vec<String> {
+        let mut fields = extra_fields(&self.extra);
+        if let Some(constraints) = &self.constraints {
+            fields.extend(constraints.unknown_fields().iter().map(|field| join("constraints", field)));
+        }
+        fields
+    }
+}
+
+impl Typed for Constraints {
+    const FIELDS: &'static [(&'static str, Check)] = &[
+        ("max_file_reads", leaf::<Option<u64>>),
+        ("max_execution_time_seconds", leaf::<Option<u64>>),
+        ("memory_limit_mb", leaf::<Option<u64>>),
+        ("max_output_bytes", leaf::<Option<u64>>),
+        ("max_output_file_bytes", leaf::<Option<u64>>),
+    ];
+
+    fn unknown_fields(&self) -> Vec<String> {
+        extra_fields(&self.extra)
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::{document, extra_fields, join, leaf, list, optional, Check, Typed};
+
+/// Name of the task list inside a docpack.
+pub const TASKS_FILE: &str = "tasks.json";
+
+/// `tasks.json`: what the agent should accomplish in a run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TasksFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mission: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tasks: Option<Vec<Task>>,
+    /// Rules for the agent (`forbidden_actions`, `output_format`, ...),
+    /// merged over the manifest's run limits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constraints: Option<Map<String, Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evaluation: Option<Value>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// One entry of `tasks`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Task {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Ids of the tasks whose outputs this one builds on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<Vec<String>>,
+    /// Tools the task may use, out of the manifest's `environment.tools`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools_allowed: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputSpec>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A task's `output`: what it writes and where.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OutputSpec {
+    /// `markdown`, `json`, ...
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Under `output/`
+    pub path: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// No tasks yet, with the agent rules every generated tasks.json has.
+impl Default for TasksFile {
+    fn default() -> Self {
+        let constraints = json!({
+            "chain_of_thought_location": "/workspace/.reasoning",
+            "forbidden_actions": ["modify_files", "execute_code"],
+            "output_format": "markdown"
+        });
+        TasksFile {
+            mission: None,
+            tasks: Some(Vec::new()),
+            constraints: constraints.as_object().cloned(),
+            evaluation: None,
+            extra: Map::new(),
+        }
+    }
+}
+
+impl Default for OutputSpec {
+    fn default() -> Self {
+        OutputSpec { kind: Some("markdown".to_string()), path: "output/overview.md".to_string(), extra: Map::new() }
+    }
+}
+
+impl TasksFile {
+    /// The tasks, empty when there is no `tasks` list.
+    pub fn task_list(&self) -> &[Task] {
+        self.tasks.as_deref().unwrap_or_default()
+    }
+}
+
+document!(TasksFile);
+
+impl Typed for TasksFile {
+    const FIELDS: &'static [(&'static str, Check)] = &[
+        ("mission", leaf::<Option<String>>),
+        ("tasks", list::<Task>),
+        ("constraints", leaf::<Option<Map<String, Value>>>),
+    ];
+
+    fn unknown_fields(&self) -> Vec<String> {
+        let mut fields = extra_fields(&self.extra);
+        for (i, task) in self.task_list().iter().enumerate() {
+            fields.extend(task.unknown_fields().iter().map(|field| join(&format!("tasks[{}]", i), field)));
+        }
+        fields
+    }
+}
+
+impl Typed for Task {
+    const FIELDS: &'static [(&'static str, Check)] = &[
+        ("id", leaf::<Option<String>>),
+        ("name", leaf::<Option<String>>),
+        ("description", leaf::<Option<String>>),
+        ("depends_on", leaf::<Option<Vec<String>>>),
+        ("tools_allowed", leaf::<Option<Vec<String>>>),
+        ("output", optional::<OutputSpec>),
+    ];
+
+    fn unknown_fields(&self) -> Vec<String> {
+        let mut fields = extra_fields(&self.extra);
+        if let Some(output) = &self.output {
+            fields.extend(output.unknown_fields().iter().map(|field| join("output", field)));
+        }
+        fields
+    }
+}
+
+impl Typed for OutputSpec {
+    const FIELDS: &'static [(&'static str, Check)] = &[("type", leaf::<Option<String>>), ("path", leaf::<String>)];
+
+    fn unknown_fields(&self) -> Vec<String> {
+        extra_fields(&self.extra)
+    }
+}
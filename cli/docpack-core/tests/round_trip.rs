@@ -0,0 +1,104 @@
+//! Existing docpacks load into the typed documents and serialize back to
+//! the same JSON, and documents that don't load say where.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use docpack_core::{Constraints, ErrorKind, Manifest, Strictness, TasksFile};
+use serde_json::{json, Value};
+
+fn repo_file(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../..").join(path)
+}
+
+fn json(path: &Path) -> Value {
+    serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+}
+
+#[test]
+fn existing_docpacks_round_trip() {
+    for path in ["example.docpack/docpack.json", "cli/tests/fixtures/manifests/1.0.json"] {
+        let path = repo_file(path);
+        let manifest = Manifest::load(&path, Strictness::Strict).unwrap();
+        assert_eq!(manifest.to_value(), json(&path), "{}", path.display());
+        let reloaded = Manifest::from_slice(manifest.to_json().as_bytes(), "docpack.json", Strictness::Strict).unwrap();
+        assert_eq!(reloaded, manifest);
+    }
+
+    let path = repo_file("example.docpack/tasks.json");
+    let tasks = TasksFile::load(&path, Strictness::Strict).unwrap();
+    assert_eq!(tasks.to_value(), json(&path));
+    assert_eq!(tasks.task_list()[1].depends_on.as_deref(), Some(&["task_1".to_string()][..]));
+    assert_eq!(tasks.task_list()[0].output.as_ref().unwrap().kind.as_deref(), Some("markdown"));
+}
+
+#[test]
+fn unknown_fields_are_kept_in_place_unless_strict() {
+    let text = r#"{
+      "version": "1.0",
+      "x-owner": "docs-team",
+      "environment": { "tools": ["read_file"], "gpu": true, "constraints": { "max_file_reads": 5, "cpus": 2 } },
+      "name": "app"
+    }"#;
+    let manifest = Manifest::from_slice(text.as_bytes(), "docpack.json", Strictness::Lenient).unwrap();
+    assert_eq!(manifest.extra["x-owner"], "docs-team");
+    let reordered = serde_json::to_string(&manifest.to_value()).unwrap();
+    assert!(reordered.starts_with(r#"{"version":"1.0","name":"app","environment":{"tools":["read_file"],"#), "{}", reordered);
+    assert_eq!(manifest.to_value(), serde_json::from_str::<Value>(text).unwrap());
+
+    let error = Manifest::from_slice(text.as_bytes(), "docpack.json", Strictness::Strict).unwrap_err();
+    assert_eq!(error.to_string(), "docpack.json: unknown field 'x-owner'");
+    let tasks = r#"{ "tasks": [{ "id": "a", "output": { "path": "output/a.md", "encoding": "utf-8" } }] }"#;
+    let error = TasksFile::from_slice(tasks.as_bytes(), "tasks.json", Strictness::Strict).unwrap_err();
+    assert_eq!(error.field.as_deref(), Some("tasks[0].output.encoding"));
+}
+
+#[test]
+fn errors_name_the_file_and_field() {
+    let load = |value: Value| Manifest::from_value(&value, "app/docpack.json", Strictness::Lenient).unwrap_err().to_string();
+    assert_eq!(load(json!({ "environment": { "tools": [] } })), "app/docpack.json: missing required field 'version'");
+    assert_eq!(
+        load(json!({ "version": "1.0", "environment": { "interpreter": "python3" } })),
+        "app/docpack.json: missing required field 'environment.tools'"
+    );
+    let wrong = load(json!({ "version": "1.0", "environment": { "tools": [], "constraints": { "memory_limit_mb": "lots" } } }));
+    assert!(wrong.starts_with("app/docpack.json: 'environment.constraints.memory_limit_mb': invalid type: string"), "{}", wrong);
+
+    let tasks = json!({ "tasks": [{ "id": "a" }, { "id": "b", "output": { "type": "markdown" } }] });
+    let error = TasksFile::from_value(&tasks, "tasks.json", Strictness::Lenient).unwrap_err();
+    assert!(matches!(error.kind, ErrorKind::Missing));
+    assert_eq!(error.to_string(), "tasks.json: missing required field 'tasks[1].output.path'");
+
+    let error = Manifest::from_slice(b"{ \"version\": ", "docpack.json", Strictness::Lenient).unwrap_err();
+    assert!(error.to_string().starts_with("Invalid JSON in docpack.json: "), "{}", error);
+}
+
+#[test]
+fn defaults_match_generated_json() {
+    assert_eq!(
+        serde_json::to_value(Constraints::default()).unwrap(),
+        json!({ "max_file_reads": 1000, "max_execution_time_seconds": 300, "memory_limit_mb": 2048 })
+    );
+    assert_eq!(
+        Manifest::default().to_value(),
+        json!({
+            "version": "1.0",
+            "environment": {
+                "tools": [],
+                "interpreter": "python3.12",
+                "constraints": { "max_file_reads": 1000, "max_execution_time_seconds": 300, "memory_limit_mb": 2048 }
+            }
+        })
+    );
+    assert_eq!(
+        TasksFile::default().to_value(),
+        json!({
+            "tasks": [],
+            "constraints": {
+                "chain_of_thought_location": "/workspace/.reasoning",
+                "forbidden_actions": ["modify_files", "execute_code"],
+                "output_format": "markdown"
+            }
+        })
+    );
+}
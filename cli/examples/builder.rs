@@ -0,0 +1,42 @@
+//! Build a docpack from a source directory and a generated file, with a
+//! search index and two tasks, the way `localdoc ingest` would.
+//!
+//! Run with: cargo run --example builder [SOURCE_DIR]
+
+use std::path::PathBuf;
+
+use localdoc::builder::{DocpackBuilder, LocaldocError, TaskSpec, Tool};
+
+fn main() -> Result<(), LocaldocError> {
+    let source = std::env::args().nth(1).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("src"));
+    let out = std::env::temp_dir().join("builder-example.docpack");
+
+    let built = DocpackBuilder::new("builder-example")
+        .description("Docpack made with localdoc::builder")
+        .add_dir(&source)
+        // Files made at build time go in next to the sources
+        .add_file("BUILD_NOTES.md", format!("Built from {}\n", source.display()))
+        .tools(&[Tool::ListFiles, Tool::ReadFile, Tool::SearchCode, Tool::WriteOutput])
+        .task(TaskSpec {
+            name: "Overview".to_string(),
+            description: "Explain what the code does and how it is organized".to_string(),
+            ..TaskSpec::default()
+        })
+        .task(TaskSpec {
+            id: Some("modules".to_string()),
+            name: "Module guide".to_string(),
+            description: "Describe each module in one paragraph".to_string(),
+            depends_on: vec!["task_1".to_string()],
+            ..TaskSpec::default()
+        })
+        .build_index(true)
+        .quiet(true)
+        .write_archive(&out)?;
+
+    println!("Wrote {} ({} files)", built.path.display(), built.plan.files.len());
+    for (phase, time) in &built.phases {
+        println!("  {:<8} {:.3}s", phase, time.as_secs_f64());
+    }
+    println!("Check it with: localdoc validate {}", built.path.display());
+    Ok(())
+}
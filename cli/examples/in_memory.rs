@@ -0,0 +1,46 @@
+//! Build a docpack archive from in-memory files and extract it back into a
+//! map, without touching the filesystem.
+//!
+//! Run with: cargo run --example in_memory
+
+use std::io::{self, Cursor};
+
+use localdoc::archive::{self, ArchiveEntry, ArchiveOptions, Limits, MemorySink};
+
+fn main() -> io::Result<()> {
+    let files = [
+        ("docpack.json", r#"{"version": "1.0", "name": "in-memory", "environment": {"tools": []}}"#),
+        ("files/README.md", "# Hello\n\nThis docpack never touched the disk.\n"),
+        ("files/src/main.py", "print('hello')\n"),
+    ];
+
+    // Build: any iterator of entries works, so a service can stream uploads in
+    let mut entries = vec![
+        Ok(ArchiveEntry::Directory("files".to_string())),
+        Ok(ArchiveEntry::Directory("index".to_string())),
+        Ok(ArchiveEntry::Directory("output".to_string())),
+    ];
+    for (path, content) in files {
+        entries.push(Ok(ArchiveEntry::File {
+            path: path.to_string(),
+            reader: Box::new(Cursor::new(content.as_bytes().to_vec())),
+            unix_mode: None,
+            size: Some(content.len() as u64),
+        }));
+    }
+
+    let (cursor, built) = archive::build_archive(entries, Cursor::new(Vec::new()), &ArchiveOptions::default())?;
+    let bytes = cursor.into_inner();
+    println!("Built archive: {} entries, {} bytes in, {} bytes zipped", built.entries, built.bytes, bytes.len());
+
+    // Extract: the sink sees each entry's metadata and a byte stream
+    let mut sink = MemorySink::default();
+    let extracted = archive::extract_entries(Cursor::new(bytes), &mut sink, &Limits::default())?;
+    println!("Extracted {} entries ({} bytes)", extracted.entries, extracted.bytes);
+
+    for (path, data) in &sink.files {
+        println!("  {} ({} bytes)", path, data.len());
+    }
+
+    Ok(())
+}
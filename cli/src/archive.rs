@@ -0,0 +1,854 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+pub use zip::CompressionMethod;
+
+/// Guardrails against zip bombs and runaway archives.
+#[derive(Clone, Debug)]
+pub struct Limits {
+    /// Maximum number of entries in one archive
+    pub max_entries: usize,
+    /// Maximum uncompressed size of a single entry, in bytes
+    pub max_entry_size: u64,
+    /// Maximum uncompressed size of all entries together, in bytes
+    pub max_total_size: u64,
+    /// Maximum uncompressed/compressed ratio for a single entry
+    pub max_ratio: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_entries: 100_000,
+            max_entry_size: 1024 * 1024 * 1024,
+            max_total_size: 4 * 1024 * 1024 * 1024,
+            max_ratio: 200,
+        }
+    }
+}
+
+impl Limits {
+    /// No limits at all, for building archives from files already on disk.
+    pub fn unlimited() -> Self {
+        Limits {
+            max_entries: usize::MAX,
+            max_entry_size: u64::MAX,
+            max_total_size: u64::MAX,
+            max_ratio: u64::MAX,
+        }
+    }
+}
+
+/// Entries at most this large are never refused for their compression
+/// ratio; tiny files of repeated bytes compress absurdly well.
+pub(crate) const MIN_RATIO_CAP: u64 = 64 * 1024;
+
+/// Metadata handed to an [`EntrySink`] for each archive entry.
+#[derive(Clone, Debug)]
+pub struct EntryMeta {
+    /// Entry path with `/` separators, guaranteed not to escape the archive root
+    pub path: String,
+    pub is_dir: bool,
+    /// Declared uncompressed size
+    pub size: u64,
+    pub unix_mode: Option<u32>,
+}
+
+/// Receives entries as they are extracted. `data` streams the entry's
+/// uncompressed bytes and enforces the extraction limits as it is read.
+pub trait EntrySink {
+    fn entry(&mut self, meta: &EntryMeta, data: &mut dyn Read) -> io::Result<()>;
+}
+
+/// Totals for an extraction or archive build.
+#[derive(Clone, Debug, Default)]
+pub struct ArchiveStats {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// Stream every entry of a zip archive into `sink`.
+///
+/// Entry paths that would escape the archive root (absolute paths, `..`)
+/// and a second file entry with the same path are rejected with
+/// `InvalidInput` rather than skipped, and `limits` are
+/// enforced on the actual decompressed bytes, not just declared sizes
+/// (`FileTooLarge`). A damaged archive fails with `InvalidData`.
+/// Backslashes in entry names are read as `/`, so [`EntryMeta::path`]
+/// always uses `/`. This is blocking I/O; from async code, run it on
+/// `tokio::task::spawn_blocking` (or use the `tokio` feature adapters).
+pub fn extract_entries<R, S>(reader: R, sink: &mut S, limits: &Limits) -> io::Result<ArchiveStats>
+where
+    R: Read + Seek,
+    S: EntrySink + ?Sized,
+{
+    let mut archive = ZipArchive::new(reader).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("cannot read central directory: {}", e))
+    })?;
+
+    if archive.len() > limits.max_entries {
+        return Err(limit_error(format!(
+            "archive has {} entries (limit {})",
+            archive.len(),
+            limits.max_entries
+        )));
+    }
+
+    let mut stats = ArchiveStats::default();
+    let mut seen = HashSet::new();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(zip_error)?;
+
+        // Archives made on Windows by other tools may use `\` separators
+        let path = match safe_entry_name(file.name()) {
+            Some(path) => path,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsafe entry path in archive: {}", file.name()),
+                ))
+            }
+        };
+        // Which copy of a duplicated file wins differs between extractors
+        if !file.is_dir() && !seen.insert(path.clone()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("duplicate entry in archive: {}", file.name()),
+            ));
+        }
+
+        let meta = EntryMeta {
+            path,
+            is_dir: file.is_dir(),
+            size: file.size(),
+            unix_mode: file.unix_mode(),
+        };
+
+        let remaining_total = limits.max_total_size.saturating_sub(stats.bytes);
+        let ratio_cap = file.compressed_size().max(1).saturating_mul(limits.max_ratio);
+        let cap = limits.max_entry_size.min(remaining_total).min(ratio_cap.max(MIN_RATIO_CAP));
+
+        let mut limited = LimitedReader { inner: &mut file, remaining: cap, path: &meta.path };
+        sink.entry(&meta, &mut limited)?;
+
+        // Drain whatever the sink did not read so limits still apply
+        io::copy(&mut limited, &mut io::sink())?;
+
+        stats.entries += 1;
+        stats.bytes += cap - limited.remaining;
+    }
+
+    Ok(stats)
+}
+
+/// Something about an archive that makes it unsafe to extract, or that
+/// [`extract_entries`] would refuse. Found by [`audit`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Hazard {
+    /// An entry name that is absolute, has a `..` segment, a drive prefix
+    /// or a NUL byte, and so could land outside the extraction root.
+    /// Backslashes count as separators, as Windows reads them
+    UnsafePath(String),
+    /// More than one file entry with this name
+    DuplicateEntry(String),
+    /// An entry whose declared size is out of proportion to its
+    /// compressed size, as in a zip bomb
+    CompressionRatio { path: String, size: u64, compressed: u64 },
+    /// Declared sizes or counts beyond `limits`
+    TooLarge(String),
+}
+
+impl Hazard {
+    /// Whether extracting the archive could write outside its root or
+    /// depend on which duplicate an extractor keeps, rather than merely
+    /// hit a limit.
+    pub fn is_unsafe(&self) -> bool {
+        matches!(self, Hazard::UnsafePath(_) | Hazard::DuplicateEntry(_))
+    }
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hazard::UnsafePath(name) => write!(f, "entry {:?} would extract outside the archive root", name),
+            Hazard::DuplicateEntry(name) => write!(f, "entry {:?} appears more than once", name),
+            Hazard::CompressionRatio { path, size, compressed } => write!(
+                f,
+                "entry {:?} declares {} bytes from {} compressed (ratio {}:1)",
+                path,
+                size,
+                compressed,
+                size / (*compressed).max(1)
+            ),
+            Hazard::TooLarge(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Check an archive for [`Hazard`]s from its central directory alone;
+/// nothing is decompressed, so declared sizes are taken at their word
+/// ([`extract_entries`] still enforces `limits` on the actual bytes).
+pub fn audit<R: Read + Seek>(reader: R, limits: &Limits) -> io::Result<Vec<Hazard>> {
+    let mut archive = ZipArchive::new(reader).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("cannot read central directory: {}", e))
+    })?;
+
+    let mut hazards = Vec::new();
+    if archive.len() > limits.max_entries {
+        hazards.push(Hazard::TooLarge(format!(
+            "archive has {} entries (limit {})",
+            archive.len(),
+            limits.max_entries
+        )));
+    }
+
+    let mut seen = HashSet::new();
+    let mut total: u64 = 0;
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).map_err(zip_error)?;
+        let name = file.name().to_string();
+
+        let safe = safe_entry_name(&name);
+        if safe.is_none() {
+            hazards.push(Hazard::UnsafePath(name.clone()));
+        }
+        // `a\b` and `a/b` extract to the same file
+        if !file.is_dir() && !seen.insert(safe.unwrap_or_else(|| name.clone())) {
+            hazards.push(Hazard::DuplicateEntry(name.clone()));
+        }
+
+        let (size, compressed) = (file.size(), file.compressed_size());
+        total = total.saturating_add(size);
+        if size > limits.max_entry_size {
+            hazards.push(Hazard::TooLarge(format!(
+                "entry {:?} declares {} bytes (limit {})",
+                name, size, limits.max_entry_size
+            )));
+        } else if size > compressed.max(1).saturating_mul(limits.max_ratio).max(MIN_RATIO_CAP) {
+            hazards.push(Hazard::CompressionRatio { path: name, size, compressed });
+        }
+    }
+    if total > limits.max_total_size {
+        hazards.push(Hazard::TooLarge(format!(
+            "entries declare {} bytes in total (limit {})",
+            total, limits.max_total_size
+        )));
+    }
+
+    Ok(hazards)
+}
+
+/// One entry to write with [`build_archive`].
+pub enum ArchiveEntry {
+    Directory(String),
+    File {
+        path: String,
+        reader: Box<dyn Read + Send>,
+        unix_mode: Option<u32>,
+        /// Length of the data, when known up front. Entries of unknown size
+        /// that the limits would let grow past 4 GB are written as zip64.
+        size: Option<u64>,
+    },
+}
+
+/// Largest entry, and largest offset, a plain (non-zip64) zip can record.
+const ZIP64_THRESHOLD: u64 = u32::MAX as u64;
+
+/// File extensions whose contents are already compressed; deflating them
+/// again costs time and saves nothing.
+const PRECOMPRESSED_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "avif", "heic", "zip", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "jar",
+    "docpack", "mp3", "ogg", "flac", "mp4", "m4v", "mov", "mkv", "webm", "woff", "woff2",
+];
+
+/// Settings for [`build_archive`].
+#[derive(Clone, Debug)]
+pub struct ArchiveOptions {
+    pub compression: CompressionMethod,
+    /// Level for `compression`; `None` uses the method's default
+    pub compression_level: Option<i32>,
+    /// Store already-compressed formats (images, video, archives) instead
+    /// of compressing them again
+    pub store_precompressed: bool,
+    /// Permissions recorded for files without an explicit mode
+    pub default_mode: u32,
+    /// Modification time recorded on every entry. The default is the zip
+    /// epoch (1980-01-01), so builds don't depend on when they ran.
+    pub modified: zip::DateTime,
+    pub limits: Limits,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        ArchiveOptions {
+            compression: CompressionMethod::Deflated,
+            compression_level: None,
+            store_precompressed: true,
+            default_mode: 0o644,
+            modified: zip::DateTime::default(),
+            limits: Limits::default(),
+        }
+    }
+}
+
+/// Write a zip archive from a stream of entries, returning the finished
+/// writer. Backslashes in entry paths are written as `/`, the only
+/// separator zip allows. Like [`extract_entries`] this is blocking and
+/// enforces `limits`.
+pub fn build_archive<I, W>(entries: I, writer: W, options: &ArchiveOptions) -> io::Result<(W, ArchiveStats)>
+where
+    I: IntoIterator<Item = io::Result<ArchiveEntry>>,
+    W: Write + Seek,
+{
+    let mut zip = ZipWriter::new(writer);
+    let mut stats = ArchiveStats::default();
+    let limits = &options.limits;
+
+    for entry in entries {
+        if stats.entries >= limits.max_entries {
+            return Err(limit_error(format!("more than {} entries", limits.max_entries)));
+        }
+
+        match entry? {
+            ArchiveEntry::Directory(path) => {
+                let path = normalize_entry_name(&path);
+                check_entry_name(&path)?;
+                let dir_name = if path.ends_with('/') { path } else { format!("{}/", path) };
+                let file_options = FileOptions::default()
+                    .compression_method(options.compression)
+                    .last_modified_time(options.modified)
+                    .unix_permissions(0o755);
+                zip.add_directory(dir_name, file_options).map_err(zip_error)?;
+            }
+            ArchiveEntry::File { path, mut reader, unix_mode, size } => {
+                let path = normalize_entry_name(&path);
+                check_entry_name(&path)?;
+                let method = if options.store_precompressed && is_precompressed(&path) {
+                    CompressionMethod::Stored
+                } else {
+                    options.compression
+                };
+                let level = if method == CompressionMethod::Stored { None } else { options.compression_level };
+                let cap = limits.max_entry_size.min(limits.max_total_size.saturating_sub(stats.bytes));
+                // The zip writer needs to know before the data whether sizes
+                // will overflow 32 bits; the archive's own entry count and
+                // offsets switch to zip64 by themselves
+                let large_file = size.unwrap_or(cap) > ZIP64_THRESHOLD;
+                let file_options = FileOptions::default()
+                    .compression_method(method)
+                    .compression_level(level)
+                    .last_modified_time(options.modified)
+                    .unix_permissions(unix_mode.unwrap_or(options.default_mode))
+                    .large_file(large_file);
+                zip.start_file(path.as_str(), file_options).map_err(zip_error)?;
+
+                let mut limited = LimitedReader { inner: &mut reader, remaining: cap, path: &path };
+                stats.bytes += io::copy(&mut limited, &mut zip)?;
+            }
+        }
+
+        stats.entries += 1;
+    }
+
+    let writer = zip.finish().map_err(zip_error)?;
+    Ok((writer, stats))
+}
+
+/// Whether an entry name has an extension of an already-compressed format.
+pub fn is_precompressed(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| PRECOMPRESSED_EXTENSIONS.iter().any(|p| p.eq_ignore_ascii_case(e)))
+        .unwrap_or(false)
+}
+
+/// Levels accepted for a compression method, or `None` if it takes none.
+pub fn compression_levels(method: CompressionMethod) -> Option<std::ops::RangeInclusive<i32>> {
+    match method {
+        CompressionMethod::Deflated => Some(0..=9),
+        CompressionMethod::Zstd => Some(1..=22),
+        _ => None,
+    }
+}
+
+/// Walk a directory and yield archive entries for it in lexicographic order
+/// of entry name, so the same tree always produces the same archive.
+/// Symlinks are skipped, and files are only opened as they are consumed.
+pub fn dir_entries(root: &Path) -> impl Iterator<Item = io::Result<ArchiveEntry>> {
+    let mut found: Vec<io::Result<(String, Option<PathBuf>)>> = walkdir::WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => return Some(Err(io::Error::other(e))),
+            };
+
+            let rel = entry.path().strip_prefix(root).ok()?;
+            if rel.as_os_str().is_empty() || entry.path_is_symlink() {
+                return None;
+            }
+
+            match rel.to_str() {
+                Some(_) => {
+                    let path = (!entry.file_type().is_dir()).then(|| entry.path().to_path_buf());
+                    Some(Ok((path_to_entry_name(rel), path)))
+                }
+                None => Some(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid UTF-8 in path: {:?}", entry.path()),
+                ))),
+            }
+        })
+        .collect();
+
+    // Errors sort first so a bad tree fails before anything is written
+    found.sort_by(|a, b| match (a, b) {
+        (Ok(a), Ok(b)) => a.0.cmp(&b.0),
+        (Err(_), Ok(_)) => std::cmp::Ordering::Less,
+        (Ok(_), Err(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Err(_)) => std::cmp::Ordering::Equal,
+    });
+
+    found.into_iter().map(|entry| entry.and_then(|(name, path)| open_entry(name, path)))
+}
+
+/// Yield archive entries for `(entry name, file)` pairs, where `None` marks
+/// a directory, sorted by entry name like [`dir_entries`]. This lets an
+/// archive be assembled from files scattered across several trees without
+/// copying them into one place first.
+pub fn path_entries(mut paths: Vec<(String, Option<PathBuf>)>) -> impl Iterator<Item = io::Result<ArchiveEntry>> {
+    paths.sort_by(|a, b| a.0.cmp(&b.0));
+    paths.into_iter().map(|(name, path)| open_entry(name, path))
+}
+
+/// Like [`path_entries`], but files are opened, and small ones read into
+/// memory, on the rayon thread pool ahead of the consumer. The archive
+/// writer stays single-threaded; it just no longer waits on the disk for
+/// every small file. Entries pass through a bounded channel, so at most a
+/// few batches of small files are held in memory at once, and order is
+/// the same as [`path_entries`].
+pub fn prefetched_entries(mut paths: Vec<(String, Option<PathBuf>)>) -> impl Iterator<Item = io::Result<ArchiveEntry>> {
+    use rayon::prelude::*;
+
+    paths.sort_by(|a, b| a.0.cmp(&b.0));
+    let (tx, rx) = std::sync::mpsc::sync_channel(PREFETCH_BATCH);
+    std::thread::spawn(move || {
+        for batch in paths.chunks(PREFETCH_BATCH) {
+            let entries: Vec<_> = batch
+                .par_iter()
+                .map(|(name, path)| open_entry_with(name.clone(), path.clone(), PREFETCH_MAX_SIZE))
+                .collect();
+            for entry in entries {
+                let failed = entry.is_err();
+                // Sending fails once the consumer has stopped
+                if tx.send(entry).is_err() || failed {
+                    return;
+                }
+            }
+        }
+    });
+    rx.into_iter()
+}
+
+/// Entries opened together by [`prefetched_entries`], and how many may
+/// wait for the consumer.
+const PREFETCH_BATCH: usize = 32;
+
+/// Files up to this size are read ahead by [`prefetched_entries`]; larger
+/// ones are streamed from disk as they are consumed.
+const PREFETCH_MAX_SIZE: u64 = 256 * 1024;
+
+fn open_entry(name: String, path: Option<PathBuf>) -> io::Result<ArchiveEntry> {
+    open_entry_with(name, path, 0)
+}
+
+/// Open an entry, reading files of at most `read_ahead` bytes into memory.
+fn open_entry_with(name: String, path: Option<PathBuf>, read_ahead: u64) -> io::Result<ArchiveEntry> {
+    let Some(path) = path else {
+        return Ok(ArchiveEntry::Directory(name));
+    };
+    let mut file = fs::File::open(&path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to open file {:?}: {}", path, e)))?;
+    let meta = file.metadata()?;
+    let reader: Box<dyn Read + Send> = if meta.len() <= read_ahead {
+        let mut data = Vec::with_capacity(meta.len() as usize);
+        file.read_to_end(&mut data)
+            .map_err(|e| io::Error::new(e.kind(), format!("Failed to read file {:?}: {}", path, e)))?;
+        Box::new(io::Cursor::new(data))
+    } else {
+        Box::new(file)
+    };
+    Ok(ArchiveEntry::File {
+        path: name,
+        reader,
+        unix_mode: file_mode(&meta),
+        size: Some(meta.len()),
+    })
+}
+
+/// The mode to record for a file: 0755 when any execute bit is set (as git
+/// does), otherwise `None` for the archive's default. Other bits are left
+/// out so archives don't depend on the umask they were built under.
+#[cfg(unix)]
+fn file_mode(meta: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    (meta.permissions().mode() & 0o111 != 0).then_some(0o755)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_meta: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Writes entries beneath a directory, restoring executable bits: files
+/// get 0755 or 0644 and directories 0755, whatever mode an entry records. On
+/// Windows, names the filesystem can't hold are renamed with a warning
+/// (see [`windows_safe_name`]) and long paths are written as `\\?\` paths.
+pub struct DirSink {
+    pub root: PathBuf,
+}
+
+impl EntrySink for DirSink {
+    fn entry(&mut self, meta: &EntryMeta, data: &mut dyn Read) -> io::Result<()> {
+        let outpath = if cfg!(windows) {
+            let name = match windows_safe_name(&meta.path) {
+                Some(renamed) => {
+                    log::warn!("{} can't be created on Windows; extracted as {}", meta.path, renamed);
+                    renamed
+                }
+                None => meta.path.clone(),
+            };
+            long_path(self.root.join(name))
+        } else {
+            self.root.join(&meta.path)
+        };
+
+        if meta.is_dir {
+            fs::create_dir_all(&outpath)?;
+        } else {
+            if let Some(parent) = outpath.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut outfile = fs::File::create(&outpath)?;
+            io::copy(data, &mut outfile)?;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if let Some(mode) = meta.unix_mode {
+                fs::set_permissions(&outpath, fs::Permissions::from_mode(extracted_mode(mode, meta.is_dir)))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The mode to give an extracted entry whose archive records `mode`. The
+/// mode is untrusted, so only its executable bits are kept: directories and
+/// executable files get 0755 and everything else 0644, the same modes
+/// archives are built with. Setuid, setgid, sticky and world-writable bits
+/// are never restored.
+fn extracted_mode(mode: u32, is_dir: bool) -> u32 {
+    if is_dir || mode & 0o111 != 0 {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+/// Collects file entries in memory, keyed by path.
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+impl EntrySink for MemorySink {
+    fn entry(&mut self, meta: &EntryMeta, data: &mut dyn Read) -> io::Result<()> {
+        if !meta.is_dir {
+            let mut buf = Vec::new();
+            data.read_to_end(&mut buf)?;
+            self.files.insert(meta.path.clone(), buf);
+        }
+        Ok(())
+    }
+}
+
+/// Async adapters that run the blocking APIs on tokio's blocking pool.
+#[cfg(feature = "tokio")]
+pub mod tokio_adapters {
+    use super::*;
+
+    /// [`extract_entries`] on `spawn_blocking`; returns the sink when done.
+    pub async fn extract_entries_async<R, S>(reader: R, mut sink: S, limits: Limits) -> io::Result<(S, ArchiveStats)>
+    where
+        R: Read + Seek + Send + 'static,
+        S: EntrySink + Send + 'static,
+    {
+        tokio::task::spawn_blocking(move || {
+            let stats = extract_entries(reader, &mut sink, &limits)?;
+            Ok((sink, stats))
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    /// [`build_archive`] on `spawn_blocking`.
+    pub async fn build_archive_async<I, W>(entries: I, writer: W, options: ArchiveOptions) -> io::Result<(W, ArchiveStats)>
+    where
+        I: IntoIterator<Item = io::Result<ArchiveEntry>> + Send + 'static,
+        W: Write + Seek + Send + 'static,
+    {
+        tokio::task::spawn_blocking(move || build_archive(entries, writer, &options))
+            .await
+            .map_err(io::Error::other)?
+    }
+}
+
+/// Reader that fails once more than `remaining` bytes are read.
+struct LimitedReader<'a, R: Read + ?Sized> {
+    inner: &'a mut R,
+    remaining: u64,
+    path: &'a str,
+}
+
+impl<R: Read + ?Sized> Read for LimitedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Read one byte past the cap so hitting it exactly is not an error
+        let want = buf.len().min(self.remaining.saturating_add(1).min(usize::MAX as u64) as usize);
+        let n = self.inner.read(&mut buf[..want])?;
+        if n as u64 > self.remaining {
+            return Err(limit_error(format!("entry {} exceeds size limits", self.path)));
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Convert a relative path to a zip entry name with `/` separators. A
+/// backslash is a separator too, whichever OS the path comes from.
+pub fn path_to_entry_name(path: &Path) -> String {
+    let parts: Vec<String> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    normalize_entry_name(&parts.join("/"))
+}
+
+/// `name` with every `\` turned into `/`.
+pub fn normalize_entry_name(name: &str) -> String {
+    name.replace('\\', "/")
+}
+
+/// The path an entry extracts to, with `/` separators and without empty
+/// or `.` segments, or `None` if it is absolute, has a drive prefix, a
+/// `..` segment or a NUL byte.
+pub fn safe_entry_name(name: &str) -> Option<String> {
+    let name = normalize_entry_name(name);
+    let drive = name.as_bytes().get(1) == Some(&b':');
+    if name.starts_with('/') || drive || name.contains('\0') {
+        return None;
+    }
+    let parts: Vec<&str> = name.split('/').filter(|part| !part.is_empty() && *part != ".").collect();
+    if parts.is_empty() || parts.contains(&"..") {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// Device names Windows reserves in every directory, with any extension.
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2",
+    "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// A name for the entry path `path` that Windows can create, or `None` if
+/// it can already. Reserved device names get a `_` after the stem
+/// (`aux.txt` becomes `aux_.txt`), characters Windows forbids become `_`,
+/// and so do trailing dots and spaces, which Windows drops.
+pub fn windows_safe_name(path: &str) -> Option<String> {
+    let parts: Vec<String> = path
+        .split('/')
+        .map(|part| {
+            let mut part: String = part
+                .chars()
+                .map(|c| if c.is_control() || "<>:\"|?*\\".contains(c) { '_' } else { c })
+                .collect();
+            let kept = part.trim_end_matches(['.', ' ']).len();
+            if kept < part.len() {
+                part.replace_range(kept.., &"_".repeat(part.len() - kept));
+            }
+            let stem = part.split('.').next().unwrap_or_default();
+            if WINDOWS_RESERVED.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+                part.insert(stem.len(), '_');
+            }
+            part
+        })
+        .collect();
+    let safe = parts.join("/");
+    (safe != path).then_some(safe)
+}
+
+/// Longest path most Windows APIs accept without the `\\?\` prefix.
+const WINDOWS_MAX_PATH: usize = 260;
+
+/// `path` as a `\\?\` path when it is too long for Windows to open
+/// otherwise; unchanged elsewhere.
+fn long_path(path: PathBuf) -> PathBuf {
+    let verbatim = path.as_os_str().to_string_lossy().starts_with(r"\\?\");
+    if !cfg!(windows) || verbatim || path.as_os_str().len() < WINDOWS_MAX_PATH {
+        return path;
+    }
+    // A verbatim path is used as is, so it has to be absolute with `\`
+    // separators only, which `absolute` gives on Windows
+    match std::path::absolute(&path) {
+        Ok(absolute) => PathBuf::from(format!(r"\\?\{}", absolute.display())),
+        Err(_) => path,
+    }
+}
+
+fn check_entry_name(name: &str) -> io::Result<()> {
+    let bad = name.is_empty()
+        || name.starts_with('/')
+        || name.split('/').any(|part| part == "..");
+    if bad {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsafe archive entry name: {}", name),
+        ));
+    }
+    Ok(())
+}
+
+fn limit_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::FileTooLarge, format!("archive limit exceeded: {}", message))
+}
+
+fn zip_error(e: zip::result::ZipError) -> io::Error {
+    match e {
+        zip::result::ZipError::Io(e) => e,
+        other => io::Error::new(io::ErrorKind::InvalidData, other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn file(path: &str, data: &[u8]) -> io::Result<ArchiveEntry> {
+        Ok(ArchiveEntry::File {
+            path: path.to_string(),
+            reader: Box::new(Cursor::new(data.to_vec())),
+            unix_mode: None,
+            size: Some(data.len() as u64),
+        })
+    }
+
+    fn build(entries: Vec<io::Result<ArchiveEntry>>, options: &ArchiveOptions) -> io::Result<Vec<u8>> {
+        build_archive(entries, Cursor::new(Vec::new()), options).map(|(writer, _)| writer.into_inner())
+    }
+
+    fn extract(bytes: Vec<u8>, limits: &Limits) -> io::Result<MemorySink> {
+        let mut sink = MemorySink::default();
+        extract_entries(Cursor::new(bytes), &mut sink, limits)?;
+        Ok(sink)
+    }
+
+    #[test]
+    fn built_archives_extract_to_the_same_files() {
+        let entries = vec![
+            Ok(ArchiveEntry::Directory("files".to_string())),
+            file("files\\src\\main.rs", b"fn main() {}\n"),
+            file("files/logo.png", b"\x89PNG not really"),
+        ];
+        let (writer, stats) = build_archive(entries, Cursor::new(Vec::new()), &ArchiveOptions::default()).unwrap();
+        assert_eq!((stats.entries, stats.bytes), (3, 28));
+
+        let bytes = writer.into_inner();
+        let mut zip = ZipArchive::new(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(zip.by_name("files/src/main.rs").unwrap().compression(), CompressionMethod::Deflated);
+        assert_eq!(zip.by_name("files/logo.png").unwrap().compression(), CompressionMethod::Stored);
+
+        let sink = extract(bytes, &Limits::default()).unwrap();
+        let names: Vec<&str> = sink.files.keys().map(String::as_str).collect();
+        assert_eq!(names, ["files/logo.png", "files/src/main.rs"]);
+        assert_eq!(sink.files["files/src/main.rs"], b"fn main() {}\n");
+    }
+
+    #[test]
+    fn building_refuses_unsafe_names_and_too_many_entries() {
+        for name in ["../x", "/etc/passwd", "files/../../x", ""] {
+            let error = build(vec![file(name, b"x")], &ArchiveOptions::default()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{}", name);
+        }
+
+        let options = ArchiveOptions { limits: Limits { max_entries: 1, ..Limits::default() }, ..ArchiveOptions::default() };
+        let error = build(vec![file("a", b"a"), file("b", b"b")], &options).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::FileTooLarge);
+    }
+
+    #[test]
+    fn extraction_enforces_limits_on_the_actual_bytes() {
+        let zeros = vec![0; 1024 * 1024];
+        let bytes = build(vec![file("zeros.bin", &zeros), file("small.txt", b"small")], &ArchiveOptions::default()).unwrap();
+        let no_ratio = Limits { max_ratio: u64::MAX, ..Limits::default() };
+        assert_eq!(extract(bytes.clone(), &no_ratio).unwrap().files["zeros.bin"].len(), zeros.len());
+
+        for limits in [
+            Limits { max_entry_size: 1000, ..no_ratio.clone() },
+            Limits { max_total_size: zeros.len() as u64 + 1, ..no_ratio.clone() },
+            Limits { max_entries: 1, ..no_ratio.clone() },
+            // A megabyte of zeros deflates far past the default 200:1
+            Limits::default(),
+        ] {
+            let error = extract(bytes.clone(), &limits).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::FileTooLarge, "{:?}: {}", limits, error);
+        }
+    }
+
+    #[test]
+    fn damaged_archives_are_invalid_data() {
+        let bytes = build(vec![file("a.txt", b"hello")], &ArchiveOptions::default()).unwrap();
+        let truncated = bytes[..bytes.len() - 10].to_vec();
+        assert_eq!(extract(truncated.clone(), &Limits::default()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(audit(Cursor::new(truncated), &Limits::default()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn extracted_modes_keep_only_the_executable_bit() {
+        assert_eq!(extracted_mode(0o644, false), 0o644);
+        assert_eq!(extracted_mode(0o700, false), 0o755);
+        assert_eq!(extracted_mode(0o4755, false), 0o755);
+        assert_eq!(extracted_mode(0o6777, false), 0o755);
+        assert_eq!(extracted_mode(0o1666, false), 0o644);
+        assert_eq!(extracted_mode(0o000, false), 0o644);
+        assert_eq!(extracted_mode(0o000, true), 0o755);
+        assert_eq!(extracted_mode(0o1777, true), 0o755);
+    }
+
+    #[test]
+    fn entry_names() {
+        assert_eq!(safe_entry_name("files\\src//./main.rs").as_deref(), Some("files/src/main.rs"));
+        for unsafe_name in ["../x", "files/../../x", "/abs", "C:\\x", "a\0b", "", "./"] {
+            assert_eq!(safe_entry_name(unsafe_name), None, "{:?}", unsafe_name);
+        }
+        assert_eq!(path_to_entry_name(Path::new("files").join("a.rs").as_path()), "files/a.rs");
+
+        assert_eq!(windows_safe_name("files/aux.txt").as_deref(), Some("files/aux_.txt"));
+        assert_eq!(windows_safe_name("files/what?.md").as_deref(), Some("files/what_.md"));
+        assert_eq!(windows_safe_name("files/notes. ").as_deref(), Some("files/notes__"));
+        assert_eq!(windows_safe_name("files/auxiliary.rs"), None);
+
+        assert!(is_precompressed("photo.JPG") && !is_precompressed("main.rs"));
+    }
+}
@@ -0,0 +1,1086 @@
+//! Create docpacks from Rust, without shelling out to `localdoc ingest`.
+//! `ingest` is itself a [`DocpackBuilder`] filled in from its flags, so a
+//! docpack built here is the one the CLI makes from the same files and
+//! settings.
+//!
+//! ```no_run
+//! use localdoc::builder::{DocpackBuilder, TaskSpec, Tool};
+//!
+//! let built = DocpackBuilder::new("my-project")
+//!     .description("Sources of my-project")
+//!     .add_dir("path/to/my-project")
+//!     .add_file("NOTES.md", "Start with src/lib.rs\n")
+//!     .tools(&[Tool::ListFiles, Tool::ReadFile, Tool::SearchCode, Tool::WriteOutput])
+//!     .task(TaskSpec {
+//!         name: "Overview".to_string(),
+//!         description: "Write an overview of the crate".to_string(),
+//!         ..TaskSpec::default()
+//!     })
+//!     .build_index(true)
+//!     .write_archive("my-project.docpack")?;
+//! println!("{} files in {}", built.plan.files.len(), built.path.display());
+//! # Ok::<(), localdoc::builder::LocaldocError>(())
+//! ```
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use log::{info, warn};
+use serde_json::{json, Value};
+use docpack_core::{Environment, Manifest, OutputSpec, Task, TasksFile, MANIFEST_FILE, TASKS_FILE};
+
+use crate::archive::{self, ArchiveEntry, ArchiveOptions, Limits};
+use crate::commands::ingest::{self, SourceFile};
+use crate::commands::{outputs, tasks};
+use crate::dedupe;
+use crate::error::WithPath;
+use crate::fingerprint;
+use crate::graph;
+use crate::hashes;
+use crate::progress::Progress;
+use crate::project;
+use crate::secrets;
+use crate::size;
+use crate::symbols;
+use crate::tools;
+
+pub use crate::archive::CompressionMethod;
+pub use crate::commands::ingest::{NonUtf8Policy, SourcePlan, SymlinkPolicy};
+pub use crate::constraints::Constraints;
+pub use crate::error::LocaldocError;
+pub use crate::filter::SourceFilter;
+pub use crate::search::IndexFormat;
+pub use crate::tools::Tool;
+
+/// Entries a plain (non-zip64) zip can hold.
+const ZIP64_MAX_ENTRIES: usize = u16::MAX as usize;
+
+/// Local header and central directory record of one zip entry, excluding
+/// the name, which appears in both.
+const ZIP_ENTRY_OVERHEAD: u64 = 30 + 46;
+
+/// A task for tasks.json. Unset fields get what `localdoc tasks add` gives
+/// them.
+#[derive(Clone, Debug, Default)]
+pub struct TaskSpec {
+    /// Default: `task_N`, N counting the tasks added so far
+    pub id: Option<String>,
+    pub name: String,
+    pub description: String,
+    /// Default: every tool of the docpack
+    pub tools: Option<Vec<Tool>>,
+    /// Path of the output file; default: `output/<id>.md`
+    pub output: Option<String>,
+    /// Ids of the tasks whose outputs this one builds on
+    pub depends_on: Vec<String>,
+}
+
+/// Settings for a docpack, set one at a time and then written with
+/// [`write_archive`](Self::write_archive) or [`write_dir`](Self::write_dir).
+/// Defaults match `localdoc ingest` without flags.
+#[derive(Clone)]
+pub struct DocpackBuilder {
+    name: String,
+    description: Option<String>,
+    language: Option<String>,
+    /// Names for `environment.tools`; `None` is the default preset
+    tools: Option<Vec<String>>,
+    constraints: Constraints,
+    /// Source directories, each with the directory under `files/` it is
+    /// mounted at, if not `files/` itself
+    dirs: Vec<(Option<String>, PathBuf)>,
+    /// In-memory files for `files/`, by path relative to it
+    files: Vec<(String, Vec<u8>)>,
+    tasks: Vec<TaskSpec>,
+    tasks_file: Option<TasksFile>,
+    mission: Option<String>,
+    /// `None` is the default excludes alone
+    filter: Option<SourceFilter>,
+    max_file_size: Option<u64>,
+    max_docpack_size: Option<u64>,
+    symlinks: SymlinkPolicy,
+    non_utf8: NonUtf8Policy,
+    secret_scan: bool,
+    fail_on_secrets: bool,
+    build_index: bool,
+    index_format: IndexFormat,
+    index_all: bool,
+    build_graph: bool,
+    /// Docs directory to copy into `output/`, and whether into `output/seed/`
+    seed_outputs: Option<(PathBuf, bool)>,
+    dedupe: bool,
+    reproducible: bool,
+    created: Option<DateTime<Utc>>,
+    compression: CompressionMethod,
+    compression_level: Option<i32>,
+    metadata: Vec<(String, Value)>,
+    quiet: bool,
+}
+
+/// What [`DocpackBuilder`] wrote.
+pub struct Built {
+    /// The archive or directory
+    pub path: PathBuf,
+    /// The files that went into `files/`, and the ones left out
+    pub plan: SourcePlan,
+    /// Possible credentials in the files, from the secret scan
+    pub secrets: Vec<secrets::Finding>,
+    /// Wall-clock time of each phase of the build, in order
+    pub phases: Vec<(&'static str, Duration)>,
+}
+
+/// Where a build goes.
+#[derive(Clone, Copy)]
+enum Target<'a> {
+    Archive(&'a Path),
+    Dir(&'a Path),
+}
+
+impl DocpackBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        DocpackBuilder {
+            name: name.into(),
+            description: None,
+            language: None,
+            tools: None,
+            constraints: Constraints::default(),
+            dirs: Vec::new(),
+            files: Vec::new(),
+            tasks: Vec::new(),
+            tasks_file: None,
+            mission: None,
+            filter: None,
+            max_file_size: Some(ingest::DEFAULT_MAX_FILE_SIZE),
+            max_docpack_size: None,
+            symlinks: SymlinkPolicy::default(),
+            non_utf8: NonUtf8Policy::default(),
+            secret_scan: true,
+            fail_on_secrets: false,
+            build_index: false,
+            index_format: IndexFormat::default(),
+            index_all: false,
+            build_graph: false,
+            seed_outputs: None,
+            dedupe: false,
+            reproducible: false,
+            created: None,
+            compression: CompressionMethod::Deflated,
+            compression_level: None,
+            metadata: Vec::new(),
+            quiet: false,
+        }
+    }
+
+    /// Default: the first paragraph of the README of a lone source
+    /// directory, or "Generated docpack".
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Primary language of the sources, recorded in `metadata.language`.
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Put the files of `path` in `files/`. A single file goes in by name.
+    pub fn add_dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.dirs.push((None, path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Put the files of `path` in `files/<mount>/`.
+    pub fn add_dir_at(mut self, mount: impl Into<String>, path: impl AsRef<Path>) -> Self {
+        self.dirs.push((Some(mount.into()), path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Put `contents` in `files/<path>`, `path` using `/` separators. Added
+    /// files are not filtered.
+    pub fn add_file(mut self, path: impl Into<String>, contents: impl Into<Vec<u8>>) -> Self {
+        self.files.push((path.into(), contents.into()));
+        self
+    }
+
+    /// The tools the agent gets. Default: the `minimal` preset.
+    pub fn tools(self, tools: &[Tool]) -> Self {
+        self.tool_names(tools.iter().map(|tool| tool.name().to_string()).collect())
+    }
+
+    /// The tools the agent gets, by name, including ones this version of
+    /// localdoc doesn't know.
+    pub fn tool_names(mut self, names: Vec<String>) -> Self {
+        self.tools = Some(names);
+        self
+    }
+
+    /// Run limits for `environment.constraints`.
+    pub fn constraints(mut self, constraints: Constraints) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Add a task. Without any, tasks.json asks for an overview of the
+    /// project.
+    pub fn task(mut self, task: TaskSpec) -> Self {
+        self.tasks.push(task);
+        self
+    }
+
+    /// Use `tasks` as tasks.json, instead of the tasks added with
+    /// [`task`](Self::task).
+    pub fn tasks_file(mut self, tasks: TasksFile) -> Self {
+        self.tasks_file = Some(tasks);
+        self
+    }
+
+    /// The `mission` of tasks.json.
+    pub fn mission(mut self, mission: impl Into<String>) -> Self {
+        self.mission = Some(mission.into());
+        self
+    }
+
+    /// Which source files go in. Default: all but the
+    /// [`DEFAULT_EXCLUDES`](crate::filter::DEFAULT_EXCLUDES) directories.
+    pub fn filter(mut self, filter: SourceFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Leave out files larger than this; `None` for no limit. Default: 50 MB.
+    pub fn max_file_size(mut self, bytes: Option<u64>) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Warn when the archive may be larger than this.
+    pub fn max_docpack_size(mut self, bytes: Option<u64>) -> Self {
+        self.max_docpack_size = bytes;
+        self
+    }
+
+    pub fn symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlinks = policy;
+        self
+    }
+
+    pub fn non_utf8(mut self, policy: NonUtf8Policy) -> Self {
+        self.non_utf8 = policy;
+        self
+    }
+
+    /// Warn about files that look like credentials. Default: on.
+    pub fn secret_scan(mut self, scan: bool) -> Self {
+        self.secret_scan = scan;
+        self
+    }
+
+    /// Fail instead of warning when the secret scan finds anything.
+    pub fn fail_on_secrets(mut self, fail: bool) -> Self {
+        self.fail_on_secrets = fail;
+        self
+    }
+
+    /// Build the search index and `index/symbols.json`.
+    pub fn build_index(mut self, build: bool) -> Self {
+        self.build_index = build;
+        self
+    }
+
+    pub fn index_format(mut self, format: IndexFormat) -> Self {
+        self.index_format = format;
+        self
+    }
+
+    /// Index lockfiles and minified files too.
+    pub fn index_all(mut self, all: bool) -> Self {
+        self.index_all = all;
+        self
+    }
+
+    /// Build `index/graph.json` from the Rust, Python and JS/TS sources.
+    pub fn build_graph(mut self, build: bool) -> Self {
+        self.build_graph = build;
+        self
+    }
+
+    /// Copy the docs in `dir` into `output/` (`output/seed/` with
+    /// `subdir`) for the agent to update.
+    pub fn seed_outputs(mut self, dir: impl AsRef<Path>, subdir: bool) -> Self {
+        self.seed_outputs = Some((dir.as_ref().to_path_buf(), subdir));
+        self
+    }
+
+    /// Store identical files once, under `blobs/`. Archives only.
+    pub fn dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    /// Give identical inputs byte-identical archives.
+    pub fn reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    /// Creation time to record. Default: `SOURCE_DATE_EPOCH`, then the zip
+    /// epoch for reproducible builds, then now.
+    pub fn created(mut self, created: DateTime<Utc>) -> Self {
+        self.created = Some(created);
+        self
+    }
+
+    /// Default: deflate at its default level.
+    pub fn compression(mut self, method: CompressionMethod, level: Option<i32>) -> Self {
+        self.compression = method;
+        self.compression_level = level;
+        self
+    }
+
+    /// Set `metadata.<key>` in docpack.json.
+    pub fn metadata(mut self, key: impl Into<String>, value: Value) -> Self {
+        self.metadata.push((key.into(), value));
+        self
+    }
+
+    /// Leave out the progress bars. Messages go through the `log` crate.
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Write a `.docpack` archive to `out`, replacing any file there.
+    pub fn write_archive(&self, out: impl AsRef<Path>) -> Result<Built, LocaldocError> {
+        let scratch = tempfile::Builder::new().prefix("docpack-build-").tempdir()?;
+        self.write_archive_in(scratch.path(), out.as_ref())
+    }
+
+    /// [`write_archive`](Self::write_archive), with scratch files in
+    /// `scratch`, which the caller removes.
+    pub(crate) fn write_archive_in(&self, scratch: &Path, out: &Path) -> Result<Built, LocaldocError> {
+        self.build(scratch, Target::Archive(out))
+    }
+
+    /// Write an extracted docpack to the directory `out`, which must not
+    /// exist or be empty. `files/` holds every file in full, deduplicated
+    /// or not.
+    pub fn write_dir(&self, out: impl AsRef<Path>) -> Result<Built, LocaldocError> {
+        let out = out.as_ref();
+        if out.exists() && fs::read_dir(out).with_path(out)?.next().is_some() {
+            return Err(format!("{} already exists and is not empty", out.display()).into());
+        }
+        let scratch = tempfile::Builder::new().prefix("docpack-build-").tempdir()?;
+        self.build(scratch.path(), Target::Dir(out))
+    }
+
+    /// Build the docpack, using `scratch` for the generated files of an
+    /// archive and the contents of added files.
+    fn build(&self, scratch: &Path, target: Target) -> Result<Built, LocaldocError> {
+        let staging = match target {
+            Target::Archive(_) => scratch.join("docpack"),
+            Target::Dir(out) => out.to_path_buf(),
+        };
+        let mut phases = Phases::new();
+
+        check_compression(self.compression, self.compression_level)?;
+        if let Some((name, _)) = self.files.iter().find(|(name, _)| {
+            name.is_empty() || !Path::new(name).components().all(|c| matches!(c, Component::Normal(_)))
+        }) {
+            return Err(format!("Cannot add a file at '{}': the path must be relative, without . or ..", name).into());
+        }
+        let filter = match &self.filter {
+            Some(filter) => filter.clone(),
+            None => SourceFilter::new(&[], &[], true)?,
+        };
+        let tools = self.tools.clone().unwrap_or_else(tools::defaults);
+
+        info!("Creating directory structure...");
+        for dir in ["index", "output"] {
+            let path = staging.join(dir);
+            fs::create_dir_all(&path).with_path(&path)?;
+        }
+
+        let created = creation_time(self.created, self.reproducible)?;
+        let timestamp = created.to_rfc3339();
+
+        // Each source gets its own copy of the filter for its ignore file
+        let mut filters = Vec::new();
+        let mut ignore_file = false;
+        for (_, root) in &self.dirs {
+            let mut filter = filter.clone();
+            if root.is_dir() && filter.load_ignore_file(root)? {
+                info!("  Using {}", crate::filter::IGNORE_FILE);
+                ignore_file = true;
+            }
+            filters.push(filter);
+        }
+
+        // Select the source files for files/
+        info!("Scanning source files...");
+        let mut progress = Progress::new("Scanning", None, self.quiet);
+        let mut copied = SourcePlan::default();
+        for ((mount, root), filter) in self.dirs.iter().zip(&filters) {
+            let plan =
+                ingest::plan_files(root, filter, self.max_file_size, self.symlinks, self.non_utf8, &mut progress)?;
+            copied.absorb(plan, mount.as_deref());
+        }
+        progress.finish();
+        for (name, contents) in &self.files {
+            let path = scratch.join("added").join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).with_path(parent)?;
+            }
+            fs::write(&path, contents).with_path(&path)?;
+            copied.dirs.extend(name.match_indices('/').map(|(end, _)| name[..end].to_string()));
+            copied.files.push(SourceFile { name: name.clone(), path, size: contents.len() as u64 });
+        }
+        let mut names = BTreeSet::new();
+        if let Some(file) = copied.files.iter().find(|file| !names.insert(file.name.as_str())) {
+            return Err(format!("files/{} is added more than once", file.name).into());
+        }
+        phases.end("scan");
+
+        for warning in &copied.link_warnings {
+            warn!("{}", warning);
+        }
+        if copied.links_skipped > 0 {
+            info!("  Skipped {} symlinks (--symlinks follow packs their targets)", copied.links_skipped);
+        }
+        ingest::print_non_utf8(&copied.non_utf8);
+
+        info!("  Found {} files", copied.files.len());
+        if !copied.default_excluded.is_empty() {
+            info!(
+                "  Skipped {} files in dependency/build directories (--no-default-excludes keeps them):",
+                copied.default_excluded.values().sum::<usize>()
+            );
+            for (rule, count) in &copied.default_excluded {
+                info!("    • {}/: {} files", rule, count);
+            }
+        }
+        if copied.ignored > 0 {
+            info!("  Skipped {} files by {}", copied.ignored, crate::filter::IGNORE_FILE);
+        }
+        if copied.filtered > 0 {
+            info!("  Skipped {} files by --include/--exclude filters", copied.filtered);
+        }
+        if let Some(limit) = self.max_file_size.filter(|_| !copied.oversized.is_empty()) {
+            info!(
+                "  Skipped {} files larger than {} (--max-file-size):",
+                copied.oversized.len(),
+                size::format_size(limit)
+            );
+            for (path, bytes) in &copied.oversized {
+                info!("    • {} ({})", path, size::format_size(*bytes));
+            }
+        }
+
+        // Credentials are reported, never recorded in the docpack
+        let secret_findings = if self.secret_scan {
+            info!("Scanning for secrets...");
+            let to_scan: Vec<(String, &Path)> = copied.files.iter().map(|f| (f.name.clone(), f.path.as_path())).collect();
+            let findings = secrets::scan(&to_scan);
+            if !findings.is_empty() {
+                let listed: Vec<String> = findings
+                    .iter()
+                    .map(|finding| match finding.line {
+                        Some(line) => format!("  • {}:{} ({})", finding.path, line, finding.kind),
+                        None => format!("  • {} ({})", finding.path, finding.kind),
+                    })
+                    .collect();
+                warn!(
+                    "Possible secrets found ({}):\n{}\n  Leave them out with --exclude or .docpackignore",
+                    findings.len(),
+                    listed.join("\n")
+                );
+            }
+            if self.fail_on_secrets && !findings.is_empty() {
+                return Err(format!(
+                    "Found {} possible secrets in the source; not creating the docpack (--fail-on-secrets)",
+                    findings.len()
+                )
+                .into());
+            }
+            phases.end("secrets");
+            findings
+        } else {
+            Vec::new()
+        };
+
+        // Record an integrity manifest for files/
+        info!("Hashing source files...");
+        let to_hash: Vec<(String, PathBuf)> = copied.files.iter().map(|f| (f.name.clone(), f.path.clone())).collect();
+        let mut progress = Progress::new("Hashing", Some(to_hash.len() as u64), self.quiet);
+        let file_hashes = hashes::hash_files(&to_hash, &mut progress)?;
+        progress.finish();
+        fs::write(
+            staging.join("index").join(hashes::FILE_NAME),
+            serde_json::to_string_pretty(&hashes::to_json(&file_hashes))?,
+        )?;
+        info!("  Created index/{}", hashes::FILE_NAME);
+
+        // Store each distinct file once when asked to; a directory always
+        // holds files/ in full
+        let layout = (self.dedupe && matches!(target, Target::Archive(_))).then(|| dedupe::plan(&to_hash, &file_hashes));
+        if let Some(layout) = &layout {
+            let map_path = staging.join(dedupe::MAP_FILE);
+            fs::write(&map_path, serde_json::to_string_pretty(&layout.map)?).with_path(&map_path)?;
+            info!(
+                "  Deduplicated {} files into {} blobs, saving {}",
+                layout.files,
+                layout.blobs.len(),
+                size::format_size(layout.saved_bytes)
+            );
+        }
+        if let Target::Dir(_) = target {
+            copy_files(&copied, &staging.join("files"))?;
+        }
+        phases.end("hash");
+
+        // Pre-seed output/ with existing documentation
+        let seeded = match &self.seed_outputs {
+            Some((seed_path, subdir)) => {
+                info!("Seeding outputs from {}...", seed_path.display());
+                let (target, seeded) = outputs::seed_into(&staging, seed_path, *subdir)?;
+                info!("  Copied {} files into {}/", seeded.len(), target);
+                Some((target, seeded))
+            }
+            None => None,
+        };
+
+        // Project details come from a lone source; several have no one README
+        let lone_root = match self.dirs.as_slice() {
+            [(_, root)] if root.is_dir() => Some(root.as_path()),
+            _ => None,
+        };
+
+        // An explicit description wins over the README
+        let readme_description = match (&self.description, lone_root) {
+            (None, Some(root)) => project::readme_description(root),
+            _ => None,
+        };
+        let description = self.description.clone().or(readme_description.clone());
+
+        // Create docpack.json manifest
+        info!("Creating manifest...");
+        let mut manifest = Manifest {
+            name: Some(self.name.clone()),
+            description: Some(description.as_deref().unwrap_or("Generated docpack").to_string()),
+            environment: Environment {
+                tools: tools.clone(),
+                constraints: Some(self.constraints.clone()),
+                ..Environment::default()
+            },
+            ..Manifest::default()
+        };
+        let metadata = manifest.metadata_mut();
+        metadata.insert("created".to_string(), json!(timestamp));
+        metadata.insert("creator".to_string(), json!("localdoc"));
+        metadata.insert("language".to_string(), json!(self.language.as_deref().unwrap_or("unknown")));
+        for (key, value) in &self.metadata {
+            metadata.insert(key.clone(), value.clone());
+        }
+
+        if !copied.oversized.is_empty() {
+            metadata.insert("skipped_files".to_string(), json!(copied
+                .oversized
+                .iter()
+                .map(|(path, bytes)| json!({
+                    "path": path,
+                    "size": bytes,
+                    "reason": "max_file_size",
+                }))
+                .collect::<Vec<_>>()));
+        }
+
+        if !copied.non_utf8.is_empty() {
+            metadata.insert("non_utf8".to_string(), ingest::non_utf8_metadata(&copied.non_utf8, self.non_utf8));
+        }
+
+        metadata.insert("hashes".to_string(), hashes::metadata(&file_hashes));
+        if let Some(layout) = &layout {
+            metadata.insert("dedupe".to_string(), layout.metadata());
+        }
+
+        metadata.insert(
+            "compression".to_string(),
+            json!({
+                "method": compression_name(self.compression),
+                "level": self.compression_level,
+                "store_precompressed": true,
+            }),
+        );
+
+        if readme_description.is_some() {
+            metadata.insert("description_source".to_string(), json!("readme"));
+            info!("  Description taken from the README");
+        }
+        if let Some(license) = lone_root.and_then(project::license) {
+            metadata.insert("license".to_string(), json!(license));
+        }
+
+        if ignore_file {
+            metadata.insert(
+                "ignore_file".to_string(),
+                json!({
+                    "path": crate::filter::IGNORE_FILE,
+                    "ignored": copied.ignored,
+                }),
+            );
+        }
+
+        if let (Some((seed_path, _)), Some((target, seeded))) = (&self.seed_outputs, &seeded) {
+            metadata.insert("seeded_outputs".to_string(), outputs::seed_metadata(seed_path, target, seeded.len(), created));
+        }
+
+        manifest.save(&staging.join(MANIFEST_FILE))?;
+        info!("  Created docpack.json");
+
+        self.write_tasks(&staging.join(TASKS_FILE), &tools, seeded.as_ref().map(|(target, _)| target.as_str()))?;
+
+        if let Some((_, seeded)) = &seeded {
+            outputs::report_seed_collisions(&outputs::seed_collisions(&staging, seeded));
+        }
+        phases.end("manifest");
+
+        // Build index if requested
+        if self.build_index {
+            info!("Building search index...");
+            let mut progress = Progress::new("Indexing", Some(copied.files.len() as u64), self.quiet);
+            let selection = ingest::selection_options(&filter, self.max_file_size, self.symlinks, self.non_utf8);
+            let stats = ingest::build_search_index(
+                &copied.files,
+                &staging.join("index"),
+                selection,
+                self.index_format,
+                self.index_all,
+                &timestamp,
+                &mut progress,
+            )?;
+            progress.finish();
+            info!("  Created index/{}", self.index_format.file_name());
+            info!("  Created index/{} ({} symbols)", symbols::FILE_NAME, stats.symbols);
+            if stats.skipped > 0 {
+                info!("  Left out {} lockfiles and minified files (--index-all to include them)", stats.skipped);
+            }
+            info!("  Fingerprint: {}", fingerprint::describe(&stats.fingerprint));
+            phases.end("index");
+        }
+
+        // Build graph if requested
+        if self.build_graph {
+            info!("Building semantic graph...");
+            let sources = copied.files.iter().filter(|file| graph::is_source(&file.name)).count();
+            let mut progress = Progress::new("Parsing", Some(sources as u64), self.quiet);
+            let stats = ingest::build_graph(&copied.files, &staging.join("index"), &timestamp, &mut progress)?;
+            progress.finish();
+            info!(
+                "  Created index/graph.json ({} nodes, {} edges from {} source files)",
+                stats.nodes, stats.edges, stats.files
+            );
+            for path in &stats.unparsed {
+                warn!("Could not parse {}; left out of the graph", path);
+            }
+            info!("  Fingerprint: {}", fingerprint::describe(&stats.fingerprint));
+            phases.end("graph");
+        }
+
+        let path = match target {
+            Target::Dir(out) => out.to_path_buf(),
+            Target::Archive(zip_path) => {
+                info!("Creating zip archive...");
+                let entries = archive_paths(&staging, &copied, layout.as_ref())?;
+                check_docpack_size(&entries, self.max_docpack_size);
+                let file_count = entries.iter().filter(|(_, path)| path.is_some()).count();
+                let mut progress = Progress::new("Compressing", Some(file_count as u64), self.quiet);
+                let mut archive_options = ArchiveOptions {
+                    compression: self.compression,
+                    compression_level: self.compression_level,
+                    // The extraction limits guard against hostile archives;
+                    // these are the user's own files, already sized by
+                    // --max-file-size
+                    limits: Limits::unlimited(),
+                    ..ArchiveOptions::default()
+                };
+                if self.reproducible {
+                    archive_options.modified = zip_time(created);
+                }
+                create_zip_archive(entries, zip_path, &archive_options, &mut progress)?;
+                progress.finish();
+                phases.end("archive");
+                zip_path.to_path_buf()
+            }
+        };
+
+        Ok(Built { path, plan: copied, secrets: secret_findings, phases: phases.times })
+    }
+
+    /// Write tasks.json: the tasks file, the added tasks, or one asking for
+    /// an overview. With seeded outputs that task asks the agent to update
+    /// the docs in `seed_target` rather than start over.
+    fn write_tasks(&self, tasks_path: &Path, tools: &[String], seed_target: Option<&str>) -> Result<(), LocaldocError> {
+        if let Some(tasks) = &self.tasks_file {
+            info!("Adding tasks.json...");
+            let mut tasks = tasks.clone();
+            if let Some(mission) = &self.mission {
+                tasks.mission = Some(mission.clone());
+            }
+            tasks.save(tasks_path)?;
+            info!("  Added tasks.json");
+            return Ok(());
+        }
+
+        info!("Creating tasks.json...");
+        let list = if self.tasks.is_empty() {
+            let task_description = match seed_target {
+                Some(target) => format!(
+                    "Update the existing documentation in {}/ so it reflects the current codebase, then create a high-level overview",
+                    target
+                ),
+                None => "Explore the codebase and create a high-level overview".to_string(),
+            };
+            vec![Task {
+                id: Some("task_1".to_string()),
+                name: Some("Analyze project structure".to_string()),
+                description: Some(task_description),
+                tools_allowed: Some(tools.to_vec()),
+                output: Some(OutputSpec::default()),
+                ..Task::default()
+            }]
+        } else {
+            self.tasks.iter().enumerate().map(|(i, spec)| spec.to_task(i + 1, tools)).collect()
+        };
+        let tasks = TasksFile {
+            mission: Some(self.mission.as_deref().unwrap_or("Explore and document this project").to_string()),
+            tasks: Some(list),
+            ..TasksFile::default()
+        };
+        tasks.save(tasks_path)?;
+        info!("  Created tasks.json");
+        Ok(())
+    }
+}
+
+impl TaskSpec {
+    /// The tasks.json entry for the `n`th task, given the docpack's tools.
+    fn to_task(&self, n: usize, tools: &[String]) -> Task {
+        let id = self.id.clone().unwrap_or_else(|| format!("task_{}", n));
+        let path = self.output.clone().unwrap_or_else(|| format!("output/{}.md", id));
+        Task {
+            name: Some(self.name.clone()),
+            description: Some(self.description.clone()),
+            depends_on: (!self.depends_on.is_empty()).then(|| self.depends_on.clone()),
+            tools_allowed: Some(match &self.tools {
+                Some(tools) => tools.iter().map(|tool| tool.name().to_string()).collect(),
+                None => tools.to_vec(),
+            }),
+            output: Some(OutputSpec { kind: Some(tasks::output_type(&path).to_string()), path, ..OutputSpec::default() }),
+            id: Some(id),
+            ..Task::default()
+        }
+    }
+}
+
+/// Wall-clock time spent in each phase of a build.
+struct Phases {
+    times: Vec<(&'static str, Duration)>,
+    started: Instant,
+}
+
+impl Phases {
+    fn new() -> Self {
+        Phases { times: Vec::new(), started: Instant::now() }
+    }
+
+    /// Close the phase `name`, which began when the previous one ended.
+    fn end(&mut self, name: &'static str) {
+        self.times.push((name, self.started.elapsed()));
+        self.started = Instant::now();
+    }
+}
+
+/// Copy the planned files into `files_dir`, empty directories included.
+fn copy_files(plan: &SourcePlan, files_dir: &Path) -> Result<(), LocaldocError> {
+    fs::create_dir_all(files_dir).with_path(files_dir)?;
+    for dir in &plan.dirs {
+        let dir = files_dir.join(dir);
+        fs::create_dir_all(&dir).with_path(&dir)?;
+    }
+    for file in &plan.files {
+        fs::copy(&file.path, files_dir.join(&file.name)).with_path(&file.path)?;
+    }
+    Ok(())
+}
+
+fn compression_name(method: CompressionMethod) -> &'static str {
+    match method {
+        CompressionMethod::Stored => "none",
+        CompressionMethod::Zstd => "zstd",
+        _ => "deflate",
+    }
+}
+
+pub(crate) fn check_compression(method: CompressionMethod, level: Option<i32>) -> Result<(), LocaldocError> {
+    let Some(level) = level else { return Ok(()) };
+    match archive::compression_levels(method) {
+        Some(range) if range.contains(&level) => Ok(()),
+        Some(range) => Err(format!(
+            "Compression level {} is out of range for {} ({}-{})",
+            level,
+            compression_name(method),
+            range.start(),
+            range.end()
+        )
+        .into()),
+        None => Err(format!("--compression-level has no effect with --compression {}", compression_name(method)).into()),
+    }
+}
+
+/// The creation time to record: `created`, then `SOURCE_DATE_EPOCH`, then
+/// the zip epoch for `reproducible` builds, and otherwise now.
+fn creation_time(created: Option<DateTime<Utc>>, reproducible: bool) -> Result<DateTime<Utc>, LocaldocError> {
+    if let Some(created) = created {
+        return Ok(created);
+    }
+
+    if let Ok(epoch) = std::env::var("SOURCE_DATE_EPOCH") {
+        let seconds: i64 = epoch
+            .trim()
+            .parse()
+            .map_err(|_| format!("SOURCE_DATE_EPOCH must be a number of seconds, got '{}'", epoch))?;
+        return DateTime::from_timestamp(seconds, 0)
+            .ok_or_else(|| format!("SOURCE_DATE_EPOCH out of range: {}", seconds).into());
+    }
+
+    if reproducible {
+        return Ok(DateTime::from_timestamp(315_532_800, 0).expect("1980-01-01 is in range"));
+    }
+
+    Ok(Utc::now())
+}
+
+/// `time` as a zip timestamp, clamped to the zip epoch (1980).
+fn zip_time(time: DateTime<Utc>) -> zip::DateTime {
+    zip::DateTime::from_date_and_time(
+        time.year().clamp(1980, 2107) as u16,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+    .filter(|_| time.year() >= 1980)
+    .unwrap_or_default()
+}
+
+/// (entry name, file) pairs for [`archive::prefetched_entries`], with `None` for
+/// directories.
+pub(crate) type EntryPaths = Vec<(String, Option<PathBuf>)>;
+
+/// Archive entries for the docpack: the generated artifacts in `staging`
+/// plus the planned source files under `files/`, or under `blobs/` once
+/// each with a deduplicated `layout`.
+pub(crate) fn archive_paths(
+    staging: &Path,
+    plan: &SourcePlan,
+    layout: Option<&dedupe::Layout>,
+) -> Result<EntryPaths, LocaldocError> {
+    let mut paths = vec![("files".to_string(), None)];
+    paths.extend(plan.dirs.iter().map(|dir| (format!("files/{}", dir), None)));
+    match layout {
+        Some(layout) => {
+            paths.push((dedupe::BLOB_DIR.to_string(), None));
+            paths.extend(
+                layout.blobs.iter().map(|(blob, path)| (format!("{}/{}", dedupe::BLOB_DIR, blob), Some(path.clone()))),
+            );
+        }
+        None => {
+            paths.extend(plan.files.iter().map(|file| (format!("files/{}", file.name), Some(file.path.clone()))));
+        }
+    }
+
+    for entry in walkdir::WalkDir::new(staging).min_depth(1) {
+        let entry = entry?;
+        let name = archive::path_to_entry_name(entry.path().strip_prefix(staging)?);
+        let path = (!entry.file_type().is_dir()).then(|| entry.path().to_path_buf());
+        paths.push((name, path));
+    }
+
+    Ok(paths)
+}
+
+/// Preflight for the archive about to be written: warn when it may exceed
+/// `max_docpack_size`, needs zip64, or is more than `run` will extract.
+fn check_docpack_size(paths: &EntryPaths, max_docpack_size: Option<u64>) {
+    let content: u64 = paths
+        .iter()
+        .filter_map(|(_, path)| path.as_ref())
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum();
+    // Stored sizes plus local and central headers bound the archive size
+    let estimate = content + paths.iter().map(|(name, _)| ZIP_ENTRY_OVERHEAD + 2 * name.len() as u64).sum::<u64>();
+
+    if let Some(limit) = max_docpack_size.filter(|&limit| estimate > limit) {
+        info!(
+            "⚠️  The docpack may be up to {}, more than --max-docpack-size {}",
+            size::format_size(estimate),
+            size::format_size(limit)
+        );
+        info!("  Narrow the source with --exclude, --max-file-size or {}", crate::filter::IGNORE_FILE);
+    }
+    if paths.len() > ZIP64_MAX_ENTRIES || estimate > u32::MAX as u64 {
+        info!(
+            "  Writing a zip64 archive ({} entries, up to {}); some older unzip tools can't open it",
+            paths.len(),
+            size::format_size(estimate)
+        );
+    }
+    let limits = Limits::default();
+    if paths.len() > limits.max_entries || content > limits.max_total_size {
+        info!(
+            "⚠️  `localdoc run` extracts at most {} entries and {}; this docpack has {} entries and {}",
+            limits.max_entries,
+            size::format_size(limits.max_total_size),
+            paths.len(),
+            size::format_size(content)
+        );
+    }
+}
+
+pub(crate) fn create_zip_archive(
+    paths: EntryPaths,
+    zip_path: &Path,
+    options: &ArchiveOptions,
+    progress: &mut Progress,
+) -> Result<(), LocaldocError> {
+    let file = fs::File::create(zip_path).with_path(zip_path)?;
+
+    // Entries are read lazily by the archive writer, so count bytes as they
+    // are pulled through rather than when the entry is produced
+    let written = Arc::new(AtomicU64::new(0));
+    let mut files = 0;
+    let entries = archive::prefetched_entries(paths).map(|entry| {
+        progress.update(files, written.load(Ordering::Relaxed));
+        entry.map(|entry| match entry {
+            ArchiveEntry::File { path, reader, unix_mode, size } => {
+                files += 1;
+                ArchiveEntry::File {
+                    path,
+                    reader: Box::new(CountingReader { inner: reader, count: Arc::clone(&written) }),
+                    unix_mode,
+                    size,
+                }
+            }
+            other => other,
+        })
+    });
+
+    archive::build_archive(entries, file, options)?;
+    Ok(())
+}
+
+/// Adds the number of bytes read to a shared counter.
+struct CountingReader {
+    inner: Box<dyn Read + Send>,
+    count: Arc<AtomicU64>,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::validate;
+    use crate::reader;
+
+    fn sample(source: &Path) -> DocpackBuilder {
+        fs::create_dir_all(source.join("src")).unwrap();
+        fs::write(source.join("README.md"), "# Sample\n\nA sample crate.\n").unwrap();
+        fs::write(source.join("src/lib.rs"), "pub fn answer() -> u32 {\n    42\n}\n").unwrap();
+        DocpackBuilder::new("sample")
+            .add_dir(source)
+            .add_file("notes/NOTES.md", "Start with src/lib.rs\n")
+            .tools(&[Tool::ListFiles, Tool::ReadFile, Tool::SearchCode, Tool::WriteOutput])
+            .task(TaskSpec { name: "Overview".to_string(), description: "Describe the crate".to_string(), ..TaskSpec::default() })
+            .task(TaskSpec {
+                id: Some("api".to_string()),
+                name: "API".to_string(),
+                description: "Document the API".to_string(),
+                output: Some("output/api.json".to_string()),
+                depends_on: vec!["task_1".to_string()],
+                ..TaskSpec::default()
+            })
+            .build_index(true)
+            .quiet(true)
+    }
+
+    fn assert_valid(path: &Path) {
+        let (errors, warnings) = validate::preflight(path, false);
+        assert!(errors.is_empty(), "{}", errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"));
+        assert!(warnings.is_empty(), "{}", warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>().join("\n"));
+    }
+
+    #[test]
+    fn archives_pass_validation() {
+        let temp = tempfile::tempdir().unwrap();
+        let out = temp.path().join("sample.docpack");
+        let built = sample(&temp.path().join("source")).write_archive(&out).unwrap();
+        assert_eq!(built.path, out);
+        assert_eq!(built.plan.files.len(), 3);
+        assert_valid(&out);
+
+        let mut docpack = reader::open(&out).unwrap();
+        assert_eq!(docpack.read("files/notes/NOTES.md").unwrap(), b"Start with src/lib.rs\n");
+        let manifest = docpack.read_manifest().unwrap();
+        assert_eq!(manifest.description.as_deref(), Some("A sample crate."));
+        assert_eq!(manifest.environment.tools, ["list_files", "read_file", "search_code", "write_output"]);
+        let tasks = docpack.read_tasks().unwrap().tasks.unwrap();
+        assert_eq!(tasks[0].id.as_deref(), Some("task_1"));
+        assert_eq!(tasks[0].output.as_ref().unwrap().path, "output/task_1.md");
+        assert_eq!(tasks[1].output.as_ref().unwrap().kind.as_deref(), Some("json"));
+        assert_eq!(tasks[1].depends_on.as_deref(), Some(&["task_1".to_string()][..]));
+    }
+
+    #[test]
+    fn directories_pass_validation() {
+        let temp = tempfile::tempdir().unwrap();
+        let out = temp.path().join("sample");
+        sample(&temp.path().join("source")).dedupe(true).write_dir(&out).unwrap();
+        assert_valid(&out);
+        assert!(out.join("files/src/lib.rs").is_file());
+        assert!(!out.join(dedupe::MAP_FILE).exists());
+
+        let again = sample(&temp.path().join("source")).write_dir(&out);
+        assert!(again.err().unwrap().to_string().contains("is not empty"));
+    }
+
+    #[test]
+    fn added_files_stay_under_files() {
+        let temp = tempfile::tempdir().unwrap();
+        for name in ["../escape.md", "/etc/passwd", ""] {
+            let result = DocpackBuilder::new("bad").add_file(name, "x").quiet(true).write_dir(temp.path().join("out"));
+            assert!(result.err().unwrap().to_string().contains("must be relative"), "{}", name);
+        }
+
+        let result = DocpackBuilder::new("twice")
+            .add_file("a.md", "one")
+            .add_file("a.md", "two")
+            .quiet(true)
+            .write_dir(temp.path().join("out"));
+        assert!(result.err().unwrap().to_string().contains("added more than once"));
+    }
+}
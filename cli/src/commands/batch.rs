@@ -0,0 +1,334 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use clap::ValueEnum;
+use log::info;
+
+use crate::commands::history::format_duration;
+use crate::commands::run::{self, RunOptions};
+use crate::error::{LocaldocError, WithPath};
+use crate::interrupt;
+
+/// How a docpack in the batch turned out.
+struct Outcome {
+    docpack: PathBuf,
+    status: String,
+    seconds: f64,
+}
+
+/// Whether `run` was given more than one docpack, a glob, or `--log-dir`,
+/// and so runs them as a batch.
+pub fn is_batch(docpacks: &[String], log_dir: Option<&str>) -> bool {
+    docpacks.len() > 1 || log_dir.is_some() || docpacks.iter().any(|docpack| is_glob(docpack))
+}
+
+/// Run each docpack with its own `localdoc run`, `jobs` at a time. Each
+/// gets its own process, so its own container, temporary copy, summary and
+/// Ctrl-C handling, exactly as if it had been run alone; a failure only
+/// marks that docpack failed. Their output is shown prefixed with the
+/// docpack's name, or written to `<log_dir>/<name>.log`.
+pub fn run(
+    docpacks: &[String],
+    jobs: usize,
+    log_dir: Option<&str>,
+    opts: &RunOptions,
+) -> Result<(), LocaldocError> {
+    if opts.output_dir.is_some() {
+        return Err(LocaldocError::InvalidArgument(
+            "--output-dir cannot be shared by several docpacks; each is copied to <name>-output/ beside it".to_string(),
+        ));
+    }
+    if opts.json || opts.detach || opts.list_tasks {
+        return Err(LocaldocError::InvalidArgument("--json, --detach and --list-tasks take a single docpack".to_string()));
+    }
+
+    let paths = expand(docpacks)?;
+    let names = unique_names(&paths);
+    if let Some(dir) = log_dir {
+        fs::create_dir_all(dir).with_path(dir)?;
+    }
+    let args = child_args(opts);
+    let exe = std::env::current_exe()?;
+    let workers = jobs.clamp(1, paths.len());
+    interrupt::defer_to_children();
+
+    info!("Running {} docpacks ({} at a time)\n", paths.len(), workers);
+
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel::<(usize, Outcome)>();
+    let mut outcomes: Vec<Option<Outcome>> = paths.iter().map(|_| None).collect();
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (next, paths, names, args, exe) = (&next, &paths, &names, &args, &exe);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                let Some(path) = paths.get(i) else { break };
+                let outcome = if interrupt::interrupted() {
+                    Outcome { docpack: path.clone(), status: "skipped".to_string(), seconds: 0.0 }
+                } else {
+                    let log = log_dir.map(|dir| Path::new(dir).join(format!("{}.log", names[i])));
+                    run_one(exe, args, path, &names[i], log.as_deref())
+                };
+                if tx.send((i, outcome)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+        for (i, outcome) in rx {
+            if outcome.status != "skipped" {
+                info!("[{}] Finished: {} ({})", names[i], outcome.status, format_duration(outcome.seconds));
+            }
+            outcomes[i] = Some(outcome);
+        }
+    });
+
+    let outcomes: Vec<Outcome> = outcomes.into_iter().flatten().collect();
+    info!("\n{:<22}  {:>9}  DOCPACK", "STATUS", "DURATION");
+    for outcome in &outcomes {
+        info!("{:<22}  {:>9}  {}", outcome.status, format_duration(outcome.seconds), outcome.docpack.display());
+    }
+    if let Some(dir) = log_dir {
+        info!("\nLogs are in {}", dir);
+    }
+
+    let failed = outcomes.iter().filter(|outcome| outcome.status != "success").count();
+    info!("\n{} of {} docpacks succeeded", outcomes.len() - failed, outcomes.len());
+    if failed > 0 {
+        Err(format!("Runs failed for {} docpack(s)", failed).into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Run one docpack in a child `localdoc run`, streaming its output.
+fn run_one(exe: &Path, args: &[OsString], docpack: &Path, name: &str, log: Option<&Path>) -> Outcome {
+    let started = Instant::now();
+    let outcome = |status: String| Outcome { docpack: docpack.to_path_buf(), status, seconds: started.elapsed().as_secs_f64() };
+
+    let sink = match log {
+        Some(path) => match fs::File::create(path) {
+            Ok(file) => Sink::File(Arc::new(Mutex::new(file))),
+            Err(e) => return outcome(format!("failed ({})", e)),
+        },
+        None => Sink::Terminal(format!("[{}] ", name)),
+    };
+    let child = Command::new(exe)
+        .arg("run")
+        .arg(docpack)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => return outcome(format!("failed ({})", e)),
+    };
+    let streams: Vec<thread::JoinHandle<()>> = [
+        child.stdout.take().map(|out| forward(out, sink.clone(), false)),
+        child.stderr.take().map(|err| forward(err, sink.clone(), true)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let status = child.wait();
+    for stream in streams {
+        let _ = stream.join();
+    }
+    match status.map(|status| status.code()) {
+        Ok(Some(0)) => outcome("success".to_string()),
+        Ok(Some(130)) | Ok(None) => outcome("interrupted".to_string()),
+        Ok(Some(code)) => outcome(format!("failed (exit {})", code)),
+        Err(e) => outcome(format!("failed ({})", e)),
+    }
+}
+
+/// Where a child's output goes.
+#[derive(Clone)]
+enum Sink {
+    /// The terminal, each line behind this prefix
+    Terminal(String),
+    File(Arc<Mutex<fs::File>>),
+}
+
+/// Copy `source` to `sink` a line at a time, so lines from parallel runs
+/// are never split. `stderr` picks the terminal stream.
+fn forward<R: Read + Send + 'static>(source: R, sink: Sink, stderr: bool) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(source);
+        let mut line = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+            if line.last() != Some(&b'\n') {
+                line.push(b'\n');
+            }
+            let _ = match &sink {
+                Sink::File(file) => file.lock().unwrap_or_else(|e| e.into_inner()).write_all(&line),
+                Sink::Terminal(prefix) if stderr => {
+                    let mut err = io::stderr().lock();
+                    err.write_all(prefix.as_bytes()).and_then(|_| err.write_all(&line))
+                }
+                Sink::Terminal(prefix) => {
+                    let mut out = io::stdout().lock();
+                    out.write_all(prefix.as_bytes()).and_then(|_| out.write_all(&line))
+                }
+            };
+            line.clear();
+        }
+    })
+}
+
+/// The flags a child `localdoc run` needs to run one docpack as this run
+/// was asked to. `--follow` is always on, so the agent's output is part of
+/// what is prefixed or logged.
+fn child_args(opts: &RunOptions) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec!["--image".into(), opts.image.into(), "--follow".into()];
+    for var in opts.env {
+        args.extend(["--env".into(), var.into()]);
+    }
+    if let Some(path) = opts.env_file {
+        args.extend(["--env-file".into(), path.into()]);
+    }
+    let mut flag = |set: bool, name: &str| {
+        if set {
+            args.push(name.into());
+        }
+    };
+    flag(opts.no_env, "--no-env");
+    flag(opts.update_archive, "--update-archive");
+    flag(opts.writable_files, "--writable-files");
+    flag(opts.no_network, "--no-network");
+    flag(opts.no_user_mapping, "--no-user-mapping");
+    flag(opts.no_validate, "--no-validate");
+    flag(opts.strict, "--strict");
+    flag(opts.dry_run, "--dry-run");
+    flag(opts.quiet, "--quiet");
+    let values = [
+        ("--max-output-bytes", opts.max_output_bytes.map(|n| n.to_string())),
+        ("--max-output-file-bytes", opts.max_output_file_bytes.map(|n| n.to_string())),
+        ("--memory", opts.memory_mb.map(|n| n.to_string())),
+        ("--cpus", opts.cpus.map(|n| n.to_string())),
+        ("--timeout", opts.timeout_seconds.map(|n| n.to_string())),
+        ("--user", opts.user.map(str::to_string)),
+        ("--runtime", opts.runtime.and_then(|r| r.to_possible_value()).map(|v| v.get_name().to_string())),
+        ("--pull", opts.pull.to_possible_value().map(|v| v.get_name().to_string())),
+    ];
+    for (name, value) in values {
+        if let Some(value) = value {
+            args.extend([name.into(), value.into()]);
+        }
+    }
+    for task in opts.tasks {
+        args.extend(["--task".into(), task.into()]);
+    }
+    args
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{']) && !Path::new(pattern).exists()
+}
+
+/// The docpacks to run: paths as given, and for each glob the docpack
+/// directories and `.docpack` archives it matches, sorted. A glob that
+/// matches nothing is an error, as is naming a docpack twice.
+fn expand(docpacks: &[String]) -> Result<Vec<PathBuf>, LocaldocError> {
+    let mut paths = Vec::new();
+    for docpack in docpacks {
+        if !is_glob(docpack) {
+            paths.push(PathBuf::from(docpack));
+            continue;
+        }
+        let matcher = globset::GlobBuilder::new(docpack).literal_separator(true).build()?.compile_matcher();
+        // Walk from the last directory before the first wildcard
+        let pattern = Path::new(docpack);
+        let base: PathBuf = pattern
+            .components()
+            .take_while(|c| !matches!(c, Component::Normal(part) if is_glob(&part.to_string_lossy())))
+            .collect();
+        let depth = pattern.components().count() - base.components().count();
+        let root = if base.as_os_str().is_empty() { PathBuf::from(".") } else { base.clone() };
+        let mut found: Vec<PathBuf> = walkdir::WalkDir::new(&root)
+            .min_depth(depth)
+            .max_depth(depth)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| {
+                let path = entry.into_path();
+                if base.as_os_str().is_empty() { path.strip_prefix(".").map(Path::to_path_buf).unwrap_or(path) } else { path }
+            })
+            .filter(|path| matcher.is_match(path))
+            .filter(|path| path.join("docpack.json").exists() || path.extension().and_then(|e| e.to_str()) == Some("docpack"))
+            .collect();
+        if found.is_empty() {
+            return Err(LocaldocError::NotFound(format!("No docpacks match {}", docpack)));
+        }
+        found.sort();
+        paths.extend(found);
+    }
+
+    let mut seen = HashSet::new();
+    for path in &paths {
+        if !seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone())) {
+            return Err(format!("{} is given more than once", path.display()).into());
+        }
+    }
+    Ok(paths)
+}
+
+/// A short name for each docpack, for prefixes and log files: its file
+/// name, with `-2`, `-3`… added where two are the same.
+fn unique_names(paths: &[PathBuf]) -> Vec<String> {
+    let mut taken = HashSet::new();
+    paths
+        .iter()
+        .map(|path| {
+            let base = run::docpack_name(path);
+            let mut name = base.clone();
+            let mut n = 1;
+            while !taken.insert(name.clone()) {
+                n += 1;
+                name = format!("{}-{}", base, n);
+            }
+            name
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_docpacks_only() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b", "a"] {
+            fs::create_dir_all(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join("docpack.json"), "{}").unwrap();
+        }
+        fs::write(dir.path().join("c.docpack"), "").unwrap();
+        fs::create_dir_all(dir.path().join("notes")).unwrap();
+
+        let pattern = format!("{}/*", dir.path().display());
+        let found = expand(&[pattern]).unwrap();
+        let names: Vec<String> = found.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, ["a", "b", "c.docpack"]);
+
+        assert!(expand(&[format!("{}/*.zip", dir.path().display())]).unwrap_err().to_string().contains("No docpacks match"));
+        let twice = dir.path().join("a").display().to_string();
+        assert!(expand(&[twice.clone(), twice]).is_err());
+    }
+
+    #[test]
+    fn names_are_made_unique() {
+        let paths = [PathBuf::from("x/api"), PathBuf::from("y/api"), PathBuf::from("web.docpack")];
+        assert_eq!(unique_names(&paths), ["api", "api-2", "web"]);
+    }
+}
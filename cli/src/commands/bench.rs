@@ -0,0 +1,202 @@
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::archive::{ArchiveOptions, Limits};
+use crate::builder;
+use crate::commands::ingest::{self, NonUtf8Policy, SourcePlan, SymlinkPolicy};
+use crate::error::{LocaldocError, WithPath};
+use crate::filter::SourceFilter;
+use crate::hashes;
+use crate::progress::Progress;
+use crate::rss;
+use crate::search::IndexFormat;
+use crate::size::format_size;
+use crate::synthetic::{self, TreeSpec};
+
+/// Settings for `localdoc bench`.
+pub struct BenchOptions<'a> {
+    /// Source directory to time; `None` generates synthetic trees
+    pub source: Option<&'a str>,
+    /// Sizes of the synthetic trees, in files
+    pub sizes: &'a [usize],
+    pub seed: u64,
+    /// Write the results as JSON to this file
+    pub json_out: Option<&'a str>,
+    /// Results of an earlier run to compare with
+    pub baseline: Option<&'a str>,
+    /// Keep the trees and archives here instead of in a temporary directory
+    pub keep: Option<&'a str>,
+}
+
+/// Everything one `bench` run measured, as written by `--json`.
+#[derive(Serialize, Deserialize)]
+struct Report {
+    localdoc_version: String,
+    os: String,
+    runs: Vec<TreeRun>,
+}
+
+/// The phases of ingesting one tree.
+#[derive(Serialize, Deserialize)]
+struct TreeRun {
+    tree: String,
+    files: usize,
+    bytes: u64,
+    phases: Vec<Phase>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Phase {
+    name: String,
+    seconds: f64,
+    /// Highest resident memory during the phase, in bytes; `None` where the
+    /// OS doesn't report it
+    peak_rss: Option<u64>,
+}
+
+pub fn run(opts: &BenchOptions) -> Result<(), LocaldocError> {
+    let baseline: Option<Report> = match opts.baseline {
+        Some(path) => {
+            let text = fs::read_to_string(path).with_path(path)?;
+            Some(serde_json::from_str(&text).map_err(|e| format!("{} is not a bench report: {}", path, e))?)
+        }
+        None => None,
+    };
+
+    let temp;
+    let work = match opts.keep {
+        Some(dir) => Path::new(dir).to_path_buf(),
+        None => {
+            temp = tempfile::Builder::new().prefix("localdoc-bench-").tempdir()?;
+            temp.path().to_path_buf()
+        }
+    };
+
+    let mut runs = Vec::new();
+    match opts.source {
+        Some(source) => {
+            let name = Path::new(source).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            runs.push(time_tree(&name, Path::new(source), &work.join(&name))?);
+        }
+        None => {
+            for &files in opts.sizes {
+                let name = format!("synthetic-{}", files);
+                let root = work.join(&name).join("source");
+                info!("Generating {} files in {}...", files, root.display());
+                let started = Instant::now();
+                let stats = synthetic::generate(&root, &TreeSpec { files, seed: opts.seed }).with_path(&root)?;
+                info!("  {} in {:.2}s", format_size(stats.bytes), started.elapsed().as_secs_f64());
+                runs.push(time_tree(&name, &root, &work.join(&name))?);
+            }
+        }
+    }
+
+    let report =
+        Report { localdoc_version: env!("CARGO_PKG_VERSION").to_string(), os: std::env::consts::OS.to_string(), runs };
+    print_table(&report, baseline.as_ref());
+    if let Some(path) = opts.json_out {
+        fs::write(path, serde_json::to_string_pretty(&report)?).with_path(path)?;
+        info!("\nWrote {}", path);
+    }
+    if opts.keep.is_some() {
+        info!("Kept the trees and archives in {}", work.display());
+    }
+    Ok(())
+}
+
+/// Run ingest's phases on `root` one at a time, as `ingest --build-index`
+/// with default settings does, writing into `work`.
+fn time_tree(name: &str, root: &Path, work: &Path) -> Result<TreeRun, LocaldocError> {
+    info!("Timing {}...", name);
+    let staging = work.join("docpack");
+    let index_dir = staging.join("index");
+    fs::create_dir_all(&index_dir).with_path(&index_dir)?;
+    if !rss::reset_peak() {
+        info!("  Peak memory is not measured on this system");
+    }
+
+    let filter = SourceFilter::new(&[], &[], true)?;
+    let max_file_size = Some(ingest::DEFAULT_MAX_FILE_SIZE);
+    let (symlinks, non_utf8) = (SymlinkPolicy::default(), NonUtf8Policy::default());
+    let mut phases = Vec::new();
+
+    let plan: SourcePlan = timed(&mut phases, "scan", || {
+        ingest::plan_files(root, &filter, max_file_size, symlinks, non_utf8, &mut Progress::new("Scanning", None, true))
+    })?;
+
+    timed(&mut phases, "hash", || {
+        let to_hash: Vec<_> = plan.files.iter().map(|f| (f.name.clone(), f.path.clone())).collect();
+        let file_hashes = hashes::hash_files(&to_hash, &mut Progress::new("Hashing", None, true))?;
+        let hashes_path = index_dir.join(hashes::FILE_NAME);
+        fs::write(&hashes_path, serde_json::to_string_pretty(&hashes::to_json(&file_hashes))?).with_path(&hashes_path)?;
+        Ok(())
+    })?;
+
+    timed(&mut phases, "index", || {
+        let selection = ingest::selection_options(&filter, max_file_size, symlinks, non_utf8);
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        let mut progress = Progress::new("Indexing", None, true);
+        ingest::build_search_index(&plan.files, &index_dir, selection, IndexFormat::default(), false, &timestamp, &mut progress)
+    })?;
+
+    timed(&mut phases, "archive", || {
+        let entries = builder::archive_paths(&staging, &plan, None)?;
+        let options = ArchiveOptions { limits: Limits::unlimited(), ..ArchiveOptions::default() };
+        builder::create_zip_archive(entries, &work.join("bench.docpack"), &options, &mut Progress::new("Compressing", None, true))
+    })?;
+
+    Ok(TreeRun {
+        tree: name.to_string(),
+        files: plan.files.len(),
+        bytes: plan.files.iter().map(|file| file.size).sum(),
+        phases,
+    })
+}
+
+/// Run `phase`, recording its wall time and peak memory in `phases`.
+fn timed<T>(
+    phases: &mut Vec<Phase>,
+    name: &str,
+    phase: impl FnOnce() -> Result<T, LocaldocError>,
+) -> Result<T, LocaldocError> {
+    rss::reset_peak();
+    let started = Instant::now();
+    let value = phase()?;
+    let seconds = started.elapsed().as_secs_f64();
+    let peak_rss = rss::peak();
+    info!("  {:<8} {:>8.3}s", name, seconds);
+    phases.push(Phase { name: name.to_string(), seconds, peak_rss });
+    Ok(value)
+}
+
+/// One row per tree and phase, with the change from `baseline` where it
+/// timed the same tree.
+fn print_table(report: &Report, baseline: Option<&Report>) {
+    info!("");
+    info!(
+        "{:<18} {:>8} {:>10}  {:<8} {:>9} {:>10} {:>8}",
+        "tree", "files", "size", "phase", "time", "peak RSS", "vs base"
+    );
+    info!("{}", "─".repeat(78));
+    for run in &report.runs {
+        let before = baseline.and_then(|baseline| baseline.runs.iter().find(|old| old.tree == run.tree));
+        for (i, phase) in run.phases.iter().enumerate() {
+            let (files, size) =
+                if i == 0 { (run.files.to_string(), format_size(run.bytes)) } else { (String::new(), String::new()) };
+            let rss = phase.peak_rss.map(format_size).unwrap_or_else(|| "-".to_string());
+            let change = before
+                .and_then(|old| old.phases.iter().find(|old| old.name == phase.name))
+                .filter(|old| old.seconds > 0.0)
+                .map(|old| format!("{:+.0}%", (phase.seconds / old.seconds - 1.0) * 100.0))
+                .unwrap_or_default();
+            let tree = if i == 0 { run.tree.as_str() } else { "" };
+            info!(
+                "{:<18} {:>8} {:>10}  {:<8} {:>8.3}s {:>10} {:>8}",
+                tree, files, size, phase.name, phase.seconds, rss, change
+            );
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::BTreeSet;
+use std::io::{IsTerminal, Read};
+use std::path::Path;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use serde_json::Value;
+
+use crate::error::LocaldocError;
+use crate::reader::{self, DocpackReader};
+use crate::size::format_size;
+
+/// Bytes of a file read for its preview; the rest is not shown.
+const MAX_PREVIEW_BYTES: u64 = 256 * 1024;
+
+/// Bytes of a binary file shown as hex.
+const HEX_PREVIEW_BYTES: usize = 256;
+
+/// Lines scrolled by PageUp/PageDown.
+const PAGE_LINES: u16 = 20;
+
+const TAB_TITLES: [&str; 4] = ["Files", "Manifest", "Tasks", "Output"];
+
+/// One row of a file tree: a file, or a directory made up from the paths
+/// below it.
+struct Entry {
+    /// Path relative to the tree's root directory
+    path: String,
+    depth: usize,
+    dir: bool,
+    size: u64,
+}
+
+/// A collapsible tree over `files/` or `output/` with a preview of the
+/// selected file.
+struct Tree {
+    root: &'static str,
+    entries: Vec<Entry>,
+    /// Directories shown open
+    expanded: BTreeSet<String>,
+    /// Indexes into `entries` of the rows currently listed
+    visible: Vec<usize>,
+    /// Whether `visible` is filtered rather than the expanded tree
+    filtered: bool,
+    state: ListState,
+    preview: Text<'static>,
+    preview_scroll: u16,
+}
+
+impl Tree {
+    fn new(root: &'static str, mut files: Vec<(String, u64)>) -> Self {
+        // Component-wise order keeps each directory's contents together
+        files.sort_by(|a, b| a.0.split('/').cmp(b.0.split('/')));
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut dirs: BTreeSet<String> = BTreeSet::new();
+        for (path, size) in files {
+            let parts: Vec<&str> = path.split('/').collect();
+            for depth in 1..parts.len() {
+                let dir = parts[..depth].join("/");
+                if dirs.insert(dir.clone()) {
+                    entries.push(Entry { path: dir, depth: depth - 1, dir: true, size: 0 });
+                }
+            }
+            let depth = parts.len() - 1;
+            entries.push(Entry { path, depth, dir: false, size });
+        }
+        // Directory sizes are the total of the files below them
+        for i in 0..entries.len() {
+            if !entries[i].dir {
+                let (path, size) = (entries[i].path.clone(), entries[i].size);
+                for entry in entries.iter_mut().filter(|entry| entry.dir && path.starts_with(&format!("{}/", entry.path))) {
+                    entry.size += size;
+                }
+            }
+        }
+
+        let mut tree = Tree {
+            root,
+            entries,
+            expanded: BTreeSet::new(),
+            visible: Vec::new(),
+            filtered: false,
+            state: ListState::default(),
+            preview: Text::default(),
+            preview_scroll: 0,
+        };
+        tree.refresh("");
+        tree
+    }
+
+    /// Recompute the listed rows: the whole tree as far as it is expanded,
+    /// or every file whose path contains `filter`.
+    fn refresh(&mut self, filter: &str) {
+        let selected = self.selected().map(|entry| entry.path.clone());
+        let filter = filter.to_lowercase();
+        self.filtered = !filter.is_empty();
+        self.visible = (0..self.entries.len())
+            .filter(|&i| {
+                let entry = &self.entries[i];
+                if !filter.is_empty() {
+                    return !entry.dir && entry.path.to_lowercase().contains(&filter);
+                }
+                let mut parent = entry.path.as_str();
+                while let Some((dir, _)) = parent.rsplit_once('/') {
+                    if !self.expanded.contains(dir) {
+                        return false;
+                    }
+                    parent = dir;
+                }
+                true
+            })
+            .collect();
+        // Keep the same row selected if it is still listed
+        let position = selected.and_then(|path| self.visible.iter().position(|&i| self.entries[i].path == path));
+        self.state.select(position.or((!self.visible.is_empty()).then_some(0)));
+    }
+
+    fn selected(&self) -> Option<&Entry> {
+        self.state.selected().and_then(|row| self.visible.get(row)).map(|&i| &self.entries[i])
+    }
+
+    fn move_by(&mut self, rows: isize) {
+        if self.visible.is_empty() {
+            return;
+        }
+        let current = self.state.selected().unwrap_or(0) as isize;
+        self.state.select(Some((current + rows).clamp(0, self.visible.len() as isize - 1) as usize));
+    }
+
+    /// Open or close the selected directory; `None` toggles it.
+    fn set_expanded(&mut self, open: Option<bool>, filter: &str) {
+        let Some(entry) = self.selected() else { return };
+        if !entry.dir {
+            return;
+        }
+        let path = entry.path.clone();
+        let open = open.unwrap_or(!self.expanded.contains(&path));
+        if open {
+            self.expanded.insert(path);
+        } else {
+            self.expanded.remove(&path);
+        }
+        self.refresh(filter);
+    }
+
+    /// Select the directory containing the selected row.
+    fn select_parent(&mut self) {
+        let Some(entry) = self.selected() else { return };
+        let Some((parent, _)) = entry.path.rsplit_once('/') else { return };
+        let parent = parent.to_string();
+        if let Some(row) = self.visible.iter().position(|&i| self.entries[i].path == parent) {
+            self.state.select(Some(row));
+        }
+    }
+
+    /// Load the preview of the selected row, reading only that file.
+    fn load_preview(&mut self, reader: &mut dyn DocpackReader) {
+        self.preview_scroll = 0;
+        self.preview = match self.selected() {
+            None => Text::raw("(empty)"),
+            Some(entry) if entry.dir => {
+                let files = self.entries.iter().filter(|e| !e.dir && e.path.starts_with(&format!("{}/", entry.path))).count();
+                Text::raw(format!("{}/\n\n{} files, {}", entry.path, files, format_size(entry.size)))
+            }
+            Some(entry) => {
+                let name = match self.root {
+                    "files" => reader.source_name(&entry.path),
+                    root => Some(format!("{}/{}", root, entry.path)),
+                };
+                let mut bytes = Vec::new();
+                let read = name
+                    .and_then(|name| reader.open_file(&name).ok())
+                    .map(|entry| entry.take(MAX_PREVIEW_BYTES).read_to_end(&mut bytes));
+                match read {
+                    Some(Ok(_)) => preview(&bytes, entry.size),
+                    Some(Err(e)) => Text::raw(format!("Cannot read {}: {}", entry.path, e)),
+                    None => Text::raw(format!("Cannot find {}", entry.path)),
+                }
+            }
+        };
+    }
+}
+
+/// Text files as they are; anything else as a hex dump of its first bytes.
+fn preview(bytes: &[u8], size: u64) -> Text<'static> {
+    let cut = size > bytes.len() as u64;
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        // A cut preview may end inside a character
+        Err(e) if cut && e.error_len().is_none() => std::str::from_utf8(&bytes[..e.valid_up_to()]).ok(),
+        Err(_) => None,
+    };
+    match text.filter(|text| !text.contains('\0')) {
+        Some(text) => {
+            let mut lines: Vec<Line<'static>> = text.lines().map(|line| Line::raw(line.replace('\t', "    "))).collect();
+            if cut {
+                lines.push(Line::raw(format!("… ({} of {} shown)", format_size(bytes.len() as u64), format_size(size))).italic());
+            }
+            Text::from(lines)
+        }
+        None => {
+            let mut lines = vec![Line::raw(format!("Binary file, {}", format_size(size))).bold(), Line::raw("")];
+            for (row, chunk) in bytes[..bytes.len().min(HEX_PREVIEW_BYTES)].chunks(16).enumerate() {
+                let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+                let ascii: String = chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+                lines.push(Line::raw(format!("{:08x}  {:<47}  |{}|", row * 16, hex.join(" "), ascii)));
+            }
+            Text::from(lines)
+        }
+    }
+}
+
+struct App {
+    reader: Box<dyn DocpackReader>,
+    title: String,
+    tab: usize,
+    files: Tree,
+    outputs: Tree,
+    manifest: Text<'static>,
+    tasks: Text<'static>,
+    /// Scroll offset of the Manifest and Tasks tabs
+    scroll: u16,
+    filter: String,
+    /// Whether keys go to the filter
+    typing: bool,
+}
+
+impl App {
+    fn tree(&mut self) -> Option<&mut Tree> {
+        match self.tab {
+            0 => Some(&mut self.files),
+            3 => Some(&mut self.outputs),
+            _ => None,
+        }
+    }
+
+    /// Apply one key press; false to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.typing {
+            match key.code {
+                KeyCode::Enter => self.typing = false,
+                KeyCode::Esc => {
+                    self.typing = false;
+                    self.set_filter(String::new());
+                }
+                KeyCode::Backspace => {
+                    let mut filter = self.filter.clone();
+                    filter.pop();
+                    self.set_filter(filter);
+                }
+                KeyCode::Char(c) => {
+                    let filter = format!("{}{}", self.filter, c);
+                    self.set_filter(filter);
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        let filter = self.filter.clone();
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Tab => self.switch_tab((self.tab + 1) % TAB_TITLES.len()),
+            KeyCode::BackTab => self.switch_tab((self.tab + TAB_TITLES.len() - 1) % TAB_TITLES.len()),
+            KeyCode::Char(c @ '1'..='4') => self.switch_tab(c as usize - '1' as usize),
+            KeyCode::Char('/') if self.tree().is_some() => self.typing = true,
+            KeyCode::Esc if !filter.is_empty() => self.set_filter(String::new()),
+            code => {
+                let reader = self.reader.as_mut();
+                match (self.tab, code) {
+                    (0 | 3, code) => {
+                        let tree = if self.tab == 0 { &mut self.files } else { &mut self.outputs };
+                        let before = tree.selected().map(|entry| entry.path.clone());
+                        match code {
+                            KeyCode::Up | KeyCode::Char('k') => tree.move_by(-1),
+                            KeyCode::Down | KeyCode::Char('j') => tree.move_by(1),
+                            KeyCode::Home => tree.move_by(isize::MIN / 2),
+                            KeyCode::End => tree.move_by(isize::MAX / 2),
+                            KeyCode::Right | KeyCode::Char('l') => tree.set_expanded(Some(true), &filter),
+                            KeyCode::Enter => tree.set_expanded(None, &filter),
+                            KeyCode::Left | KeyCode::Char('h') => match tree.selected() {
+                                Some(entry) if entry.dir && tree.expanded.contains(&entry.path) => {
+                                    tree.set_expanded(Some(false), &filter)
+                                }
+                                _ => tree.select_parent(),
+                            },
+                            KeyCode::PageDown => tree.preview_scroll = tree.preview_scroll.saturating_add(PAGE_LINES),
+                            KeyCode::PageUp => tree.preview_scroll = tree.preview_scroll.saturating_sub(PAGE_LINES),
+                            _ => {}
+                        }
+                        if tree.selected().map(|entry| entry.path.clone()) != before {
+                            tree.load_preview(reader);
+                        }
+                    }
+                    (_, KeyCode::Up | KeyCode::Char('k')) => self.scroll = self.scroll.saturating_sub(1),
+                    (_, KeyCode::Down | KeyCode::Char('j')) => self.scroll = self.scroll.saturating_add(1),
+                    (_, KeyCode::PageUp) => self.scroll = self.scroll.saturating_sub(PAGE_LINES),
+                    (_, KeyCode::PageDown) => self.scroll = self.scroll.saturating_add(PAGE_LINES),
+                    (_, KeyCode::Home) => self.scroll = 0,
+                    _ => {}
+                }
+            }
+        }
+        true
+    }
+
+    fn switch_tab(&mut self, tab: usize) {
+        // The filter belongs to the tree it was typed in
+        if !self.filter.is_empty() {
+            self.set_filter(String::new());
+        }
+        self.tab = tab;
+        self.scroll = 0;
+    }
+
+    fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        let filter = self.filter.clone();
+        let reader = self.reader.as_mut();
+        let tree = if self.tab == 3 { &mut self.outputs } else { &mut self.files };
+        tree.refresh(&filter);
+        tree.load_preview(reader);
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [tabs_area, body, status] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+        let tabs = Tabs::new(TAB_TITLES.iter().enumerate().map(|(i, title)| format!("{} {}", i + 1, title)))
+            .select(self.tab)
+            .highlight_style(Style::new().bold().reversed());
+        let [tabs_left, title_area] = Layout::horizontal([Constraint::Min(0), Constraint::Length(self.title.len() as u16 + 1)]).areas(tabs_area);
+        frame.render_widget(tabs, tabs_left);
+        frame.render_widget(Line::raw(self.title.clone()).dim(), title_area);
+
+        match self.tab {
+            0 => draw_tree(frame, body, &mut self.files, "files/"),
+            3 => draw_tree(frame, body, &mut self.outputs, "output/"),
+            tab => {
+                let (title, text) = if tab == 1 { ("docpack.json", &self.manifest) } else { ("tasks.json", &self.tasks) };
+                let paragraph = Paragraph::new(text.clone()).block(Block::bordered().title(title)).scroll((self.scroll, 0));
+                frame.render_widget(paragraph, body);
+            }
+        }
+
+        let help = if self.typing {
+            Line::raw(format!("/{}▌  (Enter to keep, Esc to clear)", self.filter))
+        } else if !self.filter.is_empty() {
+            Line::raw(format!("filter: {}  (Esc to clear)  ↑↓ move  PgUp/PgDn scroll preview  Tab switch  q quit", self.filter))
+        } else {
+            Line::raw("↑↓ move  ←→ collapse/expand  / filter  PgUp/PgDn scroll  Tab switch  q quit").dim()
+        };
+        frame.render_widget(help, status);
+    }
+}
+
+fn draw_tree(frame: &mut Frame, area: Rect, tree: &mut Tree, title: &str) {
+    let [left, right] = Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)]).areas(area);
+    let items: Vec<ListItem> = tree
+        .visible
+        .iter()
+        .map(|&i| {
+            let entry = &tree.entries[i];
+            let name = entry.path.rsplit('/').next().unwrap_or(&entry.path);
+            let text = if tree.filtered {
+                // Filtered rows are listed flat, by their whole path
+                entry.path.clone()
+            } else if entry.dir {
+                let marker = if tree.expanded.contains(&entry.path) { "▾" } else { "▸" };
+                format!("{}{} {}/", "  ".repeat(entry.depth), marker, name)
+            } else {
+                format!("{}  {}", "  ".repeat(entry.depth), name)
+            };
+            ListItem::new(text)
+        })
+        .collect();
+    let count = tree.entries.iter().filter(|entry| !entry.dir).count();
+    let list = List::new(items)
+        .block(Block::bordered().title(format!("{} ({} files)", title, count)))
+        .highlight_style(Style::new().reversed());
+    frame.render_stateful_widget(list, left, &mut tree.state);
+
+    let preview_title = tree.selected().map(|entry| entry.path.clone()).unwrap_or_default();
+    let preview = Paragraph::new(tree.preview.clone())
+        .block(Block::bordered().title(preview_title))
+        .scroll((tree.preview_scroll, 0));
+    frame.render_widget(preview, right);
+}
+
+/// Pretty-printed JSON of `name`, or why there is none.
+fn json_text(reader: &mut dyn DocpackReader, name: &str) -> Text<'static> {
+    match reader.read(name).map(|bytes| serde_json::from_slice::<Value>(&bytes)) {
+        Some(Ok(value)) => Text::raw(serde_json::to_string_pretty(&value).unwrap_or_default()),
+        Some(Err(e)) => Text::raw(format!("Invalid JSON in {}: {}", name, e)),
+        None => Text::raw(format!("No {}", name)),
+    }
+}
+
+/// tasks.json as a readable list.
+fn tasks_text(reader: &mut dyn DocpackReader) -> Text<'static> {
+    let Some(Ok(tasks)) = reader.read("tasks.json").map(|bytes| serde_json::from_slice::<Value>(&bytes)) else {
+        return json_text(reader, "tasks.json");
+    };
+    let mut lines = vec![
+        Line::raw(format!("Mission: {}", tasks["mission"].as_str().unwrap_or("(none)"))).bold(),
+        Line::raw(""),
+    ];
+    for (i, task) in tasks["tasks"].as_array().map(Vec::as_slice).unwrap_or_default().iter().enumerate() {
+        let id = task["id"].as_str().map(|id| format!(" [{}]", id)).unwrap_or_default();
+        lines.push(Line::raw(format!("{}. {}{}", i + 1, task["name"].as_str().unwrap_or("unnamed"), id)).bold());
+        if let Some(description) = task["description"].as_str() {
+            lines.extend(description.lines().map(|line| Line::raw(format!("   {}", line))));
+        }
+        if let Some(tools) = task["tools_allowed"].as_array() {
+            let tools: Vec<&str> = tools.iter().filter_map(Value::as_str).collect();
+            lines.push(Line::raw(format!("   tools: {}", tools.join(", "))).dim());
+        }
+        lines.push(Line::raw(""));
+    }
+    Text::from(lines)
+}
+
+pub fn run(docpack: &str) -> Result<(), LocaldocError> {
+    if !std::io::stdout().is_terminal() {
+        return Err("browse needs an interactive terminal; use `localdoc inspect --verbose` for plain output".into());
+    }
+    let mut reader = reader::open(Path::new(docpack))?;
+
+    let files = Tree::new("files", reader.list_files("files"));
+    let outputs = Tree::new("output", reader.list_files("output"));
+    let manifest = json_text(reader.as_mut(), "docpack.json");
+    let tasks = tasks_text(reader.as_mut());
+    let mut app = App {
+        reader,
+        title: docpack.to_string(),
+        tab: 0,
+        files,
+        outputs,
+        manifest,
+        tasks,
+        scroll: 0,
+        filter: String::new(),
+        typing: false,
+    };
+    app.files.load_preview(app.reader.as_mut());
+    app.outputs.load_preview(app.reader.as_mut());
+
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> Result<(), LocaldocError> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !app.handle_key(key) {
+                return Ok(());
+            }
+        }
+    }
+}
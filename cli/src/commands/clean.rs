@@ -0,0 +1,258 @@
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use localdoc::docpack::{Docpack, DocpackKind};
+use log::{error, info};
+
+use crate::context::REASONING_DIR;
+use crate::error::{LocaldocError, WithPath};
+use crate::run_state;
+use crate::scratch;
+use crate::seal;
+use crate::size::format_size;
+
+/// Settings for `localdoc clean <docpack>`, one field per command-line flag.
+pub struct CleanOptions {
+    /// Don't ask before deleting
+    pub yes: bool,
+    pub dry_run: bool,
+    pub force_mutable: bool,
+}
+
+/// Something `clean` would delete, with the files and bytes it holds.
+struct Target {
+    path: PathBuf,
+    files: u64,
+    bytes: u64,
+}
+
+/// Empty a docpack directory's `output/` and remove its `.reasoning/`,
+/// after asking. Nothing else in the docpack is touched, and neither is
+/// anything a symlink in those places points to.
+pub fn outputs(docpack: &str, opts: &CleanOptions) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+    if Docpack::open_any(docpack_path)?.kind != DocpackKind::Directory {
+        return Err(format!(
+            "{} is an archive; runs on an archive write their outputs beside it, so there is nothing in it to clean",
+            docpack
+        )
+        .into());
+    }
+    seal::ensure_mutable(docpack_path, opts.force_mutable)?;
+    if run_state::load(docpack_path)?.is_some() && run_state::live(docpack_path)?.is_some() {
+        return Err(format!("A detached run is writing to {}; finish it with `localdoc stop {}` first", docpack, docpack).into());
+    }
+
+    let mut targets = Vec::new();
+    let output = docpack_path.join("output");
+    if is_real_dir(&output) {
+        for entry in fs::read_dir(&output).with_path(&output)? {
+            targets.push(measure(entry?.path()));
+        }
+    }
+    let reasoning = docpack_path.join(REASONING_DIR);
+    if fs::symlink_metadata(&reasoning).is_ok() {
+        targets.push(measure(reasoning));
+    }
+    targets.sort_by(|a, b| a.path.cmp(&b.path));
+    if targets.is_empty() {
+        info!("Nothing to clean in {}", docpack);
+        return Ok(());
+    }
+
+    let (files, bytes) = totals(&targets);
+    info!("{} {}:", if opts.dry_run { "Would remove from" } else { "Removing from" }, docpack);
+    for target in &targets {
+        let name = target.path.strip_prefix(docpack_path).unwrap_or(&target.path);
+        info!("  {:<40}  {:>10}", name.display(), format_size(target.bytes));
+    }
+    if opts.dry_run {
+        info!("\nDry run: {} file(s), {} would be deleted", files, format_size(bytes));
+        return Ok(());
+    }
+    if !opts.yes && !confirm(&format!("Delete {} file(s), {}?", files, format_size(bytes)))? {
+        info!("Nothing was deleted");
+        return Ok(());
+    }
+
+    let failed = remove_all(&targets);
+    if failed > 0 {
+        return Err(format!("{} of {} item(s) could not be removed", failed, targets.len()).into());
+    }
+    info!("✓ Removed {} file(s), freed {}", files, format_size(bytes));
+    Ok(())
+}
+
+/// Settings for `localdoc clean --temp`, one field per command-line flag.
+pub struct TempOptions {
+    /// Only directories nothing in has changed for this long
+    pub older_than: Duration,
+    pub dry_run: bool,
+}
+
+/// Remove the scratch directories crashed commands left in the temp
+/// directory. Only its direct children named like localdoc's scratch
+/// directories (see [`scratch::PREFIXES`]) are considered, and only those
+/// in which nothing has changed for `older_than`, so running commands
+/// keep theirs.
+pub fn temp(opts: &TempOptions) -> Result<(), LocaldocError> {
+    let temp_dir = std::env::temp_dir();
+    let now = SystemTime::now();
+    let mut targets = Vec::new();
+    for entry in fs::read_dir(&temp_dir).with_path(&temp_dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if !name.to_str().is_some_and(is_scratch_name) || !is_real_dir(&entry.path()) {
+            continue;
+        }
+        let age = newest_change(&entry.path()).and_then(|changed| now.duration_since(changed).ok()).unwrap_or_default();
+        if age >= opts.older_than {
+            targets.push((measure(entry.path()), age));
+        }
+    }
+    targets.sort_by(|(a, _), (b, _)| a.path.cmp(&b.path));
+    if targets.is_empty() {
+        info!("No leftover scratch directories older than {} in {}", format_age(opts.older_than), temp_dir.display());
+        return Ok(());
+    }
+
+    for (target, age) in &targets {
+        info!("  {}  {:>10}  {} old", target.path.display(), format_size(target.bytes), format_age(*age));
+    }
+    let targets: Vec<Target> = targets.into_iter().map(|(target, _)| target).collect();
+    let (_, bytes) = totals(&targets);
+    if opts.dry_run {
+        info!("\nDry run: {} director(ies) would be removed, reclaiming {}", targets.len(), format_size(bytes));
+        return Ok(());
+    }
+
+    let failed = remove_all(&targets);
+    let reclaimed: u64 = targets.iter().filter(|target| !target.path.exists()).map(|target| target.bytes).sum();
+    info!("✓ Removed {} director(ies), reclaimed {}", targets.len() - failed, format_size(reclaimed));
+    if failed > 0 {
+        return Err(format!("{} director(ies) could not be removed", failed).into());
+    }
+    Ok(())
+}
+
+/// A name `tempfile` gave a scratch directory: a known prefix and the
+/// random characters after it.
+fn is_scratch_name(name: &str) -> bool {
+    scratch::PREFIXES.iter().any(|prefix| {
+        name.strip_prefix(prefix).is_some_and(|random| !random.is_empty() && random.chars().all(|c| c.is_ascii_alphanumeric()))
+    })
+}
+
+/// A directory itself, not a symlink to one.
+fn is_real_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir())
+}
+
+/// Count what `path` holds without following symlinks.
+fn measure(path: PathBuf) -> Target {
+    let (mut files, mut bytes) = (0, 0);
+    for entry in walkdir::WalkDir::new(&path).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_dir() {
+            files += 1;
+            bytes += entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        }
+    }
+    Target { path, files, bytes }
+}
+
+fn totals(targets: &[Target]) -> (u64, u64) {
+    (targets.iter().map(|target| target.files).sum(), targets.iter().map(|target| target.bytes).sum())
+}
+
+/// When anything in `dir` last changed.
+fn newest_change(dir: &Path) -> Option<SystemTime> {
+    walkdir::WalkDir::new(dir).into_iter().filter_map(|e| e.ok()).filter_map(|e| e.metadata().ok()?.modified().ok()).max()
+}
+
+/// Remove each target, without following symlinks, and return how many
+/// could not be.
+fn remove_all(targets: &[Target]) -> usize {
+    let mut failed = 0;
+    for target in targets {
+        let result = match fs::symlink_metadata(&target.path) {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(&target.path),
+            Ok(_) => fs::remove_file(&target.path),
+            Err(e) => Err(e),
+        };
+        match result {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                error!("{}", scratch::removal_failed(&target.path, &e));
+                failed += 1;
+            }
+            _ => {}
+        }
+    }
+    failed
+}
+
+/// Ask a yes/no question on the terminal; no answer is no.
+fn confirm(question: &str) -> Result<bool, LocaldocError> {
+    if !io::stdin().is_terminal() {
+        return Err("Not deleting without confirmation; pass --yes to delete, or --dry-run to only list what would go".into());
+    }
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Parse an age such as `90s`, `30m`, `12h`, `1d` or `2w`.
+pub fn parse_age(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().map_err(|_| format!("invalid age '{}' (expected e.g. 12h or 1d)", text))?;
+    let seconds = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        other => return Err(format!("unknown age unit '{}' (use s, m, h, d or w)", other)),
+    };
+    Ok(Duration::from_secs(number * seconds))
+}
+
+/// The largest whole unit of an age, e.g. `3d`.
+fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_scratch_directory_names_are_recognized() {
+        assert!(is_scratch_name("docpack-run-a1B2c3"));
+        assert!(is_scratch_name("docpack-build-XyZ987"));
+        assert!(!is_scratch_name("docpack-run-"));
+        assert!(!is_scratch_name("docpack-run-../x"));
+        assert!(!is_scratch_name("docpack-runner"));
+        assert!(!is_scratch_name("my-docpack-run-abc123"));
+    }
+
+    #[test]
+    fn ages_parse_and_print_in_whole_units() {
+        assert_eq!(parse_age("1d"), Ok(Duration::from_secs(86400)));
+        assert_eq!(parse_age("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_age("2w"), Ok(Duration::from_secs(14 * 86400)));
+        assert!(parse_age("1y").is_err());
+        assert_eq!(format_age(Duration::from_secs(7200)), "2h");
+        assert_eq!(format_age(Duration::from_secs(3 * 86400 + 5)), "3d");
+    }
+}
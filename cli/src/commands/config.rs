@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::Path;
+use log::info;
+use toml_edit::DocumentMut;
+
+use crate::config::{self, KEYS};
+use crate::error::LocaldocError;
+
+/// Print every key with the value that applies and the file it comes from.
+pub fn list() -> Result<(), LocaldocError> {
+    let config = config::load()?;
+    let width = KEYS.iter().map(|key| key.name.len()).max().unwrap_or(0);
+    for key in KEYS {
+        let (value, from) = match config.get(key.name) {
+            Some(_) if key.is_secret() => ("(hidden)".to_string(), None),
+            Some(setting) => (key.display(&setting.value), Some(setting.file.display().to_string())),
+            None => (key.default.unwrap_or("(not set)").to_string(), None),
+        };
+        match from {
+            Some(file) => println!("{:<width$}  {}  # {}", key.name, value, file),
+            None => println!("{:<width$}  {}", key.name, value),
+        }
+    }
+    for (label, path) in [("User config", config::user_path()), ("Project config", config::project_path())] {
+        if let Some(path) = path.filter(|path| path.is_file()) {
+            println!("\n{}: {}", label, path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Print the value that applies for `name`.
+pub fn get(name: &str) -> Result<(), LocaldocError> {
+    let key = config::key(name).map_err(LocaldocError::NotFound)?;
+    let config = config::load()?;
+    match (config.get(key.name), key.default) {
+        (Some(setting), _) => println!("{}", key.display(&setting.value)),
+        (None, Some(default)) => println!("{}", default),
+        (None, None) => return Err(LocaldocError::NotFound(format!("{} is not set", key.name))),
+    }
+    Ok(())
+}
+
+/// Set `name` in the user's config file, or with `project` in the nearest
+/// `.localdoc.toml` (creating one in the current directory if there is
+/// none). Other keys, comments and formatting in the file are kept.
+pub fn set(name: &str, value: &str, project: bool) -> Result<(), LocaldocError> {
+    let key = config::key(name).map_err(LocaldocError::NotFound)?;
+    let value = key.parse(value)?;
+    let path = if project {
+        match config::project_path() {
+            Some(path) => path,
+            None => std::env::current_dir()?.join(config::PROJECT_FILE),
+        }
+    } else {
+        config::user_path().ok_or("Cannot find the user config directory: neither XDG_CONFIG_HOME nor HOME is set")?
+    };
+
+    let mut doc = if path.is_file() { config::read(&path)? } else { DocumentMut::new() };
+    let (section, field) = key.name.split_once('.').expect("keys are section.field");
+    let old = doc.get(section).and_then(|table| table.get(field)).and_then(|item| item.as_value()).map(|old| key.display(old));
+    let shown = if key.is_secret() { "(hidden)".to_string() } else { key.display(&value) };
+    config::set(&mut doc, key, value);
+    write(&path, &doc)?;
+
+    match old {
+        Some(old) if !key.is_secret() => info!("✓ Set {} = {} in {} (was {})", key.name, shown, path.display(), old),
+        _ => info!("✓ Set {} = {} in {}", key.name, shown, path.display()),
+    }
+    Ok(())
+}
+
+fn write(path: &Path, doc: &DocumentMut) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, doc.to_string())
+}
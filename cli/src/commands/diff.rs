@@ -0,0 +1,326 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use docpack_core::{Manifest, Task, TasksFile, TASKS_FILE};
+use log::info;
+use serde_json::{json, Value};
+
+use crate::error::LocaldocError;
+use crate::hashes::{self, FileHash};
+use crate::reader;
+use crate::size::format_size;
+
+/// Paths listed per change kind before the summary says how many more
+/// there are; `--verbose` lists all of them.
+const MAX_SUMMARY_PATHS: usize = 10;
+
+/// Manifest fields that differ between any two builds, so comparing them
+/// would only hide real changes.
+const VOLATILE_FIELDS: &[&str] = &["metadata.created"];
+
+/// Everything that differs between two docpacks.
+#[derive(Default)]
+struct Report {
+    added: Vec<(String, u64)>,
+    removed: Vec<(String, u64)>,
+    /// (path, old, new)
+    modified: Vec<(String, FileHash, FileHash)>,
+    unchanged: usize,
+    /// (dotted field path, old, new); `Value::Null` when absent
+    manifest: Vec<(String, Value, Value)>,
+    tools_added: Vec<String>,
+    tools_removed: Vec<String>,
+    mission: Option<(Value, Value)>,
+    tasks_added: Vec<String>,
+    tasks_removed: Vec<String>,
+    tasks_modified: Vec<String>,
+    index_added: Vec<String>,
+    index_removed: Vec<String>,
+}
+
+impl Report {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.manifest.is_empty()
+            && self.tools_added.is_empty()
+            && self.tools_removed.is_empty()
+            && self.mission.is_none()
+            && self.tasks_added.is_empty()
+            && self.tasks_removed.is_empty()
+            && self.tasks_modified.is_empty()
+            && self.index_added.is_empty()
+            && self.index_removed.is_empty()
+    }
+}
+
+/// The parts of one docpack that are compared.
+struct Side {
+    manifest: Manifest,
+    tasks: Option<TasksFile>,
+    files: BTreeMap<String, FileHash>,
+    index: BTreeSet<String>,
+}
+
+pub fn run(old: &str, new: &str, verbose: bool, json: bool) -> Result<(), LocaldocError> {
+    let old_side = read_side(old)?;
+    let new_side = read_side(new)?;
+    let report = compare(&old_side, &new_side);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report_json(old, new, &report))?);
+    } else {
+        print_report(old, new, &report, verbose);
+    }
+
+    if report.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} and {} differ", old, new).into())
+    }
+}
+
+/// Read and hash one docpack, a directory or an archive read in place.
+fn read_side(docpack: &str) -> Result<Side, LocaldocError> {
+    let mut reader = reader::open(Path::new(docpack))?;
+    let manifest = reader.read_manifest()?;
+    let tasks = match reader.exists(TASKS_FILE) {
+        true => Some(reader.read_tasks()?),
+        false => None,
+    };
+
+    let mut files = BTreeMap::new();
+    for (path, _) in reader.list_files("files") {
+        let name = reader.source_name(&path).ok_or_else(|| format!("{}: no blob for files/{}", docpack, path))?;
+        let entry = reader.open_file(&name).map_err(|e| format!("{}: cannot read {}: {}", docpack, name, e))?;
+        let hash = hashes::hash_reader(entry).map_err(|e| format!("{}: cannot read {}: {}", docpack, name, e))?;
+        files.insert(path, hash);
+    }
+    let index = reader.list_files("index").into_iter().map(|(name, _)| name).collect();
+
+    Ok(Side { manifest, tasks, files, index })
+}
+
+fn compare(old: &Side, new: &Side) -> Report {
+    let mut report = Report::default();
+
+    for (path, hash) in &new.files {
+        match old.files.get(path) {
+            None => report.added.push((path.clone(), hash.size)),
+            Some(before) if before != hash => report.modified.push((path.clone(), before.clone(), hash.clone())),
+            Some(_) => report.unchanged += 1,
+        }
+    }
+    for (path, hash) in &old.files {
+        if !new.files.contains_key(path) {
+            report.removed.push((path.clone(), hash.size));
+        }
+    }
+
+    // Tools are a list, so report them by name rather than as one field
+    let tools = |side: &Side| -> BTreeSet<String> { side.manifest.environment.tools.iter().cloned().collect() };
+    let (old_tools, new_tools) = (tools(old), tools(new));
+    report.tools_added = new_tools.difference(&old_tools).cloned().collect();
+    report.tools_removed = old_tools.difference(&new_tools).cloned().collect();
+
+    let mut old_fields = BTreeMap::new();
+    let mut new_fields = BTreeMap::new();
+    flatten("", &old.manifest.to_value(), &mut old_fields);
+    flatten("", &new.manifest.to_value(), &mut new_fields);
+    let names: BTreeSet<&String> = old_fields.keys().chain(new_fields.keys()).collect();
+    for name in names {
+        if name == "environment.tools" || VOLATILE_FIELDS.contains(&name.as_str()) {
+            continue;
+        }
+        let before = old_fields.get(name).cloned().unwrap_or(Value::Null);
+        let after = new_fields.get(name).cloned().unwrap_or(Value::Null);
+        if before != after {
+            report.manifest.push((name.clone(), before, after));
+        }
+    }
+
+    let mission = |side: &Side| json!(side.tasks.as_ref().and_then(|tasks| tasks.mission.as_ref()));
+    if mission(old) != mission(new) {
+        report.mission = Some((mission(old), mission(new)));
+    }
+    let (old_tasks, new_tasks) = (tasks_by_id(old.tasks.as_ref()), tasks_by_id(new.tasks.as_ref()));
+    for (id, task) in &new_tasks {
+        match old_tasks.get(id) {
+            None => report.tasks_added.push(id.clone()),
+            Some(before) if before != task => report.tasks_modified.push(id.clone()),
+            Some(_) => {}
+        }
+    }
+    report.tasks_removed = old_tasks.keys().filter(|id| !new_tasks.contains_key(*id)).cloned().collect();
+
+    report.index_added = new.index.difference(&old.index).cloned().collect();
+    report.index_removed = old.index.difference(&new.index).cloned().collect();
+
+    report
+}
+
+/// Leaf values of `value` by dotted path; arrays are compared whole.
+fn flatten(prefix: &str, value: &Value, fields: &mut BTreeMap<String, Value>) {
+    match value.as_object() {
+        Some(object) => {
+            for (key, child) in object {
+                let name = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(&name, child, fields);
+            }
+        }
+        None => {
+            fields.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// Tasks keyed by `id`, or by name (then position) when they have none.
+fn tasks_by_id(tasks: Option<&TasksFile>) -> BTreeMap<String, &Task> {
+    let list = tasks.map(TasksFile::task_list).unwrap_or_default();
+    list.iter()
+        .enumerate()
+        .map(|(i, task)| {
+            let id = task.id.clone().or_else(|| task.name.clone()).unwrap_or_else(|| format!("#{}", i));
+            (id, task)
+        })
+        .collect()
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => "(none)".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn print_report(old: &str, new: &str, report: &Report, verbose: bool) {
+    info!("Comparing {} → {}\n", old, new);
+    if report.is_empty() {
+        info!("✓ No differences");
+        return;
+    }
+
+    let limit = if verbose { usize::MAX } else { MAX_SUMMARY_PATHS };
+    let mut truncated = false;
+    let mut list = |lines: Vec<String>| {
+        for line in lines.iter().take(limit) {
+            info!("  {}", line);
+        }
+        if lines.len() > limit {
+            info!("  … and {} more", lines.len() - limit);
+            truncated = true;
+        }
+    };
+
+    info!("📁 Files");
+    info!("{}", "─".repeat(60));
+    info!(
+        "{} added, {} removed, {} modified, {} unchanged",
+        report.added.len(),
+        report.removed.len(),
+        report.modified.len(),
+        report.unchanged
+    );
+    list(report.added.iter().map(|(path, size)| format!("+ {} ({})", path, format_size(*size))).collect());
+    list(report.removed.iter().map(|(path, size)| format!("- {} ({})", path, format_size(*size))).collect());
+    list(
+        report
+            .modified
+            .iter()
+            .map(|(path, before, after)| {
+                format!("~ {} ({} → {})", path, format_size(before.size), format_size(after.size))
+            })
+            .collect(),
+    );
+    info!("");
+
+    if !report.manifest.is_empty() || !report.tools_added.is_empty() || !report.tools_removed.is_empty() {
+        info!("📦 Manifest");
+        info!("{}", "─".repeat(60));
+        list(report.tools_added.iter().map(|tool| format!("+ tool {}", tool)).collect());
+        list(report.tools_removed.iter().map(|tool| format!("- tool {}", tool)).collect());
+        list(
+            report
+                .manifest
+                .iter()
+                .map(|(name, before, after)| {
+                    if verbose {
+                        format!("~ {}: {} → {}", name, display(before), display(after))
+                    } else {
+                        format!("~ {}", name)
+                    }
+                })
+                .collect(),
+        );
+        info!("");
+    }
+
+    let tasks_changed = !report.tasks_added.is_empty() || !report.tasks_removed.is_empty() || !report.tasks_modified.is_empty();
+    if report.mission.is_some() || tasks_changed {
+        info!("🎯 Tasks");
+        info!("{}", "─".repeat(60));
+        if let Some((before, after)) = &report.mission {
+            info!("  ~ mission: {} → {}", display(before), display(after));
+        }
+        list(report.tasks_added.iter().map(|id| format!("+ {}", id)).collect());
+        list(report.tasks_removed.iter().map(|id| format!("- {}", id)).collect());
+        list(report.tasks_modified.iter().map(|id| format!("~ {}", id)).collect());
+        info!("");
+    }
+
+    if !report.index_added.is_empty() || !report.index_removed.is_empty() {
+        info!("🔍 Index");
+        info!("{}", "─".repeat(60));
+        list(report.index_added.iter().map(|name| format!("+ index/{}", name)).collect());
+        list(report.index_removed.iter().map(|name| format!("- index/{}", name)).collect());
+        info!("");
+    }
+
+    if truncated {
+        info!("Run with --verbose to list every change");
+    }
+}
+
+fn report_json(old: &str, new: &str, report: &Report) -> Value {
+    json!({
+        "old": old,
+        "new": new,
+        "identical": report.is_empty(),
+        "files": {
+            "added": report.added.iter().map(|(path, size)| json!({ "path": path, "size": size })).collect::<Vec<_>>(),
+            "removed": report.removed.iter().map(|(path, size)| json!({ "path": path, "size": size })).collect::<Vec<_>>(),
+            "modified": report
+                .modified
+                .iter()
+                .map(|(path, before, after)| json!({
+                    "path": path,
+                    "old_size": before.size,
+                    "new_size": after.size,
+                    "old_sha256": before.sha256,
+                    "new_sha256": after.sha256,
+                }))
+                .collect::<Vec<_>>(),
+            "unchanged": report.unchanged,
+        },
+        "manifest": {
+            "tools_added": report.tools_added,
+            "tools_removed": report.tools_removed,
+            "fields": report
+                .manifest
+                .iter()
+                .map(|(name, before, after)| json!({ "field": name, "old": before, "new": after }))
+                .collect::<Vec<_>>(),
+        },
+        "tasks": {
+            "mission": report.mission.as_ref().map(|(before, after)| json!({ "old": before, "new": after })),
+            "added": report.tasks_added,
+            "removed": report.tasks_removed,
+            "modified": report.tasks_modified,
+        },
+        "index": {
+            "added": report.index_added,
+            "removed": report.index_removed,
+        },
+    })
+}
@@ -0,0 +1,283 @@
+use std::path::Path;
+use log::info;
+use serde::Serialize;
+
+use crate::commands::validate;
+use crate::error::LocaldocError;
+use crate::history;
+use crate::runtime::{self, Runtime, RuntimeChoice};
+use crate::size::format_size;
+
+/// Free space in the temp directory below which archives may not extract.
+const MIN_FREE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Free space below which a large docpack may not fit.
+const LOW_FREE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Findings of one kind listed in a docpack check before they are counted.
+const MAX_LISTED_FINDINGS: usize = 3;
+
+/// How a check turned out, from best to worst.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn symbol(self) -> &'static str {
+        match self {
+            Status::Pass => "✓",
+            Status::Warn => "⚠️ ",
+            Status::Fail => "❌",
+        }
+    }
+
+    /// The exit code of `doctor` when this is the worst result.
+    fn exit_code(self) -> i32 {
+        match self {
+            Status::Pass => 0,
+            Status::Warn => 1,
+            Status::Fail => 2,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    status: Status,
+    message: String,
+    /// What to do about a warning or failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, message: impl Into<String>) -> Self {
+        Check { name, status, message: message.into(), hint: None }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+/// Settings for `localdoc doctor`, one field per command-line flag.
+pub struct DoctorOptions<'a> {
+    /// `None` defers to `$LOCALDOC_RUNTIME`, then probing, as `run` does
+    pub runtime: Option<RuntimeChoice>,
+    pub image: &'a str,
+    /// Also validate this docpack
+    pub docpack: Option<&'a str>,
+    pub json: bool,
+}
+
+/// Check what `run` depends on and print a pass, warning or failure for
+/// each. Exits with 1 if the worst result is a warning and 2 if anything
+/// failed.
+pub fn run(opts: &DoctorOptions) -> Result<(), LocaldocError> {
+    let mut checks = Vec::new();
+    if let Some(runtime) = check_runtime(opts.runtime, &mut checks) {
+        let daemon = check_daemon(runtime);
+        let reachable = daemon.status == Status::Pass;
+        checks.push(daemon);
+        if reachable {
+            checks.push(check_image(runtime, opts.image));
+        }
+    }
+    checks.push(check_disk_space(&std::env::temp_dir()));
+    checks.push(check_temp_writable());
+    checks.push(check_history_writable());
+    if let Some(docpack) = opts.docpack {
+        checks.push(check_docpack(docpack));
+    }
+
+    let worst = checks.iter().map(|check| check.status).max().unwrap_or(Status::Pass);
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "status": worst, "checks": checks }))?);
+    } else {
+        print_checks(&checks);
+    }
+    if worst != Status::Pass {
+        std::process::exit(worst.exit_code());
+    }
+    Ok(())
+}
+
+fn print_checks(checks: &[Check]) {
+    let width = checks.iter().map(|check| check.name.len()).max().unwrap_or(0);
+    info!("Checking the environment for localdoc run\n");
+    for check in checks {
+        info!("  {} {:<width$}  {}", check.status.symbol(), check.name, check.message);
+        if let Some(hint) = &check.hint {
+            for line in hint.lines() {
+                info!("     {:<width$}  → {}", "", line);
+            }
+        }
+    }
+    let warnings = checks.iter().filter(|check| check.status == Status::Warn).count();
+    let failures = checks.iter().filter(|check| check.status == Status::Fail).count();
+    match (warnings, failures) {
+        (0, 0) => info!("\n✓ All checks passed"),
+        _ => info!("\n{} warning(s), {} failure(s)", warnings, failures),
+    }
+}
+
+/// The runtime `run` would use, if it is installed.
+fn check_runtime(choice: Option<RuntimeChoice>, checks: &mut Vec<Check>) -> Option<Runtime> {
+    let runtime = match runtime::resolve(choice) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            let (message, hint) = e.split_once("\n").unwrap_or((&e, ""));
+            checks.push(Check::new("Container runtime", Status::Fail, message).hint(hint.trim()));
+            return None;
+        }
+    };
+    match runtime.version() {
+        Some(version) => {
+            let message = if version.is_empty() { format!("{} is installed", runtime.program()) } else { version };
+            checks.push(Check::new("Container runtime", Status::Pass, message));
+            Some(runtime)
+        }
+        None => {
+            checks.push(
+                Check::new("Container runtime", Status::Fail, format!("{} is not installed or does not run", runtime.program()))
+                    .hint(install_hint(runtime)),
+            );
+            None
+        }
+    }
+}
+
+fn install_hint(runtime: Runtime) -> &'static str {
+    match runtime {
+        Runtime::Docker => "Install Docker: https://docs.docker.com/get-docker/",
+        Runtime::Podman => "Install Podman: https://podman.io/docs/installation",
+    }
+}
+
+fn check_daemon(runtime: Runtime) -> Check {
+    let name = match runtime {
+        Runtime::Docker => "Docker daemon",
+        Runtime::Podman => "Podman service",
+    };
+    let Some(error) = runtime.daemon_error() else {
+        return Check::new(name, Status::Pass, "reachable");
+    };
+    let hint = match runtime {
+        Runtime::Docker if error.to_lowercase().contains("permission denied") => {
+            "Add yourself to the docker group: `sudo usermod -aG docker $USER`, then log in again"
+        }
+        Runtime::Docker => "Start Docker Desktop, or the daemon with `sudo systemctl start docker`",
+        Runtime::Podman => "On macOS and Windows, start the VM with `podman machine start`",
+    };
+    Check::new(name, Status::Fail, error).hint(hint)
+}
+
+fn check_image(runtime: Runtime, image: &str) -> Check {
+    if runtime.has_image(image) {
+        Check::new("Agent image", Status::Pass, format!("{} is present", image))
+    } else {
+        Check::new("Agent image", Status::Warn, format!("{} is not present; run will pull it first", image))
+            .hint(format!("Pull it now with `{} pull {}`", runtime.program(), image))
+    }
+}
+
+fn check_disk_space(dir: &Path) -> Check {
+    let Some(free) = available_space(dir) else {
+        return Check::new("Disk space", Status::Warn, format!("could not tell how much space is free in {}", dir.display()));
+    };
+    let message = format!("{} free in {}", format_size(free), dir.display());
+    let hint = "Free some space, or point TMPDIR at a larger disk; archives are extracted there";
+    if free < MIN_FREE_BYTES {
+        Check::new("Disk space", Status::Fail, message).hint(hint)
+    } else if free < LOW_FREE_BYTES {
+        Check::new("Disk space", Status::Warn, message).hint(hint)
+    } else {
+        Check::new("Disk space", Status::Pass, message)
+    }
+}
+
+#[cfg(unix)]
+fn available_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stats` is only read after
+    // statvfs reports that it filled it in
+    if unsafe { libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let stats = unsafe { stats.assume_init() };
+    #[allow(clippy::unnecessary_cast)]
+    Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn available_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+fn check_temp_writable() -> Check {
+    let dir = std::env::temp_dir();
+    match tempfile::tempfile_in(&dir) {
+        Ok(_) => Check::new("Temp directory", Status::Pass, format!("{} is writable", dir.display())),
+        Err(e) => Check::new("Temp directory", Status::Fail, format!("cannot write to {}: {}", dir.display(), e))
+            .hint("Set TMPDIR to a directory you can write to"),
+    }
+}
+
+fn check_history_writable() -> Check {
+    let Some(dir) = history::path().and_then(|path| path.parent().map(Path::to_path_buf)) else {
+        return Check::new("Run history", Status::Warn, "neither XDG_DATA_HOME nor HOME is set; runs will not be logged");
+    };
+    match std::fs::create_dir_all(&dir).and_then(|_| tempfile::tempfile_in(&dir)) {
+        Ok(_) => Check::new("Run history", Status::Pass, format!("{} is writable", dir.display())),
+        Err(e) => Check::new("Run history", Status::Warn, format!("cannot write to {}: {}; runs will not be logged", dir.display(), e))
+            .hint(format!("Check the ownership of {}", dir.display())),
+    }
+}
+
+fn check_docpack(docpack: &str) -> Check {
+    let (errors, warnings) = validate::preflight(Path::new(docpack), false);
+    let listed = |findings: &[crate::findings::Finding]| {
+        let mut lines: Vec<String> = findings.iter().take(MAX_LISTED_FINDINGS).map(ToString::to_string).collect();
+        if findings.len() > MAX_LISTED_FINDINGS {
+            lines.push(format!("and {} more", findings.len() - MAX_LISTED_FINDINGS));
+        }
+        lines.push(format!("See `localdoc validate {}`", docpack));
+        lines.join("\n")
+    };
+    if !errors.is_empty() {
+        Check::new("Docpack", Status::Fail, format!("{}: {} error(s)", docpack, errors.len())).hint(listed(&errors))
+    } else if !warnings.is_empty() {
+        Check::new("Docpack", Status::Warn, format!("{}: {} warning(s)", docpack, warnings.len())).hint(listed(&warnings))
+    } else {
+        Check::new("Docpack", Status::Pass, format!("{} is valid", docpack))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_worst_status_sets_the_exit_code() {
+        let statuses = [Status::Pass, Status::Fail, Status::Warn];
+        assert_eq!(statuses.iter().max(), Some(&Status::Fail));
+        assert_eq!(Status::Pass.exit_code(), 0);
+        assert_eq!(Status::Warn.exit_code(), 1);
+        assert_eq!(Status::Fail.exit_code(), 2);
+    }
+
+    #[test]
+    fn free_space_is_found_for_the_temp_directory() {
+        let check = check_disk_space(&std::env::temp_dir());
+        assert!(check.message.contains("free in") || cfg!(not(unix)), "{}", check.message);
+    }
+}
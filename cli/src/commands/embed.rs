@@ -0,0 +1,310 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use log::info;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use localdoc::docpack::{Docpack, DocpackKind};
+
+use crate::commands::update::Selection;
+use crate::commands::{ingest, pack};
+use crate::embeddings::{self, ApiSettings, Chunk, Client};
+use crate::error::{LocaldocError, WithPath};
+use crate::fingerprint;
+use crate::progress::Progress;
+use crate::scratch::ScratchDir;
+use crate::seal;
+use crate::size;
+
+/// Settings for `localdoc embed`, one field per command-line flag.
+pub struct EmbedOptions<'a> {
+    /// `None` falls back to the environment, then the OpenAI defaults
+    pub api_base: Option<&'a str>,
+    pub api_key: Option<&'a str>,
+    pub model: Option<&'a str>,
+    /// Chunk length in bytes
+    pub chunk_size: usize,
+    /// Bytes each chunk repeats from the end of the previous one
+    pub chunk_overlap: usize,
+    /// Chunks sent in one request
+    pub batch_size: usize,
+    /// 0 disables the limit
+    pub requests_per_minute: u32,
+    /// Overrides the filters recorded in the search index when non-empty
+    pub include: &'a [String],
+    pub exclude: &'a [String],
+    pub dry_run: bool,
+    /// Replace embeddings that already exist
+    pub force: bool,
+    pub quiet: bool,
+    pub force_mutable: bool,
+}
+
+pub fn run(docpack: &str, opts: &EmbedOptions) -> Result<(), LocaldocError> {
+    if opts.chunk_size == 0 || opts.batch_size == 0 {
+        return Err(LocaldocError::InvalidArgument("--chunk-size and --batch-size must be at least 1".to_string()));
+    }
+    if opts.chunk_overlap >= opts.chunk_size {
+        return Err(format!(
+            "--chunk-overlap ({}) must be smaller than --chunk-size ({})",
+            opts.chunk_overlap, opts.chunk_size
+        )
+        .into());
+    }
+
+    let docpack_path = Path::new(docpack);
+    let kind = Docpack::open_any(docpack_path)?.kind;
+    info!("Embedding .docpack: {}", docpack);
+
+    if !opts.dry_run {
+        seal::ensure_mutable(docpack_path, opts.force_mutable)?;
+    }
+
+    // Archives are embedded in an extracted copy and rewritten at the end
+    let scratch;
+    let work_dir = if kind == DocpackKind::Archive {
+        info!("Extracting .docpack archive...");
+        scratch = ScratchDir::new("docpack-embed-")?;
+        pack::extract_docpack(docpack_path, scratch.path(), false)?;
+        scratch.path().to_path_buf()
+    } else {
+        docpack_path.to_path_buf()
+    };
+
+    let index_dir = work_dir.join("index");
+    if !opts.dry_run && index_dir.join(embeddings::BIN_FILE).exists() && !opts.force {
+        return Err(format!("{} already has index/{}; pass --force to rebuild", docpack, embeddings::BIN_FILE).into());
+    }
+    let settings = ApiSettings::resolve(opts.api_base, opts.api_key, opts.model);
+
+    // Embed the files the search index covers, unless told otherwise
+    let selection = Selection::resolve(&index_dir, opts.include, opts.exclude, false, None)?;
+
+    info!("Scanning files/...");
+    let files_dir = work_dir.join("files");
+    let mut progress = Progress::new("Scanning", None, opts.quiet);
+    let plan = if files_dir.is_dir() {
+        ingest::plan_files(&files_dir, &selection.filter, selection.max_size, selection.symlinks, selection.non_utf8, &mut progress)?
+    } else {
+        ingest::SourcePlan::default()
+    };
+    progress.finish();
+
+    // Chunks refer to their file's text by position in `texts`
+    let mut texts = Vec::new();
+    let mut chunks: Vec<(usize, Chunk)> = Vec::new();
+    for file in &plan.files {
+        let Ok(text) = fs::read_to_string(&file.path) else {
+            continue;
+        };
+        for (start, end) in embeddings::chunk_ranges(&text, opts.chunk_size, opts.chunk_overlap) {
+            chunks.push((texts.len(), Chunk { path: file.name.clone(), start, end }));
+        }
+        texts.push(text);
+    }
+    let chunk_text = |i: usize| {
+        let (file, chunk) = &chunks[i];
+        &texts[*file][chunk.start..chunk.end]
+    };
+
+    let checkpoint = embeddings::checkpoint_path(docpack_path);
+    let key = checkpoint_key(&settings.model, chunks.len(), |i| (&chunks[i].1, chunk_text(i)));
+    let mut vectors = load_checkpoint(&checkpoint, &key, chunks.len());
+    let pending: Vec<usize> = (0..chunks.len()).filter(|&i| vectors[i].is_none()).collect();
+    let pending_bytes: usize = pending.iter().map(|&i| chunk_text(i).len()).sum();
+    let requests = pending.len().div_ceil(opts.batch_size);
+
+    if opts.dry_run {
+        info!("\n🧮 Embedding Plan (dry run)");
+        info!("{}", "─".repeat(60));
+        info!("Files:          {}", texts.len());
+        info!("Chunks:         {} ({} bytes, {} overlap)", chunks.len(), opts.chunk_size, opts.chunk_overlap);
+        if pending.len() < chunks.len() {
+            info!("Already done:   {} (saved in {})", chunks.len() - pending.len(), checkpoint.display());
+        }
+        info!("To embed:       {} chunks, {}", pending.len(), size::format_size(pending_bytes as u64));
+        info!("Approx. tokens: {}", embeddings::estimate_tokens(pending_bytes));
+        info!("Requests:       {} of up to {} chunks", requests, opts.batch_size);
+        info!("Model:          {} at {}", settings.model, settings.api_base);
+        if opts.requests_per_minute > 0 && requests > 0 {
+            info!(
+                "Minimum time:   {} min at {} requests/minute",
+                requests.div_ceil(opts.requests_per_minute as usize),
+                opts.requests_per_minute
+            );
+        }
+        return Ok(());
+    }
+
+    if chunks.is_empty() {
+        return Err(format!("{} has no text files under files/ to embed", docpack).into());
+    }
+    settings.check_key()?;
+
+    if pending.len() < chunks.len() {
+        info!("Resuming: {} of {} chunks already embedded", chunks.len() - pending.len(), chunks.len());
+    }
+    info!(
+        "Embedding {} chunks with {} ({} requests)...",
+        pending.len(),
+        settings.model,
+        requests
+    );
+    let mut saved = open_checkpoint(&checkpoint, &key)?;
+    let mut client = Client::new(settings.clone(), opts.requests_per_minute);
+    let mut dimension = vectors.iter().flatten().map(Vec::len).next();
+    let mut progress = Progress::bytes("Embedding", Some(pending_bytes as u64), opts.quiet);
+    let mut sent = 0u64;
+    for batch in pending.chunks(opts.batch_size) {
+        let inputs: Vec<&str> = batch.iter().map(|&i| chunk_text(i)).collect();
+        let embedded = client.embed(&inputs).map_err(|e| {
+            let done = vectors.iter().filter(|v| v.is_some()).count();
+            format!(
+                "{}\n{} of {} chunks are saved in {}; run the same command again to resume",
+                e,
+                done,
+                chunks.len(),
+                checkpoint.display()
+            )
+        })?;
+        for vector in &embedded {
+            if vector.is_empty() {
+                return Err(format!("{} returned an empty embedding", settings.api_base).into());
+            }
+            let expected = *dimension.get_or_insert(vector.len());
+            if vector.len() != expected {
+                return Err(format!(
+                    "{} returned a {}-dimensional embedding after {}-dimensional ones",
+                    settings.api_base,
+                    vector.len(),
+                    expected
+                )
+                .into());
+            }
+        }
+        writeln!(saved, "{}", json!({ "rows": batch, "vectors": embedded }))?;
+        for (&i, vector) in batch.iter().zip(embedded) {
+            vectors[i] = Some(vector);
+        }
+        sent += inputs.iter().map(|input| input.len() as u64).sum::<u64>();
+        progress.update(0, sent);
+    }
+    progress.finish();
+    let dimension = dimension.unwrap_or(0);
+
+    fs::create_dir_all(&index_dir).with_path(&index_dir)?;
+    let packed: Vec<f32> = vectors.into_iter().flatten().flatten().collect();
+    let bin_path = index_dir.join(embeddings::BIN_FILE);
+    fs::write(&bin_path, embeddings::encode(dimension, &packed)).with_path(&bin_path)?;
+
+    let mut options = ingest::selection_options(&selection.filter, selection.max_size, selection.symlinks, selection.non_utf8);
+    options["model"] = json!(settings.model);
+    options["chunk_size"] = json!(opts.chunk_size);
+    options["chunk_overlap"] = json!(opts.chunk_overlap);
+    let fingerprint = fingerprint::fingerprint(
+        fingerprint::EMBEDDINGS_FORMAT_VERSION,
+        embeddings::ANALYZER,
+        embeddings::ANALYZER_VERSION,
+        options,
+    );
+    let meta = json!({
+        "metadata": {
+            "created": chrono::Utc::now().to_rfc3339(),
+            "model": settings.model,
+            "api_base": settings.api_base,
+            "dimension": dimension,
+            "count": chunks.len(),
+            "chunk_size": opts.chunk_size,
+            "chunk_overlap": opts.chunk_overlap,
+            "fingerprint": fingerprint,
+        },
+        "chunks": chunks.iter().map(|(_, chunk)| chunk).collect::<Vec<_>>(),
+    });
+    let meta_path = index_dir.join(embeddings::META_FILE);
+    fs::write(&meta_path, serde_json::to_string_pretty(&meta)?).with_path(&meta_path)?;
+    info!("  Created index/{} and index/{}", embeddings::BIN_FILE, embeddings::META_FILE);
+    info!("  Fingerprint: {}", fingerprint::describe(&fingerprint));
+
+    if kind == DocpackKind::Archive {
+        info!("Rewriting archive...");
+        pack::write_archive(&work_dir, docpack_path)?;
+    }
+    drop(saved);
+    fs::remove_file(&checkpoint).with_path(&checkpoint)?;
+
+    info!("\n🧮 Embeddings Summary");
+    info!("{}", "─".repeat(60));
+    info!("Files:      {}", texts.len());
+    info!("Vectors:    {} × {} dimensions", chunks.len(), dimension);
+    info!("Text sent:  {} (≈{} tokens)", size::format_size(sent), embeddings::estimate_tokens(sent as usize));
+    info!("Requests:   {} ({} retried)", requests, client.retries);
+    info!(
+        "Index size: {}",
+        size::format_size(fs::metadata(&bin_path).with_path(&bin_path)?.len())
+    );
+
+    info!("\n✓ Embedded {}", docpack);
+    Ok(())
+}
+
+/// Identifies the chunks and model a checkpoint was written for, so vectors
+/// are never reused for text or a model they weren't computed from. The
+/// endpoint may change between attempts.
+fn checkpoint_key<'a>(model: &str, count: usize, chunk: impl Fn(usize) -> (&'a Chunk, &'a str)) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}\n", model));
+    for i in 0..count {
+        let (chunk, text) = chunk(i);
+        hasher.update(format!("{}\0{}\0{}\0", chunk.path, chunk.start, chunk.end));
+        hasher.update(text);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Vectors saved by an interrupted run with the same `key`, by row. A
+/// checkpoint for other chunks or settings is ignored, and a batch cut off
+/// mid-write is simply embedded again.
+fn load_checkpoint(path: &Path, key: &str, count: usize) -> Vec<Option<Vec<f32>>> {
+    let mut vectors = vec![None; count];
+    let Ok(content) = fs::read_to_string(path) else {
+        return vectors;
+    };
+    let mut lines = content.lines();
+    let header: Option<Value> = lines.next().and_then(|line| serde_json::from_str(line).ok());
+    if header.as_ref().and_then(|header| header["key"].as_str()) != Some(key) {
+        info!("  Ignoring saved progress in {}: files or settings changed", path.display());
+        return vectors;
+    }
+    for line in lines {
+        let Ok(batch) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let rows = batch["rows"].as_array().into_iter().flatten().filter_map(Value::as_u64);
+        let saved = batch["vectors"].as_array().into_iter().flatten();
+        for (row, vector) in rows.zip(saved) {
+            if let (Some(slot), Ok(vector)) = (vectors.get_mut(row as usize), serde_json::from_value(vector.clone())) {
+                *slot = Some(vector);
+            }
+        }
+    }
+    vectors
+}
+
+/// Open the checkpoint for appending, starting it over unless it was written
+/// for `key`.
+fn open_checkpoint(path: &Path, key: &str) -> Result<fs::File, LocaldocError> {
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    let header = existing.lines().next().and_then(|line| serde_json::from_str::<Value>(line).ok());
+    if header.as_ref().and_then(|header| header["key"].as_str()) == Some(key) {
+        let mut file = fs::OpenOptions::new().append(true).open(path)?;
+        // Don't continue a line an interrupted run left unfinished
+        if !existing.ends_with('\n') {
+            writeln!(file)?;
+        }
+        return Ok(file);
+    }
+    let mut file = fs::File::create(path).with_path(path)?;
+    writeln!(file, "{}", json!({ "key": key }))?;
+    Ok(file)
+}
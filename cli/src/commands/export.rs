@@ -0,0 +1,569 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use log::info;
+use pulldown_cmark::{html, CowStr, Event, HeadingLevel, LinkType, Options, Parser, Tag, TagEnd};
+
+use crate::commands::run;
+use crate::error::{LocaldocError, WithPath};
+use crate::reader::{self, DocpackReader};
+
+/// Page names that stand for their directory, in order of preference.
+const INDEX_PAGES: [&str; 2] = ["README.md", "index.md"];
+
+/// What `localdoc export` writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// An mdBook source tree: `book.toml` and `src/SUMMARY.md`
+    Mdbook,
+    /// Standalone HTML pages sharing a navigation sidebar
+    Html,
+}
+
+/// Settings for `localdoc export`, one field per command-line flag.
+pub struct ExportOptions<'a> {
+    pub format: ExportFormat,
+    pub out: &'a str,
+    /// Replace an output directory that is not empty
+    pub force: bool,
+}
+
+/// The contents of each file in the run outputs, by path relative to the
+/// output directory.
+type Outputs = BTreeMap<String, Vec<u8>>;
+
+/// One Markdown file of the run outputs.
+struct Page {
+    /// Path relative to `output/`, e.g. `api/cli.md`
+    path: String,
+    title: String,
+    markdown: String,
+}
+
+/// A line of the navigation: a page, or a directory without an index page.
+struct Entry {
+    depth: usize,
+    title: String,
+    page: Option<usize>,
+}
+
+/// Turn the Markdown the documenter wrote to `output/` into a site: an
+/// mdBook source tree or plain HTML pages. Links between outputs are
+/// rewritten to point at the exported pages, and the images and files
+/// they reference are copied along.
+pub fn export(docpack: &str, opts: &ExportOptions) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+    let mut reader = reader::open(docpack_path)?;
+    let (source, outputs) = read_outputs(docpack_path, reader.as_mut())?;
+    let mut pages: Vec<Page> = outputs
+        .iter()
+        .filter(|(path, _)| path.ends_with(".md"))
+        .map(|(path, bytes)| {
+            let markdown = String::from_utf8_lossy(bytes).into_owned();
+            Page { title: title(path, &markdown), path: path.clone(), markdown }
+        })
+        .collect();
+    if pages.is_empty() {
+        return Err(format!(
+            "{} has no Markdown files, so there is nothing to export; run the documenter first with `localdoc run {}`",
+            source, docpack
+        )
+        .into());
+    }
+    pages.sort_by(|a, b| nav_key(&a.path).cmp(&nav_key(&b.path)));
+
+    let out = Path::new(opts.out);
+    prepare_out_dir(out, docpack_path, opts.force)?;
+    let site_root = match opts.format {
+        ExportFormat::Mdbook => out.join("src"),
+        ExportFormat::Html => out.to_path_buf(),
+    };
+
+    let page_paths: BTreeMap<&str, String> =
+        pages.iter().map(|page| (page.path.as_str(), site_path(&page.path, opts.format))).collect();
+    let mut assets = BTreeSet::new();
+    let mut rewritten = Vec::with_capacity(pages.len());
+    for page in &pages {
+        let from = &page_paths[page.path.as_str()];
+        let markdown = rewrite_links(&page.markdown, |dest| {
+            let (path, fragment) = resolve(&page.path, dest)?;
+            let target = if let Some(output) = path.strip_prefix("output/") {
+                match page_paths.get(output) {
+                    Some(target) => target.clone(),
+                    None if outputs.contains_key(output) => {
+                        assets.insert(path.clone());
+                        output.to_string()
+                    }
+                    None => return None,
+                }
+            } else if let Some(file) = path.strip_prefix("files/") {
+                let name = reader.source_name(file).unwrap_or_else(|| path.clone());
+                if !reader.exists(&name) {
+                    return None;
+                }
+                assets.insert(path.clone());
+                path.clone()
+            } else {
+                return None;
+            };
+            Some(format!("{}{}", relative(from, &target), fragment))
+        });
+        rewritten.push(markdown);
+    }
+
+    for asset in &assets {
+        let (bytes, dest) = match asset.strip_prefix("output/") {
+            Some(output) => (outputs[output].clone(), site_root.join(output)),
+            None => {
+                let file = &asset["files/".len()..];
+                let name = reader.source_name(file).unwrap_or_else(|| asset.clone());
+                (reader.read(&name).ok_or_else(|| format!("Cannot read {} in {}", asset, docpack))?, site_root.join(asset))
+            }
+        };
+        write_file(&dest, &bytes)?;
+    }
+
+    let title = run::docpack_name(docpack_path);
+    let outline = outline(&pages);
+    match opts.format {
+        ExportFormat::Mdbook => {
+            for (page, markdown) in pages.iter().zip(&rewritten) {
+                write_file(&site_root.join(&page.path), markdown.as_bytes())?;
+            }
+            let mut book = toml_edit::DocumentMut::new();
+            book["book"] = toml_edit::table();
+            book["book"]["title"] = toml_edit::value(&title);
+            book["book"]["src"] = toml_edit::value("src");
+            let book_toml = out.join("book.toml");
+            fs::write(&book_toml, book.to_string()).with_path(&book_toml)?;
+            let summary_path = site_root.join("SUMMARY.md");
+            fs::write(&summary_path, summary(&pages, &outline)).with_path(&summary_path)?;
+        }
+        ExportFormat::Html => {
+            for (i, (page, markdown)) in pages.iter().zip(&rewritten).enumerate() {
+                let path = &page_paths[page.path.as_str()];
+                let nav = nav_html(&pages, &page_paths, &outline, path, Some(i));
+                let document = page_html(&title, &page.title, &relative(path, "index.html"), &nav, &render(markdown));
+                write_file(&site_root.join(path), document.as_bytes())?;
+            }
+            if !page_paths.values().any(|path| path == "index.html") {
+                let nav = nav_html(&pages, &page_paths, &outline, "index.html", None);
+                let contents = format!("<h1>{}</h1>\n<p>Documentation generated from {}.</p>\n", escape(&title), escape(docpack));
+                let index_html = site_root.join("index.html");
+                fs::write(&index_html, page_html(&title, "Contents", "index.html", &nav, &contents)).with_path(&index_html)?;
+            }
+        }
+    }
+
+    info!("✓ Exported {} page(s) and {} asset(s) to {}", pages.len(), assets.len(), out.display());
+    match opts.format {
+        ExportFormat::Mdbook => info!("  Build it with `mdbook build {}`, or preview it with `mdbook serve {}`", out.display(), out.display()),
+        ExportFormat::Html => info!("  Open {} in a browser", out.join("index.html").display()),
+    }
+    Ok(())
+}
+
+/// The run outputs and a name for where they came from. A docpack directory keeps them in `output/`;
+/// a run on an archive copies them to `<name>-output/` beside it.
+fn read_outputs(
+    docpack_path: &Path,
+    reader: &mut dyn DocpackReader,
+) -> Result<(String, Outputs), LocaldocError> {
+    let mut outputs = BTreeMap::new();
+    for (path, _) in reader.list_files("output") {
+        let bytes = reader.try_read(&format!("output/{}", path))?;
+        outputs.insert(path, bytes);
+    }
+    let copied = docpack_path.with_file_name(format!("{}-output", run::docpack_name(docpack_path)));
+    if !outputs.is_empty() || !reader.is_archive() || !copied.is_dir() {
+        return Ok((format!("{}/output/", docpack_path.display()), outputs));
+    }
+    for entry in walkdir::WalkDir::new(&copied).into_iter().filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        let relative = entry.path().strip_prefix(&copied)?;
+        let name = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        outputs.insert(name, fs::read(entry.path()).with_path(entry.path())?);
+    }
+    Ok((format!("{}/", copied.display()), outputs))
+}
+
+/// Make `out` an empty directory, refusing one with contents unless
+/// `force`, and one inside the docpack.
+fn prepare_out_dir(out: &Path, docpack_path: &Path, force: bool) -> Result<(), LocaldocError> {
+    let parent = out.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::create_dir_all(parent).with_path(parent)?;
+    let resolved = parent.canonicalize()?.join(out.file_name().unwrap_or_default());
+    if docpack_path.is_dir() && resolved.starts_with(docpack_path.canonicalize()?) {
+        return Err(format!("Cannot export into {}, which is inside the docpack", out.display()).into());
+    }
+    let occupied = match fs::read_dir(out) {
+        Ok(mut entries) => entries.next().is_some(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+        Err(_) => true,
+    };
+    if occupied && !force {
+        return Err(format!("{} exists and is not empty; pass --force to replace it", out.display()).into());
+    }
+    if occupied {
+        fs::remove_dir_all(out).with_path(out)?;
+    }
+    fs::create_dir_all(out).with_path(out)?;
+    Ok(())
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, bytes)
+}
+
+fn is_index(path: &str) -> bool {
+    INDEX_PAGES.iter().any(|name| path.rsplit('/').next() == Some(name))
+}
+
+/// Sorts each directory's index page first, then its other pages, then
+/// its subdirectories.
+fn nav_key(path: &str) -> (Vec<&str>, usize, &str) {
+    let mut parts: Vec<&str> = path.split('/').collect();
+    let name = parts.pop().unwrap_or_default();
+    let rank = INDEX_PAGES.iter().position(|index| *index == name).unwrap_or(INDEX_PAGES.len());
+    (parts, rank, name)
+}
+
+/// Where a page goes in the site: the same path for mdBook, which turns
+/// `.md` links into `.html` itself, and `.html` (`index.html` for an
+/// index page) for HTML.
+fn site_path(path: &str, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Mdbook => path.to_string(),
+        ExportFormat::Html if is_index(path) => match path.rsplit_once('/') {
+            Some((dir, _)) => format!("{}/index.html", dir),
+            None => "index.html".to_string(),
+        },
+        ExportFormat::Html => format!("{}.html", path.trim_end_matches(".md")),
+    }
+}
+
+/// A page's first top-level heading, or its file name made readable.
+fn title(path: &str, markdown: &str) -> String {
+    let mut in_title = false;
+    let mut text = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::Heading { level: HeadingLevel::H1, .. }) => in_title = true,
+            Event::End(TagEnd::Heading(HeadingLevel::H1)) if !text.trim().is_empty() => return text.trim().to_string(),
+            Event::End(TagEnd::Heading(HeadingLevel::H1)) => in_title = false,
+            Event::Text(t) | Event::Code(t) if in_title => text.push_str(&t),
+            _ => {}
+        }
+    }
+    let mut parts: Vec<&str> = path.trim_end_matches(".md").split('/').collect();
+    if is_index(path) {
+        parts.pop();
+    }
+    parts.last().map(|name| humanize(name)).unwrap_or_else(|| "Introduction".to_string())
+}
+
+/// `getting_started` → `Getting started`
+fn humanize(name: &str) -> String {
+    let words = name.replace(['-', '_'], " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The navigation in order. A directory's index page heads its pages;
+/// a directory without one gets an entry of its own.
+fn outline(pages: &[Page]) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut headed: BTreeSet<String> = BTreeSet::new();
+    for (i, page) in pages.iter().enumerate() {
+        let dirs: Vec<&str> = page.path.rsplit_once('/').map(|(dir, _)| dir.split('/').collect()).unwrap_or_default();
+        let mut depth = dirs.len();
+        for level in 0..dirs.len() {
+            let dir = dirs[..=level].join("/");
+            if !headed.insert(dir) {
+                continue;
+            }
+            if level + 1 == dirs.len() && is_index(&page.path) {
+                depth = level;
+            } else {
+                entries.push(Entry { depth: level, title: humanize(dirs[level]), page: None });
+            }
+        }
+        entries.push(Entry { depth, title: page.title.clone(), page: Some(i) });
+    }
+    entries
+}
+
+/// mdBook's `SUMMARY.md`: a top-level index page becomes the unnumbered
+/// introduction, a directory without an index page a draft chapter.
+fn summary(pages: &[Page], outline: &[Entry]) -> String {
+    let mut summary = String::from("# Summary\n\n");
+    for (n, entry) in outline.iter().enumerate() {
+        let link = |text: &str, target: &str| format!("[{}]({})", text.replace('[', "\\[").replace(']', "\\]"), link_target(target));
+        match entry.page {
+            Some(i) if n == 0 && is_index(&pages[i].path) && !pages[i].path.contains('/') => {
+                summary.push_str(&format!("{}\n\n", link(&entry.title, &pages[i].path)));
+            }
+            Some(i) => summary.push_str(&format!("{}- {}\n", "    ".repeat(entry.depth), link(&entry.title, &pages[i].path))),
+            None => summary.push_str(&format!("{}- [{}]()\n", "    ".repeat(entry.depth), entry.title)),
+        }
+    }
+    summary
+}
+
+/// The sidebar of the HTML page at `current`, as nested lists.
+fn nav_html(pages: &[Page], page_paths: &BTreeMap<&str, String>, outline: &[Entry], current: &str, page: Option<usize>) -> String {
+    let mut nav = String::new();
+    let mut open = 0;
+    for entry in outline {
+        let depth = entry.depth + 1;
+        if depth > open {
+            for _ in open..depth {
+                nav.push_str("<ul>\n");
+            }
+        } else {
+            nav.push_str("</li>\n");
+            for _ in depth..open {
+                nav.push_str("</ul>\n</li>\n");
+            }
+        }
+        open = depth;
+        match entry.page {
+            Some(i) => {
+                let href = relative(current, &page_paths[pages[i].path.as_str()]);
+                let class = if page == Some(i) { " class=\"current\"" } else { "" };
+                nav.push_str(&format!("<li><a href=\"{}\"{}>{}</a>", escape(&href), class, escape(&entry.title)));
+            }
+            None => nav.push_str(&format!("<li><span>{}</span>", escape(&entry.title))),
+        }
+    }
+    if open > 0 {
+        nav.push_str("</li>\n");
+        for _ in 1..open {
+            nav.push_str("</ul>\n</li>\n");
+        }
+        nav.push_str("</ul>\n");
+    }
+    nav
+}
+
+fn page_html(book: &str, title: &str, home: &str, nav: &str, content: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title} - {book}</title>
+<style>
+body {{ margin: 0; display: flex; font: 16px/1.6 system-ui, sans-serif; color: #222; }}
+nav {{ flex: 0 0 16rem; padding: 1rem; background: #f6f6f6; border-right: 1px solid #ddd; min-height: 100vh; box-sizing: border-box; }}
+nav ul {{ list-style: none; margin: 0; padding-left: 1rem; }}
+nav > ul {{ padding-left: 0; }}
+nav a {{ color: #333; text-decoration: none; }}
+nav a.current {{ font-weight: bold; color: #0b5cad; }}
+nav span {{ color: #777; }}
+.book {{ display: block; font-weight: bold; margin-bottom: 1rem; }}
+main {{ flex: 1; max-width: 50rem; padding: 1rem 2rem; overflow-wrap: break-word; }}
+pre {{ background: #f6f6f6; padding: 0.75rem; overflow-x: auto; }}
+code {{ font-family: ui-monospace, monospace; font-size: 0.9em; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ddd; padding: 0.25rem 0.5rem; }}
+img {{ max-width: 100%; }}
+</style>
+</head>
+<body>
+<nav>
+<a class="book" href="{home}">{book}</a>
+{nav}</nav>
+<main>
+{content}</main>
+</body>
+</html>
+"#,
+        title = escape(title),
+        book = escape(book),
+        home = escape(home),
+        nav = nav,
+        content = content,
+    )
+}
+
+/// Render a page, giving its headings the ids mdBook would so that links
+/// to `page.html#section` land in the same place.
+pub(crate) fn render(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut events: Vec<Event> = Parser::new_ext(markdown, options).collect();
+    let mut used: BTreeMap<String, usize> = BTreeMap::new();
+    let mut i = 0;
+    while i < events.len() {
+        if let Event::Start(Tag::Heading { id: None, .. }) = &events[i] {
+            let mut text = String::new();
+            for event in &events[i + 1..] {
+                match event {
+                    Event::End(TagEnd::Heading(_)) => break,
+                    Event::Text(t) | Event::Code(t) => text.push_str(t),
+                    _ => {}
+                }
+            }
+            let base = heading_id(&text);
+            let count = used.entry(base.clone()).or_insert(0);
+            let id = if *count == 0 { base } else { format!("{}-{}", base, count) };
+            *count += 1;
+            if let Event::Start(Tag::Heading { id: slot, .. }) = &mut events[i] {
+                *slot = Some(CowStr::from(id));
+            }
+        }
+        i += 1;
+    }
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, events.into_iter());
+    rendered
+}
+
+/// mdBook's heading ids: lowercase, spaces as dashes, punctuation dropped.
+fn heading_id(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-' || c.is_whitespace())
+        .map(|c| if c.is_whitespace() { '-' } else { c.to_ascii_lowercase() })
+        .collect()
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Replace the destination of every inline link, image and link reference
+/// definition for which `target` returns one, leaving the rest of the
+/// Markdown exactly as written.
+pub(crate) fn rewrite_links(markdown: &str, mut target: impl FnMut(&str) -> Option<String>) -> String {
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    let mut edit = |span: Range<usize>, separator: &str, dest: &str, edits: &mut Vec<(Range<usize>, String)>| {
+        let Some(start) = markdown[span.clone()].rfind(separator).map(|at| span.start + at + separator.len()) else {
+            return;
+        };
+        if let (Some(raw), Some(new)) = (destination(markdown, start..span.end, separator == "]("), target(dest)) {
+            edits.push((raw, link_target(&new)));
+        }
+    };
+
+    let parser = Parser::new_ext(markdown, Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES);
+    for (_, definition) in parser.reference_definitions().iter() {
+        edit(definition.span.clone(), "]:", &definition.dest, &mut edits);
+    }
+    for (event, span) in parser.into_offset_iter() {
+        if let Event::Start(Tag::Link { link_type: LinkType::Inline, dest_url, .. } | Tag::Image { link_type: LinkType::Inline, dest_url, .. }) =
+            event
+        {
+            edit(span, "](", &dest_url, &mut edits);
+        }
+    }
+
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut rewritten = markdown.to_string();
+    for (range, replacement) in edits {
+        rewritten.replace_range(range, &replacement);
+    }
+    rewritten
+}
+
+/// `path` as a link destination, in angle brackets if the file name
+/// would otherwise end it early.
+fn link_target(path: &str) -> String {
+    if path.contains(|c: char| c.is_whitespace() || "()<>".contains(c)) {
+        format!("<{}>", path)
+    } else {
+        path.to_string()
+    }
+}
+
+/// Where the link destination starting at `span.start` is written: up to
+/// the closing `>` of `<...>`, otherwise up to whitespace or the `)` that
+/// ends an inline link.
+fn destination(markdown: &str, span: Range<usize>, inline: bool) -> Option<Range<usize>> {
+    let text = &markdown[span.clone()];
+    let start = span.start + (text.len() - text.trim_start().len());
+    let rest = &markdown[start..span.end];
+    let len = if rest.starts_with('<') {
+        rest.find('>')? + 1
+    } else {
+        rest.find(char::is_whitespace).unwrap_or(if inline { rest.len().saturating_sub(1) } else { rest.len() })
+    };
+    (len > 0).then_some(start..start + len)
+}
+
+/// The path relative to the docpack root that the link `dest` on the
+/// output page `page` points at, and its `#fragment`, if it is a local
+/// link. Links may be relative to the page, rooted at the docpack, or
+/// written as the documenter saw them, under `/workspace`.
+pub(crate) fn resolve(page: &str, dest: &str) -> Option<(String, String)> {
+    if dest.is_empty() || dest.starts_with('#') || dest.starts_with("//") || dest.contains("://") || dest.starts_with("mailto:") {
+        return None;
+    }
+    let path = dest.split(['#', '?']).next().unwrap_or_default();
+    let fragment = dest.find('#').map(|at| &dest[at..]).unwrap_or_default();
+    let joined = if let Some(rooted) = path.strip_prefix("/workspace/").or_else(|| path.strip_prefix('/')) {
+        rooted.to_string()
+    } else {
+        match page.rsplit_once('/') {
+            Some((dir, _)) => format!("output/{}/{}", dir, path),
+            None => format!("output/{}", path),
+        }
+    };
+    let mut parts: Vec<&str> = Vec::new();
+    for part in joined.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    (!parts.is_empty()).then(|| (parts.join("/"), fragment.to_string()))
+}
+
+/// The link from the site page at `from` to the site path `to`.
+fn relative(from: &str, to: &str) -> String {
+    let from_dirs: Vec<&str> = from.rsplit_once('/').map(|(dir, _)| dir.split('/').collect()).unwrap_or_default();
+    let to_parts: Vec<&str> = to.split('/').collect();
+    let common = from_dirs.iter().zip(&to_parts).take_while(|(a, b)| a == b).count().min(to_parts.len() - 1);
+    let mut parts = vec![".."; from_dirs.len() - common];
+    parts.extend(&to_parts[common..]);
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_resolve_from_the_page_the_root_and_the_workspace() {
+        assert_eq!(resolve("api/cli.md", "../intro.md#usage"), Some(("output/intro.md".into(), "#usage".into())));
+        assert_eq!(resolve("intro.md", "/workspace/files/src/main.rs"), Some(("files/src/main.rs".into(), "".into())));
+        assert_eq!(resolve("intro.md", "/output/img/logo.png"), Some(("output/img/logo.png".into(), "".into())));
+        assert_eq!(resolve("intro.md", "https://example.com/a.md"), None);
+        assert_eq!(resolve("intro.md", "#usage"), None);
+        assert_eq!(resolve("intro.md", "../../escape.md"), None);
+
+        assert_eq!(relative("api/cli.html", "index.html"), "../index.html");
+        assert_eq!(relative("api/cli.html", "api/index.html"), "index.html");
+        assert_eq!(relative("index.html", "files/src/main.rs"), "files/src/main.rs");
+    }
+
+    #[test]
+    fn only_link_destinations_are_rewritten() {
+        let markdown = "See [the CLI](cli.md \"title\") and ![logo](img/logo.png).\n\n[ref]: ./cli.md#options\n[web]: https://example.com\n";
+        let rewritten = rewrite_links(markdown, |dest| dest.contains("cli").then(|| "api/cli.html".to_string()));
+        assert_eq!(
+            rewritten,
+            "See [the CLI](api/cli.html \"title\") and ![logo](img/logo.png).\n\n[ref]: api/cli.html\n[web]: https://example.com\n"
+        );
+    }
+}
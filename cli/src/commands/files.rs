@@ -0,0 +1,163 @@
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::Path;
+use globset::Glob;
+
+use crate::error::LocaldocError;
+use crate::reader;
+use crate::search;
+use crate::size::format_size;
+
+/// Close matches named when a path is not in the docpack.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Bytes looked at to tell a binary file from text, as git does.
+const SNIFF_BYTES: usize = 8000;
+
+/// Settings for `localdoc list`, one field per command-line flag.
+pub struct ListOptions<'a> {
+    /// Only paths starting with this, e.g. `files/src/`
+    pub prefix: Option<&'a str>,
+    /// Only paths matching this glob, e.g. `**/*.md`
+    pub glob: Option<&'a str>,
+    /// Show sizes and a total
+    pub long: bool,
+}
+
+/// Print the path of every file in a docpack. Archives are listed from
+/// their central directory; nothing is extracted.
+pub fn list(docpack: &str, opts: &ListOptions) -> Result<(), LocaldocError> {
+    let mut reader = reader::open(Path::new(docpack))?;
+    let glob = opts.glob.map(|glob| Glob::new(glob).map(|glob| glob.compile_matcher())).transpose()?;
+    let all = reader.entries();
+    let entries: Vec<&(String, u64)> = all
+        .iter()
+        .filter(|(path, _)| opts.prefix.is_none_or(|prefix| path.starts_with(prefix)))
+        .filter(|(path, _)| glob.as_ref().is_none_or(|glob| glob.is_match(path)))
+        .collect();
+
+    if entries.is_empty() && (opts.prefix.is_some() || opts.glob.is_some()) {
+        let mut message = "No files match".to_string();
+        if let Some(prefix) = opts.prefix {
+            message.push_str(&format!(" --prefix {}", prefix));
+            message.push_str(&suggestions(prefix, &all));
+        }
+        if let Some(glob) = opts.glob {
+            message.push_str(&format!(" --glob {}", glob));
+        }
+        return Err(message.into());
+    }
+
+    let mut out = io::stdout().lock();
+    for (path, size) in &entries {
+        if opts.long {
+            writeln!(out, "{:>10}  {}", format_size(*size), path)?;
+        } else {
+            writeln!(out, "{}", path)?;
+        }
+    }
+    if opts.long {
+        let total: u64 = entries.iter().map(|(_, size)| size).sum();
+        writeln!(out, "\n{} file(s), {}", entries.len(), format_size(total))?;
+    }
+    Ok(())
+}
+
+/// Settings for `localdoc cat`, one field per command-line flag.
+pub struct CatOptions {
+    /// Print invalid UTF-8 as replacement characters
+    pub text: bool,
+    /// Print a binary file to a terminal anyway
+    pub force: bool,
+}
+
+/// Copy one file of a docpack to stdout. From an archive only that entry
+/// is decompressed, as it is written.
+pub fn cat(docpack: &str, path: &str, opts: &CatOptions) -> Result<(), LocaldocError> {
+    let mut reader = reader::open(Path::new(docpack))?;
+    let path = path.trim_start_matches("./");
+    // A deduplicated archive keeps files/ as blobs
+    let name = match path.strip_prefix("files/") {
+        Some(file) => reader.source_name(file).unwrap_or_else(|| path.to_string()),
+        None => path.to_string(),
+    };
+    if !reader.exists(&name) {
+        let entries = reader.entries();
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        if entries.iter().any(|(entry, _)| entry.starts_with(&prefix)) {
+            return Err(format!("{} is a directory; list it with `localdoc list {} --prefix {}`", path, docpack, prefix).into());
+        }
+        return Err(LocaldocError::NotFound(format!("No file {} in {}{}", path, docpack, suggestions(path, &entries))));
+    }
+
+    let mut input = reader.open_file(&name).map_err(|e| format!("Cannot read {} in {}: {}", path, docpack, e))?;
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    (&mut input).take(SNIFF_BYTES as u64).read_to_end(&mut head)?;
+    let mut stdout = io::stdout().lock();
+    if head.contains(&0) && stdout.is_terminal() && !opts.force {
+        return Err(format!("{} looks like a binary file; pass --force to print it, or redirect the output to a file", path).into());
+    }
+
+    let result = if opts.text {
+        let mut bytes = head;
+        input.read_to_end(&mut bytes)?;
+        stdout.write_all(String::from_utf8_lossy(&bytes).as_bytes())
+    } else {
+        stdout.write_all(&head).and_then(|_| io::copy(&mut input, &mut stdout).map(drop))
+    };
+    match result {
+        // `localdoc cat ... | head` closing the pipe early is not an error
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => Ok(other?),
+    }
+}
+
+/// `. Did you mean:` and the paths closest to `wanted`, or nothing when
+/// none are close: those within a few edits, then those with the same
+/// file name, then those containing it.
+fn suggestions(wanted: &str, entries: &[(String, u64)]) -> String {
+    let wanted_chars: Vec<char> = wanted.chars().collect();
+    let max_edits = (wanted_chars.len() / 4).clamp(1, 3);
+    let file_name = wanted.rsplit('/').next().unwrap_or(wanted).to_lowercase();
+    let needle = wanted.to_lowercase();
+
+    let mut close: Vec<(u8, &str)> = entries
+        .iter()
+        .filter_map(|(path, _)| {
+            let rank = if search::within_edits(&wanted_chars, path, max_edits) {
+                0
+            } else if !file_name.is_empty() && path.rsplit('/').next()?.to_lowercase() == file_name {
+                1
+            } else if path.to_lowercase().contains(&needle) {
+                2
+            } else {
+                return None;
+            };
+            Some((rank, path.as_str()))
+        })
+        .collect();
+    close.sort();
+    if close.is_empty() {
+        return String::new();
+    }
+    let mut message = ". Did you mean:".to_string();
+    for (_, path) in close.iter().take(MAX_SUGGESTIONS) {
+        message.push_str(&format!("\n  {}", path));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggestions_prefer_typos_then_file_names() {
+        let entries: Vec<(String, u64)> =
+            ["docpack.json", "files/src/main.rs", "files/README.md", "tasks.json"].iter().map(|path| (path.to_string(), 0)).collect();
+
+        assert_eq!(suggestions("docpak.json", &entries), ". Did you mean:\n  docpack.json");
+        assert_eq!(suggestions("files/main.rs", &entries), ". Did you mean:\n  files/src/main.rs");
+        assert_eq!(suggestions("readme", &entries), ". Did you mean:\n  files/README.md");
+        assert_eq!(suggestions("nothing/like/it", &entries), "");
+    }
+}
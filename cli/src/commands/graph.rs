@@ -0,0 +1,266 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::Path;
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::LocaldocError;
+use crate::reader;
+
+/// Nodes listed by `--stats` as the most connected.
+const TOP_NODES: usize = 10;
+/// Suggestions offered for an unknown node id.
+const MAX_SUGGESTIONS: usize = 10;
+
+#[derive(Deserialize)]
+struct Node {
+    id: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+}
+
+#[derive(Deserialize)]
+struct Edge {
+    from: String,
+    to: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+}
+
+#[derive(Deserialize)]
+struct GraphFile {
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    edges: Vec<Edge>,
+}
+
+/// What `localdoc graph` was asked for.
+pub enum Query<'a> {
+    Neighbors(&'a str),
+    Path(&'a str, &'a str),
+    Stats,
+}
+
+/// `index/graph.json` with its edges indexed by node.
+struct Graph {
+    nodes: BTreeMap<String, String>,
+    edges: Vec<Edge>,
+    /// Indexes into `edges` of the edges leaving each node
+    outgoing: HashMap<String, Vec<usize>>,
+    /// Indexes into `edges` of the edges arriving at each node
+    incoming: HashMap<String, Vec<usize>>,
+}
+
+impl Graph {
+    fn load(docpack: &str) -> Result<Graph, LocaldocError> {
+        let mut contents = reader::open(Path::new(docpack))?;
+        let Some(bytes) = contents.read("index/graph.json") else {
+            return Err(format!(
+                "{} has no graph (index/graph.json). Build one with:\n  localdoc index {} --graph",
+                docpack, docpack
+            )
+            .into());
+        };
+        let file: GraphFile =
+            serde_json::from_slice(&bytes).map_err(|e| format!("index/graph.json is not a readable graph: {}", e))?;
+
+        let mut graph = Graph {
+            nodes: file.nodes.into_iter().map(|node| (node.id, node.kind)).collect(),
+            edges: file.edges,
+            outgoing: HashMap::new(),
+            incoming: HashMap::new(),
+        };
+        for (i, edge) in graph.edges.iter().enumerate() {
+            graph.outgoing.entry(edge.from.clone()).or_default().push(i);
+            graph.incoming.entry(edge.to.clone()).or_default().push(i);
+        }
+        Ok(graph)
+    }
+
+    fn kind(&self, id: &str) -> &str {
+        self.nodes.get(id).map(String::as_str).unwrap_or("?")
+    }
+
+    fn degree(&self, id: &str) -> usize {
+        self.outgoing.get(id).map_or(0, Vec::len) + self.incoming.get(id).map_or(0, Vec::len)
+    }
+
+    /// `id` if the graph has it, else an error listing ids that contain it.
+    fn find<'a>(&self, id: &'a str) -> Result<&'a str, LocaldocError> {
+        if self.nodes.contains_key(id) {
+            return Ok(id);
+        }
+        let needle = id.to_lowercase();
+        let mut suggestions: Vec<&String> = self.nodes.keys().filter(|node| node.to_lowercase().contains(&needle)).collect();
+        // Shorter ids are usually the closer match
+        suggestions.sort_by_key(|node| (node.len(), node.as_str()));
+
+        let mut message = format!("No node '{}' in the graph", id);
+        if suggestions.is_empty() {
+            message.push_str("; `localdoc graph <docpack> --stats` lists the most connected nodes");
+        } else {
+            message.push_str(". Did you mean:");
+            for node in suggestions.iter().take(MAX_SUGGESTIONS) {
+                message.push_str(&format!("\n  {}", node));
+            }
+            if suggestions.len() > MAX_SUGGESTIONS {
+                message.push_str(&format!("\n  ... and {} more", suggestions.len() - MAX_SUGGESTIONS));
+            }
+        }
+        Err(message.into())
+    }
+
+    /// The shortest chain of edges from `from` to `to`, following edges in
+    /// either direction. Each step is (edge index, node reached).
+    fn shortest_path(&self, from: &str, to: &str) -> Option<Vec<(usize, String)>> {
+        let mut previous: HashMap<String, (usize, String)> = HashMap::new();
+        let mut queue = VecDeque::from([from.to_string()]);
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut steps = Vec::new();
+                let mut current = node;
+                while let Some((edge, before)) = previous.get(&current) {
+                    steps.push((*edge, current.clone()));
+                    current = before.clone();
+                }
+                steps.reverse();
+                return Some(steps);
+            }
+            let out = self.outgoing.get(&node).into_iter().flatten().map(|&i| (i, &self.edges[i].to));
+            let back = self.incoming.get(&node).into_iter().flatten().map(|&i| (i, &self.edges[i].from));
+            for (edge, next) in out.chain(back) {
+                if next != from && !previous.contains_key(next) {
+                    previous.insert(next.clone(), (edge, node.clone()));
+                    queue.push_back(next.clone());
+                }
+            }
+        }
+        None
+    }
+}
+
+pub fn run(docpack: &str, query: Query, json: bool) -> Result<(), LocaldocError> {
+    let graph = Graph::load(docpack)?;
+    match query {
+        Query::Neighbors(node) => neighbors(&graph, graph.find(node)?, json),
+        Query::Path(from, to) => path(&graph, graph.find(from)?, graph.find(to)?, json),
+        Query::Stats => stats(&graph, json),
+    }
+}
+
+fn neighbors(graph: &Graph, node: &str, json: bool) -> Result<(), LocaldocError> {
+    let outgoing: Vec<&Edge> = graph.outgoing.get(node).into_iter().flatten().map(|&i| &graph.edges[i]).collect();
+    let incoming: Vec<&Edge> = graph.incoming.get(node).into_iter().flatten().map(|&i| &graph.edges[i]).collect();
+
+    if json {
+        let list = |edges: &[&Edge], other: fn(&Edge) -> &str| {
+            edges
+                .iter()
+                .map(|edge| json!({ "id": other(edge), "type": graph.kind(other(edge)), "edge": edge.kind }))
+                .collect::<Vec<_>>()
+        };
+        println!("{}", serde_json::to_string_pretty(&json!({
+            "node": node,
+            "type": graph.kind(node),
+            "outgoing": list(&outgoing, |edge| &edge.to),
+            "incoming": list(&incoming, |edge| &edge.from),
+        }))?);
+        return Ok(());
+    }
+
+    println!("{} ({})", node, graph.kind(node));
+    if outgoing.is_empty() && incoming.is_empty() {
+        println!("  (no edges)");
+    }
+    for edge in &outgoing {
+        println!("  → {:<12} {:<10} {}", edge.kind, graph.kind(&edge.to), edge.to);
+    }
+    for edge in &incoming {
+        println!("  ← {:<12} {:<10} {}", edge.kind, graph.kind(&edge.from), edge.from);
+    }
+    Ok(())
+}
+
+fn path(graph: &Graph, from: &str, to: &str, json: bool) -> Result<(), LocaldocError> {
+    let steps = graph.shortest_path(from, to);
+
+    if json {
+        let steps = steps.as_ref().map(|steps| {
+            steps
+                .iter()
+                .map(|(edge, node)| {
+                    let edge = &graph.edges[*edge];
+                    let direction = if edge.to == *node { "forward" } else { "backward" };
+                    json!({ "id": node, "type": graph.kind(node), "edge": edge.kind, "direction": direction })
+                })
+                .collect::<Vec<_>>()
+        });
+        println!("{}", serde_json::to_string_pretty(&json!({
+            "from": from,
+            "to": to,
+            "length": steps.as_ref().map(Vec::len),
+            "steps": steps,
+        }))?);
+        return Ok(());
+    }
+
+    let Some(steps) = steps else {
+        println!("No path between {} and {}", from, to);
+        return Ok(());
+    };
+    println!("{} ({})", from, graph.kind(from));
+    for (edge, node) in &steps {
+        let edge = &graph.edges[*edge];
+        let arrow = if edge.to == *node { "→" } else { "←" };
+        println!("  {} {:<12} {} ({})", arrow, edge.kind, node, graph.kind(node));
+    }
+    println!("\n{} step{}", steps.len(), if steps.len() == 1 { "" } else { "s" });
+    Ok(())
+}
+
+fn stats(graph: &Graph, json: bool) -> Result<(), LocaldocError> {
+    let mut node_types: BTreeMap<&str, usize> = BTreeMap::new();
+    for kind in graph.nodes.values() {
+        *node_types.entry(kind).or_default() += 1;
+    }
+    let mut edge_types: BTreeMap<&str, usize> = BTreeMap::new();
+    for edge in &graph.edges {
+        *edge_types.entry(&edge.kind).or_default() += 1;
+    }
+    let mut top: Vec<(&str, usize)> = graph.nodes.keys().map(|id| (id.as_str(), graph.degree(id))).collect();
+    top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    top.truncate(TOP_NODES);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&json!({
+            "nodes": graph.nodes.len(),
+            "edges": graph.edges.len(),
+            "node_types": node_types,
+            "edge_types": edge_types,
+            "top_degree": top
+                .iter()
+                .map(|(id, degree)| json!({ "id": id, "type": graph.kind(id), "degree": degree }))
+                .collect::<Vec<_>>(),
+        }))?);
+        return Ok(());
+    }
+
+    info!("🕸️  Graph");
+    info!("{}", "─".repeat(60));
+    info!("Nodes: {}", graph.nodes.len());
+    for (kind, count) in &node_types {
+        info!("  {:<12} {}", kind, count);
+    }
+    info!("Edges: {}", graph.edges.len());
+    for (kind, count) in &edge_types {
+        info!("  {:<12} {}", kind, count);
+    }
+    if !top.is_empty() {
+        info!("\nMost connected:");
+        for (id, degree) in &top {
+            info!("  {:>5}  {} ({})", degree, id, graph.kind(id));
+        }
+    }
+    Ok(())
+}
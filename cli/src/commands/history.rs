@@ -0,0 +1,48 @@
+use crate::error::LocaldocError;
+use crate::history;
+
+pub fn run(limit: usize, json: bool) -> Result<(), LocaldocError> {
+    let records = history::read()?;
+    let recent = &records[records.len().saturating_sub(limit)..];
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(recent)?);
+        return Ok(());
+    }
+
+    if recent.is_empty() {
+        match history::path() {
+            Some(path) => println!("No runs recorded yet ({})", path.display()),
+            None => println!("No runs recorded yet"),
+        }
+        return Ok(());
+    }
+
+    println!("{:<19}  {:<22}  {:>9}  DOCPACK", "STARTED", "STATUS", "DURATION");
+    for record in recent.iter().rev() {
+        let started = record["started"]
+            .as_str()
+            .and_then(|started| chrono::DateTime::parse_from_rfc3339(started).ok())
+            .map(|started| started.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "?".to_string());
+        let duration = record["duration_seconds"].as_f64().map(format_duration).unwrap_or_else(|| "?".to_string());
+        println!(
+            "{:<19}  {:<22}  {:>9}  {}",
+            started,
+            record["status"].as_str().unwrap_or("?"),
+            duration,
+            record["docpack"].as_str().unwrap_or("?")
+        );
+    }
+    Ok(())
+}
+
+/// `42.3s`, or `12m 05s` from a minute up.
+pub(crate) fn format_duration(seconds: f64) -> String {
+    if seconds < 60.0 {
+        format!("{:.1}s", seconds)
+    } else {
+        let whole = seconds.round() as u64;
+        format!("{}m {:02}s", whole / 60, whole % 60)
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs;
+use std::path::Path;
+
+use localdoc::docpack::{Docpack, DocpackKind};
+use log::{info, warn};
+
+use crate::commands::update::Selection;
+use crate::commands::{ingest, pack};
+use crate::error::{LocaldocError, WithPath};
+use crate::fingerprint;
+use crate::graph;
+use crate::progress::Progress;
+use crate::scratch::ScratchDir;
+use crate::search::IndexFormat;
+use crate::seal;
+use crate::size;
+use crate::symbols;
+
+/// Settings for `localdoc index`, one field per command-line flag.
+pub struct IndexOptions<'a> {
+    /// Also build index/graph.json
+    pub graph: bool,
+    /// `None` keeps the format of an existing index, or writes JSON
+    pub format: Option<IndexFormat>,
+    /// Index lockfiles and minified files too
+    pub index_all: bool,
+    /// Replace indexes that already exist
+    pub force: bool,
+    /// Overrides the filters recorded in an existing index when non-empty
+    pub include: &'a [String],
+    pub exclude: &'a [String],
+    pub no_default_excludes: bool,
+    /// `Some(0)` disables the limit; `None` uses the recorded one
+    pub max_file_size: Option<u64>,
+    pub quiet: bool,
+    pub force_mutable: bool,
+}
+
+pub fn run(docpack: &str, opts: &IndexOptions) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+    let kind = Docpack::open_any(docpack_path)?.kind;
+    info!("Indexing .docpack: {}", docpack);
+
+    seal::ensure_mutable(docpack_path, opts.force_mutable)?;
+
+    // Archives are indexed in an extracted copy and rewritten at the end
+    let scratch;
+    let work_dir = if kind == DocpackKind::Archive {
+        info!("Extracting .docpack archive...");
+        scratch = ScratchDir::new("docpack-index-")?;
+        pack::extract_docpack(docpack_path, scratch.path(), false)?;
+        scratch.path().to_path_buf()
+    } else {
+        docpack_path.to_path_buf()
+    };
+
+    let index_dir = work_dir.join("index");
+    let current = IndexFormat::detect(&index_dir);
+    let format = opts.format.or(current).unwrap_or_default();
+    let mut existing: Vec<&str> = current.iter().map(|format| format.file_name()).collect();
+    if opts.graph && index_dir.join("graph.json").exists() {
+        existing.push("graph.json");
+    }
+    if !existing.is_empty() && !opts.force {
+        return Err(format!(
+            "{} already has index/{}; pass --force to rebuild",
+            docpack,
+            existing.join(" and index/")
+        )
+        .into());
+    }
+
+    // Index with the rules the existing index was built with, if any
+    let selection = Selection::resolve(&index_dir, opts.include, opts.exclude, opts.no_default_excludes, opts.max_file_size)?;
+
+    info!("Scanning files/...");
+    let files_dir = work_dir.join("files");
+    let mut progress = Progress::new("Scanning", None, opts.quiet);
+    let plan = if files_dir.is_dir() {
+        ingest::plan_files(&files_dir, &selection.filter, selection.max_size, selection.symlinks, selection.non_utf8, &mut progress)?
+    } else {
+        ingest::SourcePlan::default()
+    };
+    progress.finish();
+    let skipped = plan.filtered + plan.default_excluded.values().sum::<usize>() + plan.oversized.len();
+    if skipped > 0 {
+        info!("  Leaving {} files out of the index (exclude rules and size limit)", skipped);
+    }
+
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    fs::create_dir_all(&index_dir).with_path(&index_dir)?;
+
+    info!("Building search index...");
+    let mut progress = Progress::new("Indexing", Some(plan.files.len() as u64), opts.quiet);
+    let selection_options =
+        ingest::selection_options(&selection.filter, selection.max_size, selection.symlinks, selection.non_utf8);
+    let stats = ingest::build_search_index(
+        &plan.files,
+        &index_dir,
+        selection_options,
+        format,
+        opts.index_all,
+        &timestamp,
+        &mut progress,
+    )?;
+    progress.finish();
+    info!("  {} index/{}", if current == Some(format) { "Rebuilt" } else { "Created" }, format.file_name());
+    if stats.skipped > 0 {
+        info!("  Left out {} lockfiles and minified files (--index-all to include them)", stats.skipped);
+    }
+    info!("  Wrote index/{} ({} symbols)", symbols::FILE_NAME, stats.symbols);
+    // Only one format is kept, so readers never see two disagreeing indexes
+    for other in IndexFormat::ALL.into_iter().filter(|other| *other != format) {
+        let stale = index_dir.join(other.file_name());
+        if stale.exists() {
+            fs::remove_file(&stale).with_path(&stale)?;
+            info!("  Removed index/{}", other.file_name());
+        }
+    }
+    info!("  Fingerprint: {}", fingerprint::describe(&stats.fingerprint));
+
+    if opts.graph {
+        info!("Building semantic graph...");
+        let sources = plan.files.iter().filter(|file| graph::is_source(&file.name)).count();
+        let mut progress = Progress::new("Parsing", Some(sources as u64), opts.quiet);
+        let stats = ingest::build_graph(&plan.files, &index_dir, &timestamp, &mut progress)?;
+        progress.finish();
+        info!("  Created index/graph.json ({} nodes, {} edges from {} source files)", stats.nodes, stats.edges, stats.files);
+        for path in &stats.unparsed {
+            warn!("Could not parse {}; left out of the graph", path);
+        }
+        info!("  Fingerprint: {}", fingerprint::describe(&stats.fingerprint));
+    }
+
+    if kind == DocpackKind::Archive {
+        info!("Rewriting archive...");
+        pack::write_archive(&work_dir, docpack_path)?;
+    }
+
+    let index_file = index_dir.join(format.file_name());
+    let index_size = fs::metadata(&index_file).with_path(&index_file)?.len();
+    info!("\n🔎 Index Summary");
+    info!("{}", "─".repeat(60));
+    info!("Files indexed: {}", stats.files);
+    info!("Unique terms:  {}", stats.terms);
+    info!("Symbols:       {}", stats.symbols);
+    info!("Text indexed:  {}", size::format_size(stats.bytes));
+    info!("Index size:    {}", size::format_size(index_size));
+
+    info!("\n✓ Indexed {}", docpack);
+    Ok(())
+}
@@ -1,319 +1,1204 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::Path;
-use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use log::{debug, info, warn};
 use serde_json::json;
-use zip::write::FileOptions;
-use zip::ZipWriter;
-use zip::CompressionMethod;
+use docpack_core::{ErrorKind, Strictness, TasksFile};
+use localdoc::archive::{self, ArchiveOptions, CompressionMethod, Limits};
 
-pub fn run(
-    source: &str,
-    out: &str,
-    name: Option<&str>,
-    description: Option<&str>,
-    language: Option<&str>,
-    all_tools: bool,
-    build_index: bool,
-    build_graph: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Creating .docpack from source: {}", source);
+use crate::builder::{self, Built, DocpackBuilder};
+use crate::commands::validate;
+use crate::constraints::Constraints;
+use crate::download;
+use crate::error::{LocaldocError, WithPath};
+use crate::filter::{self, Skip, SourceFilter};
+use crate::fingerprint;
+use crate::git;
+use crate::graph;
+use crate::hashes;
+use crate::parallel;
+use crate::progress::Progress;
+use crate::project;
+use crate::scratch::ScratchDir;
+use crate::reader::{DirReader, DocpackReader};
+use crate::search::{self, FilesSnapshot, IndexFormat, SearchIndex, SearchMetadata};
+use crate::size;
+use crate::symbols;
+use crate::tokenize;
+use crate::tools::{self, Tool};
 
-    let source_path = Path::new(source);
+/// `--max-file-size` when none is given.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
 
-    // Validate source exists
-    if !source_path.exists() {
-        return Err(format!("Source path does not exist: {}", source).into());
+/// Settings for `localdoc ingest`, one field per command-line flag.
+pub struct IngestOptions<'a> {
+    pub out: &'a str,
+    pub name: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub language: Option<&'a str>,
+    /// Name of a preset in [`tools::PRESETS`]
+    pub preset: &'a str,
+    /// Tools added on top of the preset
+    pub tools: &'a [String],
+    pub build_index: bool,
+    pub index_format: IndexFormat,
+    /// Index lockfiles and minified files too
+    pub index_all: bool,
+    pub build_graph: bool,
+    pub seed_outputs: Option<&'a str>,
+    pub seed_subdir: bool,
+    pub include: &'a [String],
+    pub exclude: &'a [String],
+    /// Leave out the [`filter::DEFAULT_EXCLUDES`] directories
+    pub default_excludes: bool,
+    /// Files larger than this many bytes are skipped; `None` means no limit
+    pub max_file_size: Option<u64>,
+    /// Warn when the docpack may be larger than this many bytes; `None`
+    /// never warns
+    pub max_docpack_size: Option<u64>,
+    pub symlinks: SymlinkPolicy,
+    pub non_utf8: NonUtf8Policy,
+    /// Record `metadata.git` for a git checkout
+    pub git_metadata: bool,
+    /// Warn about files that look like credentials
+    pub secret_scan: bool,
+    /// Abort instead of warning when the scan finds anything
+    pub fail_on_secrets: bool,
+    pub quiet: bool,
+    /// Print one JSON summary instead of the usual commentary
+    pub json: bool,
+    /// Store identical files once under `blobs/` with a `files.map.json`
+    pub dedupe: bool,
+    /// Produce byte-identical archives for identical inputs
+    pub reproducible: bool,
+    /// Timestamp to record as the creation time instead of now
+    pub created: Option<DateTime<Utc>>,
+    pub compression: CompressionMethod,
+    pub compression_level: Option<i32>,
+    /// Run limits for `environment.constraints`
+    pub constraints: Constraints,
+    /// Expected SHA-256 of a zip or URL source
+    pub sha256: Option<&'a str>,
+    /// tasks.json to embed instead of the generated template
+    pub tasks_file: Option<&'a str>,
+    /// Replaces the mission in tasks.json
+    pub mission: Option<&'a str>,
+    /// Treat problems in the tasks file as errors
+    pub strict: bool,
+    /// `NAME=PATH` pairs mounting a source under `files/NAME/`
+    pub prefixes: &'a [String],
+}
+
+/// A source file that goes into `files/`.
+pub struct SourceFile {
+    /// Path relative to the source root, with `/` separators
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// The source files selected for `files/`, and what was left out. Files are
+/// streamed from their original location when the archive is written.
+#[derive(Default)]
+pub struct SourcePlan {
+    pub files: Vec<SourceFile>,
+    /// Directories under `files/` to record, including empty source dirs
+    pub dirs: BTreeSet<String>,
+    /// Files skipped by --include/--exclude
+    pub filtered: usize,
+    /// Files skipped by the source's .docpackignore
+    pub ignored: usize,
+    /// Files skipped by each default exclude rule
+    pub default_excluded: BTreeMap<&'static str, usize>,
+    /// Files over --max-file-size, as (path relative to the source, size)
+    pub oversized: Vec<(String, u64)>,
+    /// Symlinks left out under `--symlinks skip`
+    pub links_skipped: usize,
+    /// Symlinks that could not be packed: broken, cyclic, or pointing
+    /// outside the source
+    pub link_warnings: Vec<String>,
+    /// Files with non-UTF-8 names, as (lossy path, name in `files/` when
+    /// renamed)
+    pub non_utf8: Vec<(String, Option<String>)>,
+}
+
+impl SourcePlan {
+    /// Add the plan for a source mounted at `files/<mount>/`, or at the
+    /// top of `files/` without a mount.
+    pub(crate) fn absorb(&mut self, plan: SourcePlan, mount: Option<&str>) {
+        let under = |path: &str| match mount {
+            Some(mount) => format!("{}/{}", mount, path),
+            None => path.to_string(),
+        };
+        self.files.extend(plan.files.into_iter().map(|file| SourceFile { name: under(&file.name), ..file }));
+        self.dirs.extend(mount.map(String::from));
+        self.dirs.extend(plan.dirs.iter().map(|dir| under(dir)));
+        self.filtered += plan.filtered;
+        self.ignored += plan.ignored;
+        for (rule, count) in plan.default_excluded {
+            *self.default_excluded.entry(rule).or_default() += count;
+        }
+        self.oversized.extend(plan.oversized.into_iter().map(|(path, size)| (under(&path), size)));
+        self.links_skipped += plan.links_skipped;
+        self.link_warnings.extend(plan.link_warnings.into_iter().map(|warning| match mount {
+            Some(mount) => format!("{}: {}", mount, warning),
+            None => warning,
+        }));
+        self.non_utf8
+            .extend(plan.non_utf8.into_iter().map(|(path, renamed)| (under(&path), renamed.map(|name| under(&name)))));
     }
+}
 
-    // Determine docpack name
-    let docpack_name = name.unwrap_or_else(|| {
-        source_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("untitled")
-    });
+/// What to do with symlinks in the source tree.
+#[derive(Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum SymlinkPolicy {
+    /// Leave them out
+    #[default]
+    Skip,
+    /// Pack what they point to, if it is inside the source
+    Follow,
+    /// Fail the ingest
+    Error,
+}
 
-    // Create a temporary directory for building the docpack
-    let temp_dir = std::env::temp_dir().join(format!("docpack-build-{}", std::process::id()));
-    fs::create_dir_all(&temp_dir)?;
-
-    // Create .docpack directory structure in temp
-    println!("Creating directory structure...");
-    fs::create_dir_all(temp_dir.join("files"))?;
-    fs::create_dir_all(temp_dir.join("index"))?;
-    fs::create_dir_all(temp_dir.join("output"))?;
-
-    // Copy source files to files/
-    println!("Copying source files...");
-    copy_dir_all(source_path, &temp_dir.join("files"))?;
-
-    // Count files for reporting
-    let file_count = count_files(&temp_dir.join("files"))?;
-    println!("  Copied {} files", file_count);
-
-    // Create docpack.json manifest
-    println!("Creating manifest...");
-    let tools = if all_tools {
-        vec![
-            "list_files",
-            "read_file",
-            "read_image",
-            "read_pdf",
-            "search_code",
-            "semantic_search",
-            "query_graph",
-            "write_output",
-        ]
-    } else {
-        vec!["list_files", "read_file", "write_output"]
-    };
+impl SymlinkPolicy {
+    /// Name as given to `--symlinks`, recorded in the index fingerprint.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SymlinkPolicy::Skip => "skip",
+            SymlinkPolicy::Follow => "follow",
+            SymlinkPolicy::Error => "error",
+        }
+    }
 
-    let manifest = json!({
-        "version": "1.0",
-        "name": docpack_name,
-        "description": description.unwrap_or("Generated docpack"),
-        "environment": {
-            "tools": tools,
-            "interpreter": "python3.12",
-            "constraints": {
-                "max_file_reads": 1000,
-                "max_execution_time_seconds": 300,
-                "memory_limit_mb": 2048
-            }
-        },
-        "metadata": {
-            "created": chrono::Utc::now().to_rfc3339(),
-            "creator": "localdoc-cli",
-            "source_type": "directory",
-            "language": language.unwrap_or("unknown")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "skip" => Some(SymlinkPolicy::Skip),
+            "follow" => Some(SymlinkPolicy::Follow),
+            "error" => Some(SymlinkPolicy::Error),
+            _ => None,
         }
-    });
+    }
+}
 
-    let manifest_path = temp_dir.join("docpack.json");
-    let mut manifest_file = fs::File::create(&manifest_path)?;
-    manifest_file.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
-    println!("  Created docpack.json");
+/// What to do with source files whose names are not valid UTF-8.
+#[derive(Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum NonUtf8Policy {
+    /// Leave them out with a warning
+    #[default]
+    Skip,
+    /// Pack them under a lossy UTF-8 name, made unique if needed
+    Rename,
+    /// Fail the ingest
+    Error,
+}
 
-    // Create minimal tasks.json
-    println!("Creating tasks.json...");
-    let tasks = json!({
-        "mission": "Explore and document this project",
-        "tasks": [
-            {
-                "id": "task_1",
-                "name": "Analyze project structure",
-                "description": "Explore the codebase and create a high-level overview",
-                "tools_allowed": tools,
-                "output": {
-                    "type": "markdown",
-                    "path": "output/overview.md"
-                }
-            }
-        ],
-        "constraints": {
-            "chain_of_thought_location": "/workspace/.reasoning",
-            "forbidden_actions": ["modify_files", "execute_code"],
-            "output_format": "markdown"
+impl NonUtf8Policy {
+    /// Name as given to `--non-utf8`, recorded in the index fingerprint.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NonUtf8Policy::Skip => "skip",
+            NonUtf8Policy::Rename => "rename",
+            NonUtf8Policy::Error => "error",
         }
-    });
+    }
 
-    let tasks_path = temp_dir.join("tasks.json");
-    let mut tasks_file = fs::File::create(&tasks_path)?;
-    tasks_file.write_all(serde_json::to_string_pretty(&tasks)?.as_bytes())?;
-    println!("  Created tasks.json");
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "skip" => Some(NonUtf8Policy::Skip),
+            "rename" => Some(NonUtf8Policy::Rename),
+            "error" => Some(NonUtf8Policy::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Where the source files come from.
+#[derive(Clone, Copy, PartialEq)]
+enum SourceKind {
+    Directory,
+    Zip,
+    /// A `.tar`, `.tar.gz` or `.tgz` archive
+    Tar { gzip: bool },
+    /// A zip archive downloaded over HTTP(S)
+    Url,
+}
+
+impl SourceKind {
+    fn detect(source: &str) -> Result<Self, LocaldocError> {
+        if download::is_url(source) {
+            return Ok(SourceKind::Url);
+        }
 
-    // Build index if requested
-    if build_index {
-        println!("Building search index...");
-        build_search_index(&temp_dir.join("files"), &temp_dir.join("index"))?;
-        println!("  Created index/search.json");
+        let path = Path::new(source);
+        if !path.exists() {
+            return Err(LocaldocError::SourceNotFound { what: "Source path", path: path.to_path_buf() });
+        }
+        if !path.is_file() {
+            return Ok(SourceKind::Directory);
+        }
+        let file_name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+        Ok(if file_name.ends_with(".zip") {
+            SourceKind::Zip
+        } else if file_name.ends_with(".tar") {
+            SourceKind::Tar { gzip: false }
+        } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            SourceKind::Tar { gzip: true }
+        } else {
+            SourceKind::Directory
+        })
     }
 
-    // Build graph if requested
-    if build_graph {
-        println!("Building semantic graph...");
-        println!("  Note: Full graph building requires code analysis (coming soon)");
-        create_empty_graph(&temp_dir.join("index"))?;
-        println!("  Created index/graph.json (empty template)");
+    /// Value of `metadata.source_type`.
+    fn as_str(self) -> &'static str {
+        match self {
+            SourceKind::Directory => "directory",
+            SourceKind::Zip => "zip",
+            SourceKind::Tar { .. } => "tar",
+            SourceKind::Url => "url",
+        }
     }
+}
 
-    // Create the zip archive
-    println!("Creating zip archive...");
-    let out_path = Path::new(out);
+pub fn run(specs: &[String], opts: &IngestOptions) -> Result<(), LocaldocError> {
+    let started = Instant::now();
+    info!("Creating .docpack from source: {}", specs.join(", "));
 
-    // Ensure output path has .docpack extension
-    let zip_path = if out.ends_with(".docpack") {
-        out_path.to_path_buf()
-    } else {
-        out_path.with_extension("docpack")
+    let sources = resolve_sources(specs, opts.prefixes)?;
+
+    if opts.sha256.is_some() {
+        match sources.as_slice() {
+            [source] if source.kind == SourceKind::Directory => {
+                return Err(LocaldocError::InvalidArgument("--sha256 only applies to archive and URL sources".to_string()));
+            }
+            [_] => {}
+            _ => return Err(LocaldocError::InvalidArgument("--sha256 only applies to a single archive or URL source".to_string())),
+        }
+    }
+
+    // Determine docpack name
+    let docpack_name = match (opts.name, sources.as_slice()) {
+        (Some(name), _) => name.to_string(),
+        (None, [source]) => source_name(source.spec, source.kind).unwrap_or("untitled").to_string(),
+        (None, _) => sources.iter().filter_map(|source| source.mount.as_deref()).collect::<Vec<_>>().join("+"),
     };
 
-    create_zip_archive(&temp_dir, &zip_path)?;
+    builder::check_compression(opts.compression, opts.compression_level)?;
+    let filter = SourceFilter::new(opts.include, opts.exclude, opts.default_excludes)?;
 
-    // Clean up temp directory
-    fs::remove_dir_all(&temp_dir)?;
+    // Only generated artifacts are written here; source files are streamed
+    // into the archive from where they are. Zip sources are extracted here too.
+    // The directory is removed when this returns, even on error.
+    let temp_dir = ScratchDir::new("docpack-build-")?;
+    let built = build(&sources, &docpack_name, filter, temp_dir.path(), opts)?;
+    let zip_path = &built.path;
 
-    println!("\n✓ Successfully created .docpack archive: {}", zip_path.display());
-    println!("\nNext steps:");
-    println!("  1. Run: localdoc run {}", zip_path.display());
-    println!("  2. The archive will be automatically extracted and processed");
+    if opts.json {
+        let summary = json_summary(&sources, &docpack_name, &built, opts, started.elapsed());
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    info!("\n✓ Successfully created .docpack archive: {}", zip_path.display());
+    info!("\nNext steps:");
+    info!("  1. Run: localdoc run {}", zip_path.display());
+    info!("  2. The archive will be automatically extracted and processed");
 
     Ok(())
 }
 
-fn copy_dir_all(src: &Path, dst: &Path) -> io::Result<()> {
-    if src.is_dir() {
-        for entry in fs::read_dir(src)? {
-            let entry = entry?;
-            let ty = entry.file_type()?;
-            let dst_path = dst.join(entry.file_name());
+/// A source given to `ingest` and where its files go.
+struct Source<'a> {
+    spec: &'a str,
+    kind: SourceKind,
+    /// Directory under `files/` for the source's files; `None` puts a lone
+    /// source at the top of `files/`
+    mount: Option<String>,
+}
+
+/// Detect each source and decide where it is mounted. A lone source fills
+/// `files/` unless it is given a `--prefix`; with several, each goes under
+/// its `--prefix` name or its own name, and two may not share one.
+fn resolve_sources<'a>(specs: &'a [String], prefixes: &[String]) -> Result<Vec<Source<'a>>, LocaldocError> {
+    let same = |spec: &str, path: &str| Path::new(spec) == Path::new(path);
 
-            if ty.is_dir() {
-                fs::create_dir_all(&dst_path)?;
-                copy_dir_all(&entry.path(), &dst_path)?;
-            } else {
-                fs::copy(entry.path(), dst_path)?;
+    let mut named: Vec<(&str, &str)> = Vec::new();
+    for prefix in prefixes {
+        let (name, path) =
+            prefix.split_once('=').ok_or_else(|| LocaldocError::InvalidArgument(format!("Invalid --prefix '{}' (expected NAME=PATH)", prefix)))?;
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+            return Err(LocaldocError::InvalidArgument(format!("Invalid --prefix name '{}': must be a single directory name", name)));
+        }
+        if !specs.iter().any(|spec| same(spec, path)) {
+            return Err(LocaldocError::InvalidArgument(format!("--prefix {} doesn't match any source", prefix)));
+        }
+        named.push((name, path));
+    }
+
+    let mut sources: Vec<Source> = Vec::new();
+    for spec in specs {
+        let kind = SourceKind::detect(spec)?;
+        let mount = match named.iter().find(|(_, path)| same(spec, path)) {
+            Some((name, _)) => Some(name.to_string()),
+            None if specs.len() == 1 => None,
+            None => Some(
+                source_name(spec, kind)
+                    .ok_or_else(|| format!("Can't name source {}; give it a name with --prefix NAME={}", spec, spec))?
+                    .to_string(),
+            ),
+        };
+
+        if let Some(other) = sources.iter().find(|other| mount.is_some() && other.mount == mount) {
+            return Err(format!(
+                "Sources {} and {} would both be mounted at files/{}/; rename one with --prefix NAME=PATH",
+                other.spec,
+                spec,
+                mount.unwrap_or_default()
+            )
+            .into());
+        }
+        sources.push(Source { spec, kind, mount });
+    }
+    Ok(sources)
+}
+
+/// Name of a source: its directory name, or its archive name without the
+/// extension.
+fn source_name(spec: &str, kind: SourceKind) -> Option<&str> {
+    let path = Path::new(spec);
+    match kind {
+        SourceKind::Directory => path.file_name().and_then(|n| n.to_str()),
+        SourceKind::Zip => path.file_stem().and_then(|n| n.to_str()),
+        SourceKind::Tar { .. } => path.file_name().and_then(|n| n.to_str()).map(|name| {
+            let lower = name.to_lowercase();
+            let ext = [".tar.gz", ".tgz", ".tar"].into_iter().find(|ext| lower.ends_with(ext)).unwrap_or("");
+            &name[..name.len() - ext.len()]
+        }),
+        SourceKind::Url => download::file_name(spec).map(|name| name.trim_end_matches(".zip")),
+    }
+}
+
+/// Fetch the sources into `temp_dir` and build the docpack from them with
+/// a [`DocpackBuilder`].
+fn build(
+    sources: &[Source],
+    docpack_name: &str,
+    filter: SourceFilter,
+    temp_dir: &Path,
+    opts: &IngestOptions,
+) -> Result<Built, LocaldocError> {
+    let tools = tools::expand(opts.preset, opts.tools)?;
+    for tool in opts.tools.iter().filter(|tool| Tool::from_name(tool).is_none()) {
+        warn!("Unknown tool '{}' (may not be supported)", tool);
+    }
+
+    // Check a custom tasks file before doing any heavy lifting
+    let custom_tasks = match opts.tasks_file {
+        Some(path) => Some(load_tasks_file(Path::new(path), &tools, opts.strict)?),
+        None => None,
+    };
+
+    // Fetch and unpack archive and URL sources
+    let fetch_started = Instant::now();
+    let mut fetched = Vec::new();
+    for (i, source) in sources.iter().enumerate() {
+        fetched.push(fetch_source(source, &temp_dir.join(format!("source-{}", i)), opts)?);
+    }
+    let fetch_time = fetch_started.elapsed();
+
+    let mut builder = DocpackBuilder::new(docpack_name)
+        .language(opts.language.unwrap_or("unknown"))
+        .tool_names(tools)
+        .constraints(opts.constraints.clone())
+        .filter(filter)
+        .max_file_size(opts.max_file_size)
+        .max_docpack_size(opts.max_docpack_size)
+        .symlinks(opts.symlinks)
+        .non_utf8(opts.non_utf8)
+        .secret_scan(opts.secret_scan)
+        .fail_on_secrets(opts.fail_on_secrets)
+        .build_index(opts.build_index)
+        .index_format(opts.index_format)
+        .index_all(opts.index_all)
+        .build_graph(opts.build_graph)
+        .dedupe(opts.dedupe)
+        .reproducible(opts.reproducible)
+        .compression(opts.compression, opts.compression_level)
+        .quiet(opts.quiet);
+    if let Some(description) = opts.description {
+        builder = builder.description(description);
+    }
+    if let Some(created) = opts.created {
+        builder = builder.created(created);
+    }
+    if let Some(seed_path) = opts.seed_outputs {
+        builder = builder.seed_outputs(seed_path, opts.seed_subdir);
+    }
+    if let Some(tasks) = custom_tasks {
+        builder = builder.tasks_file(tasks);
+    }
+    if let Some(mission) = opts.mission {
+        builder = builder.mission(mission);
+    }
+    for (source, (root, _)) in sources.iter().zip(&fetched) {
+        builder = match &source.mount {
+            Some(mount) => builder.add_dir_at(mount.as_str(), root),
+            None => builder.add_dir(root),
+        };
+    }
+
+    builder = builder
+        .metadata("creator", json!("localdoc-cli"))
+        .metadata("source_type", json!(source_type(sources)))
+        .metadata("tool_preset", json!(opts.preset));
+    match (sources, fetched.as_slice()) {
+        ([source], [(_, sha256)]) => {
+            for (key, value) in source_metadata(source, sha256.as_deref(), opts) {
+                builder = builder.metadata(key, value);
             }
         }
+        _ => {
+            let records = sources
+                .iter()
+                .zip(&fetched)
+                .map(|(source, (_, sha256))| {
+                    let mut record = json!({ "name": source.mount, "path": source.spec, "type": source.kind.as_str() });
+                    for (key, value) in source_metadata(source, sha256.as_deref(), opts) {
+                        record[key] = value;
+                    }
+                    record
+                })
+                .collect();
+            builder = builder.metadata("sources", records);
+        }
+    }
+
+    // Ensure output path has .docpack extension
+    let out_path = Path::new(opts.out);
+    let zip_path = if opts.out.ends_with(".docpack") {
+        out_path.to_path_buf()
     } else {
-        // Source is a single file
-        fs::copy(src, dst)?;
+        out_path.with_extension("docpack")
+    };
+
+    let mut built = builder.write_archive_in(temp_dir, &zip_path)?;
+    if sources.iter().any(|source| source.kind != SourceKind::Directory) {
+        built.phases.insert(0, ("fetch", fetch_time));
     }
-    Ok(())
+    Ok(built)
+}
+
+/// Resolve `source` to a directory to scan, downloading and unpacking it
+/// in `work_dir` if need be. Archive and URL sources also return their
+/// SHA-256, checked against `--sha256` when given.
+fn fetch_source(
+    source: &Source,
+    work_dir: &Path,
+    opts: &IngestOptions,
+) -> Result<(PathBuf, Option<String>), LocaldocError> {
+    let source_path = Path::new(source.spec);
+    if source.kind == SourceKind::Directory {
+        return Ok((source_path.to_path_buf(), None));
+    }
+    fs::create_dir_all(work_dir).with_path(work_dir)?;
+
+    let archive_path = if source.kind == SourceKind::Url {
+        info!("Downloading {}...", source.spec);
+        let download = work_dir.join("download.zip");
+        let bytes = download::fetch(source.spec, &download, opts.quiet)?;
+        info!("  Downloaded {}", size::format_size(bytes));
+        let mut magic = [0u8; 4];
+        let is_zip = fs::File::open(&download).with_path(&download)?.read_exact(&mut magic).is_ok() && magic == *b"PK\x03\x04";
+        if !is_zip {
+            return Err(format!("Downloaded file is not a zip archive: {}", source.spec).into());
+        }
+        download
+    } else {
+        source_path.to_path_buf()
+    };
+
+    let digest = hashes::hash_reader(fs::File::open(&archive_path).with_path(&archive_path)?)?.sha256;
+    if let Some(expected) = opts.sha256 {
+        if !digest.eq_ignore_ascii_case(expected.trim()) {
+            return Err(format!(
+                "Checksum mismatch for {}\n  expected: {}\n  actual:   {}",
+                source.spec,
+                expected.trim().to_lowercase(),
+                digest
+            )
+            .into());
+        }
+        info!("  ✓ SHA-256 matches");
+    }
+
+    info!("Extracting source archive...");
+    let extract_dir = work_dir.join("source-archive");
+    match source.kind {
+        SourceKind::Tar { gzip } => extract_source_tar(&archive_path, gzip, &extract_dir)?,
+        _ => extract_source_zip(&archive_path, &extract_dir)?,
+    }
+    Ok((source_root(&extract_dir)?, Some(digest)))
 }
 
-fn count_files(dir: &Path) -> io::Result<usize> {
-    let mut count = 0;
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            if entry.file_type()?.is_dir() {
-                count += count_files(&entry.path())?;
-            } else {
-                count += 1;
+/// Where a source came from, as manifest metadata keys: the URL or archive
+/// name and checksum, or git provenance for a directory.
+fn source_metadata(source: &Source, sha256: Option<&str>, opts: &IngestOptions) -> Vec<(&'static str, serde_json::Value)> {
+    let mut metadata = Vec::new();
+    match source.kind {
+        SourceKind::Directory if opts.git_metadata => {
+            if let Some(provenance) = git::provenance(Path::new(source.spec)) {
+                info!("  Recorded git commit {}", git::describe(&provenance));
+                metadata.push(("git", provenance));
             }
         }
+        SourceKind::Directory => {}
+        SourceKind::Url => metadata.push(("source_url", json!(source.spec))),
+        SourceKind::Zip | SourceKind::Tar { .. } => {
+            let archive_name = Path::new(source.spec).file_name().map(|n| n.to_string_lossy().into_owned());
+            metadata.push(("source_archive", json!(archive_name)));
+        }
+    }
+    if let Some(digest) = sha256 {
+        metadata.push(("source_sha256", json!(digest)));
     }
-    Ok(count)
+    metadata
 }
 
-fn build_search_index(files_dir: &Path, index_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    use std::collections::HashMap;
+/// Value of `metadata.source_type`: the lone source's type, or `multiple`.
+fn source_type(sources: &[Source]) -> &'static str {
+    match sources {
+        [source] => source.kind.as_str(),
+        _ => "multiple",
+    }
+}
 
-    let mut index: HashMap<String, Vec<String>> = HashMap::new();
+/// The `--json` summary of a finished ingest.
+fn json_summary(
+    sources: &[Source],
+    name: &str,
+    built: &Built,
+    opts: &IngestOptions,
+    elapsed: Duration,
+) -> serde_json::Value {
+    let plan = &built.plan;
 
-    // Simple word-based indexing
-    for entry in walkdir::WalkDir::new(files_dir) {
-        let entry = entry?;
-        if entry.file_type().is_file() {
-            let path = entry.path();
+    let mut languages: BTreeMap<&str, (usize, u64)> = BTreeMap::new();
+    for file in &plan.files {
+        let entry = languages.entry(project::language(&file.name).unwrap_or("Other")).or_default();
+        entry.0 += 1;
+        entry.1 += file.size;
+    }
+    let phases: serde_json::Map<String, serde_json::Value> = built
+        .phases
+        .iter()
+        .map(|(phase, time)| (phase.to_string(), json!(time.as_secs_f64())))
+        .collect();
 
-            // Only index text files
-            if let Ok(content) = fs::read_to_string(path) {
-                let rel_path = path.strip_prefix(files_dir)?;
+    json!({
+        "output": built.path.display().to_string(),
+        "name": name,
+        "source": sources.iter().map(|source| source.spec).collect::<Vec<_>>().join(", "),
+        "source_type": source_type(sources),
+        "sources": sources
+            .iter()
+            .map(|source| json!({ "name": source.mount, "path": source.spec, "type": source.kind.as_str() }))
+            .collect::<Vec<_>>(),
+        "files": plan.files.len(),
+        "total_bytes": plan.files.iter().map(|f| f.size).sum::<u64>(),
+        "skipped": {
+            "filtered": plan.filtered,
+            "ignored": plan.ignored,
+            "default_excludes": plan.default_excluded,
+            "oversized": plan.oversized.iter().map(|(path, size)| json!({ "path": path, "size": size })).collect::<Vec<_>>(),
+            "symlinks": plan.links_skipped,
+            "non_utf8": plan.non_utf8.iter().filter(|(_, renamed)| renamed.is_none()).count(),
+        },
+        "languages": languages
+            .into_iter()
+            .map(|(language, (files, bytes))| (language.to_string(), json!({ "files": files, "bytes": bytes })))
+            .collect::<serde_json::Map<_, _>>(),
+        "secrets": built
+            .secrets
+            .iter()
+            .map(|finding| json!({ "path": finding.path, "line": finding.line, "kind": finding.kind }))
+            .collect::<Vec<_>>(),
+        "index_built": opts.build_index,
+        "index_format": opts.build_index.then(|| opts.index_format.as_str()),
+        "graph_built": opts.build_graph,
+        "phases": phases,
+        "elapsed_seconds": elapsed.as_secs_f64(),
+    })
+}
 
-                // Extract words and add to index
-                for word in content.split_whitespace() {
-                    let word_clean = word.to_lowercase()
-                        .trim_matches(|c: char| !c.is_alphanumeric())
-                        .to_string();
+/// Read and check a `--tasks-file`.
+/// Schema violations fail ingest; other problems are printed as warnings,
+/// or fail ingest with `strict`.
+fn load_tasks_file(
+    path: &Path,
+    tools: &[String],
+    strict: bool,
+) -> Result<TasksFile, LocaldocError> {
+    let invalid = |kind| LocaldocError::InvalidTasks {
+        path: path.to_path_buf(),
+        source: docpack_core::Error { file: format!("tasks file {}", path.display()), field: None, kind },
+    };
+    let content = fs::read_to_string(path).map_err(|e| invalid(ErrorKind::Read(e)))?;
+    let tasks: serde_json::Value = serde_json::from_str(&content).map_err(|e| invalid(ErrorKind::Syntax(e)))?;
 
-                    if word_clean.len() > 2 {
-                        index.entry(word_clean)
-                            .or_insert_with(Vec::new)
-                            .push(format!("{}:1", rel_path.display()));
+    let (mut errors, mut warnings) = (Vec::new(), Vec::new());
+    validate::validate_tasks(&tasks, Some(tools), &mut errors, &mut warnings);
+
+    // A tasks file that breaks the schema would fail validation of the docpack
+    if !errors.is_empty() {
+        let listed: Vec<String> = errors.iter().map(|e| format!("  • {}", e)).collect();
+        return Err(invalid(ErrorKind::Invalid(format!("{} error(s):\n{}", errors.len(), listed.join("\n")))));
+    }
+    // Anything the schema accepts loads
+    let typed = TasksFile::from_value(&tasks, &path.display().to_string(), Strictness::Lenient)
+        .map_err(|source| LocaldocError::InvalidTasks { path: path.to_path_buf(), source })?;
+    if warnings.is_empty() {
+        return Ok(typed);
+    }
+    let listed: Vec<String> = warnings.iter().map(|w| format!("  • {}", w)).collect();
+    if strict {
+        return Err(invalid(ErrorKind::Invalid(format!("{} problem(s) (--strict):\n{}", warnings.len(), listed.join("\n")))));
+    }
+    warn!("Tasks file warnings ({}):\n{}", warnings.len(), listed.join("\n"));
+    Ok(typed)
+}
+
+/// Archive settings recorded in `metadata.compression`, for rewriting an
+/// archive the way it was first built.
+pub fn recorded_compression(manifest: &serde_json::Value) -> ArchiveOptions {
+    let compression = &manifest["metadata"]["compression"];
+    ArchiveOptions {
+        compression: match compression["method"].as_str() {
+            Some("none") => CompressionMethod::Stored,
+            Some("zstd") => CompressionMethod::Zstd,
+            _ => CompressionMethod::Deflated,
+        },
+        compression_level: compression["level"].as_i64().map(|level| level as i32),
+        ..ArchiveOptions::default()
+    }
+}
+
+/// Select the files under `src` that pass `filter` and fit under
+/// `max_size`, treating symlinks and non-UTF-8 names according to
+/// `symlinks` and `non_utf8`. A single-file source is placed in `files/`
+/// by name.
+pub fn plan_files(
+    src: &Path,
+    filter: &SourceFilter,
+    max_size: Option<u64>,
+    symlinks: SymlinkPolicy,
+    non_utf8: NonUtf8Policy,
+    progress: &mut Progress,
+) -> Result<SourcePlan, LocaldocError> {
+    let mut plan = SourcePlan::default();
+
+    if !src.is_dir() {
+        let name = src.file_name().ok_or("Source has no file name")?;
+        let size = fs::metadata(src).with_path(src)?.len();
+        let name = name.to_string_lossy().into_owned();
+        if filter.check(Path::new(&name), false).is_some() {
+            plan.filtered += 1;
+        } else if max_size.is_some_and(|limit| size > limit) {
+            plan.oversized.push((name, size));
+        } else {
+            progress.inc(size);
+            plan.files.push(SourceFile { name, path: src.to_path_buf(), size });
+        }
+        return Ok(plan);
+    }
+
+    // Followed links must resolve inside the source root
+    let real_root = src.canonicalize()?;
+    // Indexes into `plan.files` of files to rename
+    let mut renames = Vec::new();
+
+    let mut walker = walkdir::WalkDir::new(src)
+        .min_depth(1)
+        .follow_links(symlinks == SymlinkPolicy::Follow)
+        .sort_by_file_name()
+        .into_iter();
+    while let Some(entry) = walker.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let path = e.path().map(|p| p.strip_prefix(src).unwrap_or(p).display().to_string()).unwrap_or_default();
+                if e.loop_ancestor().is_some() {
+                    plan.link_warnings.push(format!("Skipped symlink {}: it loops back to a parent directory", path));
+                    continue;
+                }
+                // Following a link that points nowhere
+                if e.io_error().is_some_and(|io| io.kind() == io::ErrorKind::NotFound) && symlinks == SymlinkPolicy::Follow {
+                    plan.link_warnings.push(format!("Skipped broken symlink {}", path));
+                    continue;
+                }
+                return Err(e.into());
+            }
+        };
+        let rel = entry.path().strip_prefix(src)?;
+
+        if entry.path_is_symlink() {
+            let target = fs::canonicalize(entry.path());
+            let shown = rel.display();
+            match (symlinks, target) {
+                (_, Err(_)) => {
+                    plan.link_warnings.push(format!("Skipped broken symlink {}", shown));
+                    continue;
+                }
+                (SymlinkPolicy::Skip, Ok(_)) => {
+                    debug!("Skipped symlink {}", shown);
+                    plan.links_skipped += 1;
+                    continue;
+                }
+                (SymlinkPolicy::Error, Ok(_)) => {
+                    return Err(format!(
+                        "Source contains a symlink: {}\nUse --symlinks skip to leave symlinks out or --symlinks follow to pack their targets",
+                        shown
+                    )
+                    .into());
+                }
+                (SymlinkPolicy::Follow, Ok(target)) if !target.starts_with(&real_root) => {
+                    plan.link_warnings.push(format!(
+                        "Refused symlink {} -> {}: it points outside the source",
+                        shown,
+                        target.display()
+                    ));
+                    if entry.file_type().is_dir() {
+                        walker.skip_current_dir();
                     }
+                    continue;
                 }
+                (SymlinkPolicy::Follow, Ok(_)) => {}
             }
         }
-    }
 
-    // Deduplicate entries
-    for entries in index.values_mut() {
-        entries.sort();
-        entries.dedup();
-    }
+        let name = archive::path_to_entry_name(rel);
+        let valid_name = rel.to_str().is_some();
 
-    let search_index = json!({
-        "index": index,
-        "metadata": {
-            "total_files": count_files(files_dir)?,
-            "indexed_at": chrono::Utc::now().to_rfc3339()
+        if entry.file_type().is_dir() {
+            // Directories are recorded as files land in them, so filtering
+            // doesn't leave empty ones behind; empty source dirs are kept
+            if fs::read_dir(entry.path()).with_path(entry.path())?.next().is_none()
+                && filter.check(rel, true).is_none()
+                && (valid_name || non_utf8 == NonUtf8Policy::Rename)
+            {
+                plan.dirs.insert(name);
+            }
+        } else if rel == Path::new(filter::IGNORE_FILE) {
+            // Build configuration, not part of the project
+        } else if let Some(skip) = filter.check(rel, false) {
+            debug!("Skipped {}: {}", name, skip);
+            match skip {
+                Skip::Excluded | Skip::NotIncluded => plan.filtered += 1,
+                Skip::Ignored => plan.ignored += 1,
+                Skip::Default(rule) => *plan.default_excluded.entry(rule).or_default() += 1,
+            }
+        } else {
+            if !valid_name {
+                match non_utf8 {
+                    NonUtf8Policy::Error => {
+                        return Err(format!(
+                            "Invalid UTF-8 in path: {:?}\nUse --non-utf8 skip to leave such files out or --non-utf8 rename to pack them under a lossy name",
+                            entry.path()
+                        )
+                        .into())
+                    }
+                    NonUtf8Policy::Skip => {
+                        plan.non_utf8.push((name, None));
+                        continue;
+                    }
+                    // Named once every valid name is known, below
+                    NonUtf8Policy::Rename => renames.push(plan.files.len()),
+                }
+            }
+            let size = entry.metadata()?.len();
+            if max_size.is_some_and(|limit| size > limit) {
+                if !valid_name {
+                    renames.pop();
+                }
+                plan.oversized.push((name, size));
+                continue;
+            }
+
+            let mut parent = Path::new(&name).parent();
+            while let Some(dir) = parent.filter(|dir| !dir.as_os_str().is_empty()) {
+                plan.dirs.insert(archive::path_to_entry_name(dir));
+                parent = dir.parent();
+            }
+            progress.inc(size);
+            plan.files.push(SourceFile { name, path: entry.path().to_path_buf(), size });
         }
-    });
+    }
 
-    let search_path = index_dir.join("search.json");
-    let mut search_file = fs::File::create(&search_path)?;
-    search_file.write_all(serde_json::to_string_pretty(&search_index)?.as_bytes())?;
+    // Lossy names can collide with real ones or with each other
+    let mut taken: BTreeSet<String> = plan
+        .files
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !renames.contains(i))
+        .map(|(_, file)| file.name.clone())
+        .collect();
+    taken.extend(plan.dirs.iter().cloned());
+    for &i in &renames {
+        let file = &mut plan.files[i];
+        let lossy = file.name.clone();
+        file.name = unique_name(&lossy, &taken);
+        taken.insert(file.name.clone());
+        plan.non_utf8.push((lossy, Some(file.name.clone())));
+    }
 
-    Ok(())
+    Ok(plan)
 }
 
-fn create_empty_graph(index_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let graph = json!({
-        "nodes": [],
-        "edges": [],
-        "metadata": {
-            "created": chrono::Utc::now().to_rfc3339(),
-            "note": "Empty graph template - populate with code analysis"
-        }
-    });
+/// Report files with non-UTF-8 names in the ingest summary.
+pub fn print_non_utf8(non_utf8: &[(String, Option<String>)]) {
+    if non_utf8.is_empty() {
+        return;
+    }
+    let listed: Vec<String> = non_utf8
+        .iter()
+        .map(|(lossy, renamed)| match renamed {
+            Some(name) => format!("  • {} (packed as {})", lossy, name),
+            None => format!("  • {} (skipped)", lossy),
+        })
+        .collect();
+    warn!("{} files have names that are not valid UTF-8:\n{}", non_utf8.len(), listed.join("\n"));
+}
 
-    let graph_path = index_dir.join("graph.json");
-    let mut graph_file = fs::File::create(&graph_path)?;
-    graph_file.write_all(serde_json::to_string_pretty(&graph)?.as_bytes())?;
+/// The `metadata.non_utf8` record for files with non-UTF-8 names.
+pub fn non_utf8_metadata(non_utf8: &[(String, Option<String>)], policy: NonUtf8Policy) -> serde_json::Value {
+    json!({
+        "policy": policy.as_str(),
+        "count": non_utf8.len(),
+        "files": non_utf8
+            .iter()
+            .map(|(lossy, renamed)| json!({ "path": lossy, "renamed": renamed }))
+            .collect::<Vec<_>>(),
+    })
+}
 
+/// `name`, or `name` with a `~N` suffix before its extension, whichever is
+/// first not in `taken`.
+fn unique_name(name: &str, taken: &BTreeSet<String>) -> String {
+    if !taken.contains(name) {
+        return name.to_string();
+    }
+    let (dir, file) = match name.rsplit_once('/') {
+        Some((dir, file)) => (format!("{}/", dir), file),
+        None => (String::new(), name),
+    };
+    let (stem, ext) = match file.rfind('.') {
+        Some(dot) if dot > 0 => file.split_at(dot),
+        _ => (file, ""),
+    };
+    (1..)
+        .map(|n| format!("{}{}~{}{}", dir, stem, n, ext))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap_or_default()
+}
+
+/// Extract a source zip into `extract_dir`.
+fn extract_source_zip(zip_path: &Path, extract_dir: &Path) -> Result<(), LocaldocError> {
+    fs::create_dir_all(extract_dir).with_path(extract_dir)?;
+    crate::commands::pack::extract_zip(zip_path, extract_dir)?;
     Ok(())
 }
 
-fn create_zip_archive(source_dir: &Path, zip_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let file = fs::File::create(zip_path)?;
-    let mut zip = ZipWriter::new(file);
-    let options = FileOptions::default()
-        .compression_method(CompressionMethod::Deflated)
-        .unix_permissions(0o644);
+/// Extract a source tarball into `extract_dir`. Only regular files and
+/// directories are unpacked; links and device nodes are skipped with a
+/// warning, and entries that would land outside `extract_dir` are an error.
+fn extract_source_tar(tar_path: &Path, gzip: bool, extract_dir: &Path) -> Result<(), LocaldocError> {
+    fs::create_dir_all(extract_dir).with_path(extract_dir)?;
+
+    let file = BufReader::new(fs::File::open(tar_path).with_path(tar_path)?);
+    let reader: Box<dyn Read> = if gzip { Box::new(GzDecoder::new(file)) } else { Box::new(file) };
+    let mut tarball = tar::Archive::new(reader);
 
-    // Configure walkdir to not follow symlinks
-    let walkdir = walkdir::WalkDir::new(source_dir)
-        .follow_links(false);
+    let limits = Limits::default();
+    let mut entries = 0usize;
+    let mut total_size = 0u64;
+    let mut skipped = Vec::new();
 
-    for entry in walkdir {
-        let entry = entry?;
-        let path = entry.path();
+    for entry in tarball
+        .entries()
+        .map_err(|e| format!("Failed to read tar archive {}: {}", tar_path.display(), e))?
+    {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar archive {}: {}", tar_path.display(), e))?;
+        let path = entry.path()?.into_owned();
+        let name = path.to_string_lossy().into_owned();
 
-        // Skip symlinks entirely
-        if path.is_symlink() {
+        let mut target = extract_dir.to_path_buf();
+        for component in path.components() {
+            match component {
+                Component::Normal(part) => target.push(part),
+                Component::CurDir => {}
+                _ => return Err(format!("Refusing to extract unsafe path from tar archive: {}", name).into()),
+            }
+        }
+
+        let kind = entry.header().entry_type();
+        if kind.is_dir() {
+            fs::create_dir_all(&target).with_path(&target)?;
+            continue;
+        }
+        if !kind.is_file() {
+            // Global pax headers carry no content of their own
+            if !kind.is_pax_global_extensions() {
+                skipped.push(name);
+            }
             continue;
         }
 
-        let name = path.strip_prefix(source_dir)?;
+        entries += 1;
+        total_size += entry.size();
+        if entries > limits.max_entries || entry.size() > limits.max_entry_size || total_size > limits.max_total_size {
+            return Err(format!(
+                "Tar archive {} exceeds extraction limits ({} entries, {})",
+                tar_path.display(),
+                limits.max_entries,
+                size::format_size(limits.max_total_size)
+            )
+            .into());
+        }
 
-        // Skip the root directory itself
-        if name.as_os_str().is_empty() {
-            continue;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
         }
+        let mut out = fs::File::create(&target).with_path(&target)?;
+        io::copy(&mut entry, &mut out)?;
 
-        // Convert path to string for zip entry name
-        let name_str = name.to_str().ok_or("Invalid UTF-8 in path")?.to_string();
-
-        if path.is_file() {
-            // Add file
-            zip.start_file(&name_str, options)?;
-            let mut f = fs::File::open(path)
-                .map_err(|e| format!("Failed to open file {:?}: {}", path, e))?;
-            io::copy(&mut f, &mut zip)
-                .map_err(|e| format!("Failed to copy file {:?}: {}", path, e))?;
-        } else if path.is_dir() {
-            // Add directory
-            let dir_name = if name_str.ends_with('/') {
-                name_str
-            } else {
-                format!("{}/", name_str)
-            };
-            zip.add_directory(&dir_name, options)?;
+        #[cfg(unix)]
+        if let Ok(mode) = entry.header().mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&target, fs::Permissions::from_mode(mode & 0o777)).with_path(&target)?;
         }
     }
 
-    zip.finish()?;
+    if !skipped.is_empty() {
+        let listed: Vec<String> = skipped.iter().map(|name| format!("  • {}", name)).collect();
+        warn!("Skipped {} links and special files in the tar archive:\n{}", skipped.len(), listed.join("\n"));
+    }
     Ok(())
 }
+
+/// The root of an extracted source archive. When everything sits under one
+/// top-level directory (as GitHub downloads do), that directory is the root
+/// so paths don't gain an extra level.
+fn source_root(extract_dir: &Path) -> Result<PathBuf, LocaldocError> {
+    // Resource forks added by macOS's archiver are not part of the project
+    let macos_meta = extract_dir.join("__MACOSX");
+    if macos_meta.is_dir() {
+        fs::remove_dir_all(&macos_meta).with_path(&macos_meta)?;
+    }
+
+    let top_level: Vec<fs::DirEntry> = fs::read_dir(extract_dir).with_path(extract_dir)?.collect::<Result<_, _>>()?;
+    match top_level.as_slice() {
+        [only] if only.file_type()?.is_dir() => {
+            info!("  Flattening top-level directory: {}/", only.file_name().to_string_lossy());
+            Ok(only.path())
+        }
+        _ => Ok(extract_dir.to_path_buf()),
+    }
+}
+
+/// What went into the search index.
+pub struct IndexStats {
+    pub fingerprint: serde_json::Value,
+    /// Text files indexed
+    pub files: usize,
+    pub terms: usize,
+    /// Distinct names in index/symbols.json
+    pub symbols: usize,
+    /// Bytes of text indexed
+    pub bytes: u64,
+    /// Lockfiles and minified files left out
+    pub skipped: usize,
+}
+
+/// The file selection settings recorded in a search index fingerprint.
+pub fn selection_options(
+    filter: &SourceFilter,
+    max_file_size: Option<u64>,
+    symlinks: SymlinkPolicy,
+    non_utf8: NonUtf8Policy,
+) -> serde_json::Value {
+    json!({
+        "filters": filter.to_json(),
+        "max_file_size": max_file_size,
+        "symlinks": symlinks.as_str(),
+        "non_utf8": non_utf8.as_str(),
+    })
+}
+
+/// Snapshot of the `files/` directory of the docpack at `root`, recorded
+/// in the search index to tell later when it has gone stale.
+pub fn files_snapshot(root: &Path) -> FilesSnapshot {
+    FilesSnapshot::of(&DirReader::new(root).list_files("files"))
+}
+
+/// Snapshot of the `files/` that `files` are indexed from: the directory
+/// next to `index_dir`, which may hold files left out of the index, or
+/// `files` themselves while ingest has yet to write it.
+fn tree_snapshot(files: &[SourceFile], index_dir: &Path) -> FilesSnapshot {
+    match index_dir.parent().filter(|root| root.join("files").is_dir()) {
+        Some(root) => files_snapshot(root),
+        None => FilesSnapshot::of(&files.iter().map(|file| (file.name.clone(), file.size)).collect::<Vec<_>>()),
+    }
+}
+
+/// Write the search index in `format`, and index/symbols.json from the
+/// same files. `selection` (see
+/// [`selection_options`]) has already been applied to `files` and is only
+/// recorded in the fingerprint. Lockfiles and minified files are left out
+/// (see [`search::skip_reason`]) unless `index_all` is set.
+pub fn build_search_index(
+    files: &[SourceFile],
+    index_dir: &Path,
+    selection: serde_json::Value,
+    format: IndexFormat,
+    index_all: bool,
+    indexed_at: &str,
+    progress: &mut Progress,
+) -> Result<IndexStats, LocaldocError> {
+    let mut options = tokenize::options();
+    if let (Some(options), Some(selection)) = (options.as_object_mut(), selection.as_object()) {
+        options.extend(selection.clone());
+    }
+    options["max_postings"] = json!(search::MAX_POSTINGS);
+    options["skip_generated"] = json!(!index_all);
+    options["max_indexed_bytes"] = json!(search::MAX_INDEXED_BYTES);
+    let fingerprint = fingerprint::fingerprint(
+        fingerprint::SEARCH_FORMAT_VERSION,
+        tokenize::ANALYZER,
+        tokenize::ANALYZER_VERSION,
+        options,
+    );
+
+    let mut index = SearchIndex::new(SearchMetadata {
+        total_files: files.len(),
+        indexed_at: indexed_at.to_string(),
+        updated_at: None,
+        skipped: BTreeMap::new(),
+        capped: BTreeSet::new(),
+        files: Some(tree_snapshot(files, index_dir)),
+        fingerprint: fingerprint.clone(),
+    });
+
+    // Files are read, tokenized and scanned for definitions in parallel and
+    // merged here as they finish, so only the files in flight are held in
+    // memory; only text files are indexed, up to MAX_INDEXED_BYTES of each
+    let (mut indexed, mut bytes) = (0, 0);
+    let mut symbols = symbols::Symbols::new();
+    parallel::for_each_result(
+        files,
+        |file| {
+            let postings = search::read_indexed_text(&file.path).map(|(content, capped)| {
+                match search::skip_reason(&file.name, &content).filter(|_| !index_all) {
+                    Some(reason) => Err(reason),
+                    None => Ok((search::file_postings(&content), symbols::extract(&file.name, &content), capped)),
+                }
+            });
+            Ok::<_, std::convert::Infallible>((file, postings))
+        },
+        |(file, postings)| {
+            progress.inc(file.size);
+            match postings {
+                Some(Ok((postings, definitions, capped))) => {
+                    index.add_file(&file.name, postings);
+                    if capped {
+                        index.metadata.capped.insert(file.name.clone());
+                    }
+                    symbols::add(&mut symbols, definitions);
+                    indexed += 1;
+                    bytes += file.size;
+                }
+                Some(Err(reason)) => {
+                    index.metadata.skipped.insert(file.name.clone(), reason.to_string());
+                }
+                None => {}
+            }
+        },
+    )?;
+    index.finish();
+
+    index.write(&index_dir.join(format.file_name()), format)?;
+    let symbols_path = index_dir.join(symbols::FILE_NAME);
+    let mut out = BufWriter::new(fs::File::create(&symbols_path).with_path(&symbols_path)?);
+    serde_json::to_writer_pretty(&mut out, &symbols)?;
+    out.flush()?;
+
+    Ok(IndexStats {
+        fingerprint,
+        files: indexed,
+        terms: index.index.len(),
+        symbols: symbols.len(),
+        bytes,
+        skipped: index.metadata.skipped.len(),
+    })
+}
+
+/// What went into index/graph.json.
+pub struct GraphStats {
+    pub fingerprint: serde_json::Value,
+    /// Source files read
+    pub files: usize,
+    pub nodes: usize,
+    pub edges: usize,
+    /// Rust files syn could not parse
+    pub unparsed: Vec<String>,
+}
+
+/// Write index/graph.json from the Rust, Python and JavaScript/TypeScript
+/// files among `files`; other files are only import targets.
+pub fn build_graph(
+    files: &[SourceFile],
+    index_dir: &Path,
+    created: &str,
+    progress: &mut Progress,
+) -> Result<GraphStats, LocaldocError> {
+    let fingerprint =
+        fingerprint::fingerprint(fingerprint::GRAPH_FORMAT_VERSION, graph::ANALYZER, graph::ANALYZER_VERSION, json!({}));
+
+    let sources: Vec<&SourceFile> = files.iter().filter(|file| graph::is_source(&file.name)).collect();
+    let (mut parsed, mut unparsed) = (Vec::new(), Vec::new());
+    parallel::for_each_result(
+        &sources,
+        |file| {
+            let content = fs::read_to_string(&file.path).ok();
+            Ok::<_, std::convert::Infallible>((*file, content.and_then(|c| graph::parse_file(&file.name, &c))))
+        },
+        |(file, result)| {
+            progress.inc(file.size);
+            match result {
+                Some(file_graph) => parsed.push(file_graph),
+                None => unparsed.push(file.name.clone()),
+            }
+        },
+    )?;
+    let known: BTreeSet<String> = files.iter().map(|file| file.name.clone()).collect();
+    let files = parsed.len();
+    unparsed.sort();
+    let graph = graph::link(parsed, &known, unparsed);
+
+    let document = json!({
+        "nodes": graph.nodes,
+        "edges": graph.edges,
+        "metadata": {
+            "created": created,
+            "files": files,
+            "unparsed": graph.unparsed,
+            "fingerprint": fingerprint,
+        }
+    });
+    let graph_path = index_dir.join("graph.json");
+    fs::write(&graph_path, serde_json::to_string_pretty(&document)?).with_path(&graph_path)?;
+
+    Ok(GraphStats { fingerprint, files, nodes: graph.nodes.len(), edges: graph.edges.len(), unparsed: graph.unparsed })
+}
@@ -1,222 +1,690 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
 use std::fs;
 use std::path::Path;
+use docpack_core::{Constraints, Manifest, TasksFile, TASKS_FILE};
+use log::{info, warn};
 use serde_json::Value;
 
-pub fn run(docpack: &str, verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
+use crate::embeddings;
+use crate::error::LocaldocError;
+use crate::fingerprint;
+use crate::plugins::{self, Section};
+use crate::reader::{self, DocpackReader};
+use crate::search::IndexFormat;
+use crate::size::format_size;
+use crate::tools::Tool;
+use crate::versions;
+
+/// Extensions listed in the size breakdown; the rest share one row.
+const MAX_BREAKDOWN_ROWS: usize = 15;
+
+/// Everything `inspect` reports about a docpack, read once and then
+/// printed, written as Markdown, or both.
+struct Inspection {
+    manifest: Manifest,
+    /// Files under `files/` with their sizes; `None` without `files/`
+    files: Option<Vec<(String, u64)>>,
+    /// `None` without `index/`
+    index: Option<IndexStatus>,
+    tasks: Option<TasksFile>,
+    outputs: Vec<(String, u64)>,
+    /// Sections from each plugin, or why it failed, by plugin name
+    plugins: Vec<(String, Result<Vec<Section>, String>)>,
+}
+
+/// What `index/` holds. Artifacts that exist but can't be read are
+/// `Some(None)`.
+struct IndexStatus {
+    search: Option<IndexFormat>,
+    /// Why the search index no longer matches `files/`
+    stale: Option<String>,
+    /// (nodes, edges)
+    graph: Option<Option<(usize, usize)>>,
+    embeddings: Option<Option<embeddings::Header>>,
+    model: Option<String>,
+    fingerprints: Vec<(String, Value)>,
+}
+
+pub fn run(
+    docpack: &str,
+    verbose: bool,
+    top: usize,
+    no_plugins: bool,
+    report: Option<&str>,
+    quiet: bool,
+) -> Result<(), LocaldocError> {
     let docpack_path = Path::new(docpack);
 
-    // Validate docpack exists
-    if !docpack_path.exists() {
-        return Err(format!("Docpack does not exist: {}", docpack).into());
+    // Archives are read in place, without extracting them
+    let mut reader = reader::open(docpack_path)?;
+    let inspection = gather(reader.as_mut(), docpack_path, no_plugins)?;
+
+    if !quiet {
+        print_inspection(docpack, &inspection, verbose, top);
+    }
+    if let Some(path) = report {
+        fs::write(path, markdown_report(docpack, &inspection, top))
+            .map_err(|e| format!("Cannot write report to {}: {}", path, e))?;
+        if !quiet {
+            info!("📝 Wrote Markdown report to {}", path);
+        }
     }
+    Ok(())
+}
 
-    println!("Inspecting .docpack: {}\n", docpack);
+fn gather(
+    reader: &mut dyn DocpackReader,
+    docpack_path: &Path,
+    no_plugins: bool,
+) -> Result<Inspection, LocaldocError> {
+    let manifest = reader.read_manifest()?;
 
-    // Read and parse manifest
-    let manifest_path = docpack_path.join("docpack.json");
-    if !manifest_path.exists() {
-        return Err("Not a valid .docpack (missing docpack.json)".into());
-    }
+    let deduplicated = manifest.metadata.as_ref().and_then(|metadata| metadata.get("dedupe")).is_some_and(Value::is_object);
+    let files = (reader.has_dir("files") || deduplicated).then(|| reader.list_files("files"));
+
+    let index = if reader.has_dir("index") {
+        let search =
+            IndexFormat::ALL.into_iter().find(|format| reader.exists(&format!("index/{}", format.file_name())));
+        let stale = search.and_then(|_| fingerprint::staleness(reader));
+        let graph = reader.exists("index/graph.json").then(|| {
+            let graph = reader.read("index/graph.json").and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())?;
+            let count = |key: &str| graph[key].as_array().map(Vec::len).unwrap_or(0);
+            Some((count("nodes"), count("edges")))
+        });
+        // Only the header of embeddings.bin is read
+        let bin_name = format!("index/{}", embeddings::BIN_FILE);
+        let embeddings = reader.exists(&bin_name).then(|| {
+            reader.open_file(&bin_name).ok().and_then(|mut bin| embeddings::read_stream_header(&mut bin).ok())
+        });
+        let model = reader
+            .read(&format!("index/{}", embeddings::META_FILE))
+            .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
+            .and_then(|meta| meta["metadata"]["model"].as_str().map(String::from));
+        let fingerprints = fingerprint::read_all(reader);
+        Some(IndexStatus { search, stale, graph, embeddings, model, fingerprints })
+    } else {
+        None
+    };
+
+    let tasks = if reader.exists(TASKS_FILE) { Some(reader.read_tasks()?) } else { None };
+
+    let outputs = reader.list_files("output");
+
+    // Plugins read extracted docpacks only; a failing plugin never fails
+    // inspect
+    let plugins = if !no_plugins && !reader.is_archive() {
+        plugins::discover()
+            .into_iter()
+            .map(|plugin| {
+                let sections = plugins::inspect(&plugin, docpack_path);
+                (plugin.name, sections)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
 
-    let manifest_content = fs::read_to_string(&manifest_path)?;
-    let manifest: Value = serde_json::from_str(&manifest_content)?;
+    Ok(Inspection { manifest, files, index, tasks, outputs, plugins })
+}
+
+fn print_inspection(docpack: &str, inspection: &Inspection, verbose: bool, top: usize) {
+    let manifest = &inspection.manifest;
+    info!("Inspecting .docpack: {}\n", docpack);
 
     // Display basic info
-    println!("📦 Docpack Information");
-    println!("{}", "─".repeat(60));
-    println!("Name:        {}", manifest["name"].as_str().unwrap_or("unknown"));
-    println!("Version:     {}", manifest["version"].as_str().unwrap_or("unknown"));
-    println!("Description: {}", manifest["description"].as_str().unwrap_or("none"));
-    println!();
+    info!("📦 Docpack Information");
+    info!("{}", "─".repeat(60));
+    info!("Name:        {}", manifest.name.as_deref().unwrap_or("unknown"));
+    info!("Version:     {}", manifest.version);
+    if let Some(message) = versions::compatibility_of(&manifest.version).message() {
+        warn!("{}", message);
+    }
+    info!("Description: {}", manifest.description.as_deref().unwrap_or("none"));
+    info!("");
 
     // Display metadata if present
-    if let Some(metadata) = manifest["metadata"].as_object() {
-        println!("📋 Metadata");
-        println!("{}", "─".repeat(60));
-        if let Some(created) = metadata.get("created").and_then(|v| v.as_str()) {
-            println!("Created:     {}", created);
-        }
-        if let Some(creator) = metadata.get("creator").and_then(|v| v.as_str()) {
-            println!("Creator:     {}", creator);
-        }
-        if let Some(source_type) = metadata.get("source_type").and_then(|v| v.as_str()) {
-            println!("Source Type: {}", source_type);
+    if manifest.metadata.is_some() {
+        info!("📋 Metadata");
+        info!("{}", "─".repeat(60));
+        for (label, value) in metadata_rows(manifest, verbose) {
+            info!("{:<12} {}", format!("{}:", label), value);
         }
-        if let Some(language) = metadata.get("language").and_then(|v| v.as_str()) {
-            println!("Language:    {}", language);
-        }
-        println!();
+        info!("");
     }
 
     // Display environment settings
-    if let Some(env) = manifest["environment"].as_object() {
-        println!("🔧 Environment");
-        println!("{}", "─".repeat(60));
+    let env = &manifest.environment;
+    info!("🔧 Environment");
+    info!("{}", "─".repeat(60));
 
-        if let Some(tools) = env.get("tools").and_then(|v| v.as_array()) {
-            println!("Tools enabled: {}", tools.len());
-            if verbose {
-                for tool in tools {
-                    println!("  - {}", tool.as_str().unwrap_or("unknown"));
-                }
-            }
+    info!("Tools enabled: {}", env.tools.len());
+    if verbose {
+        for name in &env.tools {
+            info!("  - {}{}", name, tool_note(name));
         }
+    }
 
-        if let Some(constraints) = env.get("constraints").and_then(|v| v.as_object()) {
-            if verbose {
-                println!("\nConstraints:");
-                for (key, value) in constraints {
-                    println!("  {}: {}", key, value);
-                }
+    if let Some(constraints) = &env.constraints {
+        if let Some(limits) = output_limits(constraints) {
+            info!("Output limits: {}", limits);
+        }
+
+        if verbose {
+            info!("\nConstraints:");
+            for (key, value) in constraint_rows(constraints) {
+                info!("  {}: {}", key, value);
             }
         }
-        println!();
     }
+    info!("");
 
     // Display file statistics
-    println!("📁 Content");
-    println!("{}", "─".repeat(60));
-    let files_dir = docpack_path.join("files");
-    if files_dir.exists() {
-        let file_count = count_files(&files_dir)?;
-        let total_size = dir_size(&files_dir)?;
-        println!("Files:       {} files", file_count);
-        println!("Total size:  {} bytes ({:.2} MB)", total_size, total_size as f64 / 1_048_576.0);
+    info!("📁 Content");
+    info!("{}", "─".repeat(60));
+    if let Some(files) = &inspection.files {
+        let total_size: u64 = files.iter().map(|(_, size)| size).sum();
+        info!("Files:       {} files", files.len());
+        info!("Total size:  {} bytes ({:.2} MB)", total_size, total_size as f64 / 1_048_576.0);
 
         if verbose {
-            println!("\nFile tree:");
-            print_tree(&files_dir, &files_dir, "", true)?;
+            info!("\nFile tree:");
+            let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+            for line in tree_lines(&paths) {
+                info!("{}", line);
+            }
+        }
+        info!("");
+
+        if !files.is_empty() {
+            info!("📊 Size breakdown");
+            info!("{}", "─".repeat(60));
+            for (extension, bytes, count) in size_breakdown(files) {
+                info!("{:<14} {:>10} {:>5.1}%  {:>7} files", extension, format_size(bytes), share(bytes, total_size), count);
+            }
+            info!("");
+
+            if verbose {
+                info!("🐘 Largest files");
+                info!("{}", "─".repeat(60));
+                for (path, size) in largest(files, top) {
+                    info!("{:>10}  {}", format_size(*size), path);
+                }
+                info!("");
+            }
         }
     } else {
-        println!("Files:       (no files directory)");
+        info!("Files:       (no files directory)");
+        info!("");
     }
-    println!();
 
     // Display index information
-    println!("🔍 Index");
-    println!("{}", "─".repeat(60));
-    let index_dir = docpack_path.join("index");
-    if index_dir.exists() {
-        let has_search = index_dir.join("search.json").exists();
-        let has_graph = index_dir.join("graph.json").exists();
-        let has_embeddings = index_dir.join("embeddings.bin").exists();
-
-        println!("Search index:   {}", if has_search { "✓" } else { "✗" });
-        println!("Semantic graph: {}", if has_graph { "✓" } else { "✗" });
-        println!("Embeddings:     {}", if has_embeddings { "✓" } else { "✗" });
+    info!("🔍 Index");
+    info!("{}", "─".repeat(60));
+    if let Some(index) = &inspection.index {
+        match index.search {
+            Some(format) => info!("Search index:   ✓ ({})", format.file_name()),
+            None => info!("Search index:   ✗"),
+        }
+        if let Some(reason) = &index.stale {
+            info!("Index: stale (files changed since indexing)");
+            info!("  {}; {}", reason, fingerprint::REBUILD_HINT);
+        }
+        info!("Semantic graph: {}", graph_status(index));
+        info!("Embeddings:     {}", embeddings_status(index));
+
+        for (name, fingerprint) in &index.fingerprints {
+            if fingerprint.is_null() {
+                info!("  {}: no fingerprint", name);
+            } else {
+                info!("  {}: {}", name, fingerprint::describe(fingerprint));
+            }
+        }
     } else {
-        println!("(no index directory)");
+        info!("(no index directory)");
     }
-    println!();
+    info!("");
 
     // Display tasks if present
-    let tasks_path = docpack_path.join("tasks.json");
-    if tasks_path.exists() {
-        let tasks_content = fs::read_to_string(&tasks_path)?;
-        let tasks: Value = serde_json::from_str(&tasks_content)?;
-
-        println!("🎯 Tasks");
-        println!("{}", "─".repeat(60));
-        if let Some(mission) = tasks["mission"].as_str() {
-            println!("Mission: {}", mission);
+    if let Some(tasks) = &inspection.tasks {
+        info!("🎯 Tasks");
+        info!("{}", "─".repeat(60));
+        if let Some(mission) = &tasks.mission {
+            info!("Mission: {}", mission);
         }
 
-        if let Some(task_list) = tasks["tasks"].as_array() {
-            println!("Tasks:   {} defined", task_list.len());
+        if let Some(task_list) = &tasks.tasks {
+            info!("Tasks:   {} defined", task_list.len());
             if verbose {
-                println!();
+                info!("");
                 for (i, task) in task_list.iter().enumerate() {
-                    println!("  {}. {}", i + 1, task["name"].as_str().unwrap_or("unnamed"));
-                    if let Some(desc) = task["description"].as_str() {
-                        println!("     {}", desc);
+                    info!("  {}. {}", i + 1, task.name.as_deref().unwrap_or("unnamed"));
+                    if let Some(desc) = &task.description {
+                        info!("     {}", desc);
                     }
                 }
             }
         }
-        println!();
+        info!("");
     }
 
     // Display output information
-    let output_dir = docpack_path.join("output");
-    if output_dir.exists() {
-        let output_count = count_files(&output_dir)?;
-        if output_count > 0 {
-            println!("📤 Output");
-            println!("{}", "─".repeat(60));
-            println!("Generated files: {}", output_count);
+    if !inspection.outputs.is_empty() {
+        info!("📤 Output");
+        info!("{}", "─".repeat(60));
+        info!("Generated files: {}", inspection.outputs.len());
 
-            if verbose {
-                for entry in fs::read_dir(&output_dir)? {
-                    let entry = entry?;
-                    if entry.file_type()?.is_file() {
-                        let metadata = entry.metadata()?;
-                        println!("  - {} ({} bytes)",
-                            entry.file_name().to_string_lossy(),
-                            metadata.len()
-                        );
+        if verbose {
+            for (name, size) in top_level(&inspection.outputs) {
+                info!("  - {} ({} bytes)", name, size);
+            }
+        }
+        info!("");
+    }
+
+    // Sections contributed by plugins
+    for (plugin, sections) in &inspection.plugins {
+        match sections {
+            Ok(sections) => {
+                for section in sections {
+                    info!("🔌 {} (plugin: {})", section.title, plugin);
+                    info!("{}", "─".repeat(60));
+                    for (key, value) in &section.lines {
+                        info!("{:<12} {}", format!("{}:", key), value);
                     }
+                    info!("");
                 }
             }
-            println!();
+            Err(e) => warn!("Plugin {} failed: {}", plugin, e),
         }
     }
-
-    Ok(())
 }
 
-fn count_files(dir: &Path) -> std::io::Result<usize> {
-    let mut count = 0;
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            if entry.file_type()?.is_dir() {
-                count += count_files(&entry.path())?;
-            } else {
-                count += 1;
+/// The inspection as a Markdown document, with everything `--verbose`
+/// prints, for pasting into pull requests and wikis.
+fn markdown_report(docpack: &str, inspection: &Inspection, top: usize) -> String {
+    let manifest = &inspection.manifest;
+    let name = manifest.name.as_deref().unwrap_or("unknown");
+    // Writing to a String can't fail, so results of writeln! are ignored
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# Docpack report: {}\n", escape(name));
+    let _ = writeln!(
+        out,
+        "Generated {} by localdoc {} from `{}`.\n",
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        env!("CARGO_PKG_VERSION"),
+        docpack
+    );
+
+    let _ = writeln!(out, "## Overview\n");
+    let mut overview = vec![
+        vec!["Name".to_string(), name.to_string()],
+        vec!["Version".to_string(), manifest.version.clone()],
+        vec!["Description".to_string(), manifest.description.as_deref().unwrap_or("none").to_string()],
+    ];
+    if let Some(files) = &inspection.files {
+        let total_size: u64 = files.iter().map(|(_, size)| size).sum();
+        overview.push(vec!["Files".to_string(), format!("{} ({})", files.len(), format_size(total_size))]);
+    }
+    if let Some(task_list) = inspection.tasks.as_ref().and_then(|tasks| tasks.tasks.as_ref()) {
+        overview.push(vec!["Tasks".to_string(), task_list.len().to_string()]);
+    }
+    overview.push(vec!["Outputs".to_string(), inspection.outputs.len().to_string()]);
+    table(&mut out, &["", ""], &overview);
+
+    if manifest.metadata.is_some() {
+        let _ = writeln!(out, "## Metadata\n");
+        let rows: Vec<Vec<String>> =
+            metadata_rows(manifest, true).into_iter().map(|(label, value)| vec![label.to_string(), value]).collect();
+        table(&mut out, &["Field", "Value"], &rows);
+    }
+
+    let env = &manifest.environment;
+    let _ = writeln!(out, "## Environment\n");
+    let _ = writeln!(out, "### Tools ({})\n", env.tools.len());
+    for name in &env.tools {
+        let _ = writeln!(out, "- `{}`{}", name, tool_note(name));
+    }
+    out.push('\n');
+    if let Some(constraints) = &env.constraints {
+        let _ = writeln!(out, "### Constraints\n");
+        let rows: Vec<Vec<String>> =
+            constraint_rows(constraints).iter().map(|(key, value)| vec![format!("`{}`", key), format!("`{}`", value)]).collect();
+        table(&mut out, &["Constraint", "Value"], &rows);
+        if let Some(limits) = output_limits(constraints) {
+            let _ = writeln!(out, "Output limits: {}\n", limits);
+        }
+    }
+
+    let _ = writeln!(out, "## Content\n");
+    match &inspection.files {
+        Some(files) => {
+            let total_size: u64 = files.iter().map(|(_, size)| size).sum();
+            let _ = writeln!(out, "{} files, {} ({} bytes).\n", files.len(), format_size(total_size), total_size);
+            if !files.is_empty() {
+                let _ = writeln!(out, "### Size by file type\n");
+                let rows: Vec<Vec<String>> = size_breakdown(files)
+                    .into_iter()
+                    .map(|(extension, bytes, count)| {
+                        vec![extension, format_size(bytes), format!("{:.1}%", share(bytes, total_size)), count.to_string()]
+                    })
+                    .collect();
+                table(&mut out, &["Type", "Size:", "Share:", "Files:"], &rows);
+
+                let _ = writeln!(out, "### Largest files\n");
+                let rows: Vec<Vec<String>> = largest(files, top)
+                    .into_iter()
+                    .map(|(path, size)| vec![format!("`{}`", path), format_size(*size)])
+                    .collect();
+                table(&mut out, &["Path", "Size:"], &rows);
+
+                let _ = writeln!(out, "### File tree\n");
+                let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+                let _ = writeln!(out, "```text\n{}\n```\n", tree_lines(&paths).join("\n"));
+            }
+        }
+        None => {
+            let _ = writeln!(out, "No `files/` directory.\n");
+        }
+    }
+
+    let _ = writeln!(out, "## Index\n");
+    match &inspection.index {
+        Some(index) => {
+            let search = match index.search {
+                Some(format) => format!("✓ (`{}`)", format.file_name()),
+                None => "✗".to_string(),
+            };
+            let rows = vec![
+                vec!["Search index".to_string(), search],
+                vec!["Semantic graph".to_string(), graph_status(index)],
+                vec!["Embeddings".to_string(), embeddings_status(index)],
+            ];
+            table(&mut out, &["Artifact", "Status"], &rows);
+            if let Some(reason) = &index.stale {
+                let _ = writeln!(
+                    out,
+                    "> **Index: stale (files changed since indexing).** {}; {}.\n",
+                    escape(reason),
+                    fingerprint::REBUILD_HINT
+                );
+            }
+            if !index.fingerprints.is_empty() {
+                let rows: Vec<Vec<String>> = index
+                    .fingerprints
+                    .iter()
+                    .map(|(name, fp)| {
+                        let built_by = if fp.is_null() { "no fingerprint".to_string() } else { format!("`{}`", fingerprint::describe(fp)) };
+                        vec![format!("`{}`", name), built_by]
+                    })
+                    .collect();
+                table(&mut out, &["Artifact", "Built by"], &rows);
+            }
+        }
+        None => {
+            let _ = writeln!(out, "No `index/` directory.\n");
+        }
+    }
+
+    if let Some(tasks) = &inspection.tasks {
+        let _ = writeln!(out, "## Tasks\n");
+        if let Some(mission) = &tasks.mission {
+            let _ = writeln!(out, "**Mission:** {}\n", escape(mission));
+        }
+        let task_list = tasks.task_list();
+        for (i, task) in task_list.iter().enumerate() {
+            let _ = write!(out, "{}. **{}**", i + 1, escape(task.name.as_deref().unwrap_or("unnamed")));
+            if let Some(desc) = &task.description {
+                let _ = write!(out, " — {}", escape(desc));
+            }
+            out.push('\n');
+        }
+        if !task_list.is_empty() {
+            out.push('\n');
+        }
+    }
+
+    if !inspection.outputs.is_empty() {
+        let _ = writeln!(out, "## Output\n");
+        let _ = writeln!(out, "{} generated files.\n", inspection.outputs.len());
+        for (name, size) in top_level(&inspection.outputs) {
+            let _ = writeln!(out, "- `{}` ({})", name, format_size(*size));
+        }
+        out.push('\n');
+    }
+
+    for (plugin, sections) in &inspection.plugins {
+        match sections {
+            Ok(sections) => {
+                for section in sections {
+                    let _ = writeln!(out, "## {} (plugin: {})\n", escape(&section.title), plugin);
+                    let rows: Vec<Vec<String>> = section.lines.iter().map(|(k, v)| vec![k.clone(), v.clone()]).collect();
+                    table(&mut out, &["", ""], &rows);
+                }
+            }
+            Err(e) => {
+                let _ = writeln!(out, "> Plugin {} failed: {}\n", plugin, escape(e));
             }
         }
     }
-    Ok(count)
+
+    let end = out.trim_end().len();
+    out.truncate(end);
+    out.push('\n');
+    out
 }
 
-fn dir_size(dir: &Path) -> std::io::Result<u64> {
-    let mut size = 0;
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                size += dir_size(&path)?;
-            } else {
-                size += entry.metadata()?.len();
+/// Append a Markdown table. A header ending in `:` names a right-aligned
+/// column.
+fn table(out: &mut String, headers: &[&str], rows: &[Vec<String>]) {
+    let names: Vec<&str> = headers.iter().map(|h| h.trim_end_matches(':')).collect();
+    let rules: Vec<&str> = headers.iter().map(|h| if h.ends_with(':') { "---:" } else { "---" }).collect();
+    let _ = writeln!(out, "| {} |", names.join(" | "));
+    let _ = writeln!(out, "| {} |", rules.join(" | "));
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|cell| escape(cell).replace('|', "\\|")).collect();
+        let _ = writeln!(out, "| {} |", cells.join(" | "));
+    }
+    out.push('\n');
+}
+
+/// `text` on one line, with the characters Markdown takes for markup
+/// escaped outside code spans.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    let mut in_code = false;
+    for c in text.chars() {
+        match c {
+            '`' => {
+                in_code = !in_code;
+                escaped.push(c);
             }
+            '\n' | '\r' => escaped.push(' '),
+            '*' | '_' | '<' | '>' | '[' | ']' if !in_code => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Labelled values of `metadata` that inspect shows, in order. The ignore
+/// file is only shown when `verbose`.
+fn metadata_rows(manifest: &Manifest, verbose: bool) -> Vec<(&'static str, String)> {
+    let metadata = Value::Object(manifest.metadata.clone().unwrap_or_default());
+    let mut rows = Vec::new();
+    for (label, key) in [
+        ("Created", "created"),
+        ("Creator", "creator"),
+        ("Source Type", "source_type"),
+        ("Language", "language"),
+        ("License", "license"),
+    ] {
+        if let Some(value) = metadata[key].as_str() {
+            rows.push((label, value.to_string()));
+        }
+    }
+    if metadata["git"].is_object() {
+        rows.push(("Git", crate::git::describe(&metadata["git"])));
+        if let Some(remote) = metadata["git"]["remote"].as_str() {
+            rows.push(("Remote", remote.to_string()));
+        }
+    }
+    if let Some(method) = metadata["compression"]["method"].as_str() {
+        match metadata["compression"]["level"].as_i64() {
+            Some(level) => rows.push(("Compression", format!("{} (level {})", method, level))),
+            None => rows.push(("Compression", method.to_string())),
+        }
+    }
+    if verbose {
+        match metadata.get("ignore_file") {
+            Some(ignore) => rows.push((
+                "Ignore file",
+                format!(
+                    "{} ({} files ignored)",
+                    ignore["path"].as_str().unwrap_or(".docpackignore"),
+                    ignore["ignored"].as_u64().unwrap_or(0)
+                ),
+            )),
+            None => rows.push(("Ignore file", "none".to_string())),
         }
     }
-    Ok(size)
+    rows
 }
 
-fn print_tree(path: &Path, base: &Path, prefix: &str, _is_last: bool) -> std::io::Result<()> {
-    let entries: Vec<_> = fs::read_dir(path)?
-        .filter_map(|e| e.ok())
-        .collect();
+/// What a tool does, to follow its name, or that localdoc does not know it.
+fn tool_note(name: &str) -> String {
+    match Tool::from_name(name) {
+        Some(tool) => format!(": {}", tool.description()),
+        None => " (unknown tool)".to_string(),
+    }
+}
 
-    for (i, entry) in entries.iter().enumerate() {
-        let is_last_entry = i == entries.len() - 1;
-        let name = entry.file_name();
-        let name_str = name.to_string_lossy();
+/// The output limits in the manifest's constraints, if any are set.
+fn output_limits(constraints: &Constraints) -> Option<String> {
+    if constraints.max_output_bytes.is_none() && constraints.max_output_file_bytes.is_none() {
+        return None;
+    }
+    let show = |limit: Option<u64>| limit.map(|n| format!("{} bytes", n)).unwrap_or("none".to_string());
+    Some(format!("{} total, {} per file", show(constraints.max_output_bytes), show(constraints.max_output_file_bytes)))
+}
 
-        let connector = if is_last_entry { "└── " } else { "├── " };
-        println!("{}{}{}", prefix, connector, name_str);
+/// Each constraint as it appears in docpack.json, unknown ones included.
+fn constraint_rows(constraints: &Constraints) -> Vec<(String, Value)> {
+    match serde_json::to_value(constraints) {
+        Ok(Value::Object(fields)) => fields.into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn graph_status(index: &IndexStatus) -> String {
+    match index.graph {
+        Some(Some((nodes, edges))) => format!("✓ ({} nodes, {} edges)", nodes, edges),
+        Some(None) => "✓ (unreadable)".to_string(),
+        None => "✗".to_string(),
+    }
+}
+
+fn embeddings_status(index: &IndexStatus) -> String {
+    match &index.embeddings {
+        Some(Some(header)) => format!(
+            "✓ ({} vectors × {}, {})",
+            header.count,
+            header.dimension,
+            index.model.as_deref().unwrap_or("unknown model")
+        ),
+        Some(None) => "✓ (unreadable)".to_string(),
+        None => "✗".to_string(),
+    }
+}
+
+/// Output files directly under `output/`.
+fn top_level(outputs: &[(String, u64)]) -> impl Iterator<Item = &(String, u64)> {
+    outputs.iter().filter(|(name, _)| !name.contains('/'))
+}
+
+/// Percentage of `total_size` that `bytes` make up.
+fn share(bytes: u64, total_size: u64) -> f64 {
+    if total_size > 0 {
+        bytes as f64 * 100.0 / total_size as f64
+    } else {
+        0.0
+    }
+}
+
+/// Bytes and file counts by extension, largest first, as (extension,
+/// bytes, files).
+fn size_breakdown(files: &[(String, u64)]) -> Vec<(String, u64, usize)> {
+    let mut extensions: BTreeMap<String, (u64, usize)> = BTreeMap::new();
+    for (path, size) in files {
+        let entry = extensions.entry(extension(path)).or_default();
+        entry.0 += size;
+        entry.1 += 1;
+    }
+    let mut rows: Vec<(String, u64, usize)> =
+        extensions.into_iter().map(|(extension, (bytes, count))| (extension, bytes, count)).collect();
+    rows.sort_by(|(name_a, bytes_a, _), (name_b, bytes_b, _)| bytes_b.cmp(bytes_a).then_with(|| name_a.cmp(name_b)));
+    if rows.len() > MAX_BREAKDOWN_ROWS {
+        let rest = rows.split_off(MAX_BREAKDOWN_ROWS - 1);
+        let (bytes, count) = rest.iter().fold((0, 0), |sum, (_, bytes, count)| (sum.0 + bytes, sum.1 + count));
+        rows.push((format!("({} others)", rest.len()), bytes, count));
+    }
+    rows
+}
+
+/// The `top` largest files, largest first.
+fn largest(files: &[(String, u64)], top: usize) -> Vec<&(String, u64)> {
+    let mut largest: Vec<&(String, u64)> = files.iter().collect();
+    largest.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    largest.truncate(top);
+    largest
+}
+
+/// `.rs`-style extension of `path`, or `(none)`. Dotfiles such as
+/// `.gitignore` have none.
+fn extension(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && !extension.is_empty() => format!(".{}", extension.to_lowercase()),
+        _ => "(none)".to_string(),
+    }
+}
+
+/// `paths` (relative, `/`-separated and sorted) drawn as a tree, with
+/// directories made up from the paths below them.
+fn tree_lines(paths: &[&str]) -> Vec<String> {
+    let mut lines = Vec::new();
+    add_tree_lines(paths, "", &mut lines);
+    lines
+}
 
-        if entry.file_type()?.is_dir() {
-            let new_prefix = format!(
-                "{}{}",
-                prefix,
-                if is_last_entry { "    " } else { "│   " }
-            );
-            print_tree(&entry.path(), base, &new_prefix, is_last_entry)?;
+fn add_tree_lines(paths: &[&str], prefix: &str, lines: &mut Vec<String>) {
+    // Paths under one directory are adjacent once sorted
+    let mut groups: Vec<(&str, Vec<&str>)> = Vec::new();
+    for path in paths {
+        let (head, rest) = match path.split_once('/') {
+            Some((head, rest)) => (head, Some(rest)),
+            None => (*path, None),
+        };
+        if groups.last().map(|(name, _)| *name) != Some(head) {
+            groups.push((head, Vec::new()));
+        }
+        if let (Some(rest), Some((_, children))) = (rest, groups.last_mut()) {
+            children.push(rest);
         }
     }
 
-    Ok(())
+    for (i, (name, children)) in groups.iter().enumerate() {
+        let is_last_entry = i == groups.len() - 1;
+        let connector = if is_last_entry { "└── " } else { "├── " };
+        lines.push(format!("{}{}{}", prefix, connector, name));
+
+        if !children.is_empty() {
+            let new_prefix = format!("{}{}", prefix, if is_last_entry { "    " } else { "│   " });
+            add_tree_lines(children, &new_prefix, lines);
+        }
+    }
 }
@@ -0,0 +1,21 @@
+use std::path::Path;
+
+use crate::error::LocaldocError;
+use crate::run_state;
+
+pub fn run(docpack: &str, follow: bool) -> Result<(), LocaldocError> {
+    let Some((state, runtime)) = run_state::live(Path::new(docpack))? else {
+        return Err(LocaldocError::NotFound(format!(
+            "No detached run is recorded for {}; start one with `localdoc run --detach`",
+            docpack
+        )));
+    };
+    let status = runtime
+        .logs_command(&state.container, follow)
+        .status()
+        .map_err(|e| format!("Cannot start {} ({}); is it installed?", runtime.program(), e))?;
+    if !status.success() {
+        return Err(LocaldocError::ContainerFailed { what: format!("{} logs", runtime.name()), code: status.code(), stderr: String::new() });
+    }
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::Path;
+use clap::{Arg, Command};
+use log::info;
+use crate::error::{LocaldocError, WithPath};
+
+/// Write a roff man page for `command` and for each of its subcommands,
+/// rendered from the same definitions as `--help`: `localdoc.1`,
+/// `localdoc-ingest.1`, `localdoc-tasks-add.1` and so on.
+pub fn run(mut command: Command, out_dir: &str) -> Result<(), LocaldocError> {
+    command.build();
+    let out_dir = Path::new(out_dir);
+    fs::create_dir_all(out_dir).with_path(out_dir)?;
+
+    let mut pages = Vec::new();
+    collect(&command, &[], &mut pages);
+    for (name, page) in &pages {
+        let page_path = out_dir.join(format!("{}.1", name));
+        fs::write(&page_path, page).with_path(&page_path)?;
+    }
+    info!("✓ Wrote {} man pages to {}", pages.len(), out_dir.display());
+    Ok(())
+}
+
+/// Render `command`'s page and its subcommands' pages into `pages`, each
+/// named after the commands leading to it joined with `-`.
+fn collect(command: &Command, parents: &[&str], pages: &mut Vec<(String, String)>) {
+    let mut path = parents.to_vec();
+    path.push(command.get_name());
+    pages.push((path.join("-"), page(command, &path)));
+    for subcommand in command.get_subcommands().filter(|subcommand| subcommand.get_name() != "help" && !subcommand.is_hide_set()) {
+        collect(subcommand, &path, pages);
+    }
+}
+
+fn page(command: &Command, path: &[&str]) -> String {
+    let name = path.join("-");
+    let mut roff = format!(
+        ".TH {} 1 \"\" \"localdoc {}\" \"User Commands\"\n",
+        escape(&name.to_uppercase()),
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let about = command.get_about().map(|about| about.to_string()).unwrap_or_default();
+    roff += &format!(".SH NAME\n{} \\- {}\n", escape(&name), escape(&about));
+
+    let usage = command.clone().render_usage().to_string();
+    let usage = usage.strip_prefix("Usage: ").unwrap_or(&usage);
+    let invocation = path.join(" ");
+    roff += ".SH SYNOPSIS\n";
+    roff += &match usage.strip_prefix(&invocation) {
+        Some(rest) => format!("\\fB{}\\fR{}\n", escape(&invocation), escape(rest)),
+        None => format!("{}\n", escape(usage)),
+    };
+
+    let description = command.get_long_about().or(command.get_about()).map(|text| text.to_string()).unwrap_or_default();
+    roff += ".SH DESCRIPTION\n";
+    roff += &description.split("\n\n").map(escape).collect::<Vec<_>>().join("\n.PP\n");
+    roff += "\n";
+
+    let visible = |arg: &&Arg| !arg.is_hide_set();
+    let positionals: Vec<&Arg> = command.get_positionals().filter(visible).collect();
+    if !positionals.is_empty() {
+        roff += ".SH ARGUMENTS\n";
+        for arg in positionals {
+            roff += &format!(".TP\n\\fI{}\\fR\n{}\n", escape(&values(arg)), escape(&arg_help(arg)));
+        }
+    }
+    let options: Vec<&Arg> = command.get_arguments().filter(|arg| !arg.is_positional()).filter(visible).collect();
+    if !options.is_empty() {
+        roff += ".SH OPTIONS\n";
+        for arg in options {
+            let mut flags = Vec::new();
+            if let Some(short) = arg.get_short() {
+                flags.push(format!("\\fB\\-{}\\fR", short));
+            }
+            if let Some(long) = arg.get_long() {
+                flags.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+            }
+            let mut term = flags.join(", ");
+            if arg.get_action().takes_values() {
+                term += &format!(" \\fI{}\\fR", escape(&values(arg)));
+            }
+            roff += &format!(".TP\n{}\n{}\n", term, escape(&arg_help(arg)));
+        }
+    }
+
+    let subcommands: Vec<&Command> = command.get_subcommands().filter(|subcommand| subcommand.get_name() != "help" && !subcommand.is_hide_set()).collect();
+    if !subcommands.is_empty() {
+        roff += ".SH COMMANDS\n";
+        for subcommand in subcommands {
+            let about = subcommand.get_about().map(|about| about.to_string()).unwrap_or_default();
+            roff += &format!(".TP\n\\fB{}\\-{}\\fR(1)\n{}\n", escape(&name), escape(subcommand.get_name()), escape(&about));
+        }
+    }
+
+    if let Some(text) = command.get_after_long_help() {
+        roff += ".SH EXAMPLES\n";
+        roff += &examples(&text.to_string());
+    }
+    if let Some((_, parents)) = path.split_last().filter(|(_, parents)| !parents.is_empty()) {
+        roff += &format!(".SH SEE ALSO\n\\fB{}\\fR(1)\n", escape(&parents.join("-")));
+    }
+    roff
+}
+
+/// The value names an argument takes, e.g. `<FROM> <TO>` or `<SOURCES>...`.
+fn values(arg: &Arg) -> String {
+    let names: Vec<String> = match arg.get_value_names() {
+        Some(names) => names.iter().map(|name| format!("<{}>", name)).collect(),
+        None => vec![format!("<{}>", arg.get_id().as_str().to_uppercase())],
+    };
+    let repeated = arg.get_num_args().is_some_and(|range| range.max_values() > names.len());
+    format!("{}{}", names.join(" "), if repeated { "..." } else { "" })
+}
+
+/// An argument's help with its default and possible values, as `--help`
+/// shows them.
+fn arg_help(arg: &Arg) -> String {
+    let mut help = arg.get_long_help().or(arg.get_help()).map(|help| help.to_string()).unwrap_or_default();
+    if !arg.get_action().takes_values() {
+        return help;
+    }
+    let defaults: Vec<String> = arg.get_default_values().iter().map(|value| value.to_string_lossy().into_owned()).collect();
+    if !defaults.is_empty() && !arg.is_hide_default_value_set() {
+        help += &format!(" [default: {}]", defaults.join(", "));
+    }
+    let possible: Vec<String> =
+        arg.get_possible_values().iter().filter(|value| !value.is_hide_set()).map(|value| value.get_name().to_string()).collect();
+    if !possible.is_empty() && !arg.is_hide_possible_values_set() {
+        help += &format!(" [possible values: {}]", possible.join(", "));
+    }
+    help
+}
+
+/// The Examples from `--help` (see [`crate::help`]) as roff: each `# `
+/// comment as a paragraph and the command lines under it as a code block.
+fn examples(text: &str) -> String {
+    let text = text.trim();
+    let text = text.strip_prefix("Examples:").unwrap_or(text);
+    let mut roff = String::new();
+    for example in text.split("\n\n") {
+        let (mut comments, mut commands) = (Vec::new(), Vec::new());
+        for line in example.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match line.strip_prefix("# ") {
+                Some(comment) => comments.push(comment),
+                None => commands.push(line),
+            }
+        }
+        if !comments.is_empty() {
+            roff += &format!(".PP\n{}\n", escape(&comments.join(" ")));
+        }
+        if !commands.is_empty() {
+            roff += ".PP\n.RS 4\n.EX\n";
+            for command in commands {
+                roff += &format!("{}\n", escape(command));
+            }
+            roff += ".EE\n.RE\n";
+        }
+    }
+    roff
+}
+
+/// `text` with roff's special characters escaped, so it prints as it is.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e")
+        .replace('-', "\\-")
+        .lines()
+        // A line starting with . or ' would be read as a request
+        .map(|line| if line.starts_with(['.', '\'']) { format!("\\&{}", line) } else { line.to_string() })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples_become_paragraphs_and_code_blocks() {
+        let text = "Examples:\n  # Check one docpack\n  localdoc validate app.docpack\n\n  # Then its files\n  localdoc verify app.docpack\n";
+        assert_eq!(
+            examples(text),
+            ".PP\nCheck one docpack\n.PP\n.RS 4\n.EX\nlocaldoc validate app.docpack\n.EE\n.RE\n\
+             .PP\nThen its files\n.PP\n.RS 4\n.EX\nlocaldoc verify app.docpack\n.EE\n.RE\n"
+        );
+        assert_eq!(escape("--out C:\\docs\n.hidden"), "\\-\\-out C:\\edocs\n\\&.hidden");
+    }
+}
@@ -0,0 +1,64 @@
+use std::fs;
+use std::path::Path;
+use log::info;
+use serde_json::Value;
+use similar::TextDiff;
+
+use crate::error::{LocaldocError, WithPath};
+use crate::seal;
+use crate::versions;
+
+/// Upgrade a docpack directory's docpack.json to the current manifest
+/// version, step by step, keeping the original beside it as
+/// `docpack.json.<version>.bak`. With `dry_run` the changes are only shown.
+pub fn run(docpack: &str, dry_run: bool) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+    if !docpack_path.is_dir() {
+        return Err(LocaldocError::InvalidArgument(format!("migrate needs a docpack directory, not an archive: {}", docpack)));
+    }
+    let manifest_path = docpack_path.join("docpack.json");
+    let text = fs::read_to_string(&manifest_path).map_err(|e| format!("Cannot read {}: {}", manifest_path.display(), e))?;
+    let mut manifest: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("docpack.json is not valid JSON ({}); `localdoc validate --fix` repairs trailing commas", e))?;
+
+    let from = match &manifest["version"] {
+        Value::Null => "unversioned".to_string(),
+        Value::String(version) => version.clone(),
+        other => other.to_string(),
+    };
+    let changes = versions::migrate(&mut manifest)?;
+    if changes.is_empty() {
+        info!("✓ docpack.json is already at version {}", from);
+        return Ok(());
+    }
+    if !dry_run {
+        seal::ensure_mutable(docpack_path, false)?;
+    }
+
+    info!(
+        "🔧 {} docpack.json from {} to {}",
+        if dry_run { "Would migrate" } else { "Migrating" },
+        from,
+        versions::MANIFEST_VERSION
+    );
+    info!("{}", "─".repeat(60));
+    for change in &changes {
+        info!("  • {}", change);
+    }
+    let rewritten = serde_json::to_string_pretty(&manifest)?;
+    let diff = TextDiff::from_lines(&text, &rewritten);
+    print!("{}", diff.unified_diff().context_radius(2).header("a/docpack.json", "b/docpack.json"));
+    if dry_run {
+        info!("  Dry run: nothing was written");
+        return Ok(());
+    }
+
+    let backup = docpack_path.join(format!("docpack.json.{}.bak", from));
+    if backup.exists() {
+        return Err(format!("{} already exists; move it aside to migrate again", backup.display()).into());
+    }
+    fs::write(&backup, &text).with_path(&backup)?;
+    fs::write(&manifest_path, rewritten).with_path(&manifest_path)?;
+    info!("\n✓ Migrated to version {}; the original manifest is in {}", versions::MANIFEST_VERSION, backup.display());
+    Ok(())
+}
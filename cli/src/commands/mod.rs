@@ -1,5 +1,37 @@
 pub mod ingest;
 pub mod run;
+pub mod batch;
+pub mod watch;
+pub mod doctor;
+pub mod config;
 pub mod inspect;
+pub mod files;
 pub mod validate;
+pub mod schema;
+pub mod verify;
+pub mod diff;
+pub mod browse;
+pub mod search;
+pub mod index;
+pub mod graph;
+pub mod embed;
+pub mod semantic_search;
 pub mod init;
+pub mod recover;
+pub mod set;
+pub mod tasks;
+pub mod migrate;
+pub mod history;
+pub mod logs;
+pub mod stop;
+pub mod clean;
+pub mod update;
+pub mod pack;
+pub mod registry;
+pub mod outputs;
+pub mod export;
+pub mod serve;
+pub mod preview_context;
+pub mod plugins;
+pub mod man;
+pub mod bench;
@@ -0,0 +1,504 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use docpack_core::{Manifest, Strictness};
+use log::{info, warn};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+
+use localdoc::docpack::Docpack;
+
+use crate::commands::run::copy_dir_all;
+use crate::error::{LocaldocError, WithPath};
+use crate::hashes;
+use crate::runtime;
+use crate::seal;
+
+/// Directory (relative to the docpack root) holding one snapshot of
+/// `output/` per completed run.
+pub const HISTORY_DIR: &str = "history";
+
+/// Pseudo run id referring to the live `output/` directory.
+const CURRENT: &str = "current";
+
+/// Subdirectory of `output/` that `--seed-subdir` copies existing docs into.
+pub const SEED_SUBDIR: &str = "seed";
+
+/// Copy the docpack's `output/` into `history/<run_id>/` so later runs can
+/// be compared against it, leaving out `exclude` (paths relative to
+/// `output/`). Returns the new run id: the time the run finished, with a
+/// `-2`, `-3`, ... suffix when an earlier run finished in the same second.
+pub fn snapshot(docpack_path: &Path, exclude: &[String]) -> Result<String, LocaldocError> {
+    let history_dir = docpack_path.join(HISTORY_DIR);
+    fs::create_dir_all(&history_dir).with_path(&history_dir)?;
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut run_id = stamp.clone();
+    let mut attempt = 1;
+    let dest = loop {
+        let dest = history_dir.join(&run_id);
+        match fs::create_dir(&dest) {
+            Ok(()) => break dest,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                attempt += 1;
+                run_id = format!("{}-{}", stamp, attempt);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    let output_dir = docpack_path.join("output");
+    for rel in collect_files(&output_dir)? {
+        if exclude.contains(&rel) {
+            continue;
+        }
+        let target = dest.join(&rel);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+        let source = output_dir.join(&rel);
+        fs::copy(&source, target).with_path(&source)?;
+    }
+
+    Ok(run_id)
+}
+
+/// Copy an existing docs directory into the docpack's `output/` (or
+/// `output/seed/`). Returns the target directory relative to the docpack
+/// and the seeded paths relative to `output/`.
+pub fn seed_into(
+    docpack_path: &Path,
+    source: &Path,
+    subdir: bool,
+) -> Result<(String, Vec<String>), LocaldocError> {
+    if !source.is_dir() {
+        return Err(format!("Seed path is not a directory: {}", source.display()).into());
+    }
+
+    let (target_rel, target) = if subdir {
+        (format!("output/{}", SEED_SUBDIR), docpack_path.join("output").join(SEED_SUBDIR))
+    } else {
+        ("output".to_string(), docpack_path.join("output"))
+    };
+    fs::create_dir_all(&target).with_path(&target)?;
+    copy_dir_all(source, &target)?;
+
+    let prefix = if subdir { format!("{}/", SEED_SUBDIR) } else { String::new() };
+    let seeded = collect_files(source)?
+        .into_iter()
+        .map(|rel| format!("{}{}", prefix, rel))
+        .collect();
+
+    Ok((target_rel, seeded))
+}
+
+/// The `metadata.seeded_outputs` record for a seeding operation.
+pub fn seed_metadata(source: &Path, target: &str, file_count: usize, seeded_at: chrono::DateTime<chrono::Utc>) -> Value {
+    json!({
+        "source": source.display().to_string(),
+        "target": target,
+        "files": file_count,
+        "seeded_at": seeded_at.to_rfc3339(),
+    })
+}
+
+/// Seeded paths that a task in tasks.json also declares as its output, as
+/// (path, task id) pairs. The agent will overwrite these.
+pub fn seed_collisions(docpack_path: &Path, seeded: &[String]) -> Vec<(String, String)> {
+    let declared = declared_output_tasks(docpack_path);
+    seeded
+        .iter()
+        .filter_map(|path| declared.get(path).map(|task| (path.clone(), task.clone())))
+        .collect()
+}
+
+/// Print a warning for each seeded file a task will overwrite.
+pub fn report_seed_collisions(collisions: &[(String, String)]) {
+    if collisions.is_empty() {
+        return;
+    }
+
+    let listed: Vec<String> =
+        collisions.iter().map(|(path, task)| format!("  • output/{} (declared by {})", path, task)).collect();
+    warn!("Seeded files that tasks will overwrite ({}):\n{}", collisions.len(), listed.join("\n"));
+}
+
+pub fn seed(docpack: &str, source: &str, subdir: bool, force_mutable: bool) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+    let manifest_path = docpack_path.join("docpack.json");
+
+    Docpack::open_dir(docpack_path)?;
+
+    seal::ensure_mutable(docpack_path, force_mutable)?;
+
+    info!("Seeding outputs from: {}", source);
+    let source_path = Path::new(source);
+    let (target, seeded) = seed_into(docpack_path, source_path, subdir)?;
+    info!("  Copied {} files into {}/", seeded.len(), target);
+
+    let mut manifest = Manifest::load(&manifest_path, Strictness::Lenient)?;
+    let seeded_outputs = seed_metadata(source_path, &target, seeded.len(), chrono::Utc::now());
+    manifest.metadata_mut().insert("seeded_outputs".to_string(), seeded_outputs);
+    manifest.save(&manifest_path)?;
+
+    report_seed_collisions(&seed_collisions(docpack_path, &seeded));
+
+    info!("\n✓ Seeded outputs recorded in docpack.json (metadata.seeded_outputs)");
+    Ok(())
+}
+
+pub fn diff(
+    docpack: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+    stat: bool,
+    json: bool,
+) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+
+    Docpack::open_dir(docpack_path)?;
+
+    let runs = list_runs(docpack_path)?;
+
+    // Default to the two most recent runs
+    let to_id = match to {
+        Some(id) => id.to_string(),
+        None => runs.last().cloned().unwrap_or_else(|| CURRENT.to_string()),
+    };
+    let from_id = match from {
+        Some(id) => id.to_string(),
+        None => runs
+            .iter()
+            .rev()
+            .find(|id| **id < to_id || to_id == CURRENT)
+            .cloned()
+            .ok_or("Need at least two archived runs to diff (see history/)")?,
+    };
+
+    let from_dir = resolve_run(docpack_path, &from_id)?;
+    let to_dir = resolve_run(docpack_path, &to_id)?;
+
+    let from_files = collect_files(&from_dir)?;
+    let to_files = collect_files(&to_dir)?;
+    let task_paths = declared_output_tasks(docpack_path);
+
+    let all_paths: BTreeSet<&String> = from_files.iter().chain(to_files.iter()).collect();
+    let mut entries = Vec::new();
+
+    for rel in all_paths {
+        let old = from_files.contains(rel).then(|| from_dir.join(rel));
+        let new = to_files.contains(rel).then(|| to_dir.join(rel));
+        entries.push(compare(rel, old.as_deref(), new.as_deref(), task_paths.get(rel.as_str()))?);
+    }
+
+    if json {
+        let report = json!({
+            "from": from_id,
+            "to": to_id,
+            "files": entries.iter().map(|e| e.to_json(stat)).collect::<Vec<_>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    info!("Comparing outputs: {} → {}\n", from_id, to_id);
+
+    let changed: Vec<&FileDiff> = entries.iter().filter(|e| e.status != "unchanged").collect();
+    if changed.is_empty() {
+        info!("✓ No differences");
+        return Ok(());
+    }
+
+    if stat {
+        for entry in &changed {
+            print_stat_line(entry);
+        }
+        let added: usize = changed.iter().map(|e| e.added_lines).sum();
+        let removed: usize = changed.iter().map(|e| e.removed_lines).sum();
+        info!("\n{} files changed, {} insertions(+), {} deletions(-)", changed.len(), added, removed);
+    } else {
+        for entry in &changed {
+            print_file_diff(entry);
+        }
+    }
+
+    Ok(())
+}
+
+/// Result of comparing one output path across two runs.
+struct FileDiff {
+    path: String,
+    task: Option<String>,
+    status: &'static str,
+    binary: bool,
+    added_lines: usize,
+    removed_lines: usize,
+    old_hash: Option<String>,
+    new_hash: Option<String>,
+    unified: Option<String>,
+}
+
+impl FileDiff {
+    fn to_json(&self, stat: bool) -> Value {
+        let mut value = json!({
+            "path": self.path,
+            "task": self.task,
+            "status": self.status,
+            "binary": self.binary,
+            "added_lines": self.added_lines,
+            "removed_lines": self.removed_lines,
+            "old_sha256": self.old_hash,
+            "new_sha256": self.new_hash,
+        });
+        if !stat {
+            value["diff"] = json!(self.unified);
+        }
+        value
+    }
+}
+
+fn compare(rel: &str, old: Option<&Path>, new: Option<&Path>, task: Option<&String>) -> io::Result<FileDiff> {
+    let old_bytes = old.map(fs::read).transpose()?;
+    let new_bytes = new.map(fs::read).transpose()?;
+
+    let old_hash = old_bytes.as_deref().map(sha256_hex);
+    let new_hash = new_bytes.as_deref().map(sha256_hex);
+
+    let status = match (&old_hash, &new_hash) {
+        (None, Some(_)) => "added",
+        (Some(_), None) => "removed",
+        (Some(a), Some(b)) if a == b => "unchanged",
+        _ => "modified",
+    };
+
+    let old_text = old_bytes.as_deref().map(as_text);
+    let new_text = new_bytes.as_deref().map(as_text);
+    let binary = matches!(old_text, Some(None)) || matches!(new_text, Some(None));
+
+    let mut diff = FileDiff {
+        path: rel.to_string(),
+        task: task.cloned(),
+        status,
+        binary,
+        added_lines: 0,
+        removed_lines: 0,
+        old_hash,
+        new_hash,
+        unified: None,
+    };
+
+    // Binary outputs are compared by hash only
+    if binary || status == "unchanged" {
+        return Ok(diff);
+    }
+
+    let old_text = old_text.flatten().unwrap_or("");
+    let new_text = new_text.flatten().unwrap_or("");
+    let text_diff = TextDiff::from_lines(old_text, new_text);
+
+    for change in text_diff.iter_all_changes() {
+        match change.tag() {
+            similar::ChangeTag::Insert => diff.added_lines += 1,
+            similar::ChangeTag::Delete => diff.removed_lines += 1,
+            similar::ChangeTag::Equal => {}
+        }
+    }
+
+    let old_label = if old.is_some() { format!("a/{}", rel) } else { "/dev/null".to_string() };
+    let new_label = if new.is_some() { format!("b/{}", rel) } else { "/dev/null".to_string() };
+    diff.unified = Some(
+        text_diff
+            .unified_diff()
+            .context_radius(3)
+            .header(&old_label, &new_label)
+            .to_string(),
+    );
+
+    Ok(diff)
+}
+
+fn print_stat_line(entry: &FileDiff) {
+    let task = entry.task.as_deref().map(|t| format!(" [{}]", t)).unwrap_or_default();
+    if entry.binary {
+        println!("  {:<9} {}{} (binary)", entry.status, entry.path, task);
+    } else {
+        println!(
+            "  {:<9} {}{} +{} -{}",
+            entry.status, entry.path, task, entry.added_lines, entry.removed_lines
+        );
+    }
+}
+
+fn print_file_diff(entry: &FileDiff) {
+    let task = entry.task.as_deref().map(|t| format!(" (task {})", t)).unwrap_or_default();
+    println!("{} {}{}", entry.status, entry.path, task);
+
+    if entry.binary {
+        println!(
+            "  binary file: {} → {}\n",
+            entry.old_hash.as_deref().unwrap_or("(none)"),
+            entry.new_hash.as_deref().unwrap_or("(none)")
+        );
+    } else if let Some(unified) = &entry.unified {
+        println!("{}", unified);
+    }
+}
+
+/// Archived run ids, oldest first.
+fn list_runs(docpack_path: &Path) -> io::Result<Vec<String>> {
+    let history_dir = docpack_path.join(HISTORY_DIR);
+    if !history_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut runs: Vec<String> = fs::read_dir(history_dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| e.file_name().to_str().map(String::from))
+        .collect();
+    runs.sort();
+    Ok(runs)
+}
+
+/// The directory of `run_id`, which must be `current` or one of the runs
+/// in `history/`; anything else, `..` included, is refused.
+fn resolve_run(docpack_path: &Path, run_id: &str) -> Result<PathBuf, LocaldocError> {
+    if run_id == CURRENT {
+        return Ok(docpack_path.join("output"));
+    }
+
+    if !list_runs(docpack_path)?.iter().any(|id| id == run_id) {
+        return Err(LocaldocError::NotFound(format!("Unknown run: {} (see {}/)", run_id, HISTORY_DIR)));
+    }
+    Ok(docpack_path.join(HISTORY_DIR).join(run_id))
+}
+
+/// Relative file paths under `dir`, using `/` separators.
+pub(crate) fn collect_files(dir: &Path) -> Result<BTreeSet<String>, LocaldocError> {
+    let mut files = BTreeSet::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if e.io_error().map(io::Error::kind) == Some(io::ErrorKind::PermissionDenied) => {
+                return Err(runtime::permission_denied(e.path().unwrap_or(dir)).into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if entry.file_type().is_file() {
+            let rel = entry.path().strip_prefix(dir)?;
+            let parts: Vec<String> = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            files.insert(parts.join("/"));
+        }
+    }
+    Ok(files)
+}
+
+/// Map output paths (relative to `output/`) to the id of the task declaring them.
+fn declared_output_tasks(docpack_path: &Path) -> BTreeMap<String, String> {
+    let mut map = BTreeMap::new();
+
+    let tasks: Value = match fs::read_to_string(docpack_path.join("tasks.json"))
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+    {
+        Some(tasks) => tasks,
+        None => return map,
+    };
+
+    if let Some(task_list) = tasks["tasks"].as_array() {
+        for task in task_list {
+            if let (Some(id), Some(path)) = (task["id"].as_str(), task["output"]["path"].as_str()) {
+                let rel = path.strip_prefix("output/").unwrap_or(path);
+                map.insert(rel.to_string(), id.to_string());
+            }
+        }
+    }
+
+    map
+}
+
+fn as_text(bytes: &[u8]) -> Option<&str> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(bytes).ok()
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hashes::hex(&Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn docpack_with_output() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("output")).unwrap();
+        fs::write(dir.path().join("output/overview.md"), "# Overview\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn runs_in_the_same_second_get_their_own_snapshots() {
+        let dir = docpack_with_output();
+        let first = snapshot(dir.path(), &[]).unwrap();
+        fs::write(dir.path().join("output/overview.md"), "# Overview, again\n").unwrap();
+        let second = snapshot(dir.path(), &[]).unwrap();
+        let third = snapshot(dir.path(), &[]).unwrap();
+
+        // Snapshots taken this quickly mostly share a second
+        let runs = list_runs(dir.path()).unwrap();
+        assert_eq!(runs.len(), 3, "{:?}", runs);
+        assert!(runs.contains(&first) && runs.contains(&second) && runs.contains(&third));
+        let read = |id: &str| fs::read_to_string(dir.path().join(HISTORY_DIR).join(id).join("overview.md")).unwrap();
+        assert_eq!(read(&first), "# Overview\n");
+        assert_eq!(read(&second), "# Overview, again\n");
+    }
+
+    #[test]
+    fn only_archived_runs_resolve() {
+        let dir = docpack_with_output();
+        let run = snapshot(dir.path(), &[]).unwrap();
+        fs::create_dir_all(dir.path().join("files")).unwrap();
+
+        assert_eq!(resolve_run(dir.path(), &run).unwrap(), dir.path().join(HISTORY_DIR).join(&run));
+        assert_eq!(resolve_run(dir.path(), CURRENT).unwrap(), dir.path().join("output"));
+        for id in ["..", ".", "../files", "", "20000101T000000Z"] {
+            let error = resolve_run(dir.path(), id).unwrap_err().to_string();
+            assert!(error.starts_with("Unknown run:"), "{}: {}", id, error);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_outputs_are_explained() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let locked = dir.path().join("output/guide");
+        fs::create_dir_all(&locked).unwrap();
+        fs::write(locked.join("intro.md"), "# Intro\n").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // Root reads it anyway, so there is nothing to explain
+        let readable = fs::read_dir(&locked).is_ok();
+
+        let result = collect_files(&dir.path().join("output"));
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        if readable {
+            assert_eq!(result.unwrap().into_iter().collect::<Vec<_>>(), ["guide/intro.md"]);
+        } else {
+            let message = result.unwrap_err().to_string();
+            assert!(message.starts_with(&format!("Permission denied: {}", locked.display())), "{}", message);
+            assert!(message.contains("sudo rm -rf"));
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use log::{error, info, warn};
+use serde_json::Value;
+
+use localdoc::archive::{self, ArchiveStats};
+use localdoc::docpack::{Docpack, DocpackKind};
+
+use crate::commands::{ingest, run, validate};
+use crate::dedupe;
+use crate::error::{LocaldocError, WithPath};
+use crate::hashes;
+use crate::scratch::ScratchDir;
+use crate::size::format_size;
+
+/// Settings for `localdoc pack`, one field per command-line flag.
+pub struct PackOptions<'a> {
+    /// Default: `<name>.docpack` beside the directory
+    pub out: Option<&'a str>,
+    /// Replace an existing archive
+    pub force: bool,
+    pub no_validate: bool,
+}
+
+/// Zip the docpack directory `dir` into an archive, as ingest would have
+/// built it: same compression, same layout, same entry order.
+pub fn pack(dir: &str, opts: &PackOptions) -> Result<(), LocaldocError> {
+    let dir_path = Path::new(dir);
+    if Docpack::open_any(dir_path)?.kind != DocpackKind::Directory {
+        return Err(format!("{} is already an archive; `localdoc unpack` turns it into a directory", dir).into());
+    }
+    let out = match opts.out {
+        Some(out) => PathBuf::from(out),
+        None => dir_path.with_file_name(format!("{}.docpack", run::docpack_name(dir_path))),
+    };
+    if same_path(&out, dir_path) {
+        return Err(format!("{} would be replaced by its own archive; pass -o to name the archive", dir).into());
+    }
+    let parent = out.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::create_dir_all(parent).with_path(parent)?;
+    if parent.canonicalize()?.starts_with(dir_path.canonicalize()?) {
+        return Err(format!("The archive cannot be written inside {}, which it packs", dir).into());
+    }
+    if out.exists() && !opts.force {
+        return Err(format!("{} already exists; pass --force to replace it", out.display()).into());
+    }
+
+    if !opts.no_validate {
+        let (errors, warnings) = validate::preflight(dir_path, false);
+        for warning in &warnings {
+            warn!("{}", warning);
+        }
+        if !errors.is_empty() {
+            for error in &errors {
+                error!("{}", error);
+            }
+            return Err("Validation failed; fix the docpack (see `localdoc validate`) or pass --no-validate to pack it anyway".into());
+        }
+    }
+
+    info!("Packing {}...", dir);
+    let manifest_path = dir_path.join("docpack.json");
+    let manifest: Value = serde_json::from_str(&fs::read_to_string(&manifest_path).with_path(&manifest_path)?)?;
+    // Deduplicating moves files around, so it happens in a copy
+    let stats = if manifest["metadata"]["dedupe"].is_object() {
+        let copy = ScratchDir::new("docpack-pack-")?;
+        run::copy_dir_all(dir_path, copy.path())?;
+        write_archive(copy.path(), &out)?
+    } else {
+        write_archive(dir_path, &out)?
+    };
+
+    info!("✓ Packed {}", out.display());
+    print_summary(&stats, fs::metadata(&out).with_path(&out)?.len());
+    Ok(())
+}
+
+/// Settings for `localdoc unpack`, one field per command-line flag.
+pub struct UnpackOptions<'a> {
+    /// Default: the archive's path without `.docpack`
+    pub out: Option<&'a str>,
+    /// Replace a destination that is not empty
+    pub force: bool,
+}
+
+/// Extract a docpack archive into a directory.
+pub fn unpack(archive_path: &str, opts: &UnpackOptions) -> Result<(), LocaldocError> {
+    let archive_path = Path::new(archive_path);
+    if Docpack::open_any(archive_path)?.kind != DocpackKind::Archive {
+        return Err(format!("{} is already a directory; `localdoc pack` turns it into an archive", archive_path.display()).into());
+    }
+    let out = match opts.out {
+        Some(out) => PathBuf::from(out),
+        None => archive_path.with_file_name(run::docpack_name(archive_path)),
+    };
+    if same_path(&out, archive_path) {
+        return Err(format!("{} would be replaced by its own contents; pass -o to name the directory", archive_path.display()).into());
+    }
+    let occupied = match fs::read_dir(&out) {
+        Ok(mut entries) => entries.next().is_some(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+        Err(_) => true,
+    };
+    if occupied && !opts.force {
+        return Err(format!("{} exists and is not empty; pass --force to replace it", out.display()).into());
+    }
+
+    info!("Unpacking {}...", archive_path.display());
+    // Extracted beside the destination and moved into place, so a damaged
+    // archive leaves an existing directory as it was
+    let parent = out.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::create_dir_all(parent).with_path(parent)?;
+    let staging = tempfile::Builder::new().prefix(".docpack-unpack-").tempdir_in(parent)?;
+    let stats = extract_docpack(archive_path, staging.path(), false)?;
+    if out.is_dir() {
+        fs::remove_dir_all(&out).with_path(&out)?;
+    } else if out.exists() {
+        fs::remove_file(&out).with_path(&out)?;
+    }
+    fs::rename(staging.path(), &out).with_path(staging.path())?;
+
+    info!("✓ Unpacked to {}", out.display());
+    print_summary(&stats, fs::metadata(archive_path).with_path(archive_path)?.len());
+    Ok(())
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn print_summary(stats: &ArchiveStats, archive_bytes: u64) {
+    info!("  Entries: {}", stats.entries);
+    info!("  Size:    {} ({} compressed)", format_size(stats.bytes), format_size(archive_bytes));
+    if archive_bytes > 0 {
+        info!("  Ratio:   {:.1}x", stats.bytes as f64 / archive_bytes as f64);
+    }
+}
+
+/// Extract a docpack archive, restoring the usual `files/` layout if it
+/// was built with `--dedupe`.
+pub fn extract_docpack(zip_path: &Path, extract_to: &Path, quiet: bool) -> Result<ArchiveStats, LocaldocError> {
+    let stats = extract_zip(zip_path, extract_to)?;
+    if dedupe::materialize(extract_to)? && !quiet {
+        info!("  Restored files/ from deduplicated blobs");
+    }
+    Ok(stats)
+}
+
+/// Extract a zip archive with the library's path and size checks.
+pub fn extract_zip(zip_path: &Path, extract_to: &Path) -> Result<ArchiveStats, LocaldocError> {
+    let file = fs::File::open(zip_path).with_path(zip_path)?;
+    let mut sink = archive::DirSink { root: extract_to.to_path_buf() };
+
+    archive::extract_entries(file, &mut sink, &archive::Limits::default())
+        .map_err(|source| LocaldocError::Archive { path: zip_path.to_path_buf(), source })
+}
+
+/// Write the docpack in `work_dir` to the archive at `docpack_path`, using
+/// the compression and layout recorded in its manifest. A deduplicated
+/// docpack is rearranged into that layout in place, so `work_dir` should
+/// be a copy. The archive is written next to `docpack_path` and renamed
+/// over it, so a failure leaves an existing archive intact.
+pub fn write_archive(work_dir: &Path, docpack_path: &Path) -> Result<ArchiveStats, LocaldocError> {
+    // Untyped: with --no-validate, a manifest the types reject is packed as is
+    let manifest_path = work_dir.join("docpack.json");
+    let mut manifest: Value = serde_json::from_str(&fs::read_to_string(&manifest_path).with_path(&manifest_path)?)?;
+    let options = ingest::recorded_compression(&manifest);
+
+    if manifest["metadata"]["dedupe"].is_object() {
+        let hashes_path = work_dir.join("index").join(hashes::FILE_NAME);
+        let recorded = fs::read_to_string(&hashes_path).with_path(&hashes_path)?;
+        let file_hashes = hashes::from_json(&serde_json::from_str(&recorded)?)?;
+        manifest["metadata"]["dedupe"] = dedupe::pack_dir(work_dir, &file_hashes)?;
+        fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?).with_path(&manifest_path)?;
+    }
+
+    let parent = docpack_path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let temp = tempfile::NamedTempFile::new_in(parent)?;
+    let (_, stats) = archive::build_archive(archive::dir_entries(work_dir), temp.as_file(), &options)?;
+    temp.persist(docpack_path).map_err(|e| e.error).with_path(docpack_path)?;
+    Ok(stats)
+}
@@ -0,0 +1,19 @@
+use crate::error::LocaldocError;
+use crate::plugins;
+
+pub fn list() -> Result<(), LocaldocError> {
+    let found = plugins::discover();
+
+    if found.is_empty() {
+        println!("No plugins found (executables named {}* on PATH)", plugins::PREFIX);
+        return Ok(());
+    }
+
+    println!("🔌 Plugins");
+    println!("{}", "─".repeat(60));
+    for plugin in &found {
+        println!("  • {:<20} {}", plugin.name, plugin.path.display());
+    }
+
+    Ok(())
+}
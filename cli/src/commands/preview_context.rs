@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use localdoc::docpack::Docpack;
+use log::info;
+
+use crate::context;
+use crate::error::LocaldocError;
+
+pub fn run(docpack: &str, task: Option<&str>, limit: usize, json: bool) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+
+    Docpack::open_dir(docpack_path)?;
+
+    let preview = context::preview(docpack_path, task, limit)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&preview)?);
+        return Ok(());
+    }
+
+    info!("Agent context for: {}", docpack);
+    if let Some(id) = task {
+        info!("Task: {}", id);
+    }
+    info!("");
+
+    info!("🔧 Tools");
+    info!("{}", "─".repeat(60));
+    let tools = preview["tools"].as_array().cloned().unwrap_or_default();
+    if tools.is_empty() {
+        info!("(none)");
+    }
+    for tool in &tools {
+        info!("  - {}", tool.as_str().unwrap_or("unknown"));
+    }
+    info!("");
+
+    info!("⚖️  Constraints");
+    info!("{}", "─".repeat(60));
+    match preview["constraints"].as_object() {
+        Some(constraints) if !constraints.is_empty() => {
+            for (key, value) in constraints {
+                info!("  {}: {}", key, value);
+            }
+        }
+        _ => info!("(none)"),
+    }
+    info!("");
+
+    info!("📂 Mounts");
+    info!("{}", "─".repeat(60));
+    for mount in preview["mounts"].as_array().into_iter().flatten() {
+        info!(
+            "  {} → {} ({})",
+            mount["host"].as_str().unwrap_or(""),
+            mount["container"].as_str().unwrap_or(""),
+            if mount["writable"].as_bool() == Some(true) { "read-write" } else { "read-only" }
+        );
+    }
+    for area in preview["access"].as_array().into_iter().flatten() {
+        info!(
+            "    {:<28} {}",
+            area["path"].as_str().unwrap_or(""),
+            if area["writable"].as_bool() == Some(true) { "rw" } else { "ro" }
+        );
+    }
+    info!("");
+
+    info!("📖 Reading order hints");
+    info!("{}", "─".repeat(60));
+    let hints = preview["reading_order"].as_array().cloned().unwrap_or_default();
+    if hints.is_empty() {
+        info!("(none)");
+    }
+    for (i, hint) in hints.iter().enumerate() {
+        info!("  {}. {}", i + 1, hint.as_str().unwrap_or(""));
+    }
+    info!("");
+
+    info!("💬 Prompt");
+    info!("{}", "─".repeat(60));
+    info!("{}", preview["prompt"].as_str().unwrap_or(""));
+
+    info!("📁 list_files(\".\")");
+    info!("{}", "─".repeat(60));
+    let files = preview["files"].as_array().cloned().unwrap_or_default();
+    for file in &files {
+        info!("  {}", file.as_str().unwrap_or(""));
+    }
+    let total = preview["total_files"].as_u64().unwrap_or(0);
+    if total as usize > files.len() {
+        info!("  ... and {} more ({} files total)", total as usize - files.len(), total);
+    }
+
+    Ok(())
+}
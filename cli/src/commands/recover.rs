@@ -0,0 +1,663 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use docpack_core::{Environment, Manifest, Strictness};
+use log::{info, warn};
+use serde_json::json;
+
+use crate::archive::{Limits, MIN_RATIO_CAP};
+use crate::commands::update;
+use crate::error::{LocaldocError, WithPath};
+use crate::hashes::{self, FileHash};
+use crate::progress::Progress;
+use crate::tools;
+
+const LOCAL_HEADER_SIG: u32 = 0x04034b50;
+const DATA_DESCRIPTOR_SIG: u32 = 0x08074b50;
+const LOCAL_HEADER_LEN: u64 = 30;
+const SCAN_CHUNK: usize = 64 * 1024;
+/// Extra field holding the real sizes of an entry whose header says 0xFFFFFFFF
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+const ZIP64_MARKER: u32 = 0xFFFF_FFFF;
+
+/// Outcome for a single local file header found while scanning.
+struct EntryReport {
+    name: String,
+    offset: u64,
+    result: Result<u64, String>,
+}
+
+pub fn run(docpack: &str, out: &str) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+
+    if !docpack_path.is_file() {
+        return Err(LocaldocError::SourceNotFound { what: "Docpack archive", path: docpack_path.to_path_buf() });
+    }
+
+    let out_dir = Path::new(out);
+    if out_dir.exists() && fs::read_dir(out_dir).with_path(out_dir)?.next().is_some() {
+        return Err(format!("Output directory is not empty: {}", out).into());
+    }
+    fs::create_dir_all(out_dir).with_path(out_dir)?;
+
+    info!("Recovering .docpack: {}", docpack);
+    info!("Scanning for local file headers...\n");
+
+    let reports = scan_archive(docpack_path, out_dir, &Limits::default())?;
+
+    let recovered: Vec<&EntryReport> = reports.iter().filter(|r| r.result.is_ok()).collect();
+    let lost: Vec<&EntryReport> = reports.iter().filter(|r| r.result.is_err()).collect();
+
+    if !recovered.is_empty() {
+        info!("✓ Recovered ({}):", recovered.len());
+        for report in &recovered {
+            if let Ok(size) = report.result {
+                info!("  • {} ({} bytes)", report.name, size);
+            }
+        }
+        info!("");
+    }
+
+    if !lost.is_empty() {
+        info!("❌ Lost ({}):", lost.len());
+        for report in &lost {
+            if let Err(reason) = &report.result {
+                info!("  • {} at offset {}: {}", report.name, report.offset, reason);
+            }
+        }
+        info!("");
+    }
+
+    // Make sure the result is a usable directory docpack
+    for dir in ["files", "index", "output"] {
+        let path = out_dir.join(dir);
+        fs::create_dir_all(&path).with_path(&path)?;
+    }
+
+    let had_manifest = out_dir.join("docpack.json").is_file();
+    let file_hashes = write_hashes(out_dir)?;
+    write_recovered_manifest(out_dir, docpack, &recovered, &lost, &file_hashes)?;
+
+    if !had_manifest {
+        warn!("docpack.json was not recoverable; wrote a placeholder manifest");
+    }
+
+    info!("✓ Recovered docpack written to: {}", out_dir.display());
+    info!("  {} entries recovered, {} lost", recovered.len(), lost.len());
+    info!("\nNext steps:");
+    info!("  1. Run: localdoc validate {}", out_dir.display());
+    info!("  2. Review docpack.json (metadata.recovered is set)");
+
+    Ok(())
+}
+
+/// Bytes the entries of one archive may still write between them, from
+/// [`Limits::max_total_size`]. Each entry is also capped on its own, but
+/// many entries under that cap could still fill the disk together.
+struct Budget {
+    remaining: u64,
+    exhausted: bool,
+}
+
+/// An entry's output file, charging every byte written to the [`Budget`].
+struct Budgeted<'a> {
+    file: fs::File,
+    budget: &'a mut Budget,
+}
+
+impl Write for Budgeted<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() as u64 > self.budget.remaining {
+            self.budget.exhausted = true;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "total recovered size limit reached"));
+        }
+        let n = self.file.write(buf)?;
+        self.budget.remaining -= n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Walk the raw archive bytes looking for local file headers and extract
+/// every entry that can be decoded. Only one chunk is held in memory at a
+/// time; entry data is streamed straight to disk. Scanning stops once the
+/// entries have written `limits.max_total_size` bytes between them.
+fn scan_archive(archive: &Path, out_dir: &Path, limits: &Limits) -> Result<Vec<EntryReport>, LocaldocError> {
+    let mut file = fs::File::open(archive).with_path(archive)?;
+    let archive_len = file.metadata()?.len();
+    let mut reports = Vec::new();
+    let mut pos: u64 = 0;
+    let mut chunk = vec![0u8; SCAN_CHUNK];
+    let mut budget = Budget { remaining: limits.max_total_size, exhausted: false };
+
+    while pos + LOCAL_HEADER_LEN <= archive_len {
+        if budget.exhausted {
+            warn!(
+                "Stopped at offset {} of {}: recovered entries reached the {} byte total size limit",
+                pos, archive_len, limits.max_total_size
+            );
+            break;
+        }
+
+        file.seek(SeekFrom::Start(pos))?;
+        let read = read_fully(&mut file, &mut chunk)?;
+        if read < 4 {
+            break;
+        }
+
+        let found = chunk[..read]
+            .windows(4)
+            .position(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]) == LOCAL_HEADER_SIG);
+
+        let offset = match found {
+            Some(i) => pos + i as u64,
+            None => {
+                // Keep the last three bytes in case a signature straddles chunks
+                pos += (read as u64).saturating_sub(3).max(1);
+                continue;
+            }
+        };
+
+        match recover_entry(&mut file, offset, out_dir, limits, &mut budget) {
+            Ok((report, next)) => {
+                reports.push(report);
+                pos = next;
+            }
+            Err(_) => {
+                // Not a real header (or unreadable); keep scanning after it
+                pos = offset + 1;
+            }
+        }
+    }
+
+    Ok(reports)
+}
+
+/// Parse the local header at `offset` and extract its entry. Returns the
+/// report plus the offset at which scanning should resume. An `Err` means
+/// the bytes at `offset` do not form a plausible header at all.
+fn recover_entry(
+    file: &mut fs::File,
+    offset: u64,
+    out_dir: &Path,
+    limits: &Limits,
+    budget: &mut Budget,
+) -> io::Result<(EntryReport, u64)> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut header = [0u8; LOCAL_HEADER_LEN as usize];
+    file.read_exact(&mut header)?;
+
+    let flags = u16_at(&header, 6);
+    let method = u16_at(&header, 8);
+    let header_crc = u32_at(&header, 14);
+    let mut header_csize = u32_at(&header, 18) as u64;
+    let mut header_usize = u32_at(&header, 22) as u64;
+    let name_len = u16_at(&header, 26) as usize;
+    let extra_len = u16_at(&header, 28) as usize;
+
+    if name_len == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "empty entry name"));
+    }
+
+    let mut name_bytes = vec![0u8; name_len];
+    file.read_exact(&mut name_bytes)?;
+    let name = String::from_utf8_lossy(&name_bytes).into_owned();
+
+    // Signatures also turn up inside compressed data; a control character
+    // in the name is a strong sign this is not a real header
+    if name.chars().any(|c| c.is_control()) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "implausible entry name"));
+    }
+
+    let mut extra = vec![0u8; extra_len];
+    file.read_exact(&mut extra)?;
+    let data_start = offset + LOCAL_HEADER_LEN + (name_len + extra_len) as u64;
+
+    // Zip64 entries carry their sizes in an extra field, and 8-byte sizes
+    // in their data descriptor
+    let zip64 = zip64_sizes(&extra);
+    if let Some((usize64, csize64)) = zip64 {
+        header_usize = usize64;
+        header_csize = csize64;
+    }
+
+    let has_descriptor = flags & 0x0008 != 0;
+    let skip_to = if has_descriptor { offset + 1 } else { data_start + header_csize };
+
+    let mut report = EntryReport { name: name.clone(), offset, result: Ok(0) };
+
+    if zip64.is_none() && (header_csize == ZIP64_MARKER as u64 || header_usize == ZIP64_MARKER as u64) && !has_descriptor {
+        report.result = Err("zip64 sizes missing from the local header".to_string());
+        return Ok((report, offset + 1));
+    }
+
+    if flags & 0x0001 != 0 {
+        report.result = Err("entry is encrypted".to_string());
+        return Ok((report, skip_to));
+    }
+
+    if method != 0 && method != 8 {
+        report.result = Err(format!("unsupported compression method {}", method));
+        return Ok((report, skip_to));
+    }
+
+    let Some(target) = safe_join(out_dir, &name) else {
+        report.result = Err("unsafe path (would write outside output directory)".to_string());
+        return Ok((report, skip_to));
+    };
+
+    if zip64.is_some() && has_descriptor && method == 0 {
+        report.result = Err("stored zip64 entry with a data descriptor is not supported".to_string());
+        return Ok((report, skip_to));
+    }
+
+    if name.ends_with('/') {
+        fs::create_dir_all(&target)?;
+        return Ok((report, data_start));
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let partial = target.with_file_name(format!(
+        ".{}.partial",
+        target.file_name().and_then(|n| n.to_str()).unwrap_or("entry")
+    ));
+
+    file.seek(SeekFrom::Start(data_start))?;
+    let extracted = {
+        let mut out_file = Budgeted { file: fs::File::create(&partial)?, budget: &mut *budget };
+        let mut reader = BufReader::new(&mut *file);
+        if method == 8 {
+            inflate_entry(&mut reader, &mut out_file, limits, has_descriptor, zip64.is_some(), header_csize, header_usize)
+        } else if has_descriptor {
+            copy_stored_until_descriptor(&mut reader, &mut out_file)
+        } else {
+            copy_stored(&mut reader, &mut out_file, header_csize)
+        }
+    };
+
+    let (next, outcome) = match extracted {
+        Ok(extracted) => {
+            let next = data_start + extracted.compressed + extracted.descriptor_len;
+            let (expected_crc, expected_size) = if has_descriptor {
+                (extracted.descriptor_crc, extracted.descriptor_usize)
+            } else {
+                (Some(header_crc), Some(header_usize))
+            };
+
+            let outcome = match (expected_crc, expected_size) {
+                (Some(crc), _) if crc != extracted.crc => Err(format!(
+                    "CRC mismatch (expected {:08x}, got {:08x})",
+                    crc, extracted.crc
+                )),
+                (_, Some(size)) if size != extracted.size => Err(format!(
+                    "size mismatch (expected {}, got {})",
+                    size, extracted.size
+                )),
+                (None, _) => Err("data descriptor missing or truncated".to_string()),
+                _ => Ok(extracted.size),
+            };
+            (next, outcome)
+        }
+        Err(e) if budget.exhausted => (skip_to, Err(e.to_string())),
+        Err(e) => (skip_to, Err(format!("truncated or corrupt data: {}", e))),
+    };
+
+    // A file that can't be moved into place is lost like any other, and
+    // the partial file goes either way
+    let outcome = outcome.and_then(|size| {
+        fs::rename(&partial, &target).map(|()| size).map_err(|e| format!("cannot write {}: {}", target.display(), e))
+    });
+    if outcome.is_err() && partial.exists() {
+        if let Err(e) = fs::remove_file(&partial) {
+            warn!("Could not remove {}: {}", partial.display(), e);
+        }
+    }
+
+    report.result = outcome;
+    Ok((report, next))
+}
+
+/// What was learned from streaming one entry's data to disk.
+struct Extracted {
+    /// Compressed bytes consumed from the archive
+    compressed: u64,
+    /// Uncompressed bytes written
+    size: u64,
+    crc: u32,
+    descriptor_len: u64,
+    descriptor_crc: Option<u32>,
+    descriptor_usize: Option<u64>,
+}
+
+/// Inflate one entry to `out`. Output is capped at the declared size, or,
+/// when a data descriptor means there is none, at the compression ratio and
+/// entry size extraction allows, so a zip bomb cannot fill the disk.
+fn inflate_entry<R: io::BufRead>(
+    reader: &mut R,
+    out: &mut impl Write,
+    limits: &Limits,
+    has_descriptor: bool,
+    zip64: bool,
+    csize: u64,
+    usize_hint: u64,
+) -> io::Result<Extracted> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut written: u64 = 0;
+
+    let consumed = {
+        let limited: Box<dyn io::BufRead + '_> = if has_descriptor {
+            Box::new(&mut *reader)
+        } else {
+            Box::new(io::BufReader::new((&mut *reader).take(csize)))
+        };
+        let mut decoder = flate2::bufread::DeflateDecoder::new(limited);
+        let mut buf = vec![0u8; SCAN_CHUNK];
+        loop {
+            let n = decoder.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            let cap = if has_descriptor {
+                decoder.total_in().max(1).saturating_mul(limits.max_ratio).max(MIN_RATIO_CAP)
+            } else {
+                usize_hint
+            };
+            if written + n as u64 > cap.min(limits.max_entry_size) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "inflated past the size limit"));
+            }
+            hasher.update(&buf[..n]);
+            out.write_all(&buf[..n])?;
+            written += n as u64;
+        }
+        decoder.total_in()
+    };
+
+    let mut extracted = Extracted {
+        compressed: consumed,
+        size: written,
+        crc: hasher.finalize(),
+        descriptor_len: 0,
+        descriptor_crc: None,
+        descriptor_usize: None,
+    };
+
+    if has_descriptor {
+        read_descriptor(reader, zip64, &mut extracted);
+    }
+
+    Ok(extracted)
+}
+
+fn copy_stored<R: Read>(reader: &mut R, out: &mut impl Write, csize: u64) -> io::Result<Extracted> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut limited = reader.take(csize);
+    let mut buf = vec![0u8; SCAN_CHUNK];
+    let mut written: u64 = 0;
+
+    loop {
+        let n = limited.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        out.write_all(&buf[..n])?;
+        written += n as u64;
+    }
+
+    if written != csize {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "entry data truncated"));
+    }
+
+    Ok(Extracted {
+        compressed: written,
+        size: written,
+        crc: hasher.finalize(),
+        descriptor_len: 0,
+        descriptor_crc: None,
+        descriptor_usize: None,
+    })
+}
+
+/// Stored entries with a data descriptor have no length up front, so copy
+/// byte by byte until a descriptor whose CRC and size match what has been
+/// written so far. Only a 16-byte lookahead ring is buffered.
+fn copy_stored_until_descriptor<R: Read>(reader: &mut R, out: &mut impl Write) -> io::Result<Extracted> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut ring = [0u8; 16];
+    // Index of the oldest byte in `ring`, and how many bytes it holds
+    let (mut head, mut filled) = (0, 0);
+    let mut pending = Vec::with_capacity(SCAN_CHUNK);
+    let mut written: u64 = 0;
+    let mut byte = [0u8; 1];
+
+    loop {
+        if filled == 16 {
+            let at = |i: usize| ring[(head + i) % 16];
+            if u32::from_le_bytes([at(0), at(1), at(2), at(3)]) == DATA_DESCRIPTOR_SIG {
+                let window: [u8; 16] = std::array::from_fn(at);
+                let crc = u32_at(&window, 4);
+                let csize = u32_at(&window, 8) as u64;
+                let size = u32_at(&window, 12) as u64;
+                let mut probe = hasher.clone();
+                probe.update(&pending);
+                let total = written + pending.len() as u64;
+                if csize == total && size == total && probe.clone().finalize() == crc {
+                    out.write_all(&pending)?;
+                    return Ok(Extracted {
+                        compressed: total,
+                        size: total,
+                        crc,
+                        descriptor_len: 16,
+                        descriptor_crc: Some(crc),
+                        descriptor_usize: Some(size),
+                    });
+                }
+            }
+            pending.push(ring[head]);
+            head = (head + 1) % 16;
+            filled -= 1;
+            if pending.len() == SCAN_CHUNK {
+                hasher.update(&pending);
+                out.write_all(&pending)?;
+                written += pending.len() as u64;
+                pending.clear();
+            }
+        }
+
+        if reader.read(&mut byte)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "no matching data descriptor"));
+        }
+        ring[(head + filled) % 16] = byte[0];
+        filled += 1;
+    }
+}
+
+/// Read a data descriptor following deflated data. The signature is
+/// optional per the spec, so accept both forms; zip64 entries have 8-byte
+/// sizes in theirs.
+fn read_descriptor<R: Read>(reader: &mut R, zip64: bool, extracted: &mut Extracted) {
+    let size_len = if zip64 { 8 } else { 4 };
+    let mut buf = [0u8; 24];
+    if reader.read_exact(&mut buf[..4]).is_err() {
+        return;
+    }
+    let start = if u32_at(&buf, 0) == DATA_DESCRIPTOR_SIG { 4 } else { 0 };
+    let len = start + 4 + 2 * size_len;
+    if reader.read_exact(&mut buf[4..len]).is_err() {
+        return;
+    }
+
+    let usize_at = start + 4 + size_len;
+    extracted.descriptor_len = len as u64;
+    extracted.descriptor_crc = Some(u32_at(&buf, start));
+    extracted.descriptor_usize =
+        Some(if zip64 { u64_at(&buf, usize_at) } else { u32_at(&buf, usize_at) as u64 });
+}
+
+/// The uncompressed and compressed sizes in a local header's zip64 extra
+/// field, if it has one.
+fn zip64_sizes(extra: &[u8]) -> Option<(u64, u64)> {
+    let mut rest = extra;
+    while rest.len() >= 4 {
+        let (id, len) = (u16_at(rest, 0), u16_at(rest, 2) as usize);
+        let data = rest.get(4..4 + len)?;
+        if id == ZIP64_EXTRA_ID && len >= 16 {
+            return Some((u64_at(data, 0), u64_at(data, 8)));
+        }
+        rest = &rest[4 + len..];
+    }
+    None
+}
+
+/// Join an archive entry name onto `base`, rejecting anything that could
+/// escape it (absolute paths, `..`, drive prefixes).
+fn safe_join(base: &Path, name: &str) -> Option<PathBuf> {
+    let normalized = name.replace('\\', "/");
+    let mut path = base.to_path_buf();
+    let mut depth = 0;
+
+    for component in Path::new(&normalized).components() {
+        match component {
+            Component::Normal(part) => {
+                path.push(part);
+                depth += 1;
+            }
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    if depth == 0 {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+/// Regenerate `index/hashes.json` from the recovered `files/`, so the
+/// result verifies against what was actually salvaged.
+fn write_hashes(out_dir: &Path) -> Result<BTreeMap<String, FileHash>, LocaldocError> {
+    let to_hash = update::existing_files(&out_dir.join("files"))?;
+    let mut progress = Progress::new("Hashing", Some(to_hash.len() as u64), true);
+    let file_hashes = hashes::hash_files(&to_hash, &mut progress)?;
+    let path = out_dir.join("index").join(hashes::FILE_NAME);
+    fs::write(&path, serde_json::to_string_pretty(&hashes::to_json(&file_hashes))?).with_path(&path)?;
+    Ok(file_hashes)
+}
+
+fn write_recovered_manifest(
+    out_dir: &Path,
+    source: &str,
+    recovered: &[&EntryReport],
+    lost: &[&EntryReport],
+    file_hashes: &BTreeMap<String, FileHash>,
+) -> Result<(), LocaldocError> {
+    let manifest_path = out_dir.join("docpack.json");
+
+    let mut manifest = Manifest::load(&manifest_path, Strictness::Lenient).unwrap_or_else(|_| {
+        let name = Path::new(source).file_stem().and_then(|n| n.to_str()).unwrap_or("recovered");
+        let metadata = json!({
+            "created": chrono::Utc::now().to_rfc3339(),
+            "creator": "localdoc-cli",
+            "source_type": "recovered",
+            "language": "unknown"
+        });
+        Manifest {
+            name: Some(name.to_string()),
+            description: Some("Recovered docpack".to_string()),
+            environment: Environment { tools: tools::defaults(), ..Environment::default() },
+            metadata: metadata.as_object().cloned(),
+            ..Manifest::default()
+        }
+    });
+
+    let metadata = manifest.metadata_mut();
+    metadata.insert("recovered".to_string(), json!(true));
+    metadata.insert("hashes".to_string(), hashes::metadata(file_hashes));
+    metadata.insert(
+        "recovery".to_string(),
+        json!({
+            "recovered_at": chrono::Utc::now().to_rfc3339(),
+            "source": source,
+            "entries_recovered": recovered.len(),
+            "entries_lost": lost.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(),
+        }),
+    );
+
+    manifest.save(&manifest_path)?;
+    Ok(())
+}
+
+fn read_fully<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..])? {
+            0 => break,
+            n => total += n,
+        }
+    }
+    Ok(total)
+}
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+fn u64_at(buf: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(buf[at..at + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut entry = Vec::new();
+        entry.extend(LOCAL_HEADER_SIG.to_le_bytes());
+        entry.extend([20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        entry.extend(crc32fast::hash(data).to_le_bytes());
+        entry.extend((data.len() as u32).to_le_bytes());
+        entry.extend((data.len() as u32).to_le_bytes());
+        entry.extend((name.len() as u16).to_le_bytes());
+        entry.extend(0u16.to_le_bytes());
+        entry.extend(name.as_bytes());
+        entry.extend(data);
+        entry
+    }
+
+    #[test]
+    fn entries_share_one_total_size_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("app.docpack");
+        let bytes: Vec<u8> = ["files/a.txt", "files/b.txt", "files/c.txt", "files/d.txt"]
+            .iter()
+            .flat_map(|name| stored(name, &[b'x'; 100]))
+            .collect();
+        fs::write(&archive, bytes).unwrap();
+        let out = dir.path().join("out");
+        let limits = Limits { max_total_size: 250, ..Limits::default() };
+
+        let reports = scan_archive(&archive, &out, &limits).unwrap();
+        let results: Vec<(&str, Result<u64, String>)> = reports.iter().map(|r| (r.name.as_str(), r.result.clone())).collect();
+        assert_eq!(
+            results,
+            [
+                ("files/a.txt", Ok(100)),
+                ("files/b.txt", Ok(100)),
+                ("files/c.txt", Err("total recovered size limit reached".to_string())),
+            ]
+        );
+        assert!(!out.join("files/c.txt").exists() && !out.join("files/.c.txt.partial").exists());
+    }
+}
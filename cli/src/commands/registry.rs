@@ -0,0 +1,454 @@
+//! `localdoc push` and `localdoc pull`: copy a docpack archive to and from
+//! a registry, which is any HTTP server that stores what is PUT to a URL
+//! and serves it back on GET.
+//!
+//! Every upload carries the archive's SHA-256 in [`CHECKSUM_HEADER`], and
+//! a registry that sends the header back lets `pull` verify the download.
+//! Archives larger than `--chunk-size` are uploaded in parts with
+//! `Content-Range` headers, the resumable protocol of Google Cloud
+//! Storage: the registry answers each part but the last with
+//! `308 Resume Incomplete` and a `Range` header for what it has kept, and
+//! after a failure an empty PUT with `Content-Range: bytes */<size>` asks
+//! it where to resume. Downloads resume with a `Range` request from what
+//! an interrupted attempt left in `<out>.part`.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use localdoc::docpack::{Docpack, DocpackKind};
+use log::{info, warn};
+
+use crate::commands::{pack, run};
+use crate::download;
+use crate::error::{LocaldocError, WithPath};
+use crate::hashes;
+use crate::progress::Progress;
+use crate::scratch::ScratchDir;
+use crate::size::format_size;
+
+/// Read when no `registry.token` is configured.
+pub const TOKEN_ENV: &str = "LOCALDOC_REGISTRY_TOKEN";
+
+/// The archive's SHA-256 in hex, sent with uploads and checked against
+/// downloads.
+pub const CHECKSUM_HEADER: &str = "X-Checksum-Sha256";
+
+/// What went wrong in a transfer, which decides the exit code.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Failure {
+    /// The registry wants a token, or refused the one sent
+    Auth,
+    /// The connection failed, or the registry had a server error
+    Network,
+    /// The download is not the archive the registry described
+    Checksum,
+}
+
+/// A failed push or pull, with an exit code per [`Failure`] so scripts can
+/// tell a bad token from a flaky network from a corrupt download.
+#[derive(Debug)]
+pub struct TransferError {
+    pub failure: Failure,
+    message: String,
+}
+
+impl TransferError {
+    pub fn exit_code(&self) -> i32 {
+        match self.failure {
+            Failure::Auth => 3,
+            Failure::Network => 4,
+            Failure::Checksum => 5,
+        }
+    }
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for TransferError {}
+
+fn failed(failure: Failure, message: String) -> LocaldocError {
+    TransferError { failure, message }.into()
+}
+
+/// Settings for `localdoc push`, one field per command-line flag.
+pub struct PushOptions<'a> {
+    /// Default: `$LOCALDOC_REGISTRY_TOKEN`
+    pub token: Option<&'a str>,
+    /// Larger archives are uploaded in resumable parts of this size
+    pub chunk_size: u64,
+    pub retries: u32,
+    pub quiet: bool,
+}
+
+/// Upload `docpack` to `url`. A directory is packed into an archive first.
+pub fn push(docpack: &str, url: &str, opts: &PushOptions) -> Result<(), LocaldocError> {
+    check_url(url)?;
+    let docpack_path = Path::new(docpack);
+    let scratch;
+    let archive = if Docpack::open_any(docpack_path)?.kind == DocpackKind::Directory {
+        // Packed from a copy, as packing a deduplicated docpack rearranges it
+        scratch = ScratchDir::new("docpack-push-")?;
+        let name = run::docpack_name(docpack_path);
+        let copy = scratch.path().join(&name);
+        run::copy_dir_all(docpack_path, &copy)?;
+        let archive = scratch.path().join(format!("{}.docpack", name));
+        if !opts.quiet {
+            info!("Packing {}...", docpack);
+        }
+        pack::write_archive(&copy, &archive)?;
+        archive
+    } else {
+        docpack_path.to_path_buf()
+    };
+
+    let size = fs::metadata(&archive).with_path(&archive)?.len();
+    let sha256 = hashes::hash_reader(fs::File::open(&archive).with_path(&archive)?)?.sha256;
+    let token = resolve_token(opts.token);
+    // A 308 answers a part; it is not a redirect
+    let agent = download::client().redirects(0).build();
+    let upload = Upload { agent: &agent, url, token: token.as_deref(), archive: &archive, size, sha256: &sha256 };
+
+    info!("Pushing {} ({}) to {}", docpack, format_size(size), url);
+    with_retries(opts.retries, |retry| {
+        let mut progress = Progress::bytes("Uploading", Some(size), opts.quiet);
+        if size <= opts.chunk_size {
+            upload.whole(&mut progress)?;
+        } else {
+            let start = if retry == 0 { 0 } else { upload.kept()? };
+            upload.parts(start, opts.chunk_size, &mut progress)?;
+        }
+        progress.finish();
+        Ok(())
+    })?;
+    info!("✓ Pushed {} (sha256 {})", url, sha256);
+    Ok(())
+}
+
+/// One archive on its way to one URL.
+struct Upload<'a> {
+    agent: &'a ureq::Agent,
+    url: &'a str,
+    token: Option<&'a str>,
+    archive: &'a Path,
+    size: u64,
+    sha256: &'a str,
+}
+
+impl Upload<'_> {
+    fn put(&self, length: u64) -> ureq::Request {
+        request(self.agent, "PUT", self.url, self.token)
+            .set("Content-Type", "application/zip")
+            .set("Content-Length", &length.to_string())
+            .set(CHECKSUM_HEADER, self.sha256)
+    }
+
+    /// Send the archive in one request.
+    fn whole(&self, progress: &mut Progress) -> Result<(), LocaldocError> {
+        let body = Tracked { inner: fs::File::open(self.archive).with_path(self.archive)?, progress, done: 0 };
+        let response = self.put(self.size).send(body).map_err(|e| request_error(self.url, e, self.token))?;
+        match response.status() {
+            200..=299 => Ok(()),
+            code => Err(format!("{} answered the upload with HTTP {} {}", self.url, code, response.status_text()).into()),
+        }
+    }
+
+    /// Send the archive from `start` on in parts of `chunk_size`.
+    fn parts(&self, mut start: u64, chunk_size: u64, progress: &mut Progress) -> Result<(), LocaldocError> {
+        let mut file = fs::File::open(self.archive).with_path(self.archive)?;
+        while start < self.size {
+            let end = (start + chunk_size).min(self.size);
+            file.seek(SeekFrom::Start(start))?;
+            let body = Tracked { inner: (&mut file).take(end - start), progress: &mut *progress, done: start };
+            let response = self
+                .put(end - start)
+                .set("Content-Range", &format!("bytes {}-{}/{}", start, end - 1, self.size))
+                .send(body)
+                .map_err(|e| request_error(self.url, e, self.token))?;
+            let next = match response.status() {
+                308 => kept_range(&response),
+                200..=299 if end == self.size => self.size,
+                200..=299 => {
+                    return Err(format!(
+                        "{} answered a part as if it were the whole archive, so it does not accept uploads in parts; \
+                         pass --chunk-size {} or more to send it in one request",
+                        self.url, self.size
+                    )
+                    .into())
+                }
+                code => return Err(format!("{} answered a part with HTTP {} {}", self.url, code, response.status_text()).into()),
+            };
+            if next <= start {
+                return Err(failed(
+                    Failure::Network,
+                    format!("{} kept none of the part at {} of {} bytes", self.url, start, self.size),
+                ));
+            }
+            start = next;
+        }
+        Ok(())
+    }
+
+    /// How much of an interrupted upload the registry has kept.
+    fn kept(&self) -> Result<u64, LocaldocError> {
+        let response = request(self.agent, "PUT", self.url, self.token)
+            .set("Content-Range", &format!("bytes */{}", self.size))
+            .send_bytes(&[])
+            .map_err(|e| request_error(self.url, e, self.token))?;
+        Ok(match response.status() {
+            308 => kept_range(&response),
+            200..=299 => self.size,
+            _ => 0,
+        })
+    }
+}
+
+/// The byte after the last one a `308 Resume Incomplete` response's
+/// `Range: bytes=0-<last>` header says the registry has; without the
+/// header it has nothing.
+fn kept_range(response: &ureq::Response) -> u64 {
+    response
+        .header("Range")
+        .and_then(|range| range.trim().strip_prefix("bytes=0-")?.parse::<u64>().ok())
+        .map_or(0, |last| last + 1)
+}
+
+/// Settings for `localdoc pull`, one field per command-line flag.
+pub struct PullOptions<'a> {
+    /// Default: the URL's file name, in the current directory
+    pub out: Option<&'a str>,
+    /// Default: what the registry sends in [`CHECKSUM_HEADER`]
+    pub sha256: Option<&'a str>,
+    /// Default: `$LOCALDOC_REGISTRY_TOKEN`
+    pub token: Option<&'a str>,
+    pub retries: u32,
+    /// Replace an existing file
+    pub force: bool,
+    pub quiet: bool,
+}
+
+/// Download the archive at `url`, verify it and move it into place.
+pub fn pull(url: &str, opts: &PullOptions) -> Result<(), LocaldocError> {
+    check_url(url)?;
+    let out = match opts.out {
+        Some(out) => PathBuf::from(out),
+        None => PathBuf::from(download::file_name(url).ok_or_else(|| format!("{} has no file name to save it as; pass -o", url))?),
+    };
+    if out.is_dir() {
+        return Err(format!("{} is a directory; pass -o with a file name", out.display()).into());
+    }
+    if out.exists() && !opts.force {
+        return Err(format!("{} already exists; pass --force to replace it", out.display()).into());
+    }
+    let expected = match opts.sha256 {
+        Some(sha256) if sha256.len() == 64 && sha256.bytes().all(|b| b.is_ascii_hexdigit()) => Some(sha256.to_ascii_lowercase()),
+        Some(sha256) => return Err(LocaldocError::InvalidArgument(format!("--sha256 must be 64 hex digits, not '{}'", sha256))),
+        None => None,
+    };
+
+    let mut partial = out.clone().into_os_string();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+    let token = resolve_token(opts.token);
+    let agent = download::client().build();
+    if let Ok(metadata) = fs::metadata(&partial) {
+        info!("Resuming from the {} already in {}", format_size(metadata.len()), partial.display());
+    }
+    let advertised = with_retries(opts.retries, |_| fetch_rest(&agent, url, &partial, token.as_deref(), opts.quiet))?;
+
+    let actual = hashes::hash_reader(fs::File::open(&partial).with_path(&partial)?)?;
+    match expected.or(advertised) {
+        Some(expected) if expected != actual.sha256 => {
+            let _ = fs::remove_file(&partial);
+            return Err(failed(
+                Failure::Checksum,
+                format!(
+                    "Checksum mismatch for {}: expected sha256 {}, got {}\nThe download was deleted; pull it again",
+                    url, expected, actual.sha256
+                ),
+            ));
+        }
+        Some(_) => {}
+        None => info!(
+            "⚠️  {} sent no {} header and --sha256 was not given, so the download is not verified",
+            url, CHECKSUM_HEADER
+        ),
+    }
+    if let Err(e) = Docpack::open_any(&partial) {
+        let _ = fs::remove_file(&partial);
+        return Err(format!("{} is not a docpack: {}", url, e).into());
+    }
+    fs::rename(&partial, &out).with_path(&partial)?;
+    info!("✓ Pulled {} to {} ({})", url, out.display(), format_size(actual.size));
+    Ok(())
+}
+
+/// Download what `partial` does not have yet, asking for the rest with a
+/// `Range` header, and return the checksum the registry sent.
+fn fetch_rest(
+    agent: &ureq::Agent,
+    url: &str,
+    partial: &Path,
+    token: Option<&str>,
+    quiet: bool,
+) -> Result<Option<String>, LocaldocError> {
+    let have = fs::metadata(partial).map(|metadata| metadata.len()).unwrap_or(0);
+    let mut req = request(agent, "GET", url, token);
+    if have > 0 {
+        req = req.set("Range", &format!("bytes={}-", have));
+    }
+    let response = match req.call() {
+        // Whatever is there is no prefix of the archive; start over
+        Err(ureq::Error::Status(416, _)) if have > 0 => {
+            fs::remove_file(partial).with_path(partial)?;
+            return fetch_rest(agent, url, partial, token, quiet);
+        }
+        result => result.map_err(|e| request_error(url, e, token))?,
+    };
+    let checksum = response.header(CHECKSUM_HEADER).map(|sha256| sha256.trim().to_ascii_lowercase());
+
+    let resumed = response.status() == 206;
+    let start = if resumed { have } else { 0 };
+    if resumed && response.header("Content-Range").and_then(range_start) != Some(have) {
+        fs::remove_file(partial).with_path(partial)?;
+        return Err(failed(Failure::Network, format!("{} sent a different part than was asked for; starting over", url)));
+    }
+    let total = response.header("Content-Length").and_then(|len| len.parse::<u64>().ok()).map(|len| start + len);
+    let mut file = if resumed { fs::OpenOptions::new().append(true).open(partial)? } else { fs::File::create(partial).with_path(partial)? };
+    let mut reader = response.into_reader();
+    let mut progress = Progress::bytes("Downloading", total, quiet);
+    progress.update(0, start);
+
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut written = start;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                return Err(failed(Failure::Network, format!("Download of {} was interrupted after {} bytes: {}", url, written, e)))
+            }
+        };
+        file.write_all(&buf[..n])?;
+        written += n as u64;
+        progress.update(0, written);
+    }
+    file.flush()?;
+    progress.finish();
+    if let Some(total) = total.filter(|&total| total != written) {
+        return Err(failed(Failure::Network, format!("Download of {} stopped at {} of {} bytes", url, written, total)));
+    }
+    Ok(checksum)
+}
+
+/// The first byte of a `Content-Range: bytes <first>-<last>/<size>` header.
+fn range_start(range: &str) -> Option<u64> {
+    range.trim().strip_prefix("bytes ")?.split('-').next()?.parse().ok()
+}
+
+/// Run `attempt` until it succeeds, fails for a reason other than the
+/// network, or has been retried `retries` times, waiting twice as long
+/// after each failure. `attempt` is told which retry it is, 0 for the
+/// first try.
+fn with_retries<T>(
+    retries: u32,
+    mut attempt: impl FnMut(u32) -> Result<T, LocaldocError>,
+) -> Result<T, LocaldocError> {
+    let mut retry = 0;
+    loop {
+        match attempt(retry) {
+            Err(LocaldocError::Transfer(e)) if retry < retries && e.failure == Failure::Network => {
+                retry += 1;
+                let wait = Duration::from_secs(1 << (retry - 1).min(5));
+                warn!("{}\nRetrying in {}s ({} of {})", e, wait.as_secs(), retry, retries);
+                thread::sleep(wait);
+            }
+            result => return result,
+        }
+    }
+}
+
+fn check_url(url: &str) -> Result<(), LocaldocError> {
+    if url.starts_with("s3://") {
+        return Err(LocaldocError::InvalidArgument(
+            "s3:// URLs are not supported; use the bucket's https:// endpoint or a presigned URL".to_string(),
+        ));
+    }
+    if !download::is_url(url) {
+        return Err(LocaldocError::InvalidArgument(format!("{} is not an http:// or https:// URL", url)));
+    }
+    Ok(())
+}
+
+/// `token`, or else `$LOCALDOC_REGISTRY_TOKEN`.
+fn resolve_token(token: Option<&str>) -> Option<String> {
+    token.map(String::from).or_else(|| std::env::var(TOKEN_ENV).ok()).filter(|token| !token.is_empty())
+}
+
+fn request(agent: &ureq::Agent, method: &str, url: &str, token: Option<&str>) -> ureq::Request {
+    let request = agent.request(method, url);
+    match token {
+        Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+        None => request,
+    }
+}
+
+/// Explain a failed request, as an auth or network [`TransferError`] when
+/// it is one.
+fn request_error(url: &str, e: ureq::Error, token: Option<&str>) -> LocaldocError {
+    match e {
+        ureq::Error::Status(code @ (401 | 403), response) => {
+            let hint = match token {
+                Some(_) => format!("Check the token in ${} or registry.token", TOKEN_ENV),
+                None => format!("Set a token in ${} or with `localdoc config set registry.token <TOKEN>`", TOKEN_ENV),
+            };
+            failed(Failure::Auth, format!("{} refused the request: HTTP {} {}\n{}", url, code, response.status_text(), hint))
+        }
+        ureq::Error::Status(code, response) if code >= 500 || code == 408 || code == 429 => {
+            failed(Failure::Network, format!("{} returned HTTP {} {}", url, code, response.status_text()))
+        }
+        ureq::Error::Status(code, response) => format!("{} returned HTTP {} {}", url, code, response.status_text()).into(),
+        ureq::Error::Transport(transport) => failed(
+            Failure::Network,
+            format!("Could not reach the registry: {}\nCheck your network connection (proxies are read from HTTPS_PROXY/HTTP_PROXY)", transport),
+        ),
+    }
+}
+
+/// Reports what passes through it on a progress line.
+struct Tracked<'a, R> {
+    inner: R,
+    progress: &'a mut Progress,
+    done: u64,
+}
+
+impl<R: Read> Read for Tracked<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.done += n as u64;
+        self.progress.update(0, self.done);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_are_read_from_both_directions() {
+        assert_eq!(range_start("bytes 1024-2047/4096"), Some(1024));
+        assert_eq!(range_start("bytes */4096"), None);
+        assert!(check_url("s3://bucket/app.docpack").unwrap_err().to_string().contains("not supported"));
+        assert!(check_url("app.docpack").is_err());
+        assert!(check_url("https://registry.example/app.docpack").is_ok());
+    }
+}
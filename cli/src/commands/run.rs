@@ -1,137 +1,925 @@
-use std::path::Path;
-use std::process::Command;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use std::fs;
-use std::io;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use docpack_core::{Manifest, Strictness, TasksFile, MANIFEST_FILE};
+use log::{debug, error, info, warn};
+use serde_json::json;
 
-pub fn run(docpack: &str, image: &str, follow: bool, env_file: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+use localdoc::docpack::{Docpack, DocpackKind};
+
+use crate::commands::{outputs, pack, validate};
+use crate::context;
+use crate::error::{LocaldocError, WithPath};
+use crate::hashes;
+use crate::history;
+use crate::interrupt;
+use crate::reader;
+use crate::run_state::{self, RunState};
+use crate::runtime::{self, ContainerSpec, EnvVar, PullPolicy, Runtime, RuntimeChoice};
+use crate::scratch::ScratchDir;
+use crate::seal;
+use crate::versions;
+
+/// Settings for `localdoc run`, one field per command-line flag.
+pub struct RunOptions<'a> {
+    pub image: &'a str,
+    pub follow: bool,
+    /// `KEY=VALUE`, or a bare `KEY` forwarded from this environment
+    pub env: &'a [String],
+    /// Must exist when given
+    pub env_file: Option<&'a str>,
+    /// Don't pick up `.env` from the current directory
+    pub no_env: bool,
+    pub max_output_bytes: Option<u64>,
+    pub max_output_file_bytes: Option<u64>,
+    /// `None` defers to `$LOCALDOC_RUNTIME`, then probing
+    pub runtime: Option<RuntimeChoice>,
+    /// Where to copy `output/` after a successful run
+    pub output_dir: Option<&'a str>,
+    /// Write the outputs back into the `.docpack` archive that was run
+    pub update_archive: bool,
+    /// Overrides `memory_limit_mb`
+    pub memory_mb: Option<u64>,
+    pub cpus: Option<f64>,
+    /// Overrides `max_execution_time_seconds`
+    pub timeout_seconds: Option<u64>,
+    /// Mount the whole docpack read-write instead of just `output/`
+    pub writable_files: bool,
+    /// Start the container without a network
+    pub no_network: bool,
+    /// Print the container command instead of running it
+    pub dry_run: bool,
+    pub pull: PullPolicy,
+    /// Don't show image pull progress
+    pub quiet: bool,
+    /// Ids of the tasks to run; empty runs them all
+    pub tasks: &'a [String],
+    /// Print the tasks in tasks.json instead of running
+    pub list_tasks: bool,
+    /// Print the run summary as JSON instead of the commentary
+    pub json: bool,
+    /// Start the container and return, leaving `logs` and `stop` to follow up
+    pub detach: bool,
+    /// `--user` for the container instead of the invoking user
+    pub user: Option<&'a str>,
+    /// Run as the image's own user (often root)
+    pub no_user_mapping: bool,
+    /// Skip the validation made before anything is started
+    pub no_validate: bool,
+    /// Fail validation on warnings too
+    pub strict: bool,
+}
+
+/// The run summary, written into `output/` next to what the agent wrote.
+pub const SUMMARY_FILE: &str = "run_summary.json";
+
+/// The agent's output, kept at the docpack root next to `run.json`.
+pub const LOG_FILE: &str = "run.log";
+
+/// Lines of `run.log` shown when a run fails without `--follow`.
+const LOG_TAIL_LINES: usize = 20;
+
+const LIMITS_EXCEEDED: &str = "Run produced outputs over the configured size limits (see run.json)";
+
+/// Set by `--json`, which replaces the running commentary with the run
+/// summary.
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// How often a run with a timeout checks whether the container has exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A file (or the output directory as a whole) over its size cap.
+struct LimitViolation {
+    path: String,
+    size: u64,
+    limit: u64,
+}
+
+pub fn run(docpack: &str, opts: &RunOptions) -> Result<(), LocaldocError> {
     let docpack_path = Path::new(docpack);
+    let image = opts.image;
+    let started = chrono::Utc::now();
+    JSON_OUTPUT.store(opts.json, Ordering::Relaxed);
 
-    // Validate docpack exists
-    if !docpack_path.exists() {
-        return Err(format!("Docpack does not exist: {}", docpack).into());
+    // Refuse manifests this CLI cannot read before unpacking or starting anything
+    let mut reader = reader::open(docpack_path)?;
+    // The version is checked untyped, as a manifest this CLI cannot read may
+    // not have the types' shape; with --no-validate, one they reject still
+    // runs, without its constraints
+    let raw_manifest = reader.read(MANIFEST_FILE).and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok());
+    let manifest = reader.read_manifest().ok();
+    let constraints = manifest.as_ref().and_then(|manifest| manifest.environment.constraints.as_ref());
+    let tasks = reader.read_tasks().ok();
+    drop(reader);
+    if opts.list_tasks {
+        print_tasks(tasks.as_ref());
+        return Ok(());
+    }
+    if let Some(manifest) = &raw_manifest {
+        let compatibility = versions::compatibility(manifest);
+        match (compatibility.message(), compatibility) {
+            (None, _) => {}
+            (Some(message), versions::Compatibility::Newer(_)) => warn!("{}", message),
+            (Some(message), _) => return Err(message.into()),
+        }
+    }
+    // A broken docpack would only fail once the agent is well under way;
+    // archives are checked in place, so an unsafe entry is never extracted
+    if !opts.no_validate {
+        let (errors, warnings) = validate::preflight(docpack_path, opts.strict);
+        for warning in &warnings {
+            warn!("{}", warning);
+        }
+        if !errors.is_empty() {
+            let listed: Vec<String> = errors.iter().map(|e| format!("  • {}", e)).collect();
+            error!("The docpack is not valid ({} error(s)):\n{}", errors.len(), listed.join("\n"));
+            return Err("Validation failed; fix the docpack (see `localdoc validate`) or pass --no-validate to run it anyway".into());
+        }
     }
+    let agent_constraints =
+        context::effective_constraints(constraints, tasks.as_ref().and_then(|tasks| tasks.constraints.as_ref()));
+    if opts.writable_files && context::forbids_modifying_files(&agent_constraints) {
+        return Err("--writable-files is not allowed: the docpack's constraints forbid modify_files".into());
+    }
+    // Only the selected tasks are shown to the agent, as a tasks.json
+    // mounted over the docpack's own; a task given twice runs once
+    let mut task_ids: Vec<String> = Vec::new();
+    for id in opts.tasks {
+        if !task_ids.contains(id) {
+            task_ids.push(id.clone());
+        }
+    }
+    let selected_tasks = match (task_ids.is_empty(), &tasks) {
+        (true, _) => None,
+        (false, None) => return Err(LocaldocError::InvalidArgument("--task needs a tasks.json in the docpack".to_string())),
+        (false, Some(tasks)) => Some(context::select_tasks(tasks, &task_ids).map_err(LocaldocError::NotFound)?),
+    };
+    if let Some(selected) = &selected_tasks {
+        for warning in unselected_dependencies(selected) {
+            warn!("{}", warning);
+        }
+    }
+    let runtime = runtime::resolve(opts.runtime)?;
+    let (env, env_file) = resolve_env(opts)?;
+    let container_limits = context::ContainerLimits::resolve(constraints, opts.memory_mb, opts.cpus, opts.timeout_seconds);
 
-    // Check if this is a zip file that needs extraction
-    let working_dir = if docpack_path.is_file() && docpack.ends_with(".docpack") {
-        println!("Extracting .docpack archive...");
+    let kind = Docpack::open_any(docpack_path)?.kind;
+    if opts.update_archive && kind != DocpackKind::Archive {
+        return Err(LocaldocError::InvalidArgument(
+            "--update-archive needs a .docpack archive; a docpack directory gets its output/ written in place".to_string(),
+        ));
+    }
+    // Archives, and sealed directories, are run in a temporary copy that is
+    // removed once the outputs have been copied out of it
+    let needs_copy = kind == DocpackKind::Archive || seal::sealed_manifest(docpack_path).is_some();
+    if opts.detach && needs_copy {
+        return Err("--detach needs an unsealed docpack directory, where the run is recorded in output/; \
+                    extract the archive first"
+            .into());
+    }
+    if !needs_copy && !opts.dry_run {
+        if let Some((state, _)) = run_state::live(docpack_path)? {
+            return Err(format!(
+                "Detached run {} is still recorded for this docpack; follow it with `localdoc logs {}` or finish it with `localdoc stop {}`",
+                state.container, docpack, docpack
+            )
+            .into());
+        }
+    }
+    if !opts.dry_run {
+        runtime.ensure_image(image, opts.pull, opts.quiet || opts.json)?;
+    }
 
-        // Create a temporary directory for extraction
-        let temp_dir = std::env::temp_dir().join(format!("docpack-run-{}", std::process::id()));
-        fs::create_dir_all(&temp_dir)?;
+    let mut scratch = None;
+    let working_dir = if opts.dry_run && needs_copy {
+        // Nothing is copied; the command shows where the copy would be mounted from
+        let planned = std::env::temp_dir().join("docpack-run-XXXXXX");
+        info!("Would run on a temporary copy: {}", planned.display());
+        planned
+    } else if kind == DocpackKind::Archive {
+        if opts.update_archive {
+            seal::ensure_mutable(docpack_path, false)?;
+        }
+        info!("Extracting .docpack archive...");
+        let copy = ScratchDir::new("docpack-run-")?;
+        pack::extract_docpack(docpack_path, copy.path(), JSON_OUTPUT.load(Ordering::Relaxed))?;
+        info!("  Extracted to: {}", copy.path().display());
+        scratch.insert(copy).path().to_path_buf()
+    } else if let Some(manifest) = seal::sealed_manifest(docpack_path) {
+        // Never write outputs into a sealed docpack; work on a copy instead
+        info!("Docpack is {}; running on a temporary copy", seal::describe_seal(&manifest));
+        let copy = ScratchDir::new("docpack-run-")?;
+        copy_dir_all(docpack_path, copy.path())?;
+        info!("  Copied to: {}", copy.path().display());
+        scratch.insert(copy).path().to_path_buf()
+    } else {
+        docpack_path.to_path_buf()
+    };
 
-        // Extract the zip file
-        extract_zip(docpack_path, &temp_dir)?;
+    // Where the outputs end up for good
+    let output_dir = match opts.output_dir {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None if needs_copy && !opts.update_archive => Some(default_output_dir(docpack_path)),
+        None => None,
+    };
 
-        println!("  Extracted to: {}", temp_dir.display());
-        temp_dir
-    } else if docpack_path.is_dir() {
-        // It's already a directory
-        if !docpack_path.join("docpack.json").exists() {
-            return Err(format!("Not a valid .docpack (missing docpack.json): {}", docpack).into());
+    info!("Running documenter on: {}", working_dir.display());
+    info!("Using {} image: {}", runtime.name(), image);
+    if let Some(mb) = container_limits.memory_mb {
+        info!("Memory limit: {} MB", mb);
+    }
+    if let Some(cpus) = container_limits.cpus {
+        info!("CPU limit: {}", cpus);
+    }
+    if let Some(seconds) = container_limits.timeout_seconds {
+        if opts.detach {
+            warn!("The time limit of {} s is not enforced for detached runs", seconds);
+        } else {
+            info!("Time limit: {} s", seconds);
         }
-        docpack_path.to_path_buf()
+    }
+    if opts.no_network {
+        info!("Network: none");
+    }
+    if opts.writable_files {
+        warn!("The whole docpack is mounted read-write");
+    }
+    if selected_tasks.is_some() {
+        info!("Tasks: {} of {}", task_ids.join(", "), task_count(tasks.as_ref()));
+    }
+    info!("");
+
+    // Get absolute path for the container mount
+    let abs_path = if opts.dry_run && needs_copy { working_dir.clone() } else { fs::canonicalize(&working_dir).with_path(&working_dir)? };
+
+    // The writable areas are mounted over the read-only docpack, so they
+    // have to exist; a `.reasoning/` made here is removed again if unused
+    let reasoning_dir = abs_path.join(context::REASONING_DIR);
+    let made_reasoning_dir = !opts.dry_run && !opts.writable_files && !reasoning_dir.exists();
+    if !opts.dry_run && !opts.writable_files {
+        let output_dir = abs_path.join("output");
+        fs::create_dir_all(&output_dir).with_path(&output_dir)?;
+        fs::create_dir_all(&reasoning_dir).with_path(&reasoning_dir)?;
+    }
+
+    let mut mounts = context::plan_mounts(&abs_path, opts.writable_files);
+    let mut tasks_scratch = None;
+    if let Some(selected) = &selected_tasks {
+        let host = if opts.dry_run {
+            std::env::temp_dir().join("docpack-tasks-XXXXXX").join("tasks.json")
+        } else {
+            let dir = tasks_scratch.insert(ScratchDir::new("docpack-tasks-")?);
+            let file = dir.path().join("tasks.json");
+            selected.save(&file)?;
+            file
+        };
+        mounts.push(context::Mount { host, container: format!("{}/tasks.json", context::WORKSPACE), writable: false });
+    }
+
+    let spec = ContainerSpec {
+        image,
+        name: format!("localdoc-run-{}-{}", std::process::id(), started.timestamp_millis()),
+        limits: container_limits,
+        network: !opts.no_network,
+        detach: opts.detach,
+        // Docker runs the agent as root unless told otherwise, leaving
+        // root-owned files in output/; Podman maps the user with keep-id
+        user: match opts.user {
+            Some(user) => Some(user.to_string()),
+            None if !opts.no_user_mapping && runtime == Runtime::Docker => runtime::host_user(),
+            None => None,
+        },
+        map_user: !opts.no_user_mapping,
+        mounts,
+        env,
+        env_file,
+    };
+    let selected_ids = selected_tasks.is_some().then_some(task_ids.as_slice());
+    let mut cmd = runtime.run_command(&spec);
+    if opts.dry_run {
+        print_plan(&abs_path, runtime, &spec, output_dir.as_deref());
+        info!("Command: {}", runtime::command_line(&cmd));
+        info!("\nDry run: the container was not started");
+        return Ok(());
+    }
+    debug!("Command: {}", runtime::command_line(&cmd));
+
+    if opts.detach {
+        let output = cmd.output().map_err(|e| format!("Cannot start {} ({}); is it installed?", runtime.program(), e))?;
+        if !output.status.success() {
+            return Err(LocaldocError::ContainerFailed {
+                what: format!("{} run --detach", runtime.name()),
+                code: output.status.code(),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        // The filtered tasks.json has to outlive this process; `stop` removes it
+        let tasks_file = tasks_scratch.as_mut().map(|dir| {
+            dir.keep();
+            dir.path().join("tasks.json")
+        });
+        let state = RunState {
+            runtime: runtime.program().to_string(),
+            container: spec.name.clone(),
+            working_dir: abs_path.clone(),
+            image: image.to_string(),
+            started: started.to_rfc3339(),
+            tasks: selected_ids.map(<[String]>::to_vec),
+            tasks_file,
+            memory_mb: container_limits.memory_mb,
+            cpus: container_limits.cpus,
+            max_output_bytes: opts.max_output_bytes,
+            max_output_file_bytes: opts.max_output_file_bytes,
+        };
+        run_state::save(&abs_path, &state)?;
+        info!("\n✓ Started detached container {}", spec.name);
+        info!("  Watch it:  localdoc logs {} --follow", docpack);
+        info!("  Finish it: localdoc stop {}", docpack);
+        return Ok(());
+    }
+
+    if opts.follow {
+        info!("Following the agent's output...\n");
+        info!("{}", "=".repeat(60));
     } else {
-        return Err(format!("Invalid .docpack: must be either a .docpack zip file or a directory containing docpack.json").into());
+        info!("The agent's output goes to {}; pass --follow to watch it", abs_path.join(LOG_FILE).display());
+    }
+
+    // Every line goes to run.log; with --follow it is echoed as well, with
+    // stdout kept for the summary under --json
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = cmd.spawn().map_err(|e| format!("Cannot start {} ({}); is it installed?", runtime.program(), e))?;
+    let log_path = abs_path.join(LOG_FILE);
+    let log = Arc::new(Mutex::new(fs::File::create(&log_path).with_path(&log_path)?));
+    let echo_out = match (opts.follow, opts.json) {
+        (false, _) => Echo::None,
+        (true, false) => Echo::Stdout,
+        (true, true) => Echo::Stderr,
+    };
+    let echo_err = if opts.follow { Echo::Stderr } else { Echo::None };
+    let streams: Vec<thread::JoinHandle<()>> = [
+        child.stdout.take().map(|out| stream_lines(out, Arc::clone(&log), echo_out)),
+        child.stderr.take().map(|err| stream_lines(err, Arc::clone(&log), echo_err)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    // Ctrl-C stops the container, and the outputs of a temporary copy are
+    // salvaged before it is removed
+    let watch = interrupt::watch(interrupt::Container {
+        runtime,
+        name: spec.name.clone(),
+        working_dir: abs_path.clone(),
+        salvage_to: output_dir.clone().or_else(|| scratch.is_some().then(|| default_output_dir(docpack_path))),
+    });
+    let waited = wait_with_timeout(&mut child, container_limits.timeout_seconds)?;
+    if waited.is_none() {
+        info!("\n{}", "=".repeat(60));
+        error!("Execution time limit exceeded; stopping the container");
+        stop_container(runtime, &spec.name, &mut child)?;
+    }
+    drop(watch);
+    for stream in streams {
+        let _ = stream.join();
+    }
+    if made_reasoning_dir {
+        // Fails, harmlessly, if the agent left notes in it
+        let _ = fs::remove_dir(&reasoning_dir);
+    }
+    let mut record = RunRecord {
+        docpack: docpack_path,
+        image,
+        started,
+        status: "success",
+        exit_code: waited.and_then(|status| status.code()),
+        run_id: None,
+        tasks: selected_ids,
+        container: &container_limits,
+        limits: &context::OutputLimits::default(),
+        violations: &[],
+    };
+    let Some(status) = waited else {
+        let seconds = container_limits.timeout_seconds.unwrap_or_default();
+        record.status = "timeout";
+        record_run(&working_dir, &record)?;
+        salvage_outputs(&working_dir, output_dir.as_deref(), &mut scratch)?;
+        return Err(format!("Execution time limit exceeded: the run took longer than {} seconds", seconds).into());
     };
 
-    println!("Running documenter on: {}", working_dir.display());
-    println!("Using Docker image: {}", image);
-    println!();
+    if !status.success() {
+        record.status = "failed";
+        record_run(&working_dir, &record)?;
+        if !opts.follow {
+            print_log_tail(&abs_path.join(LOG_FILE));
+        }
+        keep_for_inspection(&mut scratch);
+        return Err(LocaldocError::ContainerFailed { what: format!("{} command", runtime.name()), code: status.code(), stderr: String::new() });
+    }
+
+    info!("\n{}", "=".repeat(60));
+    info!("✓ Documenter completed successfully");
+
+    if !conclude(&working_dir, record, opts.max_output_bytes, opts.max_output_file_bytes)? {
+        keep_for_inspection(&mut scratch);
+        return Err(LIMITS_EXCEEDED.into());
+    }
 
-    // Get absolute path for Docker mount
-    let abs_path = std::fs::canonicalize(&working_dir)?;
+    // Copy the outputs out of the working copy before it is removed
+    let generated = outputs::collect_files(&working_dir.join("output"))?;
+    if let Some(dir) = &output_dir {
+        copy_outputs(&working_dir, &generated, dir)?;
+    }
+    if opts.update_archive {
+        info!("Rewriting archive with the new outputs...");
+        pack::write_archive(&working_dir, docpack_path)?;
+    }
 
-    // Build docker run command
-    let mut cmd = Command::new("docker");
-    cmd.arg("run")
-        .arg("--rm");
-    
-    // Add --env-file if provided or if .env exists in current dir
-    if let Some(env_path) = env_file {
-        if Path::new(env_path).exists() {
-            cmd.arg("--env-file").arg(env_path);
+    if generated.is_empty() {
+        info!("\nNo output files were generated");
+    } else {
+        info!("\nGenerated files:");
+        for rel in &generated {
+            let path = working_dir.join("output").join(rel);
+            let size = fs::metadata(&path).with_path(&path)?.len();
+            let location = match &output_dir {
+                Some(dir) => dir.join(rel).display().to_string(),
+                None if opts.update_archive => format!("{} → output/{}", docpack_path.display(), rel),
+                None => working_dir.join("output").join(rel).display().to_string(),
+            };
+            info!("  - {} ({} bytes)", location, size);
+        }
+        if output_dir.is_some() && opts.update_archive {
+            info!("  and under output/ in {}", docpack_path.display());
         }
-    } else if Path::new(".env").exists() {
-        cmd.arg("--env-file").arg(".env");
     }
-    
-    cmd.arg("-v")
-        .arg(format!("{}:/workspace", abs_path.display()))
-        .arg(image);
+    if let Some(ids) = selected_ids {
+        info!("\nRan {} of {} tasks: {}", ids.len(), task_count(tasks.as_ref()), ids.join(", "));
+    }
 
-    if follow {
-        println!("Following logs...\n");
-        println!("{}", "=".repeat(60));
+    Ok(())
+}
+
+/// `--list-tasks`: the id and name of each task.
+fn print_tasks(tasks: Option<&TasksFile>) {
+    let listed = tasks.map(TasksFile::task_list).unwrap_or_default();
+    if listed.is_empty() {
+        info!("No tasks in this docpack");
+        return;
+    }
+    let ids: Vec<&str> = listed.iter().map(|task| task.id.as_deref().unwrap_or("?")).collect();
+    let width = ids.iter().map(|id| id.len()).max().unwrap_or(0).max("ID".len());
+    info!("{:<width$}  NAME", "ID");
+    for (id, task) in ids.iter().zip(listed) {
+        info!("{:<width$}  {}", id, task.name.as_deref().unwrap_or(""));
     }
+}
 
-    // Execute docker run
-    let status = cmd.status()?;
+fn task_count(tasks: Option<&TasksFile>) -> usize {
+    tasks.map_or(0, |tasks| tasks.task_list().len())
+}
 
-    if !status.success() {
-        return Err(format!("Docker command failed with exit code: {:?}", status.code()).into());
+/// A warning for each `depends_on` of a selected task that was not selected.
+fn unselected_dependencies(selected: &TasksFile) -> Vec<String> {
+    let tasks = selected.task_list();
+    let is_selected = |id: &str| tasks.iter().any(|task| task.id.as_deref() == Some(id));
+    let mut warnings = Vec::new();
+    for task in tasks {
+        let dependencies = task.depends_on.as_deref().unwrap_or_default();
+        for dependency in dependencies.iter().filter(|d| !is_selected(d)) {
+            warnings.push(format!(
+                "Task {} depends on {}, which is not selected; its outputs must already be in output/",
+                task.id.as_deref().unwrap_or("?"),
+                dependency
+            ));
+        }
     }
+    warnings
+}
 
-    println!("\n{}", "=".repeat(60));
-    println!("✓ Documenter completed successfully");
-    println!("\nOutput files written to: {}/output/", working_dir.display());
+/// What `--dry-run` shows besides the command.
+fn print_plan(working_dir: &Path, runtime: Runtime, spec: &ContainerSpec, output_dir: Option<&Path>) {
+    info!("Working directory: {}", working_dir.display());
+    info!("Mounts:");
+    for mount in &spec.mounts {
+        let access = if mount.writable { "read-write" } else { "read-only" };
+        info!("  {} → {} ({})", mount.host.display(), mount.container, access);
+    }
+    match &spec.user {
+        Some(user) => info!("User: {}", user),
+        None if spec.map_user && runtime == Runtime::Podman => info!("User: you (--userns=keep-id)"),
+        None => info!("User: the image's own"),
+    }
+    match &spec.env_file {
+        Some(path) => info!("Env file: {}", path.display()),
+        None => info!("Env file: none"),
+    }
+    if !spec.env.is_empty() {
+        let names: Vec<&str> = spec.env.iter().map(|var| var.name.as_str()).collect();
+        info!("Variables: {}", names.join(", "));
+    }
+    if let Some(dir) = output_dir {
+        info!("Outputs would be copied to: {}", dir.display());
+    }
+}
 
-    // List output files
-    let output_dir = working_dir.join("output");
-    if output_dir.exists() {
-        println!("\nGenerated files:");
-        for entry in std::fs::read_dir(output_dir)? {
-            let entry = entry?;
-            if entry.file_type()?.is_file() {
-                let metadata = entry.metadata()?;
-                println!("  - {} ({} bytes)",
-                    entry.file_name().to_string_lossy(),
-                    metadata.len()
-                );
-            }
+/// Wait for the container to exit, or `None` once `timeout_seconds` have
+/// passed without it doing so.
+fn wait_with_timeout(child: &mut Child, timeout_seconds: Option<u64>) -> io::Result<Option<ExitStatus>> {
+    let Some(seconds) = timeout_seconds else {
+        return child.wait().map(Some);
+    };
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
         }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
     }
+}
 
-    // Clean up temporary directory if we extracted a zip
-    if docpack_path.is_file() && working_dir != docpack_path {
-        println!("\nNote: Extracted files are in temporary directory: {}", working_dir.display());
-        println!("They will be cleaned up on next system restart.");
+/// Stop a container that ran out of time: `stop` lets the agent exit
+/// cleanly, and `kill` follows if the runtime is still waiting on it after
+/// the grace period.
+fn stop_container(runtime: Runtime, name: &str, child: &mut Child) -> io::Result<()> {
+    let _ = runtime.stop_command(name, runtime::STOP_GRACE_SECONDS).stdout(Stdio::null()).stderr(Stdio::null()).status();
+    if wait_with_timeout(child, Some(runtime::STOP_GRACE_SECONDS))?.is_none() {
+        let _ = runtime.kill_command(name).stdout(Stdio::null()).stderr(Stdio::null()).status();
+        // The runtime process may have exited in the meantime
+        let _ = child.kill();
+        child.wait()?;
     }
+    Ok(())
+}
 
+/// Copy `files` (relative to `output/` in `working_dir`) into `dir`.
+pub(crate) fn copy_outputs(working_dir: &Path, files: &BTreeSet<String>, dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for rel in files {
+        let target = dir.join(rel);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(working_dir.join("output").join(rel), target)?;
+    }
     Ok(())
 }
 
-fn extract_zip(zip_path: &Path, extract_to: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let file = fs::File::open(zip_path)?;
-    let mut archive = zip::ZipArchive::new(file)?;
+/// Keep what a run that was stopped early left in `output/`: copied to
+/// `output_dir` if there is one, else in the kept working copy.
+fn salvage_outputs(
+    working_dir: &Path,
+    output_dir: Option<&Path>,
+    scratch: &mut Option<ScratchDir>,
+) -> Result<(), LocaldocError> {
+    let partial = outputs::collect_files(&working_dir.join("output"))?;
+    if partial.is_empty() {
+        info!("No partial outputs to salvage");
+        return Ok(());
+    }
+    match output_dir {
+        Some(dir) => {
+            copy_outputs(working_dir, &partial, dir)?;
+            info!("Salvaged {} partial output file(s) to {}", partial.len(), dir.display());
+        }
+        None if scratch.is_some() => keep_for_inspection(scratch),
+        None => info!("{} partial output file(s) left in {}", partial.len(), working_dir.join("output").display()),
+    }
+    Ok(())
+}
 
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let outpath = match file.enclosed_name() {
-            Some(path) => extract_to.join(path),
-            None => continue,
-        };
+/// The `-e` variables and the env file for the container. A named env file
+/// must exist; `.env` in the current directory is picked up unless
+/// `--no-env` is given. Bare names must be set on the host.
+fn resolve_env(opts: &RunOptions) -> Result<(Vec<EnvVar>, Option<PathBuf>), LocaldocError> {
+    let env = opts.env.iter().map(|spec| EnvVar::parse(spec)).collect::<Result<Vec<_>, _>>()?;
+    if let Some(var) = env.iter().find(|var| var.value.is_none() && std::env::var_os(&var.name).is_none()) {
+        return Err(format!("-e {}: {} is not set in this shell; pass -e {}=<value> instead", var.name, var.name, var.name).into());
+    }
 
-        if file.name().ends_with('/') {
-            fs::create_dir_all(&outpath)?;
+    let env_file = match opts.env_file {
+        Some(path) if !Path::new(path).is_file() => {
+            return Err(LocaldocError::SourceNotFound { what: "Env file", path: PathBuf::from(path) })
+        }
+        Some(path) => Some(PathBuf::from(path)),
+        None if !opts.no_env && Path::new(".env").is_file() => Some(PathBuf::from(".env")),
+        None => None,
+    };
+    Ok((env, env_file))
+}
+
+/// Where outputs of a run in a temporary copy go when no `--output-dir` is
+/// given: `<name>-output/` beside the docpack.
+fn default_output_dir(docpack_path: &Path) -> PathBuf {
+    docpack_path.with_file_name(format!("{}-output", docpack_name(docpack_path)))
+}
+
+/// The docpack's file name without `.docpack`.
+pub(crate) fn docpack_name(docpack_path: &Path) -> String {
+    docpack_path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| "docpack".to_string())
+}
+
+/// Leave a failed run's temporary copy on disk and say where it is.
+fn keep_for_inspection(scratch: &mut Option<ScratchDir>) {
+    if let Some(copy) = scratch {
+        copy.keep();
+        info!("\nThe working copy was kept for inspection: {}", copy.path().display());
+    }
+}
+
+/// Finish a run whose agent exited cleanly: check the output limits,
+/// archive the outputs and record the run. `false` when limits were exceeded.
+fn conclude(
+    working_dir: &Path,
+    record: RunRecord,
+    max_output_bytes: Option<u64>,
+    max_output_file_bytes: Option<u64>,
+) -> Result<bool, LocaldocError> {
+    // Enforce output size limits before anything is archived
+    // Read leniently: with --no-validate the manifest may not have the types' shape
+    let manifest = Manifest::load(&working_dir.join(MANIFEST_FILE), Strictness::Lenient).ok();
+    let constraints = manifest.as_ref().and_then(|manifest| manifest.environment.constraints.as_ref());
+    let limits = context::OutputLimits::resolve(constraints, max_output_bytes, max_output_file_bytes);
+    let (violations, total_exceeded) = check_output_limits(&working_dir.join("output"), &limits)?;
+
+    // Archive this run's outputs so they can be compared with `localdoc outputs diff`,
+    // leaving out anything over the limits
+    let run_id = if total_exceeded {
+        None
+    } else {
+        let mut excluded: Vec<String> = violations.iter().map(|v| v.path.clone()).collect();
+        excluded.push(SUMMARY_FILE.to_string());
+        Some(outputs::snapshot(working_dir, &excluded)?)
+    };
+    if let Some(run_id) = &run_id {
+        info!("Outputs archived as run: {}", run_id);
+    }
+
+    let record = RunRecord {
+        status: if violations.is_empty() { "success" } else { "output_limits_exceeded" },
+        run_id: run_id.as_deref(),
+        limits: &limits,
+        violations: &violations,
+        ..record
+    };
+    record_run(working_dir, &record)?;
+
+    if !violations.is_empty() {
+        info!("\n❌ Output limits exceeded:");
+        for violation in &violations {
+            info!("  • {}: {} bytes (limit {})", violation.path, violation.size, violation.limit);
+        }
+        if total_exceeded {
+            info!("  Outputs were not archived");
         } else {
-            if let Some(p) = outpath.parent() {
-                if !p.exists() {
-                    fs::create_dir_all(p)?;
-                }
-            }
-            let mut outfile = fs::File::create(&outpath)?;
-            io::copy(&mut file, &mut outfile)?;
+            info!("  Oversized files were left out of the archived run");
         }
+        return Ok(false);
+    }
+    Ok(true)
+}
 
-        // Set permissions on Unix
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            if let Some(mode) = file.unix_mode() {
-                fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))?;
+/// Finish a run started with `--detach` once its container has exited, or
+/// been stopped (`exit_code` is then whatever the runtime reported). The
+/// state file is removed first so it is not counted among the outputs.
+pub(crate) fn finish_detached(
+    docpack_path: &Path,
+    state: &RunState,
+    exit_code: Option<i32>,
+) -> Result<(), LocaldocError> {
+    run_state::remove(docpack_path, state)?;
+    let started = chrono::DateTime::parse_from_rfc3339(&state.started)
+        .map(|started| started.with_timezone(&chrono::Utc))
+        .unwrap_or_else(|_| chrono::Utc::now());
+    let container = context::ContainerLimits { memory_mb: state.memory_mb, cpus: state.cpus, timeout_seconds: None };
+    let record = RunRecord {
+        docpack: docpack_path,
+        image: &state.image,
+        started,
+        status: "success",
+        exit_code,
+        run_id: None,
+        tasks: state.tasks.as_deref(),
+        container: &container,
+        limits: &context::OutputLimits::default(),
+        violations: &[],
+    };
+    let working_dir = &state.working_dir;
+    // Fails, harmlessly, if the agent left notes in it
+    let _ = fs::remove_dir(working_dir.join(context::REASONING_DIR));
+    if exit_code != Some(0) {
+        record_run(working_dir, &RunRecord { status: "failed", ..record })?;
+        return Err(LocaldocError::ContainerFailed { what: "The detached run".to_string(), code: exit_code, stderr: String::new() });
+    }
+    info!("✓ Documenter completed successfully");
+    if !conclude(working_dir, record, state.max_output_bytes, state.max_output_file_bytes)? {
+        return Err(LIMITS_EXCEEDED.into());
+    }
+    info!("\nGenerated files:");
+    for rel in outputs::collect_files(&working_dir.join("output"))? {
+        let path = working_dir.join("output").join(&rel);
+        let size = fs::metadata(&path).with_path(&path)?.len();
+        info!("  - {} ({} bytes)", path.display(), size);
+    }
+    Ok(())
+}
+
+/// Where a line from the container is echoed besides `run.log`.
+#[derive(Clone, Copy)]
+enum Echo {
+    None,
+    Stdout,
+    Stderr,
+}
+
+/// Copy the container's output to `log` a line at a time as it arrives.
+fn stream_lines<R: Read + Send + 'static>(
+    source: R,
+    log: Arc<Mutex<fs::File>>,
+    echo: Echo,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(source);
+        let mut line = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+            let _ = log.lock().unwrap_or_else(|e| e.into_inner()).write_all(&line);
+            let _ = match echo {
+                Echo::None => Ok(()),
+                Echo::Stdout => io::stdout().lock().write_all(&line),
+                Echo::Stderr => io::stderr().lock().write_all(&line),
+            };
+            line.clear();
+        }
+    })
+}
+
+/// The end of `run.log`, for a run that failed without `--follow`.
+fn print_log_tail(log: &Path) {
+    let Ok(text) = fs::read_to_string(log) else {
+        return;
+    };
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() {
+        return;
+    }
+    eprintln!("\nLast lines of {}:", log.display());
+    for line in &lines[lines.len().saturating_sub(LOG_TAIL_LINES)..] {
+        eprintln!("  {}", line);
+    }
+}
+
+/// Check `output/` against the limits. The whole directory is reported as
+/// `output/` when the total cap is exceeded; the flag says whether it was.
+fn check_output_limits(
+    output_dir: &Path,
+    limits: &context::OutputLimits,
+) -> Result<(Vec<LimitViolation>, bool), LocaldocError> {
+    let mut violations = Vec::new();
+    let mut total: u64 = 0;
+
+    if !output_dir.is_dir() {
+        return Ok((violations, false));
+    }
+
+    for entry in walkdir::WalkDir::new(output_dir) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let size = entry.metadata()?.len();
+        total += size;
+
+        if let Some(limit) = limits.max_file_bytes {
+            if size > limit {
+                let rel = entry.path().strip_prefix(output_dir)?;
+                let parts: Vec<String> = rel
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                violations.push(LimitViolation { path: parts.join("/"), size, limit });
             }
         }
     }
 
+    let total_exceeded = matches!(limits.max_total_bytes, Some(limit) if total > limit);
+    if let (true, Some(limit)) = (total_exceeded, limits.max_total_bytes) {
+        violations.push(LimitViolation { path: "output/".to_string(), size: total, limit });
+    }
+
+    Ok((violations, total_exceeded))
+}
+
+/// What the run summary records about a run.
+struct RunRecord<'a> {
+    /// The docpack as given on the command line
+    docpack: &'a Path,
+    image: &'a str,
+    started: chrono::DateTime<chrono::Utc>,
+    /// `success`, `failed`, `output_limits_exceeded` or `timeout`
+    status: &'a str,
+    /// `None` when the container was stopped
+    exit_code: Option<i32>,
+    run_id: Option<&'a str>,
+    /// The `--task` ids, `None` when every task ran
+    tasks: Option<&'a [String]>,
+    container: &'a context::ContainerLimits,
+    limits: &'a context::OutputLimits,
+    violations: &'a [LimitViolation],
+}
+
+/// Record the outcome of a run. The summary goes to `run.json` at the
+/// docpack root and to `output/run_summary.json`, where it travels with the
+/// outputs; `--json` prints it, and a shorter line is added to the history
+/// log for `localdoc history`.
+fn record_run(working_dir: &Path, run: &RunRecord) -> Result<(), LocaldocError> {
+    let finished = chrono::Utc::now();
+    let duration_seconds = (finished - run.started).num_milliseconds() as f64 / 1000.0;
+    let docpack = fs::canonicalize(run.docpack).unwrap_or_else(|_| run.docpack.to_path_buf()).display().to_string();
+
+    let output_dir = working_dir.join("output");
+    let mut files = Vec::new();
+    for rel in outputs::collect_files(&output_dir)?.into_iter().filter(|rel| rel != SUMMARY_FILE) {
+        let path = output_dir.join(&rel);
+        let hash = hashes::hash_reader(fs::File::open(&path).with_path(&path)?)?;
+        files.push(json!({ "path": rel, "size": hash.size, "sha256": hash.sha256 }));
+    }
+
+    let record = json!({
+        "run_id": run.run_id,
+        "docpack": docpack,
+        "image": run.image,
+        "started": run.started.to_rfc3339(),
+        "finished": finished.to_rfc3339(),
+        "duration_seconds": duration_seconds,
+        "status": run.status,
+        "exit_code": run.exit_code,
+        "localdoc_version": env!("CARGO_PKG_VERSION"),
+        "outputs": files,
+        "tasks": run.tasks,
+        "container_limits": {
+            "memory_mb": run.container.memory_mb,
+            "cpus": run.container.cpus,
+            "timeout_seconds": run.container.timeout_seconds,
+        },
+        "output_limits": {
+            "max_output_bytes": run.limits.max_total_bytes,
+            "max_output_file_bytes": run.limits.max_file_bytes,
+            "violations": run.violations.iter().map(|v| json!({
+                "path": v.path,
+                "size": v.size,
+                "limit": v.limit,
+            })).collect::<Vec<_>>(),
+        },
+    });
+
+    let text = serde_json::to_string_pretty(&record)?;
+    let run_json = working_dir.join("run.json");
+    fs::write(&run_json, &text).with_path(&run_json)?;
+    fs::create_dir_all(&output_dir).with_path(&output_dir)?;
+    let summary_path = output_dir.join(SUMMARY_FILE);
+    fs::write(&summary_path, &text).with_path(&summary_path)?;
+    if JSON_OUTPUT.load(Ordering::Relaxed) {
+        println!("{}", text);
+    }
+
+    let line = json!({
+        "started": record["started"],
+        "finished": record["finished"],
+        "duration_seconds": duration_seconds,
+        "status": run.status,
+        "exit_code": run.exit_code,
+        "docpack": docpack,
+        "image": run.image,
+        "run_id": run.run_id,
+        "tasks": run.tasks,
+        "outputs": files.len(),
+    });
+    if let Err(e) = history::append(&line) {
+        warn!("Could not add the run to the history log: {}", e);
+    }
+    Ok(())
+}
+
+pub(crate) fn copy_dir_all(src: &Path, dst: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let dst_path = dst.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&dst_path)?;
+            copy_dir_all(&entry.path(), &dst_path)?;
+        } else {
+            fs::copy(entry.path(), dst_path)?;
+        }
+    }
     Ok(())
 }
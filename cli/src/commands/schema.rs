@@ -0,0 +1,25 @@
+use std::fs;
+use std::path::Path;
+
+use crate::error::LocaldocError;
+use crate::schema;
+
+pub fn run(export: Option<&str>) -> Result<(), LocaldocError> {
+    let Some(dir) = export else {
+        println!("Built-in schemas:");
+        for schema in schema::ALL {
+            println!("  {:<20} describes {}", schema.file_name, schema.document);
+        }
+        println!("\nWrite them to a directory with `localdoc schema --export <DIR>`");
+        return Ok(());
+    };
+
+    let dir = Path::new(dir);
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    for schema in schema::ALL {
+        let path = dir.join(schema.file_name);
+        fs::write(&path, schema.text).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+        println!("✓ Wrote {} ({})", path.display(), schema.document);
+    }
+    Ok(())
+}
@@ -0,0 +1,287 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use log::warn;
+use serde_json::{json, Value};
+
+use crate::error::LocaldocError;
+use crate::fingerprint;
+use crate::reader::{self, DocpackReader};
+use crate::search::{IndexFormat, MatchKind, SearchIndex};
+use crate::symbols::{self, Definition, Symbols};
+use crate::tokenize::tokenize;
+
+/// Longest snippet printed for a hit, in characters.
+const MAX_SNIPPET_CHARS: usize = 120;
+
+/// Most terms listed for one query token in the "matched as" notes.
+const MAX_LISTED_TERMS: usize = 5;
+
+/// Settings for `localdoc search`, one field per command-line flag.
+pub struct SearchOptions {
+    /// Maximum number of matching lines to show
+    pub limit: usize,
+    /// Only match whole indexed words, not words the query starts
+    pub exact: bool,
+    /// Also match words one or two typos away from the query
+    pub fuzzy: bool,
+    pub json: bool,
+}
+
+/// One matching line.
+pub(crate) struct Hit {
+    pub path: String,
+    pub line: usize,
+    /// The loosest match any query token needed in this file
+    pub kind: MatchKind,
+    /// Occurrences of matched terms in the whole file
+    pub score: usize,
+    pub snippet: String,
+}
+
+pub fn run(docpack: &str, query: &str, opts: &SearchOptions) -> Result<(), LocaldocError> {
+    let mut contents = reader::open(Path::new(docpack))?;
+    let index = load_index(contents.as_mut(), docpack)?;
+
+    // Tokenize like the indexer so the terms line up
+    let tokens: BTreeSet<String> = tokenize(query).into_iter().collect();
+    if tokens.is_empty() {
+        return Err(LocaldocError::InvalidArgument(format!(
+            "Nothing to search for in '{}' (words need at least 3 characters)",
+            query
+        )));
+    }
+    // The indexed terms each query token stands for
+    let matches: BTreeMap<&String, Vec<(&str, MatchKind)>> = tokens
+        .iter()
+        .map(|token| (token, index.matching_terms(token, !opts.exact, opts.fuzzy)))
+        .collect();
+    let truncated: BTreeSet<&str> =
+        matches.values().flatten().map(|(term, _)| *term).filter(|term| index.truncated.contains(*term)).collect();
+
+    let (shown, total) = find(&index, &matches, opts.limit, contents.as_mut());
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&json!({
+            "query": query,
+            "tokens": tokens,
+            "terms": matches
+                .iter()
+                .map(|(token, terms)| {
+                    let terms: Vec<Value> =
+                        terms.iter().map(|(term, kind)| json!({ "term": term, "match": kind.as_str() })).collect();
+                    (token.to_string(), Value::from(terms))
+                })
+                .collect::<serde_json::Map<_, _>>(),
+            "total": total,
+            "results": shown
+                .iter()
+                .map(|hit| json!({
+                    "path": hit.path,
+                    "line": hit.line,
+                    "match": hit.kind.as_str(),
+                    "score": hit.score,
+                    "snippet": hit.snippet,
+                }))
+                .collect::<Vec<_>>(),
+            "truncated": truncated,
+        }))?);
+        return Ok(());
+    }
+
+    for hit in &shown {
+        println!("{}:{}: {}", hit.path, hit.line, hit.snippet);
+    }
+    if total == 0 {
+        println!("No matches for '{}'", query);
+        if !opts.fuzzy {
+            println!("Try --fuzzy to also match words a typo or two away");
+        }
+    } else if total > shown.len() {
+        println!("\n{} of {} matching lines (--limit {})", shown.len(), total, opts.limit);
+    }
+    // Say what a token matched when it isn't an indexed word itself
+    for (token, terms) in &matches {
+        if terms.is_empty() || terms.iter().any(|(_, kind)| *kind == MatchKind::Exact) {
+            continue;
+        }
+        let listed: Vec<&str> = terms.iter().take(MAX_LISTED_TERMS).map(|(term, _)| *term).collect();
+        let more = terms.len() - listed.len();
+        println!(
+            "'{}' matched {}{}",
+            token,
+            listed.join(", "),
+            if more > 0 { format!(" and {} more", more) } else { String::new() }
+        );
+    }
+    for token in truncated {
+        warn!("'{}' occurs in more files than the index lists; results may be incomplete", token);
+    }
+    Ok(())
+}
+
+/// The docpack's search index, in whichever format it was stored.
+pub(crate) fn load_index(contents: &mut dyn DocpackReader, docpack: &str) -> Result<SearchIndex, LocaldocError> {
+    let found = IndexFormat::ALL
+        .into_iter()
+        .find_map(|format| Some((format, contents.read(&format!("index/{}", format.file_name()))?)));
+    let Some((format, bytes)) = found else {
+        return Err(format!(
+            "{} has no search index (index/search.json). Build one with:\n  localdoc index {}",
+            docpack, docpack
+        )
+        .into());
+    };
+    Ok(SearchIndex::from_bytes(&bytes, format).map_err(|e| {
+        format!("index/{} is not in the current format ({}); {}", format.file_name(), e, fingerprint::REBUILD_HINT)
+    })?)
+}
+
+/// Look up where `name` is defined in index/symbols.json. Without an exact
+/// match, symbols whose names contain `name` (ignoring case) are listed.
+pub fn symbols(docpack: &str, name: &str, limit: usize, json: bool) -> Result<(), LocaldocError> {
+    let mut contents = reader::open(Path::new(docpack))?;
+    let Some(bytes) = contents.read(&format!("index/{}", symbols::FILE_NAME)) else {
+        return Err(format!(
+            "{} has no symbol index (index/{}). Build one with:\n  localdoc index {} --force",
+            docpack,
+            symbols::FILE_NAME,
+            docpack
+        )
+        .into());
+    };
+    let index: Symbols = serde_json::from_slice(&bytes)
+        .map_err(|e| format!("index/{} is not readable: {}; {}", symbols::FILE_NAME, e, fingerprint::REBUILD_HINT))?;
+
+    let exact = index.contains_key(name);
+    let mut names: Vec<&String> = match index.get_key_value(name) {
+        Some((key, _)) => vec![key],
+        None => {
+            let needle = name.to_lowercase();
+            index.keys().filter(|key| key.to_lowercase().contains(&needle)).collect()
+        }
+    };
+    // Shorter names are usually the closer match
+    names.sort_by_key(|key| (key.len(), key.as_str()));
+
+    let found: Vec<(&String, &Definition)> =
+        names.iter().flat_map(|key| index[*key].iter().map(move |definition| (*key, definition))).collect();
+    let shown = &found[..found.len().min(limit)];
+    let results: Vec<(&String, &Definition, String)> = shown
+        .iter()
+        .map(|(key, definition)| {
+            let text = contents.source_text(&definition.path).unwrap_or_default();
+            // Lines are 1-based; a 0 from a hand-edited index has no text
+            let line = definition.line.checked_sub(1).and_then(|i| text.lines().nth(i)).map(snippet).unwrap_or_default();
+            (*key, *definition, line)
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&json!({
+            "symbol": name,
+            "exact": exact,
+            "total": found.len(),
+            "results": results
+                .iter()
+                .map(|(key, definition, snippet)| json!({
+                    "name": key,
+                    "path": definition.path,
+                    "line": definition.line,
+                    "kind": definition.kind,
+                    "snippet": snippet,
+                }))
+                .collect::<Vec<_>>(),
+        }))?);
+        return Ok(());
+    }
+
+    if found.is_empty() {
+        println!("No symbol named '{}'", name);
+        return Ok(());
+    }
+    if !exact {
+        println!("No symbol named '{}'; symbols containing it:\n", name);
+    }
+    let mut previous = None;
+    for (key, definition, snippet) in &results {
+        if previous != Some(*key) {
+            println!("{}", key);
+            previous = Some(*key);
+        }
+        println!("  {}:{}: [{}] {}", definition.path, definition.line, definition.kind, snippet);
+    }
+    if found.len() > shown.len() {
+        println!("\n{} of {} definitions (--limit {})", shown.len(), found.len(), limit);
+    }
+    Ok(())
+}
+
+/// A file's match so far: the loosest match a token needed, occurrences of
+/// matched terms, and the lines they are on.
+type FileMatch = (MatchKind, usize, BTreeSet<usize>);
+
+/// Lines in files that match every token, best files first: files whose
+/// tokens all matched exactly come before those that needed a prefix, then
+/// those that needed a fuzzy match; within each, files are ranked by how
+/// often the terms occur in them, then by path. Returns the first `limit`
+/// lines and how many matched in all; only files with a line among the
+/// first `limit` are read for snippets.
+pub(crate) fn find(
+    index: &SearchIndex,
+    matches: &BTreeMap<&String, Vec<(&str, MatchKind)>>,
+    limit: usize,
+    contents: &mut dyn DocpackReader,
+) -> (Vec<Hit>, usize) {
+    let mut files: BTreeMap<&str, FileMatch> = BTreeMap::new();
+    for (i, terms) in matches.values().enumerate() {
+        // The files this token matches, by their best term
+        let mut found: BTreeMap<&str, FileMatch> = BTreeMap::new();
+        for (term, kind) in terms {
+            for posting in &index.index[*term] {
+                let (best, count, lines) = found.entry(&posting.path).or_insert((*kind, 0, BTreeSet::new()));
+                *best = (*best).min(*kind);
+                *count += posting.lines.len();
+                lines.extend(posting.lines.iter().map(|&line| line as usize));
+            }
+        }
+        if i == 0 {
+            files = found;
+            continue;
+        }
+        files.retain(|path, _| found.contains_key(path));
+        for (path, (kind, count, lines)) in files.iter_mut() {
+            let (token_kind, token_count, token_lines) = &found[path];
+            *kind = (*kind).max(*token_kind);
+            *count += token_count;
+            lines.extend(token_lines);
+        }
+    }
+
+    let mut ranked: Vec<(&str, FileMatch)> = files.into_iter().collect();
+    ranked.sort_by(|(path_a, (kind_a, count_a, _)), (path_b, (kind_b, count_b, _))| {
+        kind_a.cmp(kind_b).then_with(|| count_b.cmp(count_a)).then_with(|| path_a.cmp(path_b))
+    });
+
+    let total = ranked.iter().map(|(_, (_, _, lines))| lines.len()).sum();
+    let mut hits = Vec::new();
+    for (path, (kind, score, lines)) in ranked {
+        if hits.len() >= limit {
+            break;
+        }
+        let text = contents.source_text(path).unwrap_or_default();
+        let file_lines: Vec<&str> = text.lines().collect();
+        for line in lines.into_iter().take(limit - hits.len()) {
+            let snippet = line.checked_sub(1).and_then(|i| file_lines.get(i)).map(|text| snippet(text)).unwrap_or_default();
+            hits.push(Hit { path: path.to_string(), line, kind, score, snippet });
+        }
+    }
+    (hits, total)
+}
+
+fn snippet(line: &str) -> String {
+    let line = line.trim();
+    if line.chars().count() <= MAX_SNIPPET_CHARS {
+        return line.to_string();
+    }
+    format!("{}…", line.chars().take(MAX_SNIPPET_CHARS).collect::<String>())
+}
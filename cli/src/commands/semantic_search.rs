@@ -0,0 +1,208 @@
+use std::io::Read;
+use std::path::Path;
+use serde_json::{json, Value};
+
+use crate::error::LocaldocError;
+use crate::reader;
+use crate::embeddings::{self, ApiSettings, Chunk, Client};
+
+/// Vectors scored per read of `embeddings.bin`, bounding memory use to
+/// this many rows regardless of the file's size.
+const ROWS_PER_BLOCK: usize = 4096;
+
+/// Longest snippet printed for a hit, in characters.
+const MAX_SNIPPET_CHARS: usize = 200;
+
+/// Settings for `localdoc semantic-search`, one field per command-line flag.
+pub struct SemanticSearchOptions<'a> {
+    /// `None` falls back to the environment, then the OpenAI defaults
+    pub api_base: Option<&'a str>,
+    pub api_key: Option<&'a str>,
+    /// `None` uses the model the docpack was embedded with
+    pub model: Option<&'a str>,
+    /// Number of results to show
+    pub k: usize,
+    /// Lowest cosine similarity shown
+    pub threshold: Option<f32>,
+    pub json: bool,
+}
+
+pub fn run(docpack: &str, query: &str, opts: &SemanticSearchOptions) -> Result<(), LocaldocError> {
+    let mut contents = reader::open(Path::new(docpack))?;
+
+    let Some(meta) = contents.read(&format!("index/{}", embeddings::META_FILE)) else {
+        return Err(format!(
+            "{} has no embeddings (index/{}). Build them with:\n  localdoc embed {}",
+            docpack,
+            embeddings::BIN_FILE,
+            docpack
+        )
+        .into());
+    };
+    let meta: Value = serde_json::from_slice(&meta)
+        .map_err(|e| format!("index/{} is not readable: {}", embeddings::META_FILE, e))?;
+    let chunks: Vec<Chunk> = serde_json::from_value(meta["chunks"].clone())
+        .map_err(|e| format!("index/{} has no readable chunk list: {}", embeddings::META_FILE, e))?;
+    let recorded_model = meta["metadata"]["model"].as_str();
+
+    // Embed the query with the model the docpack was embedded with
+    let settings = ApiSettings::resolve(opts.api_base, opts.api_key, opts.model.or(recorded_model));
+    settings.check_key()?;
+    let mut client = Client::new(settings.clone(), 0);
+    let query_vector = client.embed(&[query])?.pop().unwrap_or_default();
+
+    let mut reader = contents
+        .open_file(&format!("index/{}", embeddings::BIN_FILE))
+        .map_err(|_| format!("{} has index/{} but no index/{}", docpack, embeddings::META_FILE, embeddings::BIN_FILE))?;
+    let header = embeddings::read_stream_header(&mut reader).map_err(|e| format!("index/{}: {}", embeddings::BIN_FILE, e))?;
+
+    if query_vector.len() != header.dimension {
+        return Err(format!(
+            "Model mismatch: {} returned a {}-dimensional query embedding, but index/{} holds {}-dimensional vectors{}.\n\
+             Query with the model the docpack was embedded with (--model), or rebuild with `localdoc embed {} --force`",
+            settings.model,
+            query_vector.len(),
+            embeddings::BIN_FILE,
+            header.dimension,
+            recorded_model.map(|model| format!(" from {}", model)).unwrap_or_default(),
+            docpack
+        )
+        .into());
+    }
+    if header.count != chunks.len() {
+        return Err(format!(
+            "index/{} has {} vectors but index/{} lists {} chunks; rebuild with `localdoc embed {} --force`",
+            embeddings::BIN_FILE,
+            header.count,
+            embeddings::META_FILE,
+            chunks.len(),
+            docpack
+        )
+        .into());
+    }
+
+    let scores = top_k(&mut reader, &query_vector, header.count, opts.k, opts.threshold.unwrap_or(f32::NEG_INFINITY))?;
+    drop(reader);
+
+    let hits: Vec<(f32, &Chunk, usize, String)> = scores
+        .into_iter()
+        .map(|(score, row)| {
+            let chunk = &chunks[row];
+            let text = contents.source_text(&chunk.path).unwrap_or_default();
+            let (line, snippet) = snippet(&text, chunk);
+            (score, chunk, line, snippet)
+        })
+        .collect();
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&json!({
+            "query": query,
+            "model": settings.model,
+            "results": hits
+                .iter()
+                .map(|(score, chunk, line, snippet)| json!({
+                    "path": chunk.path,
+                    "start": chunk.start,
+                    "end": chunk.end,
+                    "line": line,
+                    "score": score,
+                    "snippet": snippet,
+                }))
+                .collect::<Vec<_>>(),
+        }))?);
+        return Ok(());
+    }
+
+    if hits.is_empty() {
+        println!("No chunks similar to '{}'", query);
+    }
+    for (score, chunk, line, snippet) in &hits {
+        println!("{:.3}  {}:{} (bytes {}-{})", score, chunk.path, line, chunk.start, chunk.end);
+        println!("       {}", snippet);
+    }
+    Ok(())
+}
+
+/// The `k` rows most similar to `query` by cosine similarity, at least
+/// `threshold`, best first. Rows are read `ROWS_PER_BLOCK` at a time.
+fn top_k(
+    reader: &mut dyn Read,
+    query: &[f32],
+    count: usize,
+    k: usize,
+    threshold: f32,
+) -> Result<Vec<(f32, usize)>, LocaldocError> {
+    let dimension = query.len();
+    let query_norm = dot(query, query).sqrt();
+    let mut best: Vec<(f32, usize)> = Vec::new();
+    let block_rows = count.min(ROWS_PER_BLOCK);
+    let mut bytes = vec![0u8; block_rows * dimension * 4];
+    let mut block = vec![0f32; block_rows * dimension];
+
+    let mut row = 0;
+    while row < count {
+        let rows = (count - row).min(block_rows);
+        let bytes = &mut bytes[..rows * dimension * 4];
+        reader
+            .read_exact(bytes)
+            .map_err(|e| format!("index/{} is truncated at row {}: {}", embeddings::BIN_FILE, row, e))?;
+        for (value, le) in block.iter_mut().zip(bytes.chunks_exact(4)) {
+            *value = f32::from_le_bytes([le[0], le[1], le[2], le[3]]);
+        }
+
+        for (i, vector) in block[..rows * dimension].chunks_exact(dimension).enumerate() {
+            let norm = dot(vector, vector).sqrt() * query_norm;
+            let score = if norm > 0.0 { dot(vector, query) / norm } else { 0.0 };
+            if score >= threshold {
+                best.push((score, row + i));
+            }
+        }
+        // Keep the candidate list short without sorting every block
+        if best.len() > k.max(1) * 4 {
+            best.select_nth_unstable_by(k, |a, b| b.0.total_cmp(&a.0));
+            best.truncate(k);
+        }
+        row += rows;
+    }
+
+    best.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+    best.truncate(k);
+    Ok(best)
+}
+
+/// Dot product over eight independent lanes, which the compiler turns
+/// into SIMD instructions.
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let mut lanes = [0f32; 8];
+    let (a_chunks, b_chunks) = (a.chunks_exact(8), b.chunks_exact(8));
+    let tail: f32 = a_chunks.remainder().iter().zip(b_chunks.remainder()).map(|(x, y)| x * y).sum();
+    for (x, y) in a_chunks.zip(b_chunks) {
+        for ((lane, x), y) in lanes.iter_mut().zip(x).zip(y) {
+            *lane += x * y;
+        }
+    }
+    lanes.iter().sum::<f32>() + tail
+}
+
+/// The 1-based line the snippet starts on, and the chunk's text on one
+/// line. Chunks that start mid-line (in the overlap with the previous one)
+/// are shown from their first full line.
+fn snippet(text: &str, chunk: &Chunk) -> (usize, String) {
+    let Some(mut slice) = text.get(chunk.start..chunk.end) else {
+        return (0, String::new());
+    };
+    let mut start = chunk.start;
+    if start > 0 && !text[..start].ends_with('\n') {
+        if let Some(newline) = slice.find('\n').filter(|&i| !slice[i + 1..].trim().is_empty()) {
+            slice = &slice[newline + 1..];
+            start += newline + 1;
+        }
+    }
+    let line = text[..start].matches('\n').count() + 1;
+    let words: Vec<&str> = slice.split_whitespace().collect();
+    let flat = words.join(" ");
+    if flat.chars().count() <= MAX_SNIPPET_CHARS {
+        return (line, flat);
+    }
+    (line, format!("{}…", flat.chars().take(MAX_SNIPPET_CHARS).collect::<String>()))
+}
@@ -0,0 +1,590 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use log::{info, warn};
+use serde_json::Value;
+
+use crate::commands::{export, run, search};
+use crate::error::LocaldocError;
+use crate::reader::{self, DocpackReader};
+use crate::search::SearchIndex;
+use crate::size::format_size;
+use crate::tokenize::tokenize;
+
+/// Longest request line and headers accepted.
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// How long a connection may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Matching lines shown for one search.
+const MAX_SEARCH_RESULTS: usize = 100;
+
+/// Bytes looked at to tell a binary file from text, as `cat` does.
+const SNIFF_BYTES: usize = 8000;
+
+/// Pages may load what the server has and nothing else, and run no
+/// scripts, whatever HTML the outputs contain.
+const CONTENT_SECURITY_POLICY: &str =
+    "default-src 'self'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; script-src 'none'";
+
+const STYLE: &str = "
+body { margin: 0; font: 16px/1.6 system-ui, sans-serif; color: #222; }
+header { display: flex; gap: 1.5rem; align-items: center; padding: 0.5rem 1.5rem; background: #f6f6f6; border-bottom: 1px solid #ddd; }
+header a { color: #333; text-decoration: none; }
+header .name { font-weight: bold; }
+header form { margin-left: auto; }
+main { max-width: 60rem; padding: 1rem 1.5rem; overflow-wrap: break-word; }
+pre { background: #f6f6f6; padding: 0.75rem; overflow-x: auto; }
+pre.lines a { display: inline-block; width: 3.5rem; color: #999; text-decoration: none; user-select: none; }
+code { font-family: ui-monospace, monospace; font-size: 0.9em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ddd; padding: 0.25rem 0.5rem; text-align: left; }
+td.size { text-align: right; color: #666; }
+.crumbs { color: #666; }
+img { max-width: 100%; }
+";
+
+/// Settings for `localdoc serve`, one field per command-line flag.
+pub struct ServeOptions<'a> {
+    pub host: &'a str,
+    pub port: u16,
+    /// Most bytes of archive entries kept in memory between requests
+    pub cache_size: u64,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn html(body: String) -> Self {
+        Response { status: 200, content_type: "text/html; charset=utf-8", body: body.into_bytes() }
+    }
+
+    fn not_found(site: &Site, message: &str) -> Self {
+        let page = site.page("Not found", &format!("<h1>Not found</h1>\n<p>{}</p>\n", export::escape(message)));
+        Response { status: 404, ..Response::html(page) }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Archive entries read so far. Once they add up to more than `cap`
+/// bytes the oldest are dropped; an entry larger than that is not kept.
+struct Cache {
+    entries: HashMap<String, Arc<Vec<u8>>>,
+    order: VecDeque<String>,
+    bytes: usize,
+    cap: usize,
+}
+
+impl Cache {
+    fn new(cap: usize) -> Self {
+        Cache { entries: HashMap::new(), order: VecDeque::new(), bytes: 0, cap }
+    }
+
+    fn insert(&mut self, name: String, bytes: Vec<u8>) -> Arc<Vec<u8>> {
+        let bytes = Arc::new(bytes);
+        if bytes.len() > self.cap {
+            return bytes;
+        }
+        self.bytes += bytes.len();
+        self.entries.insert(name.clone(), bytes.clone());
+        self.order.push_back(name);
+        while self.bytes > self.cap {
+            let Some(oldest) = self.order.pop_front() else { break };
+            if let Some(dropped) = self.entries.remove(&oldest) {
+                self.bytes -= dropped.len();
+            }
+        }
+        bytes
+    }
+}
+
+/// The docpack being served and what has been read from it.
+struct Site {
+    docpack: String,
+    name: String,
+    reader: Box<dyn DocpackReader>,
+    cache: Cache,
+    /// Loaded on the first search
+    index: Option<Result<SearchIndex, String>>,
+}
+
+/// Serve a small web UI for a docpack until interrupted: its files, its
+/// outputs rendered from Markdown, its manifest and tasks, and a search
+/// box backed by the search index. Archives are read in place, one entry
+/// at a time as pages ask for it.
+pub fn serve(docpack: &str, opts: &ServeOptions) -> Result<(), LocaldocError> {
+    let reader = reader::open(Path::new(docpack))?;
+    let listener = TcpListener::bind((opts.host, opts.port))
+        .map_err(|e| format!("Cannot listen on {}:{}: {}", opts.host, opts.port, e))?;
+    let address = listener.local_addr()?;
+    let site = Arc::new(Mutex::new(Site {
+        docpack: docpack.to_string(),
+        name: run::docpack_name(Path::new(docpack)),
+        reader,
+        cache: Cache::new(usize::try_from(opts.cache_size).unwrap_or(usize::MAX)),
+        index: None,
+    }));
+
+    info!("Serving {} at http://{}/ (Ctrl+C to stop)", docpack, address);
+    if !address.ip().is_loopback() {
+        warn!("Listening on {}, so other machines can read this docpack", address.ip());
+    }
+    io::stdout().flush()?;
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let site = Arc::clone(&site);
+        thread::spawn(move || {
+            // The browser going away mid-response is not worth reporting
+            let _ = handle_connection(stream, &site);
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, site: &Mutex<Site>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?).take(MAX_REQUEST_HEAD as u64);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are read and ignored; every response closes the connection
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    let response = if method != "GET" && method != "HEAD" {
+        Response { status: 405, content_type: "text/plain; charset=utf-8", body: b"Only GET and HEAD are supported\n".to_vec() }
+    } else {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let mut site = site.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match percent_decode(path) {
+            Some(path) => site.handle(&path, query),
+            None => Response { status: 400, content_type: "text/plain; charset=utf-8", body: b"Bad request path\n".to_vec() },
+        }
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nContent-Security-Policy: {}\r\nX-Content-Type-Options: nosniff\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len(),
+        CONTENT_SECURITY_POLICY,
+    )?;
+    if method != "HEAD" {
+        stream.write_all(&response.body)?;
+    }
+    stream.flush()
+}
+
+impl Site {
+    fn handle(&mut self, path: &str, query: &str) -> Response {
+        let (section, rest) = match path.trim_start_matches('/').split_once('/') {
+            Some((section, rest)) => (section, rest),
+            None => (path.trim_start_matches('/'), ""),
+        };
+        let Some(rest) = clean_path(rest) else {
+            return Response::not_found(self, &format!("{} is not a path in the docpack", path));
+        };
+        match (section, rest.as_str()) {
+            ("", "") => self.overview(),
+            ("manifest", "") => self.manifest(),
+            ("tasks", "") => self.tasks(),
+            ("search", "") => self.search(&query_param(query, "q").unwrap_or_default()),
+            ("files" | "output", _) => self.tree(section, &rest),
+            ("raw", rest) if !rest.is_empty() => self.raw(rest),
+            _ => Response::not_found(self, &format!("No page {}", path)),
+        }
+    }
+
+    /// Read a file of the docpack, by its path from the root. Archive
+    /// entries go through the cache; a directory is read afresh each time
+    /// so a run writing outputs shows up.
+    fn read(&mut self, path: &str) -> Option<Arc<Vec<u8>>> {
+        let name = match path.strip_prefix("files/") {
+            Some(file) => self.reader.source_name(file).unwrap_or_else(|| path.to_string()),
+            None => path.to_string(),
+        };
+        if !self.reader.is_archive() {
+            return self.reader.read(&name).map(Arc::new);
+        }
+        if let Some(bytes) = self.cache.entries.get(&name) {
+            return Some(bytes.clone());
+        }
+        let bytes = self.reader.read(&name)?;
+        Some(self.cache.insert(name, bytes))
+    }
+
+    fn read_json(&mut self, path: &str) -> Option<Value> {
+        serde_json::from_slice(&self.read(path)?).ok()
+    }
+
+    fn page(&self, title: &str, content: &str) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{title} - {name}</title>\n<style>{style}</style>\n</head>\n<body>\n<header>\n\
+             <a class=\"name\" href=\"/\">{name}</a>\n<a href=\"/files/\">Files</a>\n<a href=\"/output/\">Outputs</a>\n\
+             <a href=\"/manifest\">Manifest</a>\n<a href=\"/tasks\">Tasks</a>\n\
+             <form action=\"/search\"><input type=\"search\" name=\"q\" placeholder=\"Search files\"></form>\n\
+             </header>\n<main>\n{content}</main>\n</body>\n</html>\n",
+            title = export::escape(title),
+            name = export::escape(&self.name),
+            style = STYLE,
+            content = content,
+        )
+    }
+
+    fn overview(&mut self) -> Response {
+        let manifest = self.reader.read_manifest().ok();
+        let files = self.reader.list_files("files");
+        let outputs = self.reader.list_files("output");
+        let name = manifest.as_ref().and_then(|manifest| manifest.name.as_deref()).unwrap_or(&self.name);
+        let mut content = format!("<h1>{}</h1>\n", export::escape(name));
+        if let Some(description) = manifest.as_ref().and_then(|manifest| manifest.description.as_deref()) {
+            content.push_str(&format!("<p>{}</p>\n", export::escape(description)));
+        }
+        let size: u64 = files.iter().map(|(_, size)| size).sum();
+        content.push_str("<table>\n");
+        content.push_str(&format!("<tr><th>Docpack</th><td>{}</td></tr>\n", export::escape(&self.docpack)));
+        content.push_str(&format!(
+            "<tr><th>Version</th><td>{}</td></tr>\n",
+            export::escape(manifest.as_ref().map_or("unknown", |manifest| manifest.version.as_str()))
+        ));
+        content.push_str(&format!("<tr><th><a href=\"/files/\">Files</a></th><td>{} ({})</td></tr>\n", files.len(), format_size(size)));
+        content.push_str(&format!("<tr><th><a href=\"/output/\">Outputs</a></th><td>{}</td></tr>\n", outputs.len()));
+        content.push_str("</table>\n");
+
+        // The documentation itself, when a run has written an index page
+        if let Some(index) = ["README.md", "index.md"].iter().find(|name| outputs.iter().any(|(path, _)| path == *name)) {
+            if let Some(bytes) = self.read(&format!("output/{}", index)) {
+                content.push_str(&format!("<hr>\n<p class=\"crumbs\">output/{}</p>\n", index));
+                content.push_str(&render_output(&String::from_utf8_lossy(&bytes), index));
+            }
+        }
+        Response::html(self.page("Overview", &content))
+    }
+
+    fn manifest(&mut self) -> Response {
+        let Some(manifest) = self.read_json("docpack.json") else {
+            return Response::not_found(self, "docpack.json is missing or not valid JSON");
+        };
+        let json = serde_json::to_string_pretty(&manifest).unwrap_or_default();
+        let content = format!("<h1>Manifest</h1>\n<p class=\"crumbs\">docpack.json</p>\n<pre><code>{}</code></pre>\n", export::escape(&json));
+        Response::html(self.page("Manifest", &content))
+    }
+
+    fn tasks(&mut self) -> Response {
+        let Some(tasks) = self.read_json("tasks.json") else {
+            return Response::not_found(self, "tasks.json is missing or not valid JSON");
+        };
+        let mut content = String::from("<h1>Tasks</h1>\n");
+        if let Some(mission) = tasks["mission"].as_str() {
+            content.push_str(&format!("<p><strong>Mission:</strong> {}</p>\n", export::escape(mission)));
+        }
+        content.push_str("<ol>\n");
+        for task in tasks["tasks"].as_array().into_iter().flatten() {
+            content.push_str(&format!("<li><strong>{}</strong>", export::escape(task["name"].as_str().unwrap_or("unnamed"))));
+            if let Some(description) = task["description"].as_str() {
+                content.push_str(&format!(" - {}", export::escape(description)));
+            }
+            content.push_str("</li>\n");
+        }
+        content.push_str("</ol>\n");
+        let json = serde_json::to_string_pretty(&tasks).unwrap_or_default();
+        content.push_str(&format!("<h2>tasks.json</h2>\n<pre><code>{}</code></pre>\n", export::escape(&json)));
+        Response::html(self.page("Tasks", &content))
+    }
+
+    fn search(&mut self, query: &str) -> Response {
+        let mut content = format!(
+            "<h1>Search</h1>\n<form action=\"/search\"><input type=\"search\" name=\"q\" value=\"{}\"> <button>Search</button></form>\n",
+            export::escape(query)
+        );
+        if query.trim().is_empty() {
+            return Response::html(self.page("Search", &content));
+        }
+        let Site { docpack, reader, index, .. } = self;
+        let index = index.get_or_insert_with(|| search::load_index(reader.as_mut(), docpack).map_err(|e| e.to_string()));
+        let index = match index {
+            Ok(index) => &*index,
+            Err(e) => {
+                content.push_str(&format!("<p>{}</p>\n", export::escape(e)));
+                return Response::html(self.page("Search", &content));
+            }
+        };
+        let tokens: BTreeSet<String> = tokenize(query).into_iter().collect();
+        if tokens.is_empty() {
+            content.push_str("<p>Nothing to search for: words need at least 3 characters.</p>\n");
+            return Response::html(self.page("Search", &content));
+        }
+        let matches = tokens.iter().map(|token| (token, index.matching_terms(token, true, false))).collect();
+        let (hits, total) = search::find(index, &matches, MAX_SEARCH_RESULTS, reader.as_mut());
+
+        if total == 0 {
+            content.push_str(&format!("<p>No matches for <code>{}</code>.</p>\n", export::escape(query)));
+        } else {
+            content.push_str(&format!("<p>{} matching line(s)", total));
+            if total > hits.len() {
+                content.push_str(&format!(", the first {} shown", MAX_SEARCH_RESULTS));
+            }
+            content.push_str("</p>\n<ul>\n");
+            for hit in &hits {
+                content.push_str(&format!(
+                    "<li><a href=\"/files/{}#L{}\">{}:{}</a> <code>{}</code></li>\n",
+                    encode_path(&hit.path),
+                    hit.line,
+                    export::escape(&hit.path),
+                    hit.line,
+                    export::escape(&hit.snippet)
+                ));
+            }
+            content.push_str("</ul>\n");
+        }
+        Response::html(self.page("Search", &content))
+    }
+
+    /// A file under `files/` or `output/`, or a listing of a directory.
+    fn tree(&mut self, root: &str, path: &str) -> Response {
+        let listing = self.reader.list_files(root);
+        if listing.iter().any(|(file, _)| file == path) {
+            return self.file(root, path);
+        }
+
+        let prefix = if path.is_empty() { String::new() } else { format!("{}/", path) };
+        let mut dirs = BTreeSet::new();
+        let mut files = BTreeMap::new();
+        for (file, size) in &listing {
+            let Some(rest) = file.strip_prefix(&prefix) else { continue };
+            match rest.split_once('/') {
+                Some((dir, _)) => dirs.insert(dir.to_string()),
+                None => files.insert(rest.to_string(), *size).is_none(),
+            };
+        }
+        let full = format!("{}/{}", root, prefix);
+        if dirs.is_empty() && files.is_empty() {
+            if !path.is_empty() {
+                return Response::not_found(self, &format!("No file or directory {} in {}", full.trim_end_matches('/'), self.docpack));
+            }
+            let message = match root {
+                "output" => format!(
+                    "No outputs yet; run the documenter with <code>localdoc run {}</code>.",
+                    export::escape(&self.docpack)
+                ),
+                _ => "This docpack has no files.".to_string(),
+            };
+            return Response::html(self.page(&full, &format!("<h1>{}</h1>\n<p>{}</p>\n", export::escape(&full), message)));
+        }
+
+        let mut content = format!("<h1>{}</h1>\n<table>\n", crumbs(&full));
+        if !path.is_empty() {
+            content.push_str("<tr><td><a href=\"../\">../</a></td><td></td></tr>\n");
+        }
+        for dir in &dirs {
+            content.push_str(&format!(
+                "<tr><td><a href=\"/{}{}/\">{}/</a></td><td></td></tr>\n",
+                encode_path(&full),
+                encode_path(dir),
+                export::escape(dir)
+            ));
+        }
+        for (file, size) in &files {
+            content.push_str(&format!(
+                "<tr><td><a href=\"/{}{}\">{}</a></td><td class=\"size\">{}</td></tr>\n",
+                encode_path(&full),
+                encode_path(file),
+                export::escape(file),
+                format_size(*size)
+            ));
+        }
+        content.push_str("</table>\n");
+        Response::html(self.page(&full, &content))
+    }
+
+    /// An output page rendered from Markdown, any other output as it is
+    /// (so the images outputs show load), or a source file with numbered
+    /// lines.
+    fn file(&mut self, root: &str, path: &str) -> Response {
+        let full = format!("{}/{}", root, path);
+        let Some(bytes) = self.read(&full) else {
+            return Response::not_found(self, &format!("Cannot read {}", full));
+        };
+        if root == "output" && !path.ends_with(".md") {
+            return Response { status: 200, content_type: content_type(path), body: bytes.to_vec() };
+        }
+
+        let mut content = format!("<p class=\"crumbs\">{}</p>\n", crumbs(&full));
+        if root == "output" {
+            content.push_str(&render_output(&String::from_utf8_lossy(&bytes), path));
+        } else if content_type(path).starts_with("image/") {
+            content.push_str(&format!("<img src=\"/raw/{}\" alt=\"{}\">\n", encode_path(&full), export::escape(path)));
+        } else if bytes[..bytes.len().min(SNIFF_BYTES)].contains(&0) {
+            content.push_str(&format!(
+                "<p>Binary file, {}. <a href=\"/raw/{}\">Download it</a>.</p>\n",
+                format_size(bytes.len() as u64),
+                encode_path(&full)
+            ));
+        } else {
+            content.push_str("<pre class=\"lines\"><code>");
+            for (i, line) in String::from_utf8_lossy(&bytes).lines().enumerate() {
+                content.push_str(&format!("<a id=\"L{n}\" href=\"#L{n}\">{n}</a>{}\n", export::escape(line), n = i + 1));
+            }
+            content.push_str(&format!("</code></pre>\n<p><a href=\"/raw/{}\">Raw</a></p>\n", encode_path(&full)));
+        }
+        Response::html(self.page(&full, &content))
+    }
+
+    fn raw(&mut self, path: &str) -> Response {
+        match self.read(path) {
+            Some(bytes) => Response { status: 200, content_type: content_type(path), body: bytes.to_vec() },
+            None => Response::not_found(self, &format!("No file {} in {}", path, self.docpack)),
+        }
+    }
+}
+
+/// Render the output page `page` (a path under `output/`), pointing its
+/// local links at the server's pages, so they work wherever it is shown
+/// and whether the agent wrote them relative or under `/workspace`.
+fn render_output(markdown: &str, page: &str) -> String {
+    let markdown = export::rewrite_links(markdown, |dest| {
+        let (path, fragment) = export::resolve(page, dest)?;
+        let section = if path.starts_with("output/") || path.starts_with("files/") { "" } else { "raw/" };
+        Some(format!("/{}{}{}", section, encode_path(&path), fragment))
+    });
+    export::render(&markdown)
+}
+
+/// `files / src / main.rs`, each directory a link to its listing.
+fn crumbs(path: &str) -> String {
+    let parts: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    let mut crumbs = Vec::with_capacity(parts.len());
+    for (i, part) in parts.iter().enumerate() {
+        if i + 1 == parts.len() {
+            crumbs.push(export::escape(part));
+        } else {
+            crumbs.push(format!("<a href=\"/{}/\">{}</a>", encode_path(&parts[..=i].join("/")), export::escape(part)));
+        }
+    }
+    crumbs.join(" / ")
+}
+
+/// A path inside the docpack as the server was asked for it, without the
+/// empty parts of `a//b/`, or `None` if it tries to leave the docpack.
+fn clean_path(path: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" => {}
+            "." | ".." => return None,
+            part if part.contains('\\') => return None,
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Decode `%XX` escapes, or `None` if they do not make UTF-8.
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Escape a path for an `href`, keeping its slashes.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// The value of `name` in a query string such as `q=foo+bar`.
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (key == name).then(|| percent_decode(&value.replace('+', " ")))?
+    })
+}
+
+fn content_type(path: &str) -> &'static str {
+    let extension = path.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" => "application/json",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "md" | "txt" | "rs" | "py" | "js" | "ts" | "toml" | "yaml" | "yml" | "csv" | "sh" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_paths_stay_inside_the_docpack() {
+        assert_eq!(clean_path("src//main.rs/").as_deref(), Some("src/main.rs"));
+        assert_eq!(clean_path("src/../../etc/passwd"), None);
+        assert_eq!(clean_path("..\\secret"), None);
+        assert_eq!(percent_decode("/files/my%20file.rs").as_deref(), Some("/files/my file.rs"));
+        assert_eq!(percent_decode("/files/%zz"), None);
+        assert_eq!(query_param("x=1&q=parse+config%21", "q").as_deref(), Some("parse config!"));
+        assert_eq!(encode_path("files/my file#1.rs"), "files/my%20file%231.rs");
+    }
+
+    #[test]
+    fn the_cache_drops_the_oldest_entries_past_its_cap() {
+        let mut cache = Cache::new(10);
+        cache.insert("a".into(), vec![0; 4]);
+        cache.insert("b".into(), vec![0; 4]);
+        cache.insert("c".into(), vec![0; 4]);
+        assert!(!cache.entries.contains_key("a") && cache.entries.contains_key("c"));
+        assert_eq!(cache.bytes, 8);
+        cache.insert("huge".into(), vec![0; 11]);
+        assert!(!cache.entries.contains_key("huge"));
+    }
+}
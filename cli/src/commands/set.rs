@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use log::info;
+use serde_json::{json, Value};
+
+use localdoc::docpack::Docpack;
+
+use crate::commands::update;
+use crate::error::{LocaldocError, WithPath};
+use crate::hashes::{self, FileHash};
+use crate::progress::Progress;
+use crate::seal;
+
+pub fn run(docpack: &str, key: &str, value: &str, force_mutable: bool) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+    let manifest_path = docpack_path.join("docpack.json");
+
+    // Checked first so a sealed archive is reported as sealed
+    seal::ensure_mutable(docpack_path, force_mutable)?;
+
+    Docpack::open_dir(docpack_path)?;
+
+    let content = fs::read_to_string(&manifest_path).with_path(&manifest_path)?;
+    let mut manifest: Value = serde_json::from_str(&content)?;
+
+    // Accept JSON literals (true, 42, ["a"]) and fall back to a plain string
+    let new_value: Value = serde_json::from_str(value).unwrap_or_else(|_| json!(value));
+
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|p| p.is_empty()) {
+        return Err(LocaldocError::InvalidArgument(format!("Invalid key: {}", key)));
+    }
+
+    let mut target = &mut manifest;
+    for part in &parts[..parts.len() - 1] {
+        if target[*part].is_null() {
+            target[*part] = json!({});
+        }
+        if !target[*part].is_object() {
+            return Err(format!("Cannot set '{}': '{}' is not an object", key, part).into());
+        }
+        target = &mut target[*part];
+    }
+
+    let last = parts[parts.len() - 1];
+    let old_value = target[last].clone();
+    target[last] = new_value.clone();
+
+    // Record who sealed the pack and when, and hash files/ so validation
+    // can tell whether it changed afterwards
+    if key == "metadata.immutable" && new_value == json!(true) {
+        let file_hashes = write_hashes(docpack_path)?;
+        manifest["metadata"]["sealed_by"] = json!(sealed_by());
+        manifest["metadata"]["sealed_at"] = json!(chrono::Utc::now().to_rfc3339());
+        manifest["metadata"]["hashes"] = hashes::metadata(&file_hashes);
+    }
+
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?).with_path(&manifest_path)?;
+
+    info!("✓ Set {} = {} (was {})", key, new_value, old_value);
+    if key == "metadata.immutable" && new_value == json!(true) {
+        info!("  Docpack is now sealed; mutating commands will refuse to modify it");
+    }
+
+    Ok(())
+}
+
+/// Regenerate `index/hashes.json` from `files/`.
+fn write_hashes(docpack_path: &Path) -> Result<BTreeMap<String, FileHash>, LocaldocError> {
+    let to_hash = update::existing_files(&docpack_path.join("files"))?;
+    let mut progress = Progress::new("Hashing", Some(to_hash.len() as u64), true);
+    let file_hashes = hashes::hash_files(&to_hash, &mut progress)?;
+    let index_dir = docpack_path.join("index");
+    fs::create_dir_all(&index_dir).with_path(&index_dir)?;
+    let path = index_dir.join(hashes::FILE_NAME);
+    fs::write(&path, serde_json::to_string_pretty(&hashes::to_json(&file_hashes))?).with_path(&path)?;
+    Ok(file_hashes)
+}
+
+/// `sealed_by` when the environment names no user.
+const UNKNOWN_USER: &str = "unknown";
+
+/// Who to record as `sealed_by`: `$LOCALDOC_SEALED_BY`, else the login
+/// name from `$USER` (`$USERNAME` on Windows), else [`UNKNOWN_USER`] when
+/// neither is set, as in a bare container or CI job.
+fn sealed_by() -> String {
+    ["LOCALDOC_SEALED_BY", "USER", "USERNAME"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| UNKNOWN_USER.to_string())
+}
+
@@ -0,0 +1,49 @@
+use std::fs;
+use std::path::Path;
+use std::process::Stdio;
+
+use log::info;
+
+use crate::commands::run::{finish_detached, LOG_FILE};
+use crate::error::{LocaldocError, WithPath};
+use crate::run_state;
+use crate::runtime;
+
+pub fn run(docpack: &str) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+    let Some((state, runtime)) = run_state::live(docpack_path)? else {
+        return Err(LocaldocError::NotFound(format!(
+            "No detached run is recorded for {}; start one with `localdoc run --detach`",
+            docpack
+        )));
+    };
+
+    if runtime.container_state(&state.container).is_some_and(|s| s.running) {
+        info!("Stopping container {}...", state.container);
+        let status = runtime
+            .stop_command(&state.container, runtime::STOP_GRACE_SECONDS)
+            .stdout(Stdio::null())
+            .status()?;
+        if !status.success() {
+            return Err(LocaldocError::ContainerFailed {
+                what: format!("{} stop {}", runtime.name(), state.container),
+                code: status.code(),
+                stderr: String::new(),
+            });
+        }
+    } else {
+        info!("Container {} has already exited", state.container);
+    }
+    let exit_code = runtime.container_state(&state.container).and_then(|s| s.exit_code);
+
+    // Keep the agent's output before the container, and its logs, are removed
+    let logs = runtime.logs_command(&state.container, false).output()?;
+    let mut log = logs.stdout;
+    log.extend_from_slice(&logs.stderr);
+    let log_path = state.working_dir.join(LOG_FILE);
+    fs::write(&log_path, log).with_path(&log_path)?;
+    info!("Agent output saved to {}", state.working_dir.join(LOG_FILE).display());
+    let _ = runtime.remove_command(&state.container).stdout(Stdio::null()).stderr(Stdio::null()).status();
+
+    finish_detached(docpack_path, &state, exit_code)
+}
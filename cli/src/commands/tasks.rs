@@ -0,0 +1,243 @@
+use std::fs;
+use std::path::Path;
+use docpack_core::Manifest;
+use log::{error, info, warn};
+use serde_json::{json, Value};
+
+use localdoc::docpack::{Docpack, DocpackKind};
+
+use crate::commands::{pack, validate};
+use crate::error::{LocaldocError, WithPath};
+use crate::reader::{self, DocpackReader};
+use crate::scratch::ScratchDir;
+use crate::seal;
+
+/// Print the id, name and output path of every task.
+pub fn list(docpack: &str) -> Result<(), LocaldocError> {
+    let mut reader = reader::open(Path::new(docpack))?;
+    let tasks = read_tasks(reader.as_mut(), docpack)?;
+    let rows: Vec<[String; 3]> = task_list(&tasks)
+        .iter()
+        .enumerate()
+        .map(|(i, task)| {
+            [
+                task["id"].as_str().map(String::from).unwrap_or_else(|| format!("#{}", i)),
+                task["name"].as_str().unwrap_or("-").to_string(),
+                task["output"]["path"].as_str().unwrap_or("-").to_string(),
+            ]
+        })
+        .collect();
+    if rows.is_empty() {
+        println!("No tasks in {}; add one with `localdoc tasks {} add --name <NAME> --description <TEXT>`", docpack, docpack);
+        return Ok(());
+    }
+
+    let headers = ["ID", "NAME", "OUTPUT"];
+    let width = |column: usize| rows.iter().map(|row| row[column].chars().count()).chain([headers[column].len()]).max().unwrap_or(0);
+    let (id_width, name_width) = (width(0), width(1));
+    println!("{:<id_width$}  {:<name_width$}  {}", headers[0], headers[1], headers[2]);
+    for [id, name, output] in &rows {
+        println!("{:<id_width$}  {:<name_width$}  {}", id, name, output);
+    }
+    Ok(())
+}
+
+/// Print one task in full.
+pub fn show(docpack: &str, id: &str) -> Result<(), LocaldocError> {
+    let mut reader = reader::open(Path::new(docpack))?;
+    let tasks = read_tasks(reader.as_mut(), docpack)?;
+    let task = task_list(&tasks).iter().find(|task| task["id"] == id).ok_or_else(|| unknown_task(&tasks, id))?;
+
+    println!("{}: {}", id, task["name"].as_str().unwrap_or("unnamed"));
+    if let Some(description) = task["description"].as_str() {
+        println!("  Description: {}", description);
+    }
+    if let Some(tools) = task["tools_allowed"].as_array() {
+        let tools: Vec<&str> = tools.iter().filter_map(Value::as_str).collect();
+        println!("  Tools:       {}", if tools.is_empty() { "none".to_string() } else { tools.join(", ") });
+    }
+    if let Some(depends_on) = task["depends_on"].as_array() {
+        let ids: Vec<&str> = depends_on.iter().filter_map(Value::as_str).collect();
+        println!("  Depends on:  {}", ids.join(", "));
+    }
+    if let Some(path) = task["output"]["path"].as_str() {
+        match task["output"]["type"].as_str() {
+            Some(kind) => println!("  Output:      {} ({})", path, kind),
+            None => println!("  Output:      {}", path),
+        }
+    }
+    // Fields localdoc does not know about are shown as they are
+    let known = ["id", "name", "description", "tools_allowed", "depends_on", "output"];
+    for (key, value) in task.as_object().into_iter().flatten().filter(|(key, _)| !known.contains(&key.as_str())) {
+        println!("  {}: {}", key, value);
+    }
+    Ok(())
+}
+
+/// Settings for `localdoc tasks add`, one field per command-line flag.
+pub struct AddOptions<'a> {
+    pub name: &'a str,
+    pub description: &'a str,
+    /// Default: every tool in the manifest's `environment.tools`
+    pub tools: Option<&'a [String]>,
+    /// Default: `output/<id>.md`
+    pub output_path: Option<&'a str>,
+    pub force_mutable: bool,
+}
+
+/// Append a task with the next free `task_N` id.
+pub fn add(docpack: &str, opts: &AddOptions) -> Result<(), LocaldocError> {
+    edit(docpack, opts.force_mutable, |tasks, manifest| {
+        let id = next_id(tasks);
+        let tools = match (opts.tools, manifest) {
+            (Some(tools), _) => tools,
+            (None, Some(manifest)) => manifest.environment.tools.as_slice(),
+            (None, None) => &[],
+        };
+        let path = opts.output_path.map(String::from).unwrap_or_else(|| format!("output/{}.md", id));
+        let task = json!({
+            "id": id,
+            "name": opts.name,
+            "description": opts.description,
+            "tools_allowed": tools,
+            "output": {
+                "type": output_type(&path),
+                "path": path,
+            }
+        });
+        match &mut tasks["tasks"] {
+            Value::Array(list) => list.push(task),
+            Value::Null => tasks["tasks"] = json!([task]),
+            _ => return Err("tasks.json: 'tasks' is not an array".into()),
+        }
+        Ok(format!("Added {} ({}) writing {}", id, opts.name, path))
+    })
+}
+
+/// Delete the task with id `id`. Tasks that depend on it are left alone,
+/// so it is refused while any do.
+pub fn remove(docpack: &str, id: &str, force_mutable: bool) -> Result<(), LocaldocError> {
+    edit(docpack, force_mutable, |tasks, _| {
+        let position = task_list(tasks).iter().position(|task| task["id"] == id).ok_or_else(|| unknown_task(tasks, id))?;
+        let dependents: Vec<&str> = task_list(tasks)
+            .iter()
+            .filter(|task| task["depends_on"].as_array().is_some_and(|ids| ids.iter().any(|dep| dep == id)))
+            .filter_map(|task| task["id"].as_str())
+            .collect();
+        if !dependents.is_empty() {
+            return Err(format!("{} is a dependency of {}; remove it from their depends_on first", id, dependents.join(", ")).into());
+        }
+        let removed = tasks["tasks"].as_array_mut().map(|list| list.remove(position)).unwrap_or_default();
+        Ok(format!("Removed {} ({})", id, removed["name"].as_str().unwrap_or("unnamed")))
+    })
+}
+
+/// Apply `change` to tasks.json and write it back if it still validates.
+/// An archive is changed in an extracted copy and rewritten in place.
+fn edit(
+    docpack: &str,
+    force_mutable: bool,
+    change: impl FnOnce(&mut Value, Option<&Manifest>) -> Result<String, LocaldocError>,
+) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+    let kind = Docpack::open_any(docpack_path)?.kind;
+    seal::ensure_mutable(docpack_path, force_mutable)?;
+    let scratch;
+    let work_dir = if kind == DocpackKind::Archive {
+        scratch = ScratchDir::new("docpack-tasks-")?;
+        pack::extract_docpack(docpack_path, scratch.path(), true)?;
+        scratch.path().to_path_buf()
+    } else {
+        docpack_path.to_path_buf()
+    };
+
+    let mut reader = reader::open(&work_dir)?;
+    let mut tasks = read_tasks(reader.as_mut(), docpack)?;
+    let manifest = reader.read_manifest().ok();
+    let done = change(&mut tasks, manifest.as_ref())?;
+
+    let (mut errors, mut warnings) = (Vec::new(), Vec::new());
+    let tools = manifest.as_ref().map(|manifest| manifest.environment.tools.as_slice());
+    validate::validate_tasks(&tasks, tools, &mut errors, &mut warnings);
+    if !errors.is_empty() {
+        for error in &errors {
+            error!("{}", error);
+        }
+        return Err("tasks.json would not be valid after this change; nothing was written".into());
+    }
+    for warning in &warnings {
+        warn!("{}", warning);
+    }
+
+    let tasks_path = work_dir.join("tasks.json");
+    fs::write(&tasks_path, serde_json::to_string_pretty(&tasks)?).with_path(&tasks_path)?;
+    if kind == DocpackKind::Archive {
+        pack::write_archive(&work_dir, docpack_path)?;
+    }
+    info!("✓ {}", done);
+    Ok(())
+}
+
+fn read_tasks(reader: &mut dyn DocpackReader, docpack: &str) -> Result<Value, LocaldocError> {
+    let bytes = reader.read("tasks.json").ok_or_else(|| LocaldocError::NotFound(format!("{} has no tasks.json", docpack)))?;
+    let tasks: Value = serde_json::from_slice(&bytes).map_err(|e| format!("{}: tasks.json is not valid JSON: {}", docpack, e))?;
+    if !tasks.is_object() {
+        return Err(format!("{}: tasks.json is not a JSON object", docpack).into());
+    }
+    Ok(tasks)
+}
+
+fn task_list(tasks: &Value) -> &[Value] {
+    tasks["tasks"].as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn unknown_task(tasks: &Value, id: &str) -> LocaldocError {
+    let ids: Vec<&str> = task_list(tasks).iter().filter_map(|task| task["id"].as_str()).collect();
+    if ids.is_empty() {
+        return format!("No task {}; tasks.json has no tasks", id).into();
+    }
+    format!("No task {}; the tasks are {}", id, ids.join(", ")).into()
+}
+
+/// `task_N` for the first N above every id of that form in use.
+fn next_id(tasks: &Value) -> String {
+    let highest = task_list(tasks)
+        .iter()
+        .filter_map(|task| task["id"].as_str()?.strip_prefix("task_")?.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0);
+    format!("task_{}", highest + 1)
+}
+
+/// The `output.type` recorded for a path, from its extension.
+pub(crate) fn output_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase()).as_deref() {
+        Some("json") => "json",
+        Some("txt") => "text",
+        Some("html") => "html",
+        _ => "markdown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::MemReader;
+
+    #[test]
+    fn new_ids_follow_the_highest_task_number() {
+        assert_eq!(next_id(&json!({})), "task_1");
+        let tasks = json!({ "tasks": [{ "id": "task_2" }, { "id": "overview" }, { "id": "task_10" }] });
+        assert_eq!(next_id(&tasks), "task_11");
+        assert_eq!(output_type("output/api.json"), "json");
+        assert_eq!(output_type("output/api.md"), "markdown");
+    }
+
+    #[test]
+    fn tasks_json_must_be_an_object() {
+        let mut reader = MemReader::default().with("tasks.json", "[]");
+        let error = read_tasks(&mut reader, "memory.docpack").unwrap_err();
+        assert_eq!(error.to_string(), "memory.docpack: tasks.json is not a JSON object");
+        assert!(read_tasks(&mut MemReader::default(), "memory.docpack").is_err());
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use docpack_core::{Manifest, Strictness, MANIFEST_FILE};
+use log::{info, warn};
+use serde_json::{json, Value};
+
+use localdoc::archive;
+use localdoc::docpack::{Docpack, DocpackKind};
+
+use crate::commands::{ingest, pack};
+use crate::error::{LocaldocError, WithPath};
+use crate::filter::{self, SourceFilter};
+use crate::fingerprint;
+use crate::hashes;
+use crate::progress::Progress;
+use crate::scratch::ScratchDir;
+use crate::seal;
+use crate::search::{self, IndexFormat, SearchIndex};
+use crate::symbols;
+
+/// Settings for `localdoc update`, one field per command-line flag.
+pub struct UpdateOptions<'a> {
+    pub source: &'a str,
+    /// Overrides the filters recorded in the index when non-empty
+    pub include: &'a [String],
+    pub exclude: &'a [String],
+    /// Keep the default-excluded directories even if the index was built
+    /// without them
+    pub no_default_excludes: bool,
+    /// `Some(0)` disables the limit; `None` uses the recorded one
+    pub max_file_size: Option<u64>,
+    pub quiet: bool,
+    pub force_mutable: bool,
+}
+
+/// How `files/` changed.
+#[derive(Default)]
+struct Changes {
+    added: Vec<String>,
+    modified: Vec<String>,
+    removed: Vec<String>,
+    unchanged: usize,
+}
+
+pub fn run(docpack: &str, opts: &UpdateOptions) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+    let source_path = Path::new(opts.source);
+
+    if !source_path.is_dir() {
+        return Err(LocaldocError::SourceNotFound { what: "Source directory", path: source_path.to_path_buf() });
+    }
+
+    let kind = Docpack::open_any(docpack_path)?.kind;
+    info!("Updating .docpack: {}", docpack);
+    info!("  Source: {}", opts.source);
+
+    seal::ensure_mutable(docpack_path, opts.force_mutable)?;
+
+    // Archives are updated in an extracted copy and rewritten at the end
+    let scratch;
+    let work_dir = if kind == DocpackKind::Archive {
+        info!("Extracting .docpack archive...");
+        scratch = ScratchDir::new("docpack-update-")?;
+        pack::extract_docpack(docpack_path, scratch.path(), false)?;
+        scratch.path().to_path_buf()
+    } else {
+        docpack_path.to_path_buf()
+    };
+
+    let changes = update_dir(&work_dir, source_path, opts)?;
+
+    if kind == DocpackKind::Archive {
+        if changes.added.is_empty() && changes.modified.is_empty() && changes.removed.is_empty() {
+            info!("Archive unchanged");
+        } else {
+            info!("Rewriting archive...");
+            pack::write_archive(&work_dir, docpack_path)?;
+        }
+    }
+
+    info!("\n📝 Update Summary");
+    info!("{}", "─".repeat(60));
+    info!("Added:     {}", changes.added.len());
+    info!("Modified:  {}", changes.modified.len());
+    info!("Removed:   {}", changes.removed.len());
+    info!("Unchanged: {}", changes.unchanged);
+
+    for (label, paths) in [("+", &changes.added), ("~", &changes.modified), ("-", &changes.removed)] {
+        for path in paths {
+            info!("  {} {}", label, path);
+        }
+    }
+
+    info!("\n✓ Updated {}", docpack);
+    Ok(())
+}
+
+/// Bring `files/`, the hashes manifest, and the search index in `work_dir`
+/// in line with `source`.
+fn update_dir(work_dir: &Path, source: &Path, opts: &UpdateOptions) -> Result<Changes, LocaldocError> {
+    let files_dir = work_dir.join("files");
+    let index_dir = work_dir.join("index");
+    let manifest_path = work_dir.join(MANIFEST_FILE);
+    let mut manifest = Manifest::load(&manifest_path, Strictness::Lenient)?;
+    if manifest.metadata.as_ref().and_then(|metadata| metadata.get("sources")).is_some_and(Value::is_array) {
+        return Err("This docpack was built from several sources, which update can't refresh yet; run ingest again".into());
+    }
+
+    // Select source files with the same rules the docpack was built with
+    let selection = Selection::resolve(&index_dir, opts.include, opts.exclude, opts.no_default_excludes, opts.max_file_size)?;
+    let mut filter = selection.filter;
+    let ignore_file = filter.load_ignore_file(source)?;
+    if ignore_file {
+        info!("  Using {}", filter::IGNORE_FILE);
+    }
+    let (max_size, symlinks, non_utf8) = (selection.max_size, selection.symlinks, selection.non_utf8);
+
+    info!("Scanning source files...");
+    let mut progress = Progress::new("Scanning", None, opts.quiet);
+    let plan = ingest::plan_files(source, &filter, max_size, symlinks, non_utf8, &mut progress)?;
+    progress.finish();
+    for warning in &plan.link_warnings {
+        warn!("{}", warning);
+    }
+    ingest::print_non_utf8(&plan.non_utf8);
+
+    info!("Comparing file hashes...");
+    let to_hash: Vec<(String, PathBuf)> = plan.files.iter().map(|f| (f.name.clone(), f.path.clone())).collect();
+    let mut progress = Progress::new("Hashing source", Some(to_hash.len() as u64), opts.quiet);
+    let new_hashes = hashes::hash_files(&to_hash, &mut progress)?;
+    progress.finish();
+
+    let existing = existing_files(&files_dir)?;
+    let mut progress = Progress::new("Hashing docpack", Some(existing.len() as u64), opts.quiet);
+    let old_hashes = hashes::hash_files(&existing, &mut progress)?;
+    progress.finish();
+
+    let mut changes = Changes::default();
+    for (name, hash) in &new_hashes {
+        match old_hashes.get(name) {
+            None => changes.added.push(name.clone()),
+            Some(old) if old != hash => changes.modified.push(name.clone()),
+            Some(_) => changes.unchanged += 1,
+        }
+    }
+    changes.removed = old_hashes.keys().filter(|name| !new_hashes.contains_key(*name)).cloned().collect();
+
+    // Apply the changes to files/
+    let sources: BTreeMap<&str, &Path> = plan.files.iter().map(|f| (f.name.as_str(), f.path.as_path())).collect();
+    for name in changes.added.iter().chain(&changes.modified) {
+        let target = files_dir.join(name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+        fs::copy(sources[name.as_str()], &target).with_path(sources[name.as_str()])?;
+    }
+    for name in &changes.removed {
+        remove_and_prune(&files_dir, name)?;
+    }
+    for dir in &plan.dirs {
+        let dir_path = files_dir.join(dir);
+        fs::create_dir_all(&dir_path).with_path(&dir_path)?;
+    }
+
+    // Integrity manifest
+    fs::create_dir_all(&index_dir).with_path(&index_dir)?;
+    fs::write(
+        index_dir.join(hashes::FILE_NAME),
+        serde_json::to_string_pretty(&hashes::to_json(&new_hashes))?,
+    )?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+
+    // Search index, patched for the changed files only
+    if let Some(format) = IndexFormat::detect(&index_dir) {
+        let stale: BTreeSet<&str> = changes.modified.iter().chain(&changes.removed).map(String::as_str).collect();
+        let fresh: Vec<&str> = changes.added.iter().chain(&changes.modified).map(String::as_str).collect();
+        let search_path = index_dir.join(format.file_name());
+        patch_search_index(&search_path, format, &files_dir, &stale, &fresh, new_hashes.len(), &now)?;
+        info!("  Updated index/{} ({} files reindexed)", format.file_name(), fresh.len());
+    }
+
+    // Manifest: everything else, including hand edits, is kept as is
+    let metadata = manifest.metadata_mut();
+    metadata.insert("updated".to_string(), json!(now));
+    metadata.insert("hashes".to_string(), hashes::metadata(&new_hashes));
+    metadata.remove("skipped_files");
+    metadata.remove("ignore_file");
+    metadata.remove("non_utf8");
+    if !plan.non_utf8.is_empty() {
+        metadata.insert("non_utf8".to_string(), ingest::non_utf8_metadata(&plan.non_utf8, non_utf8));
+    }
+    if ignore_file {
+        metadata.insert("ignore_file".to_string(), json!({ "path": filter::IGNORE_FILE, "ignored": plan.ignored }));
+    }
+    if !plan.oversized.is_empty() {
+        let skipped: Vec<Value> = plan
+            .oversized
+            .iter()
+            .map(|(path, bytes)| json!({ "path": path, "size": bytes, "reason": "max_file_size" }))
+            .collect();
+        metadata.insert("skipped_files".to_string(), json!(skipped));
+    }
+    manifest.save(&manifest_path)?;
+
+    Ok(changes)
+}
+
+/// File selection rules: those recorded in an existing index's
+/// fingerprint, unless overridden on the command line.
+pub(crate) struct Selection {
+    pub filter: SourceFilter,
+    pub max_size: Option<u64>,
+    pub symlinks: ingest::SymlinkPolicy,
+    pub non_utf8: ingest::NonUtf8Policy,
+}
+
+impl Selection {
+    /// Read the rules recorded in the search index in `index_dir`. Non-empty
+    /// `include`/`exclude` replace the recorded filters; `max_file_size` of
+    /// `Some(0)` disables the limit and `None` keeps the recorded one.
+    pub fn resolve(
+        index_dir: &Path,
+        include: &[String],
+        exclude: &[String],
+        no_default_excludes: bool,
+        max_file_size: Option<u64>,
+    ) -> Result<Selection, LocaldocError> {
+        let recorded = IndexFormat::detect(index_dir)
+            .and_then(|format| search::read_metadata(fs::File::open(index_dir.join(format.file_name())).ok()?, format))
+            .map(|metadata| metadata["fingerprint"]["options"].clone())
+            .filter(|options| options.is_object());
+        let (include, exclude, default_excludes) = match &recorded {
+            Some(options) if include.is_empty() && exclude.is_empty() => {
+                let patterns = |key: &str| -> Vec<String> {
+                    options["filters"][key]
+                        .as_array()
+                        .map(|globs| globs.iter().filter_map(|g| g.as_str().map(String::from)).collect())
+                        .unwrap_or_default()
+                };
+                let (include, exclude) = (patterns("include"), patterns("exclude"));
+                if !include.is_empty() || !exclude.is_empty() {
+                    info!("  Using --include/--exclude filters recorded in the search index");
+                }
+                // Indexes from before default excludes existed recorded none
+                let default_excludes = !patterns("default_excludes").is_empty();
+                (include, exclude, default_excludes)
+            }
+            _ => (include.to_vec(), exclude.to_vec(), true),
+        };
+        let filter = SourceFilter::new(&include, &exclude, default_excludes && !no_default_excludes)?;
+        let max_size = match (max_file_size, &recorded) {
+            (Some(0), _) => None,
+            (Some(limit), _) => Some(limit),
+            (None, Some(options)) => options["max_file_size"].as_u64(),
+            (None, None) => Some(ingest::DEFAULT_MAX_FILE_SIZE),
+        };
+        let symlinks = recorded
+            .as_ref()
+            .and_then(|options| options["symlinks"].as_str())
+            .and_then(ingest::SymlinkPolicy::from_name)
+            .unwrap_or_default();
+        let non_utf8 = recorded
+            .as_ref()
+            .and_then(|options| options["non_utf8"].as_str())
+            .and_then(ingest::NonUtf8Policy::from_name)
+            .unwrap_or_default();
+        Ok(Selection { filter, max_size, symlinks, non_utf8 })
+    }
+}
+
+/// Files currently under `files/`, as (relative path, file) pairs.
+pub(crate) fn existing_files(files_dir: &Path) -> Result<Vec<(String, PathBuf)>, LocaldocError> {
+    let mut found = Vec::new();
+    if !files_dir.is_dir() {
+        return Ok(found);
+    }
+    for entry in walkdir::WalkDir::new(files_dir).min_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let rel = entry.path().strip_prefix(files_dir)?;
+            found.push((archive::path_to_entry_name(rel), entry.path().to_path_buf()));
+        }
+    }
+    Ok(found)
+}
+
+/// Delete `files/<name>` and any directories it leaves empty.
+fn remove_and_prune(files_dir: &Path, name: &str) -> std::io::Result<()> {
+    let path = files_dir.join(name);
+    fs::remove_file(&path)?;
+
+    let mut dir = path.parent();
+    while let Some(current) = dir.filter(|d| *d != files_dir) {
+        if fs::read_dir(current)?.next().is_some() {
+            break;
+        }
+        fs::remove_dir(current)?;
+        dir = current.parent();
+    }
+    Ok(())
+}
+
+/// Drop the postings of `stale` paths from the inverted index and add
+/// postings for the `fresh` files, leaving everything else untouched.
+/// index/symbols.json, if the index has one, is patched the same way.
+fn patch_search_index(
+    search_path: &Path,
+    format: IndexFormat,
+    files_dir: &Path,
+    stale: &BTreeSet<&str>,
+    fresh: &[&str],
+    total_files: usize,
+    updated_at: &str,
+) -> Result<(), LocaldocError> {
+    let mut search = SearchIndex::from_bytes(&fs::read(search_path).with_path(search_path)?, format).map_err(|e| {
+        format!("index/{} is not in the current format ({}); {}", format.file_name(), e, fingerprint::REBUILD_HINT)
+    })?;
+
+    let symbols_path = search_path.with_file_name(symbols::FILE_NAME);
+    let mut symbols: Option<symbols::Symbols> =
+        fs::read_to_string(&symbols_path).ok().and_then(|content| serde_json::from_str(&content).ok());
+
+    search.remove_files(stale);
+    search.metadata.skipped.retain(|path, _| !stale.contains(path.as_str()));
+    search.metadata.capped.retain(|path| !stale.contains(path.as_str()));
+    if let Some(symbols) = symbols.as_mut() {
+        for definitions in symbols.values_mut() {
+            definitions.retain(|definition| !stale.contains(definition.path.as_str()));
+        }
+        symbols.retain(|_, definitions| !definitions.is_empty());
+    }
+    // Leave out lockfiles and minified files if the index was built that way
+    let skip_generated = search.metadata.fingerprint["options"]["skip_generated"].as_bool().unwrap_or(false);
+    for name in fresh {
+        // Only text files are indexed, up to MAX_INDEXED_BYTES of each
+        let Some((content, capped)) = search::read_indexed_text(&files_dir.join(name)) else { continue };
+        if let Some(reason) = search::skip_reason(name, &content).filter(|_| skip_generated) {
+            search.metadata.skipped.insert(name.to_string(), reason.to_string());
+            continue;
+        }
+        search.add_file(name, search::file_postings(&content));
+        if capped {
+            search.metadata.capped.insert(name.to_string());
+        }
+        if let Some(symbols) = symbols.as_mut() {
+            symbols::add(symbols, symbols::extract(name, &content));
+        }
+    }
+    search.finish();
+
+    search.metadata.total_files = total_files;
+    search.metadata.updated_at = Some(updated_at.to_string());
+    search.metadata.files = files_dir.parent().map(ingest::files_snapshot);
+    search.write(search_path, format)?;
+    if let Some(symbols) = symbols {
+        fs::write(&symbols_path, serde_json::to_string_pretty(&symbols)?).with_path(&symbols_path)?;
+    }
+    Ok(())
+}
@@ -1,211 +1,1036 @@
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
-use serde_json::Value;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use docpack_core::{Manifest, Strictness, MANIFEST_FILE};
+use log::info;
+use serde_json::{json, Value};
+use similar::TextDiff;
 
-pub fn run(docpack: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let docpack_path = Path::new(docpack);
+use localdoc::archive::{self, Hazard, Limits};
+use localdoc::docpack::Docpack;
 
-    println!("Validating .docpack: {}\n", docpack);
+use crate::commands::outputs;
+use crate::constraints;
+use crate::dedupe;
+use crate::embeddings;
+use crate::error::{LocaldocError, WithPath};
+use crate::findings::{self, Finding};
+use crate::fingerprint;
+use crate::graph;
+use crate::hashes;
+use crate::plugins::{self, Plugin};
+use crate::reader::{self, DirReader, DocpackReader};
+use crate::repair;
+use crate::schema;
+use crate::seal;
+use crate::search::{self, IndexFormat, SearchIndex};
+use crate::symbols;
+use crate::tools::Tool;
+use crate::versions;
 
+/// Index files that must be loaded whole to be checked are skipped, with a
+/// warning, past this size. search.json and graph.json are streamed.
+const MAX_LOADED_INDEX_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Graph defects of each kind reported one by one before they are counted.
+const MAX_GRAPH_DEFECTS: usize = 5;
+
+/// Errors and warnings collected for a single docpack.
+struct ValidationReport {
+    errors: Vec<Finding>,
+    warnings: Vec<Finding>,
+    /// Informational findings from plugins
+    notes: Vec<Finding>,
+}
+
+impl ValidationReport {
+    fn to_json(&self, path: &str) -> Value {
+        json!({
+            "path": path,
+            "valid": self.errors.is_empty(),
+            "errors": self.errors,
+            "warnings": self.warnings,
+            "notes": self.notes,
+        })
+    }
+}
+
+/// Which findings count: `--ignore` drops findings by code or name, and
+/// `--strict` turns the remaining warnings into errors.
+struct Policy<'a> {
+    strict: bool,
+    ignore: &'a [String],
+}
+
+impl Policy<'_> {
+    fn apply(&self, report: &mut ValidationReport) {
+        let kept = |finding: &Finding| !self.ignore.iter().any(|selector| finding.matches(selector));
+        report.errors.retain(kept);
+        report.warnings.retain(kept);
+        report.notes.retain(kept);
+        if self.strict {
+            report.errors.append(&mut report.warnings);
+        }
+    }
+}
+
+/// Options for `localdoc validate`.
+pub struct ValidateOptions {
+    pub parallel: Option<usize>,
+    pub sort: bool,
+    pub json: bool,
+    pub no_plugins: bool,
+    pub strict: bool,
+    pub ignore: Vec<String>,
+    /// Repair mechanical problems before validating
+    pub fix: bool,
+    /// With `fix`, only show the repairs
+    pub dry_run: bool,
+}
+
+pub fn run(docpacks: &[String], opts: &ValidateOptions) -> Result<(), LocaldocError> {
+    // Plugin codes are free-form, but a mistyped built-in code would
+    // silently ignore nothing
+    for selector in &opts.ignore {
+        let builtin = selector.len() > 2 && selector[..2].eq_ignore_ascii_case("DP") && selector[2..].bytes().all(|b| b.is_ascii_digit());
+        if builtin && !findings::ALL.iter().any(|code| code.id.eq_ignore_ascii_case(selector)) {
+            return Err(LocaldocError::InvalidArgument(format!("Unknown finding code: {}", selector)));
+        }
+    }
+
+    if opts.fix {
+        let mut paths = Vec::new();
+        for docpack in docpacks {
+            expand_batch_path(Path::new(docpack), &mut paths)?;
+        }
+        for path in &paths {
+            fix(path, opts.dry_run)?;
+        }
+    }
+
+    let plugins = if opts.no_plugins { Vec::new() } else { plugins::discover() };
+    let policy = Policy { strict: opts.strict, ignore: &opts.ignore };
+
+    if docpacks.len() == 1 && !is_batch_dir(Path::new(&docpacks[0])) {
+        return run_single(&docpacks[0], &plugins, &policy, opts.json);
+    }
+
+    run_batch(docpacks, opts.parallel, opts.sort, opts.json, &plugins, &policy)
+}
+
+/// Repair what `--fix` can in a docpack directory: create `output/`,
+/// drop trailing commas, fill in and normalize manifest fields, and
+/// rewrite docpack.json and tasks.json pretty-printed. Each change is
+/// printed with a diff; with `dry_run` nothing is written. Anything else
+/// is left for validation to report.
+fn fix(docpack: &Path, dry_run: bool) -> Result<(), LocaldocError> {
+    if !docpack.is_dir() {
+        return Err(LocaldocError::InvalidArgument(format!("--fix needs a docpack directory, not an archive: {}", docpack.display())));
+    }
+    if !dry_run {
+        seal::ensure_mutable(docpack, false)?;
+    }
+
+    info!("🔧 {}: {}", if dry_run { "Would fix" } else { "Fixing" }, docpack.display());
+    info!("{}", "─".repeat(60));
+    let mut fixed = 0;
+
+    if !docpack.join("output").is_dir() {
+        info!("  • Create output/");
+        if !dry_run {
+            let output_dir = docpack.join("output");
+            fs::create_dir_all(&output_dir).with_path(&output_dir)?;
+        }
+        fixed += 1;
+    }
+
+    for name in ["docpack.json", "tasks.json"] {
+        let path = docpack.join(name);
+        let Ok(text) = fs::read_to_string(&path) else { continue };
+
+        let mut changes = Vec::new();
+        let parsed = serde_json::from_str::<Value>(&text).ok().or_else(|| {
+            let value = serde_json::from_str(&repair::strip_trailing_commas(&text)?).ok()?;
+            changes.push("removed trailing commas".to_string());
+            Some(value)
+        });
+        let Some(mut value) = parsed else {
+            info!("  • {}: not valid JSON, even without trailing commas; fix it by hand", name);
+            continue;
+        };
+        if name == "docpack.json" {
+            changes.extend(repair::manifest(&mut value));
+        }
+
+        let rewritten = serde_json::to_string_pretty(&value)?;
+        if rewritten == text {
+            continue;
+        }
+        if changes.is_empty() {
+            changes.push("reformatted".to_string());
+        }
+        for change in &changes {
+            info!("  • {}: {}", name, change);
+        }
+        let diff = TextDiff::from_lines(&text, &rewritten);
+        let diff = diff.unified_diff().context_radius(2).header(&format!("a/{}", name), &format!("b/{}", name)).to_string();
+        info!("{}", diff.trim_end_matches('\n'));
+        if !dry_run {
+            fs::write(&path, rewritten).with_path(&path)?;
+        }
+        fixed += 1;
+    }
+
+    match (fixed, dry_run) {
+        (0, _) => info!("  Nothing to fix\n"),
+        (_, true) => info!("  Dry run: nothing was written\n"),
+        (_, false) => info!(""),
+    }
+    Ok(())
+}
+
+fn run_single(docpack: &str, plugins: &[Plugin], policy: &Policy, json: bool) -> Result<(), LocaldocError> {
+    if json {
+        let report = check_with_plugins(Path::new(docpack), plugins, policy);
+        println!("{}", serde_json::to_string_pretty(&report.to_json(docpack))?);
+        return if report.errors.is_empty() { Ok(()) } else { Err("Validation failed".into()) };
+    }
+
+    info!("Validating .docpack: {}\n", docpack);
+
+    let report = check_with_plugins(Path::new(docpack), plugins, policy);
+
+    print_results(&report.errors, &report.warnings);
+    if !report.notes.is_empty() {
+        info!("ℹ️  Notes ({}):", report.notes.len());
+        for note in &report.notes {
+            info!("  • {}", note);
+        }
+        info!("");
+    }
+
+    if !report.errors.is_empty() {
+        Err("Validation failed".into())
+    } else {
+        info!("\n✓ Docpack is valid!");
+        Ok(())
+    }
+}
+
+/// Validate many docpacks on a bounded worker pool, streaming one table row
+/// (or one JSON array element) per pack as soon as it finishes. With
+/// `--sort`, the table is followed by a summary sorted by path, and JSON
+/// elements are held back and written in path order at the end.
+fn run_batch(
+    docpacks: &[String],
+    parallel: Option<usize>,
+    sort: bool,
+    json: bool,
+    plugins: &[Plugin],
+    policy: &Policy,
+) -> Result<(), LocaldocError> {
+    let mut paths = Vec::new();
+    for docpack in docpacks {
+        expand_batch_path(Path::new(docpack), &mut paths)?;
+    }
+
+    if paths.is_empty() {
+        // A single directory with no docpacks in it was probably meant to be one
+        if let [docpack] = docpacks {
+            Docpack::open_any(Path::new(docpack))?;
+        }
+        return Err(LocaldocError::NotFound("No docpacks found to validate".to_string()));
+    }
+
+    let workers = parallel
+        .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+        .clamp(1, paths.len());
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if json {
+        write!(out, "[")?;
+    } else {
+        info!("Validating {} docpacks ({} workers)\n", paths.len(), workers);
+        writeln!(out, "{:<10} {:>6} {:>8}  PATH", "STATUS", "ERRORS", "WARNINGS")?;
+        writeln!(out, "{}", "─".repeat(60))?;
+    }
+
+    // Only the summary row is retained per pack, and only when sorting; the
+    // whole report only when sorting JSON
+    let mut summary: Vec<(String, usize, usize)> = Vec::new();
+    let mut held: Vec<(String, Value)> = Vec::new();
+    let mut failed = 0;
+    let mut first = true;
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel::<(usize, ValidationReport)>();
+
+    std::thread::scope(|scope| -> Result<(), LocaldocError> {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let next = &next;
+            let paths = &paths;
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                let Some(path) = paths.get(i) else { break };
+
+                // A panic while validating one pack must not take the pool down
+                let report = std::panic::catch_unwind(|| check_with_plugins(path, plugins, policy)).unwrap_or_else(|_| ValidationReport {
+                    errors: vec![Finding::new(findings::INTERNAL_ERROR, "Internal error while validating this docpack")],
+                    warnings: Vec::new(),
+                    notes: Vec::new(),
+                });
+
+                if tx.send((i, report)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        for (i, report) in rx {
+            let path = paths[i].display().to_string();
+            if !report.errors.is_empty() {
+                failed += 1;
+            }
+
+            if json && sort {
+                let value = report.to_json(&path);
+                held.push((path, value));
+                continue;
+            } else if json {
+                write!(out, "{}\n  {}", if first { "" } else { "," }, report.to_json(&path))?;
+                first = false;
+            } else {
+                write_row(&mut out, &path, &report)?;
+            }
+            out.flush()?;
+
+            if sort {
+                summary.push((path, report.errors.len(), report.warnings.len()));
+            }
+        }
+
+        Ok(())
+    })?;
+
+    if json {
+        held.sort_by(|a, b| a.0.cmp(&b.0));
+        for (_, report) in &held {
+            write!(out, "{}\n  {}", if first { "" } else { "," }, report)?;
+            first = false;
+        }
+        writeln!(out, "\n]")?;
+    } else {
+        if sort {
+            summary.sort();
+            writeln!(out, "\nSummary (sorted by path)")?;
+            writeln!(out, "{}", "─".repeat(60))?;
+            for (path, errors, warnings) in &summary {
+                let status = if *errors == 0 { "✓ valid" } else { "✗ invalid" };
+                writeln!(out, "{:<10} {:>6} {:>8}  {}", status, errors, warnings, path)?;
+            }
+        }
+        writeln!(out, "\n{} of {} docpacks valid", paths.len() - failed, paths.len())?;
+    }
+
+    if failed > 0 {
+        Err(format!("Validation failed for {} docpack(s)", failed).into())
+    } else {
+        Ok(())
+    }
+}
+
+fn write_row<W: Write>(out: &mut W, path: &str, report: &ValidationReport) -> io::Result<()> {
+    let status = if report.errors.is_empty() { "✓ valid" } else { "✗ invalid" };
+    writeln!(out, "{:<10} {:>6} {:>8}  {}", status, report.errors.len(), report.warnings.len(), path)
+}
+
+/// A directory that is not itself a docpack is treated as a folder of docpacks.
+fn is_batch_dir(path: &Path) -> bool {
+    path.is_dir() && !path.join("docpack.json").exists()
+}
+
+fn expand_batch_path(path: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !is_batch_dir(path) {
+        paths.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut found: Vec<PathBuf> = fs::read_dir(path)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.join("docpack.json").exists()
+                || p.extension().and_then(|e| e.to_str()) == Some("docpack")
+        })
+        .collect();
+    found.sort();
+    paths.extend(found);
+    Ok(())
+}
+
+fn check(docpack_path: &Path) -> ValidationReport {
     let mut errors = Vec::new();
     let mut warnings = Vec::new();
 
     // Check if path exists
     if !docpack_path.exists() {
-        errors.push(format!("Path does not exist: {}", docpack));
-        print_results(&errors, &warnings);
-        return Err("Validation failed".into());
+        errors.push(Finding::new(findings::PATH_NOT_FOUND, format!("Path does not exist: {}", docpack_path.display())));
+        return ValidationReport { errors, warnings, notes: Vec::new() };
     }
 
-    if !docpack_path.is_dir() {
-        errors.push(format!("Path is not a directory: {}", docpack));
-        print_results(&errors, &warnings);
-        return Err("Validation failed".into());
+    // Archives are checked in place, without extracting them
+    let mut reader: Box<dyn DocpackReader> = if docpack_path.is_dir() {
+        Box::new(DirReader::new(docpack_path))
+    } else {
+        match reader::open(docpack_path) {
+            Ok(reader) => reader,
+            Err(e) => {
+                errors.push(Finding::new(findings::UNREADABLE_DOCPACK, e.to_string()));
+                return ValidationReport { errors, warnings, notes: Vec::new() };
+            }
+        }
+    };
+
+    // An archive must be safe for `run` to extract
+    if reader.is_archive() {
+        match fs::File::open(docpack_path).and_then(|file| archive::audit(file, &Limits::default())) {
+            Ok(hazards) => {
+                for hazard in hazards {
+                    let (code, entry) = match &hazard {
+                        Hazard::UnsafePath(name) => (findings::UNSAFE_ENTRY_PATH, Some(name)),
+                        Hazard::DuplicateEntry(name) => (findings::DUPLICATE_ENTRY, Some(name)),
+                        Hazard::CompressionRatio { path, .. } => (findings::SUSPICIOUS_SIZE, Some(path)),
+                        Hazard::TooLarge(_) => (findings::SUSPICIOUS_SIZE, None),
+                    };
+                    let mut finding = Finding::new(code, format!("Archive: {}", hazard));
+                    if let Some(entry) = entry {
+                        finding = finding.in_file(entry);
+                    }
+                    if hazard.is_unsafe() {
+                        errors.push(finding);
+                    } else {
+                        warnings.push(finding);
+                    }
+                }
+            }
+            Err(e) => errors.push(Finding::new(findings::UNREADABLE_DOCPACK, e.to_string())),
+        }
     }
 
-    // Check required directories
-    let files_dir = docpack_path.join("files");
-    let index_dir = docpack_path.join("index");
-    let output_dir = docpack_path.join("output");
+    // Check required directories; a deduplicated archive keeps its files
+    // in blobs/ instead
+    let has_files = reader.has_dir("files") || reader.exists(dedupe::MAP_FILE);
+    let has_index = reader.has_dir("index");
 
-    if !files_dir.exists() {
-        errors.push("Missing required directory: files/".to_string());
+    if !has_files {
+        errors.push(Finding::new(findings::MISSING_FILES_DIR, "Missing required directory: files/").in_file("files/"));
     }
 
-    if !index_dir.exists() {
-        warnings.push("Missing optional directory: index/".to_string());
+    if !has_index {
+        warnings.push(Finding::new(findings::MISSING_INDEX_DIR, "Missing optional directory: index/").in_file("index/"));
     }
 
-    if !output_dir.exists() {
-        warnings.push("Missing output directory (will be created at runtime): output/".to_string());
+    // Archives leave out empty directories, and output/ is made on every run
+    if !reader.has_dir("output") && !reader.is_archive() {
+        warnings.push(
+            Finding::new(findings::MISSING_OUTPUT_DIR, "Missing output directory (will be created at runtime): output/")
+                .in_file("output/"),
+        );
     }
 
     // Check and validate docpack.json
-    let manifest_path = docpack_path.join("docpack.json");
-    if !manifest_path.exists() {
-        errors.push("Missing required file: docpack.json".to_string());
+    let mut manifest_tools = None;
+    if !reader.exists("docpack.json") {
+        errors.push(Finding::new(findings::MISSING_MANIFEST, "Missing required file: docpack.json").in_file("docpack.json"));
     } else {
-        match fs::read_to_string(&manifest_path) {
+        match reader.try_read("docpack.json") {
             Ok(content) => {
-                match serde_json::from_str::<Value>(&content) {
-                    Ok(manifest) => {
-                        validate_manifest(&manifest, &mut errors, &mut warnings);
+                match serde_json::from_slice::<Value>(&content) {
+                    Ok(raw) => {
+                        // The schema reports what keeps it from loading; the
+                        // remaining checks need it loaded
+                        let manifest = Manifest::from_value(&raw, MANIFEST_FILE, Strictness::Lenient).ok();
+                        validate_manifest(&raw, manifest.as_ref(), &mut errors, &mut warnings);
+                        check_preexisting_outputs(reader.as_mut(), manifest.as_ref(), &mut warnings);
+                        check_seal(reader.as_mut(), manifest.as_ref(), &mut errors);
+                        // Tasks are checked against the tools once the manifest loads
+                        manifest_tools = manifest.map(|manifest| manifest.environment.tools);
                     }
                     Err(e) => {
-                        errors.push(format!("Invalid JSON in docpack.json: {}", e));
+                        errors.push(
+                            Finding::new(findings::INVALID_MANIFEST, format!("Invalid JSON in docpack.json: {}", e))
+                                .in_file("docpack.json"),
+                        );
                     }
                 }
             }
             Err(e) => {
-                errors.push(format!("Cannot read docpack.json: {}", e));
+                errors.push(
+                    Finding::new(findings::UNREADABLE_FILE, format!("Cannot read docpack.json: {}", e)).in_file("docpack.json"),
+                );
             }
         }
     }
 
     // Check and validate tasks.json
-    let tasks_path = docpack_path.join("tasks.json");
-    if !tasks_path.exists() {
-        warnings.push("Missing optional file: tasks.json (agent will run in exploration mode)".to_string());
+    if !reader.exists("tasks.json") {
+        warnings.push(
+            Finding::new(findings::MISSING_TASKS, "Missing optional file: tasks.json (agent will run in exploration mode)")
+                .in_file("tasks.json"),
+        );
     } else {
-        match fs::read_to_string(&tasks_path) {
+        match reader.try_read("tasks.json") {
             Ok(content) => {
-                match serde_json::from_str::<Value>(&content) {
+                match serde_json::from_slice::<Value>(&content) {
                     Ok(tasks) => {
-                        validate_tasks(&tasks, &mut warnings);
+                        validate_tasks(&tasks, manifest_tools.as_deref(), &mut errors, &mut warnings);
+                        check_tool_indexes(reader.as_mut(), &tasks, &mut warnings);
                     }
                     Err(e) => {
-                        errors.push(format!("Invalid JSON in tasks.json: {}", e));
+                        errors.push(
+                            Finding::new(findings::INVALID_TASKS, format!("Invalid JSON in tasks.json: {}", e)).in_file("tasks.json"),
+                        );
                     }
                 }
             }
             Err(e) => {
-                errors.push(format!("Cannot read tasks.json: {}", e));
+                errors.push(Finding::new(findings::UNREADABLE_FILE, format!("Cannot read tasks.json: {}", e)).in_file("tasks.json"));
             }
         }
     }
 
     // Check index files if index directory exists
-    if index_dir.exists() {
-        let search_path = index_dir.join("search.json");
-        let graph_path = index_dir.join("graph.json");
-
-        if search_path.exists() {
-            match fs::read_to_string(&search_path) {
-                Ok(content) => {
-                    if let Err(e) = serde_json::from_str::<Value>(&content) {
-                        errors.push(format!("Invalid JSON in index/search.json: {}", e));
+    if has_index {
+        let search_path = "index/search.json";
+        let graph_path = "index/graph.json";
+        let index_sizes: HashMap<String, u64> = reader.list_files("index").into_iter().collect();
+        // Files that have to be loaded whole to check; the rest are streamed
+        let fits = |path: &str, warnings: &mut Vec<Finding>| match index_sizes.get(&path["index/".len()..]) {
+            Some(&size) if size > MAX_LOADED_INDEX_BYTES => {
+                let message = format!(
+                    "{} is {} MB; only index files up to {} MB are checked in full",
+                    path,
+                    size / (1024 * 1024),
+                    MAX_LOADED_INDEX_BYTES / (1024 * 1024)
+                );
+                warnings.push(Finding::new(findings::INDEX_NOT_CHECKED, message).in_file(path));
+                false
+            }
+            _ => true,
+        };
+
+        if reader.exists(search_path) {
+            match reader.open_file(search_path).ok().map(search::check_json) {
+                Some(Ok(_)) => {}
+                Some(Err(e)) if e.is_io() => warnings.push(unreadable(search_path, e)),
+                Some(Err(e)) => errors.push(invalid_index(search_path, format!("Invalid index/search.json: {}", e))),
+                None => warnings.push(unreadable(search_path, "cannot open")),
+            }
+        }
+
+        let binary_path = format!("index/{}", IndexFormat::Binary.file_name());
+        if reader.exists(&binary_path) {
+            if fits(&binary_path, &mut warnings) {
+                match reader.try_read(&binary_path) {
+                    Ok(bytes) => {
+                        if let Err(e) = SearchIndex::from_bytes(&bytes, IndexFormat::Binary) {
+                            errors.push(invalid_index(&binary_path, format!("Invalid index/search.bin: {}", e)));
+                        }
+                    }
+                    Err(e) => {
+                        warnings.push(unreadable(&binary_path, e));
                     }
                 }
+            }
+            if reader.exists(search_path) {
+                warnings.push(
+                    Finding::new(findings::DUPLICATE_INDEX, "index/ has both search.json and search.bin; readers may use either")
+                        .in_file("index/"),
+                );
+            }
+        }
+
+        if reader.exists(graph_path) {
+            match reader.open_file(graph_path).ok().map(graph::check) {
+                Some(Ok(defects)) => check_graph_defects(defects, &mut errors),
+                Some(Err(e)) if e.is_io() => warnings.push(unreadable(graph_path, e)),
+                Some(Err(e)) => errors.push(invalid_index(graph_path, format!("Invalid index/graph.json: {}", e))),
+                None => warnings.push(unreadable(graph_path, "cannot open")),
+            }
+        }
+
+        let symbols_path = format!("index/{}", symbols::FILE_NAME);
+        if reader.exists(&symbols_path) && fits(&symbols_path, &mut warnings) {
+            match reader.try_read(&symbols_path) {
+                Ok(content) => match serde_json::from_slice::<Value>(&content) {
+                    Ok(value) => {
+                        if let Err(e) = symbols::check_shape(&value) {
+                            errors.push(invalid_index(&symbols_path, format!("index/{}: {}", symbols::FILE_NAME, e)));
+                        }
+                    }
+                    Err(e) => errors.push(invalid_index(&symbols_path, format!("Invalid JSON in index/{}: {}", symbols::FILE_NAME, e))),
+                },
                 Err(e) => {
-                    warnings.push(format!("Cannot read index/search.json: {}", e));
+                    warnings.push(unreadable(&symbols_path, e));
                 }
             }
         }
 
-        if graph_path.exists() {
-            match fs::read_to_string(&graph_path) {
-                Ok(content) => {
-                    if let Err(e) = serde_json::from_str::<Value>(&content) {
-                        errors.push(format!("Invalid JSON in index/graph.json: {}", e));
+        let hashes_path = format!("index/{}", hashes::FILE_NAME);
+        if reader.exists(&hashes_path) && fits(&hashes_path, &mut warnings) {
+            match reader.try_read(&hashes_path) {
+                Ok(content) => match serde_json::from_slice::<Value>(&content) {
+                    Ok(value) => {
+                        if let Err(e) = hashes::from_json(&value) {
+                            errors.push(invalid_index(&hashes_path, format!("index/{}: {}", hashes::FILE_NAME, e)));
+                        }
                     }
-                }
+                    Err(e) => errors.push(invalid_index(&hashes_path, format!("Invalid JSON in index/{}: {}", hashes::FILE_NAME, e))),
+                },
                 Err(e) => {
-                    warnings.push(format!("Cannot read index/graph.json: {}", e));
+                    warnings.push(unreadable(&hashes_path, e));
                 }
             }
         }
+
+        let embeddings_path = format!("index/{}", embeddings::BIN_FILE);
+        let meta_path = format!("index/{}", embeddings::META_FILE);
+        if reader.exists(&embeddings_path) {
+            match reader.try_read(&embeddings_path).map(|bytes| embeddings::check_length(&bytes)) {
+                Ok(Ok(header)) => match reader.try_read(&meta_path) {
+                    Ok(content) => match serde_json::from_slice::<Value>(&content) {
+                        Ok(meta) => {
+                            let chunks = meta["chunks"].as_array().map(Vec::len).unwrap_or(0);
+                            if chunks != header.count {
+                                errors.push(
+                                    Finding::new(
+                                        findings::EMBEDDINGS_MISMATCH,
+                                        format!("index/{} lists {} chunks for {} vectors", embeddings::META_FILE, chunks, header.count),
+                                    )
+                                    .in_file(&meta_path)
+                                    .at("/chunks"),
+                                );
+                            }
+                        }
+                        Err(e) => errors.push(invalid_index(&meta_path, format!("Invalid JSON in index/{}: {}", embeddings::META_FILE, e))),
+                    },
+                    Err(_) => errors.push(
+                        Finding::new(
+                            findings::EMBEDDINGS_MISMATCH,
+                            format!("index/{} has no index/{} mapping its vectors to files", embeddings::BIN_FILE, embeddings::META_FILE),
+                        )
+                        .in_file(&embeddings_path),
+                    ),
+                },
+                Ok(Err(e)) => errors.push(invalid_index(&embeddings_path, format!("Invalid index/{}: {}", embeddings::BIN_FILE, e))),
+                Err(e) => warnings.push(unreadable(&embeddings_path, e)),
+            }
+        } else if reader.exists(&meta_path) {
+            warnings.push(
+                Finding::new(
+                    findings::EMBEDDINGS_MISMATCH,
+                    format!("index/{} has no index/{}", embeddings::META_FILE, embeddings::BIN_FILE),
+                )
+                .in_file(&meta_path),
+            );
+        }
+
+        for (file, message) in fingerprint::check(reader.as_mut()) {
+            warnings.push(Finding::new(findings::INCOMPATIBLE_INDEX, message).in_file(file));
+        }
+        if let Some(reason) = fingerprint::staleness(reader.as_mut()) {
+            let message = format!("Search index is stale: {}; {}", reason, fingerprint::REBUILD_HINT);
+            warnings.push(Finding::new(findings::STALE_INDEX, message).in_file("index/"));
+        }
     }
 
-    print_results(&errors, &warnings);
+    ValidationReport { errors, warnings, notes: Vec::new() }
+}
 
-    if !errors.is_empty() {
-        Err("Validation failed".into())
-    } else {
-        println!("\n✓ Docpack is valid!");
-        Ok(())
+/// Report the first few defects of each kind in index/graph.json, then
+/// how many more there are.
+fn check_graph_defects(defects: Vec<graph::Defect>, errors: &mut Vec<Finding>) {
+    let (duplicates, dangling): (Vec<_>, Vec<_>) =
+        defects.into_iter().partition(|defect| matches!(defect, graph::Defect::DuplicateNode { .. }));
+    let kinds = [
+        (findings::DUPLICATE_GRAPH_NODE, duplicates, "repeated node ids"),
+        (findings::DANGLING_GRAPH_EDGE, dangling, "edge ends that are not nodes"),
+    ];
+    for (code, defects, noun) in kinds {
+        for defect in defects.iter().take(MAX_GRAPH_DEFECTS) {
+            let pointer = match defect {
+                graph::Defect::DuplicateNode { index, .. } => format!("/nodes/{}/id", index),
+                graph::Defect::DanglingEdge { index, end, .. } => format!("/edges/{}/{}", index, end),
+            };
+            let message = format!("index/graph.json: {}", defect);
+            errors.push(Finding::new(code, message).in_file("index/graph.json").at(pointer));
+        }
+        if defects.len() > MAX_GRAPH_DEFECTS {
+            let message = format!("index/graph.json: {} more {}", defects.len() - MAX_GRAPH_DEFECTS, noun);
+            errors.push(Finding::new(code, message).in_file("index/graph.json"));
+        }
     }
 }
 
-fn validate_manifest(manifest: &Value, errors: &mut Vec<String>, warnings: &mut Vec<String>) {
-    // Check version
-    if manifest["version"].as_str().is_none() {
-        errors.push("docpack.json: missing required field 'version'".to_string());
+fn invalid_index(path: &str, message: String) -> Finding {
+    Finding::new(findings::INVALID_INDEX, message).in_file(path)
+}
+
+fn unreadable(path: &str, error: impl std::fmt::Display) -> Finding {
+    Finding::new(findings::UNREADABLE_FILE, format!("Cannot read {}: {}", path, error)).in_file(path)
+}
+
+/// Run the built-in checks, then merge in findings from each plugin, and
+/// apply `policy`. A plugin that fails is reported as a warning and never
+/// fails validation on its own.
+/// The checks `validate` makes, without plugins, for `run` to make before
+/// it extracts or starts anything. With `strict`, warnings count as errors.
+/// Returns the errors and the warnings.
+pub(crate) fn preflight(docpack_path: &Path, strict: bool) -> (Vec<Finding>, Vec<Finding>) {
+    let mut report = check(docpack_path);
+    Policy { strict, ignore: &[] }.apply(&mut report);
+    (report.errors, report.warnings)
+}
+
+fn check_with_plugins(docpack_path: &Path, plugins: &[Plugin], policy: &Policy) -> ValidationReport {
+    let mut report = check(docpack_path);
+
+    if docpack_path.is_dir() {
+        for plugin in plugins {
+            match plugins::validate(plugin, docpack_path) {
+                Ok(found) => {
+                    for finding in found {
+                        let converted = Finding::from_plugin(&plugin.name, &finding.code, &finding.message);
+                        match finding.severity.as_str() {
+                            "error" => report.errors.push(converted),
+                            "warning" => report.warnings.push(converted),
+                            _ => report.notes.push(converted),
+                        }
+                    }
+                }
+                Err(e) => report.warnings.push(Finding::new(
+                    findings::PLUGIN_FAILED,
+                    format!("[plugin {}] failed: {}", plugin.name, e),
+                )),
+            }
+        }
     }
 
+    policy.apply(&mut report);
+    report
+}
+
+/// Check `docpack.json` as written (`raw`) and, when it loads, as `manifest`.
+fn validate_manifest(raw: &Value, manifest: Option<&Manifest>, errors: &mut Vec<Finding>, warnings: &mut Vec<Finding>) {
+    let finding = |code, message: String| Finding::new(code, message).in_file("docpack.json");
+
+    // Required fields and types
+    for violation in schema::MANIFEST.check(raw) {
+        errors.push(finding(findings::MANIFEST_SCHEMA, violation.to_string()).at(violation.pointer));
+    }
+
+    // A missing or non-string version is a schema violation already
+    if raw["version"].is_string() {
+        let compatibility = versions::compatibility(raw);
+        if let Some(message) = compatibility.message() {
+            let finding = |code| finding(code, message).at("/version");
+            match compatibility {
+                versions::Compatibility::Newer(_) => warnings.push(finding(findings::NEWER_VERSION)),
+                _ => errors.push(finding(findings::UNSUPPORTED_VERSION)),
+            }
+        }
+    }
+
+    let Some(manifest) = manifest else {
+        return;
+    };
+
     // Check name
-    if manifest["name"].as_str().is_none() {
-        warnings.push("docpack.json: missing recommended field 'name'".to_string());
+    if manifest.name.is_none() {
+        warnings.push(finding(findings::MISSING_NAME, "docpack.json: missing recommended field 'name'".to_string()).at("/name"));
     }
 
-    // Check environment
-    if manifest["environment"].is_null() {
-        errors.push("docpack.json: missing required field 'environment'".to_string());
-    } else {
-        let env = &manifest["environment"];
-
-        // Check tools
-        if let Some(tools) = env["tools"].as_array() {
-            let valid_tools = [
-                "list_files",
-                "read_file",
-                "read_image",
-                "read_pdf",
-                "search_code",
-                "query_graph",
-                "write_output",
-            ];
-
-            for tool in tools {
-                if let Some(tool_name) = tool.as_str() {
-                    if !valid_tools.contains(&tool_name) {
-                        warnings.push(format!(
-                            "docpack.json: unknown tool '{}' (may not be supported)",
-                            tool_name
-                        ));
-                    }
-                }
+    // Check tools
+    for (i, tool_name) in manifest.environment.tools.iter().enumerate() {
+        if Tool::from_name(tool_name).is_none() {
+            let message = format!("docpack.json: unknown tool '{}' (may not be supported)", tool_name);
+            warnings.push(finding(findings::UNKNOWN_TOOL, message).at(format!("/environment/tools/{}", i)));
+        }
+    }
+
+    if let Some(limits) = &manifest.environment.constraints {
+        // Check run limits
+        for (field, message) in constraints::check(limits) {
+            warnings.push(finding(findings::UNLIKELY_CONSTRAINT, message).at(format!("/environment/constraints/{}", field)));
+        }
+
+        // Check output size limits
+        if let (Some(total), Some(per_file)) = (limits.max_output_bytes, limits.max_output_file_bytes) {
+            if per_file > total {
+                let message = "docpack.json: 'max_output_file_bytes' is larger than 'max_output_bytes'".to_string();
+                warnings.push(finding(findings::OUTPUT_LIMITS, message).at("/environment/constraints/max_output_file_bytes"));
+            }
+        }
+    }
+
+    // Check seal consistency
+    if seal::is_sealed(manifest) {
+        for field in ["sealed_at", "sealed_by"] {
+            if manifest.metadata_str(field).is_none() {
+                let message = format!("docpack.json: sealed docpack has no 'metadata.{}'", field);
+                warnings.push(finding(findings::INCOMPLETE_SEAL, message).at(format!("/metadata/{}", field)));
             }
-        } else {
-            errors.push("docpack.json: 'environment.tools' must be an array".to_string());
         }
     }
 }
 
-fn validate_tasks(tasks: &Value, warnings: &mut Vec<String>) {
+/// A sealed docpack's `files/` must still match `index/hashes.json`, and
+/// those hashes the digest sealed in docpack.json's `metadata.hashes`.
+fn check_seal(reader: &mut dyn DocpackReader, manifest: Option<&Manifest>, errors: &mut Vec<Finding>) {
+    let Some(manifest) = manifest.filter(|manifest| seal::is_sealed(manifest)) else {
+        return;
+    };
+    let hashes_path = format!("index/{}", hashes::FILE_NAME);
+
+    let Ok(content) = reader.try_read(&hashes_path) else {
+        let message = format!("Sealed docpack has no {}, so its files cannot be checked", hashes_path);
+        errors.push(Finding::new(findings::BROKEN_SEAL, message).in_file(hashes_path));
+        return;
+    };
+    // A malformed manifest is reported by the index checks
+    let Some(expected) = serde_json::from_slice(&content).ok().and_then(|value| hashes::from_json(&value).ok()) else {
+        return;
+    };
+
+    let sealed_digest = manifest
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata["hashes"]["digest"].as_str().map(str::to_string));
+    if sealed_digest.is_some_and(|digest| digest != hashes::digest(&expected)) {
+        let message = format!("{} changed after the docpack was sealed", hashes_path);
+        errors.push(Finding::new(findings::BROKEN_SEAL, message).in_file(hashes_path.clone()).at("/metadata/hashes/digest"));
+    }
+
+    let listed = reader.list_files("files");
+    for (path, _) in &listed {
+        let file = format!("files/{}", path);
+        let Some(want) = expected.get(path) else {
+            errors.push(Finding::new(findings::BROKEN_SEAL, format!("{} was added after the docpack was sealed", file)).in_file(file));
+            continue;
+        };
+        // Unreadable files are reported by the checks that read them
+        let Some(Ok(hash)) = reader.source_name(path).map(|name| reader.open_file(&name).and_then(hashes::hash_reader)) else {
+            continue;
+        };
+        if hash != *want {
+            errors.push(Finding::new(findings::BROKEN_SEAL, format!("{} was modified after the docpack was sealed", file)).in_file(file));
+        }
+    }
+    for path in expected.keys().filter(|path| !listed.iter().any(|(name, _)| name == *path)) {
+        let file = format!("files/{}", path);
+        errors.push(Finding::new(findings::BROKEN_SEAL, format!("{} was removed after the docpack was sealed", file)).in_file(file));
+    }
+}
+
+/// Outputs in a docpack that has never been run are usually stale leftovers,
+/// unless they were deliberately seeded (`metadata.seeded_outputs`).
+fn check_preexisting_outputs(reader: &mut dyn DocpackReader, manifest: Option<&Manifest>, warnings: &mut Vec<Finding>) {
+    let has_run = reader.has_dir(outputs::HISTORY_DIR);
+    let seeded = manifest
+        .and_then(|manifest| manifest.metadata.as_ref())
+        .is_some_and(|metadata| metadata.get("seeded_outputs").is_some_and(Value::is_object));
+    if has_run || seeded {
+        return;
+    }
+
+    let output_files = reader.list_files("output").len();
+
+    if output_files > 0 {
+        warnings.push(
+            Finding::new(
+                findings::STALE_OUTPUTS,
+                format!("output/ already contains {} file(s) but the docpack has never been run (stale outputs?)", output_files),
+            )
+            .in_file("output/"),
+        );
+    }
+}
+
+/// Check a tasks.json document. When `tools` (the manifest's
+/// `environment.tools`) is given, tasks may only allow tools listed there.
+pub fn validate_tasks(tasks: &Value, tools: Option<&[String]>, errors: &mut Vec<Finding>, warnings: &mut Vec<Finding>) {
+    let finding = |code, message: String| Finding::new(code, message).in_file("tasks.json");
+
+    // Types and shapes
+    for violation in schema::TASKS.check(tasks) {
+        errors.push(finding(findings::TASKS_SCHEMA, violation.to_string()).at(violation.pointer));
+    }
+
     // Check mission
     if tasks["mission"].as_str().is_none() {
-        warnings.push("tasks.json: missing recommended field 'mission'".to_string());
+        warnings.push(finding(findings::MISSING_MISSION, "tasks.json: missing recommended field 'mission'".to_string()).at("/mission"));
     }
 
     // Check tasks array
     if let Some(task_list) = tasks["tasks"].as_array() {
+        let mut ids: HashMap<&str, usize> = HashMap::new();
         for (i, task) in task_list.iter().enumerate() {
+            if let Some(id) = task["id"].as_str() {
+                if let Some(first) = ids.insert(id, i) {
+                    let message = format!("tasks.json: tasks {} and {} share the id '{}'", first, i, id);
+                    errors.push(finding(findings::DUPLICATE_TASK_ID, message).at(format!("/tasks/{}/id", i)));
+                }
+            }
+            if let Some(path) = task["output"]["path"].as_str().filter(|path| !is_output_path(path)) {
+                let message = format!("tasks.json: task {} writes '{}', which is not under output/", task_label(task, i), path);
+                warnings.push(finding(findings::OUTPUT_OUTSIDE_OUTPUT_DIR, message).at(format!("/tasks/{}/output/path", i)));
+            }
             if task["name"].as_str().is_none() {
-                warnings.push(format!("tasks.json: task {} missing 'name'", i));
+                let message = format!("tasks.json: task {} missing 'name'", i);
+                warnings.push(finding(findings::TASK_MISSING_NAME, message).at(format!("/tasks/{}/name", i)));
             }
             if task["description"].as_str().is_none() {
-                warnings.push(format!("tasks.json: task {} missing 'description'", i));
+                let message = format!("tasks.json: task {} missing 'description'", i);
+                warnings.push(finding(findings::TASK_MISSING_DESCRIPTION, message).at(format!("/tasks/{}/description", i)));
+            }
+            if let (Some(tools), Some(allowed)) = (tools, task["tools_allowed"].as_array()) {
+                let listed = |tool: &Value| tool.as_str().is_some_and(|name| tools.iter().any(|t| t == name));
+                for (j, tool) in allowed.iter().enumerate().filter(|(_, tool)| !listed(tool)) {
+                    let message = format!(
+                        "tasks.json: task {} allows tool '{}' which is not in docpack.json environment.tools",
+                        task_label(task, i),
+                        tool.as_str().map(|t| t.to_string()).unwrap_or_else(|| tool.to_string())
+                    );
+                    warnings.push(finding(findings::TOOL_NOT_IN_ENVIRONMENT, message).at(format!("/tasks/{}/tools_allowed/{}", i, j)));
+                }
+            }
+        }
+    } else if tasks["tasks"].is_null() {
+        warnings.push(finding(findings::MISSING_TASK_LIST, "tasks.json: missing recommended field 'tasks'".to_string()).at("/tasks"));
+    }
+}
+
+/// A task's id, or its position when it has none.
+fn task_label(task: &Value, i: usize) -> String {
+    task["id"].as_str().map(|id| id.to_string()).unwrap_or_else(|| i.to_string())
+}
+
+/// Whether a task output path stays inside `output/`.
+fn is_output_path(path: &str) -> bool {
+    path.starts_with("output/") && !path.contains('\\') && !path.split('/').any(|part| part == "..")
+}
+
+/// Tasks that allow a tool whose index artifact the docpack lacks would
+/// fail on their first call to it.
+fn check_tool_indexes(reader: &mut dyn DocpackReader, tasks: &Value, warnings: &mut Vec<Finding>) {
+    for (i, task) in tasks["tasks"].as_array().into_iter().flatten().enumerate() {
+        for (j, tool) in task["tools_allowed"].as_array().into_iter().flatten().enumerate() {
+            let Some((tool, artifact)) = tool.as_str().and_then(|tool| Some((tool, Tool::from_name(tool)?.required_index()?))) else {
+                continue;
+            };
+            if !reader.exists(&format!("index/{}", artifact)) {
+                let message = format!(
+                    "tasks.json: task {} allows '{}' but the docpack has no index/{}",
+                    task_label(task, i),
+                    tool,
+                    artifact
+                );
+                warnings.push(
+                    Finding::new(findings::TOOL_WITHOUT_INDEX, message)
+                        .in_file("tasks.json")
+                        .at(format!("/tasks/{}/tools_allowed/{}", i, j)),
+                );
             }
         }
-    } else {
-        warnings.push("tasks.json: 'tasks' should be an array".to_string());
     }
 }
 
-fn print_results(errors: &[String], warnings: &[String]) {
+fn print_results(errors: &[Finding], warnings: &[Finding]) {
     if !errors.is_empty() {
-        println!("❌ Errors ({}):", errors.len());
+        info!("❌ Errors ({}):", errors.len());
         for error in errors {
-            println!("  • {}", error);
+            info!("  • {}", error);
         }
-        println!();
+        info!("");
     }
 
     if !warnings.is_empty() {
-        println!("⚠️  Warnings ({}):", warnings.len());
+        info!("⚠️  Warnings ({}):", warnings.len());
         for warning in warnings {
-            println!("  • {}", warning);
+            info!("  • {}", warning);
         }
-        println!();
+        info!("");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools;
+
+    #[test]
+    fn every_tool_ingest_can_emit_validates() {
+        for (preset, _) in tools::PRESETS {
+            let manifest = json!({
+                "version": "1.0",
+                "name": "x",
+                "environment": { "tools": tools::expand(preset, &[]).unwrap() },
+            });
+            let (mut errors, mut warnings) = (Vec::new(), Vec::new());
+            let typed = Manifest::from_value(&manifest, MANIFEST_FILE, Strictness::Lenient).unwrap();
+            validate_manifest(&manifest, Some(&typed), &mut errors, &mut warnings);
+            assert!(errors.is_empty() && warnings.is_empty(), "preset {}: {:?} {:?}", preset, errors, warnings);
+        }
+    }
+
+    #[test]
+    fn tasks_are_checked_against_each_other_and_the_environment() {
+        let tasks = json!({
+            "mission": "m",
+            "tasks": [
+                { "id": "a", "name": "A", "description": "d", "tools_allowed": ["read_file"], "output": { "path": "output/a.md" } },
+                { "id": "a", "name": "B", "description": "d", "tools_allowed": ["semantic_search"], "output": { "path": "../b.md" } },
+            ],
+        });
+        let tools = ["read_file".to_string()];
+        let (mut errors, mut warnings) = (Vec::new(), Vec::new());
+        validate_tasks(&tasks, Some(&tools), &mut errors, &mut warnings);
+
+        let codes = |findings: &[Finding]| findings.iter().map(|f| f.code.clone()).collect::<Vec<_>>();
+        assert_eq!(codes(&errors), ["DP035"]);
+        assert_eq!(errors[0].pointer.as_deref(), Some("/tasks/1/id"));
+        assert_eq!(codes(&warnings), ["DP034", "DP023"]);
+        assert_eq!(warnings[0].pointer.as_deref(), Some("/tasks/1/output/path"));
+
+        assert!(is_output_path("output/docs/a.md"));
+        assert!(!is_output_path("output/../a.md"));
+        assert!(!is_output_path("/output/a.md"));
+    }
+
+    #[test]
+    fn graph_defects_are_reported_with_pointers_and_counted() {
+        let mut edges: Vec<Value> = (0..7).map(|i| json!({ "from": "a", "to": format!("ghost{}", i), "type": "calls" })).collect();
+        edges.insert(0, json!({ "from": "a", "to": "b", "type": "contains" }));
+        let graph = json!({
+            "nodes": [{ "id": "a", "type": "module" }, { "id": "b", "type": "function" }, { "id": "a", "type": "module" }],
+            "edges": edges,
+        });
+        let defects = graph::check(graph.to_string().as_bytes()).unwrap();
+        let mut errors = Vec::new();
+        check_graph_defects(defects, &mut errors);
+
+        let codes: Vec<&str> = errors.iter().map(|f| f.code.as_str()).collect();
+        assert_eq!(codes, ["DP037", "DP038", "DP038", "DP038", "DP038", "DP038", "DP038"]);
+        assert_eq!(errors[0].pointer.as_deref(), Some("/nodes/2/id"));
+        assert_eq!(errors[1].pointer.as_deref(), Some("/edges/1/to"));
+        assert!(errors[6].message.ends_with("2 more edge ends that are not nodes"), "{}", errors[6].message);
+
+        assert!(graph::check(r#"{"nodes": [{"id": 1, "type": "module"}], "edges": []}"#.as_bytes()).is_err());
     }
 }
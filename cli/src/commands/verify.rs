@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use log::info;
+use serde_json::{json, Value};
+
+use localdoc::archive::{self, EntryMeta, EntrySink, Limits};
+use localdoc::docpack::{Docpack, DocpackKind};
+
+use crate::dedupe;
+use crate::error::{LocaldocError, WithPath};
+use crate::hashes::{self, FileHash};
+use crate::progress::Progress;
+
+/// What was found when comparing `files/` against the hashes manifest.
+#[derive(Default)]
+struct Report {
+    checked: usize,
+    added: Vec<String>,
+    missing: Vec<String>,
+    /// (path, expected, actual)
+    modified: Vec<(String, FileHash, FileHash)>,
+    /// Whether the manifest matches `metadata.hashes.digest`; `None` when
+    /// docpack.json records no digest
+    digest_ok: Option<bool>,
+}
+
+impl Report {
+    fn passed(&self) -> bool {
+        self.added.is_empty() && self.missing.is_empty() && self.modified.is_empty() && self.digest_ok != Some(false)
+    }
+}
+
+/// The parts of a docpack that verification reads.
+struct Contents {
+    manifest: Option<Value>,
+    hashes: Option<Value>,
+    files: BTreeMap<String, FileHash>,
+}
+
+pub fn run(docpack: &str, json: bool) -> Result<(), LocaldocError> {
+    let docpack_path = Path::new(docpack);
+    let opened = Docpack::open_any(docpack_path)?;
+
+    if !json {
+        info!("Verifying .docpack: {}\n", docpack);
+    }
+
+    let contents = match opened.kind {
+        DocpackKind::Directory => read_dir(docpack_path, json)?,
+        DocpackKind::Archive => read_archive(docpack_path, json)?,
+    };
+
+    let manifest_name = format!("index/{}", hashes::FILE_NAME);
+    let Some(hashes_json) = contents.hashes else {
+        if json {
+            println!("{}", serde_json::to_string_pretty(&json!({
+                "path": docpack,
+                "verified": false,
+                "error": "no_hashes_manifest",
+            }))?);
+        }
+        return Err(format!(
+            "No hashes manifest ({}) in {}; it was created without integrity hashes and cannot be verified",
+            manifest_name, docpack
+        )
+        .into());
+    };
+    let expected = hashes::from_json(&hashes_json).map_err(|e| format!("Invalid {}: {}", manifest_name, e))?;
+
+    let report = compare(&expected, &contents.files, contents.manifest.as_ref());
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report_json(docpack, &report))?);
+    } else {
+        print_report(&report, &manifest_name);
+    }
+
+    if report.passed() {
+        Ok(())
+    } else {
+        Err(format!("Verification failed for {}", docpack).into())
+    }
+}
+
+fn compare(expected: &BTreeMap<String, FileHash>, actual: &BTreeMap<String, FileHash>, manifest: Option<&Value>) -> Report {
+    let mut report = Report { checked: actual.len(), ..Report::default() };
+
+    for (path, hash) in actual {
+        match expected.get(path) {
+            None => report.added.push(path.clone()),
+            Some(want) if want != hash => report.modified.push((path.clone(), want.clone(), hash.clone())),
+            Some(_) => {}
+        }
+    }
+    report.missing = expected.keys().filter(|path| !actual.contains_key(*path)).cloned().collect();
+
+    report.digest_ok = manifest
+        .and_then(|m| m["metadata"]["hashes"]["digest"].as_str())
+        .map(|digest| digest == hashes::digest(expected));
+
+    report
+}
+
+fn read_dir(docpack_path: &Path, quiet: bool) -> Result<Contents, LocaldocError> {
+    let manifest = fs::read_to_string(docpack_path.join("docpack.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    let hashes_path = docpack_path.join("index").join(hashes::FILE_NAME);
+    let hashes = if hashes_path.exists() {
+        Some(serde_json::from_str(&fs::read_to_string(&hashes_path).with_path(&hashes_path)?)?)
+    } else {
+        None
+    };
+
+    let files_dir = docpack_path.join("files");
+    let mut to_hash: Vec<(String, PathBuf)> = Vec::new();
+    if files_dir.is_dir() {
+        for entry in walkdir::WalkDir::new(&files_dir).min_depth(1).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                let rel = entry.path().strip_prefix(&files_dir)?;
+                to_hash.push((archive::path_to_entry_name(rel), entry.path().to_path_buf()));
+            }
+        }
+    }
+
+    let mut progress = Progress::new("Hashing", Some(to_hash.len() as u64), quiet);
+    let files = hashes::hash_files(&to_hash, &mut progress)?;
+    progress.finish();
+
+    Ok(Contents { manifest, hashes, files })
+}
+
+fn read_archive(docpack_path: &Path, quiet: bool) -> Result<Contents, LocaldocError> {
+    let mut sink = HashSink {
+        progress: Progress::new("Hashing", None, quiet),
+        manifest: None,
+        hashes: None,
+        map: None,
+        files: BTreeMap::new(),
+        blobs: BTreeMap::new(),
+    };
+    archive::extract_entries(fs::File::open(docpack_path).with_path(docpack_path)?, &mut sink, &Limits::default())?;
+    sink.progress.finish();
+
+    let parse = |bytes: Option<Vec<u8>>, name: &str| -> Result<Option<Value>, String> {
+        bytes
+            .map(|b| serde_json::from_slice(&b).map_err(|e| format!("Invalid JSON in {}: {}", name, e)))
+            .transpose()
+    };
+
+    // A deduplicated docpack's files are its blobs, by way of the map;
+    // paths whose blob is missing are reported as missing
+    let mut files = sink.files;
+    if let Some(map) = parse(sink.map, dedupe::MAP_FILE)? {
+        for (name, entry) in map["files"].as_object().into_iter().flatten() {
+            if let Some(hash) = entry["sha256"].as_str().and_then(|blob| sink.blobs.get(blob)) {
+                files.insert(name.clone(), hash.clone());
+            }
+        }
+    }
+
+    Ok(Contents {
+        manifest: parse(sink.manifest, "docpack.json").ok().flatten(),
+        hashes: parse(sink.hashes, &format!("index/{}", hashes::FILE_NAME))?,
+        files,
+    })
+}
+
+/// Hashes `files/` and `blobs/` entries as they stream out of an archive,
+/// keeping the manifests and any `files.map.json` in memory.
+struct HashSink {
+    progress: Progress,
+    manifest: Option<Vec<u8>>,
+    hashes: Option<Vec<u8>>,
+    map: Option<Vec<u8>>,
+    files: BTreeMap<String, FileHash>,
+    /// Hashes of deduplicated blobs, keyed by blob name
+    blobs: BTreeMap<String, FileHash>,
+}
+
+impl EntrySink for HashSink {
+    fn entry(&mut self, meta: &EntryMeta, data: &mut dyn Read) -> io::Result<()> {
+        if meta.is_dir {
+            return Ok(());
+        }
+
+        let blob = meta.path.strip_prefix(dedupe::BLOB_DIR).and_then(|rest| rest.strip_prefix('/'));
+        if let Some(rel) = meta.path.strip_prefix("files/") {
+            let hash = hashes::hash_reader(data)?;
+            self.progress.inc(hash.size);
+            self.files.insert(rel.to_string(), hash);
+        } else if let Some(blob) = blob {
+            let hash = hashes::hash_reader(data)?;
+            self.progress.inc(hash.size);
+            self.blobs.insert(blob.to_string(), hash);
+        } else if meta.path == "docpack.json"
+            || meta.path == dedupe::MAP_FILE
+            || meta.path == format!("index/{}", hashes::FILE_NAME)
+        {
+            let mut buf = Vec::new();
+            data.read_to_end(&mut buf)?;
+            if meta.path == "docpack.json" {
+                self.manifest = Some(buf);
+            } else if meta.path == dedupe::MAP_FILE {
+                self.map = Some(buf);
+            } else {
+                self.hashes = Some(buf);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn print_report(report: &Report, manifest_name: &str) {
+    info!("🔒 Integrity");
+    info!("{}", "─".repeat(60));
+    info!("Checked:  {} files", report.checked);
+    info!("Added:    {}", report.added.len());
+    info!("Missing:  {}", report.missing.len());
+    info!("Modified: {}", report.modified.len());
+    match report.digest_ok {
+        Some(true) => info!("Digest:   ✓ matches docpack.json"),
+        Some(false) => info!("Digest:   ❌ {} does not match metadata.hashes.digest", manifest_name),
+        None => info!("Digest:   not recorded in docpack.json"),
+    }
+
+    if !report.added.is_empty() {
+        info!("\n⚠️  Files not in {}:", manifest_name);
+        for path in &report.added {
+            info!("  • {}", path);
+        }
+    }
+    if !report.missing.is_empty() {
+        info!("\n❌ Missing files:");
+        for path in &report.missing {
+            info!("  • {}", path);
+        }
+    }
+    if !report.modified.is_empty() {
+        info!("\n❌ Modified files:");
+        for (path, want, got) in &report.modified {
+            info!("  • {} (expected {}…, got {}…)", path, short(&want.sha256), short(&got.sha256));
+        }
+    }
+
+    if report.passed() {
+        info!("\n✓ All {} files match {}", report.checked, manifest_name);
+    }
+}
+
+fn short(hash: &str) -> &str {
+    hash.get(..12).unwrap_or(hash)
+}
+
+fn report_json(docpack: &str, report: &Report) -> Value {
+    json!({
+        "path": docpack,
+        "verified": report.passed(),
+        "checked": report.checked,
+        "digest_ok": report.digest_ok,
+        "added": report.added,
+        "missing": report.missing,
+        "modified": report.modified.iter().map(|(path, want, got)| json!({
+            "path": path,
+            "expected_sha256": want.sha256,
+            "actual_sha256": got.sha256,
+            "expected_size": want.size,
+            "actual_size": got.size,
+        })).collect::<Vec<_>>(),
+    })
+}
@@ -0,0 +1,228 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use log::{error, info, warn};
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::commands::run;
+use crate::error::LocaldocError;
+use crate::filter::SourceFilter;
+use crate::interrupt;
+
+/// How often the watcher checks on the run and for Ctrl-C between events.
+const TICK: Duration = Duration::from_millis(200);
+
+/// Changed paths named in an iteration banner before the rest are counted.
+const MAX_NAMED_CHANGES: usize = 3;
+
+/// Settings for `localdoc watch`, one field per command-line flag.
+pub struct WatchOptions<'a> {
+    pub source: &'a str,
+    /// The docpack kept up to date
+    pub out: &'a str,
+    /// Quiet time after the last change before starting over
+    pub debounce: Duration,
+    /// Passed on to each `localdoc run`
+    pub run_args: &'a [String],
+}
+
+/// What a batch of changes calls for.
+#[derive(Default)]
+struct Pending {
+    /// Source files, relative to the source root
+    source: BTreeSet<String>,
+    tasks: bool,
+    last: Option<Instant>,
+}
+
+/// Ingest `source` into `out` if needed, run it, and start over whenever
+/// the source or the docpack's tasks.json changes. Ingest, update and run
+/// each happen in a child `localdoc`, so a run still going when the next
+/// change arrives can be cancelled like any other: with Ctrl-C, which
+/// stops its container and salvages its outputs.
+pub fn run(opts: &WatchOptions) -> Result<(), LocaldocError> {
+    let source = Path::new(opts.source);
+    if !source.is_dir() {
+        return Err(LocaldocError::SourceNotFound { what: "Source directory", path: source.to_path_buf() });
+    }
+    let source = source.canonicalize()?;
+    let out = absolute(Path::new(opts.out))?;
+    let tasks_file = out.join("tasks.json");
+    // Where the docpack and its runs write; changes there are not the user's
+    let written = [out.clone(), out.with_file_name(format!("{}-output", run::docpack_name(&out)))];
+    let mut filter = SourceFilter::new(&[], &[], true)?;
+    filter.load_ignore_file(&source)?;
+
+    let exe = std::env::current_exe()?;
+    interrupt::defer_to_children();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })?;
+    watcher.watch(&source, RecursiveMode::Recursive)?;
+
+    let mut iteration = 1;
+    banner(iteration, "initial run");
+    if !out.exists() {
+        step(Command::new(&exe).arg("ingest").arg(&source).arg("-o").arg(&out).arg("--quiet"), "Ingest")?;
+    }
+    // A tasks.json in the source tree is already watched
+    if tasks_file.is_file() && !tasks_file.starts_with(&source) {
+        watcher.watch(&tasks_file, RecursiveMode::NonRecursive)?;
+    }
+    let mut running = Some(start_run(&exe, &out, opts.run_args)?);
+    info!("\nWatching {} for changes (Ctrl-C to stop)", source.display());
+
+    let mut pending = Pending::default();
+    loop {
+        if interrupt::interrupted() {
+            // The run got the same Ctrl-C and is stopping its container
+            if let Some(mut child) = running.take() {
+                child.wait()?;
+            }
+            info!("\nStopped watching");
+            return Ok(());
+        }
+
+        match rx.recv_timeout(TICK) {
+            Ok(Ok(event)) if !matches!(event.kind, EventKind::Access(_)) => {
+                for path in &event.paths {
+                    if path == &tasks_file {
+                        pending.tasks = true;
+                    } else if written.iter().any(|dir| path.starts_with(dir)) {
+                        continue;
+                    } else if let Ok(rel) = path.strip_prefix(&source) {
+                        if !rel.as_os_str().is_empty() && filter.check(rel, path.is_dir()).is_none() {
+                            pending.source.insert(rel.to_string_lossy().replace('\\', "/"));
+                        }
+                    } else {
+                        continue;
+                    }
+                    pending.last = Some(Instant::now());
+                }
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => warn!("Watch error: {}", e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Err("The file watcher stopped".into()),
+        }
+
+        if let Some(child) = &mut running {
+            if let Some(status) = child.try_wait()? {
+                report(status);
+                running = None;
+                info!("\nWatching for changes...");
+            }
+        }
+
+        let settled = pending.last.is_some_and(|last| last.elapsed() >= opts.debounce);
+        if !settled || (pending.source.is_empty() && !pending.tasks) {
+            continue;
+        }
+        let changes = std::mem::take(&mut pending);
+        if let Some(child) = running.take() {
+            info!("\nCancelling the current run...");
+            cancel(child)?;
+        }
+
+        iteration += 1;
+        banner(iteration, &describe(&changes));
+        if !changes.source.is_empty() {
+            let update = Command::new(&exe).arg("update").arg(&out).arg("--source").arg(&source).arg("--quiet").status()?;
+            if !update.success() {
+                error!("Update failed; waiting for the next change");
+                continue;
+            }
+        }
+        running = Some(start_run(&exe, &out, opts.run_args)?);
+    }
+}
+
+fn absolute(path: &Path) -> std::io::Result<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.canonicalize()?,
+        _ => std::env::current_dir()?,
+    };
+    Ok(parent.join(path.file_name().unwrap_or(path.as_os_str())))
+}
+
+/// The line that opens each iteration.
+fn banner(iteration: u32, reason: &str) {
+    let now = chrono::Local::now().format("%H:%M:%S");
+    info!("\n{}", "━".repeat(60));
+    info!("▶ Iteration {} · {} · {}", iteration, now, reason);
+    info!("{}", "━".repeat(60));
+}
+
+/// `tasks.json changed`, or the source files that did, the first few by name.
+fn describe(changes: &Pending) -> String {
+    let mut parts = Vec::new();
+    if !changes.source.is_empty() {
+        let named: Vec<&str> = changes.source.iter().take(MAX_NAMED_CHANGES).map(String::as_str).collect();
+        let more = changes.source.len().saturating_sub(MAX_NAMED_CHANGES);
+        if more > 0 {
+            parts.push(format!("changed: {} and {} more", named.join(", "), more));
+        } else {
+            parts.push(format!("changed: {}", named.join(", ")));
+        }
+    }
+    if changes.tasks {
+        parts.push("tasks.json changed".to_string());
+    }
+    parts.join("; ")
+}
+
+fn step(cmd: &mut Command, what: &str) -> Result<(), LocaldocError> {
+    let status = cmd.status()?;
+    if !status.success() {
+        return Err(format!("{} failed with exit code: {:?}", what, status.code()).into());
+    }
+    Ok(())
+}
+
+fn start_run(exe: &Path, out: &Path, run_args: &[String]) -> std::io::Result<Child> {
+    Command::new(exe).arg("run").arg(out).args(run_args).spawn()
+}
+
+fn report(status: ExitStatus) {
+    let now = chrono::Local::now().format("%H:%M:%S");
+    match status.code() {
+        Some(0) => info!("\n✓ Run finished at {}", now),
+        Some(130) | None => info!("\nRun interrupted at {}", now),
+        Some(code) => info!("\n❌ Run failed at {} (exit code {})", now, code),
+    }
+}
+
+/// Stop a run the way Ctrl-C would, so its container is stopped and its
+/// outputs salvaged, and wait for it.
+fn cancel(mut child: Child) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        // SAFETY: kill only sends a signal to the child's pid
+        if unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGINT) } != 0 {
+            child.kill()?;
+        }
+    }
+    #[cfg(not(unix))]
+    child.kill()?;
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_are_summarized() {
+        let mut changes = Pending { tasks: true, ..Pending::default() };
+        assert_eq!(describe(&changes), "tasks.json changed");
+        for file in ["a.rs", "b.rs", "c.rs", "d.rs", "e.rs"] {
+            changes.source.insert(file.to_string());
+        }
+        assert_eq!(describe(&changes), "changed: a.rs, b.rs, c.rs and 2 more; tasks.json changed");
+    }
+}
@@ -0,0 +1,337 @@
+//! Defaults for command-line flags kept in TOML files: the user's
+//! `~/.config/localdoc/config.toml`, then a project's `.localdoc.toml` in
+//! the current directory or the nearest parent that has one. A flag wins
+//! over both files, and the project file over the user's. Environment
+//! variables such as `$LOCALDOC_RUNTIME` win over the files too, as they
+//! already stand in for a flag.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use clap::ValueEnum;
+use toml_edit::{Array, DocumentMut, Item, Table, Value};
+
+use crate::commands::registry;
+use crate::constraints;
+use crate::embeddings;
+use crate::runtime::{self, RuntimeChoice};
+use crate::size;
+use crate::tools;
+
+/// The user's config file, under `$XDG_CONFIG_HOME/localdoc/`.
+pub const FILE_NAME: &str = "config.toml";
+
+/// A project's config file, found in the current directory or above it.
+pub const PROJECT_FILE: &str = ".localdoc.toml";
+
+/// What a key holds, and so how it is checked and read from `config set`.
+#[derive(Clone, Copy)]
+enum Kind {
+    Text,
+    /// Globs; comma-separated on the command line
+    List,
+    /// A byte count such as `"10MB"`, or a plain number of bytes
+    Size,
+    Megabytes,
+    Runtime,
+    Preset,
+}
+
+/// A setting that a config file may hold.
+pub struct Key {
+    pub name: &'static str,
+    kind: Kind,
+    /// What applies when no file sets it, for `config get` and `config list`
+    pub default: Option<&'static str>,
+    /// Not shown by `config list`
+    secret: bool,
+}
+
+impl Key {
+    const fn new(name: &'static str, kind: Kind, default: Option<&'static str>) -> Self {
+        Key { name, kind, default, secret: false }
+    }
+}
+
+/// Every key a config file may set, by section.
+pub const KEYS: &[Key] = &[
+    Key::new("ingest.exclude", Kind::List, None),
+    Key::new("ingest.max_file_size", Kind::Size, Some("50MB")),
+    Key::new("ingest.preset", Kind::Preset, Some(tools::DEFAULT_PRESET)),
+    Key::new("run.image", Kind::Text, Some(runtime::DEFAULT_IMAGE)),
+    Key::new("run.runtime", Kind::Runtime, Some("auto")),
+    Key::new("run.memory", Kind::Megabytes, None),
+    Key::new("embed.api_base", Kind::Text, Some(embeddings::DEFAULT_API_BASE)),
+    Key { name: "embed.api_key", kind: Kind::Text, default: None, secret: true },
+    Key::new("embed.model", Kind::Text, Some(embeddings::DEFAULT_MODEL)),
+    Key { name: "registry.token", kind: Kind::Text, default: None, secret: true },
+];
+
+/// The key called `name`.
+pub fn key(name: &str) -> Result<&'static Key, String> {
+    KEYS.iter().find(|key| key.name == name).ok_or_else(|| {
+        let names: Vec<&str> = KEYS.iter().map(|key| key.name).collect();
+        format!("Unknown config key '{}'. Known keys: {}", name, names.join(", "))
+    })
+}
+
+impl Key {
+    /// Why `value` is not allowed for this key.
+    fn check(&self, value: &Value) -> Result<(), String> {
+        match (self.kind, value) {
+            (Kind::Text, Value::String(_)) => Ok(()),
+            (Kind::Text, _) => Err("must be a string".to_string()),
+            (Kind::List, Value::Array(items)) if items.iter().all(|item| item.is_str()) => Ok(()),
+            (Kind::List, _) => Err("must be a list of strings, e.g. [\"*.log\"]".to_string()),
+            (Kind::Size, Value::String(text)) => size::parse_size(text.value()).map(drop),
+            (Kind::Size, Value::Integer(bytes)) if *bytes.value() >= 0 => Ok(()),
+            (Kind::Size, _) => Err("must be a size such as \"10MB\"".to_string()),
+            (Kind::Megabytes, Value::Integer(mb)) => constraints::parse_memory_limit(&mb.value().to_string()).map(drop),
+            (Kind::Megabytes, _) => Err("must be a number of MB".to_string()),
+            (Kind::Runtime, Value::String(text)) => RuntimeChoice::from_str(text.value(), true)
+                .map(drop)
+                .map_err(|_| format!("must be docker, podman or auto, not '{}'", text.value())),
+            (Kind::Runtime, _) => Err("must be docker, podman or auto".to_string()),
+            (Kind::Preset, Value::String(text)) => tools::expand(text.value(), &[]).map(drop),
+            (Kind::Preset, _) => Err("must be a string".to_string()),
+        }
+    }
+
+    /// `text` from `config set` as a value for this key.
+    pub fn parse(&self, text: &str) -> Result<Value, String> {
+        let value = match self.kind {
+            Kind::List => Value::Array(text.split(',').map(str::trim).filter(|item| !item.is_empty()).collect::<Array>()),
+            Kind::Megabytes => match text.trim().parse::<i64>() {
+                Ok(mb) => Value::from(mb),
+                Err(_) => return Err(format!("{}: '{}' is not a number of MB", self.name, text)),
+            },
+            _ => Value::from(text),
+        };
+        self.check(&value).map_err(|e| format!("{}: {}", self.name, e))?;
+        Ok(value)
+    }
+
+    /// `value` as `config get` prints it.
+    pub fn display(&self, value: &Value) -> String {
+        match value {
+            Value::String(text) => text.value().clone(),
+            Value::Array(items) => items.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(","),
+            other => other.to_string().trim().to_string(),
+        }
+    }
+
+    pub fn is_secret(&self) -> bool {
+        self.secret
+    }
+}
+
+/// A value from a config file, and the file.
+pub struct Setting {
+    pub value: Value,
+    pub file: PathBuf,
+}
+
+/// The settings of the user and project files, merged.
+#[derive(Default)]
+pub struct Config {
+    settings: BTreeMap<&'static str, Setting>,
+}
+
+/// `$XDG_CONFIG_HOME/localdoc/config.toml`, else under `~/.config`.
+/// `None` when neither variable is set.
+pub fn user_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("localdoc").join(FILE_NAME))
+}
+
+/// The nearest `.localdoc.toml` at or above the current directory.
+pub fn project_path() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors().map(|dir| dir.join(PROJECT_FILE)).find(|path| path.is_file())
+}
+
+/// Read the user and project files. A file that does not parse, or sets a
+/// key that does not exist or to a value it cannot take, is an error
+/// naming the file and the key.
+pub fn load() -> Result<Config, String> {
+    let mut config = Config::default();
+    for path in [user_path(), project_path()].into_iter().flatten() {
+        if path.is_file() {
+            config.merge(&path)?;
+        }
+    }
+    Ok(config)
+}
+
+/// The parsed file at `path`, which must exist.
+pub fn read(path: &Path) -> Result<DocumentMut, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    text.parse().map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+}
+
+impl Config {
+    fn merge(&mut self, path: &Path) -> Result<(), String> {
+        let invalid = |problem: String| format!("Invalid config file {}: {}", path.display(), problem);
+        let doc = read(path)?;
+        for (section, item) in doc.iter() {
+            let Some(table) = item.as_table_like() else {
+                return Err(invalid(format!("Unknown config key '{}'; keys go in a section such as [run]", section)));
+            };
+            if !KEYS.iter().any(|key| key.name.split_once('.').is_some_and(|(name, _)| name == section)) {
+                return Err(invalid(format!("Unknown config section [{}]; sections are [ingest], [run] and [embed]", section)));
+            }
+            for (field, item) in table.iter() {
+                let name = format!("{}.{}", section, field);
+                let key = key(&name).map_err(invalid)?;
+                let value = item.as_value().ok_or_else(|| invalid(format!("{}: must be a value, not a table", name)))?;
+                key.check(value).map_err(|e| invalid(format!("{}: {}", name, e)))?;
+                self.settings.insert(key.name, Setting { value: value.clone(), file: path.to_path_buf() });
+            }
+        }
+        Ok(())
+    }
+
+    /// The setting for `name`, from whichever file set it last.
+    pub fn get(&self, name: &str) -> Option<&Setting> {
+        self.settings.get(name)
+    }
+
+    fn text(&self, name: &str) -> Option<&str> {
+        self.get(name).and_then(|setting| setting.value.as_str())
+    }
+
+    /// `ingest --exclude`
+    pub fn excludes(&self) -> Option<Vec<String>> {
+        let items = self.get("ingest.exclude")?.value.as_array()?;
+        Some(items.iter().filter_map(Value::as_str).map(String::from).collect())
+    }
+
+    /// `ingest --max-file-size`
+    pub fn max_file_size(&self) -> Option<u64> {
+        match &self.get("ingest.max_file_size")?.value {
+            Value::String(text) => size::parse_size(text.value()).ok(),
+            Value::Integer(bytes) => u64::try_from(*bytes.value()).ok(),
+            _ => None,
+        }
+    }
+
+    /// `ingest --preset`
+    pub fn preset(&self) -> Option<&str> {
+        self.text("ingest.preset")
+    }
+
+    /// `run --image`
+    pub fn image(&self) -> Option<&str> {
+        self.text("run.image")
+    }
+
+    /// `run --runtime`, unless `$LOCALDOC_RUNTIME` is set
+    pub fn runtime(&self) -> Option<RuntimeChoice> {
+        unless_env(&[runtime::RUNTIME_ENV], self.text("run.runtime"))
+            .and_then(|text| RuntimeChoice::from_str(text, true).ok())
+    }
+
+    /// `run --memory`
+    pub fn memory_mb(&self) -> Option<u64> {
+        self.get("run.memory")?.value.as_integer().and_then(|mb| u64::try_from(mb).ok())
+    }
+
+    /// `--api-base`, unless `$LOCALDOC_EMBED_API_BASE` is set
+    pub fn api_base(&self) -> Option<&str> {
+        unless_env(&[embeddings::API_BASE_ENV], self.text("embed.api_base"))
+    }
+
+    /// `--api-key`, unless `$LOCALDOC_EMBED_API_KEY` or `$OPENAI_API_KEY` is set
+    pub fn api_key(&self) -> Option<&str> {
+        unless_env(&[embeddings::API_KEY_ENV, embeddings::OPENAI_KEY_ENV], self.text("embed.api_key"))
+    }
+
+    /// `embed --model`, unless `$LOCALDOC_EMBED_MODEL` is set
+    pub fn model(&self) -> Option<&str> {
+        unless_env(&[embeddings::MODEL_ENV], self.text("embed.model"))
+    }
+
+    /// The bearer token `push` and `pull` send, unless
+    /// `$LOCALDOC_REGISTRY_TOKEN` is set
+    pub fn registry_token(&self) -> Option<&str> {
+        unless_env(&[registry::TOKEN_ENV], self.text("registry.token"))
+    }
+}
+
+/// `value`, unless one of `vars` is set; the command reads those itself.
+fn unless_env<'a>(vars: &[&str], value: Option<&'a str>) -> Option<&'a str> {
+    let set = vars.iter().any(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()));
+    if set {
+        None
+    } else {
+        value
+    }
+}
+
+/// Set `key` to `value` in `doc`, creating its section if needed.
+pub fn set(doc: &mut DocumentMut, key: &Key, value: Value) {
+    let (section, field) = key.name.split_once('.').expect("keys are section.field");
+    if doc.get(section).is_none() {
+        doc.insert(section, Item::Table(Table::new()));
+    }
+    doc[section][field] = Item::Value(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, text: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn later_files_override_earlier_ones_key_by_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = write(dir.path(), "user.toml", "[run]\nimage = \"a\"\nmemory = 4096\n[ingest]\nexclude = [\"*.log\"]\n");
+        let project = write(dir.path(), "project.toml", "[run]\nimage = \"b\"\n");
+
+        let mut config = Config::default();
+        config.merge(&user).unwrap();
+        config.merge(&project).unwrap();
+        assert_eq!(config.image(), Some("b"));
+        assert_eq!(config.get("run.image").unwrap().file, project);
+        assert_eq!(config.memory_mb(), Some(4096));
+        assert_eq!(config.excludes(), Some(vec!["*.log".to_string()]));
+        assert_eq!(config.preset(), None);
+    }
+
+    #[test]
+    fn errors_name_the_file_and_the_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases = [
+            ("[run]\nimgae = \"a\"\n", "unknown config key 'run.imgae'"),
+            ("[run]\nmemory = 64\n", "run.memory: must be at least 128 MB"),
+            ("[run]\nruntime = \"lxc\"\n", "run.runtime: must be docker, podman or auto, not 'lxc'"),
+            ("[ingest]\nmax_file_size = \"10 parsecs\"\n", "ingest.max_file_size: unknown size unit"),
+            ("[ingest]\nexclude = \"*.log\"\n", "ingest.exclude: must be a list of strings"),
+            ("image = \"a\"\n", "unknown config key 'image'"),
+            ("[runn]\nimage = \"a\"\n", "unknown config section [runn]"),
+        ];
+        for (text, expected) in cases {
+            let path = write(dir.path(), "config.toml", text);
+            let error = Config::default().merge(&path).unwrap_err();
+            assert!(error.contains(&path.display().to_string()), "{}", error);
+            assert!(error.to_lowercase().contains(&expected.to_lowercase()), "{}", error);
+        }
+    }
+
+    #[test]
+    fn values_from_the_command_line_are_typed_by_key() {
+        assert_eq!(key("ingest.exclude").unwrap().parse("*.log, fixtures/**").unwrap().to_string(), "[\"*.log\", \"fixtures/**\"]");
+        assert_eq!(key("run.memory").unwrap().parse("4096").unwrap().as_integer(), Some(4096));
+        assert!(key("run.memory").unwrap().parse("lots").is_err());
+        assert!(key("ingest.preset").unwrap().parse("everything").is_err());
+        assert!(key("run.imgae").is_err());
+    }
+}
@@ -0,0 +1,75 @@
+pub use docpack_core::Constraints;
+
+/// Smallest memory limit the documenter container can start with.
+pub const MIN_MEMORY_LIMIT_MB: u64 = 128;
+
+/// Longest run allowed, so a typo doesn't book a container for a month.
+pub const MAX_EXECUTION_TIME_SECONDS: u64 = 24 * 60 * 60;
+
+/// Warnings for run limits in `environment.constraints` that are valid
+/// but unlikely to work, each with the constraint it is about. Types and
+/// ranges are checked by the manifest schema (see [`crate::schema::MANIFEST`]).
+pub fn check(constraints: &Constraints) -> Vec<(&'static str, String)> {
+    let mut warnings = Vec::new();
+
+    if let Some(memory) = constraints.memory_limit_mb.filter(|&m| m > 0 && m < MIN_MEMORY_LIMIT_MB) {
+        warnings.push((
+            "memory_limit_mb",
+            format!(
+                "docpack.json: 'environment.constraints.memory_limit_mb' is {} MB; the documenter needs at least {} MB",
+                memory, MIN_MEMORY_LIMIT_MB
+            ),
+        ));
+    }
+    if let Some(seconds) = constraints.max_execution_time_seconds.filter(|&s| s > MAX_EXECUTION_TIME_SECONDS) {
+        warnings.push((
+            "max_execution_time_seconds",
+            format!(
+                "docpack.json: 'environment.constraints.max_execution_time_seconds' is {} (more than 24 hours)",
+                seconds
+            ),
+        ));
+    }
+
+    warnings
+}
+
+/// Parse `--max-file-reads`.
+pub fn parse_file_reads(text: &str) -> Result<u64, String> {
+    parse_positive(text)
+}
+
+/// Parse `--max-execution-time`, in seconds.
+pub fn parse_execution_time(text: &str) -> Result<u64, String> {
+    let seconds = parse_positive(text)?;
+    if seconds > MAX_EXECUTION_TIME_SECONDS {
+        return Err(format!("must be at most {} seconds (24 hours)", MAX_EXECUTION_TIME_SECONDS));
+    }
+    Ok(seconds)
+}
+
+/// Parse `--memory-limit`, in MB.
+pub fn parse_memory_limit(text: &str) -> Result<u64, String> {
+    let mb = parse_positive(text)?;
+    if mb < MIN_MEMORY_LIMIT_MB {
+        return Err(format!("must be at least {} MB", MIN_MEMORY_LIMIT_MB));
+    }
+    Ok(mb)
+}
+
+/// Parse `run --cpus`, a positive number of CPUs such as `1.5`.
+pub fn parse_cpus(text: &str) -> Result<f64, String> {
+    match text.trim().parse::<f64>() {
+        Ok(cpus) if cpus.is_finite() && cpus > 0.0 => Ok(cpus),
+        Ok(_) => Err("must be greater than zero".to_string()),
+        Err(_) => Err(format!("'{}' is not a number", text)),
+    }
+}
+
+fn parse_positive(text: &str) -> Result<u64, String> {
+    match text.trim().parse::<u64>() {
+        Ok(0) => Err("must be greater than zero".to_string()),
+        Ok(n) => Ok(n),
+        Err(_) => Err(format!("'{}' is not a whole number", text)),
+    }
+}
@@ -0,0 +1,390 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use docpack_core::{Constraints, Manifest, Strictness, Task, TasksFile, MANIFEST_FILE, TASKS_FILE};
+use serde_json::{json, Map, Value};
+use crate::error::{LocaldocError, WithPath};
+
+/// Mount point of the docpack inside the documenter container.
+pub const WORKSPACE: &str = "/workspace";
+
+/// Where the agent keeps its notes, relative to the docpack root.
+pub const REASONING_DIR: &str = ".reasoning";
+
+/// Files the agent is nudged to read first, in order of preference.
+const READING_ORDER: &[&str] = &[
+    "README.md",
+    "README.rst",
+    "README.txt",
+    "README",
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "go.mod",
+    "pom.xml",
+];
+
+/// Entry-point file stems the agent should look at after the top-level docs.
+const ENTRY_POINT_STEMS: &[&str] = &["main", "lib", "index", "app", "__init__", "mod"];
+
+/// A host path mapped into the container.
+pub struct Mount {
+    pub host: PathBuf,
+    pub container: String,
+    pub writable: bool,
+}
+
+/// Mounts for running against `working_dir` (an absolute docpack directory).
+/// The docpack is read-only apart from `output/` and `.reasoning/`, each
+/// mounted over it read-write, unless `writable_files` opens all of it.
+pub fn plan_mounts(working_dir: &Path, writable_files: bool) -> Vec<Mount> {
+    let mut mounts = vec![Mount {
+        host: working_dir.to_path_buf(),
+        container: WORKSPACE.to_string(),
+        writable: writable_files,
+    }];
+    if !writable_files {
+        for dir in ["output", REASONING_DIR] {
+            mounts.push(Mount {
+                host: working_dir.join(dir),
+                container: format!("{}/{}", WORKSPACE, dir),
+                writable: true,
+            });
+        }
+    }
+    mounts
+}
+
+/// Whether the constraints (see [`effective_constraints`]) list
+/// `modify_files` among the `forbidden_actions`, which rules out a
+/// writable docpack mount.
+pub fn forbids_modifying_files(constraints: &Value) -> bool {
+    constraints["forbidden_actions"]
+        .as_array()
+        .is_some_and(|actions| actions.iter().any(|action| action == "modify_files"))
+}
+
+/// Read/write status of each docpack area as the agent sees it.
+pub fn area_access(mounts: &[Mount]) -> Vec<(String, bool)> {
+    ["files", "index", "output", REASONING_DIR, "docpack.json", "tasks.json"]
+        .iter()
+        .map(|area| {
+            let path = format!("{}/{}", WORKSPACE, area);
+            let writable = mounts
+                .iter()
+                .filter(|m| path == m.container || path.starts_with(&format!("{}/", m.container)))
+                .max_by_key(|m| m.container.len())
+                .map(|m| m.writable)
+                .unwrap_or(false);
+            (path, writable)
+        })
+        .collect()
+}
+
+/// Tools the agent can actually call: the manifest's tools, narrowed to the
+/// task's `tools_allowed` when a task is given.
+pub fn resolve_tools(manifest: &Manifest, task: Option<&Task>) -> Vec<String> {
+    let enabled = manifest.environment.tools.clone();
+    match task.and_then(|t| t.tools_allowed.as_ref()) {
+        Some(allowed) => enabled.into_iter().filter(|tool| allowed.contains(tool)).collect(),
+        None => enabled,
+    }
+}
+
+/// Constraints from the manifest merged with those declared in tasks.json.
+pub fn effective_constraints(constraints: Option<&Constraints>, task_constraints: Option<&Map<String, Value>>) -> Value {
+    let mut constraints = match constraints.map(serde_json::to_value) {
+        Some(Ok(Value::Object(map))) => map,
+        _ => Map::new(),
+    };
+
+    if let Some(task_constraints) = task_constraints {
+        for (key, value) in task_constraints {
+            constraints.insert(key.clone(), value.clone());
+        }
+    }
+
+    Value::Object(constraints)
+}
+
+/// Caps on what a run may leave in `output/`, from manifest constraints
+/// (`max_output_bytes`, `max_output_file_bytes`) or CLI overrides.
+#[derive(Clone, Copy, Default)]
+pub struct OutputLimits {
+    pub max_total_bytes: Option<u64>,
+    pub max_file_bytes: Option<u64>,
+}
+
+impl OutputLimits {
+    /// Manifest constraints, with CLI overrides taking precedence.
+    pub fn resolve(constraints: Option<&Constraints>, total_override: Option<u64>, file_override: Option<u64>) -> Self {
+        OutputLimits {
+            max_total_bytes: total_override.or_else(|| constraints?.max_output_bytes),
+            max_file_bytes: file_override.or_else(|| constraints?.max_output_file_bytes),
+        }
+    }
+}
+
+/// Resources the documenter container may use, from manifest constraints
+/// (`memory_limit_mb`, `max_execution_time_seconds`) or CLI overrides. A
+/// constraint of 0 means no limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ContainerLimits {
+    pub memory_mb: Option<u64>,
+    pub cpus: Option<f64>,
+    pub timeout_seconds: Option<u64>,
+}
+
+impl ContainerLimits {
+    /// Manifest constraints, with CLI overrides taking precedence.
+    pub fn resolve(
+        constraints: Option<&Constraints>,
+        memory_override: Option<u64>,
+        cpus_override: Option<f64>,
+        timeout_override: Option<u64>,
+    ) -> Self {
+        let declared = |limit: fn(&Constraints) -> Option<u64>| constraints.and_then(limit).filter(|&n| n > 0);
+        ContainerLimits {
+            memory_mb: memory_override.or_else(|| declared(|c| c.memory_limit_mb)),
+            cpus: cpus_override,
+            timeout_seconds: timeout_override.or_else(|| declared(|c| c.max_execution_time_seconds)),
+        }
+    }
+}
+
+/// Find a task in tasks.json by id.
+pub fn find_task<'a>(tasks: &'a TasksFile, id: &str) -> Option<&'a Task> {
+    tasks.task_list().iter().find(|task| task.id.as_deref() == Some(id))
+}
+
+/// tasks.json narrowed to the tasks with the given ids, kept in file order.
+/// Unknown ids are an error listing the ids there are.
+pub fn select_tasks(tasks: &TasksFile, ids: &[String]) -> Result<TasksFile, String> {
+    let all = tasks.task_list();
+    let is_known = |id: &String| all.iter().any(|task| task.id.as_ref() == Some(id));
+    let unknown: Vec<&str> = ids.iter().filter(|id| !is_known(id)).map(String::as_str).collect();
+    if !unknown.is_empty() {
+        let available: Vec<&str> = all.iter().filter_map(|task| task.id.as_deref()).collect();
+        return Err(format!(
+            "Unknown task id(s): {}. Tasks in tasks.json: {}",
+            unknown.join(", "),
+            if available.is_empty() { "none".to_string() } else { available.join(", ") }
+        ));
+    }
+
+    let mut selected = tasks.clone();
+    selected.tasks = Some(all.iter().filter(|task| task.id.as_ref().is_some_and(|id| ids.contains(id))).cloned().collect());
+    Ok(selected)
+}
+
+/// The documenter's prompt templates. documenter/prompts holds the copies
+/// baked into the agent image, which must stay the same.
+const TASKS_PROMPT: &str = include_str!("../prompts/tasks.txt");
+const EXPLORE_PROMPT: &str = include_str!("../prompts/explore.txt");
+
+/// The opening prompt the documenter builds from tasks.json.
+pub fn render_prompt(tasks: Option<&TasksFile>, only_task: Option<&Task>) -> String {
+    let mission = tasks
+        .and_then(|t| t.mission.as_deref())
+        .unwrap_or("Explore and understand the project structure");
+
+    let task_list: Vec<&Task> = match (only_task, tasks) {
+        (Some(task), _) => vec![task],
+        (None, Some(tasks)) => tasks.task_list().iter().collect(),
+        (None, None) => Vec::new(),
+    };
+
+    if task_list.is_empty() {
+        return fill_template(EXPLORE_PROMPT, &[("mission", mission)]);
+    }
+
+    let descriptions: Vec<String> = task_list
+        .iter()
+        .enumerate()
+        .map(|(i, task)| {
+            format!(
+                "{}. {}: {}",
+                i + 1,
+                task.name.as_deref().unwrap_or("unnamed"),
+                task.description.as_deref().unwrap_or("")
+            )
+        })
+        .collect();
+
+    fill_template(TASKS_PROMPT, &[("mission", mission), ("tasks", &descriptions.join("\n"))])
+}
+
+/// `template` with each `{name}` replaced by its value in one pass, as
+/// Python's `str.format` does, so values are never expanded themselves.
+fn fill_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let field = rest[start + 1..].split_once('}').map(|(name, _)| name);
+        match field.and_then(|name| values.iter().find(|(key, _)| *key == name)) {
+            Some((name, value)) => {
+                out.push_str(value);
+                rest = &rest[start + name.len() + 2..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Files the agent should read first: top-level docs and manifests, then
+/// likely entry points. Paths are relative to `files/`.
+pub fn reading_order_hints(files_dir: &Path) -> Vec<String> {
+    let mut hints: Vec<String> = READING_ORDER
+        .iter()
+        .filter(|name| files_dir.join(name).is_file())
+        .map(|name| name.to_string())
+        .collect();
+
+    let mut entry_points: Vec<String> = walkdir::WalkDir::new(files_dir)
+        .max_depth(3)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            e.path()
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .map(|s| ENTRY_POINT_STEMS.contains(&s))
+                .unwrap_or(false)
+        })
+        .filter_map(|e| relative_path(files_dir, e.path()))
+        .collect();
+    entry_points.sort_by_key(|p| (p.matches('/').count(), p.clone()));
+
+    hints.extend(entry_points);
+    hints
+}
+
+/// The first `limit` paths the `list_files` tool would return for ".",
+/// plus the total number of files.
+pub fn list_files_preview(files_dir: &Path, limit: usize) -> (Vec<String>, usize) {
+    let mut files: Vec<String> = walkdir::WalkDir::new(files_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| relative_path(files_dir, e.path()))
+        .collect();
+
+    let total = files.len();
+    files.truncate(limit);
+    (files, total)
+}
+
+/// Everything above as one JSON document.
+pub fn preview(docpack_path: &Path, task_id: Option<&str>, limit: usize) -> Result<Value, LocaldocError> {
+    let manifest = Manifest::load(&docpack_path.join(MANIFEST_FILE), Strictness::Lenient)?;
+
+    let tasks_path = docpack_path.join(TASKS_FILE);
+    let tasks = match tasks_path.exists() {
+        true => Some(TasksFile::load(&tasks_path, Strictness::Lenient)?),
+        false => None,
+    };
+
+    let task = match task_id {
+        Some(id) => Some(
+            tasks
+                .as_ref()
+                .and_then(|t| find_task(t, id))
+                .ok_or_else(|| LocaldocError::NotFound(format!("No task with id '{}' in tasks.json", id)))?,
+        ),
+        None => None,
+    };
+
+    let abs_path = fs::canonicalize(docpack_path).with_path(docpack_path)?;
+    let mounts = plan_mounts(&abs_path, false);
+    let files_dir = docpack_path.join("files");
+    let (files, total_files) = list_files_preview(&files_dir, limit);
+
+    Ok(json!({
+        "docpack": docpack_path.display().to_string(),
+        "task": task_id,
+        "tools": resolve_tools(&manifest, task),
+        "constraints": effective_constraints(
+            manifest.environment.constraints.as_ref(),
+            tasks.as_ref().and_then(|t| t.constraints.as_ref()),
+        ),
+        "reading_order": reading_order_hints(&files_dir),
+        "mounts": mounts.iter().map(|m| json!({
+            "host": m.host.display().to_string(),
+            "container": m.container,
+            "writable": m.writable,
+        })).collect::<Vec<_>>(),
+        "access": area_access(&mounts).into_iter().map(|(path, writable)| json!({
+            "path": path,
+            "writable": writable,
+        })).collect::<Vec<_>>(),
+        "prompt": render_prompt(tasks.as_ref(), task),
+        "files": files,
+        "total_files": total_files,
+    }))
+}
+
+fn relative_path(base: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(base).ok()?;
+    let parts: Vec<String> = rel
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompts_fill_the_documenter_templates() {
+        let tasks: TasksFile = serde_json::from_value(json!({
+            "mission": "Document the {tasks} parser",
+            "tasks": [
+                { "id": "task_1", "name": "Overview", "description": "Summarize the project" },
+                { "id": "task_2", "name": "API", "description": "List the {public} functions" },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(
+            render_prompt(Some(&tasks), None),
+            "You are a documentation agent working within a .docpack environment.\n\n\
+             Your mission: Document the {tasks} parser\n\n\
+             Tasks to complete:\n\
+             1. Overview: Summarize the project\n\
+             2. API: List the {public} functions\n\n\
+             Begin by exploring the project structure and then complete each task in order.\n\
+             Use the available tools to read files, search code, and query the semantic graph.\n\
+             Write your outputs using the write_output tool as specified in each task.\n"
+        );
+        assert!(render_prompt(Some(&tasks), Some(&tasks.task_list()[1])).contains("Tasks to complete:\n1. API: "));
+
+        assert_eq!(
+            render_prompt(None, None),
+            "You are a documentation agent working within a .docpack environment.\n\n\
+             Your mission: Explore and understand the project structure\n\n\
+             Explore the project at '.' and provide a comprehensive summary of its structure and purpose.\n"
+        );
+    }
+
+    #[test]
+    fn prompts_match_the_documenter_copies() {
+        let documenter = Path::new(env!("CARGO_MANIFEST_DIR")).join("../documenter/prompts");
+        // Not there when built from a published package
+        if !documenter.is_dir() {
+            return;
+        }
+        for (name, template) in [("tasks.txt", TASKS_PROMPT), ("explore.txt", EXPLORE_PROMPT)] {
+            let copy = fs::read_to_string(documenter.join(name)).unwrap();
+            assert_eq!(copy, template, "documenter/prompts/{} differs from cli/prompts/{}", name, name);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use serde_json::{json, Value};
+
+use crate::error::{LocaldocError, WithPath};
+use crate::hashes::FileHash;
+
+/// Maps logical paths under `files/` to blobs in a deduplicated docpack.
+pub const MAP_FILE: &str = "files.map.json";
+
+/// Holds each distinct file once, named by its SHA-256.
+pub const BLOB_DIR: &str = "blobs";
+
+/// Bumped when `files.map.json` changes incompatibly.
+const MAP_VERSION: u32 = 1;
+
+/// How `files/` is stored in a deduplicated docpack.
+pub struct Layout {
+    /// The `files.map.json` document
+    pub map: Value,
+    /// One `(blob name, file)` per distinct content, sorted by blob name
+    pub blobs: Vec<(String, PathBuf)>,
+    pub files: usize,
+    /// Bytes left out because they repeat another file
+    pub saved_bytes: u64,
+}
+
+impl Layout {
+    /// The `metadata.dedupe` record.
+    pub fn metadata(&self) -> Value {
+        json!({
+            "map": MAP_FILE,
+            "files": self.files,
+            "blobs": self.blobs.len(),
+            "saved_bytes": self.saved_bytes,
+        })
+    }
+}
+
+/// Group `(logical path, file)` pairs by content, using their `hashes`.
+/// The first file with given content supplies its blob.
+pub fn plan(files: &[(String, PathBuf)], hashes: &BTreeMap<String, FileHash>) -> Layout {
+    let mut entries = serde_json::Map::new();
+    let mut blobs: BTreeMap<String, PathBuf> = BTreeMap::new();
+    let mut saved_bytes = 0;
+
+    for (name, path) in files {
+        let Some(hash) = hashes.get(name) else { continue };
+        let mut entry = json!({ "sha256": hash.sha256 });
+        // Modes are per path; a blob may back both scripts and plain files
+        if is_executable(path) {
+            entry["executable"] = json!(true);
+        }
+        entries.insert(name.clone(), entry);
+
+        if blobs.contains_key(&hash.sha256) {
+            saved_bytes += hash.size;
+        } else {
+            blobs.insert(hash.sha256.clone(), path.clone());
+        }
+    }
+
+    Layout {
+        files: entries.len(),
+        map: json!({ "version": MAP_VERSION, "files": entries }),
+        blobs: blobs.into_iter().collect(),
+        saved_bytes,
+    }
+}
+
+/// Turn the extracted docpack in `root` into the deduplicated layout in
+/// place: one copy of each file moves to `blobs/`, the rest are deleted,
+/// and `files.map.json` is written. Directories under `files/` are kept
+/// so empty ones survive. Returns the `metadata.dedupe` record.
+pub fn pack_dir(root: &Path, hashes: &BTreeMap<String, FileHash>) -> Result<Value, LocaldocError> {
+    let files: Vec<(String, PathBuf)> = hashes.keys().map(|name| (name.clone(), root.join("files").join(name))).collect();
+    let layout = plan(&files, hashes);
+
+    let blob_dir = root.join(BLOB_DIR);
+    fs::create_dir_all(&blob_dir).with_path(&blob_dir)?;
+    for (blob, path) in &layout.blobs {
+        fs::rename(path, blob_dir.join(blob)).with_path(path)?;
+    }
+    for (_, path) in &files {
+        if path.exists() {
+            fs::remove_file(path).with_path(path)?;
+        }
+    }
+    let map_path = root.join(MAP_FILE);
+    fs::write(&map_path, serde_json::to_string_pretty(&layout.map)?).with_path(&map_path)?;
+
+    Ok(layout.metadata())
+}
+
+/// Rebuild `files/` from the blobs of a deduplicated docpack extracted to
+/// `root`, then drop the blobs and the map so it looks like any other
+/// docpack. Returns `false`, changing nothing, when there is no map.
+pub fn materialize(root: &Path) -> Result<bool, LocaldocError> {
+    let map_path = root.join(MAP_FILE);
+    if !map_path.is_file() {
+        return Ok(false);
+    }
+
+    let map: Value = serde_json::from_str(&fs::read_to_string(&map_path).with_path(&map_path)?)
+        .map_err(|e| format!("Invalid JSON in {}: {}", MAP_FILE, e))?;
+    let files = map["files"].as_object().ok_or_else(|| format!("{} has no \"files\" object", MAP_FILE))?;
+
+    let blob_dir = root.join(BLOB_DIR);
+    for (name, entry) in files {
+        // Names come from the archive, so hold them to the same rules as entry paths
+        if !Path::new(name).components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(format!("Unsafe path in {}: {}", MAP_FILE, name).into());
+        }
+        let blob = entry["sha256"]
+            .as_str()
+            .filter(|sha| sha.len() == 64 && sha.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| format!("Missing or invalid sha256 for {} in {}", name, MAP_FILE))?;
+
+        let target = root.join("files").join(name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+        fs::copy(blob_dir.join(blob), &target)
+            .map_err(|e| format!("Failed to restore files/{} from {}/{}: {}", name, BLOB_DIR, blob, e))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = if entry["executable"].as_bool() == Some(true) { 0o755 } else { 0o644 };
+            fs::set_permissions(&target, fs::Permissions::from_mode(mode)).with_path(&target)?;
+        }
+    }
+
+    if blob_dir.exists() {
+        fs::remove_dir_all(&blob_dir).with_path(&blob_dir)?;
+    }
+    fs::remove_file(&map_path).with_path(&map_path)?;
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|meta| meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    false
+}
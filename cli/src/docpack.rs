@@ -0,0 +1,413 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// How many top-level entries to list when reporting what was found instead.
+const SHOWN_ENTRIES: usize = 8;
+
+/// Where a docpack lives on disk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DocpackKind {
+    /// A directory containing `docpack.json`
+    Directory,
+    /// A zip archive with `docpack.json` at its root
+    Archive,
+}
+
+/// A path known to hold a docpack.
+#[derive(Clone, Debug)]
+pub struct Docpack {
+    pub path: PathBuf,
+    pub kind: DocpackKind,
+}
+
+/// Why a path could not be opened as a docpack.
+#[derive(Debug)]
+pub enum OpenError {
+    NotFound(PathBuf),
+    /// The path exists but is something else. `found` describes what it is
+    /// and `suggestion` is a command that would turn it into a docpack.
+    NotADocpack {
+        path: PathBuf,
+        found: String,
+        suggestion: Option<String>,
+    },
+    Io(io::Error),
+}
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenError::NotFound(path) => write!(f, "Docpack does not exist: {}", path.display()),
+            OpenError::NotADocpack { path, found, suggestion } => {
+                write!(f, "Not a .docpack: {}\n{}", path.display(), found)?;
+                if let Some(command) = suggestion {
+                    write!(f, "\n\nTo create a docpack from it, run:\n  {}", command)?;
+                }
+                Ok(())
+            }
+            OpenError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for OpenError {}
+
+impl From<io::Error> for OpenError {
+    fn from(e: io::Error) -> Self {
+        OpenError::Io(e)
+    }
+}
+
+impl Docpack {
+    /// Open a docpack directory or archive, explaining what the path is
+    /// instead when it is not one.
+    pub fn open_any(path: &Path) -> Result<Docpack, OpenError> {
+        if !path.exists() {
+            return Err(OpenError::NotFound(path.to_path_buf()));
+        }
+
+        if path.is_dir() {
+            return open_dir(path);
+        }
+
+        let mut magic = [0u8; 512];
+        let len = read_prefix(path, &mut magic)?;
+        let magic = &magic[..len];
+
+        if magic.starts_with(b"PK\x03\x04") || magic.starts_with(b"PK\x05\x06") {
+            return open_zip(path);
+        }
+
+        let (found, suggestion) = if magic.starts_with(&[0x1f, 0x8b]) {
+            (
+                "This is a gzip-compressed file (likely a .tar.gz tarball), not a zip archive.".to_string(),
+                Some(extract_and_ingest(path, "tar -xzf")),
+            )
+        } else if magic.len() >= 262 && &magic[257..262] == b"ustar" {
+            (
+                "This is a tar archive, not a zip archive.".to_string(),
+                Some(extract_and_ingest(path, "tar -xf")),
+            )
+        } else if magic.starts_with(b"BZh") || magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            (
+                "This is a bzip2/xz-compressed file, not a zip archive.".to_string(),
+                Some(extract_and_ingest(path, "tar -xaf")),
+            )
+        } else {
+            ("This file is not a zip archive (docpacks are zip archives or directories).".to_string(), None)
+        };
+
+        Err(OpenError::NotADocpack { path: path.to_path_buf(), found, suggestion })
+    }
+
+    /// Like [`Docpack::open_any`], but only accepts an extracted directory.
+    pub fn open_dir(path: &Path) -> Result<Docpack, OpenError> {
+        let docpack = Docpack::open_any(path)?;
+        if docpack.kind == DocpackKind::Archive {
+            return Err(OpenError::NotADocpack {
+                path: path.to_path_buf(),
+                found: "This is a packed .docpack archive; this command needs an extracted docpack directory."
+                    .to_string(),
+                suggestion: None,
+            });
+        }
+        Ok(docpack)
+    }
+}
+
+fn open_dir(path: &Path) -> Result<Docpack, OpenError> {
+    if path.join("docpack.json").is_file() {
+        return Ok(Docpack { path: path.to_path_buf(), kind: DocpackKind::Directory });
+    }
+
+    let mut entries: Vec<String> = fs::read_dir(path)?
+        .filter_map(|e| e.ok())
+        .map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            if e.path().is_dir() { format!("{}/", name) } else { name }
+        })
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    entries.sort();
+
+    let (found, suggestion) = if entries.is_empty() {
+        ("The directory is empty (no docpack.json).".to_string(), None)
+    } else {
+        (
+            format!("The directory has no docpack.json. Found: {}", summarize(&entries)),
+            Some(ingest_command(path)),
+        )
+    };
+
+    Err(OpenError::NotADocpack { path: path.to_path_buf(), found, suggestion })
+}
+
+fn open_zip(path: &Path) -> Result<Docpack, OpenError> {
+    let archive = match ZipArchive::new(fs::File::open(path)?) {
+        Ok(archive) => archive,
+        Err(e) => {
+            return Err(OpenError::NotADocpack {
+                path: path.to_path_buf(),
+                found: format!("This looks like a zip archive, but it cannot be read ({}).", e),
+                suggestion: Some(format!("localdoc recover {}", shell_quote(&path.display().to_string()))),
+            })
+        }
+    };
+
+    if archive.file_names().any(|name| name == "docpack.json") {
+        return Ok(Docpack { path: path.to_path_buf(), kind: DocpackKind::Archive });
+    }
+
+    let mut top_level: Vec<String> = archive
+        .file_names()
+        .filter_map(|name| {
+            let name = crate::archive::normalize_entry_name(name);
+            let name = name.trim_start_matches("./");
+            match name.split_once('/') {
+                Some((dir, _)) => Some(format!("{}/", dir)),
+                None if name.is_empty() => None,
+                None => Some(name.to_string()),
+            }
+        })
+        .collect();
+    top_level.sort();
+    top_level.dedup();
+
+    let found = if top_level.is_empty() {
+        "This zip archive is empty (no docpack.json).".to_string()
+    } else {
+        format!("This zip archive has no docpack.json. Top-level entries: {}", summarize(&top_level))
+    };
+
+    Err(OpenError::NotADocpack {
+        path: path.to_path_buf(),
+        found,
+        suggestion: Some(ingest_command(path)),
+    })
+}
+
+fn read_prefix(path: &Path, buf: &mut [u8]) -> io::Result<usize> {
+    let mut file = fs::File::open(path)?;
+    let mut len = 0;
+    while len < buf.len() {
+        match file.read(&mut buf[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    Ok(len)
+}
+
+fn summarize(entries: &[String]) -> String {
+    let mut shown = entries.iter().take(SHOWN_ENTRIES).cloned().collect::<Vec<_>>().join(", ");
+    if entries.len() > SHOWN_ENTRIES {
+        shown.push_str(&format!(", ... ({} more)", entries.len() - SHOWN_ENTRIES));
+    }
+    shown
+}
+
+/// Name for a docpack built from `path`: its stem plus `.docpack`, unless
+/// that would be the input itself.
+fn output_name(path: &Path) -> String {
+    let stem = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .map(|n| {
+            ["tar.gz", "tgz", "tar", "zip", "docpack"]
+                .iter()
+                .find_map(|ext| n.strip_suffix(&format!(".{}", ext)).map(String::from))
+                .unwrap_or(n)
+        })
+        .filter(|n| !n.is_empty() && n != "." && n != "..")
+        .unwrap_or_else(|| "out".to_string());
+
+    let name = format!("{}.docpack", stem);
+    if path.file_name().map(|n| n.to_string_lossy() == name).unwrap_or(false) {
+        format!("{}-ingested.docpack", stem)
+    } else {
+        name
+    }
+}
+
+fn ingest_command(path: &Path) -> String {
+    format!(
+        "localdoc ingest {} -o {}",
+        shell_quote(&path.display().to_string()),
+        shell_quote(&output_name(path))
+    )
+}
+
+fn extract_and_ingest(path: &Path, tar: &str) -> String {
+    // `ingest` reads tarballs itself when it can tell them by name
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if [".tar", ".tar.gz", ".tgz"].iter().any(|ext| file_name.ends_with(ext)) {
+        return format!(
+            "localdoc ingest {} -o {}",
+            shell_quote(&path.display().to_string()),
+            shell_quote(&output_name(path))
+        );
+    }
+
+    let name = output_name(path);
+    let dir = name.trim_end_matches(".docpack").to_string() + "-src";
+    format!(
+        "mkdir -p {dir} && {tar} {src} -C {dir} && localdoc ingest {dir} -o {out}",
+        dir = shell_quote(&dir),
+        tar = tar,
+        src = shell_quote(&path.display().to_string()),
+        out = shell_quote(&name)
+    )
+}
+
+/// Quote a word for a POSIX shell when it contains anything unusual.
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:@+,=".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    fn write_zip(path: &Path, names: &[&str]) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        for name in names {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(b"{}").unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn not_a_docpack(result: Result<Docpack, OpenError>) -> (String, Option<String>) {
+        match result {
+            Err(OpenError::NotADocpack { found, suggestion, .. }) => (found, suggestion),
+            other => panic!("expected NotADocpack, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = Docpack::open_any(&dir.path().join("nope"));
+        assert!(matches!(result, Err(OpenError::NotFound(_))));
+    }
+
+    #[test]
+    fn docpack_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("docpack.json"), "{}").unwrap();
+        let docpack = Docpack::open_any(dir.path()).unwrap();
+        assert_eq!(docpack.kind, DocpackKind::Directory);
+    }
+
+    #[test]
+    fn source_directory_suggests_ingest() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("my project");
+        fs::create_dir_all(src.join("src")).unwrap();
+        fs::write(src.join("Cargo.toml"), "").unwrap();
+
+        let (found, suggestion) = not_a_docpack(Docpack::open_any(&src));
+        assert!(found.contains("Cargo.toml, src/"), "{}", found);
+        assert_eq!(
+            suggestion.unwrap(),
+            format!("localdoc ingest '{}' -o 'my project.docpack'", src.display())
+        );
+    }
+
+    #[test]
+    fn empty_directory_has_no_suggestion() {
+        let dir = tempfile::tempdir().unwrap();
+        let (found, suggestion) = not_a_docpack(Docpack::open_any(dir.path()));
+        assert!(found.contains("empty"));
+        assert!(suggestion.is_none());
+    }
+
+    #[test]
+    fn docpack_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("good.docpack");
+        write_zip(&path, &["docpack.json", "files/a.txt"]);
+        let docpack = Docpack::open_any(&path).unwrap();
+        assert_eq!(docpack.kind, DocpackKind::Archive);
+        assert!(Docpack::open_dir(&path).is_err());
+    }
+
+    #[test]
+    fn source_zip_lists_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("project.zip");
+        write_zip(&path, &["project/README.md", "project/src/main.rs", "LICENSE"]);
+
+        let (found, suggestion) = not_a_docpack(Docpack::open_any(&path));
+        assert!(found.contains("LICENSE, project/"), "{}", found);
+        let expected = format!("localdoc ingest {} -o project.docpack", path.display());
+        assert_eq!(suggestion.unwrap(), expected);
+    }
+
+    #[test]
+    fn gzip_is_named() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("src.tar.gz");
+        fs::write(&path, [0x1f, 0x8b, 0x08, 0x00, 0, 0, 0, 0]).unwrap();
+
+        let (found, suggestion) = not_a_docpack(Docpack::open_any(&path));
+        assert!(found.contains("gzip"));
+        let expected = format!("localdoc ingest {} -o src.docpack", path.display());
+        assert_eq!(suggestion.unwrap(), expected);
+    }
+
+    #[test]
+    fn tar_is_named() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("src.tar");
+        let mut header = vec![0u8; 512];
+        header[257..263].copy_from_slice(b"ustar\0");
+        fs::write(&path, header).unwrap();
+
+        let (found, suggestion) = not_a_docpack(Docpack::open_any(&path));
+        assert!(found.contains("tar archive"));
+        let expected = format!("localdoc ingest {} -o src.docpack", path.display());
+        assert_eq!(suggestion.unwrap(), expected);
+    }
+
+    #[test]
+    fn unknown_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "hello").unwrap();
+
+        let (found, suggestion) = not_a_docpack(Docpack::open_any(&path));
+        assert!(found.contains("not a zip archive"));
+        assert!(suggestion.is_none());
+    }
+
+    #[test]
+    fn damaged_zip_suggests_recover() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.docpack");
+        fs::write(&path, b"PK\x03\x04 truncated").unwrap();
+
+        let (_, suggestion) = not_a_docpack(Docpack::open_any(&path));
+        assert_eq!(suggestion.unwrap(), format!("localdoc recover {}", path.display()));
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(shell_quote("a/b.zip"), "a/b.zip");
+        assert_eq!(shell_quote("it's here"), "'it'\\''s here'");
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::LocaldocError;
+use crate::progress::Progress;
+
+/// Give up on a server that stops sending data for this long.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Whether `source` names a remote file rather than a local path.
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
+
+/// The last path segment of `url`, without query or fragment.
+pub fn file_name(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    let path = path.split_once("://").map(|(_, rest)| rest).unwrap_or(path);
+    let (_, path) = path.split_once('/')?;
+    path.rsplit('/').find(|segment| !segment.is_empty())
+}
+
+/// An HTTP client with localdoc's timeouts that takes its proxy from
+/// `HTTPS_PROXY`/`HTTP_PROXY`.
+pub fn client() -> ureq::AgentBuilder {
+    ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(30))
+        .timeout_read(READ_TIMEOUT)
+        .try_proxy_from_env(true)
+}
+
+/// Stream `url` into the file at `dest`, following redirects, and return
+/// the number of bytes written.
+pub fn fetch(url: &str, dest: &Path, quiet: bool) -> Result<u64, LocaldocError> {
+    let agent = client().build();
+
+    let response = agent.get(url).call().map_err(|e| match e {
+        ureq::Error::Status(code, response) => format!(
+            "Download failed: {} returned HTTP {} {}\nCheck that the URL is correct and publicly accessible",
+            url,
+            code,
+            response.status_text()
+        ),
+        ureq::Error::Transport(transport) => format!(
+            "Could not download {}\nCheck your network connection (proxies are read from HTTPS_PROXY/HTTP_PROXY)",
+            transport
+        ),
+    })?;
+
+    let total = response.header("Content-Length").and_then(|len| len.parse().ok());
+    let mut reader = response.into_reader();
+    let mut file = fs::File::create(dest)?;
+    let mut progress = Progress::bytes("Downloading", total, quiet);
+
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut written = 0u64;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                return Err(format!(
+                    "Download of {} failed after {} bytes: {}\nThe connection was interrupted; try again",
+                    url, written, e
+                )
+                .into())
+            }
+        };
+        file.write_all(&buf[..n])?;
+        written += n as u64;
+        progress.update(0, written);
+    }
+    progress.finish();
+
+    if let Some(total) = total.filter(|&total| total != written) {
+        return Err(format!(
+            "Download of {} is incomplete: received {} of {} bytes; try again",
+            url, written, total
+        )
+        .into());
+    }
+
+    file.flush()?;
+    Ok(written)
+}
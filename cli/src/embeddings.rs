@@ -0,0 +1,312 @@
+use std::io::Read;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::error::LocaldocError;
+
+/// Leading bytes of `index/embeddings.bin`.
+const MAGIC: &[u8; 8] = b"LDEMBEDS";
+
+/// Layout version stored in the `embeddings.bin` header.
+const BIN_VERSION: u32 = 1;
+
+/// Bytes before the first vector: magic, version, dimension, count.
+pub const HEADER_LEN: usize = 24;
+
+pub const BIN_FILE: &str = "embeddings.bin";
+pub const META_FILE: &str = "embeddings.meta.json";
+
+pub const ANALYZER: &str = "chunks";
+pub const ANALYZER_VERSION: &str = "1";
+
+pub const API_BASE_ENV: &str = "LOCALDOC_EMBED_API_BASE";
+pub const API_KEY_ENV: &str = "LOCALDOC_EMBED_API_KEY";
+pub const MODEL_ENV: &str = "LOCALDOC_EMBED_MODEL";
+/// Read when [`API_KEY_ENV`] is unset, since most endpoints are OpenAI's.
+pub const OPENAI_KEY_ENV: &str = "OPENAI_API_KEY";
+
+pub const DEFAULT_API_BASE: &str = "https://api.openai.com/v1";
+pub const DEFAULT_MODEL: &str = "text-embedding-3-small";
+
+/// Attempts after the first for a request that hit a rate limit, a server
+/// error or a dropped connection.
+const MAX_RETRIES: u32 = 6;
+/// Backoff ceiling when the server does not say how long to wait.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Give up on a server that stops sending data for this long.
+const READ_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The slice of a file under `files/` that one vector was computed from.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Chunk {
+    pub path: String,
+    /// Byte offsets into the file; `end` is exclusive
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The `embeddings.bin` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub dimension: usize,
+    pub count: usize,
+}
+
+/// Byte ranges of `text` about `size` bytes long, each starting `overlap`
+/// bytes before the previous one ended. Chunks end after a line break when
+/// one falls in their second half, and never split a character.
+pub fn chunk_ranges(text: &str, size: usize, overlap: usize) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let mut end = floor_boundary(text, (start + size).min(text.len()));
+        if end < text.len() {
+            let half = floor_boundary(text, start + size / 2);
+            if half > start && half < end {
+                if let Some(newline) = text[half..end].rfind('\n') {
+                    end = half + newline + 1;
+                }
+            }
+        }
+        if end <= start {
+            // A single character wider than the chunk size
+            end = ceil_boundary(text, start + 1);
+        }
+        if !text[start..end].trim().is_empty() {
+            ranges.push((start, end));
+        }
+        if end == text.len() {
+            break;
+        }
+        let next = ceil_boundary(text, end.saturating_sub(overlap));
+        start = if next > start { next } else { end };
+    }
+    ranges
+}
+
+fn floor_boundary(text: &str, mut i: usize) -> usize {
+    while !text.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+fn ceil_boundary(text: &str, mut i: usize) -> usize {
+    while !text.is_char_boundary(i) {
+        i += 1;
+    }
+    i
+}
+
+/// Rough token count of `bytes` bytes of text, for cost estimates.
+pub fn estimate_tokens(bytes: usize) -> usize {
+    bytes.div_ceil(4)
+}
+
+/// `embeddings.bin` for `count` vectors of `dimension` floats, packed in
+/// `vectors` row after row.
+pub fn encode(dimension: usize, vectors: &[f32]) -> Vec<u8> {
+    let count = vectors.len().checked_div(dimension).unwrap_or(0);
+    let mut bytes = Vec::with_capacity(HEADER_LEN + vectors.len() * 4);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&BIN_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(dimension as u32).to_le_bytes());
+    bytes.extend_from_slice(&(count as u64).to_le_bytes());
+    for value in vectors {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
+
+/// Parse the header at the start of `bytes`.
+pub fn read_header(bytes: &[u8]) -> Result<Header, String> {
+    if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+        return Err("not an embeddings file (bad magic bytes)".to_string());
+    }
+    let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+    if version != BIN_VERSION {
+        return Err(format!("layout version {} is not supported (expected {})", version, BIN_VERSION));
+    }
+    let dimension = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+    let count = u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize;
+    Ok(Header { dimension, count })
+}
+
+/// The header at the start of an `embeddings.bin` being read, leaving
+/// `reader` at the first vector.
+pub fn read_stream_header(reader: &mut dyn Read) -> Result<Header, String> {
+    let mut bytes = [0u8; HEADER_LEN];
+    reader.read_exact(&mut bytes).map_err(|e| format!("truncated header: {}", e))?;
+    read_header(&bytes)
+}
+
+/// Check that a whole `embeddings.bin` holds as many floats as its header
+/// says.
+pub fn check_length(bytes: &[u8]) -> Result<Header, String> {
+    let header = read_header(bytes)?;
+    let expected = (header.dimension as u64 * header.count as u64 * 4).saturating_add(HEADER_LEN as u64);
+    if bytes.len() as u64 != expected {
+        return Err(format!(
+            "{} vectors of {} dimensions need {} bytes, but the file has {}",
+            header.count,
+            header.dimension,
+            expected,
+            bytes.len()
+        ));
+    }
+    Ok(header)
+}
+
+/// Where to send embedding requests. Flags win over environment variables,
+/// which win over the defaults.
+#[derive(Clone)]
+pub struct ApiSettings {
+    pub api_base: String,
+    pub api_key: Option<String>,
+    pub model: String,
+}
+
+impl ApiSettings {
+    pub fn resolve(api_base: Option<&str>, api_key: Option<&str>, model: Option<&str>) -> ApiSettings {
+        let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        ApiSettings {
+            api_base: api_base
+                .map(String::from)
+                .or_else(|| env(API_BASE_ENV))
+                .unwrap_or_else(|| DEFAULT_API_BASE.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_key: api_key.map(String::from).or_else(|| env(API_KEY_ENV)).or_else(|| env(OPENAI_KEY_ENV)),
+            model: model.map(String::from).or_else(|| env(MODEL_ENV)).unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+        }
+    }
+
+    /// Fail early when requests to OpenAI would go out without a key; other
+    /// endpoints, like a local server, may not need one.
+    pub fn check_key(&self) -> Result<(), String> {
+        if self.api_key.is_none() && self.api_base == DEFAULT_API_BASE {
+            return Err(format!("No API key for {}: pass --api-key or set {}", self.api_base, API_KEY_ENV));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [&'a str],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// An OpenAI-compatible `/embeddings` endpoint, called no more often than
+/// the configured rate and retried when it pushes back.
+pub struct Client {
+    agent: ureq::Agent,
+    settings: ApiSettings,
+    /// Minimum time between the starts of two requests
+    interval: Option<Duration>,
+    last_request: Option<Instant>,
+    /// Requests retried so far
+    pub retries: u32,
+}
+
+impl Client {
+    /// `requests_per_minute` of 0 sends requests as fast as they complete.
+    pub fn new(settings: ApiSettings, requests_per_minute: u32) -> Client {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(30))
+            .timeout_read(READ_TIMEOUT)
+            .try_proxy_from_env(true)
+            .build();
+        let interval = (requests_per_minute > 0).then(|| Duration::from_secs(60) / requests_per_minute);
+        Client { agent, settings, interval, last_request: None, retries: 0 }
+    }
+
+    /// One vector per input, in input order.
+    pub fn embed(&mut self, inputs: &[&str]) -> Result<Vec<Vec<f32>>, LocaldocError> {
+        let url = format!("{}/embeddings", self.settings.api_base);
+        let body = serde_json::to_string(&EmbeddingRequest { model: &self.settings.model, input: inputs })?;
+
+        let mut attempt = 0;
+        let response = loop {
+            self.wait_turn();
+            let mut request = self.agent.post(&url).set("Content-Type", "application/json");
+            if let Some(key) = &self.settings.api_key {
+                request = request.set("Authorization", &format!("Bearer {}", key));
+            }
+            let delay = match request.send_string(&body) {
+                Ok(response) => break response,
+                Err(ureq::Error::Status(code, response)) if (code == 429 || code >= 500) && attempt < MAX_RETRIES => {
+                    retry_after(&response).unwrap_or_else(|| backoff(attempt))
+                }
+                Err(ureq::Error::Status(code, response)) => {
+                    let status = response.status_text().to_string();
+                    let detail = response.into_string().unwrap_or_default();
+                    let hint = match code {
+                        401 | 403 => format!("\nCheck the API key (--api-key, {} or {})", API_KEY_ENV, OPENAI_KEY_ENV),
+                        404 => format!("\nCheck --api-base ({}); it should end before /embeddings", self.settings.api_base),
+                        429 => format!("\nStill rate limited after {} retries; lower --requests-per-minute", MAX_RETRIES),
+                        _ => String::new(),
+                    };
+                    return Err(format!("{} returned HTTP {} {}: {}{}", url, code, status, detail.trim(), hint).into());
+                }
+                Err(ureq::Error::Transport(_)) if attempt < MAX_RETRIES => backoff(attempt),
+                Err(ureq::Error::Transport(transport)) => {
+                    return Err(format!(
+                        "Could not reach {}: {}\nCheck your network connection (proxies are read from HTTPS_PROXY/HTTP_PROXY)",
+                        url, transport
+                    )
+                    .into())
+                }
+            };
+            attempt += 1;
+            self.retries += 1;
+            thread::sleep(delay);
+        };
+
+        let mut response: EmbeddingResponse = serde_json::from_reader(response.into_reader())
+            .map_err(|e| format!("{} returned an unexpected response: {}", url, e))?;
+        if response.data.len() != inputs.len() {
+            return Err(format!("{} returned {} embeddings for {} inputs", url, response.data.len(), inputs.len()).into());
+        }
+        response.data.sort_by_key(|data| data.index);
+        Ok(response.data.into_iter().map(|data| data.embedding).collect())
+    }
+
+    fn wait_turn(&mut self) {
+        if let (Some(interval), Some(last)) = (self.interval, self.last_request) {
+            if let Some(wait) = interval.checked_sub(last.elapsed()) {
+                thread::sleep(wait);
+            }
+        }
+        self.last_request = Some(Instant::now());
+    }
+}
+
+/// The wait a 429 or 503 response asks for in `Retry-After` seconds.
+fn retry_after(response: &ureq::Response) -> Option<Duration> {
+    let secs: f64 = response.header("Retry-After")?.trim().parse().ok()?;
+    Some(Duration::from_secs_f64(secs.clamp(0.0, MAX_BACKOFF.as_secs_f64())))
+}
+
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.min(6)).min(MAX_BACKOFF)
+}
+
+/// Hidden file next to `docpack` where finished batches are kept until
+/// every chunk has a vector.
+pub fn checkpoint_path(docpack: &Path) -> std::path::PathBuf {
+    let name = docpack.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    docpack.with_file_name(format!(".{}.embed-partial", name))
+}
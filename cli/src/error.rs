@@ -0,0 +1,366 @@
+//! The error every command returns, with an exit code per kind of failure
+//! so scripts can tell a missing input from a broken docpack from a
+//! failed container run.
+
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use localdoc::docpack::OpenError;
+
+use crate::commands::registry::TransferError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LocaldocError {
+    /// An input path given on the command line is missing. `what` names it
+    /// the way the command does: "Source directory", "Docpack", ...
+    #[error("{what} does not exist: {}", path.display())]
+    SourceNotFound { what: &'static str, path: PathBuf },
+
+    /// Something named on the command line isn't where the command looked:
+    /// a file in a docpack, a task id, a recorded run, a config key.
+    #[error("{0}")]
+    NotFound(String),
+
+    /// An option has a value the command can't use, or options that don't
+    /// go together; clap's own usage errors never get this far.
+    #[error("{0}")]
+    InvalidArgument(String),
+
+    /// `docpack.json` can't be read or doesn't have the manifest's shape.
+    /// `source` names the file and field.
+    #[error("{source}")]
+    InvalidManifest { path: PathBuf, source: docpack_core::Error },
+
+    /// `tasks.json` can't be read or doesn't have the task list's shape.
+    #[error("{source}")]
+    InvalidTasks { path: PathBuf, source: docpack_core::Error },
+
+    /// The path exists but is not a docpack; the message says what it is.
+    #[error(transparent)]
+    NotADocpack(OpenError),
+
+    /// A zip archive can't be read or extracted.
+    #[error("{}", archive_message(path, source))]
+    Archive { path: PathBuf, source: io::Error },
+
+    /// The agent's container, or a Docker or Podman command, exited
+    /// unsuccessfully. `what` is the command, e.g. "Docker logs", and
+    /// `stderr` what it printed when that was captured.
+    #[error("{what} failed with exit code: {code:?}{}", stderr_suffix(stderr))]
+    ContainerFailed { what: String, code: Option<i32>, stderr: String },
+
+    /// A filesystem operation failed, on `path` when it is known.
+    #[error("{}", io_message(path.as_deref(), source))]
+    Io { path: Option<PathBuf>, source: io::Error },
+
+    /// `localdoc push` or `pull` failed talking to the registry.
+    #[error(transparent)]
+    Transfer(#[from] TransferError),
+
+    /// Anything else: a message for the user, or an error from a library
+    /// that the commands pass on as it is.
+    #[error(transparent)]
+    Other(Box<dyn Error>),
+}
+
+impl LocaldocError {
+    /// The process exit code. 1 is any other failure; 2 is shared with
+    /// clap's usage errors and `localdoc doctor`.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LocaldocError::Transfer(e) => e.exit_code(),
+            LocaldocError::InvalidArgument(_) => 2,
+            LocaldocError::SourceNotFound { .. } | LocaldocError::NotFound(_) => 6,
+            LocaldocError::InvalidManifest { .. } | LocaldocError::InvalidTasks { .. } | LocaldocError::NotADocpack(_) => 7,
+            LocaldocError::Archive { .. } => 8,
+            LocaldocError::ContainerFailed { .. } => 9,
+            LocaldocError::Io { .. } => 10,
+            LocaldocError::Other(_) => 1,
+        }
+    }
+
+    /// The message followed by a "Caused by:" line for each underlying
+    /// error, as printed by `localdoc --verbose`. A cause that reads the
+    /// same as the error it caused, like the one behind `InvalidManifest`,
+    /// is left out.
+    pub fn chain(&self) -> String {
+        let mut text = self.to_string();
+        let mut previous = text.clone();
+        let mut cause = self.source();
+        while let Some(e) = cause {
+            let message = e.to_string();
+            if message != previous {
+                let _ = write!(text, "\nCaused by: {}", message);
+            }
+            previous = message;
+            cause = e.source();
+        }
+        text
+    }
+}
+
+fn archive_message(path: &Path, e: &io::Error) -> String {
+    if e.kind() == io::ErrorKind::InvalidData {
+        format!(
+            "Failed to read zip archive {}: {}\n\
+             The central directory may be truncated or corrupt. Try salvaging it with:\n  \
+             localdoc recover {} -o recovered/",
+            path.display(),
+            e,
+            path.display()
+        )
+    } else {
+        format!("Failed to extract zip archive {}: {}", path.display(), e)
+    }
+}
+
+fn stderr_suffix(stderr: &str) -> String {
+    match stderr.trim() {
+        "" => String::new(),
+        text => format!("\n{}", text),
+    }
+}
+
+fn io_message(path: Option<&Path>, e: &io::Error) -> String {
+    match path {
+        Some(path) => format!("{}: {}", path.display(), e),
+        None => e.to_string(),
+    }
+}
+
+/// Attach the path an I/O error is about.
+pub trait WithPath<T> {
+    fn with_path(self, path: impl AsRef<Path>) -> Result<T, LocaldocError>;
+}
+
+impl<T> WithPath<T> for io::Result<T> {
+    fn with_path(self, path: impl AsRef<Path>) -> Result<T, LocaldocError> {
+        self.map_err(|source| LocaldocError::Io { path: Some(path.as_ref().to_path_buf()), source })
+    }
+}
+
+impl From<io::Error> for LocaldocError {
+    fn from(source: io::Error) -> Self {
+        LocaldocError::Io { path: None, source }
+    }
+}
+
+impl From<OpenError> for LocaldocError {
+    fn from(e: OpenError) -> Self {
+        match e {
+            OpenError::NotFound(path) => LocaldocError::SourceNotFound { what: "Docpack", path },
+            OpenError::Io(source) => LocaldocError::Io { path: None, source },
+            e => LocaldocError::NotADocpack(e),
+        }
+    }
+}
+
+/// Manifest or task list by the file the error names, for errors raised
+/// where the docpack path isn't at hand.
+impl From<docpack_core::Error> for LocaldocError {
+    fn from(source: docpack_core::Error) -> Self {
+        let path = PathBuf::from(&source.file);
+        if path.file_name().is_some_and(|name| name == docpack_core::TASKS_FILE) {
+            LocaldocError::InvalidTasks { path, source }
+        } else {
+            LocaldocError::InvalidManifest { path, source }
+        }
+    }
+}
+
+impl From<String> for LocaldocError {
+    fn from(message: String) -> Self {
+        LocaldocError::Other(message.into())
+    }
+}
+
+impl From<&str> for LocaldocError {
+    fn from(message: &str) -> Self {
+        LocaldocError::Other(message.into())
+    }
+}
+
+impl From<Box<dyn Error>> for LocaldocError {
+    fn from(e: Box<dyn Error>) -> Self {
+        LocaldocError::Other(e)
+    }
+}
+
+/// Library errors the commands pass on with `?`.
+macro_rules! other_from {
+    ($($ty:ty),* $(,)?) => {
+        $(impl From<$ty> for LocaldocError {
+            fn from(e: $ty) -> Self {
+                LocaldocError::Other(Box::new(e))
+            }
+        })*
+    };
+}
+
+other_from!(
+    serde_json::Error,
+    walkdir::Error,
+    globset::Error,
+    notify::Error,
+    Box<bincode::ErrorKind>,
+    std::path::StripPrefixError,
+    std::string::FromUtf8Error,
+);
+
+impl From<std::convert::Infallible> for LocaldocError {
+    fn from(e: std::convert::Infallible) -> Self {
+        match e {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{init, inspect, pack, recover, set};
+    use std::fs;
+    use std::path::Path;
+
+    fn docpack_with_manifest(dir: &Path, manifest: &str) -> PathBuf {
+        let docpack = dir.join("app.docpack");
+        fs::create_dir_all(docpack.join("files")).unwrap();
+        fs::write(docpack.join("docpack.json"), manifest).unwrap();
+        docpack
+    }
+
+    fn inspect(docpack: &Path) -> Result<(), LocaldocError> {
+        inspect::run(docpack.to_str().unwrap(), false, 20, true, None, true)
+    }
+
+    #[test]
+    fn missing_inputs_are_source_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.docpack");
+        match recover::run(missing.to_str().unwrap(), dir.path().join("out").to_str().unwrap()) {
+            Err(LocaldocError::SourceNotFound { what: "Docpack archive", path }) => assert_eq!(path, missing),
+            other => panic!("expected SourceNotFound, got {:?}", other),
+        }
+        assert!(matches!(inspect(&missing), Err(LocaldocError::SourceNotFound { what: "Docpack", .. })));
+    }
+
+    #[test]
+    fn bad_manifests_name_the_docpack_and_field() {
+        let dir = tempfile::tempdir().unwrap();
+        let docpack = docpack_with_manifest(
+            dir.path(),
+            r#"{ "version": "1.0", "environment": { "tools": [], "constraints": { "memory_limit_mb": "lots" } } }"#,
+        );
+        match inspect(&docpack) {
+            Err(LocaldocError::InvalidManifest { path, source }) => {
+                assert_eq!(path, docpack.join("docpack.json"));
+                assert_eq!(source.field.as_deref(), Some("environment.constraints.memory_limit_mb"));
+                assert!(matches!(source.kind, docpack_core::ErrorKind::Invalid(_)));
+            }
+            other => panic!("expected InvalidManifest, got {:?}", other),
+        }
+
+        fs::write(docpack.join("docpack.json"), r#"{ "version": "1.0", "environment": { "tools": [] } }"#).unwrap();
+        fs::write(docpack.join("tasks.json"), r#"{ "tasks": [{ "output": {} }] }"#).unwrap();
+        let error = inspect(&docpack).unwrap_err();
+        assert!(
+            matches!(&error, LocaldocError::InvalidTasks { source, .. } if matches!(source.kind, docpack_core::ErrorKind::Missing)),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn damaged_archives_and_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let damaged = dir.path().join("damaged.docpack");
+        fs::write(&damaged, b"PK\x03\x04 and then nothing a zip reader can use").unwrap();
+        match pack::extract_zip(&damaged, &dir.path().join("out")) {
+            Err(LocaldocError::Archive { path, source }) => {
+                assert_eq!(path, damaged);
+                assert_eq!(source.kind(), io::ErrorKind::InvalidData);
+            }
+            other => panic!("expected Archive, got {:?}", other),
+        }
+
+        let notes = dir.path().join("notes.txt");
+        fs::write(&notes, "not a docpack").unwrap();
+        assert!(matches!(inspect(&notes), Err(LocaldocError::NotADocpack(OpenError::NotADocpack { .. }))));
+    }
+
+    #[test]
+    fn io_errors_carry_the_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        let target = file.join("new.docpack");
+        match init::run(target.to_str().unwrap(), None, false) {
+            Err(LocaldocError::Io { path: Some(path), .. }) => assert_eq!(path, target),
+            other => panic!("expected Io, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn named_things_that_are_missing_and_bad_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        let docpack = dir.path().join("app.docpack");
+        init::run(docpack.to_str().unwrap(), None, false).unwrap();
+        let docpack = docpack.to_str().unwrap();
+
+        assert!(matches!(set::run(docpack, "name..x", "app", false), Err(LocaldocError::InvalidArgument(_))));
+        match crate::context::preview(Path::new(docpack), Some("nope"), 10) {
+            Err(LocaldocError::NotFound(message)) => assert_eq!(message, "No task with id 'nope' in tasks.json"),
+            other => panic!("expected NotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn container_failures_show_what_the_runtime_printed() {
+        let error = LocaldocError::ContainerFailed {
+            what: "Docker run --detach".to_string(),
+            code: Some(125),
+            stderr: "Unable to find image 'nope:latest' locally\n".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Docker run --detach failed with exit code: Some(125)\nUnable to find image 'nope:latest' locally"
+        );
+    }
+
+    #[test]
+    fn exit_codes_tell_failures_apart() {
+        let errors = [
+            LocaldocError::from("anything else"),
+            LocaldocError::InvalidArgument("--chunk-size must be at least 1".to_string()),
+            LocaldocError::NotFound("No task with id 'x' in tasks.json".to_string()),
+            LocaldocError::SourceNotFound { what: "Docpack", path: PathBuf::from("a.docpack") },
+            LocaldocError::Archive { path: PathBuf::from("a.docpack"), source: io::ErrorKind::InvalidData.into() },
+            LocaldocError::ContainerFailed { what: "docker command".to_string(), code: Some(1), stderr: String::new() },
+            LocaldocError::from(io::Error::from(io::ErrorKind::PermissionDenied)),
+        ];
+        let codes: Vec<i32> = errors.iter().map(LocaldocError::exit_code).collect();
+        assert_eq!(codes, [1, 2, 6, 6, 8, 9, 10]);
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("connection refused")]
+    struct Refused;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("could not reach the registry")]
+    struct Unreachable(#[source] Refused);
+
+    #[test]
+    fn chain_lists_causes_not_already_shown() {
+        let error = LocaldocError::Other(Box::new(Unreachable(Refused)));
+        assert_eq!(error.chain(), "could not reach the registry\nCaused by: connection refused");
+
+        let error = LocaldocError::from(docpack_core::Manifest::from_slice(b"{", "docpack.json", docpack_core::Strictness::Lenient).unwrap_err());
+        assert!(matches!(error, LocaldocError::InvalidManifest { .. }));
+        let chain = error.chain();
+        let lines: Vec<&str> = chain.lines().collect();
+        assert!(lines[0].starts_with("Invalid JSON in docpack.json: "), "{}", chain);
+        assert!(lines[1].starts_with("Caused by: EOF while parsing"), "{}", chain);
+        assert_eq!(lines.len(), 2);
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+use std::path::Path;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde_json::{json, Value};
+use crate::error::LocaldocError;
+
+/// Dependency and build directories left out of every docpack unless
+/// `--no-default-excludes` is given. Each matches a directory of that name
+/// at any depth.
+pub const DEFAULT_EXCLUDES: &[&str] = &["node_modules", "target", ".git", "__pycache__", ".venv", "dist", "build", ".next"];
+
+/// Project-local exclusions read from the source root, in gitignore syntax.
+pub const IGNORE_FILE: &str = ".docpackignore";
+
+/// Why a source path is left out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Skip {
+    /// Matched an `--exclude` glob
+    Excluded,
+    /// Matched no `--include` glob
+    NotIncluded,
+    /// Matched a pattern in [`IGNORE_FILE`]
+    Ignored,
+    /// Under one of the [`DEFAULT_EXCLUDES`] directories
+    Default(&'static str),
+}
+
+impl fmt::Display for Skip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Skip::Excluded => write!(f, "matches --exclude"),
+            Skip::NotIncluded => write!(f, "matches no --include"),
+            Skip::Ignored => write!(f, "matches {}", IGNORE_FILE),
+            Skip::Default(rule) => write!(f, "under {}/, a default exclude", rule),
+        }
+    }
+}
+
+/// Decides which source files go into a docpack, from `--include` and
+/// `--exclude` globs matched against paths relative to the source root,
+/// the source's [`IGNORE_FILE`], and the [`DEFAULT_EXCLUDES`] directories.
+///
+/// Precedence, highest first: `--exclude`, then the ignore file (whose
+/// `!` patterns can bring back a default-excluded directory), then the
+/// defaults, then `--include`.
+#[derive(Clone)]
+pub struct SourceFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    default_excludes: bool,
+    ignore_file: Option<Gitignore>,
+    patterns: (Vec<String>, Vec<String>),
+}
+
+impl SourceFilter {
+    /// Build a filter. With no include globs every file is included;
+    /// excludes always win over includes.
+    pub fn new(include: &[String], exclude: &[String], default_excludes: bool) -> Result<Self, LocaldocError> {
+        Ok(SourceFilter {
+            include: if include.is_empty() { None } else { Some(build_set(include)?) },
+            exclude: build_set(exclude)?,
+            default_excludes,
+            ignore_file: None,
+            patterns: (include.to_vec(), exclude.to_vec()),
+        })
+    }
+
+    /// Read [`IGNORE_FILE`] from `root` if there is one, returning whether
+    /// it was found.
+    pub fn load_ignore_file(&mut self, root: &Path) -> Result<bool, LocaldocError> {
+        let path = root.join(IGNORE_FILE);
+        if !path.is_file() {
+            self.ignore_file = None;
+            return Ok(false);
+        }
+
+        let mut builder = GitignoreBuilder::new(root);
+        if let Some(e) = builder.add(&path) {
+            return Err(format!("Invalid {}: {}", path.display(), e).into());
+        }
+        let ignore = builder.build().map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        self.ignore_file = Some(ignore);
+        Ok(true)
+    }
+
+    /// The rules this filter was built from, for recording in index metadata.
+    pub fn to_json(&self) -> Value {
+        let defaults: &[&str] = if self.default_excludes { DEFAULT_EXCLUDES } else { &[] };
+        json!({
+            "include": self.patterns.0,
+            "exclude": self.patterns.1,
+            "default_excludes": defaults,
+        })
+    }
+
+    /// Whether the source path `rel` is left out, and why. Directories are
+    /// only checked against the exclusion rules, never `--include`.
+    pub fn check(&self, rel: &Path, is_dir: bool) -> Option<Skip> {
+        if self.exclude.is_match(rel) {
+            return Some(Skip::Excluded);
+        }
+
+        let mut whitelisted = false;
+        if let Some(ignore) = &self.ignore_file {
+            let matched = ignore.matched_path_or_any_parents(rel, is_dir);
+            if matched.is_ignore() {
+                return Some(Skip::Ignored);
+            }
+            whitelisted = matched.is_whitelist();
+        }
+
+        if !whitelisted {
+            let dir = if is_dir { Some(rel) } else { rel.parent() };
+            if let Some(rule) = dir.and_then(|dir| self.default_exclude(dir)) {
+                return Some(Skip::Default(rule));
+            }
+        }
+
+        match &self.include {
+            Some(include) if !is_dir && !include.is_match(rel) => Some(Skip::NotIncluded),
+            _ => None,
+        }
+    }
+
+    /// The default rule that leaves out everything under the directory
+    /// `dir` (relative to the source root), if any.
+    fn default_exclude(&self, dir: &Path) -> Option<&'static str> {
+        if !self.default_excludes {
+            return None;
+        }
+        dir.components().find_map(|component| {
+            let name = component.as_os_str().to_str()?;
+            DEFAULT_EXCLUDES.iter().copied().find(|rule| *rule == name)
+        })
+    }
+}
+
+fn build_set(patterns: &[String]) -> Result<GlobSet, LocaldocError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| format!("Invalid glob '{}': {}", pattern, e))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
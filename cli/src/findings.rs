@@ -0,0 +1,199 @@
+use std::fmt;
+use serde::Serialize;
+
+/// A kind of validation finding. Codes are stable: once published, a code
+/// keeps its meaning and is never reused, so CI can match on it.
+#[derive(Clone, Copy, Debug)]
+pub struct Code {
+    pub id: &'static str,
+    pub name: &'static str,
+}
+
+const fn code(id: &'static str, name: &'static str) -> Code {
+    Code { id, name }
+}
+
+pub const MISSING_MANIFEST: Code = code("DP001", "missing-manifest");
+pub const INVALID_MANIFEST: Code = code("DP002", "invalid-manifest");
+pub const MANIFEST_SCHEMA: Code = code("DP003", "manifest-schema");
+pub const UNREADABLE_FILE: Code = code("DP004", "unreadable-file");
+pub const PATH_NOT_FOUND: Code = code("DP005", "path-not-found");
+pub const UNREADABLE_DOCPACK: Code = code("DP006", "unreadable-docpack");
+pub const MISSING_FILES_DIR: Code = code("DP007", "missing-files-dir");
+pub const MISSING_INDEX_DIR: Code = code("DP008", "missing-index-dir");
+pub const MISSING_OUTPUT_DIR: Code = code("DP009", "missing-output-dir");
+pub const MISSING_TASKS: Code = code("DP010", "missing-tasks");
+pub const INVALID_TASKS: Code = code("DP011", "invalid-tasks");
+pub const TASKS_SCHEMA: Code = code("DP012", "tasks-schema");
+pub const MISSING_NAME: Code = code("DP013", "missing-name");
+pub const UNKNOWN_TOOL: Code = code("DP014", "unknown-tool");
+pub const UNLIKELY_CONSTRAINT: Code = code("DP015", "unlikely-constraint");
+pub const OUTPUT_LIMITS: Code = code("DP016", "output-limits");
+pub const INCOMPLETE_SEAL: Code = code("DP017", "incomplete-seal");
+pub const STALE_OUTPUTS: Code = code("DP018", "stale-outputs");
+pub const MISSING_MISSION: Code = code("DP019", "missing-mission");
+pub const MISSING_TASK_LIST: Code = code("DP020", "missing-task-list");
+pub const TASK_MISSING_NAME: Code = code("DP021", "task-missing-name");
+pub const TASK_MISSING_DESCRIPTION: Code = code("DP022", "task-missing-description");
+pub const TOOL_NOT_IN_ENVIRONMENT: Code = code("DP023", "tool-not-in-environment");
+pub const INVALID_INDEX: Code = code("DP024", "invalid-index");
+pub const DUPLICATE_INDEX: Code = code("DP025", "duplicate-index");
+pub const EMBEDDINGS_MISMATCH: Code = code("DP026", "embeddings-mismatch");
+pub const INCOMPATIBLE_INDEX: Code = code("DP027", "incompatible-index");
+pub const STALE_INDEX: Code = code("DP028", "stale-index");
+pub const INTERNAL_ERROR: Code = code("DP029", "internal-error");
+pub const PLUGIN_FAILED: Code = code("DP030", "plugin-failed");
+pub const UNSAFE_ENTRY_PATH: Code = code("DP031", "unsafe-entry-path");
+pub const DUPLICATE_ENTRY: Code = code("DP032", "duplicate-entry");
+pub const SUSPICIOUS_SIZE: Code = code("DP033", "suspicious-size");
+pub const OUTPUT_OUTSIDE_OUTPUT_DIR: Code = code("DP034", "output-outside-output-dir");
+pub const DUPLICATE_TASK_ID: Code = code("DP035", "duplicate-task-id");
+pub const TOOL_WITHOUT_INDEX: Code = code("DP036", "tool-without-index");
+pub const DUPLICATE_GRAPH_NODE: Code = code("DP037", "duplicate-graph-node");
+pub const DANGLING_GRAPH_EDGE: Code = code("DP038", "dangling-graph-edge");
+pub const INDEX_NOT_CHECKED: Code = code("DP039", "index-not-checked");
+pub const UNSUPPORTED_VERSION: Code = code("DP040", "unsupported-version");
+pub const NEWER_VERSION: Code = code("DP041", "newer-version");
+pub const BROKEN_SEAL: Code = code("DP042", "broken-seal");
+
+/// Every built-in code, in order.
+pub const ALL: &[Code] = &[
+    MISSING_MANIFEST,
+    INVALID_MANIFEST,
+    MANIFEST_SCHEMA,
+    UNREADABLE_FILE,
+    PATH_NOT_FOUND,
+    UNREADABLE_DOCPACK,
+    MISSING_FILES_DIR,
+    MISSING_INDEX_DIR,
+    MISSING_OUTPUT_DIR,
+    MISSING_TASKS,
+    INVALID_TASKS,
+    TASKS_SCHEMA,
+    MISSING_NAME,
+    UNKNOWN_TOOL,
+    UNLIKELY_CONSTRAINT,
+    OUTPUT_LIMITS,
+    INCOMPLETE_SEAL,
+    STALE_OUTPUTS,
+    MISSING_MISSION,
+    MISSING_TASK_LIST,
+    TASK_MISSING_NAME,
+    TASK_MISSING_DESCRIPTION,
+    TOOL_NOT_IN_ENVIRONMENT,
+    INVALID_INDEX,
+    DUPLICATE_INDEX,
+    EMBEDDINGS_MISMATCH,
+    INCOMPATIBLE_INDEX,
+    STALE_INDEX,
+    INTERNAL_ERROR,
+    PLUGIN_FAILED,
+    UNSAFE_ENTRY_PATH,
+    DUPLICATE_ENTRY,
+    SUSPICIOUS_SIZE,
+    OUTPUT_OUTSIDE_OUTPUT_DIR,
+    DUPLICATE_TASK_ID,
+    TOOL_WITHOUT_INDEX,
+    DUPLICATE_GRAPH_NODE,
+    DANGLING_GRAPH_EDGE,
+    INDEX_NOT_CHECKED,
+    UNSUPPORTED_VERSION,
+    NEWER_VERSION,
+    BROKEN_SEAL,
+];
+
+/// One problem found in a docpack, with where it is when that is known.
+#[derive(Clone, Debug, Serialize)]
+pub struct Finding {
+    /// Stable code, e.g. `DP014`, or the code a plugin reported
+    pub code: String,
+    /// Readable form of the code, e.g. `unknown-tool`; plugins have none
+    /// and repeat the code
+    pub name: String,
+    pub message: String,
+    /// The plugin that reported the finding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
+    /// Path of the file within the docpack, e.g. `docpack.json`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// JSON pointer into `file`, e.g. `/environment/tools/3`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+}
+
+impl Finding {
+    pub fn new(code: Code, message: impl Into<String>) -> Self {
+        Finding {
+            code: code.id.to_string(),
+            name: code.name.to_string(),
+            message: message.into(),
+            plugin: None,
+            file: None,
+            pointer: None,
+        }
+    }
+
+    /// A finding reported by a plugin, under the plugin's own code.
+    pub fn from_plugin(plugin: &str, code: &str, message: &str) -> Self {
+        Finding {
+            code: code.to_string(),
+            name: code.to_string(),
+            message: message.to_string(),
+            plugin: Some(plugin.to_string()),
+            file: None,
+            pointer: None,
+        }
+    }
+
+    pub fn in_file(mut self, file: impl Into<String>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn at(mut self, pointer: impl Into<String>) -> Self {
+        self.pointer = Some(pointer.into());
+        self
+    }
+
+    /// Whether `--ignore <selector>` suppresses this finding; the selector
+    /// is a code (`DP014`) or its name (`unknown-tool`).
+    pub fn matches(&self, selector: &str) -> bool {
+        self.code.eq_ignore_ascii_case(selector) || self.name.eq_ignore_ascii_case(selector)
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.plugin {
+            Some(plugin) => write!(f, "[plugin {}] {}: {}", plugin, self.code, self.message),
+            None => write!(f, "[{}] {}", self.code, self.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn codes_are_unique_and_in_order() {
+        let ids: HashSet<&str> = ALL.iter().map(|code| code.id).collect();
+        let names: HashSet<&str> = ALL.iter().map(|code| code.name).collect();
+        assert_eq!(ids.len(), ALL.len());
+        assert_eq!(names.len(), ALL.len());
+        for (i, code) in ALL.iter().enumerate() {
+            assert_eq!(code.id, format!("DP{:03}", i + 1));
+        }
+    }
+
+    #[test]
+    fn ignore_matches_code_or_name() {
+        let finding = Finding::new(UNKNOWN_TOOL, "docpack.json: unknown tool 'x'");
+        assert!(finding.matches("DP014"));
+        assert!(finding.matches("dp014"));
+        assert!(finding.matches("unknown-tool"));
+        assert!(!finding.matches("DP001"));
+    }
+}
@@ -0,0 +1,166 @@
+use serde_json::{json, Value};
+
+use crate::reader::DocpackReader;
+use crate::search::{self, FilesSnapshot, IndexFormat};
+
+/// Layout version of the search index (`index/search.json` or
+/// `index/search.bin`) written by this CLI.
+pub const SEARCH_FORMAT_VERSION: u64 = 2;
+
+/// Layout version of `index/graph.json` written by this CLI.
+pub const GRAPH_FORMAT_VERSION: u64 = 2;
+
+/// Layout version of `index/embeddings.bin` and `index/embeddings.meta.json`
+/// written by this CLI.
+pub const EMBEDDINGS_FORMAT_VERSION: u64 = 1;
+
+/// How to regenerate index artifacts, appended to compatibility warnings.
+pub const REBUILD_HINT: &str = "rebuild the index with `localdoc index <docpack> --force`";
+
+/// Index artifacts that carry a fingerprint, with the format version this
+/// CLI reads for each.
+const ARTIFACTS: &[(&str, u64)] = &[
+    ("search.json", SEARCH_FORMAT_VERSION),
+    ("search.bin", SEARCH_FORMAT_VERSION),
+    ("graph.json", GRAPH_FORMAT_VERSION),
+    ("embeddings.meta.json", EMBEDDINGS_FORMAT_VERSION),
+];
+
+/// Record of the toolchain that produced an index artifact, stored as
+/// `metadata.fingerprint` in the artifact.
+pub fn fingerprint(format_version: u64, analyzer: &str, analyzer_version: &str, options: Value) -> Value {
+    json!({
+        "cli_version": env!("CARGO_PKG_VERSION"),
+        "format_version": format_version,
+        "analyzer": {
+            "name": analyzer,
+            "version": analyzer_version,
+        },
+        "options": options,
+    })
+}
+
+/// One-line summary of a fingerprint for display.
+pub fn describe(fingerprint: &Value) -> String {
+    let mut text = format!(
+        "localdoc {}, format v{}, analyzer {} {}",
+        fingerprint["cli_version"].as_str().unwrap_or("?"),
+        fingerprint["format_version"].as_u64().map(|v| v.to_string()).unwrap_or("?".to_string()),
+        fingerprint["analyzer"]["name"].as_str().unwrap_or("?"),
+        fingerprint["analyzer"]["version"].as_str().unwrap_or("?"),
+    );
+    if let Some(options) = fingerprint["options"].as_object().filter(|o| !o.is_empty()) {
+        let options: Vec<String> = options.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        text.push_str(&format!(" ({})", options.join(", ")));
+    }
+    text
+}
+
+/// Fingerprints of the index artifacts present in the docpack's `index/`,
+/// by file name. Artifacts without a fingerprint map to `Value::Null`.
+pub fn read_all(docpack: &mut dyn DocpackReader) -> Vec<(String, Value)> {
+    ARTIFACTS
+        .iter()
+        .filter_map(|(name, _)| {
+            let format = if name.ends_with(".bin") { IndexFormat::Binary } else { IndexFormat::Json };
+            let metadata = search::read_metadata(docpack.open_file(&format!("index/{}", name)).ok()?, format)?;
+            Some((name.to_string(), metadata["fingerprint"].clone()))
+        })
+        .collect()
+}
+
+/// Warnings for index artifacts that are incompatible with this CLI or
+/// with each other, each with the path of the artifact it is about.
+pub fn check(docpack: &mut dyn DocpackReader) -> Vec<(String, String)> {
+    let mut warnings = Vec::new();
+    let fingerprints = read_all(docpack);
+
+    for (name, fingerprint) in &fingerprints {
+        let supported = ARTIFACTS.iter().find(|(n, _)| n == name).map(|(_, v)| *v).unwrap_or(0);
+        let file = format!("index/{}", name);
+
+        if fingerprint.is_null() {
+            warnings.push((
+                file,
+                format!("index/{}: no toolchain fingerprint (built by an older localdoc); {}", name, REBUILD_HINT),
+            ));
+            continue;
+        }
+
+        match fingerprint["format_version"].as_u64() {
+            Some(version) if version > supported => warnings.push((file.clone(), format!(
+                "index/{}: format v{} is newer than this localdoc supports (v{}); upgrade localdoc or {}",
+                name, version, supported, REBUILD_HINT
+            ))),
+            Some(version) if version < supported => warnings.push((file.clone(), format!(
+                "index/{}: format v{} is older than the current v{}; {}",
+                name, version, supported, REBUILD_HINT
+            ))),
+            Some(_) => {}
+            None => warnings.push((file.clone(), format!("index/{}: fingerprint has no format_version", name))),
+        }
+
+        let built_by = fingerprint["cli_version"].as_str().unwrap_or("?");
+        if major(built_by) != major(env!("CARGO_PKG_VERSION")) {
+            warnings.push((file, format!(
+                "index/{}: built by localdoc {}, which may not be compatible with this localdoc ({}); {}",
+                name,
+                built_by,
+                env!("CARGO_PKG_VERSION"),
+                REBUILD_HINT
+            )));
+        }
+    }
+
+    // Artifacts from different CLI builds may disagree on paths and tokens
+    let versions: Vec<(&str, &str)> = fingerprints
+        .iter()
+        .filter_map(|(name, fp)| fp["cli_version"].as_str().map(|v| (name.as_str(), v)))
+        .collect();
+    if versions.windows(2).any(|pair| pair[0].1 != pair[1].1) {
+        let listed: Vec<String> = versions.iter().map(|(name, v)| format!("{} {}", name, v)).collect();
+        warnings.push((
+            "index/".to_string(),
+            format!(
+                "index/: artifacts were built by different localdoc versions ({}); {}",
+                listed.join(", "),
+                REBUILD_HINT
+            ),
+        ));
+    }
+
+    warnings
+}
+
+/// Why the search index no longer matches `files/`, if it doesn't: files
+/// were added, removed or resized since the snapshot recorded in its
+/// metadata, or, in a directory, modified after the index was written.
+/// Only the file listing and timestamps are looked at; nothing is hashed.
+pub fn staleness(docpack: &mut dyn DocpackReader) -> Option<String> {
+    let format = IndexFormat::ALL.into_iter().find(|format| docpack.exists(&format!("index/{}", format.file_name())))?;
+    let index_name = format!("index/{}", format.file_name());
+    let metadata = search::read_metadata(docpack.open_file(&index_name).ok()?, format)?;
+
+    if let Ok(snapshot) = serde_json::from_value::<FilesSnapshot>(metadata["files"].clone()) {
+        let current = docpack.list_files("files");
+        if current.len() != snapshot.count {
+            return Some(format!(
+                "files/ has {} files; {} was built from {}",
+                current.len(),
+                index_name,
+                snapshot.count
+            ));
+        }
+        if FilesSnapshot::of(&current).digest != snapshot.digest {
+            return Some(format!("files in files/ were added, removed or resized since {} was built", index_name));
+        }
+    }
+
+    let indexed = docpack.modified(&index_name)?;
+    let (path, modified) = docpack.newest_under("files")?;
+    (modified > indexed).then(|| format!("files/{} was modified after {} was written", path, index_name))
+}
+
+fn major(version: &str) -> &str {
+    version.split('.').next().unwrap_or(version)
+}
@@ -0,0 +1,849 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::{BufReader, Read};
+use serde::{Deserialize, Serialize};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+/// Name recorded in the graph fingerprint.
+pub const ANALYZER: &str = "syn+imports";
+/// Bumped when the nodes or edges extracted from the same source change.
+pub const ANALYZER_VERSION: &str = "2";
+
+/// Extensions of the JavaScript and TypeScript files read for imports.
+pub const SCRIPT_EXTENSIONS: &[&str] = &["ts", "tsx", "mts", "cts", "js", "jsx", "mjs", "cjs"];
+
+/// A definition in `index/graph.json`. Ids are `<path>::<item>`, with
+/// nested items joined by `::` and trait impl methods written as
+/// `<path>::<Type as Trait>::<method>`. A file's own module uses the bare
+/// path as its id. Packages imported from outside the docpack are
+/// `external:<name>`.
+#[derive(Serialize)]
+pub struct Node {
+    pub id: String,
+    /// `module`, `struct`, `enum`, `trait`, `function`, `file` (a file
+    /// imported by a script that isn't source code itself) or `external`
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub name: String,
+    /// Path under `files/`; absent for external packages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_start: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_end: Option<usize>,
+}
+
+impl Node {
+    /// The module node of a whole source file.
+    fn module(path: &str, name: String, content: &str) -> Node {
+        Node {
+            id: path.to_string(),
+            kind: "module",
+            name,
+            path: Some(path.to_string()),
+            line_start: Some(1),
+            line_end: Some(content.lines().count().max(1)),
+        }
+    }
+}
+
+/// A relation between two nodes: `contains`, `implements`, `imports` or
+/// `calls`.
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+}
+
+/// The graph of every Rust file in a docpack.
+pub struct Graph {
+    /// Sorted by id
+    pub nodes: Vec<Node>,
+    pub edges: BTreeSet<Edge>,
+    /// Rust files syn could not parse, which are left out
+    pub unparsed: Vec<String>,
+}
+
+/// A node or edge of an `index/graph.json` that breaks its id rules.
+#[derive(Debug, PartialEq)]
+pub enum Defect {
+    /// Node `index` repeats the id of an earlier node
+    DuplicateNode { index: usize, id: String },
+    /// Edge `index` has an end, `from` or `to`, that is no node's id
+    DanglingEdge { index: usize, end: &'static str, id: String },
+}
+
+impl std::fmt::Display for Defect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Defect::DuplicateNode { id, .. } => write!(f, "node id '{}' is used more than once", id),
+            Defect::DanglingEdge { end, id, .. } => write!(f, "edge {} '{}' is not a node", end, id),
+        }
+    }
+}
+
+/// Read an `index/graph.json` from `reader` and return its defects. Only
+/// ids and types are kept while reading, so large graphs stay cheap.
+pub fn check(reader: impl Read) -> serde_json::Result<Vec<Defect>> {
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct NodeOutline {
+        id: String,
+        #[serde(rename = "type")]
+        kind: String,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct EdgeOutline {
+        from: String,
+        to: String,
+        #[serde(rename = "type")]
+        kind: String,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Outline {
+        nodes: Vec<NodeOutline>,
+        edges: Vec<EdgeOutline>,
+    }
+
+    let outline: Outline = serde_json::from_reader(BufReader::new(reader))?;
+    let mut defects = Vec::new();
+    let mut ids = HashSet::new();
+    for (index, node) in outline.nodes.iter().enumerate() {
+        if !ids.insert(node.id.as_str()) {
+            defects.push(Defect::DuplicateNode { index, id: node.id.clone() });
+        }
+    }
+    for (index, edge) in outline.edges.iter().enumerate() {
+        for (end, id) in [("from", &edge.from), ("to", &edge.to)] {
+            if !ids.contains(id.as_str()) {
+                defects.push(Defect::DanglingEdge { index, end, id: id.clone() });
+            }
+        }
+    }
+    Ok(defects)
+}
+
+/// What one Rust file defines and refers to. Names used across files are
+/// resolved by [`link`] once every file has been read.
+pub struct FileGraph {
+    /// Crate the file belongs to and its module path within it
+    module: (String, Vec<String>),
+    nodes: Vec<Node>,
+    /// `contains` and `calls` edges, which never leave the file
+    edges: Vec<Edge>,
+    /// Inline `mod` blocks, as (module path, node id)
+    modules: Vec<(Vec<String>, String)>,
+    imports: Vec<Import>,
+    impls: Vec<TraitImpl>,
+    /// Files named by Python and script imports
+    file_imports: Vec<FileImport>,
+}
+
+/// A Python or script import, resolved to a file under `files/` by
+/// [`link`].
+enum FileImport {
+    /// `import a.b` or `from ..a import b, c`
+    Python {
+        /// Leading dots of a relative import
+        level: usize,
+        module: Vec<String>,
+        names: Vec<String>,
+    },
+    /// The specifier of an `import`, `export ... from` or `require()`
+    Script(String),
+}
+
+/// A path named by a `use` declaration.
+struct Import {
+    /// Node of the module the declaration is in
+    from: String,
+    /// Module path of that module
+    module: Vec<String>,
+    path: Vec<String>,
+}
+
+/// An `impl Trait for Type` block.
+struct TraitImpl {
+    /// Id prefix of the module the block is in
+    scope: String,
+    self_ty: String,
+    trait_name: String,
+}
+
+/// A function body to look for calls in.
+struct Body<'ast> {
+    id: String,
+    scope: String,
+    /// Node id `Self` refers to, in impl and trait blocks
+    self_ty: Option<String>,
+    block: &'ast syn::Block,
+}
+
+/// The crate a file under `files/` belongs to and its module path, by
+/// Cargo's layout: `<crate>/src/a/b.rs` and `<crate>/src/a/b/mod.rs` are
+/// `crate::a::b`, and `lib.rs` or `main.rs` directly under `src/` is the
+/// crate root. Files outside a `src/` directory are roots of their own.
+pub fn module_path(path: &str) -> (String, Vec<String>) {
+    let parts: Vec<&str> = path.split('/').collect();
+    let Some(src) = parts[..parts.len() - 1].iter().rposition(|part| *part == "src") else {
+        return (path.to_string(), Vec::new());
+    };
+    let mut module: Vec<String> = parts[src + 1..].iter().map(|part| part.to_string()).collect();
+    let file = module.pop().unwrap_or_default();
+    let stem = file.strip_suffix(".rs").unwrap_or(&file);
+    let root = module.is_empty() && (stem == "lib" || stem == "main");
+    if !root && stem != "mod" {
+        module.push(stem.to_string());
+    }
+    (parts[..src].join("/"), module)
+}
+
+fn rust_path(module: &[String]) -> String {
+    std::iter::once("crate").chain(module.iter().map(String::as_str)).collect::<Vec<_>>().join("::")
+}
+
+fn lines(ident: &syn::Ident, item: &impl Spanned) -> (usize, usize) {
+    (ident.span().start().line, item.span().end().line)
+}
+
+/// Whether the graph reads files named `name`.
+pub fn is_source(name: &str) -> bool {
+    let extension = name.rsplit_once('.').map(|(_, extension)| extension).unwrap_or_default();
+    extension == "rs" || extension == "py" || SCRIPT_EXTENSIONS.contains(&extension)
+}
+
+/// Read the source of `path`: Rust items, or the imports of a Python or
+/// script file. Returns `None` for other files and Rust that syn can't
+/// parse.
+pub fn parse_file(path: &str, content: &str) -> Option<FileGraph> {
+    let extension = path.rsplit_once('.').map(|(_, extension)| extension).unwrap_or_default();
+    let (name, file_imports) = if extension == "rs" {
+        return parse_rust(path, content);
+    } else if extension == "py" {
+        let name = path.trim_end_matches(".py").trim_end_matches("/__init__").replace('/', ".");
+        (name, python_imports(content))
+    } else if SCRIPT_EXTENSIONS.contains(&extension) {
+        (path.to_string(), script_imports(content).into_iter().map(FileImport::Script).collect())
+    } else {
+        return None;
+    };
+
+    Some(FileGraph {
+        module: (path.to_string(), Vec::new()),
+        nodes: vec![Node::module(path, name, content)],
+        edges: Vec::new(),
+        modules: Vec::new(),
+        imports: Vec::new(),
+        impls: Vec::new(),
+        file_imports,
+    })
+}
+
+fn parse_rust(path: &str, content: &str) -> Option<FileGraph> {
+    let file = syn::parse_file(content).ok()?;
+    let module = module_path(path);
+
+    let mut collector = Collector {
+        path,
+        graph: FileGraph {
+            module: module.clone(),
+            nodes: vec![Node::module(path, rust_path(&module.1), content)],
+            edges: Vec::new(),
+            modules: Vec::new(),
+            imports: Vec::new(),
+            impls: Vec::new(),
+            file_imports: Vec::new(),
+        },
+        bodies: Vec::new(),
+        methods: Vec::new(),
+    };
+    collector.items(&file.items, path, &module.1);
+
+    let mut graph = collector.graph;
+    let ids: BTreeSet<&str> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+
+    // Methods hang off their type when it's defined in this file
+    for (scope, ty, method) in collector.methods {
+        let from = if ids.contains(ty.as_str()) { ty } else { scope };
+        graph.edges.push(Edge { from, to: method, kind: "contains" });
+    }
+
+    for body in collector.bodies {
+        let mut calls = Calls { body: &body, ids: &ids, found: BTreeSet::new() };
+        calls.visit_block(body.block);
+        for to in calls.found {
+            graph.edges.push(Edge { from: body.id.clone(), to, kind: "calls" });
+        }
+    }
+    Some(graph)
+}
+
+struct Collector<'a, 'ast> {
+    path: &'a str,
+    graph: FileGraph,
+    bodies: Vec<Body<'ast>>,
+    /// (enclosing scope, type id, method id), linked once every type in the
+    /// file is known
+    methods: Vec<(String, String, String)>,
+}
+
+impl<'ast> Collector<'_, 'ast> {
+    fn node(&mut self, scope: &str, id: String, kind: &'static str, name: String, (line_start, line_end): (usize, usize)) {
+        // cfg'd-out duplicates keep the first definition
+        if self.graph.nodes.iter().any(|node| node.id == id) {
+            return;
+        }
+        self.graph.edges.push(Edge { from: scope.to_string(), to: id.clone(), kind: "contains" });
+        let (path, line_start, line_end) = (Some(self.path.to_string()), Some(line_start), Some(line_end));
+        self.graph.nodes.push(Node { id, kind, name, path, line_start, line_end });
+    }
+
+    /// Collect `items`, found in the module with id prefix `scope` and
+    /// module path `module`.
+    fn items(&mut self, items: &'ast [syn::Item], scope: &str, module: &[String]) {
+        for item in items {
+            match item {
+                syn::Item::Mod(item) => {
+                    // `mod name;` is a file of its own
+                    let Some((_, items)) = &item.content else { continue };
+                    let id = format!("{}::{}", scope, item.ident);
+                    let mut inner = module.to_vec();
+                    inner.push(item.ident.to_string());
+                    self.node(scope, id.clone(), "module", rust_path(&inner), lines(&item.ident, item));
+                    self.graph.modules.push((inner.clone(), id.clone()));
+                    self.items(items, &id, &inner);
+                }
+                syn::Item::Struct(item) => {
+                    let id = format!("{}::{}", scope, item.ident);
+                    self.node(scope, id, "struct", item.ident.to_string(), lines(&item.ident, item));
+                }
+                syn::Item::Enum(item) => {
+                    let id = format!("{}::{}", scope, item.ident);
+                    self.node(scope, id, "enum", item.ident.to_string(), lines(&item.ident, item));
+                }
+                syn::Item::Fn(item) => {
+                    let id = format!("{}::{}", scope, item.sig.ident);
+                    self.node(scope, id.clone(), "function", item.sig.ident.to_string(), lines(&item.sig.ident, item));
+                    self.bodies.push(Body { id, scope: scope.to_string(), self_ty: None, block: &item.block });
+                }
+                syn::Item::Trait(item) => {
+                    let trait_id = format!("{}::{}", scope, item.ident);
+                    self.node(scope, trait_id.clone(), "trait", item.ident.to_string(), lines(&item.ident, item));
+                    for member in &item.items {
+                        let syn::TraitItem::Fn(method) = member else { continue };
+                        let id = format!("{}::{}", trait_id, method.sig.ident);
+                        let name = method.sig.ident.to_string();
+                        self.node(&trait_id, id.clone(), "function", name, lines(&method.sig.ident, method));
+                        if let Some(block) = &method.default {
+                            let self_ty = Some(trait_id.clone());
+                            self.bodies.push(Body { id, scope: scope.to_string(), self_ty, block });
+                        }
+                    }
+                }
+                syn::Item::Impl(item) => self.impl_block(item, scope),
+                syn::Item::Use(item) => {
+                    let mut paths = Vec::new();
+                    flatten_use(&item.tree, Vec::new(), &mut paths);
+                    for path in paths {
+                        self.graph.imports.push(Import { from: scope.to_string(), module: module.to_vec(), path });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn impl_block(&mut self, item: &'ast syn::ItemImpl, scope: &str) {
+        let syn::Type::Path(self_ty) = &*item.self_ty else { return };
+        let Some(ty) = self_ty.path.segments.last().map(|segment| segment.ident.to_string()) else { return };
+        let trait_name = item
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|segment| segment.ident.to_string());
+
+        let ty_id = format!("{}::{}", scope, ty);
+        let prefix = match &trait_name {
+            Some(trait_name) => format!("{}::<{} as {}>", scope, ty, trait_name),
+            None => ty_id.clone(),
+        };
+        for member in &item.items {
+            let syn::ImplItem::Fn(method) = member else { continue };
+            let id = format!("{}::{}", prefix, method.sig.ident);
+            if self.graph.nodes.iter().any(|node| node.id == id) {
+                continue;
+            }
+            let (line_start, line_end) = lines(&method.sig.ident, method);
+            self.graph.nodes.push(Node {
+                id: id.clone(),
+                kind: "function",
+                name: method.sig.ident.to_string(),
+                path: Some(self.path.to_string()),
+                line_start: Some(line_start),
+                line_end: Some(line_end),
+            });
+            self.methods.push((scope.to_string(), ty_id.clone(), id.clone()));
+            let self_ty = Some(ty_id.clone());
+            self.bodies.push(Body { id, scope: scope.to_string(), self_ty, block: &method.block });
+        }
+
+        if let Some(trait_name) = trait_name {
+            self.graph.impls.push(TraitImpl { scope: scope.to_string(), self_ty: ty, trait_name });
+        }
+    }
+}
+
+/// Every path a `use` tree names, with renames and globs reduced to the
+/// path they import from.
+fn flatten_use(tree: &syn::UseTree, mut prefix: Vec<String>, out: &mut Vec<Vec<String>>) {
+    match tree {
+        syn::UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            flatten_use(&path.tree, prefix, out);
+        }
+        syn::UseTree::Name(name) => {
+            prefix.push(name.ident.to_string());
+            out.push(prefix);
+        }
+        syn::UseTree::Rename(rename) => {
+            prefix.push(rename.ident.to_string());
+            out.push(prefix);
+        }
+        syn::UseTree::Glob(_) => out.push(prefix),
+        syn::UseTree::Group(group) => {
+            for tree in &group.items {
+                flatten_use(tree, prefix.clone(), out);
+            }
+        }
+    }
+}
+
+/// Finds the calls in one function body that name a function defined in
+/// the same file.
+struct Calls<'a, 'ast> {
+    body: &'a Body<'ast>,
+    ids: &'a BTreeSet<&'a str>,
+    found: BTreeSet<String>,
+}
+
+impl Calls<'_, '_> {
+    fn resolve(&mut self, segments: &[String]) {
+        let (base, rest) = match segments.split_first() {
+            Some((first, rest)) if first == "Self" => match &self.body.self_ty {
+                Some(self_ty) => (self_ty.clone(), rest),
+                None => return,
+            },
+            _ => (self.body.scope.clone(), segments),
+        };
+        if rest.is_empty() {
+            return;
+        }
+        let id = format!("{}::{}", base, rest.join("::"));
+        if self.ids.contains(id.as_str()) && id != self.body.id {
+            self.found.insert(id);
+        }
+    }
+}
+
+impl<'v> Visit<'v> for Calls<'_, '_> {
+    fn visit_expr_call(&mut self, call: &'v syn::ExprCall) {
+        if let syn::Expr::Path(path) = &*call.func {
+            if path.qself.is_none() {
+                let segments: Vec<String> = path.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+                self.resolve(&segments);
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'v syn::ExprMethodCall) {
+        if let syn::Expr::Path(receiver) = &*call.receiver {
+            if receiver.path.is_ident("self") {
+                self.resolve(&["Self".to_string(), call.method.to_string()]);
+            }
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    // Arguments of format!-style macros are plain expressions
+    fn visit_macro(&mut self, mac: &'v syn::Macro) {
+        let parser = Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated;
+        if let Ok(args) = mac.parse_body_with(parser) {
+            for arg in &args {
+                Visit::visit_expr(self, arg);
+            }
+        }
+    }
+
+    // Nested items have bodies of their own
+    fn visit_item(&mut self, _: &'v syn::Item) {}
+}
+
+/// Join the per-file graphs, resolving `use` declarations to modules,
+/// trait impls to the types and traits they name, and Python and script
+/// imports to the files in `known` (every path under `files/`).
+pub fn link(files: Vec<FileGraph>, known: &BTreeSet<String>, unparsed: Vec<String>) -> Graph {
+    let mut modules: BTreeMap<(&str, &[String]), &str> = BTreeMap::new();
+    for file in &files {
+        modules.insert((file.module.0.as_str(), file.module.1.as_slice()), file.nodes[0].id.as_str());
+        for (path, id) in &file.modules {
+            modules.insert((file.module.0.as_str(), path.as_slice()), id.as_str());
+        }
+    }
+
+    // Types and traits by name, for impls of items defined elsewhere
+    let mut by_name: BTreeMap<(&str, &str), Vec<&str>> = BTreeMap::new();
+    for node in files.iter().flat_map(|file| &file.nodes) {
+        let kind = match node.kind {
+            "struct" | "enum" => "type",
+            "trait" => "trait",
+            _ => continue,
+        };
+        by_name.entry((kind, node.name.as_str())).or_default().push(node.id.as_str());
+    }
+    let all_ids: BTreeSet<&str> = files.iter().flat_map(|file| &file.nodes).map(|node| node.id.as_str()).collect();
+    let find = |kind: &str, scope: &str, name: &str| -> Option<String> {
+        let local = format!("{}::{}", scope, name);
+        if all_ids.contains(local.as_str()) {
+            return Some(local);
+        }
+        match by_name.get(&(kind, name)).map(Vec::as_slice) {
+            Some([only]) => Some(only.to_string()),
+            _ => None,
+        }
+    };
+
+    let files_by_name = FileIndex::new(known);
+    // Imported files that aren't source, and external packages
+    let mut extra: BTreeMap<String, Node> = BTreeMap::new();
+
+    let mut edges = BTreeSet::new();
+    for file in &files {
+        let from = &file.nodes[0].id;
+        for import in &file.file_imports {
+            let targets = match import {
+                FileImport::Python { level, module, names } => files_by_name.python(from, *level, module, names),
+                FileImport::Script(specifier) => vec![files_by_name.script(from, specifier)],
+            };
+            for target in targets {
+                let to = match target {
+                    Target::File(path) => {
+                        if !all_ids.contains(path.as_str()) {
+                            let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+                            extra.entry(path.clone()).or_insert_with(|| Node {
+                                id: path.clone(),
+                                kind: "file",
+                                name,
+                                path: Some(path.clone()),
+                                line_start: None,
+                                line_end: None,
+                            });
+                        }
+                        path
+                    }
+                    Target::External(name) => {
+                        let id = format!("external:{}", name);
+                        extra.entry(id.clone()).or_insert_with(|| Node {
+                            id: id.clone(),
+                            kind: "external",
+                            name,
+                            path: None,
+                            line_start: None,
+                            line_end: None,
+                        });
+                        id
+                    }
+                };
+                if to != *from {
+                    edges.insert(Edge { from: from.clone(), to, kind: "imports" });
+                }
+            }
+        }
+
+        let crate_key = file.module.0.as_str();
+        for import in &file.imports {
+            let Some(target) = absolute(&import.module, &import.path) else { continue };
+            // The longest prefix that is a known module
+            let found = (0..=target.len()).rev().find_map(|len| modules.get(&(crate_key, &target[..len])));
+            if let Some(to) = found.filter(|to| **to != import.from) {
+                edges.insert(Edge { from: import.from.clone(), to: to.to_string(), kind: "imports" });
+            }
+        }
+        for block in &file.impls {
+            let ty = find("type", &block.scope, &block.self_ty);
+            let tr = find("trait", &block.scope, &block.trait_name);
+            if let (Some(from), Some(to)) = (ty, tr) {
+                edges.insert(Edge { from, to, kind: "implements" });
+            }
+        }
+    }
+
+    let mut nodes: Vec<Node> = extra.into_values().collect();
+    for file in files {
+        edges.extend(file.edges);
+        nodes.extend(file.nodes);
+    }
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+    Graph { nodes, edges, unparsed }
+}
+
+/// The crate-relative module path a `use` path in `module` points to, for
+/// paths starting with `crate`, `self` or `super`.
+fn absolute(module: &[String], path: &[String]) -> Option<Vec<String>> {
+    let (first, rest) = path.split_first()?;
+    match first.as_str() {
+        "crate" => Some(rest.to_vec()),
+        "self" => Some(module.iter().chain(rest).cloned().collect()),
+        "super" => {
+            let mut base = module.to_vec();
+            base.pop()?;
+            let mut rest = rest;
+            while rest.first().is_some_and(|next| next == "super") {
+                base.pop()?;
+                rest = &rest[1..];
+            }
+            Some(base.into_iter().chain(rest.iter().cloned()).collect())
+        }
+        _ => None,
+    }
+}
+
+/// Module names a Python file imports. Statements are found line by line,
+/// joining parenthesized and backslash-continued lines and skipping
+/// docstrings and comments.
+fn python_imports(content: &str) -> Vec<FileImport> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut in_docstring = false;
+    for line in content.lines() {
+        let code = line.split('#').next().unwrap_or_default();
+        if current.is_empty() {
+            let quotes = code.matches("\"\"\"").count() + code.matches("'''").count();
+            if in_docstring || quotes > 0 {
+                in_docstring ^= quotes % 2 == 1;
+                continue;
+            }
+            let trimmed = code.trim_start();
+            if !trimmed.starts_with("import ") && !trimmed.starts_with("from ") {
+                continue;
+            }
+        }
+        let continued = code.trim_end().ends_with('\\');
+        current.push_str(code.trim_end().trim_end_matches('\\'));
+        current.push(' ');
+        if !continued && current.matches('(').count() <= current.matches(')').count() {
+            statements.push(std::mem::take(&mut current));
+        }
+    }
+
+    let mut imports = Vec::new();
+    for statement in statements {
+        let statement = statement.trim();
+        let first_word = |part: &str| part.split_whitespace().next().map(String::from);
+        if let Some(rest) = statement.strip_prefix("import ") {
+            for module in rest.split(',').filter_map(first_word) {
+                let module = module.split('.').map(String::from).collect();
+                imports.push(FileImport::Python { level: 0, module, names: Vec::new() });
+            }
+        } else if let Some((module, names)) = statement.strip_prefix("from ").and_then(|rest| rest.split_once(" import ")) {
+            let module = module.trim();
+            let level = module.len() - module.trim_start_matches('.').len();
+            let module = module[level..].split('.').filter(|part| !part.is_empty()).map(String::from).collect();
+            let names = names
+                .replace(['(', ')'], " ")
+                .split(',')
+                .filter_map(first_word)
+                .filter(|name| name != "*")
+                .collect();
+            imports.push(FileImport::Python { level, module, names });
+        }
+    }
+    imports
+}
+
+/// Specifiers of the `import ... from`, `import "..."`, `export ... from`,
+/// `import()` and `require()` calls in a JavaScript or TypeScript file.
+fn script_imports(content: &str) -> Vec<String> {
+    let bytes = content.as_bytes();
+    let mut specifiers = Vec::new();
+    // The last two tokens before the current position
+    let mut tokens: [&str; 2] = ["", ""];
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if byte == b'/' && bytes.get(i + 1) == Some(&b'/') {
+            i = content[i..].find('\n').map_or(bytes.len(), |end| i + end);
+        } else if byte == b'/' && bytes.get(i + 1) == Some(&b'*') {
+            i = content[i + 2..].find("*/").map_or(bytes.len(), |end| i + 2 + end + 2);
+        } else if byte == b'\'' || byte == b'"' || byte == b'`' {
+            let start = i + 1;
+            let mut end = start;
+            while end < bytes.len() && bytes[end] != byte && (byte == b'`' || bytes[end] != b'\n') {
+                end += if bytes[end] == b'\\' { 2 } else { 1 };
+            }
+            let end = end.min(bytes.len());
+            let imported = match tokens {
+                [_, "from"] | [_, "import"] => byte != b'`',
+                ["require", "("] | ["import", "("] => true,
+                _ => false,
+            };
+            if imported {
+                specifiers.push(content[start..end].to_string());
+            }
+            tokens = [tokens[1], "string"];
+            i = end + 1;
+        } else if byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'$') {
+                i += 1;
+            }
+            tokens = [tokens[1], &content[start..i]];
+        } else if byte.is_ascii_whitespace() {
+            i += 1;
+        } else {
+            let len = content[i..].chars().next().map_or(1, char::len_utf8);
+            tokens = [tokens[1], &content[i..i + len]];
+            i += len;
+        }
+    }
+    specifiers
+}
+
+/// What an import points to.
+enum Target {
+    /// A path under `files/`
+    File(String),
+    /// A package from outside the docpack, or a relative import that
+    /// names no file
+    External(String),
+}
+
+/// The paths under `files/`, for resolving imports.
+struct FileIndex<'a> {
+    known: &'a BTreeSet<String>,
+    /// Paths by their last component
+    by_file_name: BTreeMap<&'a str, Vec<&'a str>>,
+}
+
+impl<'a> FileIndex<'a> {
+    fn new(known: &'a BTreeSet<String>) -> Self {
+        let mut by_file_name: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for path in known {
+            by_file_name.entry(path.rsplit('/').next().unwrap_or(path)).or_default().push(path);
+        }
+        FileIndex { known, by_file_name }
+    }
+
+    fn first(&self, candidates: impl IntoIterator<Item = String>) -> Option<String> {
+        candidates.into_iter().find(|candidate| self.known.contains(candidate))
+    }
+
+    /// The file ending in `suffix` closest to `importer`, as Python finds
+    /// top-level packages on a search path we don't know.
+    fn nearest(&self, importer: &str, suffix: &str) -> Option<String> {
+        let file_name = suffix.rsplit('/').next().unwrap_or(suffix);
+        let shared = |path: &str| {
+            path.split('/').zip(importer.split('/')).take_while(|(a, b)| a == b).count()
+        };
+        self.by_file_name
+            .get(file_name)?
+            .iter()
+            .filter(|path| **path == suffix || path.ends_with(&format!("/{}", suffix)))
+            .max_by(|a, b| shared(a).cmp(&shared(b)).then_with(|| b.cmp(a)))
+            .map(|path| path.to_string())
+    }
+
+    /// `module` as a `.py` file or a package's `__init__.py`, looked up
+    /// under `base` for relative imports and near `importer` otherwise.
+    fn python_module(&self, importer: &str, base: Option<&str>, module: &[String]) -> Option<String> {
+        let path = module.join("/");
+        let candidates = [format!("{}.py", path), format!("{}/__init__.py", path)];
+        match base {
+            Some(base) => {
+                let under = |candidate: &str| match (base.is_empty(), path.is_empty()) {
+                    (_, true) => format!("{}/__init__.py", base).trim_start_matches('/').to_string(),
+                    (true, false) => candidate.to_string(),
+                    (false, false) => format!("{}/{}", base, candidate),
+                };
+                self.first(candidates.iter().map(|candidate| under(candidate)))
+            }
+            None if path.is_empty() => None,
+            None => candidates.iter().find_map(|candidate| self.nearest(importer, candidate)),
+        }
+    }
+
+    fn python(&self, importer: &str, level: usize, module: &[String], names: &[String]) -> Vec<Target> {
+        // Relative imports count up from the importer's package
+        let base = if level > 0 {
+            let mut dirs: Vec<&str> = importer.split('/').collect();
+            dirs.pop();
+            if level - 1 > dirs.len() {
+                return vec![Target::External(format!("{}{}", ".".repeat(level), module.join(".")))];
+            }
+            dirs.truncate(dirs.len() - (level - 1));
+            Some(dirs.join("/"))
+        } else {
+            None
+        };
+
+        // `from pkg import sub` may name submodules
+        let submodules: Vec<Target> = names
+            .iter()
+            .filter_map(|name| {
+                let mut path = module.to_vec();
+                path.push(name.clone());
+                self.python_module(importer, base.as_deref(), &path).map(Target::File)
+            })
+            .collect();
+        if !submodules.is_empty() {
+            return submodules;
+        }
+        match self.python_module(importer, base.as_deref(), module) {
+            Some(path) => vec![Target::File(path)],
+            None if level > 0 => vec![Target::External(format!("{}{}", ".".repeat(level), module.join(".")))],
+            None => module.first().map(|top| Target::External(top.clone())).into_iter().collect(),
+        }
+    }
+
+    fn script(&self, importer: &str, specifier: &str) -> Target {
+        let relative = specifier == "." || specifier == ".." || specifier.starts_with("./") || specifier.starts_with("../");
+        if !relative {
+            // `@scope/name/sub` and `name/sub` come from package `@scope/name` and `name`
+            let parts = if specifier.starts_with('@') { 2 } else { 1 };
+            let package: Vec<&str> = specifier.split('/').take(parts).collect();
+            return Target::External(package.join("/"));
+        }
+
+        let mut path: Vec<&str> = importer.split('/').collect();
+        path.pop();
+        for part in specifier.split('/') {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    if path.pop().is_none() {
+                        return Target::External(specifier.to_string());
+                    }
+                }
+                part => path.push(part),
+            }
+        }
+        let path = path.join("/");
+
+        let mut candidates = vec![path.clone()];
+        // TypeScript imports `./x.js` to mean `./x.ts`
+        if let Some((stem, _)) = path.rsplit_once('.').filter(|(_, e)| SCRIPT_EXTENSIONS.contains(e)) {
+            candidates.extend(SCRIPT_EXTENSIONS.iter().map(|e| format!("{}.{}", stem, e)));
+        }
+        candidates.extend(SCRIPT_EXTENSIONS.iter().map(|e| format!("{}.{}", path, e)));
+        candidates.extend(SCRIPT_EXTENSIONS.iter().map(|e| format!("{}/index.{}", path, e).trim_start_matches('/').to_string()));
+        self.first(candidates).map(Target::File).unwrap_or_else(|| Target::External(specifier.to_string()))
+    }
+}
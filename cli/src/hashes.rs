@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::parallel;
+use crate::progress::Progress;
+
+/// Integrity manifest written under `index/`.
+pub const FILE_NAME: &str = "hashes.json";
+
+/// Hash and size of one file under `files/`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileHash {
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// SHA-256 of everything `reader` yields, read in fixed-size chunks.
+pub fn hash_reader(mut reader: impl Read) -> io::Result<FileHash> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+    Ok(FileHash { sha256: hex(&hasher.finalize()), size })
+}
+
+/// Hash `(relative path, file)` pairs on the rayon thread pool, keyed by
+/// relative path.
+pub fn hash_files(files: &[(String, PathBuf)], progress: &mut Progress) -> io::Result<BTreeMap<String, FileHash>> {
+    let mut hashes = BTreeMap::new();
+    parallel::for_each_result(
+        files,
+        |(name, path)| {
+            let hash = std::fs::File::open(path)
+                .and_then(hash_reader)
+                .map_err(|e| io::Error::new(e.kind(), format!("Failed to hash {}: {}", path.display(), e)))?;
+            Ok::<_, io::Error>((name.clone(), hash))
+        },
+        |(name, hash)| {
+            progress.inc(hash.size);
+            hashes.insert(name, hash);
+        },
+    )?;
+    Ok(hashes)
+}
+
+/// The `index/hashes.json` document.
+pub fn to_json(hashes: &BTreeMap<String, FileHash>) -> Value {
+    let files: serde_json::Map<String, Value> = hashes
+        .iter()
+        .map(|(name, hash)| (name.clone(), json!({ "sha256": hash.sha256, "size": hash.size })))
+        .collect();
+    json!({
+        "algorithm": "sha256",
+        "files": files,
+    })
+}
+
+/// The `metadata.hashes` record for docpack.json.
+pub fn metadata(hashes: &BTreeMap<String, FileHash>) -> Value {
+    json!({
+        "algorithm": "sha256",
+        "manifest": format!("index/{}", FILE_NAME),
+        "files": hashes.len(),
+        "digest": digest(hashes),
+    })
+}
+
+/// Parse an `index/hashes.json` document.
+pub fn from_json(value: &Value) -> Result<BTreeMap<String, FileHash>, String> {
+    if value["algorithm"].as_str() != Some("sha256") {
+        return Err(format!("unsupported hash algorithm: {}", value["algorithm"]));
+    }
+    let files = value["files"].as_object().ok_or("missing \"files\" object")?;
+    files
+        .iter()
+        .map(|(name, entry)| {
+            let sha256 = entry["sha256"].as_str().ok_or_else(|| format!("{}: missing sha256", name))?;
+            let size = entry["size"].as_u64().ok_or_else(|| format!("{}: missing size", name))?;
+            Ok((name.clone(), FileHash { sha256: sha256.to_string(), size }))
+        })
+        .collect()
+}
+
+/// Digest of the whole map: the SHA-256 of `sha256sum`-style lines
+/// (`<hash>  <path>\n`) in path order.
+pub fn digest(hashes: &BTreeMap<String, FileHash>) -> String {
+    let mut hasher = Sha256::new();
+    for (name, hash) in hashes {
+        hasher.update(format!("{}  {}\n", hash.sha256, name).as_bytes());
+    }
+    hex(&hasher.finalize())
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,393 @@
+//! The worked examples `--help` prints after each command's options, and
+//! that `localdoc man` renders as each page's EXAMPLES section. Every
+//! example is a `# ` comment saying what it does and the command lines
+//! that do it; examples are separated by a blank line.
+
+pub const LOCALDOC: &str = "\
+Examples:
+  # Package a project, document it and read the result
+  localdoc ingest ./my-project -o my-project.docpack --build-index
+  localdoc run my-project.docpack
+  localdoc serve my-project.docpack
+
+  # Write man pages for localdoc and every subcommand
+  localdoc man --out-dir /usr/local/share/man/man1
+";
+
+pub const INGEST: &str = "\
+Examples:
+  # Package a project with a search index for the agent
+  localdoc ingest ./my-project -o my-project.docpack --build-index
+
+  # Only Rust sources, with tighter limits on the run
+  localdoc ingest ./my-project --include '**/*.rs' --max-file-reads 50 --max-execution-time 60
+
+  # A release tarball from the web, checked against its published hash
+  localdoc ingest https://example.com/my-project-1.0.zip --sha256 <HEX> -o my-project.docpack
+
+  # Byte-identical archives in CI
+  SOURCE_DATE_EPOCH=$(git log -1 --format=%ct) localdoc ingest . -o app.docpack --reproducible
+";
+
+pub const RUN: &str = "\
+Examples:
+  # Run every task and watch the agent work
+  localdoc run my-project.docpack --follow
+
+  # One task, with a pinned image and no network
+  localdoc run my-project.docpack --task task_1 --image doctown:v2 --no-network
+
+  # Check the container command without starting anything
+  localdoc run my-project.docpack --dry-run
+
+  # A directory of docpacks, two at a time, each logging to its own file
+  localdoc run 'packs/*' --jobs 2 --log-dir logs
+";
+
+pub const WATCH: &str = "\
+Examples:
+  # Keep app.docpack documenting the current directory as it changes
+  localdoc watch . --out app.docpack
+
+  # Pass flags on to each run after --
+  localdoc watch ./src --out app.docpack --debounce 2000 -- --image doctown:v2 --follow
+";
+
+pub const DOCTOR: &str = "\
+Examples:
+  # Check everything `run` needs
+  localdoc doctor
+
+  # Check Podman and a docpack, as JSON for CI
+  localdoc doctor --runtime podman --docpack my-project.docpack --json
+";
+
+pub const LOGS: &str = "\
+Examples:
+  # Start a run in the background and stream its output
+  localdoc run my-project.docpack --detach
+  localdoc logs my-project.docpack --follow
+";
+
+pub const STOP: &str = "\
+Examples:
+  # Finish a detached run and write run.json
+  localdoc stop my-project.docpack
+";
+
+pub const CLEAN: &str = "\
+Examples:
+  # See what a docpack's outputs take up, then delete them
+  localdoc clean ./my-project --dry-run
+  localdoc clean ./my-project --yes
+
+  # Remove scratch directories crashed commands left behind over the last week
+  localdoc clean --temp --older-than 7d
+";
+
+pub const INSPECT: &str = "\
+Examples:
+  # Summarize a docpack
+  localdoc inspect my-project.docpack
+
+  # The 50 largest files too, saved as a Markdown report
+  localdoc inspect my-project.docpack --verbose --top 50 --report report.md
+";
+
+pub const LIST: &str = "\
+Examples:
+  # Every Markdown output, with sizes
+  localdoc list my-project.docpack --prefix output/ --glob '**/*.md' --long
+";
+
+pub const CAT: &str = "\
+Examples:
+  # Read the manifest and one source file without extracting anything
+  localdoc cat my-project.docpack docpack.json
+  localdoc cat my-project.docpack files/src/main.rs
+";
+
+pub const VALIDATE: &str = "\
+Examples:
+  # Check one docpack
+  localdoc validate my-project.docpack
+
+  # Every docpack in a directory, failing on warnings except DP010
+  localdoc validate packs/ --strict --ignore DP010
+
+  # Repair what can be repaired mechanically, showing the changes first
+  localdoc validate ./my-project --fix --dry-run
+";
+
+pub const SCHEMA: &str = "\
+Examples:
+  # Write the schemas where an editor can find them
+  localdoc schema --export .vscode/schemas
+";
+
+pub const SEARCH: &str = "\
+Examples:
+  # Lines containing both words
+  localdoc search my-project.docpack \"config loader\"
+
+  # Forgive a typo
+  localdoc search my-project.docpack extrat_zip --fuzzy
+
+  # Where a function is defined
+  localdoc search my-project.docpack --symbols load_config
+";
+
+pub const INDEX: &str = "\
+Examples:
+  # Add a search index and semantic graph to an existing docpack
+  localdoc index my-project.docpack --graph
+
+  # Rebuild it in the binary format, leaving out vendored code
+  localdoc index my-project.docpack --force --index-format binary --exclude 'vendor/**'
+";
+
+pub const GRAPH: &str = "\
+Examples:
+  # Node and edge counts
+  localdoc graph my-project.docpack
+
+  # What a type is connected to, and how two nodes are related
+  localdoc graph my-project.docpack --neighbors src/shapes.rs::Circle
+  localdoc graph my-project.docpack --path src/lib.rs::total src/shapes.rs::Area --json
+";
+
+pub const EMBED: &str = "\
+Examples:
+  # Estimate the cost first, then embed
+  localdoc embed my-project.docpack --dry-run
+  localdoc embed my-project.docpack
+
+  # A local OpenAI-compatible server
+  localdoc embed my-project.docpack --api-base http://localhost:8080/v1 --model nomic-embed-text
+";
+
+pub const SEMANTIC_SEARCH: &str = "\
+Examples:
+  # The five chunks closest to a question
+  localdoc semantic-search my-project.docpack \"where are retries configured?\" --k 5
+";
+
+pub const VERIFY: &str = "\
+Examples:
+  # Check that files/ is what was ingested
+  localdoc verify my-project.docpack
+";
+
+pub const DIFF: &str = "\
+Examples:
+  # What changed between two builds
+  localdoc diff old.docpack new.docpack --verbose
+";
+
+pub const BROWSE: &str = "\
+Examples:
+  # Open the terminal UI
+  localdoc browse my-project.docpack
+";
+
+pub const SERVE: &str = "\
+Examples:
+  # Browse at http://127.0.0.1:8080/
+  localdoc serve my-project.docpack
+
+  # Share on the local network on another port
+  localdoc serve my-project.docpack --host 0.0.0.0 --port 3000
+";
+
+pub const UPDATE: &str = "\
+Examples:
+  # Pick up changes to the source since the docpack was built
+  localdoc update my-project.docpack --source ./my-project
+";
+
+pub const INIT: &str = "\
+Examples:
+  # Start a docpack by hand, with an example tasks.json
+  localdoc init my-project.docpack --name my-project --with-tasks
+";
+
+pub const RECOVER: &str = "\
+Examples:
+  # Save what can be read from a truncated archive
+  localdoc recover broken.docpack -o recovered/
+";
+
+pub const PACK: &str = "\
+Examples:
+  # Zip an edited directory back into an archive
+  localdoc pack ./my-project -o my-project.docpack --force
+";
+
+pub const UNPACK: &str = "\
+Examples:
+  # Extract an archive to edit it
+  localdoc unpack my-project.docpack -o ./my-project
+";
+
+pub const PUSH: &str = "\
+Examples:
+  # Publish an archive, with the token in the environment
+  LOCALDOC_REGISTRY_TOKEN=... localdoc push my-project.docpack https://registry.example.com/docs/my-project.docpack
+
+  # Upload a large docpack in 16 MB parts
+  localdoc push ./big.docpack https://registry.example.com/docs/big.docpack --chunk-size 16MB
+";
+
+pub const PULL: &str = "\
+Examples:
+  # Download and verify against the registry's checksum
+  localdoc pull https://registry.example.com/docs/my-project.docpack
+
+  # Against a published checksum instead, to a chosen file
+  localdoc pull https://registry.example.com/docs/my-project.docpack -o app.docpack --sha256 <HEX>
+";
+
+pub const SET: &str = "\
+Examples:
+  # Record the language
+  localdoc set my-project.docpack metadata.language rust
+
+  # Seal a published docpack against accidental edits
+  localdoc set my-project.docpack metadata.immutable true
+";
+
+pub const MIGRATE: &str = "\
+Examples:
+  # Show what would change, then upgrade
+  localdoc migrate ./my-project --dry-run
+  localdoc migrate ./my-project
+";
+
+pub const HISTORY: &str = "\
+Examples:
+  # The last five runs
+  localdoc history -n 5
+";
+
+pub const PREVIEW_CONTEXT: &str = "\
+Examples:
+  # What the agent is told and can do for one task
+  localdoc preview-context ./my-project --task task_1
+";
+
+pub const EXPORT: &str = "\
+Examples:
+  # An mdBook, built with mdbook
+  localdoc export my-project.docpack --out book
+  mdbook build book
+
+  # A static site to host anywhere
+  localdoc export my-project.docpack --format html --out site --force
+";
+
+pub const TASKS: &str = "\
+Examples:
+  # List the tasks, then add one
+  localdoc tasks my-project.docpack list
+  localdoc tasks my-project.docpack add --name \"API reference\" --description \"Document every public function\"
+";
+
+pub const TASKS_LIST: &str = "\
+Examples:
+  # Ids, names and outputs
+  localdoc tasks my-project.docpack list
+";
+
+pub const TASKS_SHOW: &str = "\
+Examples:
+  # Everything about one task
+  localdoc tasks my-project.docpack show task_2
+";
+
+pub const TASKS_ADD: &str = "\
+Examples:
+  # A task limited to two tools, writing JSON
+  localdoc tasks my-project.docpack add --name Inventory --description \"List every module\" --tools read_file,list_files --output-path output/modules.json
+";
+
+pub const TASKS_REMOVE: &str = "\
+Examples:
+  # Drop a task
+  localdoc tasks my-project.docpack remove task_3
+";
+
+pub const OUTPUTS: &str = "\
+Examples:
+  # Start from existing docs, run, and see what changed
+  localdoc outputs seed ./my-project ./docs
+  localdoc run ./my-project
+  localdoc outputs diff ./my-project --stat
+";
+
+pub const OUTPUTS_SEED: &str = "\
+Examples:
+  # Give the agent the current docs to update, under output/seed/
+  localdoc outputs seed ./my-project ./docs --subdir
+";
+
+pub const OUTPUTS_DIFF: &str = "\
+Examples:
+  # The latest run against the one before it
+  localdoc outputs diff ./my-project
+
+  # A given run against what is in output/ now
+  localdoc outputs diff ./my-project --from 20251126T120000Z --to current --stat
+";
+
+pub const PLUGINS: &str = "\
+Examples:
+  # See which plugins inspect and validate will run
+  localdoc plugins list
+";
+
+pub const PLUGINS_LIST: &str = PLUGINS;
+
+pub const CONFIG: &str = "\
+Examples:
+  # Use Podman everywhere, and a pinned image in this project
+  localdoc config set run.runtime podman
+  localdoc config set run.image doctown:v2 --project
+  localdoc config list
+";
+
+pub const CONFIG_LIST: &str = "\
+Examples:
+  # Every key, its value and where it comes from
+  localdoc config list
+";
+
+pub const CONFIG_GET: &str = "\
+Examples:
+  # The image run will use
+  localdoc config get run.image
+";
+
+pub const CONFIG_SET: &str = "\
+Examples:
+  # Leave logs and fixtures out of every ingest
+  localdoc config set ingest.exclude '*.log,fixtures/**'
+";
+
+pub const MAN: &str = "\
+Examples:
+  # Build the pages and read one
+  localdoc man --out-dir man
+  man ./man/localdoc-ingest.1
+";
+
+pub const BENCH: &str = "\
+Examples:
+  # Time the default synthetic trees and keep the results
+  localdoc bench --self-test --json before.json
+
+  # After a change, compare larger trees with the earlier run
+  localdoc bench --self-test --files 1000,10000,50000 --baseline before.json
+
+  # Time a real project
+  localdoc bench ./my-project
+";
@@ -0,0 +1,47 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use serde_json::Value;
+
+/// Log of runs, one JSON object per line, kept in the user's data directory.
+pub const FILE_NAME: &str = "history.jsonl";
+
+/// `$XDG_DATA_HOME/localdoc/history.jsonl`, else under
+/// `~/.local/share`. `None` when neither variable is set.
+pub fn path() -> Option<PathBuf> {
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))?;
+    Some(data.join("localdoc").join(FILE_NAME))
+}
+
+/// Add `record` as the last line of the log.
+pub fn append(record: &Value) -> io::Result<()> {
+    let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "neither XDG_DATA_HOME nor HOME is set"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
+
+/// Every record in the log, oldest first. Lines that are not JSON, such
+/// as one cut short by a crash, are skipped.
+pub fn read() -> io::Result<Vec<Value>> {
+    let Some(path) = path() else {
+        return Ok(Vec::new());
+    };
+    let file = match fs::File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(record) = serde_json::from_str::<Value>(&line?) {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
@@ -0,0 +1,171 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Mutex, Once};
+use std::thread;
+
+use crate::commands::{outputs, run};
+use crate::runtime::{self, Runtime};
+use crate::scratch;
+
+static HANDLER: Once = Once::new();
+
+/// Ctrl-Cs received so far; the second one stops waiting on anything.
+static INTERRUPTS: AtomicU32 = AtomicU32::new(0);
+
+/// Set by a batch run, whose `localdoc run` children handle Ctrl-C for
+/// their own containers.
+static CHILDREN: AtomicBool = AtomicBool::new(false);
+
+/// The agent container currently running, if any.
+static CONTAINER: Mutex<Option<Container>> = Mutex::new(None);
+
+/// An agent container to stop on Ctrl-C.
+#[derive(Clone, Debug)]
+pub struct Container {
+    pub runtime: Runtime,
+    /// The `--name` it was started with
+    pub name: String,
+    /// The docpack directory mounted into it
+    pub working_dir: PathBuf,
+    /// Where to copy partial outputs before `working_dir` is removed, for
+    /// runs in a temporary copy
+    pub salvage_to: Option<PathBuf>,
+}
+
+/// Keeps a container registered for Ctrl-C until dropped.
+pub struct Watch(());
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        *CONTAINER.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// Stop `container` if the user presses Ctrl-C before the returned guard
+/// is dropped.
+pub fn watch(container: Container) -> Watch {
+    install();
+    *CONTAINER.lock().unwrap_or_else(|e| e.into_inner()) = Some(container);
+    Watch(())
+}
+
+/// Let a batch run outlive the first Ctrl-C, so it can wait for its
+/// children to stop their containers and report on them. A second Ctrl-C
+/// still exits straight away.
+pub fn defer_to_children() {
+    install();
+    CHILDREN.store(true, Ordering::SeqCst);
+}
+
+/// Whether Ctrl-C has been pressed.
+pub fn interrupted() -> bool {
+    INTERRUPTS.load(Ordering::SeqCst) > 0
+}
+
+/// Install the Ctrl-C handler, once. It stops a watched container, removes
+/// scratch directories and exits with code 130.
+pub fn install() {
+    HANDLER.call_once(|| {
+        // Another handler being installed already is not fatal; cleanup on
+        // drop still covers errors
+        if let Err(e) = ctrlc::set_handler(on_interrupt) {
+            eprintln!("⚠️  Could not install Ctrl-C handler: {}", e);
+        }
+    });
+}
+
+fn on_interrupt() {
+    let container = CONTAINER.lock().unwrap_or_else(|e| e.into_inner()).clone();
+
+    if INTERRUPTS.fetch_add(1, Ordering::SeqCst) > 0 {
+        if let Some(container) = &container {
+            let _ = container.runtime.kill_command(&container.name).stdout(Stdio::null()).stderr(Stdio::null()).status();
+        }
+        scratch::remove_all();
+        eprintln!("\nKilled");
+        std::process::exit(130);
+    }
+
+    if CHILDREN.load(Ordering::SeqCst) {
+        eprintln!("\nInterrupted; waiting for the running docpacks to stop (Ctrl-C again to exit now)");
+        return;
+    }
+
+    let Some(container) = container else {
+        scratch::remove_all();
+        eprintln!("\nInterrupted");
+        std::process::exit(130);
+    };
+
+    // Stopping waits on the runtime, so it happens off the handler thread,
+    // which stays free to take a second Ctrl-C
+    eprintln!("\nInterrupted; stopping container {} (Ctrl-C again to kill it)", container.name);
+    thread::spawn(move || {
+        for line in stop_and_salvage(&container) {
+            eprintln!("{}", line);
+        }
+        scratch::remove_all();
+        std::process::exit(130);
+    });
+}
+
+/// Stop `container` and copy what it left in `output/` to `salvage_to`,
+/// returning what to tell the user about the partial outputs.
+pub fn stop_and_salvage(container: &Container) -> Vec<String> {
+    let _ = container
+        .runtime
+        .stop_command(&container.name, runtime::STOP_GRACE_SECONDS)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    let output = container.working_dir.join("output");
+    let partial = match outputs::collect_files(&output) {
+        Ok(partial) => partial,
+        Err(e) => return vec![format!("Could not read partial outputs in {}: {}", output.display(), e)],
+    };
+    if partial.is_empty() {
+        return vec!["No partial outputs were written".to_string()];
+    }
+    match &container.salvage_to {
+        Some(dir) => match run::copy_outputs(&container.working_dir, &partial, dir) {
+            Ok(()) => vec![format!("{} partial output file(s) copied to: {}", partial.len(), dir.display())],
+            Err(e) => vec![format!("Could not copy partial outputs to {}: {}", dir.display(), e)],
+        },
+        None => vec![format!("{} partial output file(s) are in: {}", partial.len(), output.display())],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn interrupted_runs_are_stopped_and_their_outputs_salvaged() {
+        let dir = tempfile::tempdir().unwrap();
+        let working_dir = dir.path().join("copy");
+        fs::create_dir_all(working_dir.join("output/guide")).unwrap();
+        fs::write(working_dir.join("output/guide/intro.md"), "# Intro\n").unwrap();
+
+        // Stopping a container that doesn't exist fails quietly
+        let mut container = Container {
+            runtime: Runtime::Docker,
+            name: "localdoc-test-no-such-container".to_string(),
+            working_dir: working_dir.clone(),
+            salvage_to: Some(dir.path().join("salvaged")),
+        };
+        let report = stop_and_salvage(&container);
+        assert!(report[0].contains(&dir.path().join("salvaged").display().to_string()));
+        assert_eq!(fs::read_to_string(dir.path().join("salvaged/guide/intro.md")).unwrap(), "# Intro\n");
+
+        container.salvage_to = None;
+        assert!(stop_and_salvage(&container)[0].contains(&working_dir.join("output").display().to_string()));
+
+        let _watch = watch(container);
+        assert!(CONTAINER.lock().unwrap().is_some());
+        drop(_watch);
+        assert!(CONTAINER.lock().unwrap().is_none());
+    }
+}
@@ -0,0 +1,87 @@
+//! Library surface of `localdoc` for embedding docpack operations in other
+//! programs (for example a web service) without shelling out to the CLI.
+
+pub mod archive;
+pub mod builder;
+pub mod docpack;
+
+// The CLI's own modules, public so the `localdoc` binary can use them; not
+// a stable API
+#[doc(hidden)]
+pub mod commands;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod constraints;
+#[doc(hidden)]
+pub mod context;
+#[doc(hidden)]
+pub mod dedupe;
+#[doc(hidden)]
+pub mod download;
+#[doc(hidden)]
+pub mod embeddings;
+#[doc(hidden)]
+pub mod error;
+#[doc(hidden)]
+pub mod filter;
+#[doc(hidden)]
+pub mod findings;
+#[doc(hidden)]
+pub mod fingerprint;
+#[doc(hidden)]
+pub mod git;
+#[doc(hidden)]
+pub mod graph;
+#[doc(hidden)]
+pub mod hashes;
+#[doc(hidden)]
+pub mod help;
+#[doc(hidden)]
+pub mod history;
+#[doc(hidden)]
+pub mod interrupt;
+#[doc(hidden)]
+pub mod logging;
+#[doc(hidden)]
+pub mod parallel;
+#[doc(hidden)]
+pub mod plugins;
+#[doc(hidden)]
+pub mod progress;
+#[doc(hidden)]
+pub mod project;
+#[doc(hidden)]
+pub mod reader;
+#[doc(hidden)]
+pub mod repair;
+#[doc(hidden)]
+pub mod rss;
+#[doc(hidden)]
+pub mod run_state;
+#[doc(hidden)]
+pub mod runtime;
+#[doc(hidden)]
+pub mod schema;
+#[doc(hidden)]
+pub mod scratch;
+#[doc(hidden)]
+pub mod search;
+#[doc(hidden)]
+pub mod secrets;
+#[doc(hidden)]
+pub mod seal;
+#[doc(hidden)]
+pub mod size;
+#[doc(hidden)]
+pub mod symbols;
+#[doc(hidden)]
+pub mod synthetic;
+#[doc(hidden)]
+pub mod tokenize;
+#[doc(hidden)]
+pub mod tools;
+#[doc(hidden)]
+pub mod versions;
+
+extern crate self as localdoc;
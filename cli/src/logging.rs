@@ -0,0 +1,147 @@
+//! The console logger. Commentary goes through `info!`, problems through
+//! `warn!` and detail for `-v` through `debug!`; this is the one place that
+//! decides what is shown, where, and in which characters. Machine-readable
+//! output (`--json`, file contents, search hits) is printed directly.
+//!
+//! It is a `log` logger rather than a `tracing` subscriber: nothing here
+//! uses spans, and an application using `tracing` still receives the
+//! library's records through `tracing-log`.
+
+use std::borrow::Cow;
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::progress;
+
+/// Held while a line is written, so lines from parallel workers and the
+/// progress bar don't interleave.
+pub static CONSOLE: Mutex<()> = Mutex::new(());
+
+/// Symbols the commentary uses and what `--no-emoji` writes instead.
+const ASCII: &[(&str, &str)] = &[
+    ("✓", "[ok]"),
+    ("✗", "[x]"),
+    ("❌", "[x]"),
+    ("•", "-"),
+    ("→", "->"),
+    ("←", "<-"),
+    ("…", "..."),
+    ("├──", "|--"),
+    ("└──", "`--"),
+    ("│", "|"),
+    ("─", "-"),
+    ("━", "="),
+    ("×", "x"),
+    ("·", "-"),
+    ("≈", "~"),
+    ("—", "-"),
+];
+
+struct Logger {
+    level: LevelFilter,
+    ascii: bool,
+    color: bool,
+}
+
+/// Install the logger. `verbose` is the number of `-v` flags; `json` turns
+/// it off entirely, so the JSON is all a command prints.
+pub fn init(verbose: u8, quiet: bool, no_emoji: bool, json: bool) {
+    let level = match (json, quiet, verbose) {
+        (true, _, _) => LevelFilter::Off,
+        (_, true, _) => LevelFilter::Warn,
+        (_, _, 0) => LevelFilter::Info,
+        (_, _, 1) => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    let ascii = no_emoji || std::env::var("TERM").is_ok_and(|term| term == "dumb");
+    let color = io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty());
+    let logger = Logger { level, ascii, color };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Libraries' own logging is only wanted at -vv
+        metadata.level() <= self.level
+            && (metadata.target().starts_with(env!("CARGO_CRATE_NAME")) || self.level == LevelFilter::Trace)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        let message = if self.ascii { to_ascii(&message) } else { Cow::Borrowed(message.as_str()) };
+
+        let _console = CONSOLE.lock().unwrap_or_else(|e| e.into_inner());
+        progress::clear_bar();
+        let (prefix, color) = match record.level() {
+            Level::Info => {
+                let _ = writeln!(io::stdout(), "{}", message);
+                return;
+            }
+            Level::Error if self.ascii => ("Error: ", "31"),
+            Level::Error => ("❌ ", "31"),
+            Level::Warn if self.ascii => ("Warning: ", "33"),
+            Level::Warn => ("⚠️  ", "33"),
+            Level::Debug => ("debug: ", "2"),
+            Level::Trace => ("trace: ", "2"),
+        };
+        let _ = if self.color {
+            writeln!(io::stderr(), "\x1b[{}m{}{}\x1b[0m", color, prefix, message)
+        } else {
+            writeln!(io::stderr(), "{}{}", prefix, message)
+        };
+    }
+
+    fn flush(&self) {
+        let _ = io::stdout().flush();
+    }
+}
+
+/// `text` with the symbols in [`ASCII`] spelled out and any other emoji
+/// dropped, along with the spaces after it.
+pub fn to_ascii(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let mut text = text.to_string();
+    for (symbol, ascii) in ASCII {
+        text = text.replace(symbol, ascii);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if is_emoji(c) {
+            while chars.next_if(|&c| is_emoji(c)).is_some() {}
+            while chars.next_if_eq(&' ').is_some() {}
+        } else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Pictographs, dingbats and the joiners and variation selectors that
+/// combine them. Letters in any script are left alone.
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0x2139 | 0xFE0F | 0x200D)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_keeps_text_and_replaces_symbols() {
+        assert_eq!(to_ascii("✓ Unpacked to out/"), "[ok] Unpacked to out/");
+        assert_eq!(to_ascii("📦 Creating docpack"), "Creating docpack");
+        assert_eq!(to_ascii("⚠️  Modifying sealed docpack"), "Modifying sealed docpack");
+        assert_eq!(to_ascii("  • files/naïve.md → 日本語.md"), "  - files/naïve.md -> 日本語.md");
+        assert_eq!(to_ascii("plain"), "plain");
+    }
+}
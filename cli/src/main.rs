@@ -1,6 +1,7 @@
-mod commands;
-
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use localdoc::archive::CompressionMethod;
+use localdoc::{commands, config, constraints, help, logging, runtime, search, size, tools};
+use runtime::{PullPolicy, RuntimeChoice};
 use std::process;
 
 #[derive(Parser)]
@@ -8,19 +9,49 @@ use std::process;
     name = "localdoc",
     about = "Doctown CLI - Create and manage .docpack documentation universes",
     version,
-    long_about = "A command-line tool for creating, inspecting, validating, and running AI-powered documentation on .docpack archives."
+    long_about = "A command-line tool for creating, inspecting, validating, and running AI-powered documentation on .docpack archives.",
+    after_long_help = help::LOCALDOC
 )]
 struct Cli {
+    /// Show more detail: -v adds debug messages, what inspect and diff list
+    /// in full and, when a command fails, the errors that caused it; -vv
+    /// adds trace messages
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Print only warnings and errors, with no progress bars
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Write plain ASCII instead of emoji and symbols, e.g. for logs (also
+    /// when TERM=dumb)
+    #[arg(long, global = true)]
+    no_emoji: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
-    /// Create a new .docpack from a source (directory, zip, or git repo)
+    /// Create a new .docpack from a source (directory, zip, tarball, or git repo)
+    ///
+    /// Source files are copied under files/, filtered by .docpackignore,
+    /// --include, --exclude and the default excludes, and scanned for
+    /// credentials. docpack.json records the tools and constraints the agent
+    /// gets and tasks.json what it should write. --build-index and
+    /// --build-graph add the index/ files the agent's search tools read.
+    #[command(after_long_help = help::INGEST)]
     Ingest {
-        /// Path to source directory, zip file, or git URL
-        source: String,
+        /// Paths to source directories, zip files or tarballs, or http(s) URLs of zips. Several
+        /// sources are each mounted under files/<name>/
+        #[arg(required = true)]
+        sources: Vec<String>,
+
+        /// Mount the source at PATH under files/NAME/ instead of its own name (NAME=PATH, repeatable)
+        #[arg(long, value_name = "NAME=PATH")]
+        prefix: Vec<String>,
 
         /// Output .docpack directory path
         #[arg(short, long, default_value = "out.docpack")]
@@ -30,7 +61,7 @@ enum Commands {
         #[arg(short, long)]
         name: Option<String>,
 
-        /// Description for the docpack
+        /// Description for the docpack (defaults to the README's first paragraph)
         #[arg(short, long)]
         description: Option<String>,
 
@@ -38,54 +69,811 @@ enum Commands {
         #[arg(short, long)]
         language: Option<String>,
 
-        /// Enable all available tools (default: basic subset)
-        #[arg(long)]
+        /// Enable all available tools (same as --preset full)
+        #[arg(long, conflicts_with = "preset")]
         all_tools: bool,
 
+        /// Tool preset: minimal, code-analysis, research, or full (default: ingest.preset, or minimal)
+        #[arg(long)]
+        preset: Option<String>,
+
+        /// Add a tool on top of the preset (repeatable)
+        #[arg(long = "tool")]
+        tools: Vec<String>,
+
         /// Build search index during ingestion
         #[arg(long)]
         build_index: bool,
 
-        /// Build semantic graph during ingestion
+        /// How to store the search index; binary is several times smaller and faster to load, JSON is readable by any tool
+        #[arg(long, value_enum, default_value = "json")]
+        index_format: search::IndexFormat,
+
+        /// Also index lockfiles and minified files (*.min.*, or lines averaging over 500 characters)
+        #[arg(long)]
+        index_all: bool,
+
+        /// Build semantic graph of Rust, Python and JS/TS sources during ingestion
         #[arg(long)]
         build_graph: bool,
+
+        /// Copy an existing docs directory into output/ for the agent to update
+        #[arg(long)]
+        seed_outputs: Option<String>,
+
+        /// Put seeded docs in output/seed/ instead of output/
+        #[arg(long, requires = "seed_outputs")]
+        seed_subdir: bool,
+
+        /// Only include files matching this glob (repeatable)
+        #[arg(long)]
+        include: Vec<String>,
+
+        /// Leave out files matching this glob (repeatable; wins over --include; default: ingest.exclude)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Keep node_modules/, target/, .git/ and other dependency/build directories
+        #[arg(long)]
+        no_default_excludes: bool,
+
+        /// Skip files larger than this (e.g. 10MB); 0 disables the limit (default: ingest.max_file_size, or 50MB)
+        #[arg(long, value_parser = size::parse_size)]
+        max_file_size: Option<u64>,
+
+        /// Warn when the docpack may be larger than this (e.g. 2GB); 0 disables the check
+        #[arg(long, default_value = "4GB", value_parser = size::parse_size)]
+        max_docpack_size: u64,
+
+        /// What to do with symlinks in the source
+        #[arg(long, value_enum, default_value = "skip")]
+        symlinks: commands::ingest::SymlinkPolicy,
+
+        /// What to do with files whose names are not valid UTF-8
+        #[arg(long, value_enum, default_value = "skip")]
+        non_utf8: commands::ingest::NonUtf8Policy,
+
+        /// Don't record the commit, branch and remote of a git checkout
+        #[arg(long)]
+        no_git_metadata: bool,
+
+        /// Skip the scan for keys, tokens and credential files
+        #[arg(long)]
+        no_secret_scan: bool,
+
+        /// Abort if the secret scan finds anything
+        #[arg(long, conflicts_with = "no_secret_scan")]
+        fail_on_secrets: bool,
+
+        /// Print a JSON summary instead of progress output (errors go to stderr as JSON)
+        #[arg(long)]
+        json: bool,
+
+        /// Store identical files once (blobs/ plus files.map.json); older readers can't open the result
+        #[arg(long)]
+        dedupe: bool,
+
+        /// Build a byte-identical archive for identical inputs (fixed timestamps)
+        #[arg(long)]
+        reproducible: bool,
+
+        /// Creation time to record, as RFC 3339 (default: SOURCE_DATE_EPOCH or now)
+        #[arg(long)]
+        created: Option<chrono::DateTime<chrono::Utc>>,
+
+        /// Compression method for the archive
+        #[arg(long, value_enum, default_value = "deflate")]
+        compression: Compression,
+
+        /// Compression level (deflate: 0-9, zstd: 1-22)
+        #[arg(long)]
+        compression_level: Option<i32>,
+
+        /// Maximum number of file reads the agent may make (default: 1000)
+        #[arg(long, value_parser = constraints::parse_file_reads)]
+        max_file_reads: Option<u64>,
+
+        /// Maximum run time in seconds (default: 300)
+        #[arg(long, value_parser = constraints::parse_execution_time)]
+        max_execution_time: Option<u64>,
+
+        /// Container memory limit in MB, at least 128 (default: 2048)
+        #[arg(long, value_parser = constraints::parse_memory_limit)]
+        memory_limit: Option<u64>,
+
+        /// Expected SHA-256 of an archive or URL source; ingest fails on mismatch
+        #[arg(long)]
+        sha256: Option<String>,
+
+        /// Use this tasks.json instead of the generated template
+        #[arg(long)]
+        tasks_file: Option<String>,
+
+        /// Override the mission in tasks.json
+        #[arg(long)]
+        mission: Option<String>,
+
+        /// Fail instead of warning when the tasks file has problems
+        #[arg(long)]
+        strict: bool,
     },
 
     /// Run the documenter agent on a .docpack
+    ///
+    /// The docpack is mounted at /workspace read-only, so the agent cannot
+    /// change files/, index/, docpack.json or tasks.json. Only
+    /// /workspace/output and /workspace/.reasoning (its notes) are mounted
+    /// read-write, each with its own -v. --writable-files mounts the whole
+    /// docpack read-write instead, unless the constraints list modify_files
+    /// in forbidden_actions.
+    ///
+    /// The container has network access, which the agent needs to reach
+    /// its LLM API; --no-network starts it with --network none.
+    ///
+    /// Several docpacks, or a quoted glob such as 'packs/*', are run as a
+    /// batch, --jobs at a time, each in its own container. Their output is
+    /// prefixed with the docpack's name, or written to --log-dir.
+    #[command(after_long_help = help::RUN)]
     Run {
-        /// Path to .docpack directory
-        docpack: String,
+        /// Path(s) to .docpack archives or directories, or a glob
+        #[arg(required = true)]
+        docpacks: Vec<String>,
 
-        /// Docker image to use
-        #[arg(short, long, default_value = "doctown:latest")]
-        image: String,
+        /// Container image to use (default: run.image, or doctown:latest)
+        #[arg(short, long)]
+        image: Option<String>,
 
-        /// Follow logs in real-time
+        /// Show the agent's output as it runs (it is always written to run.log)
         #[arg(short, long)]
         follow: bool,
 
-        /// Path to .env file for Docker
+        /// Set a variable in the container; a bare KEY forwards its value from this shell (repeatable)
+        #[arg(short = 'e', long = "env", value_name = "KEY[=VALUE]")]
+        env: Vec<String>,
+
+        /// Env file for the container (default: .env in the current directory, if present)
         #[arg(long)]
         env_file: Option<String>,
+
+        /// Don't pick up .env from the current directory
+        #[arg(long)]
+        no_env: bool,
+
+        /// Maximum total size of output/ in bytes (overrides max_output_bytes)
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        max_output_bytes: Option<u64>,
+
+        /// Maximum size of a single output file in bytes (overrides max_output_file_bytes)
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        max_output_file_bytes: Option<u64>,
+
+        /// Container runtime (default: $LOCALDOC_RUNTIME, run.runtime, or auto)
+        #[arg(long, value_enum)]
+        runtime: Option<RuntimeChoice>,
+
+        /// Copy output/ here after a successful run (default for archives
+        /// and sealed docpacks: <name>-output/ beside the docpack)
+        #[arg(long)]
+        output_dir: Option<String>,
+
+        /// Write the new outputs back into the .docpack archive
+        #[arg(long)]
+        update_archive: bool,
+
+        /// Container memory limit in MB (overrides memory_limit_mb; default: run.memory)
+        #[arg(long, value_name = "MB", value_parser = constraints::parse_memory_limit)]
+        memory: Option<u64>,
+
+        /// Number of CPUs the container may use, e.g. 1.5
+        #[arg(long, value_parser = constraints::parse_cpus)]
+        cpus: Option<f64>,
+
+        /// Stop the container after this many seconds (overrides max_execution_time_seconds)
+        #[arg(long, value_name = "SECONDS", value_parser = constraints::parse_execution_time)]
+        timeout: Option<u64>,
+
+        /// Mount the whole docpack read-write, not just output/
+        #[arg(long)]
+        writable_files: bool,
+
+        /// Start the container with no network (--network none)
+        #[arg(long)]
+        no_network: bool,
+
+        /// Run the agent as this user (UID[:GID]) instead of you
+        #[arg(long, value_name = "UID[:GID]", conflicts_with = "no_user_mapping")]
+        user: Option<String>,
+
+        /// Run the agent as the image's own user, for images that must run as root
+        #[arg(long)]
+        no_user_mapping: bool,
+
+        /// Don't validate the docpack before starting the container
+        #[arg(long)]
+        no_validate: bool,
+
+        /// Refuse to run on validation warnings too
+        #[arg(long, conflicts_with = "no_validate")]
+        strict: bool,
+
+        /// Print the container command, mounts and env file without running anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// When to pull the image
+        #[arg(long, value_enum, default_value_t = PullPolicy::Missing)]
+        pull: PullPolicy,
+
+        /// Run only the task with this id from tasks.json (repeatable)
+        #[arg(long = "task", value_name = "ID")]
+        tasks: Vec<String>,
+
+        /// Print the id and name of each task in tasks.json and exit
+        #[arg(long, conflicts_with = "json")]
+        list_tasks: bool,
+
+        /// Print the run summary as JSON on stdout (the agent's output goes to stderr)
+        #[arg(long, conflicts_with = "dry_run")]
+        json: bool,
+
+        /// Start the container and return; follow it with `localdoc logs` and finish it with `localdoc stop`
+        #[arg(long, conflicts_with_all = ["follow", "json"])]
+        detach: bool,
+
+        /// With several docpacks, how many to run at once
+        #[arg(short, long, value_name = "N", default_value_t = 1)]
+        jobs: usize,
+
+        /// With several docpacks, write each one's output to <DIR>/<name>.log instead of the terminal
+        #[arg(long, value_name = "DIR")]
+        log_dir: Option<String>,
+    },
+
+    /// Re-ingest and re-run a docpack whenever its source changes
+    ///
+    /// The docpack is ingested from the source if it does not exist yet,
+    /// then run. Each change to the source (or to the docpack's tasks.json)
+    /// updates it and starts a new run, cancelling one still going. Flags
+    /// for run go after --, e.g. `localdoc watch . --out app.docpack --
+    /// --image doctown:v2`.
+    #[command(after_long_help = help::WATCH)]
+    Watch {
+        /// Source directory to watch
+        source: String,
+
+        /// The docpack to keep up to date
+        #[arg(short, long)]
+        out: String,
+
+        /// Wait this long after the last change before starting over
+        #[arg(long, value_name = "MS", default_value_t = 500)]
+        debounce: u64,
+
+        /// Flags for each `localdoc run`
+        #[arg(last = true, value_name = "RUN FLAGS")]
+        run_args: Vec<String>,
+    },
+
+    /// Check that Docker or Podman, the agent image and the disk are ready for `run`
+    ///
+    /// Exits with 0 when every check passes, 1 when the worst result is a
+    /// warning and 2 when a check fails.
+    #[command(after_long_help = help::DOCTOR)]
+    Doctor {
+        /// Container runtime to check (default: $LOCALDOC_RUNTIME, run.runtime, or auto)
+        #[arg(long, value_enum)]
+        runtime: Option<RuntimeChoice>,
+
+        /// Agent image to look for (default: run.image, or doctown:latest)
+        #[arg(short, long)]
+        image: Option<String>,
+
+        /// Also validate this docpack
+        #[arg(long)]
+        docpack: Option<String>,
+
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the output of a run started with `run --detach`
+    ///
+    /// Prints what the container has written so far; --follow keeps streaming
+    /// until it exits. Finish the run with `localdoc stop`.
+    #[command(after_long_help = help::LOGS)]
+    Logs {
+        /// Path to .docpack directory
+        docpack: String,
+
+        /// Keep streaming until the container exits
+        #[arg(short, long)]
+        follow: bool,
+    },
+
+    /// Stop a run started with `run --detach` and write its summary
+    ///
+    /// Gives the agent 10 seconds to exit, saves its output to run.log, removes
+    /// the container and writes run.json. If the agent has already exited, its
+    /// exit code decides whether the run succeeded.
+    #[command(after_long_help = help::STOP)]
+    Stop {
+        /// Path to .docpack directory
+        docpack: String,
+    },
+
+    /// Delete a docpack's outputs, or scratch directories left in the temp directory
+    ///
+    /// With a docpack, empties output/ and removes .reasoning/ after asking.
+    /// With --temp, removes localdoc's own docpack-run-*, docpack-build-* and
+    /// similar directories that nothing has changed in for --older-than.
+    #[command(after_long_help = help::CLEAN)]
+    Clean {
+        /// Path to .docpack directory
+        #[arg(required_unless_present = "temp", conflicts_with = "temp")]
+        docpack: Option<String>,
+
+        /// Clean the temp directory instead of a docpack
+        #[arg(long)]
+        temp: bool,
+
+        /// With --temp, only directories unchanged for this long, e.g. 12h or 2d
+        #[arg(long, default_value = "1d", value_parser = commands::clean::parse_age)]
+        older_than: std::time::Duration,
+
+        /// Don't ask before deleting
+        #[arg(short, long)]
+        yes: bool,
+
+        /// List what would be deleted without deleting it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Allow modifying a sealed docpack
+        #[arg(long)]
+        force_mutable: bool,
     },
 
     /// Inspect a .docpack's structure and metadata
+    ///
+    /// Archives are read in place: counts and sizes come from the zip's central
+    /// directory, so nothing is extracted. --verbose adds the file tree, the
+    /// largest files and the task list, and --report writes the same as
+    /// Markdown. Plugins only run on extracted directories.
+    #[command(after_long_help = help::INSPECT)]
     Inspect {
-        /// Path to .docpack directory
+        /// Path to a .docpack archive or extracted directory
+        docpack: String,
+
+        /// Number of files in the --verbose largest files list
+        #[arg(long, value_name = "N", default_value_t = 20)]
+        top: usize,
+
+        /// Do not run localdoc-plugin-* executables
+        #[arg(long)]
+        no_plugins: bool,
+
+        /// Also write the report as Markdown to this file
+        #[arg(long, value_name = "PATH")]
+        report: Option<String>,
+    },
+
+    /// List the files in a .docpack without extracting it
+    ///
+    /// Archives are read in place from their central directory. A deduplicated
+    /// archive is listed with its usual files/ paths.
+    #[command(after_long_help = help::LIST)]
+    List {
+        /// Path to a .docpack archive or extracted directory
         docpack: String,
 
-        /// Show detailed information
+        /// Only list paths starting with this, e.g. files/src/
+        #[arg(long)]
+        prefix: Option<String>,
+
+        /// Only list paths matching this glob, e.g. '**/*.md'
+        #[arg(long)]
+        glob: Option<String>,
+
+        /// Show each file's size, and the total
         #[arg(short, long)]
-        verbose: bool,
+        long: bool,
+    },
+
+    /// Print one file of a .docpack, e.g. docpack.json or files/src/main.rs
+    ///
+    /// Only the entry printed is decompressed. A binary file is not printed to
+    /// a terminal without --force, though redirected output is never refused. A
+    /// path that is not in the docpack fails with close matches.
+    #[command(after_long_help = help::CAT)]
+    Cat {
+        /// Path to a .docpack archive or extracted directory
+        docpack: String,
+
+        /// Path of the file within the docpack
+        path: String,
+
+        /// Replace invalid UTF-8 instead of printing the bytes as they are
+        #[arg(long)]
+        text: bool,
+
+        /// Print a binary file even to a terminal
+        #[arg(long)]
+        force: bool,
     },
 
     /// Validate a .docpack structure against the spec
+    ///
+    /// Checks docpack.json and tasks.json against their JSON Schemas and the
+    /// rules of the spec, and reports each finding with a code such as DP010.
+    /// Archives are checked without extracting them. Exits non-zero when a
+    /// docpack has errors, or warnings with --strict.
+    #[command(after_long_help = help::VALIDATE)]
     Validate {
-        /// Path to .docpack directory
+        /// Path(s) to .docpack archives or directories, or a directory containing docpacks
+        #[arg(required = true)]
+        docpacks: Vec<String>,
+
+        /// Number of docpacks to validate concurrently (default: number of CPUs)
+        #[arg(long)]
+        parallel: Option<usize>,
+
+        /// Print a summary sorted by path after all docpacks are validated
+        #[arg(long)]
+        sort: bool,
+
+        /// Do not run localdoc-plugin-* executables
+        #[arg(long)]
+        no_plugins: bool,
+
+        /// Output findings as JSON: one object for a single docpack, an array for several
+        #[arg(long)]
+        json: bool,
+
+        /// Treat warnings as errors
+        #[arg(long)]
+        strict: bool,
+
+        /// Suppress findings with this code or name, e.g. DP010 or missing-tasks (repeatable)
+        #[arg(long, value_name = "CODE")]
+        ignore: Vec<String>,
+
+        /// Repair mechanical problems (missing output/, trailing commas, missing version, tools given as a string) before validating
+        #[arg(long, conflicts_with = "json")]
+        fix: bool,
+
+        /// With --fix, show the repairs without writing them
+        #[arg(long, requires = "fix")]
+        dry_run: bool,
+    },
+
+    /// List or export the JSON Schemas that validate checks docpack.json and tasks.json against
+    ///
+    /// Without --export, lists the built-in schemas. Map the exported files to
+    /// docpack.json and tasks.json in an editor to have them checked as you
+    /// type.
+    #[command(after_long_help = help::SCHEMA)]
+    Schema {
+        /// Write the schemas into this directory, e.g. for editor autocompletion
+        #[arg(long, value_name = "DIR")]
+        export: Option<String>,
+    },
+
+    /// Search a docpack's index/search.json, or look up definitions in index/symbols.json
+    ///
+    /// The query is tokenized like the index, so case and punctuation do not
+    /// matter, and files must contain every word. Matches are ranked by how
+    /// often the words occur and printed as path:line: snippet. --symbols lists
+    /// where a name is defined instead.
+    #[command(after_long_help = help::SEARCH)]
+    Search {
+        /// Path to a .docpack archive or extracted directory
         docpack: String,
+
+        /// Words to find; files must contain all of them
+        #[arg(required_unless_present = "symbols")]
+        query: Option<String>,
+
+        /// Find where a function, class, struct or other symbol is defined
+        #[arg(long, value_name = "NAME", conflicts_with = "query")]
+        symbols: Option<String>,
+
+        /// Maximum number of matching lines to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+
+        /// Only match whole words (by default a query word also matches words that start with it)
+        #[arg(long)]
+        exact: bool,
+
+        /// Also match words within one typo (two for words of 6+ characters); exact matches rank first
+        #[arg(long)]
+        fuzzy: bool,
+
+        /// Output results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Build or rebuild the search index of an existing docpack
+    ///
+    /// Files are selected with the same rules as ingest, reusing the filters
+    /// recorded in an existing index unless new ones are given. An existing
+    /// index is left alone without --force. Archives are rewritten in place
+    /// with their original compression.
+    #[command(after_long_help = help::INDEX)]
+    Index {
+        /// Path to a .docpack archive or extracted directory
+        docpack: String,
+
+        /// Also build the semantic graph (index/graph.json)
+        #[arg(long)]
+        graph: bool,
+
+        /// How to store the index (default: the existing index's format, or json); binary is several times smaller and faster to load
+        #[arg(long, value_enum)]
+        index_format: Option<search::IndexFormat>,
+
+        /// Also index lockfiles and minified files (*.min.*, or lines averaging over 500 characters)
+        #[arg(long)]
+        index_all: bool,
+
+        /// Replace an existing index
+        #[arg(long)]
+        force: bool,
+
+        /// Only index files matching this glob (repeatable; default: filters recorded in the index)
+        #[arg(long)]
+        include: Vec<String>,
+
+        /// Leave out files matching this glob (repeatable; wins over --include)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Index node_modules/, target/, .git/ and other dependency/build directories
+        #[arg(long)]
+        no_default_excludes: bool,
+
+        /// Skip files larger than this (e.g. 10MB); 0 disables the limit
+        #[arg(long, value_parser = size::parse_size)]
+        max_file_size: Option<u64>,
+
+        /// Index even if the docpack is sealed
+        #[arg(long)]
+        force_mutable: bool,
+    },
+
+    /// Query a docpack's index/graph.json (default: --stats)
+    ///
+    /// Paths are found breadth-first and may follow edges either way. An
+    /// unknown node id fails with the ids that contain it as suggestions.
+    #[command(after_long_help = help::GRAPH)]
+    Graph {
+        /// Path to a .docpack archive or extracted directory
+        docpack: String,
+
+        /// List the nodes linked to this node id
+        #[arg(long, value_name = "NODE")]
+        neighbors: Option<String>,
+
+        /// Shortest chain of edges between two node ids
+        #[arg(long, num_args = 2, value_names = ["FROM", "TO"], conflicts_with = "neighbors")]
+        path: Option<Vec<String>>,
+
+        /// Node and edge counts by type, and the most connected nodes
+        #[arg(long, conflicts_with_all = ["neighbors", "path"])]
+        stats: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Embed a docpack's text files into index/embeddings.bin for semantic search
+    ///
+    /// Text files are split into overlapping chunks and sent to an
+    /// OpenAI-compatible /embeddings endpoint, rate limited and retried on
+    /// errors. Finished batches are saved beside the docpack, so an
+    /// interrupted run picks up where it stopped.
+    #[command(after_long_help = help::EMBED)]
+    Embed {
+        /// Path to a .docpack archive or extracted directory
+        docpack: String,
+
+        /// OpenAI-compatible API base URL (default: $LOCALDOC_EMBED_API_BASE, embed.api_base, or https://api.openai.com/v1)
+        #[arg(long)]
+        api_base: Option<String>,
+
+        /// API key (default: $LOCALDOC_EMBED_API_KEY, $OPENAI_API_KEY, or embed.api_key)
+        #[arg(long)]
+        api_key: Option<String>,
+
+        /// Embedding model (default: $LOCALDOC_EMBED_MODEL, embed.model, or text-embedding-3-small)
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Chunk length in bytes
+        #[arg(long, default_value_t = 2000)]
+        chunk_size: usize,
+
+        /// Bytes each chunk repeats from the end of the previous one
+        #[arg(long, default_value_t = 200)]
+        chunk_overlap: usize,
+
+        /// Chunks sent in one request
+        #[arg(long, default_value_t = 64)]
+        batch_size: usize,
+
+        /// Most requests sent per minute; 0 disables the limit
+        #[arg(long, default_value_t = 60)]
+        requests_per_minute: u32,
+
+        /// Only embed files matching this glob (repeatable; default: filters recorded in the search index)
+        #[arg(long)]
+        include: Vec<String>,
+
+        /// Leave out files matching this glob (repeatable; wins over --include)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Report the number of chunks, tokens and requests without calling the API
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Replace existing embeddings
+        #[arg(long)]
+        force: bool,
+
+        /// Embed even if the docpack is sealed
+        #[arg(long)]
+        force_mutable: bool,
+    },
+
+    /// Find the chunks of a docpack closest in meaning to a query, using index/embeddings.bin
+    ///
+    /// The query is embedded with the same API settings as embed and compared
+    /// by cosine similarity; each result shows a snippet from files/.
+    #[command(after_long_help = help::SEMANTIC_SEARCH)]
+    SemanticSearch {
+        /// Path to a .docpack archive or extracted directory
+        docpack: String,
+
+        /// What to look for, in plain language
+        query: String,
+
+        /// Number of results to show
+        #[arg(long, default_value_t = 10)]
+        k: usize,
+
+        /// Only show results with at least this cosine similarity (-1 to 1)
+        #[arg(long)]
+        threshold: Option<f32>,
+
+        /// Output results as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// OpenAI-compatible API base URL (default: $LOCALDOC_EMBED_API_BASE, embed.api_base, or https://api.openai.com/v1)
+        #[arg(long)]
+        api_base: Option<String>,
+
+        /// API key (default: $LOCALDOC_EMBED_API_KEY, $OPENAI_API_KEY, or embed.api_key)
+        #[arg(long)]
+        api_key: Option<String>,
+
+        /// Embedding model (default: the model the docpack was embedded with)
+        #[arg(long)]
+        model: Option<String>,
+    },
+
+    /// Check files/ against the SHA-256 hashes recorded at ingest
+    ///
+    /// index/hashes.json itself is checked against metadata.hashes in
+    /// docpack.json. Exits non-zero if a file was added, removed or changed, or
+    /// if there is no hashes manifest.
+    #[command(after_long_help = help::VERIFY)]
+    Verify {
+        /// Path to a .docpack archive or extracted directory
+        docpack: String,
+
+        /// Output a machine-readable report
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Compare two docpacks' files, manifest, tasks and index; exits non-zero when they differ
+    ///
+    /// Files are compared by SHA-256, manifest fields one by one (except
+    /// metadata.created), tasks by id and index artifacts by presence. Each
+    /// side may be an archive, read in place, or a directory.
+    #[command(after_long_help = help::DIFF)]
+    Diff {
+        /// The docpack to compare from (archive or extracted directory)
+        old: String,
+
+        /// The docpack to compare to (archive or extracted directory)
+        new: String,
+
+        /// Output a machine-readable report
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Explore a docpack's files, manifest, tasks and outputs in a terminal UI
+    ///
+    /// A collapsible file tree with a preview of the selected file, and tabs
+    /// for the manifest, tasks and outputs. Archives are read in place. Press q
+    /// to quit.
+    #[command(after_long_help = help::BROWSE)]
+    Browse {
+        /// Path to a .docpack archive or extracted directory
+        docpack: String,
+    },
+
+    /// Serve a web UI for a docpack's files, outputs, manifest and tasks
+    ///
+    /// Reads an archive in place, keeping recently read entries in memory,
+    /// and needs no network access beyond the address it listens on.
+    #[command(after_long_help = help::SERVE)]
+    Serve {
+        /// Path to a .docpack archive or extracted directory
+        docpack: String,
+
+        /// Address to listen on; use 0.0.0.0 to allow other machines
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Port to listen on (0 picks a free one)
+        #[arg(short, long, default_value_t = 8080)]
+        port: u16,
+
+        /// Most archive data to keep in memory, e.g. 256MB
+        #[arg(long, default_value = "64MB", value_parser = size::parse_size)]
+        cache_size: u64,
+    },
+
+    /// Bring a docpack's files/ and index up to date with its source
+    ///
+    /// Files are compared by SHA-256: new and changed files are copied in and
+    /// deleted ones removed. The search index is patched for the changed files
+    /// only, hand edits to docpack.json and tasks.json are kept, and an archive
+    /// is rewritten in place.
+    #[command(after_long_help = help::UPDATE)]
+    Update {
+        /// Path to a .docpack archive or extracted directory
+        docpack: String,
+
+        /// Source directory to update from
+        #[arg(long)]
+        source: String,
+
+        /// Only include files matching this glob (repeatable; default: filters recorded at ingest)
+        #[arg(long)]
+        include: Vec<String>,
+
+        /// Leave out files matching this glob (repeatable; wins over --include)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Keep node_modules/, target/, .git/ and other dependency/build directories
+        #[arg(long)]
+        no_default_excludes: bool,
+
+        /// Skip files larger than this (e.g. 10MB); 0 disables the limit
+        #[arg(long, value_parser = size::parse_size)]
+        max_file_size: Option<u64>,
+
+        /// Update even if the docpack is sealed
+        #[arg(long)]
+        force_mutable: bool,
     },
 
     /// Initialize a new empty .docpack structure
+    ///
+    /// Writes docpack.json and a README, and creates files/, index/ and
+    /// output/. Put the sources under files/, then `localdoc pack` the
+    /// directory when it is ready.
+    #[command(after_long_help = help::INIT)]
     Init {
         /// Path for new .docpack directory
         path: String,
@@ -98,48 +886,978 @@ enum Commands {
         #[arg(long)]
         with_tasks: bool,
     },
+
+    /// Salvage entries from a .docpack archive with a damaged central directory
+    ///
+    /// Scans the archive for local file headers instead of trusting its central
+    /// directory, checks each entry's CRC, and writes what it can read to a
+    /// directory docpack marked with metadata.recovered.
+    #[command(after_long_help = help::RECOVER)]
+    Recover {
+        /// Path to the damaged .docpack archive
+        docpack: String,
+
+        /// Output directory for the recovered docpack
+        #[arg(short, long, default_value = "recovered")]
+        out: String,
+    },
+
+    /// Zip a .docpack directory into an archive, as ingest builds them
+    ///
+    /// The directory is validated first unless --no-validate is given. The
+    /// compression and layout recorded in its manifest are used, so unpacking
+    /// an archive and packing it again gives back the same bytes.
+    #[command(after_long_help = help::PACK)]
+    Pack {
+        /// Path to .docpack directory
+        dir: String,
+
+        /// Archive to write (default: <name>.docpack beside the directory)
+        #[arg(short, long)]
+        out: Option<String>,
+
+        /// Replace an existing archive
+        #[arg(long)]
+        force: bool,
+
+        /// Don't validate the directory first
+        #[arg(long)]
+        no_validate: bool,
+    },
+
+    /// Extract a .docpack archive into a directory
+    ///
+    /// Entries that would land outside the destination are refused, and a
+    /// deduplicated archive gets its usual files/ back. The archive is
+    /// extracted beside the destination first, so a damaged one leaves an
+    /// existing directory as it was.
+    #[command(after_long_help = help::UNPACK)]
+    Unpack {
+        /// Path to the .docpack archive
+        archive: String,
+
+        /// Directory to extract into (default: the archive's path without .docpack)
+        #[arg(short, long)]
+        out: Option<String>,
+
+        /// Replace a destination directory that is not empty
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Upload a docpack to a registry URL with an HTTP PUT
+    ///
+    /// Any HTTP server that stores what is PUT to a URL can be a registry. The
+    /// archive's SHA-256 goes in an X-Checksum-Sha256 header, and
+    /// $LOCALDOC_REGISTRY_TOKEN or registry.token as a bearer token. Larger
+    /// archives go up in resumable parts. Exits with 3 when the registry
+    /// refuses the token and 4 on network errors.
+    #[command(after_long_help = help::PUSH)]
+    Push {
+        /// Path to a .docpack archive or directory (a directory is packed first)
+        docpack: String,
+
+        /// Where to PUT the archive
+        url: String,
+
+        /// Upload larger archives in resumable parts of this size (e.g. 64MB)
+        #[arg(long, default_value = "64MB", value_parser = size::parse_size)]
+        chunk_size: u64,
+
+        /// Times to retry after a network error
+        #[arg(long, default_value_t = 3)]
+        retries: u32,
+    },
+
+    /// Download a docpack from a registry URL and verify its checksum
+    ///
+    /// Downloads into <file>.part, resuming it after a dropped connection, then
+    /// checks it against --sha256 or the registry's X-Checksum-Sha256 header.
+    /// Exits with 3 when the registry refuses the token, 4 on network errors
+    /// and 5 on a checksum mismatch.
+    #[command(after_long_help = help::PULL)]
+    Pull {
+        /// The archive's URL
+        url: String,
+
+        /// File to save it as (default: the URL's file name)
+        #[arg(short, long)]
+        out: Option<String>,
+
+        /// Expected SHA-256 (default: the registry's X-Checksum-Sha256 header)
+        #[arg(long)]
+        sha256: Option<String>,
+
+        /// Times to retry after a network error
+        #[arg(long, default_value_t = 3)]
+        retries: u32,
+
+        /// Replace an existing file
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Set a field in a .docpack's docpack.json (e.g. metadata.immutable true)
+    ///
+    /// The value is parsed as JSON, falling back to a plain string. Setting
+    /// metadata.immutable to true seals the docpack, and commands that modify
+    /// it then refuse without --force-mutable.
+    #[command(after_long_help = help::SET)]
+    Set {
+        /// Path to .docpack directory
+        docpack: String,
+
+        /// Dotted key path, e.g. metadata.language
+        key: String,
+
+        /// New value (parsed as JSON, falling back to a string)
+        value: String,
+
+        /// Allow modifying a sealed docpack
+        #[arg(long)]
+        force_mutable: bool,
+    },
+
+    /// Upgrade a .docpack directory's docpack.json to the current manifest version
+    ///
+    /// Fields are renamed and filled in step by step as each layout change
+    /// requires, and the original is kept beside it as
+    /// docpack.json.<version>.bak.
+    #[command(after_long_help = help::MIGRATE)]
+    Migrate {
+        /// Path to .docpack directory
+        docpack: String,
+
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// List recent runs with their status and duration
+    ///
+    /// Runs are read from $XDG_DATA_HOME/localdoc/history.jsonl (by default
+    /// ~/.local/share/localdoc/history.jsonl), newest first.
+    #[command(after_long_help = help::HISTORY)]
+    History {
+        /// Number of runs to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+
+        /// Print the records as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show what the agent will see for a run, without launching anything
+    ///
+    /// Renders the tools the agent can call, the enforced constraints, the
+    /// mounts, the opening prompt built from tasks.json and the start of the
+    /// file listing, as run would set them up.
+    #[command(after_long_help = help::PREVIEW_CONTEXT)]
+    PreviewContext {
+        /// Path to .docpack directory
+        docpack: String,
+
+        /// Only preview the context for this task id
+        #[arg(long)]
+        task: Option<String>,
+
+        /// Number of file listing entries to show
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+
+        /// Output the context as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Export a .docpack's run outputs as an mdBook or a static HTML site
+    ///
+    /// Collects the Markdown in output/, builds the navigation from file
+    /// names and headings, rewrites links between outputs to work in the
+    /// site and copies the images and files they reference.
+    #[command(after_long_help = help::EXPORT)]
+    Export {
+        /// Path to .docpack file or directory
+        docpack: String,
+
+        /// What to write
+        #[arg(long, value_enum, default_value = "mdbook")]
+        format: commands::export::ExportFormat,
+
+        /// Directory to write the site to
+        #[arg(short, long)]
+        out: String,
+
+        /// Replace an output directory that is not empty
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// List, add and remove the tasks in a .docpack's tasks.json
+    ///
+    /// Changes are validated before tasks.json is written; an archive is
+    /// rewritten in place.
+    #[command(after_long_help = help::TASKS)]
+    Tasks {
+        /// Path to .docpack file or directory
+        docpack: String,
+
+        #[command(subcommand)]
+        command: TasksCommand,
+    },
+
+    /// Work with a .docpack's generated outputs
+    ///
+    /// seed copies existing documentation into output/ for the agent to update,
+    /// and diff compares the outputs of archived runs.
+    #[command(after_long_help = help::OUTPUTS)]
+    Outputs {
+        #[command(subcommand)]
+        command: OutputsCommand,
+    },
+
+    /// Manage inspect/validate plugins
+    ///
+    /// A plugin is an executable on PATH named localdoc-plugin-<name>. inspect
+    /// and validate run each one and merge the sections and findings it prints
+    /// as JSON.
+    #[command(after_long_help = help::PLUGINS)]
+    Plugins {
+        #[command(subcommand)]
+        command: PluginsCommand,
+    },
+
+    /// Read and change defaults in ~/.config/localdoc/config.toml and .localdoc.toml
+    ///
+    /// A flag on the command line wins over the project's .localdoc.toml
+    /// (in the current directory or above it), which wins over the user's
+    /// config.toml, which wins over the built-in default.
+    #[command(after_long_help = help::CONFIG)]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Write a man page for localdoc and for each of its commands
+    ///
+    /// Writes localdoc.1 and a localdoc-<command>.1 page for every command
+    /// and subcommand, with its options, their defaults and the examples
+    /// --help shows, for packages to install under share/man/man1.
+    #[command(after_long_help = help::MAN)]
+    Man {
+        /// Directory to write the pages to (created if missing)
+        #[arg(long)]
+        out_dir: String,
+    },
+
+    /// Time ingest's phases on a source tree or on generated ones
+    ///
+    /// Scans, hashes, indexes and archives the tree one phase at a time,
+    /// reporting the wall time and peak memory of each. With --self-test it
+    /// generates synthetic trees of --files sizes instead, the same on every
+    /// run, so results from different builds can be compared with --baseline.
+    #[command(hide = true, after_long_help = help::BENCH)]
+    Bench {
+        /// Directory to time (required unless --self-test)
+        #[arg(required_unless_present = "self_test", conflicts_with = "self_test")]
+        source: Option<String>,
+
+        /// Time generated trees instead of a directory
+        #[arg(long)]
+        self_test: bool,
+
+        /// Sizes of the generated trees, in files, comma-separated
+        #[arg(long, value_delimiter = ',', default_value = "1000,10000")]
+        files: Vec<usize>,
+
+        /// Seed for the generated trees
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+
+        /// Write the results as JSON to this file
+        #[arg(long, value_name = "FILE")]
+        json: Option<String>,
+
+        /// Compare with the results an earlier --json wrote
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<String>,
+
+        /// Keep the trees and archives in this directory
+        #[arg(long, value_name = "DIR")]
+        keep: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Show the value of every key and the file it comes from
+    ///
+    /// Secret keys such as embed.api_key are not shown.
+    #[command(after_long_help = help::CONFIG_LIST)]
+    List,
+
+    /// Print the value that applies for a key, e.g. run.image
+    #[command(after_long_help = help::CONFIG_GET)]
+    Get {
+        /// Key as section.name
+        key: String,
+    },
+
+    /// Set a key in the user's config.toml
+    ///
+    /// --project writes to the nearest .localdoc.toml instead, creating one in
+    /// the current directory. The file's other keys and its comments are kept.
+    #[command(after_long_help = help::CONFIG_SET)]
+    Set {
+        /// Key as section.name, e.g. run.image
+        key: String,
+
+        /// New value; lists such as ingest.exclude are comma-separated
+        value: String,
+
+        /// Write to the project's .localdoc.toml instead
+        #[arg(long)]
+        project: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PluginsCommand {
+    /// Show the localdoc-plugin-* executables found on PATH
+    #[command(after_long_help = help::PLUGINS_LIST)]
+    List,
+}
+
+#[derive(Subcommand)]
+enum TasksCommand {
+    /// Print the id, name and output of every task
+    #[command(after_long_help = help::TASKS_LIST)]
+    List,
+
+    /// Print one task in full
+    ///
+    /// Fields localdoc does not know about are shown as they are.
+    #[command(after_long_help = help::TASKS_SHOW)]
+    Show {
+        /// Task id, e.g. task_2
+        id: String,
+    },
+
+    /// Append a task with the next free task_N id
+    ///
+    /// The task may use the tools in docpack.json environment.tools unless
+    /// --tools says otherwise, and writes output/<id>.md unless --output-path
+    /// is given. tasks.json is validated before it is written.
+    #[command(after_long_help = help::TASKS_ADD)]
+    Add {
+        /// What the task is called
+        #[arg(long)]
+        name: String,
+
+        /// What the agent should do
+        #[arg(long)]
+        description: String,
+
+        /// Tools the task may use, comma-separated (default: all tools in docpack.json environment.tools)
+        #[arg(long, value_delimiter = ',')]
+        tools: Option<Vec<String>>,
+
+        /// Where the task writes its result (default: output/<id>.md)
+        #[arg(long)]
+        output_path: Option<String>,
+
+        /// Allow modifying a sealed docpack
+        #[arg(long)]
+        force_mutable: bool,
+    },
+
+    /// Delete a task
+    ///
+    /// Refused while another task lists it in depends_on.
+    #[command(after_long_help = help::TASKS_REMOVE)]
+    Remove {
+        /// Task id, e.g. task_2
+        id: String,
+
+        /// Allow modifying a sealed docpack
+        #[arg(long)]
+        force_mutable: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum OutputsCommand {
+    /// Copy an existing docs directory into a docpack's output/
+    #[command(after_long_help = help::OUTPUTS_SEED)]
+    Seed {
+        /// Path to .docpack directory
+        docpack: String,
+
+        /// Directory of existing documentation
+        source: String,
+
+        /// Put seeded docs in output/seed/ instead of output/
+        #[arg(long)]
+        subdir: bool,
+
+        /// Allow modifying a sealed docpack
+        #[arg(long)]
+        force_mutable: bool,
+    },
+
+    /// Show how outputs changed between two archived runs
+    ///
+    /// Outputs are paired by path and labelled with the task that writes them.
+    /// Text outputs get unified diffs and binary ones are compared by SHA-256.
+    #[command(after_long_help = help::OUTPUTS_DIFF)]
+    Diff {
+        /// Path to .docpack directory
+        docpack: String,
+
+        /// Run id to compare from (default: the run before --to)
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Run id to compare to, or "current" for output/ (default: latest run)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Only show a summary of added/removed lines per file
+        #[arg(long)]
+        stat: bool,
+
+        /// Output the comparison as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Compression method for docpack archives.
+#[derive(Clone, Copy, ValueEnum)]
+enum Compression {
+    None,
+    Deflate,
+    Zstd,
+}
+
+impl Compression {
+    fn method(self) -> CompressionMethod {
+        match self {
+            Compression::None => CompressionMethod::Stored,
+            Compression::Deflate => CompressionMethod::Deflated,
+            Compression::Zstd => CompressionMethod::Zstd,
+        }
+    }
 }
 
 fn main() {
     let cli = Cli::parse();
+    // A global argument can't conflict with one that isn't, so -q -v is
+    // refused here rather than by clap
+    if cli.quiet && cli.verbose > 0 {
+        Cli::command()
+            .error(clap::error::ErrorKind::ArgumentConflict, "--quiet cannot be used with --verbose")
+            .exit();
+    }
+    let json_errors = matches!(cli.command, Commands::Ingest { json: true, .. });
+    // --json output is the whole of stdout, so the commentary is switched off
+    let json_output = matches!(
+        cli.command,
+        Commands::Ingest { json: true, .. }
+            | Commands::Run { json: true, .. }
+            | Commands::Doctor { json: true, .. }
+            | Commands::Validate { json: true, .. }
+            | Commands::Search { json: true, .. }
+            | Commands::Graph { json: true, .. }
+            | Commands::SemanticSearch { json: true, .. }
+            | Commands::Verify { json: true, .. }
+            | Commands::Diff { json: true, .. }
+            | Commands::History { json: true, .. }
+            | Commands::PreviewContext { json: true, .. }
+            | Commands::Outputs { command: OutputsCommand::Diff { json: true, .. } }
+    );
+    logging::init(cli.verbose, cli.quiet, cli.no_emoji, json_output);
+    let uses_config = matches!(
+        cli.command,
+        Commands::Ingest { .. } | Commands::Run { .. } | Commands::Doctor { .. } | Commands::Embed { .. } | Commands::SemanticSearch { .. }
+    );
+    let config = if uses_config {
+        config::load().unwrap_or_else(|e| fail(&e, json_errors, 1))
+    } else {
+        config::Config::default()
+    };
 
     let result = match &cli.command {
         Commands::Ingest {
-            source,
+            sources,
+            prefix,
             out,
             name,
             description,
             language,
             all_tools,
+            preset,
+            tools,
             build_index,
+            index_format,
+            index_all,
             build_graph,
+            seed_outputs,
+            seed_subdir,
+            include,
+            exclude,
+            no_default_excludes,
+            max_file_size,
+            max_docpack_size,
+            symlinks,
+            non_utf8,
+            no_git_metadata,
+            no_secret_scan,
+            fail_on_secrets,
+            json,
+            dedupe,
+            reproducible,
+            created,
+            compression,
+            compression_level,
+            max_file_reads,
+            max_execution_time,
+            memory_limit,
+            sha256,
+            tasks_file,
+            mission,
+            strict,
         } => commands::ingest::run(
-            source,
-            out,
-            name.as_deref(),
-            description.as_deref(),
-            language.as_deref(),
-            *all_tools,
-            *build_index,
-            *build_graph,
+            sources,
+            &commands::ingest::IngestOptions {
+                out,
+                name: name.as_deref(),
+                description: description.as_deref(),
+                language: language.as_deref(),
+                preset: if *all_tools { "full" } else { preset.as_deref().or(config.preset()).unwrap_or(tools::DEFAULT_PRESET) },
+                tools,
+                build_index: *build_index,
+                index_format: *index_format,
+                index_all: *index_all,
+                build_graph: *build_graph,
+                seed_outputs: seed_outputs.as_deref(),
+                seed_subdir: *seed_subdir,
+                include,
+                exclude: &if exclude.is_empty() { config.excludes().unwrap_or_default() } else { exclude.clone() },
+                default_excludes: !*no_default_excludes,
+                max_file_size: Some(max_file_size.or(config.max_file_size()).unwrap_or(commands::ingest::DEFAULT_MAX_FILE_SIZE))
+                    .filter(|&limit| limit > 0),
+                max_docpack_size: Some(*max_docpack_size).filter(|&limit| limit > 0),
+                symlinks: *symlinks,
+                non_utf8: *non_utf8,
+                git_metadata: !*no_git_metadata,
+                secret_scan: !*no_secret_scan,
+                fail_on_secrets: *fail_on_secrets,
+                quiet: cli.quiet || *json,
+                json: *json,
+                dedupe: *dedupe,
+                reproducible: *reproducible,
+                created: *created,
+                compression: compression.method(),
+                compression_level: *compression_level,
+                constraints: {
+                    let defaults = constraints::Constraints::default();
+                    constraints::Constraints {
+                        max_file_reads: max_file_reads.or(defaults.max_file_reads),
+                        max_execution_time_seconds: max_execution_time.or(defaults.max_execution_time_seconds),
+                        memory_limit_mb: memory_limit.or(defaults.memory_limit_mb),
+                        ..defaults
+                    }
+                },
+                sha256: sha256.as_deref(),
+                tasks_file: tasks_file.as_deref(),
+                mission: mission.as_deref(),
+                strict: *strict,
+                prefixes: prefix,
+            },
         ),
         Commands::Run {
-            docpack,
+            docpacks,
             image,
             follow,
+            env,
             env_file,
-        } => commands::run::run(docpack, image, *follow, env_file.as_deref()),
-        Commands::Inspect { docpack, verbose } => commands::inspect::run(docpack, *verbose),
-        Commands::Validate { docpack } => commands::validate::run(docpack),
+            no_env,
+            max_output_bytes,
+            max_output_file_bytes,
+            runtime,
+            output_dir,
+            update_archive,
+            memory,
+            cpus,
+            timeout,
+            writable_files,
+            no_network,
+            dry_run,
+            pull,
+            tasks,
+            list_tasks,
+            json,
+            detach,
+            jobs,
+            log_dir,
+            user,
+            no_user_mapping,
+            no_validate,
+            strict,
+        } => {
+            let opts = commands::run::RunOptions {
+                image: image.as_deref().or(config.image()).unwrap_or(runtime::DEFAULT_IMAGE),
+                follow: *follow,
+                env,
+                env_file: env_file.as_deref(),
+                no_env: *no_env,
+                max_output_bytes: *max_output_bytes,
+                max_output_file_bytes: *max_output_file_bytes,
+                runtime: runtime.or(config.runtime()),
+                output_dir: output_dir.as_deref(),
+                update_archive: *update_archive,
+                memory_mb: memory.or(config.memory_mb()),
+                cpus: *cpus,
+                timeout_seconds: *timeout,
+                writable_files: *writable_files,
+                no_network: *no_network,
+                dry_run: *dry_run,
+                pull: *pull,
+                quiet: cli.quiet,
+                tasks,
+                list_tasks: *list_tasks,
+                json: *json,
+                detach: *detach,
+                user: user.as_deref(),
+                no_user_mapping: *no_user_mapping,
+                no_validate: *no_validate,
+                strict: *strict,
+            };
+            if commands::batch::is_batch(docpacks, log_dir.as_deref()) {
+                commands::batch::run(docpacks, *jobs, log_dir.as_deref(), &opts)
+            } else {
+                commands::run::run(&docpacks[0], &opts)
+            }
+        }
+        Commands::Watch { source, out, debounce, run_args } => commands::watch::run(&commands::watch::WatchOptions {
+            source,
+            out,
+            debounce: std::time::Duration::from_millis(*debounce),
+            run_args,
+        }),
+        Commands::Doctor { runtime, image, docpack, json } => commands::doctor::run(&commands::doctor::DoctorOptions {
+            runtime: runtime.or(config.runtime()),
+            image: image.as_deref().or(config.image()).unwrap_or(runtime::DEFAULT_IMAGE),
+            docpack: docpack.as_deref(),
+            json: *json,
+        }),
+        Commands::Logs { docpack, follow } => commands::logs::run(docpack, *follow),
+        Commands::Stop { docpack } => commands::stop::run(docpack),
+        Commands::Clean {
+            docpack,
+            temp,
+            older_than,
+            yes,
+            dry_run,
+            force_mutable,
+        } => match docpack {
+            Some(docpack) if !*temp => commands::clean::outputs(
+                docpack,
+                &commands::clean::CleanOptions { yes: *yes, dry_run: *dry_run, force_mutable: *force_mutable },
+            ),
+            _ => commands::clean::temp(&commands::clean::TempOptions { older_than: *older_than, dry_run: *dry_run }),
+        },
+        Commands::Inspect {
+            docpack,
+            top,
+            no_plugins,
+            report,
+        } => commands::inspect::run(docpack, cli.verbose > 0, *top, *no_plugins, report.as_deref(), cli.quiet),
+        Commands::List { docpack, prefix, glob, long } => commands::files::list(
+            docpack,
+            &commands::files::ListOptions { prefix: prefix.as_deref(), glob: glob.as_deref(), long: *long },
+        ),
+        Commands::Cat { docpack, path, text, force } => {
+            commands::files::cat(docpack, path, &commands::files::CatOptions { text: *text, force: *force })
+        }
+        Commands::Validate {
+            docpacks,
+            parallel,
+            sort,
+            json,
+            no_plugins,
+            strict,
+            ignore,
+            fix,
+            dry_run,
+        } => commands::validate::run(
+            docpacks,
+            &commands::validate::ValidateOptions {
+                parallel: *parallel,
+                sort: *sort,
+                json: *json,
+                no_plugins: *no_plugins,
+                strict: *strict,
+                ignore: ignore.clone(),
+                fix: *fix,
+                dry_run: *dry_run,
+            },
+        ),
+        Commands::Schema { export } => commands::schema::run(export.as_deref()),
+        Commands::Search { docpack, query, symbols, limit, exact, fuzzy, json } => match (symbols, query) {
+            (Some(name), _) => commands::search::symbols(docpack, name, *limit, *json),
+            (None, query) => commands::search::run(
+                docpack,
+                query.as_deref().unwrap_or_default(),
+                &commands::search::SearchOptions { limit: *limit, exact: *exact, fuzzy: *fuzzy, json: *json },
+            ),
+        },
+        Commands::Index {
+            docpack,
+            graph,
+            index_format,
+            index_all,
+            force,
+            include,
+            exclude,
+            no_default_excludes,
+            max_file_size,
+            force_mutable,
+        } => commands::index::run(
+            docpack,
+            &commands::index::IndexOptions {
+                graph: *graph,
+                format: *index_format,
+                index_all: *index_all,
+                force: *force,
+                include,
+                exclude,
+                no_default_excludes: *no_default_excludes,
+                max_file_size: *max_file_size,
+                quiet: cli.quiet,
+                force_mutable: *force_mutable,
+            },
+        ),
+        Commands::Graph { docpack, neighbors, path, stats: _, json } => {
+            let query = match (neighbors, path) {
+                (Some(node), _) => commands::graph::Query::Neighbors(node),
+                (None, Some(ends)) => commands::graph::Query::Path(&ends[0], &ends[1]),
+                (None, None) => commands::graph::Query::Stats,
+            };
+            commands::graph::run(docpack, query, *json)
+        }
+        Commands::Embed {
+            docpack,
+            api_base,
+            api_key,
+            model,
+            chunk_size,
+            chunk_overlap,
+            batch_size,
+            requests_per_minute,
+            include,
+            exclude,
+            dry_run,
+            force,
+            force_mutable,
+        } => commands::embed::run(
+            docpack,
+            &commands::embed::EmbedOptions {
+                api_base: api_base.as_deref().or(config.api_base()),
+                api_key: api_key.as_deref().or(config.api_key()),
+                model: model.as_deref().or(config.model()),
+                chunk_size: *chunk_size,
+                chunk_overlap: *chunk_overlap,
+                batch_size: *batch_size,
+                requests_per_minute: *requests_per_minute,
+                include,
+                exclude,
+                dry_run: *dry_run,
+                force: *force,
+                quiet: cli.quiet,
+                force_mutable: *force_mutable,
+            },
+        ),
+        Commands::SemanticSearch {
+            docpack,
+            query,
+            k,
+            threshold,
+            json,
+            api_base,
+            api_key,
+            model,
+        } => commands::semantic_search::run(
+            docpack,
+            query,
+            &commands::semantic_search::SemanticSearchOptions {
+                api_base: api_base.as_deref().or(config.api_base()),
+                api_key: api_key.as_deref().or(config.api_key()),
+                model: model.as_deref(),
+                k: *k,
+                threshold: *threshold,
+                json: *json,
+            },
+        ),
+        Commands::Verify { docpack, json } => commands::verify::run(docpack, *json),
+        Commands::Diff { old, new, json } => commands::diff::run(old, new, cli.verbose > 0, *json),
+        Commands::Browse { docpack } => commands::browse::run(docpack),
+        Commands::Serve { docpack, host, port, cache_size } => commands::serve::serve(
+            docpack,
+            &commands::serve::ServeOptions { host, port: *port, cache_size: *cache_size },
+        ),
+        Commands::Update {
+            docpack,
+            source,
+            include,
+            exclude,
+            no_default_excludes,
+            max_file_size,
+            force_mutable,
+        } => commands::update::run(
+            docpack,
+            &commands::update::UpdateOptions {
+                source,
+                include,
+                exclude,
+                no_default_excludes: *no_default_excludes,
+                max_file_size: *max_file_size,
+                quiet: cli.quiet,
+                force_mutable: *force_mutable,
+            },
+        ),
         Commands::Init {
             path,
             name,
             with_tasks,
         } => commands::init::run(path, name.as_deref(), *with_tasks),
+        Commands::Recover { docpack, out } => commands::recover::run(docpack, out),
+        Commands::Pack { dir, out, force, no_validate } => commands::pack::pack(
+            dir,
+            &commands::pack::PackOptions { out: out.as_deref(), force: *force, no_validate: *no_validate },
+        ),
+        Commands::Unpack { archive, out, force } => {
+            commands::pack::unpack(archive, &commands::pack::UnpackOptions { out: out.as_deref(), force: *force })
+        }
+        Commands::Push {
+            docpack,
+            url,
+            chunk_size,
+            retries,
+        } => commands::registry::push(
+            docpack,
+            url,
+            &commands::registry::PushOptions {
+                token: config.registry_token(),
+                chunk_size: *chunk_size,
+                retries: *retries,
+                quiet: cli.quiet,
+            },
+        ),
+        Commands::Pull {
+            url,
+            out,
+            sha256,
+            retries,
+            force,
+        } => commands::registry::pull(
+            url,
+            &commands::registry::PullOptions {
+                out: out.as_deref(),
+                sha256: sha256.as_deref(),
+                token: config.registry_token(),
+                retries: *retries,
+                force: *force,
+                quiet: cli.quiet,
+            },
+        ),
+        Commands::Set {
+            docpack,
+            key,
+            value,
+            force_mutable,
+        } => commands::set::run(docpack, key, value, *force_mutable),
+        Commands::Migrate { docpack, dry_run } => commands::migrate::run(docpack, *dry_run),
+        Commands::History { limit, json } => commands::history::run(*limit, *json),
+        Commands::PreviewContext {
+            docpack,
+            task,
+            limit,
+            json,
+        } => commands::preview_context::run(docpack, task.as_deref(), *limit, *json),
+        Commands::Export { docpack, format, out, force } => {
+            commands::export::export(docpack, &commands::export::ExportOptions { format: *format, out, force: *force })
+        }
+        Commands::Tasks { docpack, command } => match command {
+            TasksCommand::List => commands::tasks::list(docpack),
+            TasksCommand::Show { id } => commands::tasks::show(docpack, id),
+            TasksCommand::Add {
+                name,
+                description,
+                tools,
+                output_path,
+                force_mutable,
+            } => commands::tasks::add(
+                docpack,
+                &commands::tasks::AddOptions {
+                    name,
+                    description,
+                    tools: tools.as_deref(),
+                    output_path: output_path.as_deref(),
+                    force_mutable: *force_mutable,
+                },
+            ),
+            TasksCommand::Remove { id, force_mutable } => commands::tasks::remove(docpack, id, *force_mutable),
+        },
+        Commands::Outputs { command } => match command {
+            OutputsCommand::Seed {
+                docpack,
+                source,
+                subdir,
+                force_mutable,
+            } => commands::outputs::seed(docpack, source, *subdir, *force_mutable),
+            OutputsCommand::Diff {
+                docpack,
+                from,
+                to,
+                stat,
+                json,
+            } => commands::outputs::diff(docpack, from.as_deref(), to.as_deref(), *stat, *json),
+        },
+        Commands::Plugins { command } => match command {
+            PluginsCommand::List => commands::plugins::list(),
+        },
+        Commands::Config { command } => match command {
+            ConfigCommand::List => commands::config::list(),
+            ConfigCommand::Get { key } => commands::config::get(key),
+            ConfigCommand::Set { key, value, project } => commands::config::set(key, value, *project),
+        },
+        Commands::Man { out_dir } => commands::man::run(Cli::command(), out_dir),
+        Commands::Bench { source, self_test: _, files, seed, json, baseline, keep } => {
+            commands::bench::run(&commands::bench::BenchOptions {
+                source: source.as_deref(),
+                sizes: files,
+                seed: *seed,
+                json_out: json.as_deref(),
+                baseline: baseline.as_deref(),
+                keep: keep.as_deref(),
+            })
+        }
     };
 
     if let Err(e) = result {
+        let message = if cli.verbose > 0 { e.chain() } else { e.to_string() };
+        fail(&message, json_errors, e.exit_code());
+    }
+}
+
+fn fail(e: &dyn std::fmt::Display, json_errors: bool, code: i32) -> ! {
+    if json_errors {
+        eprintln!("{}", serde_json::json!({ "error": e.to_string() }));
+    } else {
         eprintln!("Error: {}", e);
-        process::exit(1);
     }
+    process::exit(code);
 }
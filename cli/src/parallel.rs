@@ -0,0 +1,33 @@
+use std::sync::mpsc;
+use rayon::prelude::*;
+
+/// Results that may wait for the calling thread before workers block.
+const QUEUE_DEPTH: usize = 256;
+
+/// Run `work` over `items` on the rayon thread pool and hand each result
+/// to `each` on the calling thread, in completion order, so progress and
+/// merging happen in one place. The queue between them is bounded, so
+/// workers can't run ahead and buffer the whole input in memory. Stops at
+/// the first error and returns it.
+pub fn for_each_result<'a, T, R, E>(
+    items: &'a [T],
+    work: impl Fn(&'a T) -> Result<R, E> + Send + Sync,
+    mut each: impl FnMut(R),
+) -> Result<(), E>
+where
+    T: Sync,
+    R: Send,
+    E: Send,
+{
+    let (tx, rx) = mpsc::sync_channel(QUEUE_DEPTH);
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            // Sending fails once the receiver has stopped at an error
+            let _ = items.par_iter().try_for_each_with(tx, |tx, item| tx.send(work(item)).map_err(|_| ()));
+        });
+        for result in rx {
+            each(result?);
+        }
+        Ok(())
+    })
+}
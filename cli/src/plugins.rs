@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use serde_json::Value;
+
+/// Executables on PATH whose names start with this are plugins.
+pub const PREFIX: &str = "localdoc-plugin-";
+
+/// How long a plugin may run before it is killed.
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Overrides the plugin timeout, in seconds.
+const TIMEOUT_ENV: &str = "LOCALDOC_PLUGIN_TIMEOUT";
+
+/// A discovered plugin executable.
+pub struct Plugin {
+    /// Name without the `localdoc-plugin-` prefix
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Which command is invoking the plugin.
+#[derive(Clone, Copy)]
+pub enum Mode {
+    Inspect,
+    Validate,
+}
+
+impl Mode {
+    fn as_str(self) -> &'static str {
+        match self {
+            Mode::Inspect => "inspect",
+            Mode::Validate => "validate",
+        }
+    }
+}
+
+/// A report section returned by a plugin in inspect mode.
+pub struct Section {
+    pub title: String,
+    pub lines: Vec<(String, String)>,
+}
+
+/// A finding returned by a plugin in validate mode.
+pub struct Finding {
+    pub code: String,
+    pub severity: String,
+    pub message: String,
+}
+
+/// Find plugins on PATH. When the same name appears in several PATH
+/// directories, the first one wins, as it would for the shell.
+pub fn discover() -> Vec<Plugin> {
+    let mut found: BTreeMap<String, PathBuf> = BTreeMap::new();
+
+    let Some(path_var) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+
+    for dir in std::env::split_paths(&path_var) {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let Some(name) = file_name.strip_prefix(PREFIX) else { continue };
+            let name = name.trim_end_matches(".exe").to_string();
+            if name.is_empty() || found.contains_key(&name) || !is_executable(&entry.path()) {
+                continue;
+            }
+            found.insert(name, entry.path());
+        }
+    }
+
+    found.into_iter().map(|(name, path)| Plugin { name, path }).collect()
+}
+
+/// Run a plugin in inspect mode and parse its sections.
+pub fn inspect(plugin: &Plugin, docpack: &Path) -> Result<Vec<Section>, String> {
+    let output = invoke(plugin, docpack, Mode::Inspect)?;
+    let sections = output["sections"]
+        .as_array()
+        .ok_or("output has no \"sections\" array")?;
+
+    sections
+        .iter()
+        .map(|section| {
+            let title = section["title"].as_str().ok_or("section without a \"title\"")?;
+            let lines = section["lines"]
+                .as_array()
+                .map(|lines| {
+                    lines
+                        .iter()
+                        .map(|line| (display(&line["key"]), display(&line["value"])))
+                        .collect()
+                })
+                .unwrap_or_default();
+            Ok(Section { title: title.to_string(), lines })
+        })
+        .collect()
+}
+
+/// Run a plugin in validate mode and parse its findings.
+pub fn validate(plugin: &Plugin, docpack: &Path) -> Result<Vec<Finding>, String> {
+    let output = invoke(plugin, docpack, Mode::Validate)?;
+    let findings = output["findings"]
+        .as_array()
+        .ok_or("output has no \"findings\" array")?;
+
+    findings
+        .iter()
+        .map(|finding| {
+            let severity = finding["severity"].as_str().unwrap_or("warning");
+            if !["error", "warning", "info"].contains(&severity) {
+                return Err(format!("unknown severity '{}'", severity));
+            }
+            Ok(Finding {
+                code: finding["code"].as_str().unwrap_or("PLUGIN").to_string(),
+                severity: severity.to_string(),
+                message: display(&finding["message"]),
+            })
+        })
+        .collect()
+}
+
+/// Run `plugin --docpack <path> --mode <mode> --format json` and parse its
+/// stdout as JSON, killing it if it exceeds the timeout.
+fn invoke(plugin: &Plugin, docpack: &Path, mode: Mode) -> Result<Value, String> {
+    let mut child = Command::new(&plugin.path)
+        .arg("--docpack")
+        .arg(docpack)
+        .arg("--mode")
+        .arg(mode.as_str())
+        .arg("--format")
+        .arg("json")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to start: {}", e))?;
+
+    // Drain the pipes on threads so a chatty plugin cannot block on a full pipe
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stdout_reader = std::thread::spawn(move || {
+        let mut buf = String::new();
+        stdout.read_to_string(&mut buf).map(|_| buf)
+    });
+    let stderr_reader = std::thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stderr.read_to_string(&mut buf);
+        buf
+    });
+
+    let timeout = timeout();
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timed out after {}s", timeout.as_secs()));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+            Err(e) => return Err(format!("failed to wait: {}", e)),
+        }
+    };
+
+    let stdout = stdout_reader
+        .join()
+        .map_err(|_| "failed to read output".to_string())?
+        .map_err(|e| format!("failed to read output: {}", e))?;
+    let stderr = stderr_reader.join().unwrap_or_default();
+
+    if !status.success() {
+        let detail = stderr.lines().last().unwrap_or("").trim();
+        return Err(match status.code() {
+            Some(code) if detail.is_empty() => format!("exited with status {}", code),
+            Some(code) => format!("exited with status {}: {}", code, detail),
+            None => "terminated by a signal".to_string(),
+        });
+    }
+
+    serde_json::from_str(&stdout).map_err(|e| format!("invalid JSON output: {}", e))
+}
+
+fn timeout() -> Duration {
+    let secs = std::env::var(TIMEOUT_ENV)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
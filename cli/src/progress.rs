@@ -0,0 +1,135 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::logging::CONSOLE;
+use crate::size::format_size;
+
+/// Redraw interval for the in-place progress line on a terminal.
+const BAR_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Interval between plain-text progress lines when stdout is not a terminal.
+const PLAIN_INTERVAL: Duration = Duration::from_secs(5);
+
+const BAR_WIDTH: usize = 30;
+
+/// Whether a bar is drawn on the current line, for log lines to clear first.
+static BAR_SHOWN: AtomicBool = AtomicBool::new(false);
+
+#[derive(PartialEq)]
+enum Style {
+    Hidden,
+    /// Redrawn in place with `\r` on a terminal
+    Bar,
+    /// Periodic full lines, for CI logs
+    Plain,
+}
+
+/// Files-and-bytes progress for one phase of a long operation.
+pub struct Progress {
+    label: String,
+    /// Expected files, or expected bytes when `bytes_only`
+    total: Option<u64>,
+    files: u64,
+    bytes: u64,
+    /// Count bytes alone, for transfers of a single file
+    bytes_only: bool,
+    style: Style,
+    last_draw: Instant,
+}
+
+impl Progress {
+    /// Start a phase. `total` is the expected number of files, if known.
+    pub fn new(label: &str, total: Option<u64>, quiet: bool) -> Self {
+        // Hidden along with the commentary by -q and --json
+        let style = if quiet || !log::log_enabled!(log::Level::Info) {
+            Style::Hidden
+        } else if io::stdout().is_terminal() {
+            Style::Bar
+        } else {
+            Style::Plain
+        };
+
+        Progress {
+            label: label.to_string(),
+            total,
+            files: 0,
+            bytes: 0,
+            bytes_only: false,
+            style,
+            last_draw: Instant::now(),
+        }
+    }
+
+    /// Start a phase that only counts bytes. `total` is the expected size,
+    /// if known.
+    pub fn bytes(label: &str, total: Option<u64>, quiet: bool) -> Self {
+        Progress { bytes_only: true, ..Progress::new(label, total, quiet) }
+    }
+
+    /// Count one more file of `bytes` bytes.
+    pub fn inc(&mut self, bytes: u64) {
+        self.update(self.files + 1, self.bytes + bytes);
+    }
+
+    /// Set the running totals.
+    pub fn update(&mut self, files: u64, bytes: u64) {
+        self.files = files;
+        self.bytes = bytes;
+
+        let interval = match self.style {
+            Style::Hidden => return,
+            Style::Bar => BAR_INTERVAL,
+            Style::Plain => PLAIN_INTERVAL,
+        };
+        if self.last_draw.elapsed() >= interval {
+            self.draw();
+            self.last_draw = Instant::now();
+        }
+    }
+
+    /// End the phase, clearing the progress line.
+    pub fn finish(self) {
+        if self.style == Style::Bar {
+            let _console = CONSOLE.lock().unwrap_or_else(|e| e.into_inner());
+            clear_bar();
+        }
+    }
+
+    fn draw(&self) {
+        let (done, counts) = match (self.bytes_only, self.total) {
+            (true, Some(total)) => (self.bytes, format!("{} of {}", format_size(self.bytes), format_size(total))),
+            (true, None) => (self.bytes, format_size(self.bytes)),
+            (false, Some(total)) => (self.files, format!("{}/{} files, {}", self.files, total, format_size(self.bytes))),
+            (false, None) => (self.files, format!("{} files, {}", self.files, format_size(self.bytes))),
+        };
+
+        match self.style {
+            Style::Bar => {
+                let bar = match self.total {
+                    Some(total) if total > 0 => {
+                        let filled = ((done.min(total) as u128 * BAR_WIDTH as u128) / total as u128) as usize;
+                        format!("[{}{}] ", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled))
+                    }
+                    _ => String::new(),
+                };
+                let _console = CONSOLE.lock().unwrap_or_else(|e| e.into_inner());
+                print!("\r\x1b[K  {}: {}{}", self.label, bar, counts);
+                let _ = io::stdout().flush();
+                BAR_SHOWN.store(true, Ordering::Relaxed);
+            }
+            Style::Plain => log::info!("  {}: {}", self.label, counts),
+            Style::Hidden => {}
+        }
+    }
+}
+
+/// Erase the progress bar, if one is drawn, so a line can be written in
+/// its place. The bar comes back at its next redraw. Called with
+/// [`CONSOLE`] held.
+pub fn clear_bar() {
+    if BAR_SHOWN.swap(false, Ordering::Relaxed) {
+        print!("\r\x1b[K");
+        let _ = io::stdout().flush();
+    }
+}
@@ -0,0 +1,245 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Longest description taken from a README, in characters.
+const MAX_DESCRIPTION_CHARS: usize = 300;
+
+/// README names checked at the source root, in order of preference.
+const README_NAMES: &[&str] = &["README.md", "README.markdown", "README.rst", "README.txt", "README"];
+
+/// The first prose paragraph of the README at `root`, flattened to one
+/// line and truncated, or `None` when there is no README or it has no
+/// paragraph outside headings, badges, HTML and code.
+pub fn readme_description(root: &Path) -> Option<String> {
+    let path = README_NAMES.iter().find_map(|name| find_file(root, name))?;
+    let content = fs::read_to_string(path).ok()?;
+    first_paragraph(&content).map(|text| truncate(&text, MAX_DESCRIPTION_CHARS))
+}
+
+/// SPDX identifier guessed from the license file at `root`, if there is
+/// one and it is recognizable.
+pub fn license(root: &Path) -> Option<&'static str> {
+    let path = fs::read_dir(root).ok()?.filter_map(|entry| entry.ok()).find_map(|entry| {
+        let name = entry.file_name().to_string_lossy().to_uppercase();
+        let is_license = ["LICENSE", "LICENCE", "COPYING"].iter().any(|prefix| name.starts_with(prefix));
+        (is_license && entry.path().is_file()).then(|| entry.path())
+    })?;
+    let text = fs::read_to_string(path).ok()?;
+    guess_spdx(&text)
+}
+
+/// Language of a source file, from its extension.
+pub fn language(name: &str) -> Option<&'static str> {
+    let ext = Path::new(name).extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "rs" => "Rust",
+        "py" | "pyi" => "Python",
+        "js" | "mjs" | "cjs" | "jsx" => "JavaScript",
+        "ts" | "tsx" => "TypeScript",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "C++",
+        "cs" => "C#",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "swift" => "Swift",
+        "scala" => "Scala",
+        "sh" | "bash" | "zsh" => "Shell",
+        "html" | "htm" => "HTML",
+        "css" | "scss" | "sass" => "CSS",
+        "md" | "markdown" => "Markdown",
+        "rst" => "reStructuredText",
+        "json" => "JSON",
+        "yaml" | "yml" => "YAML",
+        "toml" => "TOML",
+        "xml" => "XML",
+        "sql" => "SQL",
+        "lua" => "Lua",
+        "svelte" => "Svelte",
+        "vue" => "Vue",
+        _ => return None,
+    })
+}
+
+fn find_file(root: &Path, name: &str) -> Option<PathBuf> {
+    fs::read_dir(root)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_name().to_string_lossy().eq_ignore_ascii_case(name) && entry.path().is_file())
+        .map(|entry| entry.path())
+}
+
+fn first_paragraph(content: &str) -> Option<String> {
+    let mut in_code = false;
+    let mut paragraph: Vec<&str> = Vec::new();
+
+    for line in content.lines().chain(std::iter::once("")) {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            paragraph.clear();
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        if !trimmed.is_empty() {
+            paragraph.push(trimmed);
+            continue;
+        }
+        // A heading or badge line directly above the text doesn't count
+        let start = paragraph.iter().position(|line| is_prose(line)).unwrap_or(paragraph.len());
+        let text = &paragraph[start..];
+        if !text.is_empty() && text.iter().all(|line| is_prose(line)) {
+            return Some(clean(&text.join(" ")));
+        }
+        paragraph.clear();
+    }
+    None
+}
+
+/// Whether a README line is ordinary text rather than a heading, badge,
+/// HTML, list item, directive or rule.
+fn is_prose(line: &str) -> bool {
+    let is_rule = line.len() >= 3 && line.chars().all(|c| "=-~^*#+_".contains(c));
+    !(line.starts_with('#')
+        || line.starts_with('<')
+        || line.starts_with("[![")
+        || line.starts_with("![")
+        || line.starts_with("..")
+        || line.starts_with('|')
+        || line.starts_with('>')
+        || line.starts_with("- ")
+        || line.starts_with("* ")
+        || is_rule)
+}
+
+/// Strip the most common inline markup: links become their text, and
+/// bold and code markers are dropped.
+fn clean(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        let Some(close) = rest[open..].find("](").map(|i| open + i) else { break };
+        let Some(end) = rest[close..].find(')').map(|i| close + i) else { break };
+        out.push_str(&rest[..open]);
+        out.push_str(&rest[open + 1..close]);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out.replace("**", "").replace('`', "")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    // Break at a word when there is one reasonably close
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > max_chars / 2 => &cut[..space],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end_matches([',', ';', ':', ' ']))
+}
+
+fn guess_spdx(text: &str) -> Option<&'static str> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let has = |needle: &str| text.contains(needle);
+
+    Some(if has("GNU AFFERO GENERAL PUBLIC LICENSE") {
+        "AGPL-3.0"
+    } else if has("GNU LESSER GENERAL PUBLIC LICENSE") {
+        if has("Version 2.1") { "LGPL-2.1" } else { "LGPL-3.0" }
+    } else if has("GNU GENERAL PUBLIC LICENSE") {
+        if has("Version 2,") || has("Version 2 ") { "GPL-2.0" } else { "GPL-3.0" }
+    } else if has("Apache License") && has("Version 2.0") {
+        "Apache-2.0"
+    } else if has("Mozilla Public License") && has("2.0") {
+        "MPL-2.0"
+    } else if has("Permission is hereby granted, free of charge") {
+        "MIT"
+    } else if has("Permission to use, copy, modify, and/or distribute") || has("ISC License") {
+        "ISC"
+    } else if has("Redistribution and use in source and binary forms") {
+        if has("Neither the name") || has("names of its contributors") { "BSD-3-Clause" } else { "BSD-2-Clause" }
+    } else if has("free and unencumbered software released into the public domain") {
+        "Unlicense"
+    } else if has("CC0 1.0 Universal") {
+        "CC0-1.0"
+    } else {
+        return None;
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn description_skips_headings_badges_and_html() {
+        let readme = "# Widget\n\n\
+            [![Build](https://ci.example.com/badge.svg)](https://ci.example.com) [![Docs](https://docs.rs/badge.svg)](https://docs.rs)\n\n\
+            <p align=\"center\">\n  <img src=\"logo.png\" width=\"200\">\n</p>\n\n\
+            ```sh\ncargo install widget\n```\n\n\
+            Widget turns **Markdown** into [docpacks](https://example.com/docpack)\n\
+            with `widget build`.\n\n\
+            ## Install\n\nRun the installer.\n";
+        assert_eq!(first_paragraph(readme).as_deref(), Some("Widget turns Markdown into docpacks with widget build."));
+
+        // Text right under a heading counts; a setext title is not prose
+        assert_eq!(first_paragraph("# Widget\nA tool for docs.\n").as_deref(), Some("A tool for docs."));
+        assert_eq!(first_paragraph("Widget\n======\n\nA tool for docs.\n").as_deref(), Some("A tool for docs."));
+    }
+
+    #[test]
+    fn readmes_without_prose_have_no_description() {
+        assert_eq!(first_paragraph(""), None);
+        assert_eq!(first_paragraph("\n\n"), None);
+        assert_eq!(first_paragraph("# Widget\n\n[![Build](https://ci.example.com/badge.svg)](https://ci.example.com)\n"), None);
+        assert_eq!(first_paragraph("```\nonly code\n```\n"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("README.md"), "").unwrap();
+        assert_eq!(readme_description(dir.path()), None);
+    }
+
+    #[test]
+    fn long_descriptions_are_cut_at_a_word() {
+        let long = "word ".repeat(100);
+        let cut = truncate(long.trim(), 22);
+        assert_eq!(cut, "word word word word…");
+        assert_eq!(truncate("short", 22), "short");
+    }
+
+    #[test]
+    fn common_licenses_are_recognized() {
+        let mit = "MIT License\n\nCopyright (c) 2024 Someone\n\nPermission is hereby granted, free of\ncharge, to any person obtaining a copy";
+        assert_eq!(guess_spdx(mit), Some("MIT"));
+        let apache = "                                 Apache License\n                           Version 2.0, January 2004\n";
+        assert_eq!(guess_spdx(apache), Some("Apache-2.0"));
+        let gpl3 = "                    GNU GENERAL PUBLIC LICENSE\n                       Version 3, 29 June 2007\n";
+        assert_eq!(guess_spdx(gpl3), Some("GPL-3.0"));
+        let gpl2 = "                    GNU GENERAL PUBLIC LICENSE\n                       Version 2, June 1991\n";
+        assert_eq!(guess_spdx(gpl2), Some("GPL-2.0"));
+        let lgpl = "                   GNU LESSER GENERAL PUBLIC LICENSE\n                       Version 2.1, February 1999\n";
+        assert_eq!(guess_spdx(lgpl), Some("LGPL-2.1"));
+    }
+
+    #[test]
+    fn unrecognized_licenses_are_not_guessed() {
+        assert_eq!(guess_spdx("Copyright (c) 2024 Someone. All rights reserved.\n"), None);
+        assert_eq!(guess_spdx(""), None);
+        // Apache without a version could be 1.1 as well
+        assert_eq!(guess_spdx("Apache License\n"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(license(dir.path()), None);
+        fs::write(dir.path().join("LICENSE.txt"), "Proprietary. Do not distribute.\n").unwrap();
+        assert_eq!(license(dir.path()), None);
+        fs::write(dir.path().join("LICENSE.txt"), "Permission is hereby granted, free of charge, to any person").unwrap();
+        assert_eq!(license(dir.path()), Some("MIT"));
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde_json::Value;
+use zip::ZipArchive;
+
+use docpack_core::{Manifest, Strictness, TasksFile, MANIFEST_FILE, TASKS_FILE};
+use localdoc::archive;
+use localdoc::docpack::{Docpack, DocpackKind};
+
+use crate::dedupe;
+use crate::error::{LocaldocError, WithPath};
+
+/// Read-only access to a docpack, whether it is an extracted directory
+/// ([`DirReader`]) or a `.docpack` archive ([`ZipReader`]). Names are paths
+/// relative to the docpack root, with `/` separators.
+pub trait DocpackReader: Send {
+    /// The docpack as given to [`open`], for messages.
+    fn path(&self) -> &Path;
+
+    fn is_archive(&self) -> bool {
+        false
+    }
+
+    /// A reader over the file `name`, for files too large to read at once.
+    fn open_file(&mut self, name: &str) -> io::Result<Box<dyn Read + '_>>;
+
+    /// Every file under `dir` (the whole docpack for `""`), as paths
+    /// relative to it with their (uncompressed) sizes, sorted by path.
+    fn list_files(&mut self, dir: &str) -> Vec<(String, u64)>;
+
+    /// What is at `name`, or `None` if nothing is.
+    fn stat(&mut self, name: &str) -> Option<Stat>;
+
+    /// Every file in the docpack, as paths relative to its root with their
+    /// sizes, sorted by path.
+    fn entries(&mut self) -> Vec<(String, u64)> {
+        self.list_files("")
+    }
+
+    /// Name of the entry holding the file at `path` under `files/`.
+    fn source_name(&self, path: &str) -> Option<String> {
+        Some(format!("files/{}", path))
+    }
+
+    /// The most recently modified file under `dir`, relative to it, and
+    /// when it was modified; `None` where files carry no times of their own.
+    fn newest_under(&self, _dir: &str) -> Option<(String, SystemTime)> {
+        None
+    }
+
+    /// `docpack.json`, read leniently.
+    fn read_manifest(&mut self) -> Result<Manifest, LocaldocError> {
+        let path = self.path().join(MANIFEST_FILE);
+        let bytes = self.try_read(MANIFEST_FILE).with_path(&path)?;
+        Manifest::from_slice(&bytes, MANIFEST_FILE, Strictness::Lenient)
+            .map_err(|source| LocaldocError::InvalidManifest { path, source })
+    }
+
+    /// `tasks.json`, read leniently.
+    fn read_tasks(&mut self) -> Result<TasksFile, LocaldocError> {
+        let path = self.path().join(TASKS_FILE);
+        let bytes = self.try_read(TASKS_FILE).with_path(&path)?;
+        TasksFile::from_slice(&bytes, TASKS_FILE, Strictness::Lenient)
+            .map_err(|source| LocaldocError::InvalidTasks { path, source })
+    }
+
+    /// Bytes of the file `name`.
+    fn try_read(&mut self, name: &str) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.open_file(name)?.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Bytes of `name`, or `None` if it is missing or unreadable.
+    fn read(&mut self, name: &str) -> Option<Vec<u8>> {
+        self.try_read(name).ok()
+    }
+
+    /// Whether the file `name` exists.
+    fn exists(&mut self, name: &str) -> bool {
+        self.stat(name).is_some_and(|stat| !stat.is_dir)
+    }
+
+    /// Whether the directory `dir` exists.
+    fn has_dir(&mut self, dir: &str) -> bool {
+        self.stat(dir).is_some_and(|stat| stat.is_dir)
+    }
+
+    /// When the file `name` was last modified, if files carry times.
+    fn modified(&mut self, name: &str) -> Option<SystemTime> {
+        self.stat(name)?.modified
+    }
+
+    /// Text of the file at `path` under `files/`.
+    fn source_text(&mut self, path: &str) -> Option<String> {
+        let name = self.source_name(path)?;
+        String::from_utf8(self.read(&name)?).ok()
+    }
+}
+
+/// What [`DocpackReader::stat`] found at a name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stat {
+    pub is_dir: bool,
+    /// Uncompressed size; 0 for directories
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// A reader for the docpack at `path`, whichever kind it is. Archives are
+/// read in place; nothing is extracted.
+pub fn open(path: &Path) -> Result<Box<dyn DocpackReader>, LocaldocError> {
+    Ok(match Docpack::open_any(path)?.kind {
+        DocpackKind::Directory => Box::new(DirReader::new(path)),
+        DocpackKind::Archive => Box::new(ZipReader::open(path)?),
+    })
+}
+
+/// An extracted docpack directory.
+pub struct DirReader {
+    root: PathBuf,
+}
+
+impl DirReader {
+    /// A reader over `root`, which is not checked to be a docpack.
+    pub fn new(root: &Path) -> Self {
+        DirReader { root: root.to_path_buf() }
+    }
+
+    /// Files under `dir`, as [`DocpackReader::list_files`] names them.
+    fn walk(&self, dir: &str) -> impl Iterator<Item = (String, walkdir::DirEntry)> {
+        let base = self.root.join(dir);
+        walkdir::WalkDir::new(&base)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(move |e| {
+                let relative = e.path().strip_prefix(&base).ok()?;
+                let name = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+                Some((name, e))
+            })
+    }
+}
+
+impl DocpackReader for DirReader {
+    fn path(&self) -> &Path {
+        &self.root
+    }
+
+    fn open_file(&mut self, name: &str) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(fs::File::open(self.root.join(name))?))
+    }
+
+    fn list_files(&mut self, dir: &str) -> Vec<(String, u64)> {
+        let mut files: Vec<(String, u64)> =
+            self.walk(dir).filter_map(|(name, e)| Some((name, e.metadata().ok()?.len()))).collect();
+        files.sort();
+        files
+    }
+
+    fn stat(&mut self, name: &str) -> Option<Stat> {
+        let metadata = fs::metadata(self.root.join(name)).ok()?;
+        Some(Stat {
+            is_dir: metadata.is_dir(),
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn newest_under(&self, dir: &str) -> Option<(String, SystemTime)> {
+        self.walk(dir)
+            .filter_map(|(name, e)| Some((name, e.metadata().ok()?.modified().ok()?)))
+            .max_by_key(|(_, modified)| *modified)
+    }
+}
+
+/// A `.docpack` archive. The central directory is read once, when the
+/// archive is opened; entries are decompressed only as they are read.
+pub struct ZipReader {
+    path: PathBuf,
+    zip: ZipArchive<fs::File>,
+    /// Entry indexes by name, with `\` separators read as `/`
+    names: BTreeMap<String, usize>,
+    /// `files.map.json` of a deduplicated archive
+    map: Option<Value>,
+}
+
+impl ZipReader {
+    pub fn open(path: &Path) -> Result<Self, LocaldocError> {
+        let mut zip = ZipArchive::new(fs::File::open(path).with_path(path)?).map_err(|e| LocaldocError::Archive {
+            path: path.to_path_buf(),
+            source: io::Error::new(io::ErrorKind::InvalidData, e),
+        })?;
+        let names = (0..zip.len())
+            .filter_map(|i| Some((archive::normalize_entry_name(zip.by_index_raw(i).ok()?.name()), i)))
+            .collect();
+        let mut reader = ZipReader { path: path.to_path_buf(), zip, names, map: None };
+        reader.map = reader.read(dedupe::MAP_FILE).and_then(|bytes| serde_json::from_slice(&bytes).ok());
+        Ok(reader)
+    }
+
+    fn index(&self, name: &str) -> io::Result<usize> {
+        self.names
+            .get(name)
+            .copied()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "specified file not found in archive"))
+    }
+
+    fn size(&mut self, name: &str) -> Option<u64> {
+        let i = self.index(name).ok()?;
+        self.zip.by_index_raw(i).ok().map(|entry| entry.size())
+    }
+}
+
+impl DocpackReader for ZipReader {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn is_archive(&self) -> bool {
+        true
+    }
+
+    fn open_file(&mut self, name: &str) -> io::Result<Box<dyn Read + '_>> {
+        let i = self.index(name)?;
+        Ok(Box::new(self.zip.by_index(i)?))
+    }
+
+    /// The files of a deduplicated archive come from its map, each sized
+    /// by its blob.
+    fn list_files(&mut self, dir: &str) -> Vec<(String, u64)> {
+        let mut files: Vec<(String, u64)> = match self.map.clone() {
+            Some(map) if dir == "files" => map["files"]
+                .as_object()
+                .map(|files| {
+                    files
+                        .iter()
+                        .map(|(path, entry)| {
+                            let blob = format!("{}/{}", dedupe::BLOB_DIR, entry["sha256"].as_str().unwrap_or_default());
+                            (path.clone(), self.size(&blob).unwrap_or(0))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => {
+                let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+                self.names
+                    .iter()
+                    .filter_map(|(name, &i)| {
+                        let name = name.strip_prefix(&prefix)?;
+                        let entry = self.zip.by_index_raw(i).ok()?;
+                        (entry.is_file() && !name.is_empty()).then(|| (name.to_string(), entry.size()))
+                    })
+                    .collect()
+            }
+        };
+        files.sort();
+        files
+    }
+
+    /// A directory exists if it has an entry of its own or any entry below
+    /// it. Entries carry no times: every one has the same fixed time, so
+    /// builds are reproducible.
+    fn stat(&mut self, name: &str) -> Option<Stat> {
+        if let Some(entry) = self.index(name).ok().and_then(|i| self.zip.by_index_raw(i).ok()) {
+            return Some(Stat { is_dir: entry.is_dir(), size: entry.size(), modified: None });
+        }
+        let prefix = format!("{}/", name);
+        self.names.keys().any(|entry| entry.starts_with(&prefix)).then_some(Stat { is_dir: true, size: 0, modified: None })
+    }
+
+    /// A deduplicated archive lists its files under `files/` as
+    /// [`DocpackReader::list_files`] does, not its blobs.
+    fn entries(&mut self) -> Vec<(String, u64)> {
+        let deduplicated = self.map.is_some();
+        let blobs = format!("{}/", dedupe::BLOB_DIR);
+        let mut entries: Vec<(String, u64)> = self
+            .names
+            .iter()
+            .filter_map(|(name, &i)| {
+                let entry = self.zip.by_index_raw(i).ok()?;
+                let hidden = deduplicated && (name == dedupe::MAP_FILE || name.starts_with(&blobs));
+                (entry.is_file() && !hidden).then(|| (name.clone(), entry.size()))
+            })
+            .collect();
+        if deduplicated {
+            entries.extend(self.list_files("files").into_iter().map(|(path, size)| (format!("files/{}", path), size)));
+        }
+        entries.sort();
+        entries
+    }
+
+    /// Its blob in a deduplicated archive.
+    fn source_name(&self, path: &str) -> Option<String> {
+        match &self.map {
+            Some(map) => Some(format!("{}/{}", dedupe::BLOB_DIR, map["files"][path]["sha256"].as_str()?)),
+            None => Some(format!("files/{}", path)),
+        }
+    }
+}
+
+/// A docpack held in memory, so code written against [`DocpackReader`]
+/// can be tested without touching the filesystem.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemReader {
+    files: std::collections::BTreeMap<String, Vec<u8>>,
+}
+
+#[cfg(test)]
+impl MemReader {
+    pub fn with(mut self, name: &str, contents: impl Into<Vec<u8>>) -> Self {
+        self.files.insert(name.to_string(), contents.into());
+        self
+    }
+}
+
+#[cfg(test)]
+impl DocpackReader for MemReader {
+    fn path(&self) -> &Path {
+        Path::new("memory.docpack")
+    }
+
+    fn open_file(&mut self, name: &str) -> io::Result<Box<dyn Read + '_>> {
+        match self.files.get(name) {
+            Some(bytes) => Ok(Box::new(bytes.as_slice())),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, format!("no {} in memory", name))),
+        }
+    }
+
+    fn list_files(&mut self, dir: &str) -> Vec<(String, u64)> {
+        let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+        self.files
+            .iter()
+            .filter_map(|(name, bytes)| Some((name.strip_prefix(&prefix)?.to_string(), bytes.len() as u64)))
+            .collect()
+    }
+
+    fn stat(&mut self, name: &str) -> Option<Stat> {
+        if let Some(bytes) = self.files.get(name) {
+            return Some(Stat { is_dir: false, size: bytes.len() as u64, modified: None });
+        }
+        let prefix = format!("{}/", name);
+        self.files.keys().any(|file| file.starts_with(&prefix)).then_some(Stat { is_dir: true, size: 0, modified: None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> MemReader {
+        MemReader::default()
+            .with("docpack.json", r#"{"version": "1.0", "name": "sample", "environment": {"tools": ["read_file"]}}"#)
+            .with("tasks.json", r#"{"tasks": [{"id": "task_1", "name": "Overview", "description": "Write it"}]}"#)
+            .with("files/src/lib.rs", "pub fn f() {}\n")
+            .with("files/README.md", "# Sample\n")
+    }
+
+    #[test]
+    fn reads_through_the_trait() {
+        let mut reader = sample();
+        assert!(reader.exists("files/README.md"));
+        assert!(reader.has_dir("files/src"));
+        assert!(!reader.exists("files/src"));
+        assert_eq!(reader.list_files("files"), [("README.md".to_string(), 9), ("src/lib.rs".to_string(), 14)]);
+        assert_eq!(reader.source_text("README.md").as_deref(), Some("# Sample\n"));
+        assert_eq!(reader.read_manifest().unwrap().name.as_deref(), Some("sample"));
+        assert_eq!(reader.read_tasks().unwrap().task_list()[0].id.as_deref(), Some("task_1"));
+    }
+
+    #[test]
+    fn broken_documents_name_their_file() {
+        let mut reader = MemReader::default().with("docpack.json", "{");
+        assert!(matches!(reader.read_manifest(), Err(LocaldocError::InvalidManifest { .. })));
+        let error = reader.read_tasks().unwrap_err();
+        assert!(matches!(&error, LocaldocError::Io { path: Some(path), .. } if path.ends_with("tasks.json")), "{}", error);
+    }
+
+    #[test]
+    fn directories_and_archives_list_the_same_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("sample.docpack");
+        for (name, bytes) in &sample().files {
+            fs::create_dir_all(root.join(name).parent().unwrap()).unwrap();
+            fs::write(root.join(name), bytes).unwrap();
+        }
+        let archive = dir.path().join("sample.zip");
+        crate::commands::pack::write_archive(&root, &archive).unwrap();
+
+        let mut directory = open(&root).unwrap();
+        let mut zip = open(&archive).unwrap();
+        assert!(zip.is_archive() && !directory.is_archive());
+        assert_eq!(directory.entries(), zip.entries());
+        assert_eq!(directory.list_files("files"), zip.list_files("files"));
+        assert_eq!(zip.stat("files/src/lib.rs").map(|stat| stat.size), Some(14));
+        assert!(zip.has_dir("files/src") && directory.has_dir("files/src"));
+        assert!(directory.modified("docpack.json").is_some() && zip.modified("docpack.json").is_none());
+    }
+}
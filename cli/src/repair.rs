@@ -0,0 +1,128 @@
+use serde_json::{json, Value};
+
+use crate::tools;
+use crate::versions;
+
+/// Remove commas directly before a `}` or `]`, as hand editing leaves
+/// behind. Text inside strings is left alone. `None` when there were none.
+pub fn strip_trailing_commas(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut pending_comma: Option<usize> = None;
+    let mut removed = false;
+
+    for c in text.chars() {
+        if in_string {
+            out.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '}' | ']' => {
+                if let Some(comma) = pending_comma.take() {
+                    out.remove(comma);
+                    removed = true;
+                }
+            }
+            ',' => pending_comma = Some(out.len()),
+            c if c.is_whitespace() => {}
+            _ => pending_comma = None,
+        }
+        in_string = c == '"';
+        out.push(c);
+    }
+
+    removed.then_some(out)
+}
+
+/// Fill in and normalize the docpack.json fields that can be fixed without
+/// guessing, returning a description of each change.
+pub fn manifest(manifest: &mut Value) -> Vec<String> {
+    let mut changes = Vec::new();
+    let Some(fields) = manifest.as_object_mut() else {
+        return changes;
+    };
+
+    match fields.get("version") {
+        None | Some(Value::Null) => {
+            fields.insert("version".to_string(), json!(versions::MANIFEST_VERSION));
+            changes.push(format!("set missing 'version' to \"{}\"", versions::MANIFEST_VERSION));
+        }
+        Some(Value::Number(number)) => {
+            let version = number.to_string();
+            changes.push(format!("converted 'version' {} to a string", version));
+            fields.insert("version".to_string(), json!(version));
+        }
+        Some(_) => {}
+    }
+
+    changes.extend(environment_tools(manifest));
+    changes
+}
+
+/// Fill in `environment.tools` when it is missing and split it into an
+/// array when it is one string.
+pub fn environment_tools(manifest: &mut Value) -> Option<String> {
+    let environment = manifest.as_object_mut()?.entry("environment").or_insert_with(|| json!({}));
+    let environment = environment.as_object_mut()?;
+    match environment.get("tools") {
+        None | Some(Value::Null) => {
+            let defaults = tools::defaults();
+            let change = format!(
+                "set missing 'environment.tools' to the {} preset ({})",
+                tools::DEFAULT_PRESET,
+                defaults.join(", ")
+            );
+            environment.insert("tools".to_string(), json!(defaults));
+            Some(change)
+        }
+        Some(Value::String(listed)) => {
+            let split: Vec<String> = listed
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|tool| !tool.is_empty())
+                .map(str::to_string)
+                .collect();
+            environment.insert("tools".to_string(), json!(split));
+            Some("converted 'environment.tools' from a string to an array".to_string())
+        }
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_commas_outside_strings_are_removed() {
+        let text = "{\n  \"a\": [1, 2,],\n  \"b\": \"x,]\",\n}";
+        let fixed = strip_trailing_commas(text).unwrap();
+        assert_eq!(fixed, "{\n  \"a\": [1, 2],\n  \"b\": \"x,]\"\n}");
+        assert!(serde_json::from_str::<Value>(&fixed).is_ok());
+        assert_eq!(strip_trailing_commas("{\"a\": \"\\\",}\"}"), None);
+        assert_eq!(strip_trailing_commas("[1, 2]"), None);
+    }
+
+    #[test]
+    fn manifest_fields_are_filled_and_normalized() {
+        let mut value = json!({ "environment": { "tools": "read_file, list_files write_output" } });
+        let changes = manifest(&mut value);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(value["version"], "1.0");
+        assert_eq!(value["environment"]["tools"], json!(["read_file", "list_files", "write_output"]));
+
+        let mut value = json!({ "version": 1, "name": "x" });
+        manifest(&mut value);
+        assert_eq!(value["version"], "1");
+        assert!(value["environment"]["tools"].as_array().is_some_and(|tools| !tools.is_empty()));
+
+        let mut value = json!({ "version": "1.0", "environment": { "tools": ["read_file"] } });
+        assert!(manifest(&mut value).is_empty());
+    }
+}
@@ -0,0 +1,30 @@
+//! Peak resident memory of this process, for `localdoc bench`. Only Linux
+//! reports it; elsewhere every reading is `None`.
+
+/// Highest resident set size in bytes since the process started or since
+/// the last [`reset_peak`].
+#[cfg(target_os = "linux")]
+pub fn peak() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kb: u64 = line.trim_start_matches("VmHWM:").trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn peak() -> Option<u64> {
+    None
+}
+
+/// Restart the peak from the current usage, so the next [`peak`] covers
+/// only what ran in between. Returns whether the kernel allowed it.
+#[cfg(target_os = "linux")]
+pub fn reset_peak() -> bool {
+    // Writing 5 to clear_refs resets VmHWM (Linux 4.0 and later)
+    std::fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn reset_peak() -> bool {
+    false
+}
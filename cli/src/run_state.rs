@@ -0,0 +1,127 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{LocaldocError, WithPath};
+use crate::runtime::Runtime;
+
+/// Where a detached run is recorded, under the docpack's `output/`.
+pub const STATE_FILE: &str = ".run_state.json";
+
+/// A run started with `run --detach`, for `logs` and `stop` to find.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RunState {
+    /// `docker` or `podman`
+    pub runtime: String,
+    pub container: String,
+    /// The docpack directory mounted into the container
+    pub working_dir: PathBuf,
+    pub image: String,
+    /// RFC 3339
+    pub started: String,
+    /// The `--task` ids, if only some tasks run
+    #[serde(default)]
+    pub tasks: Option<Vec<String>>,
+    /// The filtered tasks.json mounted for `--task`, removed by `stop`
+    #[serde(default)]
+    pub tasks_file: Option<PathBuf>,
+    #[serde(default)]
+    pub memory_mb: Option<u64>,
+    #[serde(default)]
+    pub cpus: Option<f64>,
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+    #[serde(default)]
+    pub max_output_file_bytes: Option<u64>,
+}
+
+impl RunState {
+    pub fn runtime(&self) -> Result<Runtime, String> {
+        Runtime::from_program(&self.runtime).ok_or_else(|| format!("Unknown runtime '{}' in {}", self.runtime, STATE_FILE))
+    }
+}
+
+pub fn path(docpack: &Path) -> PathBuf {
+    docpack.join("output").join(STATE_FILE)
+}
+
+/// The detached run recorded for `docpack`, if any.
+pub fn load(docpack: &Path) -> Result<Option<RunState>, LocaldocError> {
+    match fs::read_to_string(path(docpack)) {
+        Ok(text) => Ok(Some(
+            serde_json::from_str(&text).map_err(|e| format!("Failed to read {}: {}", path(docpack).display(), e))?,
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save(docpack: &Path, state: &RunState) -> Result<(), LocaldocError> {
+    let output_dir = docpack.join("output");
+    fs::create_dir_all(&output_dir).with_path(&output_dir)?;
+    let state_path = path(docpack);
+    fs::write(&state_path, serde_json::to_string_pretty(state)?).with_path(&state_path)?;
+    Ok(())
+}
+
+/// Forget the detached run, along with the tasks.json made for it.
+pub fn remove(docpack: &Path, state: &RunState) -> io::Result<()> {
+    if let Some(file) = &state.tasks_file {
+        if let Some(dir) = file.parent() {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+    match fs::remove_file(path(docpack)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// The recorded run, after dropping the record if its container no longer
+/// exists. `Ok(None)` then, with a note printed.
+pub fn live(docpack: &Path) -> Result<Option<(RunState, Runtime)>, LocaldocError> {
+    let Some(state) = load(docpack)? else {
+        return Ok(None);
+    };
+    let runtime = state.runtime()?;
+    if runtime.container_state(&state.container).is_none() {
+        remove(docpack, &state)?;
+        info!("Container {} of the detached run no longer exists; removed its stale {}", state.container, STATE_FILE);
+        return Ok(None);
+    }
+    Ok(Some((state, runtime)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_round_trips_and_is_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let tasks_dir = dir.path().join("tasks");
+        fs::create_dir_all(&tasks_dir).unwrap();
+        let state = RunState {
+            runtime: "podman".to_string(),
+            container: "localdoc-run-1".to_string(),
+            working_dir: dir.path().to_path_buf(),
+            image: "doctown:latest".to_string(),
+            started: "2025-01-01T00:00:00+00:00".to_string(),
+            tasks: Some(vec!["task_1".to_string()]),
+            tasks_file: Some(tasks_dir.join("tasks.json")),
+            memory_mb: Some(2048),
+            cpus: None,
+            max_output_bytes: None,
+            max_output_file_bytes: None,
+        };
+        save(dir.path(), &state).unwrap();
+        assert_eq!(load(dir.path()).unwrap(), Some(state.clone()));
+        assert_eq!(state.runtime(), Ok(Runtime::Podman));
+
+        remove(dir.path(), &state).unwrap();
+        assert_eq!(load(dir.path()).unwrap(), None);
+        assert!(!tasks_dir.exists());
+    }
+}
@@ -0,0 +1,540 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use clap::ValueEnum;
+use log::info;
+
+use crate::context::{ContainerLimits, Mount};
+
+/// Environment variable naming the runtime when `--runtime` is not given.
+pub const RUNTIME_ENV: &str = "LOCALDOC_RUNTIME";
+
+/// Agent image used when neither `--image` nor a config file names one.
+pub const DEFAULT_IMAGE: &str = "doctown:latest";
+
+/// How long a container being stopped gets to exit before it is killed.
+pub const STOP_GRACE_SECONDS: u64 = 10;
+
+/// Container runtime asked for with `run --runtime` or `$LOCALDOC_RUNTIME`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum RuntimeChoice {
+    Docker,
+    Podman,
+    /// Docker if it is installed, else Podman
+    Auto,
+}
+
+/// When `run` pulls the agent image, from `--pull`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PullPolicy {
+    /// Pull before every run
+    Always,
+    /// Pull only if the image is not available locally
+    #[default]
+    Missing,
+    /// Never pull; fail if the image is not available locally
+    Never,
+}
+
+/// Whether a container is still running, or how it exited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContainerState {
+    pub running: bool,
+    pub exit_code: Option<i32>,
+}
+
+/// A container runtime that launches the agent image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Runtime {
+    Docker,
+    Podman,
+}
+
+impl Runtime {
+    /// Probed in this order by `--runtime auto`.
+    const ALL: [Runtime; 2] = [Runtime::Docker, Runtime::Podman];
+
+    /// The executable.
+    pub fn program(self) -> &'static str {
+        match self {
+            Runtime::Docker => "docker",
+            Runtime::Podman => "podman",
+        }
+    }
+
+    /// The runtime whose executable is `program`.
+    pub fn from_program(program: &str) -> Option<Runtime> {
+        Runtime::ALL.into_iter().find(|runtime| runtime.program() == program)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Runtime::Docker => "Docker",
+            Runtime::Podman => "Podman",
+        }
+    }
+
+    /// Whether the executable is installed and answers.
+    fn is_available(self) -> bool {
+        Command::new(self.program())
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    /// What `--version` prints, if the executable is installed.
+    pub fn version(self) -> Option<String> {
+        let output = Command::new(self.program()).arg("--version").stderr(Stdio::null()).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Why the daemon (or Podman's machine) cannot be reached, or `None`
+    /// if `info` succeeds.
+    pub fn daemon_error(self) -> Option<String> {
+        match Command::new(self.program()).arg("info").stdout(Stdio::null()).output() {
+            Ok(output) if output.status.success() => None,
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                // The first line says what went wrong; the rest is usually advice for another setup
+                Some(stderr.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("info failed").to_string())
+            }
+            Err(e) => Some(e.to_string()),
+        }
+    }
+
+    /// Whether `image` is available locally.
+    pub fn has_image(self, image: &str) -> bool {
+        Command::new(self.program())
+            .args(["image", "inspect", image])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    pub fn pull_command(self, image: &str, quiet: bool) -> Command {
+        let mut cmd = Command::new(self.program());
+        cmd.arg("pull");
+        if quiet {
+            cmd.arg("--quiet");
+        }
+        cmd.arg(image);
+        cmd
+    }
+
+    /// Make sure `image` is available as `policy` asks, pulling it if
+    /// need be. Pull progress is shown unless `quiet`; a failed pull is
+    /// reported with the runtime's own message, which says why (an unknown
+    /// image, a registry that wants `login`, no daemon).
+    pub fn ensure_image(self, image: &str, policy: PullPolicy, quiet: bool) -> Result<(), String> {
+        match policy {
+            PullPolicy::Always => {}
+            PullPolicy::Missing | PullPolicy::Never if self.has_image(image) => return Ok(()),
+            PullPolicy::Missing => {}
+            PullPolicy::Never => {
+                return Err(format!(
+                    "Image {} is not available locally and --pull never was given.\n  \
+                     Pull it with `{} pull {}`, or run with --pull missing",
+                    image,
+                    self.program(),
+                    image
+                ))
+            }
+        }
+
+        if !quiet {
+            info!("Pulling {}...", image);
+        }
+        let mut cmd = self.pull_command(image, quiet);
+        cmd.stdout(if quiet { Stdio::null() } else { Stdio::inherit() });
+        let output = cmd.output().map_err(|e| format!("Cannot start {} ({}); is it installed?", self.program(), e))?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let mut error = format!("Could not pull {}: {}", image, if message.is_empty() { "no error message" } else { &message });
+        let lower = message.to_lowercase();
+        if ["denied", "unauthorized", "authentication required", "login"].iter().any(|word| lower.contains(word)) {
+            error.push_str(&format!("\n  If the registry needs credentials, sign in with `{} login` first", self.program()));
+        }
+        Err(error)
+    }
+
+    /// The `-v` argument for `mount`. Podman relabels the host directory so
+    /// SELinux lets the container use it, which Docker does not need.
+    pub fn volume_arg(self, mount: &Mount) -> String {
+        let mut options = Vec::new();
+        if !mount.writable {
+            options.push("ro");
+        }
+        if self == Runtime::Podman {
+            options.push("z");
+        }
+        let mut arg = format!("{}:{}", mount.host.display(), mount.container);
+        if !options.is_empty() {
+            arg.push(':');
+            arg.push_str(&options.join(","));
+        }
+        arg
+    }
+
+    /// The command that starts the container described by `spec`.
+    /// Rootless Podman maps the host user into the container, so files
+    /// written to a mount stay owned by whoever ran `localdoc`. Variable
+    /// values are set on the runtime's own environment and only their names
+    /// passed with `-e`, so they never show up in a process listing.
+    pub fn run_command(self, spec: &ContainerSpec) -> Command {
+        let mut cmd = Command::new(self.program());
+        // A detached container is kept after it exits, for `logs` and for
+        // `stop` to read its exit code
+        cmd.arg("run").arg(if spec.detach { "--detach" } else { "--rm" }).arg("--name").arg(&spec.name);
+        if self == Runtime::Podman && spec.map_user {
+            cmd.arg("--userns=keep-id");
+        }
+        if let Some(user) = &spec.user {
+            cmd.arg("--user").arg(user);
+        }
+        if !spec.network {
+            cmd.arg("--network").arg("none");
+        }
+        if let Some(mb) = spec.limits.memory_mb {
+            cmd.arg("--memory").arg(format!("{}m", mb));
+        }
+        if let Some(cpus) = spec.limits.cpus {
+            cmd.arg("--cpus").arg(cpus.to_string());
+        }
+        if let Some(env_file) = &spec.env_file {
+            cmd.arg("--env-file").arg(env_file);
+        }
+        for var in &spec.env {
+            if let Some(value) = &var.value {
+                cmd.env(&var.name, value);
+            }
+            cmd.arg("-e").arg(&var.name);
+        }
+        for mount in &spec.mounts {
+            cmd.arg("-v").arg(self.volume_arg(mount));
+        }
+        cmd.arg(spec.image);
+        cmd
+    }
+
+    /// Stop the container called `name`, giving it `grace_seconds` to exit
+    /// before the runtime kills it.
+    pub fn stop_command(self, name: &str, grace_seconds: u64) -> Command {
+        let mut cmd = Command::new(self.program());
+        cmd.arg("stop").arg("--time").arg(grace_seconds.to_string()).arg(name);
+        cmd
+    }
+
+    pub fn kill_command(self, name: &str) -> Command {
+        let mut cmd = Command::new(self.program());
+        cmd.arg("kill").arg(name);
+        cmd
+    }
+
+    pub fn logs_command(self, name: &str, follow: bool) -> Command {
+        let mut cmd = Command::new(self.program());
+        cmd.arg("logs");
+        if follow {
+            cmd.arg("--follow");
+        }
+        cmd.arg(name);
+        cmd
+    }
+
+    pub fn remove_command(self, name: &str) -> Command {
+        let mut cmd = Command::new(self.program());
+        cmd.arg("rm").arg(name);
+        cmd
+    }
+
+    /// The state of the container called `name`, or `None` if there is no
+    /// such container.
+    pub fn container_state(self, name: &str) -> Option<ContainerState> {
+        let output = Command::new(self.program())
+            .args(["container", "inspect", "--format", "{{.State.Running}} {{.State.ExitCode}}", name])
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        let mut fields = text.split_whitespace();
+        let running = fields.next()? == "true";
+        let exit_code = fields.next().and_then(|code| code.parse().ok());
+        Some(ContainerState { running, exit_code })
+    }
+}
+
+/// `uid:gid` of the invoking user, for Docker's `--user`, so files the agent
+/// writes into the mounted docpack belong to them rather than to root.
+/// `None` as root, and off Linux, where Docker Desktop's file sharing
+/// already gives the files to the user.
+#[cfg(target_os = "linux")]
+pub fn host_user() -> Option<String> {
+    // SAFETY: getuid and getgid always succeed and touch no memory
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    (uid != 0).then(|| format!("{}:{}", uid, gid))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn host_user() -> Option<String> {
+    None
+}
+
+/// What to say when a file in a run's output cannot be read or removed:
+/// most likely the agent wrote it as another user.
+pub fn permission_denied(path: &Path) -> String {
+    format!(
+        "Permission denied: {}\n  The agent container probably wrote it as another user (such as root). \
+         Remove it with `sudo rm -rf {}`; runs that keep the default user mapping \
+         (no --no-user-mapping) write files as you.",
+        path.display(),
+        path.display()
+    )
+}
+
+/// Everything [`Runtime::run_command`] needs to start the agent.
+pub struct ContainerSpec<'a> {
+    pub image: &'a str,
+    /// Container name, so the container can be stopped from outside
+    pub name: String,
+    /// The timeout is not part of the command; `run` enforces it
+    pub limits: ContainerLimits,
+    /// `false` starts the container without a network
+    pub network: bool,
+    /// Start the container in the background and keep it after it exits
+    pub detach: bool,
+    /// `--user`, as `uid[:gid]`
+    pub user: Option<String>,
+    /// Under Podman, map the invoking user into the container with
+    /// `--userns=keep-id`
+    pub map_user: bool,
+    pub mounts: Vec<Mount>,
+    pub env: Vec<EnvVar>,
+    pub env_file: Option<PathBuf>,
+}
+
+/// A variable set in the container with `-e`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvVar {
+    pub name: String,
+    /// `None` forwards the value from the host environment
+    pub value: Option<String>,
+}
+
+impl EnvVar {
+    /// Parse `KEY=VALUE` or a bare `KEY`. Only the first `=` separates, so
+    /// the value may contain more of them, and spaces, as is.
+    pub fn parse(spec: &str) -> Result<EnvVar, String> {
+        let (name, value) = match spec.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (spec, None),
+        };
+        let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!("Invalid environment variable name in -e '{}'", spec));
+        }
+        Ok(EnvVar { name: name.to_string(), value })
+    }
+}
+
+/// Whether the value of variable `name` should be hidden when the command
+/// is shown.
+pub fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    ["SECRET", "KEY", "TOKEN"].iter().any(|word| name.contains(word))
+}
+
+/// `cmd` as a shell command line, with the variables it sets in front and
+/// the values of secret ones replaced by `***`.
+pub fn command_line(cmd: &Command) -> String {
+    let mut words = Vec::new();
+    for (name, value) in cmd.get_envs() {
+        let name = name.to_string_lossy();
+        let value = match value {
+            Some(_) if is_secret(&name) => "***".to_string(),
+            Some(value) => shell_quote(&value.to_string_lossy()),
+            None => continue,
+        };
+        words.push(format!("{}={}", name, value));
+    }
+    words.push(shell_quote(&cmd.get_program().to_string_lossy()));
+    words.extend(cmd.get_args().map(|arg| shell_quote(&arg.to_string_lossy())));
+    words.join(" ")
+}
+
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+fn parse_choice(value: &str) -> Result<RuntimeChoice, String> {
+    RuntimeChoice::from_str(value.trim(), true)
+        .map_err(|_| format!("{} must be docker, podman or auto, not '{}'", RUNTIME_ENV, value))
+}
+
+/// The runtime to use: `choice` from the command line, else
+/// `$LOCALDOC_RUNTIME`, else the first of Docker and Podman installed.
+pub fn resolve(choice: Option<RuntimeChoice>) -> Result<Runtime, String> {
+    let choice = match choice {
+        Some(choice) => choice,
+        None => match std::env::var(RUNTIME_ENV) {
+            Ok(value) if !value.trim().is_empty() => parse_choice(&value)?,
+            _ => RuntimeChoice::Auto,
+        },
+    };
+    match choice {
+        RuntimeChoice::Docker => Ok(Runtime::Docker),
+        RuntimeChoice::Podman => Ok(Runtime::Podman),
+        RuntimeChoice::Auto => Runtime::ALL.into_iter().find(|runtime| runtime.is_available()).ok_or_else(|| {
+            "No container runtime found: neither docker nor podman is on PATH.\n  \
+             Install Docker (https://docs.docker.com/get-docker/) or Podman (https://podman.io/docs/installation), \
+             or pass --runtime to name one"
+                .to_string()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use docpack_core::Constraints;
+
+    #[test]
+    fn podman_relabels_volumes_and_keeps_the_user() {
+        let mounts = [
+            Mount { host: PathBuf::from("/work/pack"), container: "/workspace".to_string(), writable: true },
+            Mount { host: PathBuf::from("/work/pack/files"), container: "/workspace/files".to_string(), writable: false },
+        ];
+        assert_eq!(Runtime::Docker.volume_arg(&mounts[0]), "/work/pack:/workspace");
+        assert_eq!(Runtime::Docker.volume_arg(&mounts[1]), "/work/pack/files:/workspace/files:ro");
+        assert_eq!(Runtime::Podman.volume_arg(&mounts[0]), "/work/pack:/workspace:z");
+        assert_eq!(Runtime::Podman.volume_arg(&mounts[1]), "/work/pack/files:/workspace/files:ro,z");
+
+        let layout: Vec<String> =
+            crate::context::plan_mounts(std::path::Path::new("/work/pack"), false).iter().map(|m| Runtime::Docker.volume_arg(m)).collect();
+        assert_eq!(
+            layout,
+            ["/work/pack:/workspace:ro", "/work/pack/output:/workspace/output", "/work/pack/.reasoning:/workspace/.reasoning"]
+        );
+
+        let [workspace, _] = mounts;
+        let spec = ContainerSpec {
+            image: "doctown:latest",
+            name: "localdoc-1".to_string(),
+            limits: ContainerLimits::default(),
+            network: true,
+            detach: false,
+            user: None,
+            map_user: true,
+            mounts: vec![workspace],
+            env: Vec::new(),
+            env_file: None,
+        };
+        let args = |runtime: Runtime| args(&runtime.run_command(&spec));
+        assert_eq!(args(Runtime::Docker), ["run", "--rm", "--name", "localdoc-1", "-v", "/work/pack:/workspace", "doctown:latest"]);
+        assert_eq!(args(Runtime::Podman)[..5], ["run", "--rm", "--name", "localdoc-1", "--userns=keep-id"]);
+    }
+
+    #[test]
+    fn user_mapping_flags() {
+        let mut spec = ContainerSpec {
+            image: "doctown:latest",
+            name: "localdoc-1".to_string(),
+            limits: ContainerLimits::default(),
+            network: true,
+            detach: false,
+            user: Some("1000:1000".to_string()),
+            map_user: true,
+            mounts: Vec::new(),
+            env: Vec::new(),
+            env_file: None,
+        };
+        assert_eq!(
+            args(&Runtime::Docker.run_command(&spec)),
+            ["run", "--rm", "--name", "localdoc-1", "--user", "1000:1000", "doctown:latest"]
+        );
+        spec.user = None;
+        spec.map_user = false;
+        assert!(!args(&Runtime::Podman.run_command(&spec)).iter().any(|arg| arg.starts_with("--user")));
+    }
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn limits_become_runtime_flags() {
+        let constraints = Constraints { memory_limit_mb: Some(2048), max_execution_time_seconds: Some(300), ..Constraints::default() };
+        let limits = ContainerLimits::resolve(Some(&constraints), None, Some(1.5), None);
+        assert_eq!(limits.timeout_seconds, Some(300));
+        let spec = ContainerSpec {
+            image: "doctown:latest",
+            name: "localdoc-1".to_string(),
+            limits,
+            network: false,
+            detach: false,
+            user: None,
+            map_user: true,
+            mounts: Vec::new(),
+            env: Vec::new(),
+            env_file: None,
+        };
+        assert_eq!(
+            args(&Runtime::Docker.run_command(&spec)),
+            ["run", "--rm", "--name", "localdoc-1", "--network", "none", "--memory", "2048m", "--cpus", "1.5", "doctown:latest"]
+        );
+
+        let overridden = ContainerLimits::resolve(Some(&constraints), Some(512), None, Some(60));
+        assert_eq!((overridden.memory_mb, overridden.timeout_seconds), (Some(512), Some(60)));
+        let unlimited = Constraints { memory_limit_mb: Some(0), max_execution_time_seconds: None, ..Constraints::default() };
+        assert_eq!(ContainerLimits::resolve(Some(&unlimited), None, None, None), ContainerLimits::default());
+
+        assert_eq!(args(&Runtime::Podman.stop_command("localdoc-1", 10)), ["stop", "--time", "10", "localdoc-1"]);
+    }
+
+    #[test]
+    fn variables_are_passed_by_name_and_secrets_redacted() {
+        let var = EnvVar::parse("OPENAI_API_KEY=sk-a=b c").unwrap();
+        assert_eq!(var.value.as_deref(), Some("sk-a=b c"));
+        assert_eq!(EnvVar::parse("HOME").unwrap().value, None);
+        assert!(EnvVar::parse("=x").is_err());
+        assert!(EnvVar::parse("1A=x").is_err());
+
+        let spec = ContainerSpec {
+            image: "doctown:latest",
+            name: "localdoc-1".to_string(),
+            limits: ContainerLimits::default(),
+            network: true,
+            detach: false,
+            user: None,
+            map_user: true,
+            mounts: Vec::new(),
+            env: vec![var, EnvVar::parse("MODEL=gpt 4").unwrap(), EnvVar::parse("HOME").unwrap()],
+            env_file: Some(PathBuf::from("my env")),
+        };
+        let cmd = Runtime::Docker.run_command(&spec);
+        let value = cmd.get_envs().find(|(name, _)| *name == "OPENAI_API_KEY").and_then(|(_, value)| value);
+        assert_eq!(value.and_then(|value| value.to_str()), Some("sk-a=b c"));
+        assert_eq!(
+            command_line(&cmd),
+            "MODEL='gpt 4' OPENAI_API_KEY=*** docker run --rm --name localdoc-1 --env-file 'my env' -e OPENAI_API_KEY -e MODEL -e HOME doctown:latest"
+        );
+        assert!(is_secret("github_token") && is_secret("AWS_SECRET_ACCESS_KEY") && !is_secret("MODEL"));
+    }
+
+    #[test]
+    fn environment_choice_is_parsed_case_insensitively() {
+        assert_eq!(parse_choice("Podman"), Ok(RuntimeChoice::Podman));
+        assert_eq!(parse_choice(" auto "), Ok(RuntimeChoice::Auto));
+        assert!(parse_choice("lxc").unwrap_err().contains(RUNTIME_ENV));
+        assert_eq!(resolve(Some(RuntimeChoice::Podman)), Ok(Runtime::Podman));
+    }
+}
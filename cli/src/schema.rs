@@ -0,0 +1,102 @@
+use std::fmt;
+use std::sync::OnceLock;
+use jsonschema::Validator;
+use serde_json::Value;
+
+/// A JSON Schema built into the binary for one docpack file.
+#[derive(Clone, Copy)]
+pub struct Schema {
+    /// The file the schema describes, e.g. `docpack.json`
+    pub document: &'static str,
+    /// Name the schema is exported under
+    pub file_name: &'static str,
+    pub text: &'static str,
+}
+
+pub const MANIFEST: Schema = Schema {
+    document: "docpack.json",
+    file_name: "docpack.schema.json",
+    text: include_str!("../schemas/docpack.schema.json"),
+};
+
+pub const TASKS: Schema = Schema {
+    document: "tasks.json",
+    file_name: "tasks.schema.json",
+    text: include_str!("../schemas/tasks.schema.json"),
+};
+
+pub const ALL: [Schema; 2] = [MANIFEST, TASKS];
+
+impl Schema {
+    fn validator(&self) -> &'static Validator {
+        static MANIFEST_VALIDATOR: OnceLock<Validator> = OnceLock::new();
+        static TASKS_VALIDATOR: OnceLock<Validator> = OnceLock::new();
+        let cell = if self.document == MANIFEST.document { &MANIFEST_VALIDATOR } else { &TASKS_VALIDATOR };
+        cell.get_or_init(|| {
+            let schema: Value = serde_json::from_str(self.text).expect("built-in schema is valid JSON");
+            jsonschema::validator_for(&schema).expect("built-in schema is a valid JSON Schema")
+        })
+    }
+
+    /// Where `value` breaks the schema, one violation per offending field.
+    pub fn check(&self, value: &Value) -> Vec<Violation> {
+        self.validator()
+            .iter_errors(value)
+            .map(|error| {
+                let pointer = error.instance_path().as_str();
+                Violation {
+                    document: self.document,
+                    pointer: if pointer.is_empty() { "/".to_string() } else { pointer.to_string() },
+                    message: error.to_string(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// One way a document breaks its schema.
+pub struct Violation {
+    pub document: &'static str,
+    /// JSON pointer to the offending field, `/` for the whole document
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.document, self.pointer, self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn built_in_schemas_compile() {
+        for schema in ALL {
+            assert!(schema.check(&json!({})).iter().all(|violation| violation.document == schema.document));
+        }
+    }
+
+    #[test]
+    fn violations_name_the_field() {
+        let manifest = json!({
+            "version": "1.0",
+            "environment": { "tools": ["read_file", 3], "constraints": { "memory_limit_mb": 0 } },
+        });
+        let errors = MANIFEST.check(&manifest).iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors.iter().any(|e| e.starts_with("docpack.json: /environment/tools/1: ")));
+        assert!(errors.iter().any(|e| e.starts_with("docpack.json: /environment/constraints/memory_limit_mb: ")));
+
+        let missing = MANIFEST.check(&json!({ "name": "x" })).iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert!(missing.iter().any(|e| e.starts_with("docpack.json: /: ") && e.contains("\"version\"")), "{:?}", missing);
+
+        let tasks = json!({ "tasks": [{ "id": 1, "output": { "type": "markdown" } }] });
+        let errors = TASKS.check(&tasks).iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert!(errors.iter().any(|e| e.starts_with("tasks.json: /tasks/0/id: ")), "{:?}", errors);
+        assert!(errors.iter().any(|e| e.starts_with("tasks.json: /tasks/0/output: ")), "{:?}", errors);
+    }
+}
@@ -0,0 +1,114 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::warn;
+
+use crate::interrupt;
+use crate::runtime;
+
+/// Name prefixes of the scratch directories commands make, by which
+/// `localdoc clean --temp` recognizes ones a crash left behind.
+pub const PREFIXES: &[&str] = &[
+    "docpack-build-",
+    "docpack-embed-",
+    "docpack-index-",
+    "docpack-pack-",
+    "docpack-push-",
+    "docpack-run-",
+    "docpack-tasks-",
+    "docpack-update-",
+];
+
+/// Scratch directories that still exist, removed by the Ctrl-C handler.
+static LIVE: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// A uniquely named temporary directory that is removed when dropped,
+/// whether the command succeeds or fails, and when the process is
+/// interrupted with Ctrl-C.
+pub struct ScratchDir {
+    dir: tempfile::TempDir,
+    kept: bool,
+}
+
+impl ScratchDir {
+    /// Create `<tmp>/<prefix><random>`. `prefix` should be one of
+    /// [`PREFIXES`], so `clean --temp` can find the directory if it is
+    /// ever left behind.
+    pub fn new(prefix: &str) -> io::Result<Self> {
+        interrupt::install();
+        let dir = tempfile::Builder::new().prefix(prefix).tempdir()?;
+        LIVE.lock().unwrap_or_else(|e| e.into_inner()).push(dir.path().to_path_buf());
+        Ok(ScratchDir { dir, kept: false })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Leave the directory on disk when this is dropped, e.g. so a failure
+    /// can be looked into.
+    pub fn keep(&mut self) {
+        self.kept = true;
+        self.dir.disable_cleanup(true);
+        LIVE.lock().unwrap_or_else(|e| e.into_inner()).retain(|p| p != self.dir.path());
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        LIVE.lock().unwrap_or_else(|e| e.into_inner()).retain(|p| p != self.dir.path());
+        if self.kept {
+            return;
+        }
+        // Removed here rather than by `dir`, which would ignore a failure
+        self.dir.disable_cleanup(true);
+        match std::fs::remove_dir_all(self.dir.path()) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => warn!("{}", removal_failed(self.dir.path(), &e)),
+            _ => {}
+        }
+    }
+}
+
+/// Why a scratch directory was left behind. Files the agent container wrote
+/// as root cannot be removed by the user who ran it.
+pub(crate) fn removal_failed(dir: &Path, e: &io::Error) -> String {
+    match e.kind() {
+        io::ErrorKind::PermissionDenied => runtime::permission_denied(dir),
+        _ => format!("Could not remove {}: {}", dir.display(), e),
+    }
+}
+
+/// Remove every scratch directory that still exists, for the Ctrl-C
+/// handler, which exits without running destructors.
+pub fn remove_all() {
+    let live = LIVE.lock().unwrap_or_else(|e| e.into_inner());
+    for dir in live.iter() {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_errors_say_how_to_clean_up() {
+        let dir = Path::new("/tmp/docpack-run-abc123");
+        let message = removal_failed(dir, &io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(message.contains("sudo rm -rf /tmp/docpack-run-abc123"), "{}", message);
+        assert!(message.contains("--no-user-mapping"));
+
+        let other = removal_failed(dir, &io::Error::other("disk on fire"));
+        assert_eq!(other, "Could not remove /tmp/docpack-run-abc123: disk on fire");
+    }
+
+    #[test]
+    fn dropping_removes_the_directory() {
+        let scratch = ScratchDir::new("docpack-test-").unwrap();
+        let path = scratch.path().to_path_buf();
+        std::fs::write(path.join("file"), "x").unwrap();
+        drop(scratch);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,45 @@
+use std::path::Path;
+use docpack_core::Manifest;
+use log::warn;
+use serde_json::Value;
+use crate::error::LocaldocError;
+use crate::reader;
+
+/// Returns the manifest of a sealed docpack (`metadata.immutable: true`),
+/// a directory or an archive, or `None` when the docpack is mutable or has
+/// no readable manifest.
+pub fn sealed_manifest(docpack_path: &Path) -> Option<Manifest> {
+    let manifest = reader::open(docpack_path).ok()?.read_manifest().ok()?;
+    is_sealed(&manifest).then_some(manifest)
+}
+
+/// Whether `manifest` has `metadata.immutable: true`.
+pub fn is_sealed(manifest: &Manifest) -> bool {
+    manifest.metadata.as_ref().and_then(|metadata| metadata.get("immutable")).and_then(Value::as_bool) == Some(true)
+}
+
+/// Refuse to modify a sealed docpack unless the caller passed `--force-mutable`.
+pub fn ensure_mutable(docpack_path: &Path, force_mutable: bool) -> Result<(), LocaldocError> {
+    let Some(manifest) = sealed_manifest(docpack_path) else {
+        return Ok(());
+    };
+
+    if force_mutable {
+        warn!("Modifying sealed docpack ({})", describe_seal(&manifest));
+        return Ok(());
+    }
+
+    Err(format!(
+        "Docpack is sealed ({}): {}\nPass --force-mutable to modify it anyway",
+        describe_seal(&manifest),
+        docpack_path.display()
+    )
+    .into())
+}
+
+/// Human-readable "sealed by X at Y" from the manifest metadata.
+pub fn describe_seal(manifest: &Manifest) -> String {
+    let by = manifest.metadata_str("sealed_by").unwrap_or("unknown");
+    let at = manifest.metadata_str("sealed_at").unwrap_or("unknown time");
+    format!("sealed by {} at {}", by, at)
+}
@@ -0,0 +1,686 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::Arc;
+use bincode::Options;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::error::LocaldocError;
+use crate::hashes;
+use crate::tokenize::tokenize;
+
+/// Most files listed for one term. Terms found in more files keep the
+/// files where they occur most and are marked in `truncated`.
+pub const MAX_POSTINGS: usize = 500;
+
+/// Shortest query token that fuzzy matching allows two edits in; shorter
+/// ones allow one, since two edits turn most short words into others.
+const FUZZY_TWO_EDITS_CHARS: usize = 6;
+
+/// Most bytes of one file that are indexed. Text past this point is left
+/// out, and the file is listed in `metadata.capped`.
+pub const MAX_INDEXED_BYTES: usize = 1024 * 1024;
+
+/// Average characters per line above which a file is taken for minified
+/// or generated code, whose "words" are useless to search.
+pub const MAX_AVERAGE_LINE_CHARS: usize = 500;
+
+/// Files smaller than this are indexed whatever their line length, so
+/// short one-line files such as small JSON configs are kept.
+pub const MIN_MINIFIED_BYTES: usize = 1024;
+
+/// Machine-written dependency lockfiles, left out of the index by name.
+pub const LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Cargo.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "Gemfile.lock",
+    "composer.lock",
+];
+
+/// Leading bytes of `index/search.bin`.
+const BINARY_MAGIC: &[u8; 8] = b"LDSEARCH";
+
+/// How the search index is stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum IndexFormat {
+    /// `index/search.json`: readable by any JSON tool
+    #[default]
+    Json,
+    /// `index/search.bin`: several times smaller and faster to load, but
+    /// needs localdoc (or a bincode reader) to decode
+    Binary,
+}
+
+impl IndexFormat {
+    pub const ALL: [IndexFormat; 2] = [IndexFormat::Json, IndexFormat::Binary];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            IndexFormat::Json => "search.json",
+            IndexFormat::Binary => "search.bin",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IndexFormat::Json => "json",
+            IndexFormat::Binary => "binary",
+        }
+    }
+
+    /// The format of the index present in `index_dir`, if any.
+    pub fn detect(index_dir: &Path) -> Option<IndexFormat> {
+        IndexFormat::ALL.into_iter().find(|format| index_dir.join(format.file_name()).is_file())
+    }
+}
+
+/// A posting in `index/search.bin`: (path id, line gaps).
+type BinaryPosting = (u32, Vec<u32>);
+
+/// `index/search.bin` after the magic bytes, encoded with bincode's varint
+/// options. Paths are interned into a table and postings refer to them by
+/// position; line numbers are stored as gaps from the previous line.
+/// Written through [`BinaryIndexRef`], which must keep the same fields.
+#[derive(Deserialize)]
+struct BinaryIndex {
+    /// [`SearchMetadata`] as JSON, first so it can be read on its own: the
+    /// fingerprint is free-form JSON, which bincode can't carry
+    metadata: String,
+    paths: Vec<String>,
+    terms: Vec<(String, Vec<BinaryPosting>)>,
+    truncated: Vec<String>,
+}
+
+/// [`BinaryIndex`] borrowed from a [`SearchIndex`], so writing it doesn't
+/// copy the index.
+#[derive(Serialize)]
+struct BinaryIndexRef<'a> {
+    metadata: String,
+    paths: Vec<&'a str>,
+    terms: BinaryTerms<'a>,
+    truncated: &'a BTreeSet<String>,
+}
+
+/// The terms of a [`SearchIndex`], encoded one at a time as they are
+/// written.
+struct BinaryTerms<'a> {
+    index: &'a BTreeMap<String, Vec<Posting>>,
+    ids: &'a BTreeMap<&'a str, u32>,
+}
+
+impl Serialize for BinaryTerms<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.index.iter().map(|(token, postings)| {
+            let postings: Vec<BinaryPosting> = postings
+                .iter()
+                .map(|posting| {
+                    let mut previous = 0;
+                    let gaps = posting
+                        .lines
+                        .iter()
+                        .map(|&line| {
+                            let gap = line - previous;
+                            previous = line;
+                            gap
+                        })
+                        .collect();
+                    (self.ids[&*posting.path], gaps)
+                })
+                .collect();
+            (token, postings)
+        }))
+    }
+}
+
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// `index/search.json`: an inverted index from search tokens to the lines
+/// they occur on. Tokens come from [`tokenize`], so queries must go
+/// through it too.
+#[derive(Serialize, Deserialize)]
+pub struct SearchIndex {
+    /// Postings per token, sorted by path
+    pub index: BTreeMap<String, Vec<Posting>>,
+    /// Tokens whose postings were cut at [`MAX_POSTINGS`]
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub truncated: BTreeSet<String>,
+    pub metadata: SearchMetadata,
+}
+
+/// How an indexed term matched a query token, best first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    Exact,
+    /// The term starts with the token
+    Prefix,
+    /// The term is one or two edits away from the token
+    Fuzzy,
+}
+
+impl MatchKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatchKind::Exact => "exact",
+            MatchKind::Prefix => "prefix",
+            MatchKind::Fuzzy => "fuzzy",
+        }
+    }
+}
+
+/// Where one token occurs in one file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Posting {
+    /// Path under `files/`, shared by every posting of the file
+    pub path: Arc<str>,
+    /// 1-based line numbers, ascending
+    pub lines: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SearchMetadata {
+    pub total_files: usize,
+    pub indexed_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// Files left out as lockfiles or minified code, with the reason from
+    /// [`skip_reason`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub skipped: BTreeMap<String, String>,
+    /// Files indexed only up to [`MAX_INDEXED_BYTES`]
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub capped: BTreeSet<String>,
+    /// The `files/` tree when the index was built or last updated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files: Option<FilesSnapshot>,
+    /// See [`crate::fingerprint::fingerprint`]
+    pub fingerprint: Value,
+}
+
+/// What `files/` held when an index was built, cheap enough to compare
+/// against the current tree without reading any file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FilesSnapshot {
+    /// Files under `files/`, indexed or not
+    pub count: usize,
+    /// SHA-256 of `<size> <path>\n` lines in path order
+    pub digest: String,
+}
+
+impl FilesSnapshot {
+    /// Snapshot of `files`, (path, size) pairs in any order.
+    pub fn of(files: &[(String, u64)]) -> Self {
+        let mut sorted: Vec<&(String, u64)> = files.iter().collect();
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut hasher = Sha256::new();
+        for (path, size) in sorted {
+            hasher.update(format!("{} {}\n", size, path).as_bytes());
+        }
+        FilesSnapshot { count: files.len(), digest: hashes::hex(&hasher.finalize()) }
+    }
+}
+
+/// Why the file at `path` is left out of the index, if it is a lockfile,
+/// is named like `*.min.*`, or has lines averaging more than
+/// [`MAX_AVERAGE_LINE_CHARS`].
+pub fn skip_reason(path: &str, content: &str) -> Option<&'static str> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if LOCKFILES.contains(&name) {
+        return Some("lockfile");
+    }
+    if name.contains(".min.") {
+        return Some("minified");
+    }
+    if content.len() >= MIN_MINIFIED_BYTES {
+        let (lines, chars) = content.lines().fold((0, 0), |(lines, chars), line| (lines + 1, chars + line.chars().count()));
+        if chars / lines.max(1) > MAX_AVERAGE_LINE_CHARS {
+            return Some("long lines");
+        }
+    }
+    None
+}
+
+/// The text of the file at `path` that goes into the index: its first
+/// [`MAX_INDEXED_BYTES`], and whether it was cut there. `None` for files
+/// that can't be read or aren't UTF-8 text.
+pub fn read_indexed_text(path: &Path) -> Option<(String, bool)> {
+    let mut bytes = Vec::new();
+    fs::File::open(path).ok()?.take(MAX_INDEXED_BYTES as u64 + 1).read_to_end(&mut bytes).ok()?;
+    let capped = bytes.len() > MAX_INDEXED_BYTES;
+    bytes.truncate(MAX_INDEXED_BYTES);
+    match String::from_utf8(bytes) {
+        Ok(text) => Some((text, capped)),
+        // The cut fell inside a character; drop its first bytes
+        Err(e) if capped && e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut bytes = e.into_bytes();
+            bytes.truncate(valid);
+            String::from_utf8(bytes).ok().map(|text| (text, true))
+        }
+        Err(_) => None,
+    }
+}
+
+/// Lines each token of `content` occurs on.
+pub fn file_postings(content: &str) -> BTreeMap<String, Vec<u32>> {
+    let mut postings: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i as u32 + 1;
+        for token in tokenize(line) {
+            let lines = postings.entry(token).or_default();
+            if lines.last() != Some(&line_number) {
+                lines.push(line_number);
+            }
+        }
+    }
+    postings
+}
+
+impl SearchIndex {
+    pub fn new(metadata: SearchMetadata) -> Self {
+        SearchIndex { index: BTreeMap::new(), truncated: BTreeSet::new(), metadata }
+    }
+
+    /// Decode an index stored as `format`.
+    pub fn from_bytes(bytes: &[u8], format: IndexFormat) -> Result<Self, LocaldocError> {
+        match format {
+            IndexFormat::Json => Ok(serde_json::from_slice(bytes)?),
+            IndexFormat::Binary => {
+                let body = bytes.strip_prefix(BINARY_MAGIC).ok_or("not a localdoc binary search index")?;
+                let binary: BinaryIndex = bincode_options().deserialize(body)?;
+                let mut index = BTreeMap::new();
+                let paths: Vec<Arc<str>> = binary.paths.into_iter().map(Arc::from).collect();
+                for (token, postings) in binary.terms {
+                    let mut decoded = Vec::with_capacity(postings.len());
+                    for (id, gaps) in postings {
+                        let path = paths.get(id as usize).ok_or("posting refers to an unknown path")?;
+                        let mut line = 0u32;
+                        let lines = gaps
+                            .iter()
+                            .map(|gap| {
+                                line = line.checked_add(*gap)?;
+                                Some(line)
+                            })
+                            .collect::<Option<_>>()
+                            .ok_or_else(|| format!("line numbers of '{}' overflow", token))?;
+                        decoded.push(Posting { path: path.clone(), lines });
+                    }
+                    index.insert(token, decoded);
+                }
+                Ok(SearchIndex {
+                    index,
+                    truncated: binary.truncated.into_iter().collect(),
+                    metadata: serde_json::from_str(&binary.metadata)?,
+                })
+            }
+        }
+    }
+
+    /// Write the index to `path` as `format`, streaming it out rather than
+    /// building the encoded file in memory. JSON is compact:
+    /// pretty-printing puts every line number on a line of its own.
+    pub fn write(&self, path: &Path, format: IndexFormat) -> Result<(), LocaldocError> {
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        match format {
+            IndexFormat::Json => serde_json::to_writer(&mut out, self)?,
+            IndexFormat::Binary => {
+                let mut ids: BTreeMap<&str, u32> = BTreeMap::new();
+                for posting in self.index.values().flatten() {
+                    ids.entry(&posting.path).or_insert(0);
+                }
+                for (id, slot) in ids.values_mut().enumerate() {
+                    *slot = id as u32;
+                }
+                let binary = BinaryIndexRef {
+                    metadata: serde_json::to_string(&self.metadata)?,
+                    paths: ids.keys().copied().collect(),
+                    terms: BinaryTerms { index: &self.index, ids: &ids },
+                    truncated: &self.truncated,
+                };
+                out.write_all(BINARY_MAGIC)?;
+                bincode_options().serialize_into(&mut out, &binary)?;
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Add the postings of the file at `path`, as from [`file_postings`].
+    /// Tokens found in many files are cut back to the [`MAX_POSTINGS`] that
+    /// [`finish`](Self::finish) would keep as they grow, so common words
+    /// don't hold a posting for every file until the end.
+    pub fn add_file(&mut self, path: &str, postings: BTreeMap<String, Vec<u32>>) {
+        let path: Arc<str> = Arc::from(path);
+        for (token, lines) in postings {
+            let list = self.index.entry(token.clone()).or_default();
+            list.push(Posting { path: path.clone(), lines });
+            if list.len() >= 2 * MAX_POSTINGS {
+                keep_most_frequent(list);
+                self.truncated.insert(token);
+            }
+        }
+    }
+
+    /// Indexed terms that `token` matches, each with how. The term dictionary
+    /// is sorted, so terms starting with `token` are one range scan; with
+    /// `fuzzy`, every term is also checked for being within one edit of
+    /// `token` (two for tokens of six characters or more).
+    pub fn matching_terms(&self, token: &str, prefix: bool, fuzzy: bool) -> Vec<(&str, MatchKind)> {
+        let mut terms: Vec<(&str, MatchKind)> = Vec::new();
+        if prefix {
+            let from: (Bound<&str>, Bound<&str>) = (Bound::Included(token), Bound::Unbounded);
+            for term in self.index.range::<str, _>(from).map(|(term, _)| term).take_while(|term| term.starts_with(token)) {
+                let kind = if term == token { MatchKind::Exact } else { MatchKind::Prefix };
+                terms.push((term, kind));
+            }
+        } else if let Some((term, _)) = self.index.get_key_value(token) {
+            terms.push((term, MatchKind::Exact));
+        }
+        if fuzzy {
+            let token: Vec<char> = token.chars().collect();
+            let max_edits = if token.len() >= FUZZY_TWO_EDITS_CHARS { 2 } else { 1 };
+            for term in self.index.keys() {
+                if !terms.iter().any(|(found, _)| found == term) && within_edits(&token, term, max_edits) {
+                    terms.push((term, MatchKind::Fuzzy));
+                }
+            }
+        }
+        terms
+    }
+
+    /// Drop every posting for `paths`, and tokens left with none.
+    pub fn remove_files(&mut self, paths: &BTreeSet<&str>) {
+        for postings in self.index.values_mut() {
+            postings.retain(|posting| !paths.contains(&*posting.path));
+        }
+        self.index.retain(|_, postings| !postings.is_empty());
+    }
+
+    /// Sort postings by path and cap them at [`MAX_POSTINGS`] per token.
+    pub fn finish(&mut self) {
+        for (token, postings) in self.index.iter_mut() {
+            if postings.len() > MAX_POSTINGS {
+                keep_most_frequent(postings);
+                self.truncated.insert(token.clone());
+            }
+            postings.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        self.truncated.retain(|token| self.index.contains_key(token));
+    }
+}
+
+/// Cut `postings` to the [`MAX_POSTINGS`] files where the token occurs on
+/// the most lines, ties going to the first path.
+fn keep_most_frequent(postings: &mut Vec<Posting>) {
+    postings.sort_by(|a, b| b.lines.len().cmp(&a.lines.len()).then_with(|| a.path.cmp(&b.path)));
+    postings.truncate(MAX_POSTINGS);
+}
+
+/// Whether `term` is at most `max_edits` insertions, deletions or
+/// substitutions away from `token` (Levenshtein distance).
+pub fn within_edits(token: &[char], term: &str, max_edits: usize) -> bool {
+    let term: Vec<char> = term.chars().collect();
+    if token.len().abs_diff(term.len()) > max_edits {
+        return false;
+    }
+    // One row of the edit-distance table at a time, stopping once every
+    // entry in a row is over the limit
+    let mut previous: Vec<usize> = (0..=term.len()).collect();
+    for (i, a) in token.iter().enumerate() {
+        let mut row = vec![i + 1; term.len() + 1];
+        for (j, b) in term.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != b);
+            row[j + 1] = substitution.min(previous[j + 1] + 1).min(row[j] + 1);
+        }
+        if row.iter().all(|&edits| edits > max_edits) {
+            return false;
+        }
+        previous = row;
+    }
+    previous[term.len()] <= max_edits
+}
+
+/// The `metadata` object of an index stored as `format`, without decoding
+/// the postings. Also reads JSON indexes in older layouts.
+pub fn read_metadata(reader: impl Read, format: IndexFormat) -> Option<Value> {
+    let mut reader = BufReader::new(reader);
+    match format {
+        IndexFormat::Json => {
+            // The postings are skipped as they stream past, never collected
+            #[derive(Deserialize)]
+            struct MetadataOnly {
+                #[serde(default)]
+                metadata: Value,
+            }
+            let index: MetadataOnly = serde_json::from_reader(reader).ok()?;
+            Some(index.metadata)
+        }
+        IndexFormat::Binary => {
+            let mut magic = [0; BINARY_MAGIC.len()];
+            reader.read_exact(&mut magic).ok()?;
+            if &magic != BINARY_MAGIC {
+                return None;
+            }
+            // The metadata string comes first; the postings are left unread
+            let metadata: String = bincode_options().deserialize_from(reader).ok()?;
+            serde_json::from_str(&metadata).ok()
+        }
+    }
+}
+
+/// Check that `reader` holds a well-formed `index/search.json`, returning
+/// its number of tokens. Postings are checked one token at a time and
+/// dropped, so an index of any size is checked in little memory.
+pub fn check_json(reader: impl Read) -> serde_json::Result<usize> {
+    // The other fields are read only to check their shape
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Shape {
+        index: TokenCount,
+        #[serde(default)]
+        truncated: BTreeSet<String>,
+        metadata: SearchMetadata,
+    }
+
+    struct TokenCount(usize);
+
+    impl<'de> Deserialize<'de> for TokenCount {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_map(TokenCount(0))
+        }
+    }
+
+    impl<'de> serde::de::Visitor<'de> for TokenCount {
+        type Value = TokenCount;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("an object of tokens to postings")
+        }
+
+        fn visit_map<A: serde::de::MapAccess<'de>>(mut self, mut map: A) -> Result<Self::Value, A::Error> {
+            while let Some((token, postings)) = map.next_entry::<String, Vec<Posting>>()? {
+                for posting in &postings {
+                    let ascending = posting.lines.windows(2).all(|pair| pair[0] < pair[1]);
+                    if posting.lines.first() == Some(&0) || !ascending {
+                        return Err(serde::de::Error::custom(format!(
+                            "lines of '{}' in {} are not ascending 1-based line numbers",
+                            token, posting.path
+                        )));
+                    }
+                }
+                self.0 += 1;
+            }
+            Ok(self)
+        }
+    }
+
+    let shape: Shape = serde_json::from_reader(BufReader::new(reader))?;
+    Ok(shape.index.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "use std::fs;\n\n/// Read the config.\nfn load(path: &str) -> String {\n    fs::read_to_string(path).unwrap()\n}\n";
+
+    fn minified_bundle() -> String {
+        // One statement after another with no line breaks, as bundlers emit
+        let mut bundle = String::from("!function(e,t){\"use strict\";");
+        for i in 0..200 {
+            bundle.push_str(&format!("var a{i}=function(n){{return n&&n.__esModule?n:{{default:n}}}};e.x{i}=a{i};"));
+        }
+        bundle.push_str("}(this);\n");
+        bundle
+    }
+
+    #[test]
+    fn ordinary_files_are_indexed() {
+        assert_eq!(skip_reason("src/config.rs", SOURCE), None);
+        assert_eq!(skip_reason("README.md", &"A paragraph of prose wrapped at eighty columns.\n".repeat(100)), None);
+        // Under the size floor a single long line is kept
+        assert_eq!(skip_reason("config.json", &format!("{{\"name\":\"{}\"}}", "x".repeat(700))), None);
+    }
+
+    #[test]
+    fn lockfiles_are_skipped_by_name() {
+        let lock = "[[package]]\nname = \"serde\"\nversion = \"1.0.0\"\nchecksum = \"abc123\"\n";
+        assert_eq!(skip_reason("Cargo.lock", lock), Some("lockfile"));
+        assert_eq!(skip_reason("web/package-lock.json", "{}"), Some("lockfile"));
+        assert_eq!(skip_reason("frontend/yarn.lock", "# yarn lockfile v1\n"), Some("lockfile"));
+        // Only exact names count
+        assert_eq!(skip_reason("docs/Cargo.lock.md", SOURCE), None);
+    }
+
+    #[test]
+    fn minified_names_are_skipped() {
+        assert_eq!(skip_reason("static/jquery.min.js", SOURCE), Some("minified"));
+        assert_eq!(skip_reason("css/site.min.css", "body{margin:0}"), Some("minified"));
+        assert_eq!(skip_reason("src/admin.js", SOURCE), None);
+    }
+
+    #[test]
+    fn long_lines_are_skipped() {
+        let bundle = minified_bundle();
+        assert!(bundle.len() > MIN_MINIFIED_BYTES);
+        assert_eq!(skip_reason("dist/app.js", &bundle), Some("long lines"));
+
+        // A few long lines among many short ones keep the average down
+        let mixed = format!("{}{}", SOURCE.repeat(40), bundle);
+        assert_eq!(skip_reason("src/vendor.js", &mixed), None);
+    }
+
+    #[test]
+    fn snapshot_changes_with_paths_and_sizes_only() {
+        let files = vec![("src/a.rs".to_string(), 10), ("README.md".to_string(), 5)];
+        let snapshot = FilesSnapshot::of(&files);
+        assert_eq!(snapshot.count, 2);
+
+        let reordered = vec![files[1].clone(), files[0].clone()];
+        assert_eq!(FilesSnapshot::of(&reordered), snapshot);
+
+        let resized = vec![("src/a.rs".to_string(), 11), ("README.md".to_string(), 5)];
+        assert_ne!(FilesSnapshot::of(&resized).digest, snapshot.digest);
+        let renamed = vec![("src/b.rs".to_string(), 10), ("README.md".to_string(), 5)];
+        assert_ne!(FilesSnapshot::of(&renamed).digest, snapshot.digest);
+    }
+
+    #[test]
+    fn terms_match_by_prefix_and_edit_distance() {
+        let mut index = SearchIndex::new(SearchMetadata {
+            total_files: 1,
+            indexed_at: String::new(),
+            updated_at: None,
+            skipped: BTreeMap::new(),
+            capped: BTreeSet::new(),
+            files: None,
+            fingerprint: Value::Null,
+        });
+        index.add_file("src/lib.rs", file_postings("extract extract_zip extracted extra zip"));
+        index.finish();
+
+        let matched = |token: &str, prefix: bool, fuzzy: bool| {
+            let mut terms = index.matching_terms(token, prefix, fuzzy);
+            terms.sort();
+            terms
+        };
+        assert_eq!(matched("extract", false, false), vec![("extract", MatchKind::Exact)]);
+        assert_eq!(
+            matched("extract", true, false),
+            vec![("extract", MatchKind::Exact), ("extract_zip", MatchKind::Prefix), ("extracted", MatchKind::Prefix)]
+        );
+        assert_eq!(matched("extrat_zip", false, true), vec![("extract_zip", MatchKind::Fuzzy)]);
+        // Two edits only for longer tokens
+        assert_eq!(matched("extrac", false, true), vec![("extra", MatchKind::Fuzzy), ("extract", MatchKind::Fuzzy)]);
+        assert_eq!(matched("zap", false, true), vec![("zip", MatchKind::Fuzzy)]);
+        assert!(matched("zoop", false, true).is_empty());
+    }
+
+    #[test]
+    fn metadata_is_streamed_and_json_shape_is_checked() {
+        let mut index = SearchIndex::new(SearchMetadata {
+            total_files: 1,
+            indexed_at: "2026-01-01T00:00:00Z".to_string(),
+            updated_at: None,
+            skipped: BTreeMap::new(),
+            capped: BTreeSet::new(),
+            files: None,
+            fingerprint: serde_json::json!({ "tokenizer": "t" }),
+        });
+        index.add_file("src/main.rs", file_postings(SOURCE));
+        index.finish();
+
+        let dir = tempfile::tempdir().unwrap();
+        for format in IndexFormat::ALL {
+            let path = dir.path().join(format.file_name());
+            index.write(&path, format).unwrap();
+            let metadata = read_metadata(fs::File::open(&path).unwrap(), format).unwrap();
+            assert_eq!(metadata["fingerprint"]["tokenizer"], "t", "{}", format.as_str());
+        }
+
+        let json = fs::read(dir.path().join("search.json")).unwrap();
+        assert!(check_json(json.as_slice()).unwrap() > 0);
+
+        let metadata = r#""metadata": {"total_files": 1, "indexed_at": "", "fingerprint": null}"#;
+        let shaped = |index: &str| format!(r#"{{"index": {}, {}}}"#, index, metadata);
+        assert_eq!(check_json(shaped(r#"{"fn": [{"path": "a.rs", "lines": [1, 4]}]}"#).as_bytes()).unwrap(), 1);
+        for bad in [r#"{"fn": ["a.rs"]}"#, r#"{"fn": [{"path": "a.rs", "lines": [4, 1]}]}"#, r#"["fn"]"#] {
+            assert!(check_json(shaped(bad).as_bytes()).is_err(), "{}", bad);
+        }
+        assert!(check_json(r#"{"index": {}}"#.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn binary_line_gaps_that_overflow_are_a_format_error() {
+        let metadata = r#"{"total_files": 1, "indexed_at": "", "fingerprint": null}"#;
+        let encode = |gaps: Vec<u32>| {
+            let terms = vec![("load".to_string(), vec![(0u32, gaps)])];
+            let body = bincode_options().serialize(&(metadata, vec!["a.rs"], terms, Vec::<String>::new())).unwrap();
+            [&BINARY_MAGIC[..], &body].concat()
+        };
+
+        let index = SearchIndex::from_bytes(&encode(vec![3, 4]), IndexFormat::Binary).unwrap();
+        assert_eq!(index.index["load"][0].lines, [3, 7]);
+        let error = SearchIndex::from_bytes(&encode(vec![u32::MAX, 1]), IndexFormat::Binary).err().unwrap();
+        assert!(error.to_string().contains("line numbers of 'load' overflow"), "{}", error);
+    }
+
+    #[test]
+    fn threshold_is_on_average_line_length() {
+        let at_limit = format!("{}\n", "a".repeat(MAX_AVERAGE_LINE_CHARS)).repeat(4);
+        assert_eq!(skip_reason("data.txt", &at_limit), None);
+        let over_limit = format!("{}\n", "a".repeat(MAX_AVERAGE_LINE_CHARS + 1)).repeat(4);
+        assert_eq!(skip_reason("data.txt", &over_limit), Some("long lines"));
+    }
+}
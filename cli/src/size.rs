@@ -0,0 +1,41 @@
+/// Parse a byte count such as `1048576`, `512K`, `10MB` or `1.5GiB`.
+/// Units are binary (`1K` = 1024 bytes) and case-insensitive.
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        other => return Err(format!("unknown size unit '{}' (use B, KB, MB, GB or TB)", other)),
+    };
+
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}' (expected e.g. 1048576 or 10MB)", text))?;
+    Ok((value * multiplier as f64).round() as u64)
+}
+
+/// Format a byte count for display, e.g. `2.31 GB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{:.2} {}", value, unit)
+}
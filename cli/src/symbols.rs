@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::graph::SCRIPT_EXTENSIONS;
+
+pub const FILE_NAME: &str = "symbols.json";
+
+/// Where a symbol is defined.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Definition {
+    /// Path under `files/`
+    pub path: String,
+    /// 1-based line of the definition
+    pub line: usize,
+    /// `function`, `class`, `struct`, `enum`, `trait` or `export`
+    pub kind: String,
+}
+
+/// `index/symbols.json`: definitions by symbol name.
+pub type Symbols = BTreeMap<String, Vec<Definition>>;
+
+/// Definition patterns for one language, each capturing the name.
+struct Matchers {
+    extensions: &'static [&'static str],
+    patterns: Vec<(&'static str, Regex)>,
+}
+
+fn matchers() -> &'static [Matchers] {
+    static MATCHERS: OnceLock<Vec<Matchers>> = OnceLock::new();
+    MATCHERS.get_or_init(|| {
+        let compile = |patterns: &[(&'static str, &str)]| {
+            patterns.iter().map(|(kind, pattern)| (*kind, Regex::new(pattern).unwrap())).collect()
+        };
+        // Rust items may be preceded by visibility and qualifiers
+        let rust_prefix = r#"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:(?:const|async|unsafe|default|extern(?:\s+"[^"]*")?)\s+)*"#;
+        vec![
+            Matchers {
+                extensions: &["py", "pyi"],
+                patterns: compile(&[
+                    ("function", r"^\s*(?:async\s+)?def\s+([A-Za-z_]\w*)"),
+                    ("class", r"^\s*class\s+([A-Za-z_]\w*)"),
+                ]),
+            },
+            Matchers {
+                extensions: &["rs"],
+                patterns: compile(&[
+                    ("function", &format!(r"{}fn\s+([A-Za-z_]\w*)", rust_prefix)),
+                    ("struct", &format!(r"{}struct\s+([A-Za-z_]\w*)", rust_prefix)),
+                    ("enum", &format!(r"{}enum\s+([A-Za-z_]\w*)", rust_prefix)),
+                    ("trait", &format!(r"{}(?:auto\s+)?trait\s+([A-Za-z_]\w*)", rust_prefix)),
+                ]),
+            },
+            Matchers {
+                extensions: SCRIPT_EXTENSIONS,
+                patterns: compile(&[
+                    ("function", r"^\s*(?:export\s+)?(?:default\s+)?(?:async\s+)?function\s*\*?\s*([A-Za-z_$][\w$]*)"),
+                    ("class", r"^\s*(?:export\s+)?(?:default\s+)?(?:abstract\s+)?class\s+([A-Za-z_$][\w$]*)"),
+                    (
+                        "export",
+                        r"^\s*export\s+(?:declare\s+)?(?:const|let|var|interface|type|enum)\s+([A-Za-z_$][\w$]*)",
+                    ),
+                ]),
+            },
+        ]
+    })
+}
+
+/// The definitions in `content`, the text of the file at `path`, as
+/// (name, definition) pairs. Files in languages without matchers have none.
+pub fn extract(path: &str, content: &str) -> Vec<(String, Definition)> {
+    let extension = path.rsplit_once('.').map(|(_, extension)| extension).unwrap_or_default();
+    let Some(matchers) = matchers().iter().find(|m| m.extensions.contains(&extension)) else {
+        return Vec::new();
+    };
+
+    let mut found = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        for (kind, pattern) in &matchers.patterns {
+            if let Some(name) = pattern.captures(line).and_then(|captures| captures.get(1)) {
+                let definition = Definition { path: path.to_string(), line: i + 1, kind: kind.to_string() };
+                found.push((name.as_str().to_string(), definition));
+                break;
+            }
+        }
+    }
+    found
+}
+
+/// Add `definitions` to `symbols`, keeping each list sorted by path and line.
+pub fn add(symbols: &mut Symbols, definitions: Vec<(String, Definition)>) {
+    for (name, definition) in definitions {
+        let list = symbols.entry(name).or_default();
+        let at = list.partition_point(|existing| *existing < definition);
+        list.insert(at, definition);
+    }
+}
+
+/// Why `value` is not a valid `index/symbols.json`, if it isn't.
+pub fn check_shape(value: &Value) -> Result<(), String> {
+    let Some(symbols) = value.as_object() else {
+        return Err("must be an object mapping symbol names to definitions".to_string());
+    };
+    for (name, definitions) in symbols {
+        let Some(definitions) = definitions.as_array() else {
+            return Err(format!("'{}' must map to an array of definitions", name));
+        };
+        for definition in definitions {
+            let valid = definition["path"].is_string() && definition["line"].is_u64() && definition["kind"].is_string();
+            if !valid {
+                return Err(format!("'{}' has a definition without a string path, numeric line and string kind", name));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+//! Synthetic source trees for `localdoc bench` and the integration tests.
+//! The same spec always writes the same tree, byte for byte, so timings
+//! from different releases compare like with like.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Source files per directory before the generator starts another one.
+const FILES_PER_DIR: usize = 40;
+
+/// Extensions drawn for generated files, with how often each is drawn.
+const EXTENSIONS: &[(&str, u32)] = &[("rs", 30), ("py", 25), ("ts", 20), ("md", 15), ("json", 10)];
+
+const WORDS: &[&str] = &[
+    "account", "buffer", "cache", "config", "cursor", "decode", "entry", "event", "fetch", "handler", "index", "limit",
+    "manifest", "offset", "parse", "query", "record", "render", "request", "schema", "session", "token", "update",
+    "value", "worker",
+];
+
+/// What to generate.
+#[derive(Clone, Debug)]
+pub struct TreeSpec {
+    pub files: usize,
+    /// Trees with the same seed and size are identical
+    pub seed: u64,
+}
+
+impl TreeSpec {
+    pub fn new(files: usize) -> Self {
+        TreeSpec { files, seed: 1 }
+    }
+}
+
+/// What [`generate`] wrote.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TreeStats {
+    pub files: usize,
+    /// Directories holding generated files
+    pub dirs: usize,
+    pub bytes: u64,
+}
+
+/// Write `spec.files` source files under `root`, in nested directories of
+/// up to 40 files. Sizes vary the way a real repository's do: most files
+/// are under 4 KB, about a quarter are up to 32 KB and one in fifty is up
+/// to 256 KB. Contents are code and prose made of a small vocabulary, so
+/// the search index has terms and symbols to find.
+pub fn generate(root: &Path, spec: &TreeSpec) -> io::Result<TreeStats> {
+    let mut rng = Rng(spec.seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1);
+    let mut stats = TreeStats::default();
+
+    fs::create_dir_all(root)?;
+    fs::write(root.join("README.md"), format!("# Synthetic project\n\n{} generated source files.\n", spec.files))?;
+    stats.files += 1;
+    stats.bytes += fs::metadata(root.join("README.md"))?.len();
+
+    for i in 0..spec.files.saturating_sub(1) {
+        let n = i / FILES_PER_DIR;
+        let dir = root.join(format!("src/{}/{}_{}", WORDS[n % WORDS.len()], WORDS[n / WORDS.len() % WORDS.len()], n));
+        if i % FILES_PER_DIR == 0 {
+            fs::create_dir_all(&dir)?;
+            stats.dirs += 1;
+        }
+
+        let size = match rng.below(100) {
+            0..=74 => 200 + rng.below(3_900),
+            75..=97 => 4_096 + rng.below(28_672),
+            _ => 32_768 + rng.below(229_376),
+        } as usize;
+        let extension = rng.pick(EXTENSIONS);
+        let contents = contents(extension, size, &mut rng);
+        fs::write(dir.join(format!("{}_{}.{}", WORDS[rng.below(WORDS.len() as u64) as usize], i, extension)), &contents)?;
+        stats.files += 1;
+        stats.bytes += contents.len() as u64;
+    }
+
+    Ok(stats)
+}
+
+/// About `size` bytes of a file of type `extension`.
+fn contents(extension: &str, size: usize, rng: &mut Rng) -> String {
+    let mut text = String::with_capacity(size + 128);
+    if extension == "json" {
+        text.push_str("{\n");
+    }
+    let mut n = 0;
+    while text.len() < size {
+        let (a, b) = (WORDS[rng.below(WORDS.len() as u64) as usize], WORDS[rng.below(WORDS.len() as u64) as usize]);
+        let line = match extension {
+            "rs" => format!("pub fn {}_{}_{}(value: u64) -> u64 {{\n    value.wrapping_mul({})\n}}\n\n", a, b, n, n + 3),
+            "py" => format!("def {}_{}_{}(value):\n    return value * {}\n\n", a, b, n, n + 3),
+            "ts" => format!("export function {}{}{}(value: number): number {{\n  return value * {};\n}}\n\n", a, capitalize(b), n, n + 3),
+            "json" => format!("  \"{}_{}_{}\": {},\n", a, b, n, n + 3),
+            _ => format!("The {} {} step {} keeps the {} in order.\n\n", a, b, n, WORDS[n % WORDS.len()]),
+        };
+        text.push_str(&line);
+        n += 1;
+    }
+    if extension == "json" {
+        text.push_str("  \"end\": 0\n}\n");
+    }
+    text
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+}
+
+/// xorshift64: fast, and the same everywhere, unlike an OS-seeded RNG.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    fn pick<'a>(&mut self, weighted: &[(&'a str, u32)]) -> &'a str {
+        let total: u32 = weighted.iter().map(|(_, weight)| weight).sum();
+        let mut roll = self.below(total as u64) as u32;
+        for (item, weight) in weighted {
+            if roll < *weight {
+                return item;
+            }
+            roll -= weight;
+        }
+        weighted[0].0
+    }
+}
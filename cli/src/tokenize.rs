@@ -0,0 +1,97 @@
+use serde_json::{json, Value};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Name and version recorded in index fingerprints. Bump the version
+/// whenever the tokens produced for the same text change.
+pub const ANALYZER: &str = "unicode-words";
+pub const ANALYZER_VERSION: &str = "2";
+
+/// Minimum length (in characters) for a non-CJK token to be indexed.
+const MIN_TOKEN_CHARS: usize = 3;
+
+/// Tokenizer settings recorded in index fingerprints.
+pub fn options() -> Value {
+    json!({
+        "min_token_chars": MIN_TOKEN_CHARS,
+        "lowercase": true,
+        "cjk": "bigrams",
+    })
+}
+
+/// Split text into normalized search tokens.
+///
+/// Words come from Unicode word boundaries (UAX #29) and are lowercased with
+/// full Unicode case mapping. Scripts written without spaces (CJK) have no
+/// usable word boundaries, so runs of those characters are indexed as
+/// overlapping bigrams instead; Hangul is too, so a Korean compound is found
+/// by its parts. Indexing and querying must both go through
+/// this function so their tokens line up.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut run = String::new();
+
+    for segment in text.split_word_bounds() {
+        if segment.chars().all(is_cjk) {
+            run.push_str(segment);
+            continue;
+        }
+
+        push_cjk_run(&mut tokens, &run);
+        run.clear();
+
+        if segment.chars().any(char::is_alphanumeric) && segment.chars().count() >= MIN_TOKEN_CHARS {
+            tokens.push(segment.to_lowercase());
+        }
+    }
+    push_cjk_run(&mut tokens, &run);
+
+    tokens
+}
+
+/// Emit overlapping bigrams for a run of CJK characters; a lone character
+/// is kept as-is so single-ideograph words stay searchable.
+fn push_cjk_run(tokens: &mut Vec<String>, run: &str) {
+    let chars: Vec<char> = run.chars().collect();
+    match chars.len() {
+        0 => {}
+        1 => tokens.push(chars[0].to_string()),
+        _ => {
+            for pair in chars.windows(2) {
+                tokens.push(pair.iter().collect());
+            }
+        }
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x309F     // Hiragana
+        | 0x30A0..=0x30FF   // Katakana
+        | 0x3400..=0x4DBF   // CJK Unified Ideographs Extension A
+        | 0x4E00..=0x9FFF   // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF   // Hangul Syllables
+        | 0xF900..=0xFAFF   // CJK Compatibility Ideographs
+        | 0xFF66..=0xFF9F   // Halfwidth Katakana
+        | 0x20000..=0x2FA1F // CJK Extensions B-F and supplement
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_are_lowercased_with_unicode_case_mapping() {
+        assert_eq!(tokenize("Die Größe der Datei"), ["die", "größe", "der", "datei"]);
+        assert_eq!(tokenize("ÜBER Straße naïve"), ["über", "straße", "naïve"]);
+        assert_eq!(tokenize("fn parse_config(x: u8) -> Ok"), ["parse_config"]);
+    }
+
+    #[test]
+    fn cjk_runs_become_bigrams_between_other_words() {
+        assert_eq!(tokenize("// 設定値を読む"), ["設定", "定値", "値を", "を読", "読む"]);
+        assert_eq!(tokenize("let 検索 = search_index;"), ["let", "検索", "search_index"]);
+        assert_eq!(tokenize("字"), ["字"]);
+        assert_eq!(tokenize("검색엔진 query"), ["검색", "색엔", "엔진", "query"]);
+    }
+}
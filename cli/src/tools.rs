@@ -0,0 +1,105 @@
+use crate::embeddings;
+
+/// A tool the documenter agent provides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    ListFiles,
+    ReadFile,
+    ReadImage,
+    ReadPdf,
+    SearchCode,
+    SemanticSearch,
+    QueryGraph,
+    WriteOutput,
+}
+
+impl Tool {
+    /// Every tool, in manifest order.
+    pub const ALL: [Tool; 8] = [
+        Tool::ListFiles,
+        Tool::ReadFile,
+        Tool::ReadImage,
+        Tool::ReadPdf,
+        Tool::SearchCode,
+        Tool::SemanticSearch,
+        Tool::QueryGraph,
+        Tool::WriteOutput,
+    ];
+
+    /// Name used in `environment.tools` and `tools_allowed`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::ListFiles => "list_files",
+            Tool::ReadFile => "read_file",
+            Tool::ReadImage => "read_image",
+            Tool::ReadPdf => "read_pdf",
+            Tool::SearchCode => "search_code",
+            Tool::SemanticSearch => "semantic_search",
+            Tool::QueryGraph => "query_graph",
+            Tool::WriteOutput => "write_output",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Tool::ListFiles => "List files in a directory",
+            Tool::ReadFile => "Read a file's contents",
+            Tool::ReadImage => "Read an image for vision/OCR analysis",
+            Tool::ReadPdf => "Render a PDF page for vision/OCR analysis",
+            Tool::SearchCode => "Search the inverted index",
+            Tool::SemanticSearch => "Search by meaning using embeddings",
+            Tool::QueryGraph => "Query the relationship graph",
+            Tool::WriteOutput => "Write a file to output/",
+        }
+    }
+
+    /// The artifact under `index/` the tool cannot work without, if any.
+    pub fn required_index(self) -> Option<&'static str> {
+        match self {
+            Tool::SemanticSearch => Some(embeddings::BIN_FILE),
+            Tool::QueryGraph => Some("graph.json"),
+            _ => None,
+        }
+    }
+
+    /// The tool called `name`, if the agent provides one.
+    pub fn from_name(name: &str) -> Option<Tool> {
+        Tool::ALL.into_iter().find(|tool| tool.name() == name)
+    }
+}
+
+const MINIMAL: &[Tool] = &[Tool::ListFiles, Tool::ReadFile, Tool::WriteOutput];
+
+/// Named tool lists for `ingest --preset`. Each preset adds to `minimal`.
+pub const PRESETS: &[(&str, &[Tool])] = &[
+    ("minimal", &[]),
+    ("code-analysis", &[Tool::SearchCode, Tool::QueryGraph]),
+    ("research", &[Tool::ReadPdf, Tool::ReadImage]),
+    ("full", &Tool::ALL),
+];
+
+/// Preset used when none is given.
+pub const DEFAULT_PRESET: &str = "minimal";
+
+/// The tools for `preset` plus `extra`, in manifest order with any unknown
+/// extras last.
+pub fn expand(preset: &str, extra: &[String]) -> Result<Vec<String>, String> {
+    let Some((_, added)) = PRESETS.iter().find(|(name, _)| *name == preset) else {
+        let names: Vec<&str> = PRESETS.iter().map(|(name, _)| *name).collect();
+        return Err(format!("Unknown preset '{}'. Available presets: {}", preset, names.join(", ")));
+    };
+
+    let selected = |tool: &Tool| MINIMAL.contains(tool) || added.contains(tool) || extra.iter().any(|e| e == tool.name());
+    let mut tools: Vec<String> = Tool::ALL.iter().filter(|tool| selected(tool)).map(|tool| tool.name().to_string()).collect();
+    for tool in extra {
+        if Tool::from_name(tool).is_none() && !tools.contains(tool) {
+            tools.push(tool.clone());
+        }
+    }
+    Ok(tools)
+}
+
+/// The tools of the default preset, for docpacks made without one.
+pub fn defaults() -> Vec<String> {
+    expand(DEFAULT_PRESET, &[]).expect("the default preset exists")
+}
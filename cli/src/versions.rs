@@ -0,0 +1,189 @@
+use std::fmt;
+use serde_json::{json, Value};
+
+use crate::repair;
+
+pub use docpack_core::MANIFEST_VERSION;
+
+/// Oldest manifest version read as is. Older manifests are upgraded by
+/// `localdoc migrate`.
+pub const MIN_MANIFEST_VERSION: Version = Version { major: 1, minor: 0 };
+
+/// Newest major version read. Later minor versions of it only add optional
+/// fields, so they are read with a warning; later majors are refused.
+pub const MAX_MANIFEST_MAJOR: u32 = 1;
+
+/// A manifest `version`: `"<major>.<minor>"`, or just `"<major>"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl Version {
+    pub fn parse(text: &str) -> Option<Version> {
+        let (major, minor) = text.split_once('.').unwrap_or((text, "0"));
+        Some(Version { major: major.parse().ok()?, minor: minor.parse().ok()? })
+    }
+
+    /// The version this CLI writes.
+    pub fn current() -> Version {
+        Version::parse(MANIFEST_VERSION).expect("MANIFEST_VERSION parses")
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Whether this CLI can read a manifest, going by its `version`.
+#[derive(Debug, PartialEq)]
+pub enum Compatibility {
+    Supported,
+    /// A later minor version: read, but the fields it added are ignored
+    Newer(Version),
+    /// Older than [`MIN_MANIFEST_VERSION`], or `None` when the manifest
+    /// has no version at all; `localdoc migrate` upgrades it
+    Outdated(Option<Version>),
+    /// A later major version, or a `version` that is not one
+    Unsupported(String),
+}
+
+impl Compatibility {
+    /// What to tell the user, unless the manifest is supported.
+    pub fn message(&self) -> Option<String> {
+        match self {
+            Compatibility::Supported => None,
+            Compatibility::Newer(version) => Some(format!(
+                "docpack.json is version {}, newer than this localdoc ({}); fields it added are ignored",
+                version, MANIFEST_VERSION
+            )),
+            Compatibility::Outdated(Some(version)) => Some(format!(
+                "docpack.json is version {}, older than {}; upgrade it with `localdoc migrate`",
+                version, MIN_MANIFEST_VERSION
+            )),
+            Compatibility::Outdated(None) => {
+                Some("docpack.json has no version; upgrade it with `localdoc migrate`".to_string())
+            }
+            Compatibility::Unsupported(version) => Some(format!(
+                "docpack.json is version {}, which this localdoc cannot read (it reads {}.x from {})",
+                version, MAX_MANIFEST_MAJOR, MIN_MANIFEST_VERSION
+            )),
+        }
+    }
+}
+
+/// The manifest's version, `None` if it has none, or the text of a
+/// `version` that is not one.
+fn version_of(manifest: &Value) -> Result<Option<Version>, String> {
+    let text = match &manifest["version"] {
+        Value::Null => return Ok(None),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    Version::parse(&text).map(Some).ok_or(text)
+}
+
+pub fn compatibility(manifest: &Value) -> Compatibility {
+    classify(version_of(manifest))
+}
+
+/// [`compatibility`] of a typed manifest's `version`.
+pub fn compatibility_of(version: &str) -> Compatibility {
+    classify(Version::parse(version).map(Some).ok_or_else(|| version.to_string()))
+}
+
+fn classify(version: Result<Option<Version>, String>) -> Compatibility {
+    match version {
+        Err(text) => Compatibility::Unsupported(format!("'{}'", text)),
+        Ok(None) => Compatibility::Outdated(None),
+        Ok(Some(version)) if version.major > MAX_MANIFEST_MAJOR => Compatibility::Unsupported(version.to_string()),
+        Ok(Some(version)) if version < MIN_MANIFEST_VERSION => Compatibility::Outdated(Some(version)),
+        Ok(Some(version)) if version > Version::current() => Compatibility::Newer(version),
+        Ok(Some(_)) => Compatibility::Supported,
+    }
+}
+
+/// One upgrade of the manifest layout. Steps run in order, each on
+/// manifests older than its `to`, which it then sets as the version.
+pub struct Step {
+    pub to: Version,
+    pub summary: &'static str,
+    /// Rewrites the manifest, returning a description of each change
+    pub apply: fn(&mut Value) -> Vec<String>,
+}
+
+/// Every manifest upgrade, oldest first. A new layout adds a step here
+/// and bumps [`MANIFEST_VERSION`].
+pub const STEPS: &[Step] = &[Step {
+    to: Version { major: 1, minor: 0 },
+    summary: "manifests from before `version` was required",
+    apply: unversioned_to_1_0,
+}];
+
+/// Unversioned manifests could leave out `environment` or list the tools
+/// as one string.
+fn unversioned_to_1_0(manifest: &mut Value) -> Vec<String> {
+    let mut changes = Vec::new();
+    if manifest["environment"].is_null() {
+        manifest["environment"] = json!({});
+        changes.push("added an empty 'environment'".to_string());
+    }
+    changes.extend(repair::environment_tools(manifest));
+    changes
+}
+
+/// Upgrade `manifest` to [`MANIFEST_VERSION`] through every step it is
+/// older than, returning what each one changed. Manifests of an unreadable
+/// or newer version are left alone and reported as an error.
+pub fn migrate(manifest: &mut Value) -> Result<Vec<String>, String> {
+    if !manifest.is_object() {
+        return Err("docpack.json is not a JSON object".to_string());
+    }
+    if let compatibility @ (Compatibility::Unsupported(_) | Compatibility::Newer(_)) = compatibility(manifest) {
+        return Err(compatibility.message().unwrap_or_default());
+    }
+    // Unversioned manifests predate every step
+    let from = version_of(manifest).ok().flatten().unwrap_or(Version { major: 0, minor: 0 });
+
+    let mut changes = Vec::new();
+    for step in STEPS.iter().filter(|step| from < step.to) {
+        changes.extend((step.apply)(manifest));
+        manifest["version"] = json!(step.to.to_string());
+        changes.push(format!("set 'version' to \"{}\" ({})", step.to, step.summary));
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_parse_and_order() {
+        assert_eq!(Version::parse("1.0"), Some(Version { major: 1, minor: 0 }));
+        assert_eq!(Version::parse("2"), Some(Version { major: 2, minor: 0 }));
+        assert_eq!(Version::parse("1.x"), None);
+        assert!(Version::parse("1.10").unwrap() > Version::parse("1.9").unwrap());
+        assert_eq!(Version::current().to_string(), MANIFEST_VERSION);
+    }
+
+    #[test]
+    fn compatibility_follows_the_supported_range() {
+        let of = |version: Value| compatibility(&json!({ "version": version }));
+        assert_eq!(of(json!(MANIFEST_VERSION)), Compatibility::Supported);
+        assert_eq!(of(json!("1.3")), Compatibility::Newer(Version { major: 1, minor: 3 }));
+        assert_eq!(of(json!("0.9")), Compatibility::Outdated(Some(Version { major: 0, minor: 9 })));
+        assert_eq!(compatibility(&json!({})), Compatibility::Outdated(None));
+        assert!(matches!(of(json!("2.0")), Compatibility::Unsupported(_)));
+        assert!(matches!(of(json!("next")), Compatibility::Unsupported(_)));
+    }
+
+    #[test]
+    fn steps_are_in_order_and_end_at_the_current_version() {
+        assert!(STEPS.windows(2).all(|pair| pair[0].to < pair[1].to));
+        assert_eq!(STEPS.last().map(|step| step.to), Some(Version::current()));
+    }
+}
@@ -0,0 +1,95 @@
+//! Archives that would be unsafe to extract are caught by `archive::audit`
+//! and by `validate`, before `run` ever unpacks them.
+
+mod common;
+
+use std::io::{Cursor, Write};
+
+use localdoc::archive::{self, Hazard, Limits, MemorySink};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// A docpack archive with `extra` entries written after the usual ones.
+fn archive_with(extra: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let manifest = br#"{"version": "1.0", "name": "x", "environment": {"tools": ["read_file"]}}"#;
+    let mut entries = vec![("docpack.json", manifest.to_vec()), ("files/a.txt", b"hello".to_vec())];
+    entries.extend(extra.iter().cloned());
+    for (name, data) in entries {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(&data).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+#[test]
+fn audit_finds_escapes_duplicates_and_bombs() {
+    let bytes = archive_with(&[
+        ("../evil.sh", b"echo".to_vec()),
+        ("/etc/passwd", b"root".to_vec()),
+        ("files/a.txt", b"again".to_vec()),
+        ("files/zeros.bin", vec![0; 4 * 1024 * 1024]),
+    ]);
+    let hazards = archive::audit(Cursor::new(bytes.clone()), &Limits::default()).unwrap();
+
+    assert!(hazards.contains(&Hazard::UnsafePath("../evil.sh".to_string())), "{:?}", hazards);
+    assert!(hazards.contains(&Hazard::UnsafePath("/etc/passwd".to_string())), "{:?}", hazards);
+    assert!(hazards.contains(&Hazard::DuplicateEntry("files/a.txt".to_string())), "{:?}", hazards);
+    assert!(
+        hazards.iter().any(|h| matches!(h, Hazard::CompressionRatio { path, .. } if path == "files/zeros.bin")),
+        "{:?}",
+        hazards
+    );
+    assert_eq!(hazards.iter().filter(|h| h.is_unsafe()).count(), 3);
+
+    let mut sink = MemorySink::default();
+    assert!(archive::extract_entries(Cursor::new(bytes), &mut sink, &Limits::default()).is_err());
+}
+
+#[test]
+fn audit_passes_a_clean_archive() {
+    let bytes = archive_with(&[("files/src/lib.rs", b"fn main() {}".to_vec())]);
+    assert_eq!(archive::audit(Cursor::new(bytes), &Limits::default()).unwrap(), Vec::new());
+}
+
+#[test]
+fn extraction_refuses_duplicate_files() {
+    let bytes = archive_with(&[("files/a.txt", b"again".to_vec())]);
+    let mut sink = MemorySink::default();
+    let error = archive::extract_entries(Cursor::new(bytes), &mut sink, &Limits::default()).unwrap_err();
+    assert!(error.to_string().contains("duplicate entry"), "{}", error);
+}
+
+#[test]
+fn validate_reports_unsafe_archives() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("evil.docpack");
+    std::fs::write(&path, archive_with(&[("files/../../evil.sh", b"echo".to_vec())])).unwrap();
+
+    let out = common::localdoc(dir.path(), &["validate", "--no-plugins", "--json", "evil.docpack"]);
+    assert!(!out.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let errors = report["errors"].as_array().unwrap();
+    assert!(
+        errors.iter().any(|e| e["code"] == "DP031" && e["file"] == "files/../../evil.sh"),
+        "{:?}",
+        errors
+    );
+}
+
+#[test]
+fn run_refuses_unsafe_archives_before_extracting() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("evil.docpack");
+    std::fs::write(&path, archive_with(&[("files/../../evil.sh", b"echo".to_vec())])).unwrap();
+
+    let out = common::command(dir.path())
+        .env("PATH", dir.path().join("no-such-bin"))
+        .args(["run", "--runtime", "docker", "--no-env", "evil.docpack"])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("[DP031]") && stderr.contains("Validation failed"), "{}", stderr);
+}
@@ -0,0 +1,55 @@
+//! Synthetic trees are reproducible, ingest like any other tree, and
+//! `localdoc bench` reports every phase for each of them.
+
+mod common;
+
+use std::fs;
+
+use common::{localdoc, snapshot, stdout};
+use localdoc::synthetic::{self, TreeSpec};
+use serde_json::Value;
+
+#[test]
+fn the_same_seed_writes_the_same_tree() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b, c) = (dir.path().join("a"), dir.path().join("b"), dir.path().join("c"));
+    let stats = synthetic::generate(&a, &TreeSpec::new(100)).unwrap();
+    assert_eq!(synthetic::generate(&b, &TreeSpec::new(100)).unwrap(), stats);
+    assert_eq!(snapshot(&a), snapshot(&b));
+    assert_eq!(stats.files, 100);
+    assert_eq!(stats.dirs, 3);
+    assert_eq!(stats.bytes, snapshot(&a).iter().map(|(_, data)| data.len() as u64).sum::<u64>());
+
+    synthetic::generate(&c, &TreeSpec { files: 100, seed: 2 }).unwrap();
+    assert_ne!(snapshot(&a), snapshot(&c));
+}
+
+#[test]
+fn synthetic_trees_ingest_every_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("source");
+    let stats = synthetic::generate(&source, &TreeSpec::new(120)).unwrap();
+    stdout(&localdoc(dir.path(), &["ingest", "source", "-o", "out.docpack", "--build-index", "-q"]));
+    let listed = stdout(&localdoc(dir.path(), &["list", "out.docpack", "--prefix", "files/"]));
+    let expected: Vec<String> = snapshot(&source).into_iter().map(|(name, _)| format!("files/{}", name)).collect();
+    assert_eq!(listed.lines().collect::<Vec<_>>(), expected);
+    assert_eq!(expected.len(), stats.files);
+}
+
+#[test]
+fn bench_reports_each_phase() {
+    let dir = tempfile::tempdir().unwrap();
+    stdout(&localdoc(dir.path(), &["-q", "bench", "--self-test", "--files", "50,80", "--json", "first.json"]));
+    let report: Value = serde_json::from_str(&fs::read_to_string(dir.path().join("first.json")).unwrap()).unwrap();
+    let runs = report["runs"].as_array().unwrap();
+    assert_eq!(runs.iter().map(|run| run["tree"].as_str().unwrap()).collect::<Vec<_>>(), ["synthetic-50", "synthetic-80"]);
+    for run in runs {
+        let phases: Vec<&str> = run["phases"].as_array().unwrap().iter().map(|phase| phase["name"].as_str().unwrap()).collect();
+        assert_eq!(phases, ["scan", "hash", "index", "archive"]);
+        assert!(run["phases"][0]["seconds"].as_f64().unwrap() >= 0.0);
+    }
+    assert_eq!(runs[1]["files"], 80);
+
+    stdout(&localdoc(dir.path(), &["-q", "bench", "--self-test", "--files", "50", "--baseline", "first.json", "--keep", "kept"]));
+    assert!(dir.path().join("kept/synthetic-50/bench.docpack").is_file());
+}
@@ -0,0 +1,69 @@
+//! `localdoc clean` deletes a docpack's outputs and localdoc's leftover
+//! scratch directories, and nothing else.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Output, Stdio};
+
+use common::stdout;
+
+/// localdoc with its scratch directories under `tmp` and nothing on stdin.
+fn localdoc(dir: &Path, tmp: &Path, args: &[&str]) -> Output {
+    common::command(dir).env("TMPDIR", tmp).args(args).stdin(Stdio::null()).output().unwrap()
+}
+
+#[test]
+fn outputs_are_emptied_only_when_confirmed() {
+    let dir = tempfile::tempdir().unwrap();
+    let tmp = tempfile::tempdir().unwrap();
+    common::project(dir.path(), 5);
+    stdout(&localdoc(dir.path(), tmp.path(), &["ingest", "project", "-o", "app.docpack", "--quiet"]));
+    stdout(&localdoc(dir.path(), tmp.path(), &["unpack", "app.docpack", "-o", "app"]));
+    fs::create_dir_all(dir.path().join("app/output/api")).unwrap();
+    fs::write(dir.path().join("app/output/overview.md"), "# Old\n").unwrap();
+    fs::write(dir.path().join("app/output/api/cli.md"), "# Old\n").unwrap();
+    fs::create_dir_all(dir.path().join("app/.reasoning")).unwrap();
+    fs::write(dir.path().join("app/.reasoning/notes.md"), "thinking\n").unwrap();
+
+    let listed = stdout(&localdoc(dir.path(), tmp.path(), &["clean", "app", "--dry-run"]));
+    assert!(listed.contains("3 file(s)") && listed.contains("output/api"), "{}", listed);
+    assert!(dir.path().join("app/output/overview.md").exists());
+
+    // Without a terminal to ask on, only --yes deletes
+    let unconfirmed = localdoc(dir.path(), tmp.path(), &["clean", "app"]);
+    assert!(String::from_utf8_lossy(&unconfirmed.stderr).contains("pass --yes"));
+    assert!(dir.path().join("app/output/overview.md").exists());
+
+    stdout(&localdoc(dir.path(), tmp.path(), &["clean", "app", "--yes"]));
+    assert_eq!(fs::read_dir(dir.path().join("app/output")).unwrap().count(), 0);
+    assert!(!dir.path().join("app/.reasoning").exists());
+    assert!(dir.path().join("app/files/README.md").exists() && dir.path().join("app/tasks.json").exists());
+}
+
+#[test]
+fn only_recognized_scratch_directories_leave_the_temp_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let tmp = tempfile::tempdir().unwrap();
+    fs::create_dir_all(tmp.path().join("docpack-run-a1b2c3/output")).unwrap();
+    fs::write(tmp.path().join("docpack-run-a1b2c3/output/big.md"), vec![b'x'; 4096]).unwrap();
+    fs::create_dir_all(tmp.path().join("docpack-build-Zz9Yy8")).unwrap();
+    fs::create_dir_all(tmp.path().join("docpack-run-")).unwrap();
+    fs::create_dir_all(tmp.path().join("unrelated-dir")).unwrap();
+    fs::write(tmp.path().join("docpack-run-f1le00"), "not a directory").unwrap();
+
+    // Nothing is a day old yet
+    let recent = stdout(&localdoc(dir.path(), tmp.path(), &["clean", "--temp"]));
+    assert!(recent.contains("No leftover scratch directories older than 1d"), "{}", recent);
+
+    let listed = stdout(&localdoc(dir.path(), tmp.path(), &["clean", "--temp", "--older-than", "0s", "--dry-run"]));
+    assert!(listed.contains("2 director(ies) would be removed, reclaiming 4.00 KB"), "{}", listed);
+    assert!(tmp.path().join("docpack-run-a1b2c3").exists());
+
+    let cleaned = stdout(&localdoc(dir.path(), tmp.path(), &["clean", "--temp", "--older-than", "0s"]));
+    assert!(cleaned.contains("✓ Removed 2 director(ies), reclaimed 4.00 KB"), "{}", cleaned);
+    let mut left: Vec<String> = fs::read_dir(tmp.path()).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    left.sort();
+    assert_eq!(left, ["docpack-run-", "docpack-run-f1le00", "unrelated-dir"]);
+}
@@ -0,0 +1,62 @@
+//! Running the localdoc binary and building docpacks to run it on, for the
+//! integration tests. Not every test file uses every helper.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use localdoc::synthetic::{self, TreeSpec};
+
+/// localdoc run in `dir`, which is also its config directory, so the
+/// user's own config.toml never applies.
+pub fn command(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_localdoc"));
+    command.current_dir(dir).env("XDG_CONFIG_HOME", dir);
+    command
+}
+
+pub fn localdoc(dir: &Path, args: &[&str]) -> Output {
+    command(dir).args(args).output().unwrap()
+}
+
+/// What a command that must have succeeded printed.
+pub fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// A generated project of `files` files, a README.md and sources, in
+/// `dir/project`.
+pub fn project(dir: &Path, files: usize) -> PathBuf {
+    let source = dir.join("project");
+    synthetic::generate(&source, &TreeSpec::new(files)).unwrap();
+    source
+}
+
+/// Ingest `dir/project` into `dir/app.docpack`.
+pub fn ingest(dir: &Path, extra: &[&str]) {
+    let mut args = vec!["ingest", "project", "-o", "app.docpack", "--quiet"];
+    args.extend(extra);
+    stdout(&localdoc(dir, &args));
+}
+
+/// Every file under `root` and its contents, sorted by `/`-separated path.
+pub fn snapshot(root: &Path) -> Vec<(String, Vec<u8>)> {
+    let mut files: Vec<_> = walkdir::WalkDir::new(root)
+        .into_iter()
+        .map(Result::unwrap)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| {
+            let name = entry.path().strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/");
+            (name, fs::read(entry.path()).unwrap())
+        })
+        .collect();
+    files.sort();
+    files
+}
@@ -0,0 +1,96 @@
+//! `localdoc config` edits the user's config.toml and a project's
+//! .localdoc.toml, and commands take their defaults from both, with the
+//! project file winning and a flag winning over either.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use common::stdout;
+
+/// localdoc in `dir/project` with its user config in `dir/config` and no
+/// container runtime to find.
+fn localdoc(dir: &Path, args: &[&str]) -> Output {
+    common::command(&dir.join("project"))
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env("PATH", dir.join("no-such-bin"))
+        .env_remove("LOCALDOC_RUNTIME")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// The image in the command `run --dry-run` prints.
+fn dry_run_image(dir: &Path, args: &[&str]) -> String {
+    let pack = dir.join("project/pack");
+    let mut all = vec!["run", pack.to_str().unwrap(), "--dry-run", "--runtime", "docker", "--no-env"];
+    all.extend(args);
+    let printed = stdout(&localdoc(dir, &all));
+    let line = printed.lines().find_map(|line| line.strip_prefix("Command: ")).unwrap();
+    line.rsplit(' ').next().unwrap().to_string()
+}
+
+fn setup() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let pack = dir.path().join("project/pack");
+    for sub in ["files", "index", "output"] {
+        fs::create_dir_all(pack.join(sub)).unwrap();
+    }
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/manifests/1.0.json");
+    fs::copy(manifest, pack.join("docpack.json")).unwrap();
+    dir
+}
+
+#[test]
+fn flags_win_over_the_project_file_which_wins_over_the_user_file() {
+    let dir = setup();
+    assert_eq!(dry_run_image(dir.path(), &[]), "doctown:latest");
+
+    let set = stdout(&localdoc(dir.path(), &["config", "set", "run.image", "doctown:user"]));
+    assert!(set.contains("Set run.image = doctown:user"), "{}", set);
+    assert!(dir.path().join("config/localdoc/config.toml").is_file());
+    assert_eq!(dry_run_image(dir.path(), &[]), "doctown:user");
+
+    stdout(&localdoc(dir.path(), &["config", "set", "run.image", "doctown:project", "--project"]));
+    assert!(dir.path().join("project/.localdoc.toml").is_file());
+    assert_eq!(dry_run_image(dir.path(), &[]), "doctown:project");
+    assert_eq!(stdout(&localdoc(dir.path(), &["config", "get", "run.image"])).trim(), "doctown:project");
+
+    assert_eq!(dry_run_image(dir.path(), &["--image", "doctown:flag"]), "doctown:flag");
+
+    let list = stdout(&localdoc(dir.path(), &["config", "list"]));
+    assert!(list.lines().any(|line| line.starts_with("run.image") && line.contains(".localdoc.toml")), "{}", list);
+    assert!(list.lines().any(|line| line.starts_with("ingest.preset") && line.trim_end().ends_with("minimal")), "{}", list);
+}
+
+#[test]
+fn setting_a_key_keeps_the_rest_of_the_file() {
+    let dir = setup();
+    let file = dir.path().join("config/localdoc/config.toml");
+    fs::create_dir_all(file.parent().unwrap()).unwrap();
+    fs::write(&file, "# My defaults\n[ingest]\nexclude = [\"*.log\"]\n").unwrap();
+
+    stdout(&localdoc(dir.path(), &["config", "set", "ingest.exclude", "*.log,fixtures/**"]));
+    stdout(&localdoc(dir.path(), &["config", "set", "run.memory", "4096"]));
+    let text = fs::read_to_string(&file).unwrap();
+    assert!(text.starts_with("# My defaults\n[ingest]\nexclude = [\"*.log\", \"fixtures/**\"]\n"), "{}", text);
+    assert!(text.contains("[run]\nmemory = 4096\n"), "{}", text);
+
+    let rejected = localdoc(dir.path(), &["config", "set", "run.memory", "64"]);
+    assert!(!rejected.status.success());
+    assert!(String::from_utf8_lossy(&rejected.stderr).contains("run.memory: must be at least 128 MB"));
+}
+
+#[test]
+fn an_invalid_file_is_an_error_naming_the_file_and_key() {
+    let dir = setup();
+    fs::write(dir.path().join("project/.localdoc.toml"), "[run]\nimgae = \"doctown:v2\"\n").unwrap();
+
+    let output = localdoc(dir.path(), &["run", "pack", "--dry-run"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(".localdoc.toml"), "{}", stderr);
+    assert!(stderr.contains("Unknown config key 'run.imgae'"), "{}", stderr);
+}
@@ -0,0 +1,99 @@
+//! Precedence of the source filters: `--exclude` globs win over
+//! `.docpackignore`, which wins over the default excludes.
+
+mod common;
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+fn write_fixture(root: &Path) {
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("build")).unwrap();
+    fs::create_dir_all(root.join("dist")).unwrap();
+    fs::create_dir_all(root.join("scratch")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("src/notes.tmp"), "scratch notes\n").unwrap();
+    fs::write(root.join("scratch/data.txt"), "throwaway\n").unwrap();
+    fs::write(root.join("build/generated.md"), "# Generated API docs\n").unwrap();
+    fs::write(root.join("dist/bundle.js"), "console.log(1)\n").unwrap();
+    fs::write(root.join("debug.log"), "noise\n").unwrap();
+    fs::write(root.join("keep.log"), "wanted\n").unwrap();
+    fs::write(
+        root.join(".docpackignore"),
+        "# Project-local exclusions\n*.tmp\nscratch/\n*.log\n!keep.log\n!build/\n",
+    )
+    .unwrap();
+}
+
+/// The paths under `files/` of the ingested `docpack`, plus its manifest.
+fn contents(docpack: &Path) -> (Vec<String>, serde_json::Value) {
+    let mut archive = zip::ZipArchive::new(fs::File::open(docpack).unwrap()).unwrap();
+    let files = archive
+        .file_names()
+        .filter_map(|name| name.strip_prefix("files/"))
+        .filter(|name| !name.is_empty() && !name.ends_with('/'))
+        .map(String::from)
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let mut manifest = String::new();
+    archive.by_name("docpack.json").unwrap().read_to_string(&mut manifest).unwrap();
+    (files, serde_json::from_str(&manifest).unwrap())
+}
+
+#[test]
+fn ignore_file_overrides_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("project");
+    write_fixture(&source);
+
+    common::ingest(dir.path(), &[]);
+    let (files, manifest) = contents(&dir.path().join("app.docpack"));
+
+    // build/ is brought back by `!build/`; dist/ stays default-excluded
+    assert_eq!(files, ["build/generated.md", "keep.log", "src/main.rs"]);
+    assert_eq!(manifest["metadata"]["ignore_file"]["path"], ".docpackignore");
+    assert_eq!(manifest["metadata"]["ignore_file"]["ignored"], 3);
+}
+
+#[test]
+fn cli_excludes_override_ignore_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("project");
+    write_fixture(&source);
+
+    common::ingest(dir.path(), &["--exclude", "keep.log", "--exclude", "build/**"]);
+    let (files, _) = contents(&dir.path().join("app.docpack"));
+
+    assert_eq!(files, ["src/main.rs"]);
+}
+
+#[test]
+fn defaults_apply_without_ignore_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("project");
+    write_fixture(&source);
+    fs::remove_file(source.join(".docpackignore")).unwrap();
+
+    common::ingest(dir.path(), &[]);
+    let (files, manifest) = contents(&dir.path().join("app.docpack"));
+
+    assert_eq!(
+        files,
+        ["debug.log", "keep.log", "scratch/data.txt", "src/main.rs", "src/notes.tmp"]
+    );
+    assert!(manifest["metadata"].get("ignore_file").is_none());
+}
+
+#[test]
+fn ignore_file_applies_without_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("project");
+    write_fixture(&source);
+
+    common::ingest(dir.path(), &["--no-default-excludes"]);
+    let (files, _) = contents(&dir.path().join("app.docpack"));
+
+    assert_eq!(files, ["build/generated.md", "dist/bundle.js", "keep.log", "src/main.rs"]);
+}
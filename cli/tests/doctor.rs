@@ -0,0 +1,74 @@
+//! `localdoc doctor` reports on the runtime, image, disk and docpack, with
+//! an exit code for the worst result. A stand-in `docker` on PATH plays a
+//! daemon that is either unreachable or missing the image.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Output;
+
+fn doctor(dir: &Path, info_fails: bool, extra: &[&str]) -> (Output, serde_json::Value) {
+    let bin = dir.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let info = if info_fails {
+        "echo 'permission denied while trying to connect to the Docker daemon socket' >&2; exit 1"
+    } else {
+        "exit 0"
+    };
+    let script = format!(
+        "#!/bin/sh\ncase \"$1\" in\n  --version) echo 'Docker version 27.0.3' ;;\n  info) {} ;;\n  image) exit 1 ;;\nesac\n",
+        info
+    );
+    fs::write(bin.join("docker"), script).unwrap();
+    fs::set_permissions(bin.join("docker"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = common::command(dir)
+        .env("PATH", format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default()))
+        .env_remove("LOCALDOC_RUNTIME")
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .args(["doctor", "--runtime", "docker", "--json"])
+        .args(extra)
+        .output()
+        .unwrap();
+    let report = serde_json::from_slice(&output.stdout).unwrap();
+    (output, report)
+}
+
+fn check<'a>(report: &'a serde_json::Value, name: &str) -> &'a serde_json::Value {
+    report["checks"].as_array().unwrap().iter().find(|check| check["name"] == name).unwrap()
+}
+
+#[test]
+fn an_unreachable_daemon_fails_with_a_hint() {
+    let dir = tempfile::tempdir().unwrap();
+    let (output, report) = doctor(dir.path(), true, &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(report["status"], "fail");
+    assert_eq!(check(&report, "Container runtime")["message"], "Docker version 27.0.3");
+    let daemon = check(&report, "Docker daemon");
+    assert_eq!(daemon["status"], "fail");
+    assert!(daemon["hint"].as_str().unwrap().contains("usermod -aG docker"));
+    // The image cannot be looked for without the daemon
+    assert!(report["checks"].as_array().unwrap().iter().all(|check| check["name"] != "Agent image"));
+}
+
+#[test]
+fn a_missing_image_and_an_invalid_docpack_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = dir.path().join("pack");
+    fs::create_dir_all(docpack.join("files")).unwrap();
+    fs::write(docpack.join("docpack.json"), r#"{"version": "1.0", "name": "x"}"#).unwrap();
+    let (output, report) = doctor(dir.path(), false, &["--docpack", docpack.to_str().unwrap()]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let image = check(&report, "Agent image");
+    assert_eq!(image["status"], "warn");
+    assert_eq!(image["hint"], "Pull it now with `docker pull doctown:latest`");
+    assert_eq!(check(&report, "Run history")["status"], "pass");
+    assert_eq!(check(&report, "Docpack")["status"], "fail");
+}
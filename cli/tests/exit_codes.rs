@@ -0,0 +1,57 @@
+//! Failures exit with a code per kind of error, and `--verbose` adds the
+//! errors underneath.
+
+mod common;
+
+use std::fs;
+use std::io::Write;
+
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use common::{localdoc, stderr};
+
+#[test]
+fn each_kind_of_failure_has_its_own_code() {
+    let dir = tempfile::tempdir().unwrap();
+    
+    let missing = localdoc(dir.path(), &["inspect", "missing.docpack"]);
+    assert_eq!(missing.status.code(), Some(6), "{}", stderr(&missing));
+
+    let docpack = dir.path().join("app.docpack");
+    fs::create_dir_all(docpack.join("files")).unwrap();
+    fs::write(docpack.join("docpack.json"), r#"{ "version": "1.0" }"#).unwrap();
+    let invalid = localdoc(dir.path(), &["inspect", "app.docpack"]);
+    assert_eq!(invalid.status.code(), Some(7));
+    assert!(stderr(&invalid).contains("missing required field 'environment'"), "{}", stderr(&invalid));
+
+    // Readable, but refused on extraction
+    let mut zip = ZipWriter::new(fs::File::create(dir.path().join("twice.docpack")).unwrap());
+    for name in ["docpack.json", "files/a.txt", "files/a.txt"] {
+        zip.start_file(name, FileOptions::default()).unwrap();
+        zip.write_all(b"{}").unwrap();
+    }
+    zip.finish().unwrap();
+    let damaged = localdoc(dir.path(), &["unpack", "twice.docpack"]);
+    assert_eq!(damaged.status.code(), Some(8), "{}", stderr(&damaged));
+
+    let occupied = localdoc(dir.path(), &["init", "app.docpack"]);
+    assert_eq!(occupied.status.code(), Some(1));
+}
+
+#[test]
+fn verbose_prints_the_causes() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = dir.path().join("app.docpack");
+    fs::create_dir_all(docpack.join("files")).unwrap();
+    fs::write(docpack.join("docpack.json"), "{ \"version\": ").unwrap();
+    let docpack = "app.docpack";
+
+    let quiet = stderr(&localdoc(dir.path(), &["inspect", docpack]));
+    assert!(quiet.starts_with("Error: Invalid JSON in docpack.json: "), "{}", quiet);
+    assert!(!quiet.contains("Caused by"));
+
+    let verbose = localdoc(dir.path(), &["--verbose", "inspect", docpack]);
+    assert_eq!(verbose.status.code(), Some(7));
+    assert!(stderr(&verbose).contains("\nCaused by: EOF while parsing"), "{}", stderr(&verbose));
+}
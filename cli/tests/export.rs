@@ -0,0 +1,68 @@
+//! `localdoc export` turns a docpack's run outputs into an mdBook source
+//! tree or an HTML site, with links between outputs still working.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{localdoc, stdout};
+
+/// A docpack directory `app` with outputs as a run would leave them.
+fn docpack(dir: &Path) {
+    let source = dir.join("project");
+    fs::create_dir_all(source.join("src")).unwrap();
+    fs::write(source.join("src/main.rs"), "fn main() {}\n").unwrap();
+    stdout(&localdoc(dir, &["ingest", "project", "-o", "app.docpack", "--quiet"]));
+    stdout(&localdoc(dir, &["unpack", "app.docpack", "-o", "app"]));
+
+    let output = dir.join("app/output");
+    fs::create_dir_all(output.join("api")).unwrap();
+    fs::create_dir_all(output.join("img")).unwrap();
+    fs::write(output.join("README.md"), "# Overview\n\n![Logo](img/logo.png)\n\nSee the [CLI](api/cli.md#usage) and [main](/workspace/files/src/main.rs).\n").unwrap();
+    fs::write(output.join("api/cli.md"), "# Command line\n\n## Usage\n\nBack to the [overview](../README.md).\n").unwrap();
+    fs::write(output.join("img/logo.png"), [0x89, b'P', b'N', b'G']).unwrap();
+}
+
+#[test]
+fn outputs_export_as_an_mdbook_and_as_html() {
+    let dir = tempfile::tempdir().unwrap();
+    docpack(dir.path());
+
+    let exported = stdout(&localdoc(dir.path(), &["export", "app", "-o", "book"]));
+    assert!(exported.contains("Exported 2 page(s) and 2 asset(s)"), "{}", exported);
+    let summary = fs::read_to_string(dir.path().join("book/src/SUMMARY.md")).unwrap();
+    assert_eq!(summary, "# Summary\n\n[Overview](README.md)\n\n- [Api]()\n    - [Command line](api/cli.md)\n");
+    assert!(fs::read_to_string(dir.path().join("book/book.toml")).unwrap().contains("title = \"app\""));
+    let readme = fs::read_to_string(dir.path().join("book/src/README.md")).unwrap();
+    assert!(readme.contains("[CLI](api/cli.md#usage)") && readme.contains("[main](files/src/main.rs)"), "{}", readme);
+    assert_eq!(fs::read(dir.path().join("book/src/files/src/main.rs")).unwrap(), b"fn main() {}\n");
+    assert!(dir.path().join("book/src/img/logo.png").is_file());
+
+    stdout(&localdoc(dir.path(), &["export", "app", "--format", "html", "-o", "site"]));
+    let index = fs::read_to_string(dir.path().join("site/index.html")).unwrap();
+    assert!(index.contains("<a href=\"api/cli.html#usage\">CLI</a>"), "{}", index);
+    assert!(index.contains("<img src=\"img/logo.png\" alt=\"Logo\" />"), "{}", index);
+    let cli = fs::read_to_string(dir.path().join("site/api/cli.html")).unwrap();
+    assert!(cli.contains("<h2 id=\"usage\">Usage</h2>"), "{}", cli);
+    assert!(cli.contains("<a href=\"../index.html\">overview</a>"), "{}", cli);
+    assert!(cli.contains("<a href=\"cli.html\" class=\"current\">Command line</a>"), "{}", cli);
+
+    let refused = localdoc(dir.path(), &["export", "app", "--format", "html", "-o", "site"]);
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("pass --force"));
+    stdout(&localdoc(dir.path(), &["export", "app", "--format", "html", "-o", "site", "--force"]));
+}
+
+#[test]
+fn a_docpack_without_outputs_asks_for_a_run() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("project")).unwrap();
+    fs::write(dir.path().join("project/main.rs"), "fn main() {}\n").unwrap();
+    stdout(&localdoc(dir.path(), &["ingest", "project", "-o", "app.docpack", "--quiet"]));
+
+    let failed = localdoc(dir.path(), &["export", "app.docpack", "-o", "book"]);
+    assert!(!failed.status.success());
+    let stderr = String::from_utf8_lossy(&failed.stderr);
+    assert!(stderr.contains("has no Markdown files") && stderr.contains("localdoc run app.docpack"), "{}", stderr);
+    assert!(!dir.path().join("book").exists());
+}
@@ -0,0 +1,54 @@
+//! `localdoc list` and `localdoc cat` read a docpack's files in place,
+//! from a directory or an archive, including a deduplicated one.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{localdoc, stdout};
+
+fn ingest(dir: &Path, extra: &[&str]) {
+    let source = dir.join("project");
+    fs::create_dir_all(source.join("src")).unwrap();
+    fs::write(source.join("README.md"), "# Fixture\n").unwrap();
+    fs::write(source.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(source.join("src/copy.rs"), "fn main() {}\n").unwrap();
+    fs::write(source.join("logo.bin"), [0u8, 1, 2, 0xff]).unwrap();
+    common::ingest(dir, extra);
+}
+
+#[test]
+fn archives_are_listed_and_read_like_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    ingest(dir.path(), &["--dedupe"]);
+
+    let listed = stdout(&localdoc(dir.path(), &["list", "app.docpack", "--prefix", "files/"]));
+    assert_eq!(listed, "files/README.md\nfiles/logo.bin\nfiles/src/copy.rs\nfiles/src/main.rs\n");
+    let globbed = stdout(&localdoc(dir.path(), &["list", "app.docpack", "--glob", "**/*.rs", "--long"]));
+    assert!(globbed.contains("files/src/copy.rs") && globbed.contains("2 file(s)"), "{}", globbed);
+
+    assert_eq!(stdout(&localdoc(dir.path(), &["cat", "app.docpack", "files/src/copy.rs"])), "fn main() {}\n");
+    let manifest: serde_json::Value = serde_json::from_str(&stdout(&localdoc(dir.path(), &["cat", "app.docpack", "docpack.json"]))).unwrap();
+    assert!(manifest["metadata"]["dedupe"].is_object());
+
+    // Not a terminal, so binary bytes come through as they are
+    assert_eq!(localdoc(dir.path(), &["cat", "app.docpack", "files/logo.bin"]).stdout, [0u8, 1, 2, 0xff]);
+    assert_eq!(stdout(&localdoc(dir.path(), &["cat", "app.docpack", "files/logo.bin", "--text"])), "\0\u{1}\u{2}\u{fffd}");
+}
+
+#[test]
+fn missing_paths_fail_with_close_matches() {
+    let dir = tempfile::tempdir().unwrap();
+    ingest(dir.path(), &[]);
+
+    let missing = localdoc(dir.path(), &["cat", "app.docpack", "files/main.rs"]);
+    assert!(!missing.status.success());
+    let stderr = String::from_utf8_lossy(&missing.stderr);
+    assert!(stderr.contains("No file files/main.rs in app.docpack. Did you mean:\n  files/src/main.rs"), "{}", stderr);
+
+    let directory = localdoc(dir.path(), &["cat", "app.docpack", "files/src"]);
+    assert!(String::from_utf8_lossy(&directory.stderr).contains("files/src is a directory"));
+
+    assert!(!localdoc(dir.path(), &["list", "app.docpack", "--prefix", "output/"]).status.success());
+}
@@ -0,0 +1,64 @@
+//! `ingest` records where a git checkout was at, and still works when git
+//! itself isn't installed.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use common::{localdoc, stdout};
+use serde_json::Value;
+
+/// Make `dir/project` a checkout with one commit; false without git.
+fn commit_project(dir: &Path) -> bool {
+    let project = common::project(dir, 3);
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(&project)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com", "-c", "commit.gpgsign=false"])
+            .args(args)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+    // Not every machine that builds this has git
+    git(&["init", "--quiet"]) && git(&["add", "."]) && git(&["commit", "--quiet", "-m", "Initial commit"])
+}
+
+/// `metadata.git` of `dir/app.docpack`.
+fn recorded(dir: &Path) -> Value {
+    let out = dir.join("unpacked");
+    let _ = fs::remove_dir_all(&out);
+    stdout(&localdoc(dir, &["unpack", "app.docpack", "-o", "unpacked"]));
+    let manifest: Value = serde_json::from_str(&fs::read_to_string(out.join("docpack.json")).unwrap()).unwrap();
+    manifest["metadata"]["git"].clone()
+}
+
+#[test]
+fn checkouts_record_their_commit() {
+    let dir = tempfile::tempdir().unwrap();
+    if !commit_project(dir.path()) {
+        return;
+    }
+    common::ingest(dir.path(), &[]);
+    let git = recorded(dir.path());
+    assert_eq!(git["commit"].as_str().map(str::len), Some(40), "{}", git);
+    assert_eq!(git["dirty"], false, "{}", git);
+}
+
+#[test]
+fn a_missing_git_binary_records_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    if !commit_project(dir.path()) {
+        return;
+    }
+    let empty = tempfile::tempdir().unwrap();
+    let output = common::command(dir.path())
+        .args(["ingest", "project", "-o", "app.docpack", "--quiet"])
+        .env("PATH", empty.path())
+        .output()
+        .unwrap();
+    stdout(&output);
+    assert_eq!(recorded(dir.path()), Value::Null);
+}
@@ -0,0 +1,257 @@
+//! `--build-graph` on a small crate with known structure.
+
+mod common;
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use common::{localdoc, stdout};
+
+const LIB_RS: &str = r#"mod geometry;
+
+use crate::geometry::{Area, Circle};
+
+pub mod util {
+    pub fn round(value: f64) -> f64 {
+        value.round()
+    }
+}
+
+/// Total area, rounded.
+pub fn total(circles: &[Circle]) -> f64 {
+    let sum = circles.iter().map(|c| c.area()).sum();
+    util::round(sum)
+}
+"#;
+
+const GEOMETRY_RS: &str = r#"use std::fmt;
+
+pub trait Area {
+    fn area(&self) -> f64;
+
+    fn describe(&self) -> String {
+        format!("area {}", self.area())
+    }
+}
+
+pub struct Circle {
+    radius: f64,
+}
+
+pub enum Unit {
+    Metre,
+    Foot,
+}
+
+impl Circle {
+    pub fn new(radius: f64) -> Self {
+        Self::checked(radius)
+    }
+
+    fn checked(radius: f64) -> Self {
+        Circle { radius: radius.abs() }
+    }
+}
+
+impl Area for Circle {
+    fn area(&self) -> f64 {
+        std::f64::consts::PI * self.radius * self.radius
+    }
+}
+
+impl fmt::Display for Circle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.describe())
+    }
+}
+"#;
+
+fn write_fixture(root: &Path) {
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("Cargo.toml"), "[package]\nname = \"shapes\"\nversion = \"0.1.0\"\n").unwrap();
+    fs::write(root.join("README.md"), "# Shapes\n").unwrap();
+    fs::write(root.join("src/lib.rs"), LIB_RS).unwrap();
+    fs::write(root.join("src/geometry.rs"), GEOMETRY_RS).unwrap();
+    fs::write(root.join("src/broken.rs"), "fn broken( {\n").unwrap();
+}
+
+#[test]
+fn rust_graph_matches_fixture() {
+    let dir = tempfile::tempdir().unwrap();
+    write_fixture(&dir.path().join("shapes"));
+    stdout(&localdoc(dir.path(), &["ingest", "shapes", "-o", "shapes.docpack", "--build-graph", "--quiet"]));
+
+    let extracted = dir.path().join("extracted");
+    let mut archive = zip::ZipArchive::new(fs::File::open(dir.path().join("shapes.docpack")).unwrap()).unwrap();
+    archive.extract(&extracted).unwrap();
+    let graph: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(extracted.join("index/graph.json")).unwrap()).unwrap();
+
+    let nodes: Vec<(&str, &str, u64, u64)> = graph["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|node| {
+            let field = |key: &str| node[key].as_str().unwrap();
+            (field("id"), field("type"), node["line_start"].as_u64().unwrap(), node["line_end"].as_u64().unwrap())
+        })
+        .collect();
+    assert_eq!(nodes, [
+        ("src/geometry.rs", "module", 1, 40),
+        ("src/geometry.rs::<Circle as Area>::area", "function", 31, 33),
+        ("src/geometry.rs::<Circle as Display>::fmt", "function", 37, 39),
+        ("src/geometry.rs::Area", "trait", 3, 9),
+        ("src/geometry.rs::Area::area", "function", 4, 4),
+        ("src/geometry.rs::Area::describe", "function", 6, 8),
+        ("src/geometry.rs::Circle", "struct", 11, 13),
+        ("src/geometry.rs::Circle::checked", "function", 25, 27),
+        ("src/geometry.rs::Circle::new", "function", 21, 23),
+        ("src/geometry.rs::Unit", "enum", 15, 18),
+        ("src/lib.rs", "module", 1, 15),
+        ("src/lib.rs::total", "function", 12, 15),
+        ("src/lib.rs::util", "module", 5, 9),
+        ("src/lib.rs::util::round", "function", 6, 8),
+    ]);
+
+    let edges: Vec<(&str, &str, &str)> = graph["edges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|edge| (edge["from"].as_str().unwrap(), edge["type"].as_str().unwrap(), edge["to"].as_str().unwrap()))
+        .collect();
+    assert_eq!(edges, [
+        ("src/geometry.rs", "contains", "src/geometry.rs::Area"),
+        ("src/geometry.rs", "contains", "src/geometry.rs::Circle"),
+        ("src/geometry.rs", "contains", "src/geometry.rs::Unit"),
+        ("src/geometry.rs::Area", "contains", "src/geometry.rs::Area::area"),
+        ("src/geometry.rs::Area", "contains", "src/geometry.rs::Area::describe"),
+        ("src/geometry.rs::Area::describe", "calls", "src/geometry.rs::Area::area"),
+        ("src/geometry.rs::Circle", "contains", "src/geometry.rs::<Circle as Area>::area"),
+        ("src/geometry.rs::Circle", "contains", "src/geometry.rs::<Circle as Display>::fmt"),
+        ("src/geometry.rs::Circle", "implements", "src/geometry.rs::Area"),
+        ("src/geometry.rs::Circle", "contains", "src/geometry.rs::Circle::checked"),
+        ("src/geometry.rs::Circle", "contains", "src/geometry.rs::Circle::new"),
+        ("src/geometry.rs::Circle::new", "calls", "src/geometry.rs::Circle::checked"),
+        ("src/lib.rs", "imports", "src/geometry.rs"),
+        ("src/lib.rs", "contains", "src/lib.rs::total"),
+        ("src/lib.rs", "contains", "src/lib.rs::util"),
+        ("src/lib.rs::total", "calls", "src/lib.rs::util::round"),
+        ("src/lib.rs::util", "contains", "src/lib.rs::util::round"),
+    ]);
+
+    // Unparsable files are reported, non-Rust files ignored
+    assert_eq!(graph["metadata"]["files"], 2);
+    assert_eq!(graph["metadata"]["unparsed"], serde_json::json!(["src/broken.rs"]));
+
+    let inspect = stdout(&localdoc(dir.path(), &["inspect", "extracted"]));
+    assert!(inspect.contains("Semantic graph: ✓ (14 nodes, 17 edges)"), "{}", inspect);
+}
+
+const IMPORT_FIXTURE: &[(&str, &str)] = &[
+    ("app/__init__.py", ""),
+    (
+        "app/main.py",
+        "\"\"\"Entry point.\n\nimport fake_in_docstring\n\"\"\"\nimport os, sys\nimport requests.adapters as adapters\n\
+         from app.pkg import helpers\nfrom .config import (\n    load,\n    save,  # persisted\n)\n\
+         from . import missing_module\n\ndef run():\n    import json\n    return helpers.go()\n",
+    ),
+    ("app/config.py", "X = 1\n"),
+    ("app/pkg/__init__.py", ""),
+    (
+        "app/pkg/helpers.py",
+        "from .. import config\nfrom ..config import load\nfrom .sub.deep import thing\nfrom ...outside import nothing\n",
+    ),
+    ("app/pkg/sub/deep.py", "thing = 1\n"),
+    (
+        "web/src/index.ts",
+        "import React from \"react\";\nimport { Button } from \"./components\";\n\
+         import type { Props } from './components/Button';\nexport * from \"./utils/format.js\";\n\
+         // import ignored from \"./commented\";\nconst lazy = () => import(\"./utils/lazy\");\n\
+         const pkg = require('@scope/pkg/deep/path');\nimport \"./styles.css\";\n\
+         const s = `import nope from \"./template\"`;\n",
+    ),
+    ("web/src/components/index.ts", "export { Button } from \"./Button\";\n"),
+    ("web/src/components/Button.tsx", "export const Button = 1;\n"),
+    ("web/src/utils/format.ts", "export const format = (x) => x;\n"),
+    ("web/src/utils/lazy.js", "module.exports = require(\"../index\");\n"),
+    ("web/src/styles.css", "body {}\n"),
+];
+
+#[test]
+fn python_and_script_imports_resolve_to_files() {
+    let dir = tempfile::tempdir().unwrap();
+    for (path, content) in IMPORT_FIXTURE {
+        let path = dir.path().join("project").join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    common::ingest(dir.path(), &["--build-graph"]);
+
+    let mut archive = zip::ZipArchive::new(fs::File::open(dir.path().join("app.docpack")).unwrap()).unwrap();
+    let mut content = String::new();
+    archive.by_name("index/graph.json").unwrap().read_to_string(&mut content).unwrap();
+    let graph: serde_json::Value = serde_json::from_str(&content).unwrap();
+
+    let nodes: Vec<(&str, &str)> = graph["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|node| (node["id"].as_str().unwrap(), node["type"].as_str().unwrap()))
+        .collect();
+    assert_eq!(nodes, [
+        ("app/__init__.py", "module"),
+        ("app/config.py", "module"),
+        ("app/main.py", "module"),
+        ("app/pkg/__init__.py", "module"),
+        ("app/pkg/helpers.py", "module"),
+        ("app/pkg/sub/deep.py", "module"),
+        ("external:...outside", "external"),
+        ("external:@scope/pkg", "external"),
+        ("external:json", "external"),
+        ("external:os", "external"),
+        ("external:react", "external"),
+        ("external:requests", "external"),
+        ("external:sys", "external"),
+        ("web/src/components/Button.tsx", "module"),
+        ("web/src/components/index.ts", "module"),
+        ("web/src/index.ts", "module"),
+        ("web/src/styles.css", "file"),
+        ("web/src/utils/format.ts", "module"),
+        ("web/src/utils/lazy.js", "module"),
+    ]);
+
+    let edges: Vec<(&str, &str)> = graph["edges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .inspect(|edge| assert_eq!(edge["type"], "imports"))
+        .map(|edge| (edge["from"].as_str().unwrap(), edge["to"].as_str().unwrap()))
+        .collect();
+    assert_eq!(edges, [
+        // `from . import missing_module` names something in the package itself
+        ("app/main.py", "app/__init__.py"),
+        ("app/main.py", "app/config.py"),
+        ("app/main.py", "app/pkg/helpers.py"),
+        ("app/main.py", "external:json"),
+        ("app/main.py", "external:os"),
+        ("app/main.py", "external:requests"),
+        ("app/main.py", "external:sys"),
+        // `from .. import config` and `from ..config import load`
+        ("app/pkg/helpers.py", "app/config.py"),
+        ("app/pkg/helpers.py", "app/pkg/sub/deep.py"),
+        // Climbs above the top-level package
+        ("app/pkg/helpers.py", "external:...outside"),
+        ("web/src/components/index.ts", "web/src/components/Button.tsx"),
+        ("web/src/index.ts", "external:@scope/pkg"),
+        ("web/src/index.ts", "external:react"),
+        ("web/src/index.ts", "web/src/components/Button.tsx"),
+        // `./components` is the directory's index.ts
+        ("web/src/index.ts", "web/src/components/index.ts"),
+        ("web/src/index.ts", "web/src/styles.css"),
+        // `./utils/format.js` is written as format.ts
+        ("web/src/index.ts", "web/src/utils/format.ts"),
+        ("web/src/index.ts", "web/src/utils/lazy.js"),
+        ("web/src/utils/lazy.js", "web/src/index.ts"),
+    ]);
+}
@@ -0,0 +1,61 @@
+//! The binary search index answers queries exactly like the JSON one.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{localdoc, stdout};
+
+fn write_fixture(root: &Path) {
+    fs::create_dir_all(root.join("src/auth")).unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::write(
+        root.join("src/auth/login.rs"),
+        "// Login handler\nfn login(user: &str) {\n    check_password(user);\n    audit(\"login\", user);\n}\n",
+    )
+    .unwrap();
+    fs::write(root.join("src/auth/token.rs"), "/// Refresh a login token\nfn refresh_token() {}\n").unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {\n    login(\"admin\");\n}\n").unwrap();
+    fs::write(
+        root.join("docs/guide.md"),
+        "# Guide\n\nUsers log in with a password.\n\nThe login token expires after an hour.\n\nUnicode: Ünïcödé 検索エンジン\n",
+    )
+    .unwrap();
+}
+
+#[test]
+fn binary_and_json_indexes_return_the_same_results() {
+    let dir = tempfile::tempdir().unwrap();
+    write_fixture(&dir.path().join("project"));
+
+    stdout(&localdoc(dir.path(), &["ingest", "project", "-o", "json.docpack", "--build-index", "--quiet"]));
+    stdout(&localdoc(dir.path(), &["ingest", "project", "-o", "binary.docpack", "--build-index", "--index-format", "binary", "--quiet"]));
+
+    let archive = zip::ZipArchive::new(fs::File::open(dir.path().join("binary.docpack")).unwrap()).unwrap();
+    let names: Vec<&str> = archive.file_names().collect();
+    assert!(names.contains(&"index/search.bin"));
+    assert!(!names.contains(&"index/search.json"));
+
+    for query in ["login", "login token", "password", "ünïcödé", "検索", "nothingmatches"] {
+        let json = stdout(&localdoc(dir.path(), &["search", "json.docpack", query, "--json", "--limit", "100"]));
+        let binary = stdout(&localdoc(dir.path(), &["search", "binary.docpack", query, "--json", "--limit", "100"]));
+        assert_eq!(json, binary, "results differ for '{}'", query);
+    }
+}
+
+#[test]
+fn index_command_switches_formats() {
+    let dir = tempfile::tempdir().unwrap();
+    write_fixture(&dir.path().join("project"));
+    common::ingest(dir.path(), &["--build-index"]);
+    let before = stdout(&localdoc(dir.path(), &["search", "app.docpack", "login", "--json"]));
+
+    stdout(&localdoc(dir.path(), &["index", "app.docpack", "--force", "--index-format", "binary", "--quiet"]));
+
+    let archive = zip::ZipArchive::new(fs::File::open(dir.path().join("app.docpack")).unwrap()).unwrap();
+    let names: Vec<&str> = archive.file_names().collect();
+    assert!(names.contains(&"index/search.bin"));
+    assert!(!names.contains(&"index/search.json"));
+    assert_eq!(before, stdout(&localdoc(dir.path(), &["search", "app.docpack", "login", "--json"])));
+}
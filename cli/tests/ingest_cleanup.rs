@@ -0,0 +1,83 @@
+//! `ingest` must not leave its temporary build directory behind, whether
+//! it succeeds or fails partway through.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// `ingest source -o out` in `dir`, building under `tmp`.
+fn ingest(dir: &Path, tmp: &Path, source: &str, out: &str) -> Command {
+    let mut command = common::command(dir);
+    command.args(["ingest", source, "-o", out, "--build-index", "--quiet"]).env("TMPDIR", tmp);
+    command
+}
+
+fn leftovers(tmp: &Path) -> Vec<String> {
+    fs::read_dir(tmp)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("docpack-build-"))
+        .collect()
+}
+
+fn setup() -> (tempfile::TempDir, std::path::PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let tmp = dir.path().join("tmp");
+    let source = dir.path().join("project");
+    fs::create_dir_all(&tmp).unwrap();
+    fs::create_dir_all(source.join("src")).unwrap();
+    fs::write(source.join("README.md"), "# Project\n").unwrap();
+    fs::write(source.join("src/lib.rs"), "pub fn f() {}\n").unwrap();
+    (dir, tmp)
+}
+
+#[test]
+fn temp_dir_removed_after_success() {
+    let (dir, tmp) = setup();
+
+    common::stdout(&ingest(dir.path(), &tmp, "project", "ok.docpack").output().unwrap());
+    assert_eq!(leftovers(&tmp), Vec::<String>::new());
+}
+
+#[test]
+fn temp_dir_removed_when_zip_step_fails() {
+    let (dir, tmp) = setup();
+
+    // The archive cannot be created inside a directory that doesn't exist
+    let output = ingest(dir.path(), &tmp, "project", "missing/out.docpack").output().unwrap();
+    assert!(!output.status.success());
+    assert!(!dir.path().join("missing/out.docpack").exists());
+    assert_eq!(leftovers(&tmp), Vec::<String>::new());
+}
+
+#[test]
+fn temp_dir_removed_when_source_zip_is_corrupt() {
+    let (dir, tmp) = setup();
+
+    fs::write(dir.path().join("broken.zip"), b"PK\x03\x04 definitely not a zip archive").unwrap();
+    let output = ingest(dir.path(), &tmp, "broken.zip", "broken.docpack").output().unwrap();
+    assert!(!output.status.success());
+    assert_eq!(leftovers(&tmp), Vec::<String>::new());
+}
+
+#[test]
+fn concurrent_ingests_use_distinct_directories() {
+    let (dir, tmp) = setup();
+
+    let children: Vec<_> = (0..4)
+        .map(|i| ingest(dir.path(), &tmp, "project", &format!("{}.docpack", i)).spawn().unwrap())
+        .collect();
+    for mut child in children {
+        assert!(child.wait().unwrap().success());
+    }
+
+    for i in 0..4 {
+        let archive = fs::File::open(dir.path().join(format!("{}.docpack", i))).unwrap();
+        assert!(zip::ZipArchive::new(archive).unwrap().by_name("files/src/lib.rs").is_ok());
+    }
+    assert_eq!(leftovers(&tmp), Vec::<String>::new());
+}
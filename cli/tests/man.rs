@@ -0,0 +1,28 @@
+//! `localdoc man` writes a page per command, each with worked examples.
+
+mod common;
+
+use std::fs;
+
+#[test]
+fn every_command_gets_a_page_with_examples() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("man1");
+    common::stdout(&common::localdoc(dir.path(), &["man", "--out-dir", "man1"]));
+
+    let ingest = fs::read_to_string(out.join("localdoc-ingest.1")).unwrap();
+    assert!(ingest.starts_with(".TH LOCALDOC\\-INGEST 1"), "{}", ingest);
+    assert!(ingest.contains("\\fB\\-\\-build\\-index\\fR"), "{}", ingest);
+    assert!(fs::read_to_string(out.join("localdoc-tasks-add.1")).unwrap().contains("\\fBlocaldoc\\-tasks\\fR(1)"));
+
+    let mut pages = 0;
+    for entry in fs::read_dir(&out).unwrap() {
+        let page = fs::read_to_string(entry.unwrap().path()).unwrap();
+        assert!(page.contains(".SH EXAMPLES\n.PP\n"), "{}", page);
+        pages += 1;
+    }
+    assert!(pages > 40, "{} pages", pages);
+
+    let help = common::stdout(&common::localdoc(dir.path(), &["ingest", "--help"]));
+    assert!(help.contains("Examples:\n  # Package a project"));
+}
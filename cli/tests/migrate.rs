@@ -0,0 +1,79 @@
+//! `localdoc migrate` brings a manifest of every historical version up to
+//! the current one, and `validate` flags versions this CLI cannot read.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use common::localdoc;
+
+/// Manifest fixtures by the version they were written at, oldest first.
+const FIXTURES: &[(&str, &str)] = &[("unversioned", "unversioned.json"), ("1.0", "1.0.json")];
+
+const CURRENT: &str = "1.0";
+
+fn docpack_with(dir: &Path, manifest: &str) -> PathBuf {
+    let docpack = dir.join("pack");
+    for sub in ["files", "index", "output"] {
+        fs::create_dir_all(docpack.join(sub)).unwrap();
+    }
+    fs::write(docpack.join("docpack.json"), manifest).unwrap();
+    docpack
+}
+
+fn fixture(name: &str) -> String {
+    fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/manifests").join(name)).unwrap()
+}
+
+fn error_codes(dir: &Path) -> Vec<String> {
+    let out = localdoc(dir, &["validate", "--no-plugins", "--json", "pack"]);
+    let report: Value = serde_json::from_slice(&out.stdout).unwrap();
+    report["errors"].as_array().unwrap().iter().map(|e| e["code"].as_str().unwrap().to_string()).collect()
+}
+
+#[test]
+fn every_historical_version_migrates_to_the_current_one() {
+    for (version, file) in FIXTURES {
+        let dir = tempfile::tempdir().unwrap();
+        let original = fixture(file);
+        let docpack = docpack_with(dir.path(), &original);
+
+        let dry = localdoc(dir.path(), &["migrate", "--dry-run", "pack"]);
+        assert!(dry.status.success(), "{}: {}", version, String::from_utf8_lossy(&dry.stderr));
+        assert_eq!(fs::read_to_string(docpack.join("docpack.json")).unwrap(), original, "{}", version);
+
+        let out = localdoc(dir.path(), &["migrate", "pack"]);
+        assert!(out.status.success(), "{}: {}", version, String::from_utf8_lossy(&out.stderr));
+        let manifest: Value = serde_json::from_str(&fs::read_to_string(docpack.join("docpack.json")).unwrap()).unwrap();
+        assert_eq!(manifest["version"], CURRENT, "{}", version);
+        assert!(manifest["environment"]["tools"].is_array(), "{}", version);
+
+        let backup = docpack.join(format!("docpack.json.{}.bak", version));
+        if *version == CURRENT {
+            assert!(!backup.exists());
+        } else {
+            assert_eq!(fs::read_to_string(&backup).unwrap(), original);
+        }
+        assert_eq!(error_codes(dir.path()), Vec::<String>::new(), "{}", version);
+    }
+}
+
+#[test]
+fn versions_outside_the_supported_range_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = fixture("1.0.json").replace("\"1.0\"", "\"2.0\"");
+    let docpack = docpack_with(dir.path(), &manifest);
+
+    let out = localdoc(dir.path(), &["migrate", "pack"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("cannot read"));
+    assert_eq!(error_codes(dir.path()), ["DP040"]);
+
+    fs::write(docpack.join("docpack.json"), fixture("1.0.json").replace("\"1.0\"", "\"1.4\"")).unwrap();
+    let out = localdoc(dir.path(), &["validate", "--no-plugins", "--json", "pack"]);
+    let report: Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert!(report["warnings"].as_array().unwrap().iter().any(|w| w["code"] == "DP041"), "{}", report);
+}
@@ -0,0 +1,103 @@
+//! Files whose names are not valid UTF-8 are skipped, renamed, or rejected
+//! according to `--non-utf8` instead of aborting the ingest.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Read;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::process::Output;
+
+fn write_fixture(root: &Path) {
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::write(root.join("README.md"), "# Fixture\n").unwrap();
+    // Latin-1 "café.txt", as left behind by old tarballs
+    fs::write(root.join(OsStr::from_bytes(b"caf\xe9.txt")), "latin-1 name\n").unwrap();
+    fs::write(root.join("docs").join(OsStr::from_bytes(b"r\xe9sum\xe9.md")), "more\n").unwrap();
+}
+
+/// Ingest `dir/project` into `dir/out.docpack`.
+fn ingest(dir: &Path, policy: &str) -> Output {
+    common::localdoc(dir, &["ingest", "project", "-o", "out.docpack", "--quiet", "--non-utf8", policy])
+}
+
+fn contents(out: &Path) -> (BTreeSet<String>, serde_json::Value) {
+    let mut archive = zip::ZipArchive::new(fs::File::open(out).unwrap()).unwrap();
+    let files = archive
+        .file_names()
+        .filter_map(|name| name.strip_prefix("files/"))
+        .filter(|name| !name.is_empty() && !name.ends_with('/'))
+        .map(String::from)
+        .collect();
+    let mut manifest = String::new();
+    archive.by_name("docpack.json").unwrap().read_to_string(&mut manifest).unwrap();
+    (files, serde_json::from_str(&manifest).unwrap())
+}
+
+#[test]
+fn skip_leaves_them_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("project");
+    write_fixture(&source);
+    let out = dir.path().join("out.docpack");
+
+    let output = ingest(dir.path(), "skip");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("caf\u{FFFD}.txt (skipped)"));
+
+    let (files, manifest) = contents(&out);
+    assert_eq!(files, BTreeSet::from(["README.md".to_string()]));
+    assert_eq!(manifest["metadata"]["non_utf8"]["policy"], "skip");
+    assert_eq!(manifest["metadata"]["non_utf8"]["count"], 2);
+}
+
+#[test]
+fn rename_uses_lossy_unique_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("project");
+    write_fixture(&source);
+    // A real file already has the lossy name
+    fs::write(source.join("caf\u{FFFD}.txt"), "valid name\n").unwrap();
+    let out = dir.path().join("out.docpack");
+
+    let output = ingest(dir.path(), "rename");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let (files, manifest) = contents(&out);
+    let expected: BTreeSet<String> = [
+        "README.md",
+        "caf\u{FFFD}.txt",
+        "caf\u{FFFD}~1.txt",
+        "docs/r\u{FFFD}sum\u{FFFD}.md",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    assert_eq!(files, expected);
+    assert_eq!(manifest["metadata"]["non_utf8"]["count"], 2);
+    let renamed: Vec<&str> = manifest["metadata"]["non_utf8"]["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|file| file["renamed"].as_str())
+        .collect();
+    assert!(renamed.contains(&"caf\u{FFFD}~1.txt"), "{:?}", renamed);
+}
+
+#[test]
+fn error_fails_the_ingest() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("project");
+    write_fixture(&source);
+    let out = dir.path().join("out.docpack");
+
+    let output = ingest(dir.path(), "error");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid UTF-8 in path"));
+    assert!(!out.exists());
+}
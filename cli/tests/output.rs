@@ -0,0 +1,103 @@
+//! `-q`, `-v`, `--no-emoji` and `--json` decide what a command prints.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+fn ingest(dir: &Path, flags: &[&str], extra: &[&str]) -> Output {
+    let _ = fs::remove_file(dir.join("out.docpack"));
+    common::command(dir)
+        .env_remove("NO_COLOR")
+        .args(flags)
+        .args(["ingest", "project", "-o", "out.docpack"])
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+fn project(dir: &Path) {
+    fs::create_dir_all(dir.join("project/node_modules/left-pad")).unwrap();
+    fs::write(dir.join("project/README.md"), "# Project\n").unwrap();
+    fs::write(dir.join("project/node_modules/left-pad/index.js"), "module.exports = 1;\n").unwrap();
+}
+
+#[test]
+fn quiet_and_verbose() {
+    let dir = tempfile::tempdir().unwrap();
+    project(dir.path());
+
+    let normal = ingest(dir.path(), &[], &[]);
+    let stdout = String::from_utf8_lossy(&normal.stdout);
+    assert!(stdout.contains("Found 1 files"), "{}", stdout);
+    assert!(!String::from_utf8_lossy(&normal.stderr).contains("debug:"));
+
+    // -q is one flag, before the command or after it
+    for (flags, extra) in [(&["-q"][..], &[][..]), (&[], &["-q"]), (&[], &["--quiet"])] {
+        let quiet = ingest(dir.path(), flags, extra);
+        assert!(quiet.status.success());
+        assert_eq!(String::from_utf8_lossy(&quiet.stdout), "", "{:?} {:?}", flags, extra);
+    }
+    let both = ingest(dir.path(), &["-v"], &["-q"]);
+    assert!(String::from_utf8_lossy(&both.stderr).contains("--quiet cannot be used with --verbose"));
+
+    let verbose = ingest(dir.path(), &["-v"], &[]);
+    let stderr = String::from_utf8_lossy(&verbose.stderr);
+    assert!(stderr.contains("debug: Skipped node_modules/left-pad/index.js: under node_modules/, a default exclude"), "{}", stderr);
+}
+
+#[test]
+fn no_emoji_writes_ascii() {
+    let dir = tempfile::tempdir().unwrap();
+    project(dir.path());
+
+    let output = ingest(dir.path(), &["--no-emoji"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.is_ascii(), "{}", stdout);
+    assert!(stdout.contains("[ok] Successfully created"), "{}", stdout);
+    assert!(stdout.contains("    - node_modules/: 1 files"), "{}", stdout);
+}
+
+#[test]
+fn json_is_all_that_is_printed() {
+    let dir = tempfile::tempdir().unwrap();
+    project(dir.path());
+
+    let output = ingest(dir.path(), &["-v"], &["--json"]);
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(summary.is_object());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn verbose_is_accepted_after_the_command() {
+    let dir = tempfile::tempdir().unwrap();
+    project(dir.path());
+    common::stdout(&common::localdoc(dir.path(), &["ingest", "project", "-o", "app.docpack", "--quiet"]));
+
+    for args in [&["run", "app.docpack", "--dry-run", "-v"][..], &["inspect", "app.docpack", "-v"], &["-v", "inspect", "app.docpack"]] {
+        let output = common::localdoc(dir.path(), args);
+        assert!(output.status.success(), "{:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    }
+    let detailed = common::stdout(&common::localdoc(dir.path(), &["inspect", "app.docpack", "--verbose"]));
+    assert!(detailed.contains("README.md"), "{}", detailed);
+}
+
+#[test]
+fn quiet_fix_prints_no_diff() {
+    let dir = tempfile::tempdir().unwrap();
+    common::stdout(&common::localdoc(dir.path(), &["init", "app.docpack"]));
+    let manifest = dir.path().join("app.docpack/docpack.json");
+    // Compact JSON is rewritten pretty-printed
+    let compact = fs::read_to_string(&manifest).unwrap().replace('\n', "");
+    fs::write(&manifest, &compact).unwrap();
+
+    let loud = common::stdout(&common::localdoc(dir.path(), &["validate", "app.docpack", "--fix", "--dry-run"]));
+    assert!(loud.contains("+++ b/docpack.json"), "{}", loud);
+    let quiet = common::localdoc(dir.path(), &["validate", "app.docpack", "--fix", "-q"]);
+    assert!(quiet.status.success(), "{}", String::from_utf8_lossy(&quiet.stderr));
+    assert_eq!(String::from_utf8_lossy(&quiet.stdout), "");
+    assert_ne!(fs::read_to_string(&manifest).unwrap(), compact);
+}
@@ -0,0 +1,72 @@
+//! `localdoc unpack` and `localdoc pack` turn an archive into a directory
+//! and back, byte for byte, keeping executable bits on the way.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use common::localdoc;
+
+fn ingest(dir: &Path) {
+    let source = dir.join("project");
+    fs::create_dir_all(source.join("scripts")).unwrap();
+    fs::write(source.join("README.md"), "# Fixture\n").unwrap();
+    fs::write(source.join("scripts/build.sh"), "#!/bin/sh\necho building\n").unwrap();
+    fs::set_permissions(source.join("scripts/build.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    common::ingest(dir, &[]);
+}
+
+#[test]
+fn unpacking_and_packing_again_gives_the_same_archive() {
+    let dir = tempfile::tempdir().unwrap();
+    ingest(dir.path());
+
+    let unpacked = localdoc(dir.path(), &["unpack", "app.docpack"]);
+    assert!(unpacked.status.success(), "{}", String::from_utf8_lossy(&unpacked.stderr));
+    assert!(String::from_utf8_lossy(&unpacked.stdout).contains("Entries: "));
+    let script = dir.path().join("app/files/scripts/build.sh");
+    assert_eq!(fs::metadata(script).unwrap().permissions().mode() & 0o777, 0o755);
+
+    let packed = localdoc(dir.path(), &["pack", "app", "-o", "again.docpack"]);
+    assert!(packed.status.success(), "{}", String::from_utf8_lossy(&packed.stderr));
+    assert!(String::from_utf8_lossy(&packed.stdout).contains("Ratio: "));
+    assert_eq!(fs::read(dir.path().join("app.docpack")).unwrap(), fs::read(dir.path().join("again.docpack")).unwrap());
+}
+
+#[test]
+fn existing_destinations_need_force() {
+    let dir = tempfile::tempdir().unwrap();
+    ingest(dir.path());
+    fs::create_dir_all(dir.path().join("app")).unwrap();
+    fs::write(dir.path().join("app/notes.txt"), "mine").unwrap();
+
+    let refused = localdoc(dir.path(), &["unpack", "app.docpack"]);
+    assert!(!refused.status.success());
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("pass --force"));
+    assert!(dir.path().join("app/notes.txt").exists());
+
+    assert!(localdoc(dir.path(), &["unpack", "app.docpack", "--force"]).status.success());
+    assert!(!dir.path().join("app/notes.txt").exists());
+    assert!(dir.path().join("app/docpack.json").is_file());
+
+    let refused = localdoc(dir.path(), &["pack", "app"]);
+    assert!(!refused.status.success());
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("app.docpack already exists"));
+}
+
+#[test]
+fn an_invalid_directory_is_not_packed() {
+    let dir = tempfile::tempdir().unwrap();
+    let pack = dir.path().join("broken");
+    fs::create_dir_all(pack.join("files")).unwrap();
+    fs::write(pack.join("docpack.json"), r#"{"version": "1.0", "name": "x"}"#).unwrap();
+
+    let refused = localdoc(dir.path(), &["pack", "broken"]);
+    assert!(!refused.status.success());
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("Validation failed"));
+    assert!(!dir.path().join("broken.docpack").exists());
+}
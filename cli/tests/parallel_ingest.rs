@@ -0,0 +1,37 @@
+//! Ingest spreads copying, hashing and indexing over the rayon thread pool
+//! and merges the results; the docpack must come out the same as a build
+//! on one thread.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{command, stdout};
+
+/// Ingest `dir/project` into `out` with `threads` rayon workers, and
+/// return the archive's bytes.
+fn ingest(dir: &Path, out: &str, threads: &str) -> Vec<u8> {
+    let args = ["ingest", "project", "-o", out, "--build-index", "--build-graph", "--reproducible", "--quiet"];
+    stdout(&command(dir).env("RAYON_NUM_THREADS", threads).env("SOURCE_DATE_EPOCH", "1700000000").args(args).output().unwrap());
+    fs::read(dir.join(out)).unwrap()
+}
+
+#[test]
+fn parallel_ingest_matches_a_serial_one() {
+    let dir = tempfile::tempdir().unwrap();
+    common::project(dir.path(), 200);
+
+    let serial = ingest(dir.path(), "serial.docpack", "1");
+    let parallel = ingest(dir.path(), "parallel.docpack", "8");
+
+    for name in ["serial", "parallel"] {
+        stdout(&common::localdoc(dir.path(), &["unpack", &format!("{}.docpack", name), "-o", name]));
+    }
+    for index in ["hashes.json", "search.json", "graph.json"] {
+        let read = |name: &str| fs::read_to_string(dir.path().join(name).join("index").join(index)).unwrap();
+        assert_eq!(read("serial"), read("parallel"), "index/{} differs", index);
+    }
+    assert_eq!(common::snapshot(&dir.path().join("serial")), common::snapshot(&dir.path().join("parallel")));
+    assert!(serial == parallel, "archives differ");
+}
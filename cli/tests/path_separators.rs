@@ -0,0 +1,108 @@
+//! Entry names are written with `/` only, and archives made on Windows
+//! with `\` separators read the same everywhere. Paths are built by hand so
+//! the tests don't depend on the host OS.
+
+mod common;
+
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+use localdoc::archive::{self, ArchiveEntry, ArchiveOptions, Hazard, Limits, MemorySink};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// A zip with `entries` written under exactly these names.
+fn zip_of(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, data) in entries {
+        zip.start_file(*name, FileOptions::default()).unwrap();
+        zip.write_all(data).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+fn windows_docpack() -> Vec<u8> {
+    zip_of(&[
+        ("docpack.json", br#"{"version": "1.0", "name": "win", "environment": {"tools": ["read_file"]}}"#),
+        ("files\\src\\main.rs", b"fn main() {}\n"),
+        ("files\\README.md", b"# Win\n"),
+    ])
+}
+
+#[test]
+fn backslash_entries_extract_under_slash_paths() {
+    let bytes = windows_docpack();
+    assert_eq!(archive::audit(Cursor::new(bytes.clone()), &Limits::default()).unwrap(), Vec::new());
+
+    let mut sink = MemorySink::default();
+    archive::extract_entries(Cursor::new(bytes), &mut sink, &Limits::default()).unwrap();
+    let names: Vec<&str> = sink.files.keys().map(String::as_str).collect();
+    assert_eq!(names, ["docpack.json", "files/README.md", "files/src/main.rs"]);
+}
+
+#[test]
+fn backslash_archives_are_read_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("win.docpack");
+    std::fs::write(&path, windows_docpack()).unwrap();
+
+    let localdoc = |args: &[&str]| common::stdout(&common::localdoc(dir.path(), args));
+    assert_eq!(localdoc(&["list", "win.docpack", "--prefix", "files/"]), "files/README.md\nfiles/src/main.rs\n");
+    assert_eq!(localdoc(&["cat", "win.docpack", "files/src/main.rs"]), "fn main() {}\n");
+}
+
+#[test]
+fn backslashes_cannot_escape_the_root() {
+    let bytes = zip_of(&[
+        ("..\\evil.sh", b"echo"),
+        ("files\\..\\..\\evil.sh", b"echo"),
+        ("C:\\Windows\\evil.dll", b"MZ"),
+        ("files/a.txt", b"one"),
+        ("files\\a.txt", b"two"),
+    ]);
+    let hazards = archive::audit(Cursor::new(bytes.clone()), &Limits::default()).unwrap();
+    for name in ["..\\evil.sh", "files\\..\\..\\evil.sh", "C:\\Windows\\evil.dll"] {
+        assert!(hazards.contains(&Hazard::UnsafePath(name.to_string())), "{}: {:?}", name, hazards);
+    }
+    assert!(hazards.contains(&Hazard::DuplicateEntry("files\\a.txt".to_string())), "{:?}", hazards);
+
+    let mut sink = MemorySink::default();
+    let error = archive::extract_entries(Cursor::new(bytes), &mut sink, &Limits::default()).unwrap_err();
+    assert!(error.to_string().contains("unsafe entry path"), "{}", error);
+}
+
+#[test]
+fn archives_are_written_with_slashes() {
+    let entries = ["files\\src", "files\\src\\lib.rs"].map(|name| {
+        Ok(if name.ends_with(".rs") {
+            let reader = Box::new(Cursor::new(b"x".to_vec()));
+            ArchiveEntry::File { path: name.to_string(), reader, unix_mode: None, size: Some(1) }
+        } else {
+            ArchiveEntry::Directory(name.to_string())
+        })
+    });
+    let (cursor, _) = archive::build_archive(entries, Cursor::new(Vec::new()), &ArchiveOptions::default()).unwrap();
+    let mut zip = ZipArchive::new(cursor).unwrap();
+    let mut names: Vec<&str> = zip.file_names().collect();
+    names.sort();
+    assert_eq!(names, ["files/src/", "files/src/lib.rs"]);
+    let mut data = String::new();
+    zip.by_name("files/src/lib.rs").unwrap().read_to_string(&mut data).unwrap();
+    assert_eq!(data, "x");
+
+    assert_eq!(archive::path_to_entry_name(Path::new("src\\main.rs")), "src/main.rs");
+    assert_eq!(archive::path_to_entry_name(&Path::new("src").join("main.rs")), "src/main.rs");
+}
+
+#[test]
+fn names_windows_cannot_create_are_renamed() {
+    assert_eq!(archive::windows_safe_name("files/src/main.rs"), None);
+    assert_eq!(archive::windows_safe_name("files/console.txt"), None);
+    assert_eq!(archive::windows_safe_name("files/aux.txt").as_deref(), Some("files/aux_.txt"));
+    assert_eq!(archive::windows_safe_name("files/CON").as_deref(), Some("files/CON_"));
+    assert_eq!(archive::windows_safe_name("files/lpt1.tar.gz").as_deref(), Some("files/lpt1_.tar.gz"));
+    assert_eq!(archive::windows_safe_name("files/nul/readme.md").as_deref(), Some("files/nul_/readme.md"));
+    assert_eq!(archive::windows_safe_name("files/what?.md").as_deref(), Some("files/what_.md"));
+    assert_eq!(archive::windows_safe_name("files/a:b<c>.md").as_deref(), Some("files/a_b_c_.md"));
+    assert_eq!(archive::windows_safe_name("files/notes. ").as_deref(), Some("files/notes__"));
+}
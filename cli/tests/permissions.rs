@@ -0,0 +1,79 @@
+//! Executable bits and empty directories survive a round trip through
+//! `ingest` and the extractor `run` uses.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use localdoc::archive::{self, DirSink, Limits};
+
+fn write_fixture(root: &Path) {
+    fs::create_dir_all(root.join("scripts")).unwrap();
+    fs::create_dir_all(root.join("docs/empty")).unwrap();
+    fs::create_dir_all(root.join("placeholder")).unwrap();
+    fs::write(root.join("README.md"), "# Fixture\n").unwrap();
+    fs::write(root.join("scripts/build.sh"), "#!/bin/sh\necho building\n").unwrap();
+    fs::set_permissions(root.join("scripts/build.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    // Owner-only execute is still executable
+    fs::write(root.join("scripts/private.sh"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(root.join("scripts/private.sh"), fs::Permissions::from_mode(0o700)).unwrap();
+}
+
+fn ingest_and_extract(dir: &Path) -> std::path::PathBuf {
+    write_fixture(&dir.join("project"));
+    common::ingest(dir, &[]);
+
+    let extracted = dir.join("extracted");
+    let mut sink = DirSink { root: extracted.clone() };
+    archive::extract_entries(fs::File::open(dir.join("app.docpack")).unwrap(), &mut sink, &Limits::default()).unwrap();
+    extracted
+}
+
+fn mode(path: &Path) -> u32 {
+    fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[test]
+fn executable_bit_survives() {
+    let dir = tempfile::tempdir().unwrap();
+    let extracted = ingest_and_extract(dir.path());
+
+    assert_eq!(mode(&extracted.join("files/scripts/build.sh")), 0o755);
+    assert_eq!(mode(&extracted.join("files/scripts/private.sh")), 0o755);
+    assert_eq!(mode(&extracted.join("files/README.md")), 0o644);
+}
+
+#[test]
+fn empty_directories_survive() {
+    let dir = tempfile::tempdir().unwrap();
+    let extracted = ingest_and_extract(dir.path());
+
+    for empty in ["files/docs/empty", "files/placeholder"] {
+        let path = extracted.join(empty);
+        assert!(path.is_dir(), "{} missing", empty);
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 0);
+    }
+}
+
+#[test]
+fn untrusted_modes_are_normalized() {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, mode) in [("files/setuid.sh", 0o4777), ("files/open.txt", 0o666), ("files/hidden.txt", 0o000)] {
+        zip.start_file(name, zip::write::FileOptions::default().unix_permissions(mode)).unwrap();
+        std::io::Write::write_all(&mut zip, b"data\n").unwrap();
+    }
+    let bytes = zip.finish().unwrap().into_inner();
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut sink = DirSink { root: dir.path().to_path_buf() };
+    archive::extract_entries(std::io::Cursor::new(bytes), &mut sink, &Limits::default()).unwrap();
+
+    let full_mode = |name: &str| fs::metadata(dir.path().join(name)).unwrap().permissions().mode() & 0o7777;
+    assert_eq!(full_mode("files/setuid.sh"), 0o755);
+    assert_eq!(full_mode("files/open.txt"), 0o644);
+    assert_eq!(full_mode("files/hidden.txt"), 0o644);
+}
@@ -0,0 +1,113 @@
+//! End-to-end tests of the plugin invocation protocol, using the example
+//! plugin shipped in `plugins/` plus a few misbehaving ones.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Output;
+
+use common::stdout;
+
+fn write_plugin(dir: &Path, name: &str, script: &str) {
+    let path = dir.join(format!("localdoc-plugin-{}", name));
+    fs::write(&path, script).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Run localdoc with only `plugin_dir` (and the system directories) on PATH.
+fn localdoc(plugin_dir: &Path, args: &[&str]) -> Output {
+    let path = format!("{}:/usr/bin:/bin", plugin_dir.display());
+    common::command(plugin_dir).env("PATH", path).env("LOCALDOC_PLUGIN_TIMEOUT", "1").args(args).output().unwrap()
+}
+
+fn setup() -> (tempfile::TempDir, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let bin = dir.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    fs::copy(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("plugins/localdoc-plugin-example"),
+        bin.join("localdoc-plugin-example"),
+    )
+    .unwrap();
+
+    let docpack = dir.path().join("p.docpack").display().to_string();
+    let init = localdoc(&bin, &["init", &docpack]);
+    assert!(init.status.success());
+    for entry in fs::read_dir(Path::new(&docpack).join("files")).unwrap() {
+        fs::remove_file(entry.unwrap().path()).unwrap();
+    }
+    (dir, docpack)
+}
+
+#[test]
+fn list_shows_discovered_plugins() {
+    let (dir, _) = setup();
+    let out = localdoc(&dir.path().join("bin"), &["plugins", "list"]);
+    assert!(out.status.success());
+    assert!(stdout(&out).contains("example"), "{}", stdout(&out));
+}
+
+#[test]
+fn inspect_merges_plugin_sections() {
+    let (dir, docpack) = setup();
+    let out = localdoc(&dir.path().join("bin"), &["inspect", &docpack]);
+    assert!(out.status.success());
+    let text = stdout(&out);
+    assert!(text.contains("Doc coverage (plugin: example)"), "{}", text);
+    assert!(text.contains("README:      false"), "{}", text);
+
+    let out = localdoc(&dir.path().join("bin"), &["inspect", &docpack, "--no-plugins"]);
+    assert!(!stdout(&out).contains("plugin: example"));
+}
+
+#[test]
+fn validate_attributes_plugin_findings() {
+    let (dir, docpack) = setup();
+    let out = localdoc(&dir.path().join("bin"), &["validate", &docpack]);
+    assert!(out.status.success());
+    assert!(stdout(&out).contains("[plugin example] EX001:"), "{}", stdout(&out));
+
+    fs::write(Path::new(&docpack).join("files/README.md"), "# hi").unwrap();
+    let out = localdoc(&dir.path().join("bin"), &["validate", &docpack]);
+    assert!(!stdout(&out).contains("EX001"));
+}
+
+#[test]
+fn plugin_errors_fail_validation() {
+    let (dir, docpack) = setup();
+    let bin = dir.path().join("bin");
+    write_plugin(
+        &bin,
+        "strict",
+        "#!/bin/sh\necho '{\"findings\": [{\"code\": \"S1\", \"severity\": \"error\", \"message\": \"nope\"}]}'\n",
+    );
+
+    let out = localdoc(&bin, &["validate", &docpack]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stdout).contains("[plugin strict] S1: nope"));
+}
+
+#[test]
+fn broken_plugins_never_break_the_run() {
+    let (dir, docpack) = setup();
+    let bin = dir.path().join("bin");
+    write_plugin(&bin, "crash", "#!/bin/sh\necho 'boom' >&2\nexit 3\n");
+    write_plugin(&bin, "garbage", "#!/bin/sh\necho 'not json'\n");
+    write_plugin(&bin, "slow", "#!/bin/sh\nexec sleep 30\n");
+
+    let out = localdoc(&bin, &["validate", &docpack]);
+    assert!(out.status.success());
+    let text = stdout(&out);
+    assert!(text.contains("[plugin crash] failed: exited with status 3: boom"), "{}", text);
+    assert!(text.contains("[plugin garbage] failed: invalid JSON output"), "{}", text);
+    assert!(text.contains("[plugin slow] failed: timed out after 1s"), "{}", text);
+
+    let out = localdoc(&bin, &["inspect", &docpack]);
+    assert!(out.status.success());
+    // Warnings go to stderr
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Plugin slow failed: timed out"));
+}
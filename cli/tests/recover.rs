@@ -0,0 +1,141 @@
+//! `localdoc recover` salvages entries from archives whose central
+//! directory is gone, by scanning for local file headers. The archives
+//! here are written header by header to control exactly what is damaged.
+
+mod common;
+
+use std::fs;
+use std::io::Write;
+
+use common::{localdoc, stdout};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde_json::Value;
+
+const DESCRIPTOR: u16 = 0x0008;
+
+/// One local file header and its data, as a zip writer would lay them out.
+fn local_entry(name: &str, method: u16, flags: u16, crc: u32, sizes: (u32, u32), extra: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::new();
+    entry.extend(0x04034b50u32.to_le_bytes());
+    entry.extend(20u16.to_le_bytes());
+    entry.extend(flags.to_le_bytes());
+    entry.extend(method.to_le_bytes());
+    entry.extend([0u8; 4]);
+    entry.extend(crc.to_le_bytes());
+    entry.extend(sizes.0.to_le_bytes());
+    entry.extend(sizes.1.to_le_bytes());
+    entry.extend((name.len() as u16).to_le_bytes());
+    entry.extend((extra.len() as u16).to_le_bytes());
+    entry.extend(name.as_bytes());
+    entry.extend(extra);
+    entry.extend(data);
+    entry
+}
+
+fn stored(name: &str, data: &[u8]) -> Vec<u8> {
+    let size = data.len() as u32;
+    local_entry(name, 0, 0, crc32fast::hash(data), (size, size), &[], data)
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// An entry whose CRC and sizes only follow its data, in a descriptor.
+fn with_descriptor(name: &str, method: u16, data: &[u8]) -> Vec<u8> {
+    let body = if method == 8 { deflate(data) } else { data.to_vec() };
+    let mut entry = local_entry(name, method, DESCRIPTOR, 0, (0, 0), &[], &body);
+    entry.extend(0x08074b50u32.to_le_bytes());
+    entry.extend(crc32fast::hash(data).to_le_bytes());
+    entry.extend((body.len() as u32).to_le_bytes());
+    entry.extend((data.len() as u32).to_le_bytes());
+    entry
+}
+
+/// A stored entry whose header defers its sizes to a zip64 extra field.
+fn zip64(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut extra = Vec::new();
+    extra.extend(0x0001u16.to_le_bytes());
+    extra.extend(16u16.to_le_bytes());
+    extra.extend((data.len() as u64).to_le_bytes());
+    extra.extend((data.len() as u64).to_le_bytes());
+    local_entry(name, 0, 0, crc32fast::hash(data), (u32::MAX, u32::MAX), &extra, data)
+}
+
+/// A docpack with one entry of each kind and no central directory, only
+/// the first bytes of one.
+fn damaged_docpack() -> Vec<u8> {
+    let manifest = br#"{"version": "1.0", "name": "app", "environment": {"tools": ["read_file"]}}"#;
+    let mut bytes = stored("docpack.json", manifest);
+    bytes.extend(with_descriptor("files/README.md", 8, &b"# App\n\nRecovered from a descriptor.\n".repeat(20)));
+    bytes.extend(with_descriptor("files/src/main.rs", 0, b"fn main() {}\n"));
+    bytes.extend(zip64("files/src/big.rs", b"pub fn big() {}\n"));
+    let mut bad_crc = stored("files/bad.txt", b"corrupted\n");
+    bad_crc[14] ^= 0xff;
+    bytes.extend(bad_crc);
+    bytes.extend(stored("../x", b"escaped\n"));
+    bytes.extend(with_descriptor("files/bomb.bin", 8, &vec![0u8; 4 * 1024 * 1024]));
+    bytes.extend(0x02014b50u32.to_le_bytes());
+    bytes.extend([0u8; 6]);
+    bytes
+}
+
+#[test]
+fn entries_are_recovered_and_damaged_ones_listed_as_lost() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("work")).unwrap();
+    fs::write(dir.path().join("work/app.docpack"), damaged_docpack()).unwrap();
+
+    let printed = stdout(&localdoc(&dir.path().join("work"), &["recover", "app.docpack", "-o", "out"]));
+    assert!(printed.contains("Recovered (4):"), "{}", printed);
+    assert!(printed.contains("Lost (3):"), "{}", printed);
+    assert!(printed.contains("files/bad.txt at offset") && printed.contains("CRC mismatch"), "{}", printed);
+    assert!(printed.contains("../x at offset") && printed.contains("unsafe path"), "{}", printed);
+    assert!(printed.contains("files/bomb.bin at offset") && printed.contains("size limit"), "{}", printed);
+
+    let out = dir.path().join("work/out");
+    assert_eq!(fs::read(out.join("files/README.md")).unwrap(), b"# App\n\nRecovered from a descriptor.\n".repeat(20));
+    assert_eq!(fs::read_to_string(out.join("files/src/main.rs")).unwrap(), "fn main() {}\n");
+    assert_eq!(fs::read_to_string(out.join("files/src/big.rs")).unwrap(), "pub fn big() {}\n");
+    assert!(!out.join("files/bad.txt").exists() && !out.join("files/bomb.bin").exists());
+
+    // Nothing was written beside the output directory, partial files included
+    let mut beside: Vec<String> =
+        fs::read_dir(dir.path().join("work")).unwrap().map(|e| e.unwrap().file_name().into_string().unwrap()).collect();
+    beside.sort();
+    assert_eq!(beside, ["app.docpack", "out"]);
+    assert!(!dir.path().join("x").exists());
+    let names: Vec<String> = common::snapshot(&out).into_iter().map(|(name, _)| name).collect();
+    assert!(names.iter().all(|name| !name.contains(".partial")), "{:?}", names);
+
+    let manifest: Value = serde_json::from_str(&fs::read_to_string(out.join("docpack.json")).unwrap()).unwrap();
+    assert_eq!(manifest["name"], "app");
+    assert_eq!(manifest["metadata"]["recovered"], true);
+    assert_eq!(manifest["metadata"]["recovery"]["entries_recovered"], 4);
+    assert_eq!(manifest["metadata"]["recovery"]["entries_lost"], serde_json::json!(["files/bad.txt", "../x", "files/bomb.bin"]));
+
+    // Hashes are regenerated for what was salvaged, so the result verifies
+    let hashes: Value = serde_json::from_str(&fs::read_to_string(out.join("index/hashes.json")).unwrap()).unwrap();
+    let hashed: Vec<&String> = hashes["files"].as_object().unwrap().keys().collect();
+    assert_eq!(hashed, ["README.md", "src/big.rs", "src/main.rs"]);
+    stdout(&localdoc(&dir.path().join("work"), &["verify", "out"]));
+}
+
+#[test]
+fn entries_that_cannot_be_moved_into_place_are_lost() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut bytes = stored("files/docs/", b"");
+    bytes.extend(stored("files/docs", b"a file where the directory is\n"));
+    bytes.extend(stored("files/README.md", b"# App\n"));
+    fs::write(dir.path().join("app.docpack"), bytes).unwrap();
+
+    let printed = stdout(&localdoc(dir.path(), &["recover", "app.docpack", "-o", "out"]));
+    assert!(printed.contains("Lost (1):"), "{}", printed);
+    assert!(printed.contains("files/docs at offset") && printed.contains("cannot write"), "{}", printed);
+    assert_eq!(fs::read_to_string(dir.path().join("out/files/README.md")).unwrap(), "# App\n");
+    let names: Vec<String> = common::snapshot(&dir.path().join("out")).into_iter().map(|(name, _)| name).collect();
+    assert!(names.iter().all(|name| !name.contains(".partial")), "{:?}", names);
+}
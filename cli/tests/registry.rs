@@ -0,0 +1,182 @@
+//! `localdoc push` and `localdoc pull` against a registry that keeps
+//! archives in memory, resuming both ways after a dropped connection.
+
+mod common;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::Output;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use common::stdout;
+use sha2::{Digest, Sha256};
+
+const TOKEN: &str = "s3cret";
+
+fn localdoc(dir: &Path, args: &[&str]) -> Output {
+    common::command(dir).env("LOCALDOC_REGISTRY_TOKEN", TOKEN).args(args).output().unwrap()
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Archives by path, and connections to drop once to test resuming.
+#[derive(Default)]
+struct Registry {
+    blobs: Mutex<HashMap<String, Vec<u8>>>,
+    parts: AtomicUsize,
+    drop_next_part: AtomicBool,
+    drop_next_download: AtomicBool,
+}
+
+/// Start a registry on a free port and return it and its address.
+fn registry() -> (Arc<Registry>, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let registry = Arc::new(Registry::default());
+    let state = registry.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let state = state.clone();
+            thread::spawn(move || handle(&state, stream.unwrap()));
+        }
+    });
+    (registry, address)
+}
+
+fn handle(registry: &Registry, mut stream: TcpStream) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap().to_string(), parts.next().unwrap().to_string());
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        match line.trim_end().split_once(": ") {
+            Some((name, value)) => headers.insert(name.to_ascii_lowercase(), value.to_string()),
+            None => break,
+        };
+    }
+    let length: usize = headers.get("content-length").map_or(0, |len| len.parse().unwrap());
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+
+    let respond = |stream: &mut TcpStream, status: &str, headers: &[(&str, String)], body: &[u8]| {
+        write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len()).unwrap();
+        for (name, value) in headers {
+            write!(stream, "{}: {}\r\n", name, value).unwrap();
+        }
+        stream.write_all(b"\r\n").unwrap();
+        stream.write_all(body).unwrap();
+    };
+    if headers.get("authorization").map(String::as_str) != Some(&format!("Bearer {}", TOKEN)) {
+        return respond(&mut stream, "401 Unauthorized", &[], b"");
+    }
+
+    let mut blobs = registry.blobs.lock().unwrap();
+    if method == "GET" {
+        let Some(blob) = blobs.get(&path) else { return respond(&mut stream, "404 Not Found", &[], b"") };
+        let checksum = ("X-Checksum-Sha256", sha256(blob));
+        match headers.get("range").and_then(|range| range.strip_prefix("bytes=")?.strip_suffix('-')?.parse::<usize>().ok()) {
+            Some(start) => {
+                let range = ("Content-Range", format!("bytes {}-{}/{}", start, blob.len() - 1, blob.len()));
+                respond(&mut stream, "206 Partial Content", &[checksum, range], &blob[start..])
+            }
+            None if registry.drop_next_download.swap(false, Ordering::SeqCst) => {
+                // Promise the whole archive, send half and hang up
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", blob.len()).unwrap();
+                stream.write_all(&blob[..blob.len() / 2]).unwrap();
+            }
+            None => respond(&mut stream, "200 OK", &[checksum], blob),
+        }
+        return;
+    }
+
+    let stored = blobs.entry(path).or_default();
+    let kept = |stored: &Vec<u8>| vec![("Range", format!("bytes=0-{}", stored.len().saturating_sub(1)))];
+    let Some(range) = headers.get("content-range") else {
+        *stored = body;
+        return respond(&mut stream, "201 Created", &[], b"");
+    };
+    if range.starts_with("bytes */") {
+        return respond(&mut stream, "308 Resume Incomplete", &kept(stored), b"");
+    }
+    let (first, rest) = range.strip_prefix("bytes ").unwrap().split_once('-').unwrap();
+    let (last, size) = rest.split_once('/').unwrap();
+    let (first, last, size): (usize, usize, usize) = (first.parse().unwrap(), last.parse().unwrap(), size.parse().unwrap());
+    registry.parts.fetch_add(1, Ordering::SeqCst);
+    if first == stored.len() {
+        stored.extend_from_slice(&body);
+    }
+    if first > 0 && registry.drop_next_part.swap(false, Ordering::SeqCst) {
+        // Keep the part but hang up before answering
+        return;
+    }
+    if last + 1 < size {
+        return respond(&mut stream, "308 Resume Incomplete", &kept(stored), b"");
+    }
+    let status = if Some(&sha256(stored)) == headers.get("x-checksum-sha256") { "201 Created" } else { "400 Bad Request" };
+    respond(&mut stream, status, &[], b"");
+}
+
+#[test]
+fn an_archive_goes_up_in_parts_and_comes_back_verified() {
+    let dir = tempfile::tempdir().unwrap();
+    common::project(dir.path(), 5);
+    common::ingest(dir.path(), &[]);
+    let (registry, address) = registry();
+    let url = format!("http://{}/docs/app.docpack", address);
+
+    registry.drop_next_part.store(true, Ordering::SeqCst);
+    let pushed = localdoc(dir.path(), &["push", "app.docpack", &url, "--chunk-size", "512B"]);
+    assert!(stdout(&pushed).contains("✓ Pushed"));
+    assert!(String::from_utf8_lossy(&pushed.stderr).contains("Retrying in 1s (1 of 3)"));
+    let original = fs::read(dir.path().join("app.docpack")).unwrap();
+    assert_eq!(registry.blobs.lock().unwrap()["/docs/app.docpack"], original);
+    assert!(registry.parts.load(Ordering::SeqCst) > 2);
+
+    registry.drop_next_download.store(true, Ordering::SeqCst);
+    let pulled = localdoc(dir.path(), &["pull", &url, "-o", "copy.docpack"]);
+    assert!(stdout(&pulled).contains("✓ Pulled"));
+    assert_eq!(fs::read(dir.path().join("copy.docpack")).unwrap(), original);
+    assert!(!dir.path().join("copy.docpack.part").exists());
+
+    // A directory is packed on the way up
+    stdout(&localdoc(dir.path(), &["unpack", "app.docpack", "-o", "app"]));
+    stdout(&localdoc(dir.path(), &["push", "app", &format!("http://{}/dir.docpack", address), "--quiet"]));
+    stdout(&localdoc(dir.path(), &["pull", &format!("http://{}/dir.docpack", address), "--quiet"]));
+    assert!(stdout(&localdoc(dir.path(), &["validate", "dir.docpack"])).contains("✓"));
+}
+
+#[test]
+fn auth_network_and_checksum_failures_exit_differently() {
+    let dir = tempfile::tempdir().unwrap();
+    common::project(dir.path(), 5);
+    common::ingest(dir.path(), &[]);
+    let (_registry, address) = registry();
+    let url = format!("http://{}/app.docpack", address);
+    stdout(&localdoc(dir.path(), &["push", "app.docpack", &url, "--quiet"]));
+
+    let unauthorized =
+        common::command(dir.path()).env_remove("LOCALDOC_REGISTRY_TOKEN").args(["pull", &url, "-o", "copy.docpack"]).output().unwrap();
+    assert_eq!(unauthorized.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&unauthorized.stderr).contains("LOCALDOC_REGISTRY_TOKEN"));
+
+    let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let unreachable = localdoc(dir.path(), &["pull", &format!("http://{}/app.docpack", closed), "-o", "copy.docpack", "--retries", "0"]);
+    assert_eq!(unreachable.status.code(), Some(4));
+
+    let wrong = "0".repeat(64);
+    let mismatched = localdoc(dir.path(), &["pull", &url, "-o", "copy.docpack", "--sha256", &wrong, "--quiet"]);
+    assert_eq!(mismatched.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&mismatched.stderr).contains("Checksum mismatch"));
+    assert!(!dir.path().join("copy.docpack").exists() && !dir.path().join("copy.docpack.part").exists());
+}
@@ -0,0 +1,58 @@
+//! `ingest --reproducible` must produce byte-identical archives for the
+//! same source tree.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+fn write_fixture(root: &Path) {
+    fs::create_dir_all(root.join("src/nested")).unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::write(root.join("README.md"), "# Fixture\n\nA small project used to test reproducible builds.\n").unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {\n    println!(\"hello world\");\n}\n").unwrap();
+    fs::write(root.join("src/nested/lib.rs"), "pub fn answer() -> u32 {\n    42\n}\n").unwrap();
+    fs::write(root.join("src.txt"), "sorts between src and src/ entries\n").unwrap();
+    fs::write(root.join("docs/guide.md"), "Usage guide for the fixture project.\n").unwrap();
+}
+
+/// Ingest `dir/project` into `dir/out` and return the archive's bytes.
+fn ingest(dir: &Path, out: &str, extra: &[&str], epoch: Option<&str>) -> Vec<u8> {
+    let mut cmd = common::command(dir);
+    cmd.args(["ingest", "project", "-o", out, "--build-index", "--build-graph", "--quiet"])
+        .args(extra)
+        .env_remove("SOURCE_DATE_EPOCH");
+    if let Some(epoch) = epoch {
+        cmd.env("SOURCE_DATE_EPOCH", epoch);
+    }
+
+    common::stdout(&cmd.output().unwrap());
+    fs::read(dir.join(out)).unwrap()
+}
+
+#[test]
+fn same_source_gives_identical_bytes() {
+    let dir = tempfile::tempdir().unwrap();
+    write_fixture(&dir.path().join("project"));
+
+    let first = ingest(dir.path(), "a.docpack", &["--reproducible"], None);
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let second = ingest(dir.path(), "b.docpack", &["--reproducible"], None);
+
+    assert_eq!(first, second);
+}
+
+#[test]
+fn pinned_creation_time_is_honored() {
+    let dir = tempfile::tempdir().unwrap();
+    write_fixture(&dir.path().join("project"));
+
+    let from_env = ingest(dir.path(), "a.docpack", &["--reproducible"], Some("1700000000"));
+    let from_flag = ingest(dir.path(), "b.docpack", &["--reproducible", "--created", "2023-11-14T22:13:20Z"], None);
+    assert_eq!(from_env, from_flag);
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(from_env)).unwrap();
+    let mut manifest = String::new();
+    std::io::Read::read_to_string(&mut archive.by_name("docpack.json").unwrap(), &mut manifest).unwrap();
+    assert!(manifest.contains("2023-11-14T22:13:20"), "{}", manifest);
+}
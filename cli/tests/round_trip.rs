@@ -0,0 +1,54 @@
+//! Docpacks built by `ingest` and `init` load strictly into the
+//! docpack-core types and serialize back to the same JSON.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{localdoc, stdout};
+use docpack_core::{Manifest, Strictness, TasksFile};
+use serde_json::Value;
+
+/// Load `docpack.json` and `tasks.json` under `dir` and check both come
+/// back out unchanged.
+fn assert_round_trips(dir: &Path) {
+    let manifest = Manifest::load(&dir.join("docpack.json"), Strictness::Strict).unwrap();
+    let original: Value = serde_json::from_slice(&fs::read(dir.join("docpack.json")).unwrap()).unwrap();
+    assert_eq!(manifest.to_value(), original);
+    assert_eq!(manifest.to_json(), fs::read_to_string(dir.join("docpack.json")).unwrap());
+
+    let tasks = TasksFile::load(&dir.join("tasks.json"), Strictness::Strict).unwrap();
+    assert_eq!(tasks.to_json(), fs::read_to_string(dir.join("tasks.json")).unwrap());
+}
+
+#[test]
+fn generated_docpacks_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    common::project(dir.path(), 5);
+    common::ingest(dir.path(), &["--max-file-reads", "50"]);
+    stdout(&localdoc(dir.path(), &["unpack", "app.docpack", "-o", "app"]));
+    assert_round_trips(&dir.path().join("app"));
+    let manifest = Manifest::load(&dir.path().join("app/docpack.json"), Strictness::Strict).unwrap();
+    assert_eq!(manifest.environment.constraints.as_ref().unwrap().max_file_reads, Some(50));
+    assert_eq!(manifest.metadata_str("description_source"), Some("readme"));
+
+    stdout(&localdoc(dir.path(), &["init", "blank", "--with-tasks"]));
+    assert_round_trips(&dir.path().join("blank"));
+    let inspected = stdout(&localdoc(dir.path(), &["inspect", "blank", "--verbose"]));
+    assert!(inspected.contains("max_file_reads: 1000"), "{}", inspected);
+}
+
+#[test]
+fn commands_name_the_field_a_manifest_breaks_on() {
+    let dir = tempfile::tempdir().unwrap();
+    stdout(&localdoc(dir.path(), &["init", "blank"]));
+    let path = dir.path().join("blank/docpack.json");
+    let text = fs::read_to_string(&path).unwrap().replace("\"memory_limit_mb\": 2048", "\"memory_limit_mb\": \"2 GB\"");
+    fs::write(&path, text).unwrap();
+
+    let inspected = localdoc(dir.path(), &["inspect", "blank"]);
+    assert!(!inspected.status.success());
+    let stderr = String::from_utf8_lossy(&inspected.stderr);
+    assert!(stderr.contains("docpack.json: 'environment.constraints.memory_limit_mb': invalid type: string"), "{}", stderr);
+}
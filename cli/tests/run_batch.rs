@@ -0,0 +1,96 @@
+//! `localdoc run` with several docpacks runs each in its own container and
+//! reports them all, whether or not some fail. A stand-in `docker` on PATH
+//! fails for any docpack named `broken`.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+fn fake_runtime(dir: &Path) -> PathBuf {
+    let bin = dir.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let script = r##"#!/bin/sh
+case "$1" in
+  --version|image) exit 0 ;;
+esac
+for arg in "$@"; do
+  case "$arg" in
+    *:/workspace|*:/workspace:ro) workspace="${arg%%:/workspace*}" ;;
+  esac
+done
+case "$workspace" in
+  */broken) echo "agent: cannot reach the API" >&2; exit 3 ;;
+esac
+mkdir -p "$workspace/output"
+echo "# Intro" > "$workspace/output/intro.md"
+echo "agent: wrote intro.md"
+"##;
+    fs::write(bin.join("docker"), script).unwrap();
+    fs::set_permissions(bin.join("docker"), fs::Permissions::from_mode(0o755)).unwrap();
+    bin
+}
+
+fn docpacks(dir: &Path, names: &[&str]) {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/manifests/1.0.json");
+    for name in names {
+        let docpack = dir.join("packs").join(name);
+        for sub in ["files", "index", "output"] {
+            fs::create_dir_all(docpack.join(sub)).unwrap();
+        }
+        fs::copy(&manifest, docpack.join("docpack.json")).unwrap();
+    }
+}
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    let path = format!("{}:{}", fake_runtime(dir).display(), std::env::var("PATH").unwrap_or_default());
+    common::command(dir)
+        .env("PATH", path)
+        .env_remove("LOCALDOC_RUNTIME")
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .args(["run", "--runtime", "docker", "--no-env"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn one_failure_does_not_stop_the_others() {
+    let dir = tempfile::tempdir().unwrap();
+    docpacks(dir.path(), &["api", "broken", "web"]);
+    let output = run(dir.path(), &["packs/*", "--jobs", "2"]);
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("[api] agent: wrote intro.md"), "{}", stdout);
+    assert!(stderr.contains("[broken] agent: cannot reach the API"), "{}", stderr);
+    let rows: Vec<&str> = stdout.lines().skip_while(|line| !line.starts_with("STATUS")).skip(1).take(3).collect();
+        assert!(rows[0].starts_with("success") && rows[0].ends_with("packs/api"));
+    assert!(rows[1].starts_with("failed (exit 9)") && rows[1].ends_with("packs/broken"));
+    assert!(rows[2].starts_with("success") && rows[2].ends_with("packs/web"));
+    assert!(stdout.contains("2 of 3 docpacks succeeded"));
+
+    for name in ["api", "web"] {
+        let summary = fs::read_to_string(dir.path().join("packs").join(name).join("run.json")).unwrap();
+        assert!(summary.contains("\"status\": \"success\""));
+    }
+}
+
+#[test]
+fn output_can_go_to_log_files() {
+    let dir = tempfile::tempdir().unwrap();
+    docpacks(dir.path(), &["api", "web"]);
+    let output = run(dir.path(), &["packs/api", "packs/web", "--log-dir", "logs"]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("agent:"));
+    for name in ["api", "web"] {
+        let log = fs::read_to_string(dir.path().join("logs").join(format!("{}.log", name))).unwrap();
+        assert!(log.contains("agent: wrote intro.md"), "{}", log);
+    }
+}
@@ -0,0 +1,120 @@
+//! `localdoc run --detach` leaves the container running and records it in
+//! `output/.run_state.json`; `localdoc logs` and `localdoc stop` find it
+//! there. A stand-in `docker` on PATH keeps one container's state in a file.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+/// A fake `docker` whose single container is described by `container`
+/// (`<running> <exit code>`, as `container inspect` prints them). `run`
+/// writes `output/guide/intro.md` into the mounted docpack.
+fn fake_runtime(dir: &Path) -> PathBuf {
+    let bin = dir.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let script = format!(
+        r##"#!/bin/sh
+echo "$1" >> "{calls}"
+case "$1" in
+  --version|image) exit 0 ;;
+  container) [ -f "{state}" ] || exit 1; cat "{state}"; exit 0 ;;
+  logs) echo "agent: wrote guide/intro.md"; exit 0 ;;
+  stop) echo "false 0" > "{state}"; exit 0 ;;
+  rm) rm -f "{state}"; exit 0 ;;
+esac
+for arg in "$@"; do
+  case "$arg" in
+    *:/workspace|*:/workspace:ro) workspace="${{arg%%:/workspace*}}" ;;
+  esac
+done
+mkdir -p "$workspace/output/guide"
+echo "# Intro" > "$workspace/output/guide/intro.md"
+echo "true 0" > "{state}"
+echo 4f2a9c
+"##,
+        calls = dir.join("calls.log").display(),
+        state = dir.join("container").display()
+    );
+    fs::write(bin.join("docker"), script).unwrap();
+    fs::set_permissions(bin.join("docker"), fs::Permissions::from_mode(0o755)).unwrap();
+    bin
+}
+
+fn docpack(dir: &Path) -> PathBuf {
+    let docpack = dir.join("pack");
+    for sub in ["files", "index", "output"] {
+        fs::create_dir_all(docpack.join(sub)).unwrap();
+    }
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/manifests/1.0.json");
+    fs::copy(manifest, docpack.join("docpack.json")).unwrap();
+    docpack
+}
+
+fn localdoc(dir: &Path, args: &[&str], docpack: &Path) -> Output {
+    let path = format!("{}:{}", fake_runtime(dir).display(), std::env::var("PATH").unwrap_or_default());
+    common::command(dir)
+        .env("PATH", path)
+        .env_remove("LOCALDOC_RUNTIME")
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .args(args)
+        .arg(docpack)
+        .output()
+        .unwrap()
+}
+
+fn detach(dir: &Path, docpack: &Path) {
+    let output = localdoc(dir, &["run", "--runtime", "docker", "--no-env", "--detach"], docpack);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("localdoc stop"));
+}
+
+#[test]
+fn detached_runs_are_followed_and_stopped() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = docpack(dir.path());
+    detach(dir.path(), &docpack);
+    let state = docpack.join("output/.run_state.json");
+    assert!(state.is_file());
+
+    // A second run is refused while the first is recorded
+    let again = localdoc(dir.path(), &["run", "--runtime", "docker", "--no-env"], &docpack);
+    assert!(!again.status.success());
+    assert!(String::from_utf8_lossy(&again.stderr).contains("localdoc stop"));
+
+    let logs = localdoc(dir.path(), &["logs"], &docpack);
+    assert!(logs.status.success(), "{}", String::from_utf8_lossy(&logs.stderr));
+    assert!(String::from_utf8_lossy(&logs.stdout).contains("agent: wrote guide/intro.md"));
+
+    let stop = localdoc(dir.path(), &["stop"], &docpack);
+    assert!(stop.status.success(), "{}", String::from_utf8_lossy(&stop.stderr));
+    assert!(!state.exists());
+    assert!(!dir.path().join("container").exists(), "container was not removed");
+    let calls = fs::read_to_string(dir.path().join("calls.log")).unwrap();
+    assert!(calls.lines().any(|call| call == "stop"));
+
+    let summary: serde_json::Value = serde_json::from_str(&fs::read_to_string(docpack.join("run.json")).unwrap()).unwrap();
+    assert_eq!(summary["status"], "success");
+    assert_eq!(summary["exit_code"], 0);
+    let outputs: Vec<&str> = summary["outputs"].as_array().unwrap().iter().map(|o| o["path"].as_str().unwrap()).collect();
+    assert_eq!(outputs, ["guide/intro.md"]);
+    assert!(fs::read_to_string(docpack.join("run.log")).unwrap().contains("agent: wrote"));
+}
+
+#[test]
+fn stale_state_is_cleaned_up() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = docpack(dir.path());
+    detach(dir.path(), &docpack);
+    // The container went away without `localdoc stop`
+    fs::remove_file(dir.path().join("container")).unwrap();
+
+    let logs = localdoc(dir.path(), &["logs"], &docpack);
+    assert!(!logs.status.success());
+    assert!(String::from_utf8_lossy(&logs.stdout).contains("no longer exists"));
+    assert!(!docpack.join("output/.run_state.json").exists());
+}
@@ -0,0 +1,160 @@
+//! `localdoc run --dry-run` prints the container command it would start.
+//! PATH is emptied, so the test also shows that no runtime is called.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn docpack(dir: &Path) -> PathBuf {
+    let docpack = dir.join("pack");
+    for sub in ["files", "index", "output"] {
+        fs::create_dir_all(docpack.join(sub)).unwrap();
+    }
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/manifests/1.0.json");
+    fs::copy(manifest, docpack.join("docpack.json")).unwrap();
+    fs::canonicalize(docpack).unwrap()
+}
+
+/// The words of the printed command line, with the generated container
+/// name replaced by `NAME`.
+fn dry_run(dir: &Path, docpack: &Path, args: &[&str]) -> Vec<String> {
+    let output = common::command(dir)
+        .env("PATH", dir.join("no-such-bin"))
+        .env_remove("LOCALDOC_RUNTIME")
+        .arg("run")
+        .arg(docpack)
+        .args(["--dry-run"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().find_map(|line| line.strip_prefix("Command: ")).unwrap();
+    let mut words: Vec<String> = line.split(' ').map(str::to_string).collect();
+    let name = words.iter().position(|word| word == "--name").unwrap() + 1;
+    assert!(words[name].starts_with("localdoc-run-"));
+    words[name] = "NAME".to_string();
+    words
+}
+
+#[test]
+fn docker_defaults_mount_the_docpack_read_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let pack = docpack(dir.path());
+    let p = pack.display();
+
+    // --user stands in for the invoking user, which depends on who runs the tests
+    assert_eq!(
+        dry_run(dir.path(), &pack, &["--runtime", "docker", "--no-env", "--user", "1000:1000"]),
+        [
+            "docker".to_string(),
+            "run".to_string(),
+            "--rm".to_string(),
+            "--name".to_string(),
+            "NAME".to_string(),
+            "--user".to_string(),
+            "1000:1000".to_string(),
+            "--memory".to_string(),
+            "2048m".to_string(),
+            "-v".to_string(),
+            format!("{}:/workspace:ro", p),
+            "-v".to_string(),
+            format!("{}/output:/workspace/output", p),
+            "-v".to_string(),
+            format!("{}/.reasoning:/workspace/.reasoning", p),
+            "doctown:latest".to_string(),
+        ]
+    );
+    assert!(!pack.join(".reasoning").exists());
+}
+
+#[test]
+fn podman_with_limits_variables_and_no_network() {
+    let dir = tempfile::tempdir().unwrap();
+    let pack = docpack(dir.path());
+    let args = [
+        "--runtime", "podman", "--no-env", "--no-network", "--memory", "512", "--cpus", "1.5", "--writable-files",
+        "-e", "MODEL=gpt 4", "-i", "doctown:v2",
+    ];
+
+    assert_eq!(
+        dry_run(dir.path(), &pack, &args),
+        [
+            "MODEL='gpt".to_string(),
+            "4'".to_string(),
+            "podman".to_string(),
+            "run".to_string(),
+            "--rm".to_string(),
+            "--name".to_string(),
+            "NAME".to_string(),
+            "--userns=keep-id".to_string(),
+            "--network".to_string(),
+            "none".to_string(),
+            "--memory".to_string(),
+            "512m".to_string(),
+            "--cpus".to_string(),
+            "1.5".to_string(),
+            "-e".to_string(),
+            "MODEL".to_string(),
+            "-v".to_string(),
+            format!("{}:/workspace:z", pack.display()),
+            "doctown:v2".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn user_mapping_can_be_turned_off() {
+    let dir = tempfile::tempdir().unwrap();
+    let pack = docpack(dir.path());
+
+    for runtime in ["docker", "podman"] {
+        let words = dry_run(dir.path(), &pack, &["--runtime", runtime, "--no-env", "--no-user-mapping"]);
+        assert!(!words.iter().any(|word| word.starts_with("--user")), "{:?}", words);
+    }
+}
+
+#[test]
+fn env_files_are_picked_up_and_secrets_redacted() {
+    let dir = tempfile::tempdir().unwrap();
+    let pack = docpack(dir.path());
+    fs::write(dir.path().join(".env"), "OPENAI_API_KEY=from-file\n").unwrap();
+
+    let words = dry_run(dir.path(), &pack, &["--runtime", "docker", "-e", "OPENAI_API_KEY=sk-secret"]);
+    assert_eq!(words[0], "OPENAI_API_KEY=***");
+    assert!(words.windows(2).any(|pair| pair == ["--env-file", ".env"]));
+    assert!(words.windows(2).any(|pair| pair == ["-e", "OPENAI_API_KEY"]));
+
+    let words = dry_run(dir.path(), &pack, &["--runtime", "docker", "--no-env"]);
+    assert!(!words.iter().any(|word| word == "--env-file"));
+}
+
+#[test]
+fn invalid_docpacks_are_refused_before_anything_starts() {
+    let dir = tempfile::tempdir().unwrap();
+    let pack = docpack(dir.path());
+    let localdoc = |args: &[&str]| {
+        common::command(dir.path())
+            .env("PATH", dir.path().join("no-such-bin"))
+            .args(["run", "--dry-run", "--runtime", "docker", "--no-env"])
+            .arg(&pack)
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    // Warnings alone (no tasks.json) only fail with --strict
+    assert!(localdoc(&[]).status.success());
+    let strict = localdoc(&["--strict"]);
+    assert!(!strict.status.success());
+    assert!(String::from_utf8_lossy(&strict.stderr).contains("[DP010]"));
+
+    fs::write(pack.join("docpack.json"), r#"{"version": "1.0", "name": "x"}"#).unwrap();
+    let refused = localdoc(&[]);
+    assert!(!refused.status.success());
+    let stderr = String::from_utf8_lossy(&refused.stderr);
+    assert!(stderr.contains("\"environment\" is a required property"), "{}", stderr);
+    assert!(stderr.contains("--no-validate"));
+    assert!(localdoc(&["--no-validate"]).status.success());
+}
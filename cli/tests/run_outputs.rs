@@ -0,0 +1,218 @@
+//! Outputs of a run in a temporary copy are copied somewhere that lasts,
+//! and the copy itself is removed. A stand-in `docker` on PATH plays the
+//! agent.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A fake `docker` that writes `output/guide/intro.md` into the mounted
+/// docpack and records where that was. A tasks.json mounted on its own is
+/// copied to `output/tasks.json`. With `$FAKE_HANG` set it then keeps
+/// running until `docker stop`.
+fn fake_runtime(dir: &Path) -> PathBuf {
+    let bin = dir.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let script = format!(
+        r##"#!/bin/sh
+[ "$1" = "--version" ] && exit 0
+[ "$1" = "image" ] && exit 0
+if [ "$1" = "stop" ]; then
+  kill "$(cat "{pid}")"
+  exit 0
+fi
+for arg in "$@"; do
+  case "$arg" in
+    *:/workspace|*:/workspace:ro) workspace="${{arg%%:/workspace*}}" ;;
+    *:/workspace/tasks.json:ro) tasks="${{arg%%:/workspace/tasks.json*}}" ;;
+  esac
+done
+mkdir -p "$workspace/output/guide"
+[ -n "$tasks" ] && cp "$tasks" "$workspace/output/tasks.json"
+echo "# Intro" > "$workspace/output/guide/intro.md"
+echo "$workspace" > "{workspace}"
+if [ -n "$FAKE_HANG" ]; then
+  echo $$ > "{pid}"
+  exec sleep 30
+fi
+"##,
+        workspace = dir.join("workspace.txt").display(),
+        pid = dir.join("agent.pid").display()
+    );
+    fs::write(bin.join("docker"), script).unwrap();
+    fs::set_permissions(bin.join("docker"), fs::Permissions::from_mode(0o755)).unwrap();
+    bin
+}
+
+/// `dir/app.docpack`, ingested from a project of just a README.
+fn docpack(dir: &Path) -> PathBuf {
+    fs::create_dir_all(dir.join("project")).unwrap();
+    fs::write(dir.join("project/README.md"), "# Project\n").unwrap();
+    common::ingest(dir, &[]);
+    dir.join("app.docpack")
+}
+
+fn localdoc_run(dir: &Path, docpack: &Path, extra: &[&str]) -> Command {
+    let path = format!("{}:{}", fake_runtime(dir).display(), std::env::var("PATH").unwrap_or_default());
+    let mut cmd = common::command(dir);
+    cmd.env("PATH", path)
+        .env_remove("LOCALDOC_RUNTIME")
+        .env_remove("FAKE_HANG")
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .args(["run", "--runtime", "docker"])
+        .arg(docpack)
+        .args(extra);
+    cmd
+}
+
+fn run(dir: &Path, docpack: &Path, extra: &[&str]) -> Output {
+    let output = localdoc_run(dir, docpack, extra).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    output
+}
+
+fn working_copy(dir: &Path) -> PathBuf {
+    PathBuf::from(fs::read_to_string(dir.join("workspace.txt")).unwrap().trim())
+}
+
+#[test]
+fn archive_outputs_go_to_the_output_dir_and_the_copy_is_removed() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = docpack(dir.path());
+    let target = dir.path().join("docs");
+
+    let output = run(dir.path(), &docpack, &["--output-dir", target.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(target.join("guide/intro.md")).unwrap(), "# Intro\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&target.join("guide/intro.md").display().to_string()));
+    assert!(!working_copy(dir.path()).exists());
+}
+
+#[test]
+fn archive_outputs_default_beside_the_archive() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = docpack(dir.path());
+
+    run(dir.path(), &docpack, &[]);
+    assert!(dir.path().join("app-output/guide/intro.md").is_file());
+    assert!(!working_copy(dir.path()).exists());
+}
+
+#[test]
+fn update_archive_writes_outputs_into_the_docpack() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = docpack(dir.path());
+
+    run(dir.path(), &docpack, &["--update-archive"]);
+    let mut zip = zip::ZipArchive::new(fs::File::open(&docpack).unwrap()).unwrap();
+    assert!(zip.by_name("output/guide/intro.md").is_ok());
+    assert!(zip.by_name("run.json").is_ok());
+    assert!(!dir.path().join("app-output").exists());
+    assert!(!working_copy(dir.path()).exists());
+}
+
+#[test]
+fn timed_out_runs_are_stopped_and_their_outputs_salvaged() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = docpack(dir.path());
+    let target = dir.path().join("docs");
+
+    let started = std::time::Instant::now();
+    let output = localdoc_run(dir.path(), &docpack, &["--timeout", "1", "--output-dir", target.to_str().unwrap()])
+        .env("FAKE_HANG", "1")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(started.elapsed().as_secs() < 20);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Execution time limit exceeded"));
+    assert_eq!(fs::read_to_string(target.join("guide/intro.md")).unwrap(), "# Intro\n");
+    assert!(!working_copy(dir.path()).exists());
+}
+
+#[test]
+fn selected_tasks_replace_tasks_json_for_the_agent() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = docpack(dir.path());
+
+    let output = run(dir.path(), &docpack, &["--task", "task_1"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Ran 1 of 1 tasks: task_1"), "{}", stdout);
+    let shown: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("app-output/tasks.json")).unwrap()).unwrap();
+    assert_eq!(shown["tasks"].as_array().unwrap().len(), 1);
+    assert_eq!(shown["tasks"][0]["id"], "task_1");
+
+    let output = localdoc_run(dir.path(), &docpack, &["--task", "task_9"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Tasks in tasks.json: task_1"));
+
+    let output = localdoc_run(dir.path(), &docpack, &["--list-tasks"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("task_1  Analyze project structure"));
+}
+
+#[test]
+fn a_task_given_twice_runs_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = docpack(dir.path());
+
+    let output = run(dir.path(), &docpack, &["--task", "task_1", "--task", "task_1"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Tasks: task_1 of 1\n"), "{}", stdout);
+    assert!(stdout.contains("Ran 1 of 1 tasks: task_1\n"), "{}", stdout);
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("app-output/run_summary.json")).unwrap()).unwrap();
+    assert_eq!(summary["tasks"], serde_json::json!(["task_1"]));
+}
+
+#[test]
+fn runs_are_summarized_and_logged() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = docpack(dir.path());
+
+    let output = run(dir.path(), &docpack, &["--json"]);
+    let printed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(printed["status"], "success");
+    assert_eq!(printed["exit_code"], 0);
+    assert_eq!(printed["image"], "doctown:latest");
+    assert_eq!(printed["localdoc_version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(printed["outputs"][0]["path"], "guide/intro.md");
+    assert_eq!(printed["outputs"][0]["size"], 8);
+    assert_eq!(printed["outputs"][0]["sha256"].as_str().unwrap().len(), 64);
+    let written: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("app-output/run_summary.json")).unwrap()).unwrap();
+    assert_eq!(written, printed);
+
+    let log = fs::read_to_string(dir.path().join("data/localdoc/history.jsonl")).unwrap();
+    assert_eq!(log.lines().count(), 1);
+    let history = common::command(dir.path())
+        .env("XDG_DATA_HOME", dir.path().join("data"))
+        .arg("history")
+        .output()
+        .unwrap();
+    let listed = String::from_utf8_lossy(&history.stdout);
+    assert!(listed.contains("success") && listed.contains("app.docpack"), "{}", listed);
+}
+
+#[test]
+fn output_limits_fail_the_run_and_must_be_positive() {
+    let dir = tempfile::tempdir().unwrap();
+    let docpack = docpack(dir.path());
+
+    let output = localdoc_run(dir.path(), &docpack, &["--json", "--max-output-bytes", "4"]).output().unwrap();
+    assert!(!output.status.success());
+    let printed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(printed["status"], "output_limits_exceeded");
+    assert_eq!(printed["output_limits"]["max_output_bytes"], 4);
+    assert_eq!(printed["output_limits"]["violations"][0]["size"], 8);
+
+    for flag in ["--max-output-bytes", "--max-output-file-bytes"] {
+        let output = localdoc_run(dir.path(), &docpack, &[flag, "0"]).output().unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("0 is not in 1.."), "{}", stderr);
+    }
+}
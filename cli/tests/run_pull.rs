@@ -0,0 +1,79 @@
+//! `localdoc run --pull` decides whether the agent image is pulled, and a
+//! failed pull is reported with the runtime's own message. A stand-in
+//! `docker` on PATH has no images and a registry that refuses every pull.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+/// A fake `docker` that logs each call to `calls.log`.
+fn fake_runtime(dir: &Path) -> PathBuf {
+    let bin = dir.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let script = format!(
+        r#"#!/bin/sh
+echo "$@" >> "{}"
+case "$1" in
+  --version) exit 0 ;;
+  image) echo "Error: No such image: $3" >&2; exit 1 ;;
+  pull) for image; do :; done
+        echo "Error response from daemon: pull access denied for $image, repository does not exist or may require 'docker login': denied: requested access to the resource is denied" >&2; exit 1 ;;
+esac
+exit 0
+"#,
+        dir.join("calls.log").display()
+    );
+    fs::write(bin.join("docker"), script).unwrap();
+    fs::set_permissions(bin.join("docker"), fs::Permissions::from_mode(0o755)).unwrap();
+    bin
+}
+
+fn run(dir: &Path, extra: &[&str]) -> (Output, String) {
+    let docpack = dir.join("pack");
+    for sub in ["files", "index", "output"] {
+        fs::create_dir_all(docpack.join(sub)).unwrap();
+    }
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/manifests/1.0.json");
+    fs::copy(manifest, docpack.join("docpack.json")).unwrap();
+
+    let path = format!("{}:{}", fake_runtime(dir).display(), std::env::var("PATH").unwrap_or_default());
+    let output = common::command(dir)
+        .env("PATH", path)
+        .env_remove("LOCALDOC_RUNTIME")
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .args(["run", "--runtime", "docker", "--no-env"])
+        .arg(&docpack)
+        .args(extra)
+        .output()
+        .unwrap();
+    let calls = fs::read_to_string(dir.join("calls.log")).unwrap_or_default();
+    (output, calls)
+}
+
+#[test]
+fn missing_images_are_pulled_and_registry_errors_shown() {
+    let dir = tempfile::tempdir().unwrap();
+    let (output, calls) = run(dir.path(), &["--quiet"]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("pull access denied for doctown:latest"), "{}", stderr);
+    assert!(stderr.contains("docker login"));
+    assert_eq!(calls.lines().collect::<Vec<_>>(), ["image inspect doctown:latest", "pull --quiet doctown:latest"]);
+}
+
+#[test]
+fn never_fails_fast_without_pulling() {
+    let dir = tempfile::tempdir().unwrap();
+    let (output, calls) = run(dir.path(), &["--pull", "never"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("docker pull doctown:latest"));
+    assert!(!calls.contains("pull"));
+    assert!(!calls.contains("run"));
+}
@@ -0,0 +1,105 @@
+//! A sealed docpack (`metadata.immutable: true`) refuses commands that
+//! would change it, unless they are given `--force-mutable`.
+
+mod common;
+
+use std::fs;
+
+use common::{localdoc, snapshot, stderr, stdout};
+
+#[test]
+fn sealed_docpacks_refuse_changes_unless_forced() {
+    let dir = tempfile::tempdir().unwrap();
+    stdout(&localdoc(dir.path(), &["init", "app.docpack"]));
+    fs::write(dir.path().join("app.docpack/tasks.json"), r#"{ "tasks": [] }"#).unwrap();
+    let seal = common::command(dir.path())
+        .args(["set", "app.docpack", "metadata.immutable", "true"])
+        .env("USER", "release-bot")
+        .output()
+        .unwrap();
+    stdout(&seal);
+    let sealed = snapshot(&dir.path().join("app.docpack"));
+
+    let add = ["tasks", "app.docpack", "add", "--name", "Overview", "--description", "Summarize the project"];
+    for args in [&["set", "app.docpack", "name", "renamed"][..], &add] {
+        let output = localdoc(dir.path(), args);
+        assert!(!output.status.success(), "{:?} was allowed", args);
+        let message = stderr(&output);
+        assert!(message.contains("Docpack is sealed (sealed by release-bot at "), "{}", message);
+        assert!(message.contains("--force-mutable"), "{}", message);
+    }
+    assert_eq!(snapshot(&dir.path().join("app.docpack")), sealed);
+
+    stdout(&localdoc(dir.path(), &["set", "app.docpack", "name", "renamed", "--force-mutable"]));
+    stdout(&localdoc(dir.path(), &["tasks", "app.docpack", "add", "--name", "Overview", "--description", "Summarize the project", "--force-mutable"]));
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("app.docpack/docpack.json")).unwrap()).unwrap();
+    assert_eq!(manifest["name"], "renamed");
+    assert_eq!(manifest["metadata"]["immutable"], true);
+    let tasks = fs::read_to_string(dir.path().join("app.docpack/tasks.json")).unwrap();
+    assert!(tasks.contains("\"Overview\""), "{}", tasks);
+}
+
+#[test]
+fn sealed_archives_refuse_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    stdout(&localdoc(dir.path(), &["init", "app.docpack"]));
+    fs::write(dir.path().join("app.docpack/tasks.json"), r#"{ "tasks": [] }"#).unwrap();
+    stdout(&localdoc(dir.path(), &["set", "app.docpack", "metadata.immutable", "true"]));
+    stdout(&localdoc(dir.path(), &["pack", "app.docpack", "-o", "sealed.docpack"]));
+    let packed = fs::read(dir.path().join("sealed.docpack")).unwrap();
+
+    for args in [
+        &["tasks", "sealed.docpack", "add", "--name", "Overview", "--description", "Summarize the project"][..],
+        &["set", "sealed.docpack", "name", "renamed"],
+    ] {
+        let output = localdoc(dir.path(), args);
+        assert!(!output.status.success(), "{:?} was allowed", args);
+        let message = stderr(&output);
+        assert!(message.contains("Docpack is sealed"), "{}", message);
+    }
+    assert_eq!(fs::read(dir.path().join("sealed.docpack")).unwrap(), packed);
+}
+
+#[test]
+fn sealed_by_falls_back_when_no_user_is_set() {
+    let dir = tempfile::tempdir().unwrap();
+    stdout(&localdoc(dir.path(), &["init", "a.docpack"]));
+    stdout(&localdoc(dir.path(), &["init", "b.docpack"]));
+
+    let seal = |docpack: &str, env: &[(&str, &str)]| {
+        let mut cmd = common::command(dir.path());
+        cmd.args(["set", docpack, "metadata.immutable", "true"])
+            .env_remove("LOCALDOC_SEALED_BY")
+            .env_remove("USER")
+            .env_remove("USERNAME")
+            .envs(env.iter().copied());
+        stdout(&cmd.output().unwrap());
+        let manifest = fs::read_to_string(dir.path().join(docpack).join("docpack.json")).unwrap();
+        serde_json::from_str::<serde_json::Value>(&manifest).unwrap()["metadata"]["sealed_by"].clone()
+    };
+    assert_eq!(seal("a.docpack", &[]), "unknown");
+    assert_eq!(seal("b.docpack", &[("USER", "someone"), ("LOCALDOC_SEALED_BY", "release-bot")]), "release-bot");
+}
+
+#[test]
+fn validation_reports_files_changed_after_sealing() {
+    let dir = tempfile::tempdir().unwrap();
+    common::project(dir.path(), 3);
+    common::ingest(dir.path(), &[]);
+    stdout(&localdoc(dir.path(), &["unpack", "app.docpack", "-o", "app"]));
+    stdout(&localdoc(dir.path(), &["set", "app", "metadata.immutable", "true"]));
+    stdout(&localdoc(dir.path(), &["validate", "app"]));
+
+    let files = dir.path().join("app/files");
+    let first = walkdir::WalkDir::new(&files).into_iter().flatten().find(|e| e.file_type().is_file()).unwrap();
+    fs::write(first.path(), "changed after sealing\n").unwrap();
+    fs::write(files.join("extra.txt"), "added after sealing\n").unwrap();
+
+    let output = localdoc(dir.path(), &["validate", "app", "--json"]);
+    assert!(!output.status.success());
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(report.contains("DP042"), "{}", report);
+    assert!(report.contains("was modified after the docpack was sealed"), "{}", report);
+    assert!(report.contains("files/extra.txt was added after the docpack was sealed"), "{}", report);
+}
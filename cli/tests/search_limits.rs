@@ -0,0 +1,39 @@
+//! `localdoc search` stops at `--limit`, and a symbol index written by hand
+//! can't crash `--symbols`.
+
+mod common;
+
+use std::fs;
+
+use common::{localdoc, stdout};
+use serde_json::Value;
+
+#[test]
+fn results_stop_at_the_limit_but_count_every_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = common::project(dir.path(), 3);
+    let many: String = (0..20).map(|i| format!("let widget_{} = widget();\n", i)).collect();
+    fs::write(project.join("widgets.rs"), many).unwrap();
+    common::ingest(dir.path(), &["--build-index"]);
+
+    let printed = stdout(&localdoc(dir.path(), &["search", "app.docpack", "widget", "--limit", "3", "--json"]));
+    let report: Value = serde_json::from_str(&printed).unwrap();
+    assert_eq!(report["results"].as_array().unwrap().len(), 3, "{}", report);
+    assert_eq!(report["total"], 20, "{}", report);
+    assert_eq!(report["results"][2]["snippet"], "let widget_2 = widget();");
+}
+
+#[test]
+fn symbols_on_line_zero_have_no_snippet() {
+    let dir = tempfile::tempdir().unwrap();
+    common::project(dir.path(), 3);
+    common::ingest(dir.path(), &["--build-index"]);
+    stdout(&localdoc(dir.path(), &["unpack", "app.docpack", "-o", "app"]));
+    let symbols = r#"{ "broken": [{ "path": "README.md", "line": 0, "kind": "function" }] }"#;
+    fs::write(dir.path().join("app/index/symbols.json"), symbols).unwrap();
+
+    let printed = stdout(&localdoc(dir.path(), &["search", "app", "--symbols", "broken", "--json"]));
+    let report: Value = serde_json::from_str(&printed).unwrap();
+    assert_eq!(report["results"][0]["line"], 0);
+    assert_eq!(report["results"][0]["snippet"], "");
+}
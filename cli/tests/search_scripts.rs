@@ -0,0 +1,40 @@
+//! Words in any script are found by `localdoc search`: Japanese and Korean
+//! text through bigrams, German through Unicode case mapping.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{localdoc, stdout};
+use serde_json::Value;
+
+/// The files `search --json` finds for `query`.
+fn found(dir: &Path, query: &str) -> Vec<String> {
+    let printed = stdout(&localdoc(dir, &["search", "app.docpack", query, "--json"]));
+    let results: Value = serde_json::from_str(&printed).unwrap();
+    let mut paths: Vec<String> =
+        results["results"].as_array().unwrap().iter().map(|hit| hit["path"].as_str().unwrap().to_string()).collect();
+    paths.dedup();
+    paths
+}
+
+#[test]
+fn mixed_script_documents_are_searchable() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = common::project(dir.path(), 3);
+    fs::write(
+        project.join("settings.rs"),
+        "// 設定値を読み込む\nfn load(設定値: &str) -> usize {\n    設定値.len()\n}\n\n// 검색엔진 설정\nconst MAX_SIZE: usize = 4;\n",
+    )
+    .unwrap();
+    fs::write(project.join("LIESMICH.md"), "# Übersicht\n\nDie Größe der Datei wird geprüft.\n").unwrap();
+    common::ingest(dir.path(), &["--build-index"]);
+
+    assert_eq!(found(dir.path(), "設定値"), ["settings.rs"]);
+    assert_eq!(found(dir.path(), "読み込む"), ["settings.rs"]);
+    assert_eq!(found(dir.path(), "검색"), ["settings.rs"]);
+    assert_eq!(found(dir.path(), "Größe"), ["LIESMICH.md"]);
+    assert_eq!(found(dir.path(), "übersicht"), ["LIESMICH.md"]);
+    assert_eq!(found(dir.path(), "GEPRÜFT"), ["LIESMICH.md"]);
+}
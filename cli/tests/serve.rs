@@ -0,0 +1,82 @@
+//! `localdoc serve` answers HTTP requests for a docpack's pages, reading
+//! an archive in place.
+
+mod common;
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::{Child, Stdio};
+
+use common::localdoc;
+
+/// Stops the server when a test ends, passed or not.
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Start serving `docpack` on a free port and return the server and its
+/// address.
+fn serve(dir: &Path, docpack: &str) -> (Server, String) {
+    let mut child = common::command(dir)
+        .args(["serve", docpack, "--port", "0"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+    let address = line.split("http://").nth(1).and_then(|rest| rest.split('/').next()).expect(&line).to_string();
+    (Server(child), address)
+}
+
+/// The status code and body of a GET request.
+fn get(address: &str, path: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, address).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.contains("Content-Security-Policy: default-src 'self'"), "{}", head);
+    (head.split(' ').nth(1).unwrap().parse().unwrap(), body.to_string())
+}
+
+#[test]
+fn an_archive_is_served_with_rendered_outputs_and_search() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("project");
+    fs::create_dir_all(source.join("src")).unwrap();
+    fs::write(source.join("src/main.rs"), "fn main() {\n    parse_config();\n}\n").unwrap();
+    let seed = dir.path().join("docs");
+    fs::create_dir_all(&seed).unwrap();
+    fs::write(seed.join("README.md"), "# Overview\n\nStart at [main](/workspace/files/src/main.rs).\n").unwrap();
+    let ingested = localdoc(
+        dir.path(),
+        &["ingest", "project", "-o", "app.docpack", "--build-index", "--seed-outputs", "docs", "--quiet"],
+    );
+    assert!(ingested.status.success(), "{}", String::from_utf8_lossy(&ingested.stderr));
+
+    let (_server, address) = serve(dir.path(), "app.docpack");
+
+    let (status, overview) = get(&address, "/");
+    assert_eq!(status, 200);
+    assert!(overview.contains(">Overview</h1>") && overview.contains("href=\"/files/src/main.rs\""), "{}", overview);
+
+    let (_, listing) = get(&address, "/files/src/");
+    assert!(listing.contains("href=\"/files/src/main.rs\""), "{}", listing);
+    let (_, file) = get(&address, "/files/src/main.rs");
+    assert!(file.contains("<a id=\"L2\" href=\"#L2\">2</a>    parse_config();"), "{}", file);
+    assert_eq!(get(&address, "/raw/files/src/main.rs").1, "fn main() {\n    parse_config();\n}\n");
+
+    let (_, results) = get(&address, "/search?q=parse_config");
+    assert!(results.contains("href=\"/files/src/main.rs#L2\""), "{}", results);
+    assert!(get(&address, "/tasks").1.contains("<h1>Tasks</h1>"));
+
+    assert_eq!(get(&address, "/files/../docpack.json").0, 404);
+    assert_eq!(get(&address, "/files/missing.rs").0, 404);
+}
@@ -0,0 +1,80 @@
+//! `localdoc tasks` edits tasks.json without breaking it, in a directory
+//! or in an archive.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{localdoc, stdout};
+
+/// A small generated project ingested into `dir/app.docpack`.
+fn ingest(dir: &Path) {
+    common::project(dir, 5);
+    common::ingest(dir, &[]);
+}
+
+#[test]
+fn tasks_are_added_and_removed_in_an_archive() {
+    let dir = tempfile::tempdir().unwrap();
+    ingest(dir.path());
+
+    let added = stdout(&localdoc(
+        dir.path(),
+        &["tasks", "app.docpack", "add", "--name", "Document the API", "--description", "Describe every public function", "--output-path", "output/api.md"],
+    ));
+    assert!(added.contains("✓ Added task_2 (Document the API) writing output/api.md"), "{}", added);
+    let listed = stdout(&localdoc(dir.path(), &["tasks", "app.docpack", "list"]));
+    assert!(listed.starts_with("ID      NAME                       OUTPUT\n"), "{}", listed);
+    assert!(listed.contains("task_2  Document the API           output/api.md\n"), "{}", listed);
+    let shown = stdout(&localdoc(dir.path(), &["tasks", "app.docpack", "show", "task_2"]));
+    assert!(shown.contains("  Output:      output/api.md (markdown)"), "{}", shown);
+
+    // A tool the manifest does not list is allowed, with a warning
+    let warned = localdoc(
+        dir.path(),
+        &["tasks", "app.docpack", "add", "--name", "Diagrams", "--description", "Draw it", "--tools", "read_file,draw"],
+    );
+    assert!(String::from_utf8_lossy(&warned.stderr).contains("allows tool 'draw'"));
+    assert!(stdout(&warned).contains("output/task_3.md"));
+
+    stdout(&localdoc(dir.path(), &["tasks", "app.docpack", "remove", "task_2"]));
+    let missing = localdoc(dir.path(), &["tasks", "app.docpack", "remove", "task_2"]);
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("No task task_2; the tasks are task_1, task_3"));
+    assert!(stdout(&localdoc(dir.path(), &["validate", "app.docpack"])).contains("✓"));
+}
+
+#[test]
+fn edits_keep_unknown_fields_in_order_and_refuse_invalid_results() {
+    let dir = tempfile::tempdir().unwrap();
+    ingest(dir.path());
+    stdout(&localdoc(dir.path(), &["unpack", "app.docpack", "-o", "app"]));
+    fs::write(
+        dir.path().join("app/tasks.json"),
+        r#"{
+  "tasks": [
+    { "id": "task_1", "name": "Overview", "description": "Summarize", "priority": 1, "output": { "path": "output/overview.md" } },
+    { "id": "task_2", "name": "Details", "description": "Dig in", "depends_on": ["task_1"], "output": { "path": "output/details.md" } }
+  ],
+  "mission": "Document it",
+  "x-owner": "docs-team"
+}"#,
+    )
+    .unwrap();
+
+    let refused = localdoc(dir.path(), &["tasks", "app", "remove", "task_1"]);
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("task_1 is a dependency of task_2"));
+    stdout(&localdoc(dir.path(), &["tasks", "app", "add", "--name", "Extra", "--description", "More"]));
+    stdout(&localdoc(dir.path(), &["tasks", "app", "remove", "task_3"]));
+    let tasks = fs::read_to_string(dir.path().join("app/tasks.json")).unwrap();
+    let order: Vec<usize> = ["\"tasks\"", "\"mission\"", "\"x-owner\""].iter().map(|key| tasks.find(key).unwrap()).collect();
+    assert!(order.windows(2).all(|pair| pair[0] < pair[1]), "{}", tasks);
+    assert!(tasks.contains("\"priority\": 1"), "{}", tasks);
+
+    // A file that is already broken is not written over
+    let broken = r#"{ "tasks": [{ "id": 7, "output": {} }] }"#;
+    fs::write(dir.path().join("app/tasks.json"), broken).unwrap();
+    let invalid = localdoc(dir.path(), &["tasks", "app", "add", "--name", "More", "--description", "x"]);
+    assert!(String::from_utf8_lossy(&invalid.stderr).contains("nothing was written"));
+    assert_eq!(fs::read_to_string(dir.path().join("app/tasks.json")).unwrap(), broken);
+}
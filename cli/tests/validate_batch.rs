@@ -0,0 +1,63 @@
+//! `localdoc validate` over a directory of docpacks prints a row per pack
+//! as it finishes, and with `--sort` puts them in path order.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{localdoc, stdout};
+use serde_json::Value;
+
+/// Valid docpacks `b`, `c` and `d`, and `a`, whose manifest is cut short.
+fn packs(dir: &Path) {
+    for name in ["c", "a", "d", "b"] {
+        stdout(&localdoc(dir, &["init", &format!("packs/{}", name)]));
+    }
+    let manifest = dir.join("packs/a/docpack.json");
+    let text = fs::read_to_string(&manifest).unwrap();
+    fs::write(&manifest, &text[..text.len() / 2]).unwrap();
+}
+
+#[test]
+fn every_pack_gets_a_row_and_failures_fail_the_batch() {
+    let dir = tempfile::tempdir().unwrap();
+    packs(dir.path());
+
+    let output = localdoc(dir.path(), &["validate", "packs", "--parallel", "2", "--sort", "--no-plugins"]);
+    assert!(!output.status.success());
+    let printed = String::from_utf8_lossy(&output.stdout);
+    assert!(printed.contains("Validating 4 docpacks (2 workers)\n\nSTATUS     ERRORS WARNINGS  PATH\n"), "{}", printed);
+    assert!(printed.contains("3 of 4 docpacks valid"), "{}", printed);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Validation failed for 1 docpack(s)"));
+
+    let summary = printed.split("Summary (sorted by path)").nth(1).unwrap();
+    let rows: Vec<(&str, &str)> = summary
+        .lines()
+        .filter_map(|line| line.rsplit_once("  packs/").map(|(status, name)| (name, status.split("  ").next().unwrap())))
+        .collect();
+    assert_eq!(rows, [("a", "✗ invalid"), ("b", "✓ valid"), ("c", "✓ valid"), ("d", "✓ valid")], "{}", printed);
+}
+
+#[test]
+fn sorted_json_is_one_array_in_path_order() {
+    let dir = tempfile::tempdir().unwrap();
+    packs(dir.path());
+
+    let output = localdoc(dir.path(), &["validate", "packs", "--json", "--sort", "--parallel", "3", "--no-plugins"]);
+    assert!(!output.status.success());
+    let reports: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let reports = reports.as_array().unwrap();
+    let paths: Vec<&str> = reports.iter().map(|report| report["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, ["packs/a", "packs/b", "packs/c", "packs/d"]);
+    assert_eq!(reports[0]["valid"], false);
+    assert!(!reports[0]["errors"].as_array().unwrap().is_empty(), "{}", reports[0]);
+    assert!(reports[1..].iter().all(|report| report["valid"] == true));
+
+    // Unsorted, every pack is still there once
+    let output = localdoc(dir.path(), &["validate", "packs", "--json", "--no-plugins"]);
+    let reports: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let mut paths: Vec<&str> = reports.as_array().unwrap().iter().map(|report| report["path"].as_str().unwrap()).collect();
+    paths.sort();
+    assert_eq!(paths, ["packs/a", "packs/b", "packs/c", "packs/d"]);
+}
@@ -0,0 +1,66 @@
+//! `localdoc watch` ingests and runs a docpack, then updates and runs it
+//! again when the source changes. A stand-in `docker` on PATH counts runs.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+fn fake_runtime(dir: &Path) -> String {
+    let bin = dir.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let script = format!(
+        "#!/bin/sh\ncase \"$1\" in\n  --version|image) exit 0 ;;\nesac\necho run >> \"{}\"\n",
+        dir.join("runs.log").display()
+    );
+    fs::write(bin.join("docker"), script).unwrap();
+    fs::set_permissions(bin.join("docker"), fs::Permissions::from_mode(0o755)).unwrap();
+    format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default())
+}
+
+fn wait_for(what: &str, mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn changes_start_a_new_iteration() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("src");
+    fs::create_dir_all(&source).unwrap();
+    fs::write(source.join("README.md"), "# Project\n").unwrap();
+    let runs = || fs::read_to_string(dir.path().join("runs.log")).unwrap_or_default().lines().count();
+
+    let mut watch = common::command(dir.path())
+        .env("PATH", fake_runtime(dir.path()))
+        .env_remove("LOCALDOC_RUNTIME")
+        .env("XDG_DATA_HOME", dir.path().join("data"))
+        .args(["watch", "src", "--out", "pack.docpack", "--debounce", "100", "--", "--runtime", "docker", "--no-env"])
+        .stdout(fs::File::create(dir.path().join("watch.out")).unwrap())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    wait_for("the first run", || runs() == 1);
+    assert!(dir.path().join("pack.docpack").is_file());
+    // Let the watcher report the run before changing anything
+    std::thread::sleep(Duration::from_millis(300));
+    fs::write(source.join("README.md"), "# Project\n\nNow with words.\n").unwrap();
+    wait_for("the second run", || runs() == 2);
+
+    Command::new("kill").args(["-INT", &watch.id().to_string()]).status().unwrap();
+    let status = watch.wait().unwrap();
+    assert!(status.success());
+    let out = fs::read_to_string(dir.path().join("watch.out")).unwrap();
+    assert!(out.contains("Iteration 1") && out.contains("initial run"), "{}", out);
+    assert!(out.contains("Iteration 2") && out.contains("changed: README.md"), "{}", out);
+    assert!(out.contains("Stopped watching"));
+}
@@ -0,0 +1,39 @@
+//! Archives with more entries than a plain zip can count are written as
+//! zip64 and read back by the extractor `run` uses.
+
+use std::io::Cursor;
+
+use localdoc::archive::{self, ArchiveEntry, ArchiveOptions, Limits, MemorySink};
+
+/// More than the 65,535 entries a non-zip64 end record can hold.
+const ENTRIES: usize = 70_000;
+
+#[test]
+#[cfg_attr(not(feature = "slow-tests"), ignore = "writes 70,000 entries; run with --features slow-tests")]
+fn many_entries_round_trip() {
+    let entries = (0..ENTRIES).map(|i| {
+        let content = i.to_string().into_bytes();
+        Ok(ArchiveEntry::File {
+            path: format!("files/{:05}.txt", i),
+            size: Some(content.len() as u64),
+            reader: Box::new(Cursor::new(content)),
+            unix_mode: None,
+        })
+    });
+    let options = ArchiveOptions { limits: Limits::unlimited(), ..ArchiveOptions::default() };
+    let (cursor, built) = archive::build_archive(entries, Cursor::new(Vec::new()), &options).unwrap();
+    assert_eq!(built.entries, ENTRIES);
+    let bytes = cursor.into_inner();
+
+    // The classic end record saturates its entry count and points at zip64
+    let eocd = bytes.windows(4).rposition(|w| w == b"PK\x05\x06").unwrap();
+    assert_eq!(&bytes[eocd + 10..eocd + 12], &[0xff, 0xff]);
+    assert!(bytes.windows(4).any(|w| w == b"PK\x06\x06"), "no zip64 end record");
+
+    let mut sink = MemorySink::default();
+    let extracted = archive::extract_entries(Cursor::new(bytes), &mut sink, &Limits::default()).unwrap();
+    assert_eq!(extracted.entries, ENTRIES);
+    assert_eq!(sink.files.len(), ENTRIES);
+    assert_eq!(sink.files["files/00000.txt"], b"0");
+    assert_eq!(sink.files["files/69999.txt"], b"69999");
+}
@@ -6,9 +6,17 @@ from openai import OpenAI
 import json
 import os
 import sys
+from pathlib import Path
 from sandbox import Sandbox
 from tools import DocpackTools
 
+PROMPTS_DIR = Path(__file__).parent / "prompts"
+
+
+def load_prompt(name):
+    """A prompt template from prompts/, a copy of cli/prompts that `localdoc context` renders too."""
+    return (PROMPTS_DIR / f"{name}.txt").read_text()
+
 
 def main():
     """Main orchestrator loop."""
@@ -68,25 +76,10 @@ def main():
             f"{i+1}. {task['name']}: {task['description']}"
             for i, task in enumerate(tasks_config["tasks"])
         ])
-        initial_prompt = f"""You are a documentation agent working within a .docpack environment.
-
-Your mission: {tasks_config['mission']}
-
-Tasks to complete:
-{task_descriptions}
-
-Begin by exploring the project structure and then complete each task in order.
-Use the available tools to read files, search code, and query the semantic graph.
-Write your outputs using the write_output tool as specified in each task.
-"""
+        initial_prompt = load_prompt("tasks").format(mission=tasks_config['mission'], tasks=task_descriptions)
     else:
         # Fallback exploration mode
-        initial_prompt = f"""You are a documentation agent working within a .docpack environment.
-
-Your mission: {tasks_config['mission']}
-
-Explore the project at '.' and provide a comprehensive summary of its structure and purpose.
-"""
+        initial_prompt = load_prompt("explore").format(mission=tasks_config['mission'])
 
     # Initialize conversation
     messages = [{"role": "user", "content": initial_prompt}]
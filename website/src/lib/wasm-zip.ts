@@ -25,6 +25,8 @@ export interface ExtractedFile {
 export async function extractZip(zipData: Uint8Array): Promise<{
 	isDocpack: boolean;
 	files: ExtractedFile[];
+	manifest: any;
+	tasks: any;
 }> {
 	await ensureWasmInit();
 
@@ -45,14 +47,28 @@ export async function extractZip(zipData: Uint8Array): Promise<{
 		throw new Error(`Failed to get files: ${error}`);
 	}
 
-	return { isDocpack, files };
+	// Parsed by docpack-core, exactly as the CLI parses them
+	let manifest = null;
+	let tasks = null;
+	if (isDocpack) {
+		try {
+			manifest = processor.get_manifest();
+			tasks = processor.get_tasks();
+		} catch (error) {
+			throw new Error(`Invalid docpack: ${error}`);
+		}
+	}
+
+	return { isDocpack, files, manifest, tasks };
 }
 
 export async function extractZipToMap(zipData: Uint8Array): Promise<{
 	isDocpack: boolean;
 	filesMap: Map<string, File>;
+	manifest: any;
+	tasks: any;
 }> {
-	const { isDocpack, files } = await extractZip(zipData);
+	const { isDocpack, files, manifest, tasks } = await extractZip(zipData);
 	const filesMap = new Map<string, File>();
 
 	for (const extracted of files) {
@@ -60,5 +76,5 @@ export async function extractZipToMap(zipData: Uint8Array): Promise<{
 		filesMap.set(extracted.path, file);
 	}
 
-	return { isDocpack, filesMap };
+	return { isDocpack, filesMap, manifest, tasks };
 }
@@ -1,7 +1,15 @@
+use docpack_core::{Manifest, Strictness, TasksFile, MANIFEST_FILE, TASKS_FILE};
 use wasm_bindgen::prelude::*;
 use zip::ZipArchive;
 use std::io::Cursor;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Maps logical paths to blobs in a docpack built with `localdoc ingest --dedupe`
+const DEDUPE_MAP: &str = "files.map.json";
+
+/// Prefix of the deduplicated blobs, each named by its SHA-256
+const DEDUPE_BLOBS: &str = "blobs/";
 
 #[wasm_bindgen]
 extern "C" {
@@ -32,6 +40,8 @@ impl ZipProcessor {
 
     /// Extract all files from a zip archive
     /// Returns true if it's a valid docpack (has docpack.json), false if it's a regular zip
+    /// Zip64 docpacks (over 65,535 entries) are read like any other; archives
+    /// over 4 GB can't be loaded into wasm32 memory in the first place
     #[wasm_bindgen]
     pub fn extract_zip(&mut self, zip_data: &[u8]) -> Result<bool, JsValue> {
         self.files.clear();
@@ -47,7 +57,8 @@ impl ZipProcessor {
             let mut file = archive.by_index(i)
                 .map_err(|e| JsValue::from_str(&format!("Failed to read file at index {}: {}", i, e)))?;
 
-            let path = file.name().to_string();
+            // Zips made on Windows by other tools may use `\` separators
+            let path = file.name().replace('\\', "/");
 
             // Check if it's a docpack
             if path == "docpack.json" {
@@ -74,9 +85,69 @@ impl ZipProcessor {
             });
         }
 
+        self.materialize_dedupe()?;
+
         Ok(is_docpack)
     }
 
+    /// Rebuild the logical `files/` layout of a docpack built with
+    /// `--dedupe`: each path in files.map.json gets the data of its blob,
+    /// and the blobs and the map themselves are dropped
+    fn materialize_dedupe(&mut self) -> Result<(), JsValue> {
+        let Some(map_file) = self.files.iter().find(|f| f.path == DEDUPE_MAP) else {
+            return Ok(());
+        };
+        let map: serde_json::Value = serde_json::from_slice(&map_file.data)
+            .map_err(|e| JsValue::from_str(&format!("Invalid {}: {}", DEDUPE_MAP, e)))?;
+        let entries = map["files"].as_object()
+            .ok_or_else(|| JsValue::from_str(&format!("{} has no \"files\" object", DEDUPE_MAP)))?;
+
+        let (blobs, mut files): (Vec<ExtractedFile>, Vec<ExtractedFile>) = std::mem::take(&mut self.files)
+            .into_iter()
+            .filter(|f| f.path != DEDUPE_MAP)
+            .partition(|f| f.path.starts_with(DEDUPE_BLOBS));
+        let blobs: HashMap<String, Vec<u8>> = blobs.into_iter()
+            .map(|f| (f.path[DEDUPE_BLOBS.len()..].to_string(), f.data))
+            .collect();
+
+        for (name, entry) in entries {
+            let sha = entry["sha256"].as_str().unwrap_or_default();
+            let data = blobs.get(sha)
+                .ok_or_else(|| JsValue::from_str(&format!("Missing blob for files/{}", name)))?;
+            files.push(ExtractedFile {
+                path: format!("files/{}", name),
+                data: data.clone(),
+                filename: name.rsplit('/').next().unwrap_or(name).to_string(),
+            });
+        }
+
+        self.files = files;
+        Ok(())
+    }
+
+    /// docpack.json parsed the way `localdoc` parses it, or an error naming
+    /// the field it breaks on
+    #[wasm_bindgen]
+    pub fn get_manifest(&self) -> Result<JsValue, JsValue> {
+        let file = self.files.iter()
+            .find(|f| f.path == MANIFEST_FILE)
+            .ok_or_else(|| JsValue::from_str("Invalid docpack: missing docpack.json"))?;
+        let manifest = Manifest::from_slice(&file.data, MANIFEST_FILE, Strictness::Lenient)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        to_js(&manifest)
+    }
+
+    /// tasks.json parsed the way `localdoc` parses it, or null without one
+    #[wasm_bindgen]
+    pub fn get_tasks(&self) -> Result<JsValue, JsValue> {
+        let Some(file) = self.files.iter().find(|f| f.path == TASKS_FILE) else {
+            return Ok(JsValue::NULL);
+        };
+        let tasks = TasksFile::from_slice(&file.data, TASKS_FILE, Strictness::Lenient)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        to_js(&tasks)
+    }
+
     /// Get the number of extracted files
     #[wasm_bindgen]
     pub fn file_count(&self) -> usize {
@@ -118,3 +189,9 @@ impl ZipProcessor {
             .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
     }
 }
+
+/// A document as a plain JS object, the shape `JSON.parse` would give
+fn to_js<T: Serialize>(document: &T) -> Result<JsValue, JsValue> {
+    document.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+}